@@ -0,0 +1,285 @@
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+
+use weggli_ruleset::matcher::{RuleMatch, RuleMatcher, RuleMatcherError};
+use weggli_ruleset::reporting::RuleMatchReport;
+use weggli_ruleset::rule::Severity;
+use weggli_ruleset::{Language, RuleError, RuleSet};
+
+mod exceptions {
+    use pyo3::create_exception;
+    use pyo3::exceptions::PyException;
+
+    // `RuleError` and `RuleMatcherError` derive from `Error`, so that callers
+    // can catch both
+    create_exception!(weggli_ruleset, Error, PyException);
+    create_exception!(weggli_ruleset, RuleError, Error);
+    create_exception!(weggli_ruleset, RuleMatcherError, Error);
+}
+
+// NOTE: errors are raised with the messages of the crate's errors
+fn rule_error(e: RuleError) -> PyErr {
+    exceptions::RuleError::new_err(e.to_string())
+}
+
+fn matcher_error(e: RuleMatcherError) -> PyErr {
+    match e {
+        RuleMatcherError::Rules(e) => rule_error(e),
+        e => exceptions::RuleMatcherError::new_err(e.to_string()),
+    }
+}
+
+// severities are named as in rules, e.g., `high`
+fn severity_name(severity: Severity) -> &'static str {
+    match severity {
+        Severity::None => "none",
+        Severity::Low => "low",
+        Severity::Medium => "medium",
+        Severity::High => "high",
+        Severity::Critical => "critical",
+    }
+}
+
+fn parse_severity(name: &str) -> PyResult<Severity> {
+    Ok(match name.to_ascii_lowercase().as_str() {
+        "none" => Severity::None,
+        "low" => Severity::Low,
+        "medium" => Severity::Medium,
+        "high" => Severity::High,
+        "critical" => Severity::Critical,
+        _ => {
+            return Err(PyValueError::new_err(format!(
+                "unknown severity `{name}` (expected `low`, `medium`, `high`, or `critical`)"
+            )))
+        }
+    })
+}
+
+// A set of rules; filtering returns a new set, whose rules below the severity
+// (or without the tag) are disabled in the matchers created from it.
+#[pyclass(name = "RuleSet", module = "weggli_ruleset")]
+#[derive(Clone)]
+struct PyRuleSet {
+    rules: RuleSet,
+    enabled: Vec<bool>,
+}
+
+impl PyRuleSet {
+    fn new(rules: RuleSet) -> Self {
+        let enabled = vec![true; rules.len()];
+        Self { rules, enabled }
+    }
+
+    fn filter(&self, f: impl Fn(&weggli_ruleset::Rule) -> bool) -> Self {
+        let enabled = self
+            .rules
+            .iter()
+            .zip(&self.enabled)
+            .map(|((_, rule), enabled)| *enabled && f(rule))
+            .collect();
+
+        Self {
+            rules: self.rules.clone(),
+            enabled,
+        }
+    }
+}
+
+#[pymethods]
+impl PyRuleSet {
+    #[staticmethod]
+    #[pyo3(signature = (root, ignore_errors = true))]
+    fn from_directory(root: PathBuf, ignore_errors: bool) -> PyResult<Self> {
+        RuleSet::from_directory(root, ignore_errors)
+            .map(Self::new)
+            .map_err(rule_error)
+    }
+
+    #[staticmethod]
+    fn from_file(path: PathBuf) -> PyResult<Self> {
+        RuleSet::from_file(path).map(Self::new).map_err(rule_error)
+    }
+
+    #[staticmethod]
+    fn from_str(rule: &str) -> PyResult<Self> {
+        RuleSet::from_str(rule).map(Self::new).map_err(rule_error)
+    }
+
+    fn filter_min_severity(&self, severity: &str) -> PyResult<Self> {
+        let severity = parse_severity(severity)?;
+        // NOTE: rules whose checks may escalate to `severity` are kept
+        Ok(self.filter(|rule| rule.max_severity() >= severity))
+    }
+
+    fn filter_tag(&self, tag: &str) -> Self {
+        self.filter(|rule| rule.has_tag(tag))
+    }
+
+    // the ids of the enabled rules, in load order
+    fn ids(&self) -> Vec<String> {
+        self.rules
+            .iter()
+            .zip(&self.enabled)
+            .filter(|(_, enabled)| **enabled)
+            .map(|((_, rule), _)| rule.id().to_owned())
+            .collect()
+    }
+
+    fn __len__(&self) -> usize {
+        self.enabled.iter().filter(|enabled| **enabled).count()
+    }
+
+    fn __repr__(&self) -> String {
+        format!("<RuleSet of {} rules>", self.__len__())
+    }
+}
+
+#[pyclass(name = "RuleMatcher", module = "weggli_ruleset")]
+struct PyRuleMatcher {
+    matcher: RuleMatcher,
+}
+
+#[pymethods]
+impl PyRuleMatcher {
+    #[new]
+    fn new(rules: &PyRuleSet) -> PyResult<Self> {
+        let mut matcher = RuleMatcher::new(rules.rules.clone()).map_err(matcher_error)?;
+
+        for (index, enabled) in rules.enabled.iter().enumerate() {
+            if !enabled {
+                if let Some(key) = rules.rules.key(index) {
+                    matcher.set_rule_enabled(key, false);
+                }
+            }
+        }
+
+        Ok(Self { matcher })
+    }
+
+    // NOTE: the GIL is released while matching, so that other threads can
+    // run during long scans
+    #[pyo3(signature = (source, is_cxx = false))]
+    fn matches(
+        &mut self,
+        py: Python<'_>,
+        source: String,
+        is_cxx: bool,
+    ) -> PyResult<Vec<PyRuleMatch>> {
+        let language = if is_cxx {
+            Language::Cplusplus
+        } else {
+            Language::C
+        };
+
+        let matcher = &mut self.matcher;
+        let matches = py
+            .allow_threads(move || matcher.matches_lang(source, language))
+            .map_err(matcher_error)?;
+
+        Ok(matches.into_iter().map(|m| PyRuleMatch { m }).collect())
+    }
+}
+
+#[pyclass(name = "RuleMatch", module = "weggli_ruleset")]
+struct PyRuleMatch {
+    m: RuleMatch,
+}
+
+#[pymethods]
+impl PyRuleMatch {
+    #[getter]
+    fn rule_id(&self) -> &str {
+        self.m.rule().id()
+    }
+
+    #[getter]
+    fn checker(&self) -> &str {
+        self.m.checker().name()
+    }
+
+    #[getter]
+    fn severity(&self) -> &'static str {
+        severity_name(self.m.effective_severity())
+    }
+
+    // sorted, for determinism
+    #[getter]
+    fn tags(&self) -> Vec<String> {
+        let mut tags = self.m.rule().tags().iter().cloned().collect::<Vec<_>>();
+        tags.sort();
+        tags
+    }
+
+    #[getter]
+    fn start_offset(&self) -> usize {
+        self.m.start_offset()
+    }
+
+    #[getter]
+    fn end_offset(&self) -> usize {
+        self.m.end_offset()
+    }
+
+    #[getter]
+    fn start_line(&self) -> usize {
+        self.m.start_line()
+    }
+
+    #[getter]
+    fn start_column(&self) -> usize {
+        self.m.start_column()
+    }
+
+    #[getter]
+    fn end_line(&self) -> usize {
+        self.m.end_line()
+    }
+
+    #[getter]
+    fn end_column(&self) -> usize {
+        self.m.end_column()
+    }
+
+    // the values of the rule's variables, e.g., `{"$dst": "buf"}`
+    #[getter]
+    fn captures(&self) -> BTreeMap<String, String> {
+        RuleMatchReport::new(&self.m).captures().clone()
+    }
+
+    #[pyo3(signature = (before = 2, after = 2, line_numbers = true))]
+    fn display(&self, before: usize, after: usize, line_numbers: bool) -> String {
+        self.m.display(before, after, line_numbers)
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "<RuleMatch {}/{} ({}) at {}:{}>",
+            self.rule_id(),
+            self.checker(),
+            self.severity(),
+            self.start_line(),
+            self.start_column()
+        )
+    }
+}
+
+#[pymodule]
+fn weggli_ruleset(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    let py = m.py();
+
+    m.add_class::<PyRuleSet>()?;
+    m.add_class::<PyRuleMatcher>()?;
+    m.add_class::<PyRuleMatch>()?;
+
+    m.add("Error", py.get_type_bound::<exceptions::Error>())?;
+    m.add("RuleError", py.get_type_bound::<exceptions::RuleError>())?;
+    m.add(
+        "RuleMatcherError",
+        py.get_type_bound::<exceptions::RuleMatcherError>(),
+    )?;
+
+    Ok(())
+}
@@ -0,0 +1,88 @@
+import pathlib
+import threading
+
+import pytest
+
+import weggli_ruleset
+
+FIXTURES = pathlib.Path(__file__).resolve().parents[3] / "tests"
+
+
+LOCAL_BUFFER = """
+id: local-buffer
+severity: low
+check-pattern:
+  pattern: '{ char $buf[_]; }'
+"""
+
+UNBOUNDED_COPY = """
+id: unbounded-copy
+severity: high
+tags:
+- CWE-120
+- CWE-676
+check-patterns:
+- name: strcpy
+  pattern: '{ strcpy($dst, _); }'
+"""
+
+
+def rules(directory):
+    (directory / "local-buffer.yml").write_text(LOCAL_BUFFER)
+    (directory / "unbounded-copy.yml").write_text(UNBOUNDED_COPY)
+
+    return weggli_ruleset.RuleSet.from_directory(str(directory), False)
+
+
+def test_matches(tmp_path):
+    matcher = weggli_ruleset.RuleMatcher(rules(tmp_path))
+    source = (FIXTURES / "utf8-comments.c").read_text(encoding="utf-8")
+    matches = matcher.matches(source)
+
+    assert len(matches) == 3
+
+    m = next(m for m in matches if m.rule_id == "unbounded-copy")
+    assert m.checker == "strcpy"
+    assert m.severity == "high"
+    assert m.tags == sorted(m.tags)
+    assert m.start_line == 6
+    assert "$dst" in m.captures
+    assert "strcpy" in m.display(0, 0)
+
+
+def test_filters(tmp_path):
+    assert len(rules(tmp_path).filter_min_severity("high")) < len(rules(tmp_path))
+    assert rules(tmp_path).filter_tag("no-such-tag").ids() == []
+
+    matcher = weggli_ruleset.RuleMatcher(rules(tmp_path).filter_min_severity("high"))
+    source = (FIXTURES / "utf8-comments.c").read_text(encoding="utf-8")
+
+    assert all(m.severity == "high" for m in matcher.matches(source))
+
+    with pytest.raises(ValueError):
+        rules(tmp_path).filter_min_severity("severe")
+
+
+def test_errors():
+    with pytest.raises(weggli_ruleset.RuleError) as error:
+        weggli_ruleset.RuleSet.from_str("id: [")
+
+    assert "cannot parse rule" in str(error.value)
+    assert issubclass(weggli_ruleset.RuleMatcherError, weggli_ruleset.Error)
+
+
+def test_threads(tmp_path):
+    ruleset = rules(tmp_path)
+    source = (FIXTURES / "utf8-comments.c").read_text(encoding="utf-8")
+    results = []
+
+    def scan():
+        results.append(len(weggli_ruleset.RuleMatcher(ruleset).matches(source)))
+
+    threads = [threading.Thread(target=scan) for _ in range(4)]
+    for thread in threads:
+        thread.start()
+    for thread in threads:
+        thread.join()
+
+    assert results == [3] * 4
@@ -0,0 +1,4 @@
+#[cfg(feature = "wasm-bindgen")]
+mod wasm;
+#[cfg(feature = "wasm-bindgen")]
+pub use wasm::match_rule;
@@ -0,0 +1,87 @@
+use std::panic::{self, UnwindSafe};
+use std::sync::Once;
+
+use serde::Serialize;
+use wasm_bindgen::prelude::*;
+
+use weggli_ruleset::matcher::RuleMatcher;
+use weggli_ruleset::reporting::ReportSet;
+use weggli_ruleset::Language;
+
+#[wasm_bindgen]
+extern "C" {
+    #[wasm_bindgen(js_namespace = console)]
+    fn error(message: &str);
+}
+
+// NOTE: the hook logs the message and location of a panic to the console,
+// before `match_rule` catches it; built without unwinding (see
+// `.cargo/config.toml`), a panic would abort instead, which traps
+fn set_panic_hook() {
+    static HOOK: Once = Once::new();
+
+    HOOK.call_once(|| {
+        panic::set_hook(Box::new(|info| error(&format!("weggli-ruleset: {info}"))));
+    });
+}
+
+// Matches the rule of the YAML document `rule` against `source` (as C, or as
+// C++ if `is_cxx`), returning the matches as serialized by `ReportSet`, i.e.,
+// an object with a `summary` and the `findings`. Errors (e.g., of rules that
+// cannot be parsed) are thrown as `Error`s with the crate's messages, and so
+// are panics, with that of the panic.
+#[wasm_bindgen]
+pub fn match_rule(rule: &str, source: &str, is_cxx: bool) -> Result<JsValue, JsError> {
+    set_panic_hook();
+
+    catching(|| {
+        let language = if is_cxx {
+            Language::Cplusplus
+        } else {
+            Language::C
+        };
+
+        let mut matcher = RuleMatcher::from_str(rule)?;
+        let matches = matcher.matches_lang(source, language)?;
+
+        let serializer = serde_wasm_bindgen::Serializer::json_compatible();
+
+        ReportSet::from_matches(&matches)
+            .serialize(&serializer)
+            .map_err(|e| JsError::new(&e.to_string()))
+    })
+}
+
+// the result of `f`, or an error with the message of its panic
+fn catching<T>(f: impl FnOnce() -> Result<T, JsError> + UnwindSafe) -> Result<T, JsError> {
+    panic::catch_unwind(f).unwrap_or_else(|payload| {
+        let message = payload
+            .downcast_ref::<&str>()
+            .copied()
+            .or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+            .unwrap_or("unknown panic");
+
+        Err(JsError::new(&format!("weggli-ruleset panicked: {message}")))
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use wasm_bindgen::prelude::*;
+    use wasm_bindgen_test::*;
+
+    use super::catching;
+
+    wasm_bindgen_test_configure!(run_in_browser);
+
+    #[wasm_bindgen_test]
+    fn test_catching() {
+        let error = catching(|| -> Result<(), JsError> { panic!("unreachable") }).unwrap_err();
+        let message = js_sys::Reflect::get(&JsValue::from(error), &JsValue::from_str("message"));
+
+        assert_eq!(
+            message.unwrap().as_string().as_deref(),
+            Some("weggli-ruleset panicked: unreachable")
+        );
+    }
+}
@@ -0,0 +1,38 @@
+// wasm-pack test --headless --firefox bindings/wasm
+#![cfg(target_arch = "wasm32")]
+
+use wasm_bindgen::JsValue;
+use wasm_bindgen_test::*;
+
+use weggli_ruleset_wasm::match_rule;
+
+wasm_bindgen_test_configure!(run_in_browser);
+
+const RULE: &str = r#"
+id: unbounded-copy
+check pattern:
+  regex: func=^strcpy$
+  pattern: '{ $func($dst, _); }'
+"#;
+const SOURCE: &str = include_str!("../../../tests/utf8-comments.c");
+
+fn get(value: &JsValue, key: &str) -> JsValue {
+    js_sys::Reflect::get(value, &JsValue::from_str(key)).unwrap()
+}
+
+#[wasm_bindgen_test]
+fn test_match_rule() {
+    let set = match_rule(RULE, SOURCE, false).unwrap();
+    let findings = js_sys::Array::from(&get(&set, "findings"));
+
+    assert_eq!(findings.length(), 2);
+    assert_eq!(
+        get(&findings.get(0), "rule").as_string().as_deref(),
+        Some("unbounded-copy")
+    );
+}
+
+#[wasm_bindgen_test]
+fn test_match_rule_errors() {
+    assert!(match_rule("id: [", SOURCE, false).is_err());
+}
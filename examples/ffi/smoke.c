@@ -0,0 +1,55 @@
+// A smoke test of the C ABI (see `src/ffi.rs`); from the root of the crate:
+//
+//   cargo rustc --features ffi --crate-type cdylib
+//   cc -Iinclude examples/ffi/smoke.c -Ltarget/debug -lweggli_ruleset -o smoke
+//   LD_LIBRARY_PATH=target/debug ./smoke [RULES_DIR]
+#include <stdio.h>
+
+#include "weggli_ruleset.h"
+
+static const char *SOURCE = "void f(char *dst, const char *src) { strcpy(dst, src); }\n";
+
+static const char *RULE = "id: unbounded-copy\n"
+                          "severity: high\n"
+                          "check-pattern:\n"
+                          "  pattern: '{ strcpy($dst, _); }'\n";
+
+int main(int argc, char **argv) {
+    WrsRuleSet *rules = argc > 1 ? wrs_ruleset_load_dir(argv[1], 0) : wrs_ruleset_load_str(RULE);
+    if (!rules) {
+        fprintf(stderr, "error: %s\n", wrs_last_error());
+        return 1;
+    }
+
+    WrsMatcher *matcher = wrs_matcher_new(rules);
+    wrs_ruleset_free(rules);
+    if (!matcher) {
+        fprintf(stderr, "error: %s\n", wrs_last_error());
+        return 1;
+    }
+
+    WrsResults *results = NULL;
+    if (wrs_matcher_scan(matcher, SOURCE, 0, &results) != 0) {
+        fprintf(stderr, "error: %s\n", wrs_last_error());
+        wrs_matcher_free(matcher);
+        return 1;
+    }
+
+    for (size_t i = 0; i < wrs_results_len(results); i++) {
+        size_t start, end;
+        wrs_results_offsets(results, i, &start, &end);
+
+        printf("%s/%s (severity %d) at %zu..%zu\n", wrs_results_rule_id(results, i),
+               wrs_results_checker(results, i), wrs_results_severity(results, i), start, end);
+
+        for (size_t j = 0; j < wrs_results_captures_len(results, i); j++) {
+            printf("  %s = %s\n", wrs_results_capture_name(results, i, j),
+                   wrs_results_capture_value(results, i, j));
+        }
+    }
+
+    wrs_results_free(results);
+    wrs_matcher_free(matcher);
+
+    return 0;
+}
@@ -0,0 +1,180 @@
+#ifndef WEGGLI_RULESET_H
+#define WEGGLI_RULESET_H
+
+/* Generated by cbindgen from src/ffi.rs; do not edit. */
+
+#include <stddef.h>
+
+// The severities returned by `wrs_results_severity`.
+#define WRS_SEVERITY_NONE 0
+#define WRS_SEVERITY_LOW 1
+#define WRS_SEVERITY_MEDIUM 2
+#define WRS_SEVERITY_HIGH 3
+#define WRS_SEVERITY_CRITICAL 4
+
+// A matcher of the rules of a rule set, created with `wrs_matcher_new` and
+// released with `wrs_matcher_free`.
+typedef struct WrsMatcher WrsMatcher;
+
+// The matches of a scan, returned by `wrs_matcher_scan` and released with
+// `wrs_results_free`; matches are indexed from 0 to `wrs_results_len() - 1`.
+typedef struct WrsResults WrsResults;
+
+// A set of rules, loaded with `wrs_ruleset_load_dir` or `wrs_ruleset_load_str`
+// and released with `wrs_ruleset_free`.
+typedef struct WrsRuleSet WrsRuleSet;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+// Returns the message of the last error on the calling thread, or NULL if
+// the last call succeeded.
+//
+// The string is owned by the library, and is valid until the next call on
+// the same thread.
+const char *wrs_last_error(void);
+
+// Loads the rules of the files below the directory `path`; if `ignore_errors`
+// is nonzero, files that are not valid rules are skipped.
+//
+// Returns NULL on error. The rule set is owned by the caller, and must be
+// released with `wrs_ruleset_free`.
+//
+// # Safety
+//
+// `path` must be NULL or a NUL-terminated string.
+WrsRuleSet *wrs_ruleset_load_dir(const char *path, int ignore_errors);
+
+// Loads the rule of the YAML document `rule`.
+//
+// Returns NULL on error. The rule set is owned by the caller, and must be
+// released with `wrs_ruleset_free`.
+//
+// # Safety
+//
+// `rule` must be NULL or a NUL-terminated string.
+WrsRuleSet *wrs_ruleset_load_str(const char *rule);
+
+// Releases a rule set; NULL is ignored.
+//
+// # Safety
+//
+// `rules` must be NULL or returned by `wrs_ruleset_load_*`, and not released
+// already.
+void wrs_ruleset_free(WrsRuleSet *rules);
+
+// Creates a matcher of the rules of `rules`, which remains owned by the
+// caller (and may be released once the matcher is created).
+//
+// Returns NULL on error. The matcher is owned by the caller, and must be
+// released with `wrs_matcher_free`.
+//
+// # Safety
+//
+// `rules` must be NULL or a live rule set.
+WrsMatcher *wrs_matcher_new(const WrsRuleSet *rules);
+
+// Releases a matcher; NULL is ignored.
+//
+// # Safety
+//
+// `matcher` must be NULL or returned by `wrs_matcher_new`, and not released
+// already.
+void wrs_matcher_free(WrsMatcher *matcher);
+
+// Scans `source` as C, or as C++ if `is_cxx` is nonzero, and stores its
+// matches in `*results`.
+//
+// Returns 0 on success, and -1 on error (leaving `*results` untouched). The
+// results are owned by the caller, and must be released with
+// `wrs_results_free`.
+//
+// # Safety
+//
+// `matcher` must be NULL or a live matcher, not used by another thread
+// during the call; `source` must be NULL or a NUL-terminated string;
+// `results` must be NULL or valid for writes.
+int wrs_matcher_scan(WrsMatcher *matcher, const char *source, int is_cxx, WrsResults **results);
+
+// Releases the results of a scan, and the strings they own; NULL is ignored.
+//
+// # Safety
+//
+// `results` must be NULL or returned by `wrs_matcher_scan`, and not released
+// already.
+void wrs_results_free(WrsResults *results);
+
+// Returns the number of matches, or 0 if `results` is NULL.
+//
+// # Safety
+//
+// `results` must be NULL or live results.
+size_t wrs_results_len(const WrsResults *results);
+
+// Returns the id of the rule of the match at `index`, or NULL if there is no
+// such match. The string is owned by the results.
+//
+// # Safety
+//
+// `results` must be NULL or live results.
+const char *wrs_results_rule_id(const WrsResults *results, size_t index);
+
+// Returns the name of the checker of the match at `index`, or NULL if there
+// is no such match. The string is owned by the results.
+//
+// # Safety
+//
+// `results` must be NULL or live results.
+const char *wrs_results_checker(const WrsResults *results, size_t index);
+
+// Returns the severity of the rule of the match at `index`, from 0 (none)
+// to 4 (critical; see `WRS_SEVERITY_*`), or -1 if there is no such match.
+//
+// # Safety
+//
+// `results` must be NULL or live results.
+int wrs_results_severity(const WrsResults *results, size_t index);
+
+// Stores the byte offsets of the start and end (exclusive) of the match at
+// `index` in the source in `*start` and `*end`.
+//
+// Returns 0 on success, and -1 if there is no such match.
+//
+// # Safety
+//
+// `results` must be NULL or live results; `start` and `end` must be NULL or
+// valid for writes.
+int wrs_results_offsets(const WrsResults *results, size_t index, size_t *start, size_t *end);
+
+// Returns the number of variables (e.g., `$dst`) captured by the match at
+// `index`, or 0 if there is no such match.
+//
+// # Safety
+//
+// `results` must be NULL or live results.
+size_t wrs_results_captures_len(const WrsResults *results, size_t index);
+
+// Returns the name (with its `$`) of the variable at `capture` of the match
+// at `index`, in name order, or NULL if there is no such variable. The
+// string is owned by the results.
+//
+// # Safety
+//
+// `results` must be NULL or live results.
+const char *wrs_results_capture_name(const WrsResults *results, size_t index, size_t capture);
+
+// Returns the source text of the variable at `capture` of the match at
+// `index`, or NULL if there is no such variable. The string is owned by the
+// results.
+//
+// # Safety
+//
+// `results` must be NULL or live results.
+const char *wrs_results_capture_value(const WrsResults *results, size_t index, size_t capture);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* WEGGLI_RULESET_H */
@@ -0,0 +1,255 @@
+use std::io::{self, Read, Write};
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use anyhow::{anyhow, bail, Context};
+
+use weggli_ruleset::matcher::{RuleMatch, RuleMatcher};
+use weggli_ruleset::reporting::sarif::{SarifOptions, SarifReport};
+use weggli_ruleset::reporting::{jsonl, write_oneline, DisplayOptions, ExitPolicy, ReportSet};
+use weggli_ruleset::rule::Severity;
+use weggli_ruleset::Language;
+
+// NOTE: exit codes 0-2 are those of `ExitStatus` (pass, warn, fail); usage
+// and scanning errors are reported with a distinct code
+const ERROR_CODE: u8 = 3;
+
+const USAGE: &str = "\
+usage: weggli-ruleset [OPTIONS] <RULES> <TARGET>...
+
+Scans C/C++ sources with the weggli rules of a rule file or directory.
+
+arguments:
+  <RULES>                    a rule file, or a directory of rule files
+  <TARGET>...                source files or directories, or `-` for stdin
+
+options:
+  -l, --lang <LANG>          parse files and stdin as `c` or `c++` (by
+                             default, from extensions or contents)
+  -C, --context <LINES>      lines of context around text findings [default: 2]
+  -s, --min-severity <SEV>   ignore findings below `low`, `medium`, `high`, or
+                             `critical`
+  -f, --format <FORMAT>      `text`, `oneline`, `json`, `jsonl`, or `sarif`
+                             [default: text]
+      --fail-on <SEV>        exit with 2 if a finding is at least as severe
+  -h, --help                 print this help
+";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Format {
+    Text,
+    Oneline,
+    Json,
+    Jsonl,
+    Sarif,
+}
+
+#[derive(Debug)]
+struct Args {
+    rules: PathBuf,
+    targets: Vec<String>,
+    language: Option<Language>,
+    context: usize,
+    min_severity: Option<Severity>,
+    format: Format,
+    fail_on: Option<Severity>,
+}
+
+impl Args {
+    // returns `None` if help was requested
+    fn parse(args: impl IntoIterator<Item = String>) -> anyhow::Result<Option<Self>> {
+        let mut args = args.into_iter();
+        let mut positional = Vec::new();
+
+        let mut language = None;
+        let mut context = 2;
+        let mut min_severity = None;
+        let mut format = Format::Text;
+        let mut fail_on = None;
+
+        while let Some(arg) = args.next() {
+            // accepts both `--option value` and `--option=value`
+            let (name, inline) = match arg.split_once('=') {
+                Some((name, value)) if name.starts_with("--") => (name.to_owned(), Some(value)),
+                _ => (arg.clone(), None),
+            };
+
+            let mut value = || {
+                inline
+                    .map(str::to_owned)
+                    .or_else(|| args.next())
+                    .ok_or_else(|| anyhow!("missing value for `{name}`"))
+            };
+
+            match name.as_str() {
+                "-h" | "--help" => return Ok(None),
+                "-l" | "--lang" => language = Some(parse_language(&value()?)?),
+                "-C" | "--context" => {
+                    let lines = value()?;
+                    context = lines
+                        .parse()
+                        .with_context(|| format!("invalid number of lines `{lines}`"))?;
+                }
+                "-s" | "--min-severity" => min_severity = Some(parse_severity(&value()?)?),
+                "-f" | "--format" => format = parse_format(&value()?)?,
+                "--fail-on" => fail_on = Some(parse_severity(&value()?)?),
+                "--" => positional.extend(args.by_ref()),
+                "-" => positional.push(arg),
+                _ if arg.starts_with('-') => bail!("unknown option `{arg}`"),
+                _ => positional.push(arg),
+            }
+        }
+
+        let mut positional = positional.into_iter();
+        let rules = positional
+            .next()
+            .map(PathBuf::from)
+            .ok_or_else(|| anyhow!("missing rule file or directory"))?;
+        let targets = positional.collect::<Vec<_>>();
+
+        if targets.is_empty() {
+            bail!("missing target (use `-` for stdin)");
+        }
+
+        Ok(Some(Self {
+            rules,
+            targets,
+            language,
+            context,
+            min_severity,
+            format,
+            fail_on,
+        }))
+    }
+}
+
+fn parse_language(value: &str) -> anyhow::Result<Language> {
+    // the same names as the `language` of checkers
+    serde_json::from_value(serde_json::Value::from(value))
+        .map_err(|_| anyhow!("unknown language `{value}` (expected `c` or `c++`)"))
+}
+
+fn parse_severity(value: &str) -> anyhow::Result<Severity> {
+    serde_json::from_value(serde_json::Value::from(value.to_ascii_lowercase())).map_err(|_| {
+        anyhow!("unknown severity `{value}` (expected `low`, `medium`, `high`, or `critical`)")
+    })
+}
+
+fn parse_format(value: &str) -> anyhow::Result<Format> {
+    Ok(match value {
+        "text" => Format::Text,
+        "oneline" => Format::Oneline,
+        "json" => Format::Json,
+        "jsonl" => Format::Jsonl,
+        "sarif" => Format::Sarif,
+        _ => bail!(
+            "unknown format `{value}` (expected `text`, `oneline`, `json`, `jsonl`, or `sarif`)"
+        ),
+    })
+}
+
+fn scan(args: &Args) -> anyhow::Result<Vec<RuleMatch>> {
+    let mut matcher = if args.rules.is_dir() {
+        RuleMatcher::from_directory(&args.rules)
+    } else {
+        RuleMatcher::from_file(&args.rules)
+    }
+    .with_context(|| format!("cannot load rules from `{}`", args.rules.display()))?;
+
+    if let Some(severity) = args.min_severity {
+        matcher.set_min_severity(severity);
+    }
+
+    let mut matches = Vec::new();
+
+    for target in &args.targets {
+        let path = PathBuf::from(target);
+
+        // NOTE: files below directories are always parsed according to their
+        // extension, as those without a C or C++ extension are skipped
+        let found = if target == "-" {
+            let mut source = String::new();
+            io::stdin()
+                .read_to_string(&mut source)
+                .context("cannot read source from stdin")?;
+
+            let language = args.language.unwrap_or_else(|| Language::detect(&source));
+            matcher.matches_lang(&source, language)
+        } else if path.is_dir() {
+            matcher.matches_directory(&path)
+        } else if let Some(language) = args.language {
+            matcher.matches_file_with(&path, language)
+        } else {
+            matcher.matches_file(&path)
+        }
+        .with_context(|| format!("cannot scan `{target}`"))?;
+
+        matches.extend(found);
+    }
+
+    Ok(matches)
+}
+
+fn write(args: &Args, matches: &[RuleMatch], mut writer: impl Write) -> anyhow::Result<()> {
+    match args.format {
+        Format::Text => {
+            let options = DisplayOptions::new().context(args.context, args.context);
+
+            for m in matches {
+                writeln!(writer, "{}", m.display_colored(&options))?;
+            }
+        }
+        Format::Oneline => write_oneline(&mut writer, matches)?,
+        Format::Json => {
+            ReportSet::from_matches(matches).to_json(&mut writer)?;
+            writeln!(writer)?;
+        }
+        Format::Jsonl => {
+            jsonl::write_matches(&mut writer, matches)?;
+        }
+        Format::Sarif => {
+            SarifReport::from_matches(matches, &SarifOptions::new()).to_writer(&mut writer)?;
+            writeln!(writer)?;
+        }
+    }
+
+    writer.flush()?;
+
+    Ok(())
+}
+
+fn run() -> anyhow::Result<u8> {
+    let Some(args) = Args::parse(std::env::args().skip(1))? else {
+        print!("{USAGE}");
+        return Ok(0);
+    };
+
+    let matches = scan(&args)?;
+    write(&args, &matches, io::stdout().lock())?;
+
+    let code = match args.fail_on {
+        Some(severity) => ExitPolicy::new(severity).evaluate(&matches).exit_code(),
+        None => 0,
+    };
+
+    Ok(code as u8)
+}
+
+fn main() -> ExitCode {
+    match run() {
+        Ok(code) => ExitCode::from(code),
+        // e.g., piped to `head`
+        Err(e)
+            if e.chain()
+                .filter_map(|e| e.downcast_ref::<io::Error>())
+                .any(|e| e.kind() == io::ErrorKind::BrokenPipe) =>
+        {
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            eprintln!("weggli-ruleset: {e:#}");
+            eprintln!("try `weggli-ruleset --help` for more information");
+            ExitCode::from(ERROR_CODE)
+        }
+    }
+}
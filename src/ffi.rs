@@ -0,0 +1,511 @@
+// A C ABI for embedding the matcher in (e.g., IDA or Binary Ninja) plugins;
+// `include/weggli_ruleset.h` declares it for C and C++.
+//
+// NOTE: unlike the rest of the crate, items are documented with doc comments,
+// as cbindgen copies them into the header. All strings are NUL-terminated
+// UTF-8. Functions never unwind into the caller: panics are caught and
+// reported like errors, via `wrs_last_error`.
+
+use std::cell::RefCell;
+use std::ffi::{c_char, c_int, CStr, CString};
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr;
+
+use crate::matcher::RuleMatcher;
+use crate::reporting;
+use crate::rule::{Language, RuleSet, Severity};
+
+/// The severities returned by `wrs_results_severity`.
+pub const WRS_SEVERITY_NONE: c_int = 0;
+pub const WRS_SEVERITY_LOW: c_int = 1;
+pub const WRS_SEVERITY_MEDIUM: c_int = 2;
+pub const WRS_SEVERITY_HIGH: c_int = 3;
+pub const WRS_SEVERITY_CRITICAL: c_int = 4;
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+/// A set of rules, loaded with `wrs_ruleset_load_dir` or `wrs_ruleset_load_str`
+/// and released with `wrs_ruleset_free`.
+pub struct WrsRuleSet(RuleSet);
+
+/// A matcher of the rules of a rule set, created with `wrs_matcher_new` and
+/// released with `wrs_matcher_free`.
+pub struct WrsMatcher(RuleMatcher);
+
+/// The matches of a scan, returned by `wrs_matcher_scan` and released with
+/// `wrs_results_free`; matches are indexed from 0 to `wrs_results_len() - 1`.
+pub struct WrsResults(Vec<WrsMatch>);
+
+struct WrsMatch {
+    rule_id: CString,
+    checker: CString,
+    severity: Severity,
+    start: usize,
+    end: usize,
+    captures: Vec<(CString, CString)>,
+}
+
+fn set_last_error(message: impl Into<Vec<u8>>) {
+    // NOTE: messages never contain NULs, but are truncated if they would
+    let mut message = message.into();
+    message.truncate(
+        message
+            .iter()
+            .position(|&b| b == 0)
+            .unwrap_or(message.len()),
+    );
+
+    LAST_ERROR.with(|error| *error.borrow_mut() = CString::new(message).ok());
+}
+
+// runs `f`, recording its error or panic for `wrs_last_error`; the last error
+// is cleared on success
+fn guard<T>(fallback: T, f: impl FnOnce() -> Result<T, String>) -> T {
+    match panic::catch_unwind(AssertUnwindSafe(f)) {
+        Ok(Ok(value)) => {
+            LAST_ERROR.with(|error| *error.borrow_mut() = None);
+            value
+        }
+        Ok(Err(message)) => {
+            set_last_error(message);
+            fallback
+        }
+        Err(payload) => {
+            let message = payload
+                .downcast_ref::<&str>()
+                .map(|s| s.to_string())
+                .or_else(|| payload.downcast_ref::<String>().cloned())
+                .unwrap_or_else(|| String::from("unknown error"));
+            set_last_error(format!("panic: {message}"));
+            fallback
+        }
+    }
+}
+
+unsafe fn to_str<'a>(s: *const c_char, what: &str) -> Result<&'a str, String> {
+    if s.is_null() {
+        return Err(format!("{what} is NULL"));
+    }
+
+    CStr::from_ptr(s)
+        .to_str()
+        .map_err(|e| format!("{what} is not valid UTF-8: {e}"))
+}
+
+fn to_cstring(s: &str) -> CString {
+    // NOTE: sources may contain NULs, which C strings cannot
+    CString::new(s.replace('\0', "")).unwrap_or_default()
+}
+
+/// Returns the message of the last error on the calling thread, or NULL if
+/// the last call succeeded.
+///
+/// The string is owned by the library, and is valid until the next call on
+/// the same thread.
+#[no_mangle]
+pub extern "C" fn wrs_last_error() -> *const c_char {
+    LAST_ERROR.with(|error| error.borrow().as_ref().map_or(ptr::null(), |e| e.as_ptr()))
+}
+
+/// Loads the rules of the files below the directory `path`; if `ignore_errors`
+/// is nonzero, files that are not valid rules are skipped.
+///
+/// Returns NULL on error. The rule set is owned by the caller, and must be
+/// released with `wrs_ruleset_free`.
+///
+/// # Safety
+///
+/// `path` must be NULL or a NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn wrs_ruleset_load_dir(
+    path: *const c_char,
+    ignore_errors: c_int,
+) -> *mut WrsRuleSet {
+    guard(ptr::null_mut(), || {
+        let path = to_str(path, "path")?;
+        let rules = RuleSet::from_directory(path, ignore_errors != 0).map_err(|e| e.to_string())?;
+
+        Ok(Box::into_raw(Box::new(WrsRuleSet(rules))))
+    })
+}
+
+/// Loads the rule of the YAML document `rule`.
+///
+/// Returns NULL on error. The rule set is owned by the caller, and must be
+/// released with `wrs_ruleset_free`.
+///
+/// # Safety
+///
+/// `rule` must be NULL or a NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn wrs_ruleset_load_str(rule: *const c_char) -> *mut WrsRuleSet {
+    guard(ptr::null_mut(), || {
+        let rule = to_str(rule, "rule")?;
+        let rules = RuleSet::from_str(rule).map_err(|e| e.to_string())?;
+
+        Ok(Box::into_raw(Box::new(WrsRuleSet(rules))))
+    })
+}
+
+/// Releases a rule set; NULL is ignored.
+///
+/// # Safety
+///
+/// `rules` must be NULL or returned by `wrs_ruleset_load_*`, and not released
+/// already.
+#[no_mangle]
+pub unsafe extern "C" fn wrs_ruleset_free(rules: *mut WrsRuleSet) {
+    if !rules.is_null() {
+        drop(Box::from_raw(rules));
+    }
+}
+
+/// Creates a matcher of the rules of `rules`, which remains owned by the
+/// caller (and may be released once the matcher is created).
+///
+/// Returns NULL on error. The matcher is owned by the caller, and must be
+/// released with `wrs_matcher_free`.
+///
+/// # Safety
+///
+/// `rules` must be NULL or a live rule set.
+#[no_mangle]
+pub unsafe extern "C" fn wrs_matcher_new(rules: *const WrsRuleSet) -> *mut WrsMatcher {
+    guard(ptr::null_mut(), || {
+        let rules = rules.as_ref().ok_or("rule set is NULL")?;
+        let matcher = RuleMatcher::new(rules.0.clone()).map_err(|e| e.to_string())?;
+
+        Ok(Box::into_raw(Box::new(WrsMatcher(matcher))))
+    })
+}
+
+/// Releases a matcher; NULL is ignored.
+///
+/// # Safety
+///
+/// `matcher` must be NULL or returned by `wrs_matcher_new`, and not released
+/// already.
+#[no_mangle]
+pub unsafe extern "C" fn wrs_matcher_free(matcher: *mut WrsMatcher) {
+    if !matcher.is_null() {
+        drop(Box::from_raw(matcher));
+    }
+}
+
+/// Scans `source` as C, or as C++ if `is_cxx` is nonzero, and stores its
+/// matches in `*results`.
+///
+/// Returns 0 on success, and -1 on error (leaving `*results` untouched). The
+/// results are owned by the caller, and must be released with
+/// `wrs_results_free`.
+///
+/// # Safety
+///
+/// `matcher` must be NULL or a live matcher, not used by another thread
+/// during the call; `source` must be NULL or a NUL-terminated string;
+/// `results` must be NULL or valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn wrs_matcher_scan(
+    matcher: *mut WrsMatcher,
+    source: *const c_char,
+    is_cxx: c_int,
+    results: *mut *mut WrsResults,
+) -> c_int {
+    guard(-1, || {
+        let matcher = matcher.as_mut().ok_or("matcher is NULL")?;
+        let source = to_str(source, "source")?;
+
+        if results.is_null() {
+            return Err(String::from("results is NULL"));
+        }
+
+        let language = if is_cxx != 0 {
+            Language::Cplusplus
+        } else {
+            Language::C
+        };
+
+        let matches = matcher
+            .0
+            .matches_lang(source, language)
+            .map_err(|e| e.to_string())?
+            .iter()
+            .map(|m| WrsMatch {
+                rule_id: to_cstring(m.rule().id()),
+                checker: to_cstring(m.checker().name()),
+                severity: m.effective_severity(),
+                start: m.start_offset(),
+                end: m.end_offset(),
+                captures: reporting::captures(m.source_ref(), m.result())
+                    .into_iter()
+                    .map(|(var, value)| (to_cstring(var), to_cstring(value)))
+                    .collect(),
+            })
+            .collect();
+
+        *results = Box::into_raw(Box::new(WrsResults(matches)));
+
+        Ok(0)
+    })
+}
+
+/// Releases the results of a scan, and the strings they own; NULL is ignored.
+///
+/// # Safety
+///
+/// `results` must be NULL or returned by `wrs_matcher_scan`, and not released
+/// already.
+#[no_mangle]
+pub unsafe extern "C" fn wrs_results_free(results: *mut WrsResults) {
+    if !results.is_null() {
+        drop(Box::from_raw(results));
+    }
+}
+
+unsafe fn get<'a>(results: *const WrsResults, index: usize) -> Option<&'a WrsMatch> {
+    results.as_ref()?.0.get(index)
+}
+
+/// Returns the number of matches, or 0 if `results` is NULL.
+///
+/// # Safety
+///
+/// `results` must be NULL or live results.
+#[no_mangle]
+pub unsafe extern "C" fn wrs_results_len(results: *const WrsResults) -> usize {
+    results.as_ref().map_or(0, |results| results.0.len())
+}
+
+/// Returns the id of the rule of the match at `index`, or NULL if there is no
+/// such match. The string is owned by the results.
+///
+/// # Safety
+///
+/// `results` must be NULL or live results.
+#[no_mangle]
+pub unsafe extern "C" fn wrs_results_rule_id(
+    results: *const WrsResults,
+    index: usize,
+) -> *const c_char {
+    get(results, index).map_or(ptr::null(), |m| m.rule_id.as_ptr())
+}
+
+/// Returns the name of the checker of the match at `index`, or NULL if there
+/// is no such match. The string is owned by the results.
+///
+/// # Safety
+///
+/// `results` must be NULL or live results.
+#[no_mangle]
+pub unsafe extern "C" fn wrs_results_checker(
+    results: *const WrsResults,
+    index: usize,
+) -> *const c_char {
+    get(results, index).map_or(ptr::null(), |m| m.checker.as_ptr())
+}
+
+/// Returns the severity of the rule of the match at `index`, from 0 (none)
+/// to 4 (critical; see `WRS_SEVERITY_*`), or -1 if there is no such match.
+///
+/// # Safety
+///
+/// `results` must be NULL or live results.
+#[no_mangle]
+pub unsafe extern "C" fn wrs_results_severity(results: *const WrsResults, index: usize) -> c_int {
+    get(results, index).map_or(-1, |m| match m.severity {
+        Severity::None => WRS_SEVERITY_NONE,
+        Severity::Low => WRS_SEVERITY_LOW,
+        Severity::Medium => WRS_SEVERITY_MEDIUM,
+        Severity::High => WRS_SEVERITY_HIGH,
+        Severity::Critical => WRS_SEVERITY_CRITICAL,
+    })
+}
+
+/// Stores the byte offsets of the start and end (exclusive) of the match at
+/// `index` in the source in `*start` and `*end`.
+///
+/// Returns 0 on success, and -1 if there is no such match.
+///
+/// # Safety
+///
+/// `results` must be NULL or live results; `start` and `end` must be NULL or
+/// valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn wrs_results_offsets(
+    results: *const WrsResults,
+    index: usize,
+    start: *mut usize,
+    end: *mut usize,
+) -> c_int {
+    match get(results, index) {
+        Some(m) if !start.is_null() && !end.is_null() => {
+            *start = m.start;
+            *end = m.end;
+            0
+        }
+        _ => -1,
+    }
+}
+
+/// Returns the number of variables (e.g., `$dst`) captured by the match at
+/// `index`, or 0 if there is no such match.
+///
+/// # Safety
+///
+/// `results` must be NULL or live results.
+#[no_mangle]
+pub unsafe extern "C" fn wrs_results_captures_len(
+    results: *const WrsResults,
+    index: usize,
+) -> usize {
+    get(results, index).map_or(0, |m| m.captures.len())
+}
+
+/// Returns the name (with its `$`) of the variable at `capture` of the match
+/// at `index`, in name order, or NULL if there is no such variable. The
+/// string is owned by the results.
+///
+/// # Safety
+///
+/// `results` must be NULL or live results.
+#[no_mangle]
+pub unsafe extern "C" fn wrs_results_capture_name(
+    results: *const WrsResults,
+    index: usize,
+    capture: usize,
+) -> *const c_char {
+    get(results, index)
+        .and_then(|m| m.captures.get(capture))
+        .map_or(ptr::null(), |(name, _)| name.as_ptr())
+}
+
+/// Returns the source text of the variable at `capture` of the match at
+/// `index`, or NULL if there is no such variable. The string is owned by the
+/// results.
+///
+/// # Safety
+///
+/// `results` must be NULL or live results.
+#[no_mangle]
+pub unsafe extern "C" fn wrs_results_capture_value(
+    results: *const WrsResults,
+    index: usize,
+    capture: usize,
+) -> *const c_char {
+    get(results, index)
+        .and_then(|m| m.captures.get(capture))
+        .map_or(ptr::null(), |(_, value)| value.as_ptr())
+}
+
+#[cfg(test)]
+mod test {
+    use std::ffi::{CStr, CString};
+    use std::ptr;
+
+    use super::*;
+
+    unsafe fn string(s: *const c_char) -> Option<String> {
+        (!s.is_null()).then(|| CStr::from_ptr(s).to_string_lossy().into_owned())
+    }
+
+    #[test]
+    fn test_ffi() {
+        unsafe {
+            let rule = CString::new(
+                r#"
+id: unbounded-copy
+severity: high
+check-patterns:
+- name: strcpy
+  regex: func=^strcpy$
+  pattern: '{ $func($dst, _); }'
+- name: gets
+  pattern: '{ gets(_); }'
+"#,
+            )
+            .unwrap();
+            let rules = wrs_ruleset_load_str(rule.as_ptr());
+            assert!(!rules.is_null());
+            assert!(wrs_last_error().is_null());
+
+            let matcher = wrs_matcher_new(rules);
+            assert!(!matcher.is_null());
+            wrs_ruleset_free(rules);
+
+            let source = std::fs::read_to_string("tests/utf8-comments.c").unwrap();
+            let source = CString::new(source).unwrap();
+
+            let mut results = ptr::null_mut();
+            assert_eq!(
+                wrs_matcher_scan(matcher, source.as_ptr(), 0, &mut results),
+                0
+            );
+            assert_eq!(wrs_results_len(results), 2);
+
+            let index = (0..2)
+                .find(|&i| {
+                    string(wrs_results_rule_id(results, i)).as_deref() == Some("unbounded-copy")
+                })
+                .unwrap();
+
+            assert_eq!(
+                string(wrs_results_checker(results, index)).as_deref(),
+                Some("strcpy")
+            );
+            assert_eq!(wrs_results_severity(results, index), WRS_SEVERITY_HIGH);
+
+            let (mut start, mut end) = (0, 0);
+            assert_eq!(wrs_results_offsets(results, index, &mut start, &mut end), 0);
+            assert!(source.to_str().unwrap()[start..end].starts_with("strcpy("));
+
+            let captures = (0..wrs_results_captures_len(results, index))
+                .map(|j| {
+                    (
+                        string(wrs_results_capture_name(results, index, j)).unwrap(),
+                        string(wrs_results_capture_value(results, index, j)).unwrap(),
+                    )
+                })
+                .collect::<Vec<_>>();
+            assert!(!captures.is_empty());
+            assert!(captures.windows(2).all(|w| w[0].0 < w[1].0));
+
+            // out of range, or NULL
+            assert!(wrs_results_rule_id(results, 2).is_null());
+            assert_eq!(wrs_results_severity(results, 2), -1);
+            assert_eq!(wrs_results_len(ptr::null()), 0);
+            wrs_results_free(results);
+
+            // errors are reported, not raised
+            assert_eq!(wrs_matcher_scan(matcher, ptr::null(), 0, &mut results), -1);
+            assert_eq!(string(wrs_last_error()).as_deref(), Some("source is NULL"));
+
+            let bad = CString::new("id: [").unwrap();
+            assert!(wrs_ruleset_load_str(bad.as_ptr()).is_null());
+            assert!(!wrs_last_error().is_null());
+
+            let dir =
+                std::env::temp_dir().join(format!("weggli-ruleset-ffi-{}", std::process::id()));
+            std::fs::create_dir_all(&dir).unwrap();
+            std::fs::write(dir.join("copy.yml"), rule.as_bytes()).unwrap();
+
+            let path = CString::new(dir.to_str().unwrap()).unwrap();
+            let rules = wrs_ruleset_load_dir(path.as_ptr(), 0);
+            std::fs::remove_dir_all(&dir).unwrap();
+            assert!(!rules.is_null());
+            assert!(wrs_last_error().is_null());
+            wrs_ruleset_free(rules);
+
+            wrs_matcher_free(matcher);
+            wrs_ruleset_free(ptr::null_mut());
+
+            // panics do not unwind across the boundary
+            assert_eq!(
+                guard(-1, || -> Result<c_int, String> { panic!("boom") }),
+                -1
+            );
+            assert_eq!(string(wrs_last_error()).as_deref(), Some("panic: boom"));
+        }
+    }
+}
@@ -1,6 +1,18 @@
+#[cfg(feature = "ffi")]
+pub mod ffi;
+
+mod location;
+
 pub mod matcher;
 
+pub mod preprocess;
+
 pub mod reporting;
 
 pub mod rule;
-pub use rule::{Rule, RuleSet, RuleError};
+pub use rule::{Language, Rule, RuleError, RuleSet};
+
+#[cfg(test)]
+mod testing;
+
+mod trace;
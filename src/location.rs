@@ -0,0 +1,119 @@
+use std::ops::Range;
+
+// NOTE: all offsets handled by this crate are byte offsets into the source;
+// the helpers below translate them into character-based positions without
+// ever slicing through the middle of a multi-byte codepoint.
+
+pub(crate) fn floor_char_boundary(source: &str, offset: usize) -> usize {
+    if offset >= source.len() {
+        return source.len();
+    }
+
+    let mut offset = offset;
+    while !source.is_char_boundary(offset) {
+        offset -= 1;
+    }
+    offset
+}
+
+pub(crate) fn ceil_char_boundary(source: &str, offset: usize) -> usize {
+    if offset >= source.len() {
+        return source.len();
+    }
+
+    let mut offset = offset;
+    while !source.is_char_boundary(offset) {
+        offset += 1;
+    }
+    offset
+}
+
+pub(crate) fn clamp_range(source: &str, range: Range<usize>) -> Range<usize> {
+    let start = floor_char_boundary(source, range.start);
+    let end = ceil_char_boundary(source, range.end.max(start));
+    start..end
+}
+
+pub(crate) fn slice(source: &str, range: Range<usize>) -> &str {
+    &source[clamp_range(source, range)]
+}
+
+pub(crate) fn char_offset(source: &str, offset: usize) -> usize {
+    source[..floor_char_boundary(source, offset)]
+        .chars()
+        .count()
+}
+
+// 1-based line and 1-based column (in characters) of a byte offset
+pub(crate) fn line_column(source: &str, offset: usize) -> (usize, usize) {
+    let offset = floor_char_boundary(source, offset);
+    let prefix = &source[..offset];
+
+    let line_start = prefix.rfind('\n').map(|i| i + 1).unwrap_or(0);
+    let line = memchr::memchr_iter(b'\n', prefix.as_bytes()).count() + 1;
+    let column = prefix[line_start..].chars().count() + 1;
+
+    (line, column)
+}
+
+// the lines spanned by `range`, extended by `before` and `after` lines of
+// context, without the final line terminator
+pub(crate) fn line_span(
+    source: &str,
+    range: Range<usize>,
+    before: usize,
+    after: usize,
+) -> Range<usize> {
+    let range = clamp_range(source, range);
+    let bytes = source.as_bytes();
+
+    let line_start = |offset: usize| memchr::memrchr(b'\n', &bytes[..offset]).map_or(0, |i| i + 1);
+    let line_end =
+        |offset: usize| memchr::memchr(b'\n', &bytes[offset..]).map_or(bytes.len(), |i| offset + i);
+
+    let mut start = line_start(range.start);
+    for _ in 0..before {
+        if start == 0 {
+            break;
+        }
+        start = line_start(start - 1);
+    }
+
+    let mut end = line_end(range.end);
+    for _ in 0..after {
+        if end == bytes.len() {
+            break;
+        }
+        end = line_end(end + 1);
+    }
+
+    start..end
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_multibyte_positions() {
+        let source = "// 日本語 🦀\nint x;\n";
+
+        assert_eq!(floor_char_boundary(source, 4), 3);
+        assert_eq!(ceil_char_boundary(source, 4), 6);
+        assert_eq!(slice(source, 4..5), "日");
+
+        let x = source.find('x').unwrap();
+
+        assert_eq!(char_offset(source, x), 13);
+        assert_eq!(line_column(source, x), (2, 5));
+        assert_eq!(line_column(source, 0), (1, 1));
+        assert_eq!(line_column(source, source.len() + 10), (3, 1));
+
+        let source = "a\nbb\nccc\ndd\ne";
+        let c = source.find('c').unwrap();
+
+        assert_eq!(&source[line_span(source, c..c + 1, 0, 0)], "ccc");
+        assert_eq!(&source[line_span(source, c..c + 1, 1, 1)], "bb\nccc\ndd");
+        assert_eq!(&source[line_span(source, c..c + 1, 9, 9)], source);
+    }
+}
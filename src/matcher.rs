@@ -1,44 +1,250 @@
+use std::borrow::Cow;
 use std::fmt::Debug;
-use std::path::Path;
+#[cfg(feature = "std-fs")]
+use std::io;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 
+use rustc_hash::{FxHashMap, FxHashSet};
 use thiserror::Error;
-use tree_sitter::Parser;
-use weggli::result::QueryResult;
+use tree_sitter::{Parser, Tree};
+use weggli::result::{CaptureResult, QueryResult};
 
-use crate::rule::{Checker, Rule, RuleError, RuleSet};
+use crate::location;
+use crate::preprocess::addresses::AddressExtractor;
+use crate::preprocess::comments;
+use crate::preprocess::decompiler::Normalizer;
+use crate::preprocess::macros::MacroExpander;
+use crate::preprocess::OffsetMap;
+use crate::reporting::{DisplayOptions, ReportKey, SourceBase};
+use crate::rule::{
+    Checker, FileSize, Language, Rule, RuleError, RuleSet, Severity, VARIABLE_CAPTURE,
+};
+use crate::trace;
+
+#[cfg(feature = "std-fs")]
+mod batch;
+
+#[cfg(feature = "std-fs")]
+mod chunked;
+#[cfg(feature = "std-fs")]
+pub use chunked::{ChunkInput, MatchChunks};
+
+mod coverage;
+use coverage::CoverageTracker;
+pub use coverage::{CheckCoverage, RuleCoverage, COVERAGE_EXAMPLES};
+
+mod decode;
+pub use decode::DecodePolicy;
+
+pub mod diff;
+use diff::DiffError;
+
+mod explain;
+pub use explain::{
+    CandidateExplanation, CaptureExplanation, ConstraintExplanation, MatchExplanation,
+};
+
+mod fingerprint;
+pub(crate) use fingerprint::{function_definition, function_lines_within, Fnv};
+
+mod group;
+pub(crate) use group::location_order;
+pub use group::{group_by_origin, group_by_rule, OriginGroup, RuleGroup};
+
+#[cfg(feature = "std-fs")]
+mod input;
+
+mod intern;
+use intern::SourceInterner;
+
+mod metrics;
+pub use metrics::{LanguageMismatch, OverBudget, ScanMetrics, SkippedFiles};
+
+mod parts;
+pub use parts::MatchIds;
+
+mod prefilter;
+use prefilter::PrefilterCounts;
+pub use prefilter::{CheckPrefilterStats, PrefilterStats, SKIP_RATIO_BUCKETS};
+
+mod progress;
+pub use progress::{NoProgress, ScanEvent, ScanProgress, SkipReason};
+
+mod ranges;
+pub use ranges::RangeMode;
+
+mod reader;
+pub use reader::{ReadLimits, READ_LIMIT};
+
+mod retention;
+pub use retention::SourceRetention;
+
+mod split;
+pub use split::Segment;
+
+mod suppression;
+pub use suppression::{SuppressionMode, SuppressionReason, SUPPRESSION_MARKER};
+
+mod unique;
+use unique::SeenValues;
+
+mod viability;
+pub use viability::ViabilityEntry;
+
+#[cfg(feature = "std-fs")]
+mod walk;
+#[cfg(feature = "std-fs")]
+pub use walk::WalkOptions;
 
 pub struct RuleMatcher {
     rules: RuleSet,
+    filter: RuleFilter,
+    prefilter: PrefilterMode,
+    range_mode: RangeMode,
+    normalizer: Option<Normalizer>,
+    macro_expander: Option<MacroExpander>,
+    address_extractor: Option<AddressExtractor>,
+    min_function_lines: Option<usize>,
+    max_function_lines: Option<usize>,
+    suppression: SuppressionMode,
+    metrics: ScanMetrics,
+    prefilter_counts: PrefilterCounts,
+    coverage: Option<CoverageTracker>,
+    // the values of `unique-by` variables in `scan` scope
+    seen: SeenValues,
+    // the sources of the current batch scan, if any
+    interner: Option<SourceInterner>,
+    check_budget: Option<Duration>,
+    circuit_breaker: bool,
+    source_timeout: Option<Duration>,
+    run_mismatched_languages: bool,
+    max_file_size: Option<usize>,
+    retention: SourceRetention,
+    #[cfg(feature = "mmap")]
+    mapped_input: bool,
+    // the checks the circuit breaker disabled in the current scan, by rule
+    // and check index
+    tripped: FxHashSet<(usize, usize)>,
     c_parser: Parser,
     cxx_parser: Parser,
 }
 
 pub struct RuleMatch {
     rule: Arc<Rule>,
+    rule_key: Arc<str>,
     rule_id: usize,
     checker_id: usize,
     source: Arc<str>,
+    language: Language,
+    origin: Option<Arc<Path>>,
+    conversion: Option<DecodePolicy>,
+    original: Option<Arc<Original>>,
+    // where `source` starts in the scanned source, if it is a snippet of it
+    // (see `SourceRetention`)
+    base: Option<SourceBase>,
+    fingerprint: Arc<str>,
+    suppression: Option<SuppressionReason>,
+    segment: Option<Arc<Segment>>,
+    // the severity of the rule, unless an escalation of the check applies
+    severity: Severity,
+    address: Option<u64>,
     result: QueryResult,
 }
 
+// the input as it was before preprocessing, and the map from offsets in the
+// preprocessed `source` back to it
+#[derive(Debug)]
+struct Original {
+    source: Arc<str>,
+    map: OffsetMap,
+}
+
+// How checkers' identifiers are searched for before a source is parsed.
+//
+// `CodeOnly` ignores identifiers that only occur within comments, which
+// avoids parsing and matching heavily commented sources that mention, but do
+// not call, the functions a rule targets. String literals are retained, since
+// patterns may refer to them. It falls back to `Raw` when the source cannot
+// be tokenized (e.g., an unterminated comment).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum PrefilterMode {
+    #[default]
+    Raw,
+    CodeOnly,
+}
+
+#[derive(Default)]
+struct RuleFilter {
+    disabled: FxHashSet<usize>,
+    min_severity: Option<Severity>,
+}
+
+impl RuleFilter {
+    fn allows(&self, index: usize, rule: &Rule) -> bool {
+        !self.disabled.contains(&index)
+            && self
+                .min_severity
+                .is_none_or(|severity| rule.max_severity() >= severity)
+    }
+}
+
+#[derive(Clone, Default)]
+struct SourceContext {
+    language: Language,
+    origin: Option<Arc<Path>>,
+    conversion: Option<DecodePolicy>,
+    // the lines findings must be on, if not all
+    ranges: Option<Arc<[Range<usize>]>>,
+    segment: Option<Arc<Segment>>,
+    // the tree of the source, if it was already parsed
+    tree: Option<Tree>,
+    // the only check to report (by rule and check index), if not all
+    check: Option<(usize, usize)>,
+}
+
 impl RuleMatch {
     pub fn rule(&self) -> &Rule {
         &self.rule
     }
 
+    // the key of the rule within the `RuleSet` used for matching
+    pub fn rule_key(&self) -> &str {
+        &self.rule_key
+    }
+
+    // the index of the rule within the `RuleSet` used for matching
     pub fn rule_id(&self) -> usize {
         self.rule_id
     }
 
+    // the index of the checker within the rule's checks
     pub fn checker_id(&self) -> usize {
         self.checker_id
     }
 
+    // (rule key, rule id, checker name, checker index)
+    pub fn check_ref(&self) -> (&str, &str, &str, usize) {
+        (
+            &self.rule_key,
+            self.rule.id(),
+            self.checker().name(),
+            self.checker_id,
+        )
+    }
+
     pub fn checker(&self) -> &Checker {
         &self.rule().checks()[self.checker_id]
     }
 
+    // the severity of the rule, or that of the first escalation of the check
+    // whose captures match
+    pub fn effective_severity(&self) -> Severity {
+        self.severity
+    }
+
     pub fn source(&self) -> Arc<str> {
         self.source.clone()
     }
@@ -47,17 +253,229 @@ impl RuleMatch {
         &self.source
     }
 
+    // absent unless the match retains a snippet of the source (see
+    // `SourceRetention`)
+    pub fn source_base(&self) -> Option<&SourceBase> {
+        self.base.as_ref()
+    }
+
+    // the grammar used to parse the source
+    pub fn language(&self) -> Language {
+        self.language
+    }
+
+    // the path of the file the source was read from, if any
+    pub fn origin(&self) -> Option<&Path> {
+        self.origin.as_deref()
+    }
+
+    // the policy used to convert the input when it was not valid UTF-8; when
+    // set, offsets refer to the converted `source`, not the original bytes
+    pub fn conversion(&self) -> Option<DecodePolicy> {
+        self.conversion
+    }
+
     pub fn result(&self) -> &QueryResult {
         &self.result
     }
 
+    // the text captured by `variable` (e.g., `$func`), if any
+    pub fn capture(&self, variable: &str) -> Option<&str> {
+        self.result.value(variable, &self.source)
+    }
+
+    // the capture of `variable` as the rule's regex constraints also see it,
+    // if the rule's `mangling` applies to it (see `Mangling::demangle`)
+    pub fn demangled_capture(&self, variable: &str) -> Option<Cow<'_, str>> {
+        self.rule.mangling().demangle(self.capture(variable)?)
+    }
+
+    // the address of the match in the binary the source was decompiled from,
+    // if an `AddressExtractor` was set and found one
+    pub fn address(&self) -> Option<u64> {
+        self.address
+    }
+
+    // Identifies the finding across scans, independently of where it is in
+    // the file: it is derived from the rule id, the checker name, the origin,
+    // the name of the enclosing function (if any), and the matched text (with
+    // whitespace normalized), but not from offsets, lines, or columns.
+    //
+    // It changes when any of these do, e.g., when a rule is renamed, the file
+    // is moved, or the matched code is edited; identical findings within a
+    // source are distinguished by an ordinal suffix (e.g., `-2`), which
+    // depends on their relative order.
+    pub fn fingerprint(&self) -> &str {
+        &self.fingerprint
+    }
+
+    // the identity of the finding, as that of its report (see `ReportKey`)
+    pub fn key(&self) -> ReportKey<'_> {
+        ReportKey::from_match(self)
+    }
+
+    // why the finding was suppressed, if it was retained nonetheless (see
+    // `RuleMatcher::set_suppression_mode`)
+    pub fn suppression(&self) -> Option<&SuppressionReason> {
+        self.suppression.as_ref()
+    }
+
+    pub fn is_suppressed(&self) -> bool {
+        self.suppression.is_some()
+    }
+
+    // e.g., for findings suppressed by a baseline or by the configuration
+    pub fn with_suppression(mut self, reason: SuppressionReason) -> Self {
+        self.suppression = Some(reason);
+        self
+    }
+
+    // NOTE: offsets are byte offsets into the scanned source, which is
+    // `source` unless the match retains a snippet; `result` refers to
+    // `source` either way
+    pub fn start_offset(&self) -> usize {
+        self.base_offset() + self.source_span().start
+    }
+
+    pub fn end_offset(&self) -> usize {
+        self.base_offset() + self.source_span().end
+    }
+
+    fn base_offset(&self) -> usize {
+        self.base.map_or(0, |base| base.offset())
+    }
+
+    // the span of the match within `source`
+    pub(crate) fn source_span(&self) -> Range<usize> {
+        match_span(&self.result)
+    }
+
+    // the line number of the first line of `source`
+    pub(crate) fn first_line(&self) -> usize {
+        self.base.map_or(1, |base| base.line())
+    }
+
+    pub fn matched_text(&self) -> &str {
+        location::slice(&self.source, self.source_span())
+    }
+
+    pub fn char_start(&self) -> usize {
+        self.base.map_or(0, |base| base.char_offset())
+            + location::char_offset(&self.source, self.source_span().start)
+    }
+
+    pub fn char_end(&self) -> usize {
+        self.base.map_or(0, |base| base.char_offset())
+            + location::char_offset(&self.source, self.source_span().end)
+    }
+
+    // the line and column of `offset` (into the scanned source)
+    fn line_column(&self, offset: usize) -> (usize, usize) {
+        let (line, column) = location::line_column(&self.source, offset - self.base_offset());
+        (self.first_line() + line - 1, column)
+    }
+
+    // NOTE: lines and columns are 1-based; columns count characters
+    pub fn start_line(&self) -> usize {
+        self.line_column(self.start_offset()).0
+    }
+
+    pub fn start_column(&self) -> usize {
+        self.line_column(self.start_offset()).1
+    }
+
+    pub fn end_line(&self) -> usize {
+        self.line_column(self.end_offset()).0
+    }
+
+    pub fn end_column(&self) -> usize {
+        self.line_column(self.end_offset()).1
+    }
+
     pub fn into_result(self) -> QueryResult {
         self.result
     }
 
     pub fn display(&self, before: usize, after: usize, line_numbers: bool) -> String {
-        self.result
-            .display(&self.source, before, after, line_numbers)
+        self.display_with(&DisplayOptions::positional(before, after, line_numbers))
+    }
+
+    // the map from offsets in `source` to offsets in the input, when the input
+    // was preprocessed (e.g., normalized) before matching
+    pub fn offset_map(&self) -> Option<&OffsetMap> {
+        self.original.as_ref().map(|original| &original.map)
+    }
+
+    // the input before preprocessing, or `source` if it was not preprocessed
+    pub fn original_source(&self) -> &str {
+        self.original
+            .as_ref()
+            .map_or(&self.source, |original| &original.source)
+    }
+
+    // NOTE: the `*_original` accessors mirror the ones above, but refer to
+    // `original_source` rather than to `source`
+    pub fn start_offset_original(&self) -> usize {
+        self.original_range(self.start_offset()..self.end_offset())
+            .start
+    }
+
+    pub fn end_offset_original(&self) -> usize {
+        self.original_range(self.start_offset()..self.end_offset())
+            .end
+    }
+
+    fn line_column_original(&self, offset: usize) -> (usize, usize) {
+        match self.original {
+            Some(ref original) => location::line_column(&original.source, offset),
+            None => self.line_column(offset),
+        }
+    }
+
+    pub fn start_line_original(&self) -> usize {
+        self.line_column_original(self.start_offset_original()).0
+    }
+
+    pub fn start_column_original(&self) -> usize {
+        self.line_column_original(self.start_offset_original()).1
+    }
+
+    pub fn end_line_original(&self) -> usize {
+        self.line_column_original(self.end_offset_original()).0
+    }
+
+    pub fn end_column_original(&self) -> usize {
+        self.line_column_original(self.end_offset_original()).1
+    }
+
+    pub fn display_original(&self, before: usize, after: usize, line_numbers: bool) -> String {
+        let Some(ref original) = self.original else {
+            return self.display(before, after, line_numbers);
+        };
+
+        let captures = self
+            .result
+            .captures
+            .iter()
+            .map(|c| CaptureResult {
+                range: self.original_range(c.range.clone()),
+                ..c.clone()
+            })
+            .collect::<Vec<_>>();
+        let function = captures.first().map(|c| c.range.clone()).unwrap_or(0..0);
+
+        self.display_original_with(
+            &original.source,
+            &QueryResult::new(captures, self.result.vars.clone(), function),
+            &DisplayOptions::positional(before, after, line_numbers),
+        )
+    }
+
+    fn original_range(&self, range: Range<usize>) -> Range<usize> {
+        match self.original {
+            Some(ref original) => original.map.range_to_original(range),
+            None => range,
+        }
     }
 }
 
@@ -68,12 +486,34 @@ impl Debug for RuleMatch {
         m.field("rule", &self.rule.id() as _);
         m.field("checker", &self.checker().name() as _);
 
+        if let Some(ref origin) = self.origin {
+            m.field("origin", origin as _);
+        }
+
+        m.field("language", &self.language as _);
+
         if let Some(ref description) = self.rule().description() {
             m.field("description", description as _);
         }
 
         m.field("tags", self.rule().tags() as _);
-        m.field("severity", &self.rule().severity() as _);
+        m.field("severity", &self.severity as _);
+
+        if let Some(ref conversion) = self.conversion {
+            m.field("conversion", conversion as _);
+        }
+
+        if let Some(ref suppression) = self.suppression {
+            m.field("suppression", suppression as _);
+        }
+
+        if let Some(ref segment) = self.segment {
+            m.field("segment", segment as _);
+        }
+
+        if let Some(ref address) = self.address {
+            m.field("address", &format_args!("{address:#x}") as _);
+        }
 
         m.field("match", &self.result as _);
 
@@ -81,35 +521,122 @@ impl Debug for RuleMatch {
     }
 }
 
+// NOTE: the first capture is the outermost node of the match (e.g., the
+// enclosing function); the span of a match is that of the remaining captures,
+// i.e., what `QueryResult::display` highlights, except those of `ts-query`
+// checks that are variables only (see `VARIABLE_CAPTURE`)
+pub(crate) fn match_span(result: &QueryResult) -> Range<usize> {
+    let inner = result
+        .captures
+        .iter()
+        .skip(1)
+        .filter(|c| c.query_id != VARIABLE_CAPTURE)
+        .map(|c| c.range.clone());
+    let span = inner.reduce(|a, b| a.start.min(b.start)..a.end.max(b.end));
+
+    span.or_else(|| result.captures.first().map(|c| c.range.clone()))
+        .unwrap_or_else(|| result.start_offset()..result.start_offset())
+}
+
+// Drops the matches of the rules with a condition (see `Condition`) that it
+// does not report, given the checks that matched the source.
+//
+// NOTE: conditions see the matches before suppressions apply
+fn retain_reported(results: &mut Vec<RuleMatch>) {
+    if results.iter().all(|m| m.rule.condition().is_none()) {
+        return;
+    }
+
+    let fired = results
+        .iter()
+        .map(|m| (m.rule_id, m.checker_id))
+        .collect::<FxHashSet<_>>();
+    let mut reported = FxHashMap::<usize, Vec<usize>>::default();
+
+    results.retain(|m| {
+        let Some(condition) = m.rule.condition() else {
+            return true;
+        };
+
+        reported
+            .entry(m.rule_id)
+            .or_insert_with(|| condition.reported(|i| fired.contains(&(m.rule_id, i))))
+            .contains(&m.checker_id)
+    });
+}
+
 #[derive(Debug, Error)]
 pub enum RuleMatcherError {
     #[error("cannot construct parser")]
     Parser(weggli::WeggliError),
     #[error(transparent)]
     Rules(#[from] RuleError),
+    #[error("source is not valid UTF-8: {0}")]
+    Utf8(#[from] std::str::Utf8Error),
+    #[error("cannot read source file {}: {1}", _0.display())]
+    Io(PathBuf, std::io::Error),
+    #[error("cannot read source: {0}")]
+    Read(std::io::Error),
+    #[error("source exceeds the limit of {0} bytes")]
+    TooLarge(usize),
+    #[error("invalid ignore pattern `{0}`: {1}")]
+    IgnorePattern(String, Box<dyn std::error::Error + Send + Sync>),
+    #[error("cannot parse diff: {0}")]
+    Diff(#[from] DiffError),
+    #[error("no rule with key or id `{0}`")]
+    UnknownRule(String),
+    #[error("rule `{rule}` has no check named `{check}`")]
+    UnknownCheck { rule: String, check: String },
 }
 
 impl RuleMatcher {
     pub fn new(rules: RuleSet) -> Result<Self, RuleMatcherError> {
         Ok(Self {
             rules,
+            filter: RuleFilter::default(),
+            prefilter: PrefilterMode::default(),
+            range_mode: RangeMode::default(),
+            normalizer: None,
+            macro_expander: None,
+            address_extractor: None,
+            min_function_lines: None,
+            max_function_lines: None,
+            suppression: SuppressionMode::default(),
+            metrics: ScanMetrics::default(),
+            prefilter_counts: PrefilterCounts::default(),
+            coverage: None,
+            seen: SeenValues::default(),
+            interner: None,
+            check_budget: None,
+            circuit_breaker: false,
+            run_mismatched_languages: false,
+            max_file_size: None,
+            retention: SourceRetention::default(),
+            #[cfg(feature = "mmap")]
+            mapped_input: false,
+            source_timeout: None,
+            tripped: FxHashSet::default(),
             c_parser: weggli::get_parser(false).map_err(RuleMatcherError::Parser)?,
             cxx_parser: weggli::get_parser(true).map_err(RuleMatcherError::Parser)?,
         })
     }
 
+    #[cfg(feature = "std-fs")]
     pub fn from_file(path: impl AsRef<Path>) -> Result<Self, RuleMatcherError> {
         Self::new(RuleSet::from_file(path)?)
     }
 
+    #[allow(clippy::should_implement_trait)]
     pub fn from_str(rule: impl AsRef<str>) -> Result<Self, RuleMatcherError> {
         Self::new(RuleSet::from_str(rule)?)
     }
 
+    #[cfg(feature = "std-fs")]
     pub fn from_directory(root: impl AsRef<Path>) -> Result<Self, RuleMatcherError> {
         Self::from_directory_with(root, true)
     }
 
+    #[cfg(feature = "std-fs")]
     pub fn from_directory_with(
         root: impl AsRef<Path>,
         ignore_errors: bool,
@@ -121,107 +648,994 @@ impl RuleMatcher {
         &self.rules
     }
 
+    // enables/disables all rules whose key or id is `rule`; returns false if
+    // no such rule exists
+    pub fn set_rule_enabled(&mut self, rule: impl AsRef<str>, enabled: bool) -> bool {
+        let rule = rule.as_ref();
+        let mut found = false;
+
+        for (i, (key, r)) in self.rules.iter().enumerate() {
+            if key != rule && r.id() != rule {
+                continue;
+            }
+
+            found = true;
+
+            if enabled {
+                self.filter.disabled.remove(&i);
+            } else {
+                self.filter.disabled.insert(i);
+            }
+        }
+
+        found
+    }
+
+    pub fn is_rule_enabled(&self, index: usize) -> bool {
+        !self.filter.disabled.contains(&index)
+    }
+
+    pub fn disabled_rules(&self) -> impl Iterator<Item = (&str, &Rule)> {
+        self.rules
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| self.filter.disabled.contains(i))
+            .map(|(_, r)| r)
+    }
+
     pub fn matches(&mut self, source: impl AsRef<str>) -> Result<Vec<RuleMatch>, RuleMatcherError> {
-        self.matches_with(source, false)
+        self.matches_lang(source, Language::C)
     }
 
+    #[deprecated(note = "use `matches_lang`")]
     pub fn matches_with(
         &mut self,
         source: impl AsRef<str>,
         is_cxx: bool,
     ) -> Result<Vec<RuleMatch>, RuleMatcherError> {
-        let source = source.as_ref();
+        let language = if is_cxx {
+            Language::Cplusplus
+        } else {
+            Language::C
+        };
+        self.matches_lang(source, language)
+    }
 
-        let checkers = self.rules.viable_checkers(source);
+    pub fn matches_lang(
+        &mut self,
+        source: impl AsRef<str>,
+        language: Language,
+    ) -> Result<Vec<RuleMatch>, RuleMatcherError> {
+        let context = SourceContext {
+            language,
+            ..Default::default()
+        };
 
-        if checkers.is_empty() {
-            return Ok(Vec::with_capacity(0));
-        }
+        self.scan(|matcher| matcher.matches_source(source.as_ref(), context))
+    }
 
-        let tree = if is_cxx {
-            self.cxx_parser.parse(source.as_bytes(), None)
-        } else {
-            self.c_parser.parse(source.as_bytes(), None)
+    // Matches as `matches_lang` does, but keeps only the findings on the
+    // lines within `ranges` (1-based, end exclusive; see `RangeMode`), e.g.,
+    // those a change modified. The findings are those a full scan would
+    // report on these lines, but checks that cannot match within the
+    // top-level definitions spanning them are not run.
+    pub fn matches_in_ranges(
+        &mut self,
+        source: impl AsRef<str>,
+        language: Language,
+        ranges: &[Range<usize>],
+    ) -> Result<Vec<RuleMatch>, RuleMatcherError> {
+        let context = SourceContext {
+            language,
+            ranges: Some(Arc::from(ranges)),
+            ..Default::default()
         };
 
-        // parse failed...
-        let Some(tree) = tree else {
-            return Ok(Vec::with_capacity(0));
+        self.scan(|matcher| matcher.matches_source(source.as_ref(), context))
+    }
+
+    // Runs the check named `check` of the rule whose key or id is `rule` only,
+    // e.g., while writing it, regardless of the prefilter (see
+    // `Checker::can_match` for its outcome). The findings are those a full
+    // scan would report for the check: the matcher's filters apply (e.g.,
+    // disabled rules match nothing), and the other checks of a rule with a
+    // condition are run too, though not reported, since it depends on them.
+    //
+    // NOTE: the first rule with that key or id is run
+    pub fn run_check(
+        &mut self,
+        rule: impl AsRef<str>,
+        check: impl AsRef<str>,
+        source: impl AsRef<str>,
+        language: Language,
+    ) -> Result<Vec<RuleMatch>, RuleMatcherError> {
+        let (rule, check) = (rule.as_ref(), check.as_ref());
+
+        let (rule_id, (_, found)) = self
+            .rules
+            .iter()
+            .enumerate()
+            .find(|(_, (key, r))| *key == rule || r.id() == rule)
+            .ok_or_else(|| RuleMatcherError::UnknownRule(rule.to_owned()))?;
+        let checker_id = found
+            .checks()
+            .iter()
+            .position(|c| c.name() == check)
+            .ok_or_else(|| RuleMatcherError::UnknownCheck {
+                rule: rule.to_owned(),
+                check: check.to_owned(),
+            })?;
+
+        let context = SourceContext {
+            language,
+            check: Some((rule_id, checker_id)),
+            ..Default::default()
         };
 
-        let source = Arc::<str>::from(source);
+        // NOTE: the run is not part of the coverage of the rule set, as the
+        // other checks are not considered
+        let coverage = self.coverage.take();
+        let matches = self.scan(|matcher| matcher.matches_source(source.as_ref(), context));
+        self.coverage = coverage;
 
-        let results = checkers
-            .into_iter()
-            .flat_map(|(rule_id, rule, checker_id, checker)| {
-                let source = source.clone();
-                checker
-                    .check_match(&tree, &source)
-                    .into_iter()
-                    .map(move |result| RuleMatch {
-                        rule: rule.clone(),
-                        rule_id,
-                        checker_id,
-                        source: source.clone(),
-                        result,
-                    })
-            })
-            .collect();
+        matches
+    }
 
-        Ok(results)
+    pub fn matches_detect(
+        &mut self,
+        source: impl AsRef<str>,
+    ) -> Result<Vec<RuleMatch>, RuleMatcherError> {
+        let source = source.as_ref();
+        self.matches_lang(source, Language::detect(source))
     }
-}
 
-#[cfg(test)]
-mod test {
-    use super::RuleMatcher;
-    use std::fs;
+    pub fn matches_bytes(
+        &mut self,
+        bytes: impl AsRef<[u8]>,
+        language: Language,
+        policy: DecodePolicy,
+    ) -> Result<Vec<RuleMatch>, RuleMatcherError> {
+        let (source, converted) = policy.decode(bytes.as_ref())?;
 
-    #[test]
-    fn test_strcpy() -> Result<(), Box<dyn std::error::Error>> {
-        let decompiler_output = r#"
-char *__fastcall sub_XYZ(char *s, size_t a2)
-{
-  char *v2; // rbx
-  char *v3; // rax
-  const char *v4; // r15
-  char *v5; // rax
-  const char *v6; // r15
+        let context = SourceContext {
+            language,
+            conversion: if converted { Some(policy) } else { None },
+            ..Default::default()
+        };
 
-  v2 = s;
-  v3 = j__secure_getenv("ZZZ");
-  if ( !v3 || (v4 = v3, !*v3) )
-  {
-    v5 = j__secure_getenv("HOME");
-    if ( v5 )
-    {
-      v6 = v5;
-      if ( *v5 )
-      {
-        if ( strlen(v5) + 6 < a2 )
-        {
-          strcpy(s, v6);
-          *(_WORD *)&s[strlen(s)] = 47;
-          strcat(s, ".rnd");
-          return v2;
+        self.scan(|matcher| matcher.matches_source(&source, context))
+    }
+
+    // the language is derived from the file's extension, falling back to
+    // detection from its contents
+    #[cfg(feature = "std-fs")]
+    pub fn matches_file(
+        &mut self,
+        path: impl AsRef<Path>,
+    ) -> Result<Vec<RuleMatch>, RuleMatcherError> {
+        self.matches_file_impl(path.as_ref(), None)
+    }
+
+    #[cfg(feature = "std-fs")]
+    pub fn matches_file_with(
+        &mut self,
+        path: impl AsRef<Path>,
+        language: Language,
+    ) -> Result<Vec<RuleMatch>, RuleMatcherError> {
+        self.matches_file_impl(path.as_ref(), Some(language))
+    }
+
+    #[cfg(feature = "std-fs")]
+    fn matches_file_impl(
+        &mut self,
+        path: &Path,
+        language: Option<Language>,
+    ) -> Result<Vec<RuleMatch>, RuleMatcherError> {
+        self.scan(|matcher| {
+            let io_error = |e| RuleMatcherError::Io(path.to_owned(), e);
+
+            let bytes = matcher.read_file(path).map_err(io_error)?;
+            let source = std::str::from_utf8(&bytes)
+                .map_err(|e| io_error(io::Error::new(io::ErrorKind::InvalidData, e)))?;
+
+            let language = language
+                .or_else(|| Language::from_path(path))
+                .unwrap_or_else(|| Language::detect(source));
+
+            let context = SourceContext {
+                language,
+                origin: Some(Arc::from(path)),
+                ..Default::default()
+            };
+
+            matcher.matches_source(source, context)
+        })
+    }
+
+    // the metrics of the most recent scanning call, which replaces those of
+    // the previous one
+    pub fn last_metrics(&self) -> &ScanMetrics {
+        &self.metrics
+    }
+
+    // When enabled, the matcher counts, for each check, the sources it was
+    // considered for, passed the prefilter for, and the findings it produced,
+    // across all scanning calls (including directory scans) until coverage is
+    // reset or tracking is disabled; see `coverage`.
+    //
+    // NOTE: tracking is disabled by default, as it adds work to every source
+    pub fn set_coverage_tracking(&mut self, enabled: bool) {
+        match (enabled, &self.coverage) {
+            (true, None) => self.coverage = Some(CoverageTracker::default()),
+            (false, Some(_)) => self.coverage = None,
+            _ => {}
         }
-      }
     }
-    return 0LL;
-  }
-  if ( strlen(v3) + 1 >= a2 )
-    return 0LL;
-  strcpy(s, v4);
-  return v2;
-}
-"#;
 
-        let rule = r#"
-id: call-to-unbounded-copy-functions
-description: call to unbounded copy functions
-severity: medium
-tags:
-- CWE-120
+    pub fn is_tracking_coverage(&self) -> bool {
+        self.coverage.is_some()
+    }
+
+    // the coverage of the rule set so far; all counters are zero if tracking
+    // is disabled
+    pub fn coverage(&self) -> RuleCoverage {
+        self.coverage
+            .as_ref()
+            .map(|tracker| tracker.report(&self.rules))
+            .unwrap_or_else(|| CoverageTracker::default().report(&self.rules))
+    }
+
+    pub fn reset_coverage(&mut self) {
+        if let Some(tracker) = &mut self.coverage {
+            *tracker = CoverageTracker::default();
+        }
+    }
+
+    fn scan<T>(
+        &mut self,
+        f: impl FnOnce(&mut Self) -> Result<T, RuleMatcherError>,
+    ) -> Result<T, RuleMatcherError> {
+        let started = Instant::now();
+        self.metrics = ScanMetrics::default();
+        self.prefilter_counts.clear();
+        self.tripped.clear();
+        self.seen.clear();
+
+        let result = f(self);
+
+        self.metrics.set_elapsed(started.elapsed());
+        result
+    }
+
+    // rules below `severity` are excluded before the prefilter, and so are
+    // never evaluated
+    pub fn set_min_severity(&mut self, severity: Severity) {
+        self.filter.min_severity = Some(severity);
+    }
+
+    pub fn min_severity(&self) -> Option<Severity> {
+        self.filter.min_severity
+    }
+
+    pub fn clear_min_severity(&mut self) {
+        self.filter.min_severity = None;
+    }
+
+    // when set, sources are normalized before matching; the normalized text
+    // is the source stored on each match
+    pub fn set_normalizer(&mut self, normalizer: Option<Normalizer>) {
+        self.normalizer = normalizer;
+    }
+
+    pub fn normalizer(&self) -> Option<Normalizer> {
+        self.normalizer
+    }
+
+    // when set, the macros a source defines are expanded before it is
+    // normalized and matched (see `MacroExpander`); as with the normalizer,
+    // matches display the expanded text, and the `*_original` accessors refer
+    // to the input
+    pub fn set_macro_expander(&mut self, expander: Option<MacroExpander>) {
+        self.macro_expander = expander;
+    }
+
+    pub fn macro_expander(&self) -> Option<MacroExpander> {
+        self.macro_expander
+    }
+
+    // when set, matches are mapped back to addresses in the binary (see
+    // `AddressExtractor` and `RuleMatch::address`)
+    pub fn set_address_extractor(&mut self, extractor: Option<AddressExtractor>) {
+        self.address_extractor = extractor;
+    }
+
+    pub fn address_extractor(&self) -> Option<&AddressExtractor> {
+        self.address_extractor.as_ref()
+    }
+
+    // when set, matches within functions of fewer (or more) lines are dropped,
+    // for all rules, in addition to the `min-function-lines` and
+    // `max-function-lines` of their checks; matches outside any function are
+    // kept
+    pub fn set_min_function_lines(&mut self, lines: Option<usize>) {
+        self.min_function_lines = lines;
+    }
+
+    pub fn min_function_lines(&self) -> Option<usize> {
+        self.min_function_lines
+    }
+
+    pub fn set_max_function_lines(&mut self, lines: Option<usize>) {
+        self.max_function_lines = lines;
+    }
+
+    pub fn max_function_lines(&self) -> Option<usize> {
+        self.max_function_lines
+    }
+
+    // whether findings are suppressed by comments in the source (see
+    // `SUPPRESSION_MARKER`), and if so, whether they are retained
+    pub fn set_suppression_mode(&mut self, mode: SuppressionMode) {
+        self.suppression = mode;
+    }
+
+    pub fn suppression_mode(&self) -> SuppressionMode {
+        self.suppression
+    }
+
+    pub fn set_prefilter_mode(&mut self, mode: PrefilterMode) {
+        self.prefilter = mode;
+    }
+
+    pub fn prefilter_mode(&self) -> PrefilterMode {
+        self.prefilter
+    }
+
+    // A run of a check that takes longer than the budget is reported in the
+    // scan's metrics (see `ScanMetrics::over_budget`); with the circuit
+    // breaker, the check is also disabled for the rest of the scan.
+    //
+    // NOTE: a run cannot be interrupted, so a check whose matching blows up
+    // is only reported (and disabled) once it completes; `max-source-bytes`
+    // keeps checks from running on sources too large for them
+    pub fn set_check_budget(&mut self, budget: Option<Duration>) {
+        self.check_budget = budget;
+    }
+
+    pub fn check_budget(&self) -> Option<Duration> {
+        self.check_budget
+    }
+
+    pub fn set_circuit_breaker(&mut self, enabled: bool) {
+        self.circuit_breaker = enabled;
+    }
+
+    pub fn is_circuit_breaker_enabled(&self) -> bool {
+        self.circuit_breaker
+    }
+
+    // a coarse bound on the time spent on a source: the deadline is checked
+    // before each check is run, and once it expires, the remaining checks
+    // are skipped (see `ScanMetrics::sources_timed_out`)
+    pub fn set_source_timeout(&mut self, timeout: Option<Duration>) {
+        self.source_timeout = timeout;
+    }
+
+    pub fn source_timeout(&self) -> Option<Duration> {
+        self.source_timeout
+    }
+
+    // By default, checks written for C++ are skipped for sources parsed as C,
+    // where they would silently match nothing (or the wrong nodes), and each
+    // skip is reported in the scan's metrics (see
+    // `ScanMetrics::language_mismatches`); this runs them anyway, as before.
+    //
+    // NOTE: checks written for C run on C++ sources, as the C++ grammar
+    // extends that of C
+    pub fn set_run_mismatched_languages(&mut self, enabled: bool) {
+        self.run_mismatched_languages = enabled;
+    }
+
+    pub fn runs_mismatched_languages(&self) -> bool {
+        self.run_mismatched_languages
+    }
+
+    // the size (in bytes) of the largest source the rules without a
+    // `max-file-size` of their own are run on; larger sources are only
+    // scanned by the rules that allow them
+    pub fn set_max_file_size(&mut self, bytes: Option<usize>) {
+        self.max_file_size = bytes;
+    }
+
+    pub fn max_file_size(&self) -> Option<usize> {
+        self.max_file_size
+    }
+
+    pub fn set_source_retention(&mut self, retention: SourceRetention) {
+        self.retention = retention;
+    }
+
+    pub fn source_retention(&self) -> SourceRetention {
+        self.retention
+    }
+
+    pub fn set_range_mode(&mut self, mode: RangeMode) {
+        self.range_mode = mode;
+    }
+
+    pub fn range_mode(&self) -> RangeMode {
+        self.range_mode
+    }
+
+    fn viable_checkers<'a>(
+        &self,
+        rules: &'a RuleSet,
+        source: &str,
+        context: &SourceContext,
+    ) -> Vec<(usize, Arc<Rule>, usize, &'a Checker)> {
+        let bytes = source.len();
+        let source = self.prefilter_source(source);
+        let source = source.as_ref();
+
+        rules.viable_checkers_with(source, |i, rule| {
+            self.selects(i, rule, context) && self.fits(rule, bytes)
+        })
+    }
+
+    // whether a source of `bytes` is within the rule's `max-file-size` (or
+    // the matcher's default)
+    fn fits(&self, rule: &Rule, bytes: usize) -> bool {
+        rule.max_file_size()
+            .map(FileSize::bytes)
+            .or(self.max_file_size)
+            .is_none_or(|max| bytes <= max)
+    }
+
+    // NOTE: sources without an origin satisfy all path constraints
+    fn selects(&self, index: usize, rule: &Rule, context: &SourceContext) -> bool {
+        self.filter.allows(index, rule)
+            && context
+                .origin
+                .as_ref()
+                .is_none_or(|path| rule.applies_to(path))
+    }
+
+    // records the checks selected for a source (by rule and check index), and
+    // those that passed the prefilter
+    fn record_prefilter(
+        &mut self,
+        selected: &[(usize, usize)],
+        checkers: &[(usize, Arc<Rule>, usize, &Checker)],
+    ) {
+        for &(rule_id, checker_id) in selected {
+            self.prefilter_counts.record_evaluated(rule_id, checker_id);
+        }
+        for (rule_id, _, checker_id, _) in checkers {
+            self.prefilter_counts.record_passed(*rule_id, *checker_id);
+        }
+
+        #[cfg(feature = "trace")]
+        for &(rule_id, checker_id) in selected {
+            trace::event!(
+                TRACE,
+                "prefilter",
+                rule = self
+                    .rules
+                    .get(rule_id)
+                    .map_or_else(String::new, |r| r.id().to_owned()),
+                check = self
+                    .rules
+                    .get(rule_id)
+                    .and_then(|r| r.checks().get(checker_id).map(|c| c.name().to_owned()))
+                    .unwrap_or_default(),
+                passed = checkers
+                    .iter()
+                    .any(|(r, _, c, _)| (*r, *c) == (rule_id, checker_id)),
+            );
+        }
+
+        let Some(tracker) = &mut self.coverage else {
+            return;
+        };
+
+        tracker.record_source();
+        for &(rule_id, checker_id) in selected {
+            tracker.record_considered(rule_id, checker_id);
+        }
+        for (rule_id, _, checker_id, _) in checkers {
+            tracker.record_viable(*rule_id, *checker_id);
+        }
+    }
+
+    fn record_too_large(&mut self, rules: &[(usize, (&str, &Rule))]) {
+        self.metrics.record_too_large(rules.len());
+
+        let Some(tracker) = &mut self.coverage else {
+            return;
+        };
+
+        for &(rule_id, (_, rule)) in rules {
+            for checker_id in 0..rule.checks().len() {
+                tracker.record_too_large(rule_id, checker_id);
+            }
+        }
+    }
+
+    // reports (and, with the circuit breaker, disables) a check whose run took
+    // longer than the budget
+    fn check_elapsed(
+        &mut self,
+        rules: &RuleSet,
+        (rule_id, checker_id): (usize, usize),
+        context: &SourceContext,
+        started: Instant,
+    ) {
+        let elapsed = started.elapsed();
+        if self.check_budget.is_none_or(|budget| elapsed <= budget) {
+            return;
+        }
+
+        let Some((key, rule)) = rules.iter().nth(rule_id) else {
+            return;
+        };
+        let check = rule.checks()[checker_id].name();
+
+        if self.circuit_breaker {
+            self.tripped.insert((rule_id, checker_id));
+        }
+
+        self.metrics.record_over_budget(
+            (key, rule.id(), check),
+            context.origin.as_deref(),
+            elapsed,
+            self.circuit_breaker,
+        );
+    }
+
+    fn is_mismatched(&self, checker: &Checker, context: &SourceContext) -> bool {
+        !self.run_mismatched_languages && checker.language().is_cxx() && context.language.is_c()
+    }
+
+    // the text identifiers are searched for, according to the prefilter mode
+    fn prefilter_source<'s>(&self, source: &'s str) -> Cow<'s, str> {
+        match self.prefilter {
+            PrefilterMode::Raw => Cow::Borrowed(source),
+            PrefilterMode::CodeOnly => {
+                comments::blank_comments(source).unwrap_or(Cow::Borrowed(source))
+            }
+        }
+    }
+
+    // the source to match (with macros expanded and normalized, if set),
+    // unless it is the input as is, and the input if preprocessing changed it
+    fn normalize(&self, input: &str) -> (Option<Arc<str>>, Option<Arc<Original>>) {
+        if self.normalizer.is_none() && self.macro_expander.is_none() {
+            return (None, None);
+        }
+
+        let (expanded, map) = match self.macro_expander {
+            Some(ref expander) => expander.expand_mapped(input),
+            None => (Cow::Borrowed(input), OffsetMap::new()),
+        };
+        let (normalized, next) = match self.normalizer {
+            Some(ref normalizer) => normalizer.normalize_mapped(&expanded),
+            None => (Cow::Borrowed(expanded.as_ref()), OffsetMap::new()),
+        };
+        let map = map.compose(next);
+
+        let original = (!map.is_identity()).then(|| {
+            Arc::new(Original {
+                source: Arc::from(input),
+                map,
+            })
+        });
+
+        (Some(Arc::from(normalized.as_ref())), original)
+    }
+
+    fn parse(&mut self, source: &str, language: Language) -> Option<Tree> {
+        if language.is_cxx() {
+            self.cxx_parser.parse(source.as_bytes(), None)
+        } else {
+            self.c_parser.parse(source.as_bytes(), None)
+        }
+    }
+
+    fn matches_source(
+        &mut self,
+        input: &str,
+        mut context: SourceContext,
+    ) -> Result<Vec<RuleMatch>, RuleMatcherError> {
+        let _span = trace::span!(
+            "match_source",
+            origin = context
+                .origin
+                .as_deref()
+                .map_or("-".into(), |path| path.display().to_string()),
+            language = format_args!("{:?}", context.language),
+            bytes = input.len(),
+        );
+
+        let deadline = self.source_timeout.map(|timeout| Instant::now() + timeout);
+
+        let interned = self
+            .interner
+            .as_ref()
+            .and_then(|interner| interner.get(input, context.language));
+
+        let (prepared, original, tree, handle) = match interned {
+            Some(interned) => {
+                self.metrics.record_duplicate();
+                (
+                    Some(interned.source),
+                    interned.original,
+                    interned.tree,
+                    Some(interned.handle),
+                )
+            }
+            None => {
+                let (mut source, original) = self.normalize(input);
+                let handle = self.interner.as_mut().map(|interner| {
+                    let source = source.get_or_insert_with(|| Arc::from(input)).clone();
+                    interner.insert(input, context.language, source, original.clone())
+                });
+                (source, original, None, handle)
+            }
+        };
+
+        // NOTE: an input matched as is (e.g., a mapped file) is only copied
+        // into the source the matches share once a check matches it
+        let source = prepared.as_deref().unwrap_or(input);
+        let mut shared = prepared.clone();
+
+        // NOTE: with `Snippet` retention, matches of a source that was not
+        // preprocessed are converted to snippets of it as soon as they are
+        // complete, so it is never copied; until then, they share an empty one
+        let snippet = match self.retention {
+            SourceRetention::Snippet { before, after } if original.is_none() => {
+                shared.get_or_insert_with(|| Arc::from(""));
+                Some((before, after))
+            }
+            _ => None,
+        };
+
+        let rules = self.rules.clone();
+
+        // NOTE: rules are skipped for sources larger than their
+        // `max-file-size` before the prefilter
+        let (selected, too_large) = rules
+            .iter()
+            .enumerate()
+            .filter(|(i, (_, rule))| self.selects(*i, rule, &context))
+            .partition::<Vec<_>, _>(|(_, (_, rule))| self.fits(rule, source.len()));
+
+        // NOTE: a single check bypasses the prefilter, along with the other
+        // checks of its rule if it has a condition
+        let checkers = match context.check {
+            Some((rule_id, checker_id)) => selected
+                .iter()
+                .filter(|(i, _)| *i == rule_id)
+                .flat_map(|&(i, (_, rule))| {
+                    let shared = rules.get(i).expect("selected rules exist");
+                    rule.checks()
+                        .iter()
+                        .enumerate()
+                        .filter(move |(j, _)| *j == checker_id || rule.condition().is_some())
+                        .map(move |(j, checker)| (i, shared.clone(), j, checker))
+                })
+                .collect(),
+            None => self.viable_checkers(&rules, source, &context),
+        };
+
+        self.record_too_large(&too_large);
+
+        let selected = selected
+            .into_iter()
+            .flat_map(|(i, (_, rule))| (0..rule.checks().len()).map(move |j| (i, j)))
+            .collect::<Vec<_>>();
+
+        // NOTE: a single check bypasses the prefilter, so it has no outcome to
+        // record
+        if context.check.is_none() {
+            self.record_prefilter(&selected, &checkers);
+        }
+        let selected = selected.len();
+
+        if checkers.is_empty() {
+            self.metrics.record_source(source.len(), 0, selected);
+            return Ok(Vec::with_capacity(0));
+        }
+
+        // NOTE: sources are only parsed if a viable check needs their tree,
+        // e.g., not if only text checks (`match-regex`) are viable
+        let parse = self.address_extractor.is_some()
+            || self.min_function_lines.is_some()
+            || self.max_function_lines.is_some()
+            || checkers
+                .iter()
+                .any(|(_, _, _, checker)| checker.needs_parse());
+
+        let tree = match tree {
+            Some(tree) => Some(tree),
+            None if !parse => None,
+            None => {
+                // NOTE: a tree parsed beforehand is that of the input, so it
+                // is only reused if normalization left it unchanged
+                let tree = match context.tree.take() {
+                    Some(tree) if original.is_none() => Some(tree),
+                    _ => self.parse(source, context.language),
+                };
+
+                // parse failed...
+                let Some(tree) = tree else {
+                    self.metrics.record_parse_failure();
+                    return Ok(Vec::with_capacity(0));
+                };
+
+                self.metrics.record_parse();
+                if let (Some(interner), Some(handle)) = (&mut self.interner, handle) {
+                    interner.set_tree(handle, &tree);
+                }
+                Some(tree)
+            }
+        };
+
+        // NOTE: the checks of rules with a condition all run, since it depends
+        // on the matches outside the ranges too; so do all checks when the
+        // normalizer moved lines
+        let checkers = match (&context.ranges, &tree) {
+            (Some(ranges), Some(tree)) if original.is_none() => {
+                let window = ranges::window(tree, ranges);
+                checkers
+                    .into_iter()
+                    .filter(|(_, rule, _, checker)| {
+                        rule.condition().is_some()
+                            || ranges::can_match_within(checker, source, &window)
+                    })
+                    .collect()
+            }
+            _ => checkers,
+        };
+
+        let mut results = Vec::new();
+        let mut evaluated = 0;
+        let mut timed_out = false;
+
+        // NOTE: the conditions of rules some of whose checks were skipped
+        // cannot be evaluated, e.g., `a and not b` if `b` was not run
+        let mut incomplete = FxHashSet::default();
+
+        for (rule_id, rule, checker_id, checker) in &checkers {
+            let (rule_id, checker_id) = (*rule_id, *checker_id);
+
+            timed_out = timed_out || deadline.is_some_and(|deadline| Instant::now() >= deadline);
+
+            let skipped = if source.len() > checker.max_source_bytes() {
+                self.metrics.record_oversized();
+                true
+            } else if self.is_mismatched(checker, &context) {
+                self.metrics.record_language_mismatch(
+                    (
+                        &rules.key_shared(rule_id).unwrap_or_default(),
+                        rule.id(),
+                        checker.name(),
+                    ),
+                    context.origin.as_deref(),
+                    checker.language(),
+                    context.language,
+                );
+                true
+            } else {
+                timed_out || self.tripped.contains(&(rule_id, checker_id))
+            };
+
+            if skipped {
+                if rule.condition().is_some() {
+                    incomplete.insert(rule_id);
+                }
+                continue;
+            }
+
+            // NOTE: an identical source has the same results, at the same
+            // offsets
+            let cached = match (&mut self.interner, handle) {
+                (Some(interner), Some(handle)) => interner.results(handle, rule_id, checker_id),
+                _ => None,
+            };
+
+            let found = match cached {
+                Some(found) => found,
+                None => {
+                    let _span =
+                        trace::span!("check_match", rule = rule.id(), check = checker.name());
+
+                    evaluated += 1;
+                    let started = Instant::now();
+                    let found = checker.check(tree.as_ref(), source);
+                    self.check_elapsed(&rules, (rule_id, checker_id), &context, started);
+                    trace::event!(TRACE, "checked", matches = found.len());
+
+                    if let (Some(interner), Some(handle)) = (&mut self.interner, handle) {
+                        interner.set_results(handle, rule_id, checker_id, &found);
+                    }
+                    found
+                }
+            };
+
+            if found.is_empty() {
+                continue;
+            }
+
+            let rule_key = rules.key_shared(rule_id).unwrap_or_default();
+            let shared_source = shared.get_or_insert_with(|| Arc::from(source));
+
+            results.extend(found.into_iter().map(|result| {
+                RuleMatch {
+                    severity: checker
+                        .escalation(&result, source)
+                        .unwrap_or(rule.severity()),
+                    rule: rule.clone(),
+                    rule_key: rule_key.clone(),
+                    rule_id,
+                    checker_id,
+                    source: shared_source.clone(),
+                    language: context.language,
+                    origin: context.origin.clone(),
+                    conversion: context.conversion,
+                    original: original.clone(),
+                    base: None,
+                    fingerprint: Arc::from(""),
+                    suppression: None,
+                    segment: context.segment.clone(),
+                    address: None,
+                    result,
+                }
+            }));
+        }
+
+        if timed_out {
+            self.metrics.record_timeout();
+        }
+
+        results.retain(|m| !incomplete.contains(&m.rule_id));
+
+        self.metrics
+            .record_source(source.len(), evaluated, selected - checkers.len());
+
+        retain_reported(&mut results);
+
+        if let Some(check) = context.check {
+            results.retain(|m| (m.rule_id, m.checker_id) == check);
+        }
+
+        // rules that may escalate are matched, but their matches must still
+        // reach the minimum severity
+        if let Some(severity) = self.filter.min_severity {
+            results.retain(|m| m.severity >= severity);
+        }
+
+        if let (Some(tree), true) = (
+            &tree,
+            self.min_function_lines.is_some() || self.max_function_lines.is_some(),
+        ) {
+            results.retain(|m| {
+                function_lines_within(
+                    tree,
+                    match_span(&m.result),
+                    self.min_function_lines,
+                    self.max_function_lines,
+                )
+            });
+        }
+
+        fingerprint::assign(&mut results, source, tree.as_ref());
+
+        if let Some(ref extractor) = self.address_extractor {
+            for m in &mut results {
+                let function = tree
+                    .as_ref()
+                    .and_then(|tree| function_definition(tree, match_span(&m.result)))
+                    .map(|node| node.byte_range());
+                m.address = extractor.address(source, m.start_offset(), function);
+            }
+        }
+
+        if self.suppression != SuppressionMode::Off {
+            for m in &mut results {
+                m.suppression = suppression::inline(source, m.start_offset(), m.rule.id());
+            }
+            if self.suppression == SuppressionMode::Drop {
+                results.retain(|m| m.suppression.is_none());
+            }
+        }
+
+        if let Some((before, after)) = snippet {
+            results = results
+                .into_iter()
+                .map(|m| m.into_snippet_of(source, before, after))
+                .collect();
+        }
+
+        unique::retain(&mut results, &mut self.seen);
+
+        if let Some(ref ranges) = context.ranges {
+            results.retain(|m| self.range_mode.contains(ranges, m));
+        }
+
+        for m in results.iter().filter(|m| !m.is_suppressed()) {
+            self.metrics.record_match(m.severity);
+
+            if let Some(tracker) = &mut self.coverage {
+                tracker.record_match(m.rule_id, m.checker_id, m.origin());
+            }
+        }
+
+        trace::event!(DEBUG, "matched", matches = results.len());
+
+        Ok(results)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{
+        DecodePolicy, PrefilterMode, RuleMatcher, RuleMatcherError, SourceContext, SuppressionMode,
+        SuppressionReason,
+    };
+    use crate::preprocess::addresses::AddressExtractor;
+    use crate::preprocess::decompiler::{Dialect, Normalizer};
+    use crate::preprocess::macros::MacroExpander;
+    use crate::reporting::RuleMatchReport;
+    use crate::rule::{Language, Severity};
+    use crate::testing;
+    use rustc_hash::FxHashSet;
+    use std::fs;
+
+    #[test]
+    fn test_strcpy() -> Result<(), Box<dyn std::error::Error>> {
+        let decompiler_output = r#"
+char *__fastcall sub_XYZ(char *s, size_t a2)
+{
+  char *v2; // rbx
+  char *v3; // rax
+  const char *v4; // r15
+  char *v5; // rax
+  const char *v6; // r15
+
+  v2 = s;
+  v3 = j__secure_getenv("ZZZ");
+  if ( !v3 || (v4 = v3, !*v3) )
+  {
+    v5 = j__secure_getenv("HOME");
+    if ( v5 )
+    {
+      v6 = v5;
+      if ( *v5 )
+      {
+        if ( strlen(v5) + 6 < a2 )
+        {
+          strcpy(s, v6);
+          *(_WORD *)&s[strlen(s)] = 47;
+          strcat(s, ".rnd");
+          return v2;
+        }
+      }
+    }
+    return 0LL;
+  }
+  if ( strlen(v3) + 1 >= a2 )
+    return 0LL;
+  strcpy(s, v4);
+  return v2;
+}
+"#;
+
+        let rule = r#"
+id: call-to-unbounded-copy-functions
+description: call to unbounded copy functions
+severity: medium
+tags:
+- CWE-120
 - CWE-242
 - CWE-676
 check-patterns:
@@ -249,7 +1663,7 @@ check-patterns:
 
         let mut matcher = RuleMatcher::from_str(rule)?;
 
-        let matches = matcher.matches_with(decompiler_output, false)?;
+        let matches = matcher.matches_lang(decompiler_output, Language::C)?;
 
         println!("{matches:#?}");
 
@@ -273,7 +1687,7 @@ check pattern:
         let mut matcher = RuleMatcher::from_str(rule1)?;
         let input = fs::read_to_string("tests/ls-main.c")?;
 
-        let matches = matcher.matches_with(&input, false)?;
+        let matches = matcher.matches_lang(&input, Language::C)?;
 
         assert_eq!(matches.len(), 1);
 
@@ -292,7 +1706,7 @@ check pattern:
         let input = fs::read_to_string("tests/objdump-disas.c")?;
 
         let mut matcher = RuleMatcher::from_str(rule1)?;
-        let matches = matcher.matches_with(&input, false)?;
+        let matches = matcher.matches_lang(&input, Language::C)?;
 
         assert_eq!(matches.len(), 2);
 
@@ -302,4 +1716,789 @@ check pattern:
 
         Ok(())
     }
+
+    #[test]
+    fn test_matches_bytes_latin1() -> Result<(), Box<dyn std::error::Error>> {
+        let rule = r#"
+id: unbounded-copy
+check pattern:
+  regex: func=^strcpy$
+  pattern: |
+    { $func($dst, _); }
+"#;
+
+        let input = fs::read("tests/latin1-comments.c")?;
+        assert!(std::str::from_utf8(&input).is_err());
+
+        let mut matcher = RuleMatcher::from_str(rule)?;
+
+        assert!(matches!(
+            matcher.matches_bytes(&input, Language::C, DecodePolicy::Strict),
+            Err(RuleMatcherError::Utf8(_))
+        ));
+
+        let lossy = matcher.matches_bytes(&input, Language::C, DecodePolicy::Lossy)?;
+        let latin1 = matcher.matches_bytes(&input, Language::C, DecodePolicy::Latin1)?;
+
+        assert_eq!(lossy.len(), 1);
+        assert_eq!(latin1.len(), 1);
+
+        assert_eq!(lossy[0].conversion(), Some(DecodePolicy::Lossy));
+        assert_eq!(latin1[0].conversion(), Some(DecodePolicy::Latin1));
+
+        // offsets refer to the converted text, which differs between policies
+        assert_ne!(lossy[0].start_offset(), latin1[0].start_offset());
+
+        for m in lossy.iter().chain(latin1.iter()) {
+            assert_eq!(m.matched_text(), "strcpy(buf");
+            assert_eq!(
+                &m.source_ref()[m.start_offset()..m.end_offset()],
+                m.matched_text()
+            );
+            assert_eq!(m.start_line(), 5);
+            assert!(m.display(1, 1, true).contains("strcpy(buf, name)"));
+        }
+
+        let ascii = matcher.matches_bytes(
+            b"void f(char *s) { strcpy(s, \"x\"); }",
+            Language::C,
+            DecodePolicy::Latin1,
+        )?;
+
+        assert_eq!(ascii.len(), 1);
+        assert_eq!(ascii[0].conversion(), None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_set_rule_enabled() -> Result<(), Box<dyn std::error::Error>> {
+        let pack = testing::pack()?;
+
+        let mut matcher = RuleMatcher::from_directory_with(pack.path(), false)?;
+        let source = fs::read_to_string("tests/utf8-comments.c")?;
+
+        assert_eq!(matcher.matches(&source)?.len(), 3);
+        assert_eq!(matcher.disabled_rules().count(), 0);
+
+        assert!(matcher.set_rule_enabled("unbounded-copy", false));
+
+        let matches = matcher.matches(&source)?;
+
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].rule().id(), "local-buffer");
+
+        let disabled = matcher.disabled_rules().collect::<Vec<_>>();
+
+        assert_eq!(disabled.len(), 1);
+        assert_eq!(disabled[0].1.id(), "unbounded-copy");
+
+        let key = String::from(disabled[0].0);
+
+        assert!(matcher.set_rule_enabled(&key, true));
+        assert_eq!(matcher.matches(&source)?.len(), 3);
+
+        assert!(!matcher.set_rule_enabled("no-such-rule", false));
+        assert_eq!(matcher.disabled_rules().count(), 0);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_min_severity() -> Result<(), Box<dyn std::error::Error>> {
+        let pack = testing::pack()?;
+
+        let mut matcher = RuleMatcher::from_directory_with(pack.path(), false)?;
+        let source = fs::read_to_string("tests/utf8-comments.c")?;
+
+        let viable = |matcher: &RuleMatcher| {
+            matcher
+                .viable_checkers(&matcher.rules, &source, &SourceContext::default())
+                .into_iter()
+                .map(|(_, rule, _, _)| rule.id().to_owned())
+                .collect::<FxHashSet<_>>()
+        };
+
+        assert!(viable(&matcher).contains("local-buffer"));
+
+        matcher.set_min_severity(Severity::High);
+
+        assert_eq!(matcher.min_severity(), Some(Severity::High));
+        assert!(!viable(&matcher).contains("local-buffer"));
+        assert!(viable(&matcher).contains("unbounded-copy"));
+
+        let matches = matcher.matches(&source)?;
+
+        assert_eq!(matches.len(), 2);
+        assert!(matches
+            .iter()
+            .all(|m| m.rule().severity() >= Severity::High));
+
+        matcher.clear_min_severity();
+
+        assert_eq!(matcher.min_severity(), None);
+        assert_eq!(matcher.matches(&source)?.len(), 3);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_language() -> Result<(), Box<dyn std::error::Error>> {
+        let rule = r#"
+id: delete-this
+check pattern:
+  language: c++
+  pattern: '{ delete this; }'
+"#;
+
+        let source = r#"
+void Foo::release() {
+    delete this;
+}
+"#;
+
+        assert_eq!(Language::from_path("a/b.cpp"), Some(Language::Cplusplus));
+        assert_eq!(Language::from_path("a/b.H"), Some(Language::C));
+        assert_eq!(Language::from_path("a/b.txt"), None);
+
+        assert_eq!(Language::detect(source), Language::Cplusplus);
+        assert_eq!(Language::detect("int main() { return 0; }"), Language::C);
+
+        // C++ syntax, rather than C++ keywords used as (parts of) identifiers,
+        // or within comments and string literals
+        for source in [
+            "class Foo : public Bar {};",
+            "class Foo\n{\n};",
+            "bool operator==(const Foo &other) const;",
+            "void *operator new(size_t size);",
+            "namespace {}",
+            "template<typename T> T max(T a, T b);",
+            "char *p = nullptr;",
+        ] {
+            assert_eq!(Language::detect(source), Language::Cplusplus, "{source}");
+        }
+        for source in [
+            "int operator_id = operator;",
+            "struct class_info *class;",
+            "// class Foo { };\nint main() { return 0; }",
+            "/* Foo::bar */ int x;",
+            "puts(\"std::string\");",
+            "char c = ':';\nint public;",
+        ] {
+            assert_eq!(Language::detect(source), Language::C, "{source}");
+        }
+
+        let mut matcher = RuleMatcher::from_str(rule)?;
+        let matches = matcher.matches_detect(source)?;
+
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].language(), Language::Cplusplus);
+        assert!(format!("{:?}", matches[0]).contains("language: Cplusplus"));
+
+        let matches = matcher.matches_file("tests/utf8-comments.c")?;
+        assert!(matches.iter().all(|m| m.language() == Language::C));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_language_mismatch() -> Result<(), Box<dyn std::error::Error>> {
+        let rule = r#"
+id: release
+check-patterns:
+- name: delete-this
+  language: c++
+  pattern: '{ delete this; }'
+- name: free
+  pattern: '{ free($p); }'
+"#;
+
+        let source = "void release(struct foo *p) {\n    delete this;\n    free(p);\n}\n";
+
+        // the C++ check is skipped for a source parsed as C, and reported
+        let mut matcher = RuleMatcher::from_str(rule)?;
+        let matches = matcher.matches_lang(source, Language::C)?;
+        assert!(matches.iter().all(|m| m.checker().name() == "free"));
+        assert_eq!(matches.len(), 1);
+
+        let metrics = matcher.last_metrics();
+        let mismatches = metrics.language_mismatches();
+        assert_eq!(mismatches.len(), 1);
+        assert_eq!(
+            (
+                mismatches[0].check.as_str(),
+                mismatches[0].check_language,
+                mismatches[0].source_language
+            ),
+            ("delete-this", Language::Cplusplus, Language::C)
+        );
+        assert!(metrics
+            .to_string()
+            .contains("1 checks skipped for language"));
+
+        // checks written for C run on C++ sources
+        matcher.matches_lang(source, Language::Cplusplus)?;
+        assert!(matcher.last_metrics().language_mismatches().is_empty());
+        assert_eq!(matcher.last_metrics().checks_evaluated(), 2);
+
+        // unless mismatched checks are run anyway
+        matcher.set_run_mismatched_languages(true);
+        matcher.matches_lang(source, Language::C)?;
+        assert!(matcher.last_metrics().language_mismatches().is_empty());
+        assert_eq!(matcher.last_metrics().checks_evaluated(), 2);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_path_scoped_rules() -> Result<(), Box<dyn std::error::Error>> {
+        let rule = r#"
+id: unbounded-copy
+paths:
+  include:
+  - tests/*.c
+  exclude:
+  - '*-comments.c'
+check pattern:
+  regex: func=^strcpy$
+  pattern: '{ $func(_, _); }'
+"#;
+
+        let mut matcher = RuleMatcher::from_str(rule)?;
+
+        assert_eq!(matcher.matches_file("tests/ls-main.c")?.len(), 1);
+        assert!(matcher.matches_file("tests/utf8-comments.c")?.is_empty());
+
+        // in-memory sources have no origin, and so are never excluded
+        let source = fs::read_to_string("tests/utf8-comments.c")?;
+        assert_eq!(matcher.matches(source)?.len(), 2);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_normalization() -> Result<(), Box<dyn std::error::Error>> {
+        let rule = r#"
+id: small-stack-buffer-copy
+check pattern:
+  regex: func=^mem(cpy|move)$
+  pattern: '{ unsigned char $buf[_]; $func($buf, _, _); }'
+"#;
+
+        let mut matcher = RuleMatcher::from_str(rule)?;
+
+        assert!(matcher.matches_file("tests/ghidra-output.c")?.is_empty());
+
+        matcher.set_normalizer(Some(Normalizer::new(Dialect::Ghidra)));
+
+        let matches = matcher.matches_file("tests/ghidra-output.c")?;
+
+        assert_eq!(matches.len(), 1);
+        assert_eq!(
+            matches[0].matched_text().lines().next(),
+            Some("unsigned char local_28 [16];")
+        );
+        assert!(matches[0]
+            .source_ref()
+            .contains("unsigned char local_28 [16];"));
+        assert!(matches[0]
+            .display(0, 0, false)
+            .contains("memcpy(local_28,param_1,(unsigned long)param_2);"));
+
+        let rule = r#"
+id: unchecked-copy
+check pattern:
+  regex: func=^strcpy$
+  pattern: 'int $fn(_, _) { $func(_, _); }'
+"#;
+
+        let mut matcher = RuleMatcher::from_str(rule)?;
+
+        assert!(matcher.matches_file("tests/hexrays-usercall.c")?.is_empty());
+
+        matcher.set_normalizer(Some(Normalizer::new(Dialect::HexRays)));
+
+        assert_eq!(matcher.matches_file("tests/hexrays-usercall.c")?.len(), 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_original_offsets() -> Result<(), Box<dyn std::error::Error>> {
+        let rule = r#"
+id: unchecked-copy
+check pattern:
+  regex: func=^strcpy$
+  pattern: '{ $func($dst, _); }'
+"#;
+
+        let source = fs::read_to_string("tests/hexrays-usercall.c")?;
+
+        let mut matcher = RuleMatcher::from_str(rule)?;
+
+        let m = &matcher.matches_lang("void f(char *s) { strcpy(s, s); }", Language::C)?[0];
+
+        assert!(m.offset_map().is_none());
+        assert_eq!(m.start_offset_original(), m.start_offset());
+        assert_eq!(m.display_original(0, 0, false), m.display(0, 0, false));
+
+        matcher.set_normalizer(Some(Normalizer::new(Dialect::HexRays)));
+        let matches = matcher.matches_lang(&source, Language::C)?;
+
+        assert_eq!(matches.len(), 1);
+
+        let m = &matches[0];
+        let start = source.find("strcpy(dst, src)").unwrap();
+        let end = start + "strcpy(dst".len();
+
+        assert!(m.offset_map().is_some());
+        assert_eq!(m.original_source(), source);
+        assert_ne!(m.start_offset(), start);
+
+        assert_eq!(m.start_offset_original(), start);
+        assert_eq!(m.end_offset_original(), end);
+        assert_eq!(m.start_line_original(), 10);
+        assert_eq!(m.start_column_original(), 5);
+        assert_eq!(m.end_line_original(), 10);
+        assert_eq!(m.end_column_original(), 15);
+        assert_eq!(&source[start..end], m.matched_text());
+
+        assert!(m.display_original(10, 0, false).contains("@<edi>"));
+        assert!(!m.display(10, 0, false).contains("@<edi>"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_addresses() -> Result<(), Box<dyn std::error::Error>> {
+        let rule = "id: copy\ncheck-pattern:\n  pattern: '{ strcpy($dst, _); }'\n";
+
+        let mut matcher = RuleMatcher::from_str(rule)?;
+        matcher.set_normalizer(Some(Normalizer::new(Dialect::HexRays)));
+
+        assert_eq!(
+            matcher.matches_file("tests/hexrays-dump.c")?[0].address(),
+            None
+        );
+
+        // from the header of the enclosing function
+        matcher.set_address_extractor(Some(AddressExtractor::new(Dialect::HexRays)));
+
+        let matches = matcher.matches_file("tests/hexrays-dump.c")?;
+        assert_eq!(matches[0].address(), Some(0x401040));
+
+        let report = serde_json::to_value(RuleMatchReport::new(&matches[0]))?;
+        assert_eq!(report["address"], 0x401040);
+
+        // from a comment on a preceding line
+        let source = "\
+void f(char *d, char *s)
+{
+  init(d); // 0x401234
+  strcpy(d, s);
+}
+";
+        assert_eq!(matcher.matches(source)?[0].address(), Some(0x401234));
+
+        // from the definition of a Ghidra function
+        let rule = "id: copy\ncheck-pattern:\n  pattern: '{ memcpy($dst, _, _); }'\n";
+
+        let mut matcher = RuleMatcher::from_str(rule)?;
+        matcher.set_normalizer(Some(Normalizer::new(Dialect::Ghidra)));
+        matcher.set_address_extractor(Some(AddressExtractor::new(Dialect::Ghidra)));
+
+        let matches = matcher.matches_file("tests/ghidra-output.c")?;
+        assert_eq!(matches[0].address(), Some(0x101189));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_macro_expansion() -> Result<(), Box<dyn std::error::Error>> {
+        let rule = r#"
+id: stack-copy
+check pattern:
+  pattern: '{ char $buf[_]; strcpy($buf, _); }'
+"#;
+
+        let source = fs::read_to_string("tests/macro-copy.c")?;
+
+        let mut matcher = RuleMatcher::from_str(rule)?;
+        assert!(matcher.matches_lang(&source, Language::C)?.is_empty());
+
+        matcher.set_macro_expander(Some(MacroExpander::new()));
+        let matches = matcher.matches_lang(&source, Language::C)?;
+        assert_eq!(matches.len(), 1);
+
+        // displayed as expanded, at the lines of the source
+        let m = &matches[0];
+        assert!(m.display(0, 0, false).contains("char buf[16];"));
+        assert!(m.display(0, 0, false).contains("strcpy(buf, name)"));
+        assert_eq!(m.start_line(), 11);
+        assert_eq!(m.start_line_original(), 11);
+
+        // a use spanning lines is expanded on its first one
+        assert_eq!(m.end_line(), 13);
+        assert_eq!(m.end_line_original(), 14);
+
+        let start = source.find("char buf[BUF_SIZE]").unwrap();
+        assert_eq!(m.start_offset_original(), start);
+        assert!(m.display_original(0, 0, false).contains("COPY(buf,"));
+
+        // only object-like macros
+        matcher.set_macro_expander(Some(MacroExpander::new().function_like(false)));
+        assert!(matcher.matches_lang(&source, Language::C)?.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_prefilter_code_only() -> Result<(), Box<dyn std::error::Error>> {
+        let rule = r#"
+id: unbounded-copy
+check patterns:
+- name: strcpy
+  pattern: '{ strcpy($dst, _); }'
+- name: gets
+  pattern: '{ gets(_); }'
+"#;
+
+        let mut matcher = RuleMatcher::from_str(rule)?;
+
+        let source = fs::read_to_string("tests/commented-copy.c")?;
+        let context = SourceContext::default();
+
+        // both checks mention identifiers only present in comments, and so the
+        // raw prefilter considers both viable, while neither can match
+        let raw = matcher.viable_checkers(&matcher.rules, &source, &context);
+        assert_eq!(raw.len(), 2);
+        assert!(matcher.matches(&source)?.is_empty());
+
+        matcher.set_prefilter_mode(PrefilterMode::CodeOnly);
+
+        let code_only = matcher.viable_checkers(&matcher.rules, &source, &context);
+        assert!(code_only.is_empty());
+
+        // structural matching is unaffected
+        let source = fs::read_to_string("tests/utf8-comments.c")?;
+        assert_eq!(matcher.matches(&source)?.len(), 2);
+
+        matcher.set_prefilter_mode(PrefilterMode::Raw);
+        assert_eq!(matcher.matches(&source)?.len(), 2);
+
+        matcher.set_prefilter_mode(PrefilterMode::CodeOnly);
+
+        // sources that cannot be tokenized fall back to the raw prefilter
+        let unterminated = "void f(char *s) { puts(s); /* strcpy(s, s); gets(s);";
+        assert_eq!(
+            matcher
+                .viable_checkers(&matcher.rules, unterminated, &context)
+                .len(),
+            2
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_suppression_mode() -> Result<(), Box<dyn std::error::Error>> {
+        let rule = r#"
+id: unbounded-copy
+check pattern:
+  pattern: '{ strcpy($dst, _); }'
+"#;
+
+        let mut matcher = RuleMatcher::from_str(rule)?;
+
+        assert_eq!(matcher.suppression_mode(), SuppressionMode::Off);
+
+        // by default, suppression comments are ignored
+        let matches = matcher.matches_file("tests/suppressed-copy.c")?;
+
+        assert_eq!(matches.len(), 3);
+        assert!(matches.iter().all(|m| !m.is_suppressed()));
+
+        matcher.set_suppression_mode(SuppressionMode::Drop);
+        let matches = matcher.matches_file("tests/suppressed-copy.c")?;
+
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].start_line(), 11);
+
+        matcher.set_suppression_mode(SuppressionMode::Retain);
+        let matches = matcher.matches_file("tests/suppressed-copy.c")?;
+
+        assert_eq!(matches.len(), 3);
+        assert_eq!(
+            matches
+                .iter()
+                .filter_map(|m| match m.suppression()? {
+                    SuppressionReason::Comment { line, .. } => Some(*line),
+                    _ => None,
+                })
+                .collect::<Vec<_>>(),
+            [2, 6]
+        );
+
+        Ok(())
+    }
+
+    #[cfg(feature = "std-fs")]
+    #[test]
+    fn test_max_file_size() -> Result<(), Box<dyn std::error::Error>> {
+        use std::fs;
+
+        let dir =
+            std::env::temp_dir().join(format!("weggli-ruleset-file-size-{}", std::process::id()));
+        let rules = dir.join("rules");
+        fs::create_dir_all(&rules)?;
+        fs::write(
+            rules.join("small.yml"),
+            "id: small\nmax-file-size: 16k\ncheck-pattern:\n  pattern: '{ strcpy($d, $s); }'\n",
+        )?;
+        fs::write(
+            rules.join("any.yml"),
+            "id: any\ncheck-pattern:\n  pattern: '{ strcpy($d, $s); }'\n",
+        )?;
+
+        // an amalgamation-like source of about 64KiB
+        let mut source = String::from("void f(char *d, char *s) {\n    strcpy(d, s);\n}\n");
+        while source.len() < 64 * 1024 {
+            source.push_str("static int unused_padding_variable_with_a_long_name = 0;\n");
+        }
+        let large = dir.join("large.c");
+        fs::write(&large, &source)?;
+
+        let mut matcher = RuleMatcher::from_directory(&rules)?;
+        matcher.set_coverage_tracking(true);
+
+        // the limited rule is skipped, the other one still scans the source
+        let matches = matcher.matches_file(&large)?;
+        assert_eq!(
+            matches.iter().map(|m| m.rule().id()).collect::<Vec<_>>(),
+            ["any"]
+        );
+        assert_eq!(matcher.last_metrics().rules_too_large(), 1);
+
+        let coverage = matcher.coverage();
+        let small = coverage
+            .checks()
+            .iter()
+            .find(|c| c.rule() == "small")
+            .ok_or("no coverage")?;
+        assert_eq!((small.too_large(), small.considered()), (1, 0));
+
+        // small sources are scanned by both
+        assert_eq!(matcher.matches(&source[..64])?.len(), 2);
+        assert_eq!(matcher.last_metrics().rules_too_large(), 0);
+
+        // the matcher's default applies to rules without a limit of their own
+        matcher.set_max_file_size(Some(1024));
+        assert!(matcher.matches_file(&large)?.is_empty());
+        assert_eq!(matcher.last_metrics().rules_too_large(), 2);
+
+        fs::remove_dir_all(&dir)?;
+
+        Ok(())
+    }
+
+    #[cfg(feature = "std-fs")]
+    #[test]
+    fn test_match_guards() -> Result<(), Box<dyn std::error::Error>> {
+        use std::path::Path;
+        use std::time::Duration;
+
+        let rule = "id: copy\ncheck-pattern:\n  pattern: '{ strcpy($dst, _); }'\n";
+        let files = ["tests/utf8-comments.c", "tests/edge-copy.c"];
+
+        // with a budget no run can meet, each run is reported...
+        let mut matcher = RuleMatcher::from_str(rule)?;
+        let all = matcher.matches_files(files)?;
+        matcher.set_check_budget(Some(Duration::ZERO));
+        assert_eq!(matcher.matches_files(files)?.len(), all.len());
+
+        let metrics = matcher.last_metrics();
+        assert_eq!(metrics.over_budget().len(), 2);
+        assert_eq!(metrics.over_budget()[1].check, "default");
+        assert_eq!(
+            metrics.over_budget()[1].origin.as_deref(),
+            Some(Path::new(files[1]))
+        );
+        assert_eq!(metrics.disabled_checks().count(), 0);
+
+        // ...and with the circuit breaker, the check is disabled after the
+        // first file, for the current scan only
+        matcher.set_circuit_breaker(true);
+        let matches = matcher.matches_files(files)?;
+        assert!(!matches.is_empty());
+        assert!(matches
+            .iter()
+            .all(|m| m.origin() == Some(Path::new(files[0]))));
+        assert_eq!(
+            matcher.last_metrics().disabled_checks().collect::<Vec<_>>(),
+            [("default", "default")]
+        );
+        assert!(matcher
+            .last_metrics()
+            .to_string()
+            .contains("1 checks over budget"));
+        assert_eq!(matcher.matches_files(files)?.len(), matches.len());
+
+        // an expired timeout skips the checks of a source
+        let source = fs::read_to_string(files[0])?;
+        let mut matcher = RuleMatcher::from_str(rule)?;
+        matcher.set_source_timeout(Some(Duration::ZERO));
+        assert!(matcher.matches(&source)?.is_empty());
+        assert_eq!(matcher.last_metrics().sources_timed_out(), 1);
+        matcher.set_source_timeout(Some(Duration::from_secs(60)));
+        assert!(!matcher.matches(&source)?.is_empty());
+        assert_eq!(matcher.last_metrics().sources_timed_out(), 0);
+
+        // a check is run on sources up to its `max-source-bytes`
+        for (max, expected) in [(source.len(), false), (source.len() - 1, true)] {
+            let rule = format!("{rule}  max-source-bytes: {max}\n");
+            let mut matcher = RuleMatcher::from_str(&rule)?;
+            assert_eq!(matcher.matches(&source)?.is_empty(), expected);
+            assert_eq!(matcher.last_metrics().checks_oversized(), expected as usize);
+
+            let yaml = serde_yaml::to_string(&*matcher.rules().get(0).unwrap())?;
+            assert!(yaml.contains(&format!("max-source-bytes: {max}")));
+        }
+
+        // the condition of a rule is not evaluated without all of its checks
+        let rule = r#"
+id: unchecked-alloc
+check-patterns:
+- name: alloc
+  pattern: '{ $p = malloc(_); }'
+- name: guarded
+  pattern: '{ if (!$p) return; }'
+  max-source-bytes: 8
+condition: alloc and not guarded
+"#;
+        let source = "void g(void) {\n    int *p;\n    p = malloc(4);\n}\n";
+        let mut matcher = RuleMatcher::from_str(rule)?;
+        assert!(matcher.matches(source)?.is_empty());
+        assert_eq!(matcher.last_metrics().checks_oversized(), 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_run_check() -> Result<(), Box<dyn std::error::Error>> {
+        // a single check finds what a full scan reports for it
+        fn differential(
+            matcher: &mut RuleMatcher,
+            source: &str,
+        ) -> Result<usize, Box<dyn std::error::Error>> {
+            let all = matcher.matches(source)?;
+            let checks = matcher
+                .rules()
+                .iter()
+                .flat_map(|(key, rule)| {
+                    rule.checks()
+                        .iter()
+                        .map(move |c| (key.to_owned(), c.name().to_owned()))
+                })
+                .collect::<Vec<_>>();
+
+            for (key, check) in &checks {
+                let expected = all
+                    .iter()
+                    .filter(|m| m.rule_key() == key && m.checker().name() == check)
+                    .map(|m| (m.start_offset(), m.end_offset(), m.fingerprint().to_owned()))
+                    .collect::<Vec<_>>();
+                let found = matcher
+                    .run_check(key, check, source, Language::C)?
+                    .iter()
+                    .map(|m| (m.start_offset(), m.end_offset(), m.fingerprint().to_owned()))
+                    .collect::<Vec<_>>();
+                assert_eq!(found, expected, "{key}: {check}");
+            }
+
+            Ok(all.len())
+        }
+
+        let rule = r#"
+id: unbounded-copy
+check-patterns:
+- name: strcpy
+  regex: func=^st(r|p)cpy$
+  pattern: |
+    { $func($dst, _); }
+- name: gets
+  regex: func=^gets$
+  pattern: |
+    { $func(_); }
+"#;
+
+        let source = fs::read_to_string("tests/edge-copy.c")?;
+        let mut matcher = RuleMatcher::from_str(rule)?;
+        assert!(differential(&mut matcher, &source)? > 0);
+
+        // the checks of a rule with a condition depend on each other
+        let rule = r#"
+id: unchecked-alloc
+check-patterns:
+- name: alloc
+  pattern: '{ $p = malloc(_); }'
+- name: guarded
+  pattern: '{ $p = malloc(_); if (!$p) return; }'
+condition: alloc and not guarded
+"#;
+        let unchecked = "\nvoid f(void) {\n    int *p;\n    p = malloc(4);\n}\n";
+        let checked = "void g(void) {\n    int *q;\n    q = malloc(4);\n    if (!q) return;\n}\n";
+
+        let mut matcher = RuleMatcher::from_str(rule)?;
+        assert_eq!(differential(&mut matcher, unchecked)?, 1);
+        assert_eq!(differential(&mut matcher, checked)?, 0);
+
+        let found = matcher.run_check("unchecked-alloc", "alloc", unchecked, Language::C)?;
+        assert_eq!(found[0].start_line(), 4);
+        assert!(matcher
+            .run_check("unchecked-alloc", "alloc", checked, Language::C)?
+            .is_empty());
+
+        // the prefilter is bypassed
+        let rule = "id: copy\ncheck-pattern:\n  name: copy\n  pattern: '{ strcpy(_, _); }'\n";
+        let mut matcher = RuleMatcher::from_str(rule)?;
+        matcher.set_coverage_tracking(true);
+        assert!(!matcher.rules().get_ref(0).unwrap().checks()[0].can_match("void f(void) {}"));
+        assert!(matcher
+            .run_check("copy", "copy", "void f(void) {}", Language::C)?
+            .is_empty());
+        assert_eq!(matcher.last_metrics().parsed(), 1);
+
+        // ...and neither its outcome nor the run are recorded
+        assert!(matcher
+            .prefilter_stats()
+            .checks()
+            .iter()
+            .all(|c| (c.evaluated(), c.passed()) == (0, 0)));
+        assert_eq!(
+            matcher
+                .run_check(
+                    "copy",
+                    "copy",
+                    "void f(char *s) { strcpy(s, s); }",
+                    Language::C
+                )?
+                .len(),
+            1
+        );
+        assert_eq!(matcher.coverage().sources(), 0);
+        assert!(matcher.coverage().checks().iter().all(|c| c.matched() == 0));
+        assert!(matcher.is_tracking_coverage());
+
+        // rules and checks are named
+        assert!(matches!(
+            matcher.run_check("missing", "copy", "", Language::C),
+            Err(RuleMatcherError::UnknownRule(rule)) if rule == "missing"
+        ));
+        assert!(matches!(
+            matcher.run_check("copy", "missing", "", Language::C),
+            Err(RuleMatcherError::UnknownCheck { check, .. }) if check == "missing"
+        ));
+
+        Ok(())
+    }
 }
@@ -0,0 +1,571 @@
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Instant;
+
+use walkdir::{DirEntry, FilterEntry, WalkDir};
+
+use crate::matcher::progress::{NoProgress, ScanEvent, ScanProgress, SkipReason};
+#[cfg(feature = "gitignore")]
+use crate::matcher::walk::GitIgnores;
+use crate::matcher::walk::IgnorePatterns;
+use crate::matcher::{RuleMatch, RuleMatcher, RuleMatcherError, SourceContext, WalkOptions};
+use crate::rule::Language;
+
+impl RuleMatcher {
+    pub fn matches_files<P>(
+        &mut self,
+        paths: impl IntoIterator<Item = P>,
+    ) -> Result<Vec<RuleMatch>, RuleMatcherError>
+    where
+        P: AsRef<Path>,
+    {
+        self.matches_files_with_progress(paths, &NoProgress)
+    }
+
+    // files that cannot be read, or are not valid UTF-8, are skipped
+    pub fn matches_files_with_progress<P>(
+        &mut self,
+        paths: impl IntoIterator<Item = P>,
+        progress: &impl ScanProgress,
+    ) -> Result<Vec<RuleMatch>, RuleMatcherError>
+    where
+        P: AsRef<Path>,
+    {
+        // NOTE: identical files (e.g., vendored copies) are parsed and matched
+        // once
+        self.interning(|matcher| {
+            matcher.scan(|matcher| {
+                let mut files = 0;
+                let mut results = Vec::new();
+
+                for path in paths {
+                    if matcher.scan_file(path.as_ref(), None, &mut results, progress)? {
+                        files += 1;
+                    }
+                }
+
+                progress.event(ScanEvent::Done {
+                    files,
+                    matches: results.len(),
+                });
+
+                Ok(results)
+            })
+        })
+    }
+
+    pub fn matches_directory(
+        &mut self,
+        root: impl AsRef<Path>,
+    ) -> Result<Vec<RuleMatch>, RuleMatcherError> {
+        self.matches_directory_with_progress(root, &NoProgress)
+    }
+
+    // scans all files with a C or C++ extension below `root`, in file name
+    // order
+    pub fn matches_directory_with_progress(
+        &mut self,
+        root: impl AsRef<Path>,
+        progress: &impl ScanProgress,
+    ) -> Result<Vec<RuleMatch>, RuleMatcherError> {
+        self.matches_directory_with(root, &WalkOptions::default(), progress)
+    }
+
+    // see `matches_directory_with_progress`; files and directories ignored by
+    // `options` are skipped without being read
+    pub fn matches_directory_with(
+        &mut self,
+        root: impl AsRef<Path>,
+        options: &WalkOptions,
+        progress: &impl ScanProgress,
+    ) -> Result<Vec<RuleMatch>, RuleMatcherError> {
+        let walk = DirectoryWalk::new(root.as_ref(), options)?;
+
+        // NOTE: identical files (e.g., vendored copies) are parsed and matched
+        // once
+        self.interning(|matcher| {
+            matcher.scan(|matcher| {
+                let mut files = 0;
+                let mut results = Vec::new();
+
+                for entry in walk {
+                    let path = match entry {
+                        WalkEntry::File(path) => path,
+                        WalkEntry::Skipped(path, reason) => {
+                            matcher.skip(&path, reason, progress);
+                            continue;
+                        }
+                    };
+
+                    if matcher.scan_file(&path, None, &mut results, progress)? {
+                        files += 1;
+                    }
+                }
+
+                progress.event(ScanEvent::Done {
+                    files,
+                    matches: results.len(),
+                });
+
+                Ok(results)
+            })
+        })
+    }
+
+    // the language is derived from the path, unless given
+    pub(crate) fn scan_file(
+        &mut self,
+        path: &Path,
+        language: Option<Language>,
+        results: &mut Vec<RuleMatch>,
+        progress: &impl ScanProgress,
+    ) -> Result<bool, RuleMatcherError> {
+        progress.event(ScanEvent::FileStarted(path));
+
+        let started = Instant::now();
+
+        let bytes = match self.read_file(path) {
+            Ok(bytes) => bytes,
+            Err(e) => {
+                self.skip(path, SkipReason::Unreadable(e.kind()), progress);
+                return Ok(false);
+            }
+        };
+
+        let Ok(source) = std::str::from_utf8(&bytes) else {
+            self.skip(path, SkipReason::InvalidUtf8, progress);
+            return Ok(false);
+        };
+
+        let language = language
+            .or_else(|| Language::from_path(path))
+            .unwrap_or_else(|| Language::detect(source));
+
+        let context = SourceContext {
+            language,
+            origin: Some(Arc::from(path)),
+            ..Default::default()
+        };
+
+        let matches = self.matches_source(source, context)?;
+
+        progress.event(ScanEvent::FileFinished {
+            path,
+            matches: matches.len(),
+            elapsed: started.elapsed(),
+        });
+
+        results.extend(matches);
+
+        Ok(true)
+    }
+
+    pub(crate) fn skip(&mut self, path: &Path, reason: SkipReason, progress: &impl ScanProgress) {
+        self.metrics.record_skip(reason);
+        progress.event(ScanEvent::Skipped { path, reason });
+    }
+}
+
+pub(crate) enum WalkEntry {
+    File(PathBuf),
+    Skipped(PathBuf, SkipReason),
+}
+
+// The files with a C or C++ extension below a root, in file name order, and
+// the entries skipped on the way; ignored directories are not walked.
+pub(crate) struct DirectoryWalk {
+    entries: FilterEntry<walkdir::IntoIter, fn(&DirEntry) -> bool>,
+    patterns: IgnorePatterns,
+    #[cfg(feature = "gitignore")]
+    git: Option<GitIgnores>,
+}
+
+impl DirectoryWalk {
+    pub(crate) fn new(root: &Path, options: &WalkOptions) -> Result<Self, RuleMatcherError> {
+        let patterns = IgnorePatterns::new(root, options)?;
+        let entries = WalkDir::new(root)
+            .sort_by_file_name()
+            .into_iter()
+            .filter_entry(is_source_or_dir as fn(&DirEntry) -> bool);
+
+        Ok(Self {
+            entries,
+            patterns,
+            #[cfg(feature = "gitignore")]
+            git: options
+                .respects_gitignore()
+                .then(|| GitIgnores::new(root))
+                .flatten(),
+        })
+    }
+
+    // by the patterns of the options, or by git
+    fn is_ignored(&mut self, dirent: &DirEntry, is_dir: bool) -> bool {
+        if self.patterns.is_ignored(dirent.path(), is_dir) {
+            return true;
+        }
+
+        #[cfg(feature = "gitignore")]
+        if let Some(git) = &mut self.git {
+            return git.is_ignored(dirent.path(), dirent.depth(), is_dir);
+        }
+
+        false
+    }
+}
+
+fn is_source_or_dir(e: &DirEntry) -> bool {
+    e.file_type().is_dir() || Language::from_path(e.path()).is_some()
+}
+
+impl Iterator for DirectoryWalk {
+    type Item = WalkEntry;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let dirent = match self.entries.next()? {
+                Ok(dirent) => dirent,
+                Err(e) => {
+                    let Some(path) = e.path() else {
+                        continue;
+                    };
+                    let reason = SkipReason::Unreadable(
+                        e.io_error()
+                            .map(|e| e.kind())
+                            .unwrap_or(std::io::ErrorKind::Other),
+                    );
+                    return Some(WalkEntry::Skipped(path.to_owned(), reason));
+                }
+            };
+
+            let is_dir = dirent.file_type().is_dir();
+
+            if self.is_ignored(&dirent, is_dir) {
+                if is_dir {
+                    self.entries.skip_current_dir();
+                }
+                return Some(WalkEntry::Skipped(dirent.into_path(), SkipReason::Ignored));
+            }
+
+            if is_dir {
+                #[cfg(feature = "gitignore")]
+                if let Some(git) = &mut self.git {
+                    git.enter(dirent.path(), dirent.depth());
+                }
+                continue;
+            }
+
+            return Some(WalkEntry::File(dirent.into_path()));
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+    #[cfg(feature = "gitignore")]
+    use std::path::Path;
+    use std::path::PathBuf;
+    use std::sync::Mutex;
+
+    use crate::matcher::progress::{ScanEvent, SkipReason};
+    use crate::matcher::{RuleMatcher, RuleMatcherError, WalkOptions};
+    use crate::rule::Severity;
+    use crate::testing;
+    #[cfg(feature = "gitignore")]
+    use crate::testing::TempDir;
+
+    #[test]
+    fn test_progress() -> Result<(), Box<dyn std::error::Error>> {
+        let rule = r#"
+id: unbounded-copy
+check pattern:
+  pattern: '{ strcpy($dst, _); }'
+"#;
+
+        let mut matcher = RuleMatcher::from_str(rule)?;
+
+        let events = Mutex::new(Vec::new());
+        let progress = |event: ScanEvent<'_>| {
+            let event = match event {
+                ScanEvent::FileStarted(path) => format!("started {}", path.display()),
+                ScanEvent::FileFinished { path, matches, .. } => {
+                    format!("finished {} {matches}", path.display())
+                }
+                ScanEvent::Skipped { path, reason } => {
+                    format!("skipped {} {reason:?}", path.display())
+                }
+                ScanEvent::Done { files, matches } => format!("done {files} {matches}"),
+            };
+            events.lock().unwrap().push(event);
+        };
+
+        let matches = matcher.matches_files_with_progress(
+            [
+                "tests/utf8-comments.c",
+                "tests/latin1-comments.c",
+                "tests/missing.c",
+            ],
+            &progress,
+        )?;
+
+        assert_eq!(matches.len(), 2);
+
+        let events = events.into_inner()?;
+
+        assert_eq!(
+            events,
+            [
+                "started tests/utf8-comments.c",
+                "finished tests/utf8-comments.c 2",
+                "started tests/latin1-comments.c",
+                "skipped tests/latin1-comments.c InvalidUtf8",
+                "started tests/missing.c",
+                "skipped tests/missing.c Unreadable(NotFound)",
+                "done 1 2",
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_directory() -> Result<(), Box<dyn std::error::Error>> {
+        let rule = r#"
+id: unbounded-copy
+check pattern:
+  pattern: '{ strcpy($dst, _); }'
+"#;
+
+        let mut matcher = RuleMatcher::from_str(rule)?;
+
+        let skipped = Mutex::new(Vec::new());
+        let progress = |event: ScanEvent<'_>| {
+            if let ScanEvent::Skipped { path, reason } = event {
+                skipped.lock().unwrap().push((path.to_owned(), reason));
+            }
+        };
+
+        let matches = matcher.matches_directory_with_progress("tests/walk", &progress)?;
+
+        assert!(matches.iter().all(|m| m.origin().is_some()));
+        assert!(matches
+            .iter()
+            .any(|m| m.origin() == Some(PathBuf::from("tests/walk/utf8-comments.c").as_path())));
+
+        assert_eq!(
+            skipped.into_inner()?,
+            [(
+                PathBuf::from("tests/walk/latin1-comments.c"),
+                SkipReason::InvalidUtf8
+            )]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_ignored() -> Result<(), Box<dyn std::error::Error>> {
+        let rule = r#"
+id: unbounded-copy
+check pattern:
+  pattern: '{ strcpy($dst, _); }'
+"#;
+
+        let mut matcher = RuleMatcher::from_str(rule)?;
+
+        let scan = |matcher: &mut RuleMatcher, options| {
+            let skipped = Mutex::new(Vec::new());
+            let progress = |event: ScanEvent<'_>| {
+                if let ScanEvent::Skipped { path, reason } = event {
+                    skipped.lock().unwrap().push((path.to_owned(), reason));
+                }
+            };
+
+            let mut origins = matcher
+                .matches_directory_with("tests/repo", &options, &progress)?
+                .iter()
+                .filter_map(|m| m.origin().map(|p| p.to_string_lossy().into_owned()))
+                .collect::<Vec<_>>();
+            origins.sort();
+
+            let skipped = skipped.into_inner().unwrap();
+            Ok::<_, RuleMatcherError>((origins, skipped))
+        };
+
+        // by default, nothing is ignored
+        let (origins, skipped) = scan(&mut matcher, WalkOptions::new())?;
+
+        assert_eq!(origins.len(), 4);
+        assert!(skipped.is_empty());
+
+        // ignored directories are skipped as a whole
+        let (origins, skipped) = scan(
+            &mut matcher,
+            WalkOptions::new().ignore("build/").ignore("*.gen.c"),
+        )?;
+
+        assert_eq!(origins, ["tests/repo/src/copy.c"]);
+        assert_eq!(
+            skipped,
+            [
+                (PathBuf::from("tests/repo/build"), SkipReason::Ignored),
+                (
+                    PathBuf::from("tests/repo/src/copy.gen.c"),
+                    SkipReason::Ignored
+                ),
+                (
+                    PathBuf::from("tests/repo/src/keep.gen.c"),
+                    SkipReason::Ignored
+                ),
+            ]
+        );
+        assert_eq!(matcher.last_metrics().skipped().ignored, 3);
+
+        assert!(matches!(
+            scan(&mut matcher, WalkOptions::new().ignore("[z-a]")),
+            Err(RuleMatcherError::IgnorePattern(..))
+        ));
+
+        Ok(())
+    }
+
+    // `tests/repo/.gitignore` ignores `build/` and `*.gen.c`, while
+    // `tests/repo/src/.gitignore` re-includes `keep.gen.c`
+    #[cfg(feature = "gitignore")]
+    #[test]
+    fn test_gitignore() -> Result<(), Box<dyn std::error::Error>> {
+        let rule = r#"
+id: unbounded-copy
+check pattern:
+  pattern: '{ strcpy($dst, _); }'
+"#;
+
+        let mut matcher = RuleMatcher::from_str(rule)?;
+
+        let scan = |matcher: &mut RuleMatcher, root: &Path, options| {
+            let skipped = Mutex::new(Vec::new());
+            let progress = |event: ScanEvent<'_>| {
+                if let ScanEvent::Skipped { path, reason } = event {
+                    skipped.lock().unwrap().push((path.to_owned(), reason));
+                }
+            };
+
+            let mut origins = matcher
+                .matches_directory_with(root, &options, &progress)?
+                .iter()
+                .filter_map(|m| m.origin().map(Path::to_owned))
+                .collect::<Vec<_>>();
+            origins.sort();
+
+            let skipped = skipped.into_inner().unwrap();
+            Ok::<_, RuleMatcherError>((origins, skipped))
+        };
+
+        // what git ignores is skipped, except what a deeper `.gitignore`
+        // re-includes
+        let (origins, skipped) = scan(
+            &mut matcher,
+            Path::new("tests/repo"),
+            WalkOptions::new().respect_gitignore(true),
+        )?;
+
+        assert_eq!(
+            origins,
+            [
+                Path::new("tests/repo/src/copy.c"),
+                Path::new("tests/repo/src/keep.gen.c")
+            ]
+        );
+        assert_eq!(
+            skipped,
+            [
+                (PathBuf::from("tests/repo/build"), SkipReason::Ignored),
+                (
+                    PathBuf::from("tests/repo/src/copy.gen.c"),
+                    SkipReason::Ignored
+                ),
+            ]
+        );
+        assert_eq!(matcher.last_metrics().skipped().ignored, 2);
+
+        // the options apply on top of git's ignore files
+        let (origins, _) = scan(
+            &mut matcher,
+            Path::new("tests/repo"),
+            WalkOptions::new()
+                .respect_gitignore(true)
+                .ignore("keep.gen.c"),
+        )?;
+
+        assert_eq!(origins, [Path::new("tests/repo/src/copy.c")]);
+        assert_eq!(matcher.last_metrics().skipped().ignored, 3);
+
+        // and so does the repository's `.git/info/exclude`
+        let copy = "void f(char *s) { strcpy(s, \"\"); }\n";
+        let dir = TempDir::with_files(&[
+            (".git/info/exclude", "*.gen.c\n"),
+            ("copy.c", copy),
+            ("copy.gen.c", copy),
+        ])?;
+
+        let (origins, skipped) = scan(
+            &mut matcher,
+            dir.path(),
+            WalkOptions::new().respect_gitignore(true),
+        )?;
+
+        assert_eq!(origins, [dir.path().join("copy.c")]);
+        assert_eq!(
+            skipped,
+            [(dir.path().join("copy.gen.c"), SkipReason::Ignored)]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_metrics() -> Result<(), Box<dyn std::error::Error>> {
+        let pack = testing::pack_with(&[("unbounded-copy.yml", testing::UNBOUNDED_COPY_GETS)])?;
+
+        let mut matcher = RuleMatcher::from_directory_with(pack.path(), false)?;
+
+        let matches = matcher.matches_files([
+            "tests/utf8-comments.c",
+            "tests/latin1-comments.c",
+            "tests/missing.c",
+        ])?;
+
+        let metrics = matcher.last_metrics().clone();
+
+        assert_eq!(metrics.files(), 1);
+        assert_eq!(metrics.skipped().invalid_utf8, 1);
+        assert_eq!(metrics.skipped().unreadable, 1);
+        assert_eq!(
+            metrics.bytes(),
+            fs::metadata("tests/utf8-comments.c")?.len() as usize
+        );
+        assert_eq!(metrics.checks_evaluated() + metrics.checks_prefiltered(), 3);
+        assert_eq!(metrics.matches(), matches.len());
+        assert_eq!(metrics.matches_by_severity()[&Severity::High], 2);
+        assert_eq!(metrics.matches_by_severity()[&Severity::Low], 1);
+
+        let line = metrics.to_string();
+
+        assert!(line.starts_with("1 files (2 skipped), "));
+        assert!(line.contains("3 matches (high: 2, low: 1)"));
+
+        let value = serde_yaml::to_value(&metrics)?;
+
+        assert_eq!(value["skipped"]["unreadable"].as_u64(), Some(1));
+        assert_eq!(value["matches"]["high"].as_u64(), Some(2));
+
+        matcher.matches("int main(void) { return 0; }")?;
+
+        assert_eq!(matcher.last_metrics().files(), 1);
+        assert_eq!(matcher.last_metrics().matches(), 0);
+        assert_eq!(matcher.last_metrics().skipped().total(), 0);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,270 @@
+use std::collections::VecDeque;
+use std::path::PathBuf;
+use std::time::Instant;
+
+use crate::matcher::batch::{DirectoryWalk, WalkEntry};
+use crate::matcher::progress::NoProgress;
+use crate::matcher::{
+    location_order, RuleMatch, RuleMatcher, RuleMatcherError, SourceContext, WalkOptions,
+};
+use crate::rule::Language;
+
+// What `matches_chunked` scans: a single source, files, or the files with a C
+// or C++ extension below a directory (as `matches_directory` does).
+#[derive(Debug, Clone)]
+pub enum ChunkInput<'a> {
+    Source(&'a str),
+    Files(Vec<PathBuf>),
+    Directory(PathBuf),
+}
+
+enum Pending<'a> {
+    Source(Option<&'a str>),
+    Files(std::vec::IntoIter<PathBuf>),
+    Directory(Box<DirectoryWalk>),
+}
+
+// The matches of a scan, in chunks of `chunk_size` matches (the last one may
+// be smaller): within a file, matches are in location order, and files are
+// in path order (for directories, as walked, i.e., by file name at each
+// level). Files are scanned as chunks are requested, and at most one file's
+// matches beyond the current chunk are held, so that the sources of files
+// are released once their chunks are dropped.
+//
+// NOTE: unreadable files and files that are not valid UTF-8 are skipped, and
+// the scan ends after an error
+pub struct MatchChunks<'m, 'a> {
+    matcher: &'m mut RuleMatcher,
+    pending: Pending<'a>,
+    language: Option<Language>,
+    chunk_size: usize,
+    buffer: VecDeque<RuleMatch>,
+    started: Instant,
+    done: bool,
+}
+
+impl RuleMatcher {
+    // see `MatchChunks`; without a language, that of each file is derived from
+    // its path (and that of a source detected), and chunks have at least one
+    // match
+    pub fn matches_chunked<'a>(
+        &mut self,
+        input: ChunkInput<'a>,
+        language: Option<Language>,
+        chunk_size: usize,
+    ) -> Result<MatchChunks<'_, 'a>, RuleMatcherError> {
+        let pending = match input {
+            ChunkInput::Source(source) => Pending::Source(Some(source)),
+            ChunkInput::Files(mut paths) => {
+                paths.sort();
+                Pending::Files(paths.into_iter())
+            }
+            ChunkInput::Directory(root) => Pending::Directory(Box::new(DirectoryWalk::new(
+                &root,
+                &WalkOptions::default(),
+            )?)),
+        };
+
+        // as `scan` does, for the whole iteration
+        self.metrics = Default::default();
+        self.prefilter_counts.clear();
+        self.tripped.clear();
+        self.seen.clear();
+
+        Ok(MatchChunks {
+            matcher: self,
+            pending,
+            language,
+            chunk_size: chunk_size.max(1),
+            buffer: VecDeque::new(),
+            started: Instant::now(),
+            done: false,
+        })
+    }
+}
+
+impl MatchChunks<'_, '_> {
+    // the matches held beyond those of the chunks returned so far
+    pub fn buffered(&self) -> usize {
+        self.buffer.len()
+    }
+
+    // the matches of the next source, or `None` when there are no more
+    fn scan_next(&mut self) -> Option<Result<Vec<RuleMatch>, RuleMatcherError>> {
+        let matcher = &mut *self.matcher;
+
+        let path = match &mut self.pending {
+            Pending::Source(source) => {
+                let source = source.take()?;
+                let context = SourceContext {
+                    language: self.language.unwrap_or_else(|| Language::detect(source)),
+                    ..Default::default()
+                };
+                return Some(matcher.matches_source(source, context));
+            }
+            Pending::Files(paths) => paths.next()?,
+            Pending::Directory(walk) => loop {
+                match walk.next()? {
+                    WalkEntry::File(path) => break path,
+                    WalkEntry::Skipped(path, reason) => matcher.skip(&path, reason, &NoProgress),
+                }
+            },
+        };
+
+        let mut results = Vec::new();
+        Some(
+            matcher
+                .scan_file(&path, self.language, &mut results, &NoProgress)
+                .map(|_| results),
+        )
+    }
+}
+
+impl Iterator for MatchChunks<'_, '_> {
+    type Item = Result<Vec<RuleMatch>, RuleMatcherError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while !self.done && self.buffer.len() < self.chunk_size {
+            match self.scan_next() {
+                Some(Ok(mut matches)) => {
+                    matches.sort_by(location_order);
+                    self.buffer.extend(matches);
+                }
+                Some(Err(e)) => {
+                    self.done = true;
+                    self.buffer.clear();
+                    return Some(Err(e));
+                }
+                None => {
+                    self.done = true;
+                    self.matcher.metrics.set_elapsed(self.started.elapsed());
+                }
+            }
+        }
+
+        if self.buffer.is_empty() {
+            return None;
+        }
+
+        let len = self.chunk_size.min(self.buffer.len());
+        let chunk = self.buffer.drain(..len).collect::<Vec<_>>();
+
+        // NOTE: the buffer may have grown by a large file
+        self.buffer.shrink_to(self.chunk_size);
+
+        Some(Ok(chunk))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::PathBuf;
+    use std::sync::{Arc, Weak};
+
+    use crate::matcher::{ChunkInput, RuleMatcher};
+
+    #[test]
+    fn test_matches_chunked() -> Result<(), Box<dyn std::error::Error>> {
+        let rule = r#"
+id: unbounded-copy
+check pattern:
+  regex: func=^strcpy$
+  pattern: |
+    { $func($dst, _); }
+"#;
+
+        let mut matcher = RuleMatcher::from_str(rule)?;
+        let all = matcher.matches_directory("tests")?;
+
+        // the largest number of matches in a file
+        let mut per_file = rustc_hash::FxHashMap::default();
+        for m in &all {
+            *per_file.entry(m.origin().unwrap().to_owned()).or_insert(0) += 1;
+        }
+        let largest = per_file.values().copied().max().unwrap();
+        assert!(per_file.len() > 2);
+
+        for chunk_size in [1, 2, 5, 1000] {
+            let mut chunks = matcher.matches_chunked(
+                ChunkInput::Directory(PathBuf::from("tests")),
+                None,
+                chunk_size,
+            )?;
+
+            let mut found = Vec::new();
+            let mut released = Vec::<(PathBuf, Weak<str>)>::new();
+
+            while let Some(chunk) = chunks.next() {
+                let chunk = chunk?;
+                assert!(!chunk.is_empty() && chunk.len() <= chunk_size);
+                assert!(chunk.len() + chunks.buffered() <= chunk_size + largest);
+
+                for m in &chunk {
+                    found.push((
+                        m.origin().unwrap().to_owned(),
+                        m.start_offset(),
+                        m.fingerprint().to_owned(),
+                    ));
+                    released.push((m.origin().unwrap().to_owned(), Arc::downgrade(&m.source())));
+                }
+                drop(chunk);
+
+                // the sources of the files the buffer no longer refers to are
+                // released
+                let current = found.last().map(|(path, ..)| path.clone());
+                assert!(released
+                    .iter()
+                    .filter(|(path, _)| Some(path) != current.as_ref())
+                    .all(|(_, source)| source.upgrade().is_none()));
+            }
+
+            // the matches of a full scan, in order
+            let mut expected = all
+                .iter()
+                .map(|m| {
+                    (
+                        m.origin().unwrap().to_owned(),
+                        m.start_offset(),
+                        m.fingerprint().to_owned(),
+                    )
+                })
+                .collect::<Vec<_>>();
+            expected.sort_by(|a, b| a.0.cmp(&b.0).then(a.1.cmp(&b.1)));
+
+            let mut sorted = found.clone();
+            sorted.sort_by(|a, b| a.0.cmp(&b.0).then(a.1.cmp(&b.1)));
+            assert_eq!(sorted, expected);
+
+            // within a file, in location order
+            assert!(found
+                .windows(2)
+                .all(|w| w[0].0 != w[1].0 || w[0].1 <= w[1].1));
+        }
+
+        // files, in path order, and a single source
+        let files = vec![
+            PathBuf::from("tests/utf8-comments.c"),
+            PathBuf::from("tests/edge-copy.c"),
+        ];
+        let expected = matcher.matches_files(&files)?.len();
+        let chunks = matcher
+            .matches_chunked(ChunkInput::Files(files), None, 2)?
+            .collect::<Result<Vec<_>, _>>()?;
+        let origins = chunks
+            .iter()
+            .flatten()
+            .map(|m| m.origin().unwrap().to_owned())
+            .collect::<Vec<_>>();
+        assert!(origins.is_sorted());
+        assert_eq!(origins.len(), expected);
+
+        let source = std::fs::read_to_string("tests/utf8-comments.c")?;
+        let chunks = matcher
+            .matches_chunked(ChunkInput::Source(&source), None, 2)?
+            .map(|chunk| chunk.map(|chunk| chunk.len()))
+            .collect::<Result<Vec<_>, _>>()?;
+        assert_eq!(chunks, [2, 1]);
+
+        Ok(())
+    }
+}
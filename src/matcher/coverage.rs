@@ -0,0 +1,257 @@
+use std::path::{Path, PathBuf};
+
+use rustc_hash::FxHashMap;
+use serde::Serialize;
+
+use crate::rule::RuleSet;
+
+// the number of distinct origins recorded per check
+pub const COVERAGE_EXAMPLES: usize = 3;
+
+#[derive(Debug, Clone, Default)]
+struct CheckCounts {
+    considered: usize,
+    too_large: usize,
+    viable: usize,
+    matched: usize,
+    examples: Vec<PathBuf>,
+}
+
+// Counters per check, accumulated over all scanning calls since tracking was
+// enabled (see `RuleMatcher::set_coverage_tracking`), keyed by rule index and
+// check index.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct CoverageTracker {
+    sources: usize,
+    checks: FxHashMap<(usize, usize), CheckCounts>,
+}
+
+impl CoverageTracker {
+    pub(crate) fn record_source(&mut self) {
+        self.sources += 1;
+    }
+
+    pub(crate) fn record_considered(&mut self, rule: usize, check: usize) {
+        self.checks.entry((rule, check)).or_default().considered += 1;
+    }
+
+    pub(crate) fn record_too_large(&mut self, rule: usize, check: usize) {
+        self.checks.entry((rule, check)).or_default().too_large += 1;
+    }
+
+    pub(crate) fn record_viable(&mut self, rule: usize, check: usize) {
+        self.checks.entry((rule, check)).or_default().viable += 1;
+    }
+
+    pub(crate) fn record_match(&mut self, rule: usize, check: usize, origin: Option<&Path>) {
+        let counts = self.checks.entry((rule, check)).or_default();
+        counts.matched += 1;
+
+        if let Some(origin) = origin {
+            if counts.examples.len() < COVERAGE_EXAMPLES
+                && !counts.examples.iter().any(|p| p == origin)
+            {
+                counts.examples.push(origin.to_owned());
+            }
+        }
+    }
+
+    pub(crate) fn report(&self, rules: &RuleSet) -> RuleCoverage {
+        let checks = rules
+            .iter()
+            .enumerate()
+            .flat_map(|(i, (key, rule))| {
+                rule.checks().iter().enumerate().map(move |(j, checker)| {
+                    let counts = self.checks.get(&(i, j)).cloned().unwrap_or_default();
+
+                    CheckCoverage {
+                        rule_key: key.to_owned(),
+                        rule: rule.id().to_owned(),
+                        check: checker.name().to_owned(),
+                        check_index: j,
+                        considered: counts.considered,
+                        too_large: counts.too_large,
+                        viable: counts.viable,
+                        matched: counts.matched,
+                        examples: counts.examples,
+                    }
+                })
+            })
+            .collect();
+
+        RuleCoverage {
+            sources: self.sources,
+            checks,
+        }
+    }
+}
+
+// How often a check of the rule set was run and matched (see
+// `RuleMatcher::coverage`).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CheckCoverage {
+    rule_key: String,
+    rule: String,
+    check: String,
+    check_index: usize,
+    considered: usize,
+    too_large: usize,
+    viable: usize,
+    matched: usize,
+    examples: Vec<PathBuf>,
+}
+
+impl CheckCoverage {
+    pub fn rule_key(&self) -> &str {
+        &self.rule_key
+    }
+
+    pub fn rule(&self) -> &str {
+        &self.rule
+    }
+
+    pub fn check(&self) -> &str {
+        &self.check
+    }
+
+    pub fn check_index(&self) -> usize {
+        self.check_index
+    }
+
+    // sources the rule was selected for (i.e., it was enabled, severe enough,
+    // and its paths applied)
+    pub fn considered(&self) -> usize {
+        self.considered
+    }
+
+    // sources the rule was skipped for, as they were larger than its
+    // `max-file-size` (which are not `considered`)
+    pub fn too_large(&self) -> usize {
+        self.too_large
+    }
+
+    // sources the check passed the prefilter for
+    pub fn viable(&self) -> usize {
+        self.viable
+    }
+
+    // findings, without those suppressed
+    pub fn matched(&self) -> usize {
+        self.matched
+    }
+
+    // the first `COVERAGE_EXAMPLES` distinct origins of findings
+    pub fn examples(&self) -> &[PathBuf] {
+        &self.examples
+    }
+}
+
+// The coverage of a rule set over the sources scanned while tracking, with an
+// entry for each check of each rule, in rule set order.
+//
+// NOTE: checks of rules that were never selected (e.g., disabled, below the
+// minimum severity, or restricted to other paths) are never viable either;
+// they are those that were never `considered`
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct RuleCoverage {
+    sources: usize,
+    checks: Vec<CheckCoverage>,
+}
+
+impl RuleCoverage {
+    // sources that were prefiltered, including those that failed to parse
+    pub fn sources(&self) -> usize {
+        self.sources
+    }
+
+    pub fn checks(&self) -> &[CheckCoverage] {
+        &self.checks
+    }
+
+    pub fn never_viable(&self) -> impl Iterator<Item = &CheckCoverage> {
+        self.checks.iter().filter(|c| c.viable == 0)
+    }
+
+    pub fn viable_but_never_matched(&self) -> impl Iterator<Item = &CheckCoverage> {
+        self.checks
+            .iter()
+            .filter(|c| c.viable > 0 && c.matched == 0)
+    }
+
+    // the keys of the rules none of whose checks matched
+    pub fn never_matched_rules(&self) -> Vec<&str> {
+        self.checks
+            .chunk_by(|a, b| a.rule_key == b.rule_key)
+            .filter(|checks| checks.iter().all(|c| c.matched == 0))
+            .map(|checks| checks[0].rule_key())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::COVERAGE_EXAMPLES;
+    use crate::matcher::RuleMatcher;
+    use crate::rule::Severity;
+    use crate::testing;
+
+    #[test]
+    fn test_coverage() -> Result<(), Box<dyn std::error::Error>> {
+        let pack = testing::pack_with(&[("unbounded-copy.yml", testing::UNBOUNDED_COPY_GETS)])?;
+
+        let mut matcher = RuleMatcher::from_directory_with(pack.path(), false)?;
+
+        matcher.matches_file("tests/repo/src/copy.c")?;
+        assert_eq!(matcher.coverage().sources(), 0);
+
+        // counters accumulate over the files of a scan, and over scans
+        matcher.set_coverage_tracking(true);
+        matcher.matches_directory("tests/repo")?;
+        matcher.matches("int main(void) { return 0; }")?;
+
+        let coverage = matcher.coverage();
+        assert_eq!(coverage.sources(), 5);
+        assert_eq!(coverage.checks().len(), 3);
+
+        let strcpy = &coverage.checks()[1];
+        assert_eq!(
+            (strcpy.rule(), strcpy.check()),
+            ("unbounded-copy", "strcpy")
+        );
+        assert_eq!((strcpy.considered(), strcpy.viable()), (5, 5));
+        assert_eq!(strcpy.matched(), 4);
+        assert_eq!(strcpy.examples().len(), COVERAGE_EXAMPLES);
+
+        // `char` does not occur in the last source
+        assert_eq!(coverage.checks()[0].viable(), 4);
+        assert_eq!(coverage.never_viable().count(), 0);
+        assert_eq!(
+            coverage
+                .viable_but_never_matched()
+                .map(|c| (c.rule(), c.check()))
+                .collect::<Vec<_>>(),
+            [("local-buffer", "default"), ("unbounded-copy", "gets")]
+        );
+        assert_eq!(coverage.never_matched_rules(), ["local-buffer.yml"]);
+
+        let value = serde_json::to_value(&coverage)?;
+        assert_eq!(value["checks"][1]["matched"], 4);
+
+        // rules that are not selected are never viable
+        matcher.reset_coverage();
+        matcher.set_min_severity(Severity::High);
+        matcher.matches_file("tests/repo/src/copy.c")?;
+
+        let coverage = matcher.coverage();
+        let never_viable = coverage.never_viable().collect::<Vec<_>>();
+        assert_eq!(never_viable.len(), 1);
+        assert_eq!(never_viable[0].rule(), "local-buffer");
+        assert_eq!(never_viable[0].considered(), 0);
+
+        matcher.set_coverage_tracking(false);
+        assert!(!matcher.is_tracking_coverage());
+        assert_eq!(matcher.coverage().checks()[1].matched(), 0);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,63 @@
+use std::borrow::Cow;
+use std::str::Utf8Error;
+
+// Policies for turning raw bytes into a source that can be parsed.
+//
+// NOTE: `Strict` never alters the input, and so offsets always refer to the
+// original bytes. `Lossy` replaces each invalid sequence with U+FFFD (three
+// bytes), and `Latin1` maps every byte >= 0x80 to a two-byte codepoint; in
+// both cases offsets refer to the converted text, which is the source stored
+// on each match, so that display remains consistent.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum DecodePolicy {
+    #[default]
+    Strict,
+    Lossy,
+    Latin1,
+}
+
+impl DecodePolicy {
+    // returns the decoded source, and whether it differs from the input
+    pub fn decode(self, bytes: &[u8]) -> Result<(Cow<'_, str>, bool), Utf8Error> {
+        match self {
+            Self::Strict => Ok((Cow::Borrowed(std::str::from_utf8(bytes)?), false)),
+            Self::Lossy => {
+                let source = String::from_utf8_lossy(bytes);
+                let converted = matches!(source, Cow::Owned(_));
+                Ok((source, converted))
+            }
+            Self::Latin1 => {
+                if bytes.is_ascii() {
+                    Ok((Cow::Borrowed(std::str::from_utf8(bytes)?), false))
+                } else {
+                    let source = bytes.iter().copied().map(char::from).collect();
+                    Ok((Cow::Owned(source), true))
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::DecodePolicy;
+
+    #[test]
+    fn test_decode() {
+        let input = b"/* \xa9 2021 */ int x;";
+
+        assert!(DecodePolicy::Strict.decode(input).is_err());
+
+        let (lossy, converted) = DecodePolicy::Lossy.decode(input).unwrap();
+        assert!(converted);
+        assert_eq!(lossy, "/* \u{fffd} 2021 */ int x;");
+
+        let (latin1, converted) = DecodePolicy::Latin1.decode(input).unwrap();
+        assert!(converted);
+        assert_eq!(latin1, "/* \u{a9} 2021 */ int x;");
+
+        let (ascii, converted) = DecodePolicy::Latin1.decode(b"int x;").unwrap();
+        assert!(!converted);
+        assert_eq!(ascii, "int x;");
+    }
+}
@@ -0,0 +1,379 @@
+#[cfg(feature = "std-fs")]
+use std::io;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+#[cfg(feature = "std-fs")]
+use std::sync::Arc;
+
+use thiserror::Error;
+
+#[cfg(feature = "std-fs")]
+use crate::matcher::{RuleMatch, RuleMatcher, RuleMatcherError, SourceContext};
+#[cfg(feature = "std-fs")]
+use crate::rule::Language;
+
+#[derive(Debug, Error)]
+pub enum DiffError {
+    // lines of the diff are 1-based
+    #[error("line {0}: invalid hunk header `{1}`")]
+    InvalidHeader(usize, String),
+    #[error("line {0}: hunk without a file header")]
+    MissingFile(usize),
+    #[error("line {0}: unexpected `{1}` within a hunk")]
+    UnexpectedLine(usize, String),
+    #[error("line {0}: hunk ends before its {1} lines")]
+    Truncated(usize, &'static str),
+}
+
+// The lines a diff adds or modifies in a file, as ranges of lines (1-based,
+// end exclusive) of its new version, i.e., as `matches_in_ranges` expects.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileChanges {
+    path: PathBuf,
+    old_path: Option<PathBuf>,
+    ranges: Vec<Range<usize>>,
+}
+
+impl FileChanges {
+    // the path of the new version, relative to the root of the repository
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    // the path of the old version, if the file was renamed
+    pub fn old_path(&self) -> Option<&Path> {
+        self.old_path.as_deref()
+    }
+
+    pub fn ranges(&self) -> &[Range<usize>] {
+        &self.ranges
+    }
+}
+
+// A file section of the diff, until its next `diff --git` (or `---`) header.
+#[derive(Default)]
+struct Section {
+    old_path: Option<String>,
+    new_path: Option<String>,
+    // whether the `+++` header was seen
+    headers: bool,
+    ranges: Vec<Range<usize>>,
+}
+
+impl Section {
+    // NOTE: files that were deleted, or whose changes only delete lines, have
+    // no lines to scan
+    fn finish(self, changes: &mut Vec<FileChanges>) {
+        let Some(path) = self.new_path else {
+            return;
+        };
+        if self.ranges.is_empty() {
+            return;
+        }
+
+        let old_path = self.old_path.filter(|old| *old != path);
+
+        changes.push(FileChanges {
+            path: PathBuf::from(path),
+            old_path: old_path.map(PathBuf::from),
+            ranges: self.ranges,
+        });
+    }
+
+    fn add(&mut self, line: usize) {
+        match self.ranges.last_mut() {
+            Some(range) if range.end == line => range.end += 1,
+            _ => self.ranges.push(line..line + 1),
+        }
+    }
+}
+
+// Parses a unified diff (e.g., the output of `git diff`) into the lines each
+// file gained, in the order of the diff. Paths lose the `a/` and `b/` prefixes
+// of git, and any timestamp; `/dev/null` stands for a missing version.
+pub fn parse_unified(diff: &str) -> Result<Vec<FileChanges>, DiffError> {
+    let mut changes = Vec::new();
+    let mut section = Section::default();
+    let mut lines = diff.lines().enumerate().map(|(i, line)| (i + 1, line));
+
+    while let Some((number, line)) = lines.next() {
+        if line.starts_with("diff ") {
+            std::mem::take(&mut section).finish(&mut changes);
+        } else if let Some(path) = line.strip_prefix("--- ") {
+            // a new file section, for diffs without `diff` headers
+            if section.headers {
+                std::mem::take(&mut section).finish(&mut changes);
+            }
+            section.old_path = parse_path(path, "a/");
+        } else if let Some(path) = line.strip_prefix("+++ ") {
+            section.new_path = parse_path(path, "b/");
+            section.headers = true;
+        } else if let Some(path) = line.strip_prefix("rename from ") {
+            section.old_path = Some(unquote(path).to_owned());
+        } else if let Some(path) = line.strip_prefix("rename to ") {
+            section.new_path = Some(unquote(path).to_owned());
+        } else if line.starts_with("@@") {
+            let (mut old, mut new, mut next) = parse_header(line)
+                .ok_or_else(|| DiffError::InvalidHeader(number, line.to_owned()))?;
+
+            if !section.headers {
+                return Err(DiffError::MissingFile(number));
+            }
+
+            let header = number;
+
+            while old > 0 || new > 0 {
+                let Some((number, line)) = lines.next() else {
+                    let missing = if new > 0 { "new" } else { "old" };
+                    return Err(DiffError::Truncated(header, missing));
+                };
+
+                // NOTE: some tools strip the space of empty context lines
+                match line.as_bytes().first() {
+                    Some(b' ') | None if old > 0 && new > 0 => {
+                        old -= 1;
+                        new -= 1;
+                        next += 1;
+                    }
+                    Some(b'+') if new > 0 => {
+                        section.add(next);
+                        new -= 1;
+                        next += 1;
+                    }
+                    Some(b'-') if old > 0 => old -= 1,
+                    Some(b'\\') => {}
+                    _ => return Err(DiffError::UnexpectedLine(number, line.to_owned())),
+                }
+            }
+        }
+    }
+
+    section.finish(&mut changes);
+
+    Ok(changes)
+}
+
+// `@@ -old[,count] +new[,count] @@`: the number of old and new lines, and the
+// first new line
+fn parse_header(line: &str) -> Option<(usize, usize, usize)> {
+    let mut fields = line.strip_prefix("@@ ")?.split(' ');
+
+    let (_, old) = parse_range(fields.next()?.strip_prefix('-')?)?;
+    let (start, new) = parse_range(fields.next()?.strip_prefix('+')?)?;
+
+    if fields.next()? != "@@" {
+        return None;
+    }
+
+    // NOTE: an empty range starts at the line before it
+    Some((old, new, start.max(1)))
+}
+
+fn parse_range(range: &str) -> Option<(usize, usize)> {
+    match range.split_once(',') {
+        Some((start, count)) => Some((start.parse().ok()?, count.parse().ok()?)),
+        None => Some((range.parse().ok()?, 1)),
+    }
+}
+
+fn parse_path(path: &str, prefix: &str) -> Option<String> {
+    // e.g., `--- a.c\t2024-01-01 00:00:00`
+    let path = unquote(path.split('\t').next().unwrap_or(path));
+
+    if path == "/dev/null" {
+        return None;
+    }
+
+    Some(path.strip_prefix(prefix).unwrap_or(path).to_owned())
+}
+
+fn unquote(path: &str) -> &str {
+    path.strip_prefix('"')
+        .and_then(|p| p.strip_suffix('"'))
+        .unwrap_or(path)
+}
+
+#[cfg(feature = "std-fs")]
+impl RuleMatcher {
+    // Scans the lines each file in `diff` gained (see `parse_unified`), in the
+    // working tree at `root`; files without a C or C++ extension are skipped.
+    pub fn scan_diff(
+        &mut self,
+        root: impl AsRef<Path>,
+        diff: &str,
+    ) -> Result<Vec<RuleMatch>, RuleMatcherError> {
+        let root = root.as_ref();
+        let changes = parse_unified(diff)?;
+
+        self.scan(|matcher| {
+            let mut results = Vec::new();
+
+            for file in &changes {
+                let Some(language) = Language::from_path(file.path()) else {
+                    continue;
+                };
+
+                let path = root.join(file.path());
+                let io_error = |e| RuleMatcherError::Io(path.clone(), e);
+
+                let bytes = matcher.read_file(&path).map_err(io_error)?;
+                let source = std::str::from_utf8(&bytes)
+                    .map_err(|e| io_error(io::Error::new(io::ErrorKind::InvalidData, e)))?;
+
+                let context = SourceContext {
+                    language,
+                    origin: Some(Arc::from(path)),
+                    ranges: Some(Arc::from(file.ranges())),
+                    ..Default::default()
+                };
+
+                results.extend(matcher.matches_source(source, context)?);
+            }
+
+            Ok(results)
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::Path;
+
+    use super::parse_unified;
+    use crate::matcher::RuleMatcher;
+
+    const DIFF: &str = "\
+diff --git a/src/copy.c b/src/copy.c
+index 1111111..2222222 100644
+--- a/src/copy.c
++++ b/src/copy.c
+@@ -1,4 +1,6 @@
+ #include <string.h>
++#include <stdio.h>
++
+ void f(char *d, char *s) {
+-    strncpy(d, s, 8);
++    strcpy(d, s);
+ }
+@@ -10,3 +12,2 @@ void g(void) {
+ int x;
+-int y;
+ int z;
+\\ No newline at end of file
+diff --git a/old.c b/new.c
+similarity index 90%
+rename from old.c
+rename to new.c
+--- a/old.c
++++ b/new.c
+@@ -5,0 +6,2 @@
++int a;
++int b;
+diff --git a/gone.c b/gone.c
+deleted file mode 100644
+--- a/gone.c
++++ /dev/null
+@@ -1,1 +0,0 @@
+-int gone;
+diff --git a/added.c b/added.c
+new file mode 100644
+--- /dev/null
++++ b/added.c
+@@ -0,0 +1,2 @@
++int one;
++int two;
+";
+
+    #[test]
+    fn test_parse_unified() -> Result<(), Box<dyn std::error::Error>> {
+        let changes = parse_unified(DIFF)?;
+
+        let files = changes
+            .iter()
+            .map(|c| (c.path(), c.old_path(), c.ranges()))
+            .collect::<Vec<_>>();
+
+        // the deleted file, and the hunk that only deletes a line, have no
+        // lines to scan
+        assert_eq!(
+            files,
+            [
+                (Path::new("src/copy.c"), None, &[2..4, 5..6][..]),
+                (Path::new("new.c"), Some(Path::new("old.c")), &[6..8; 1]),
+                (Path::new("added.c"), None, &[1..3; 1]),
+            ]
+        );
+
+        // without git headers, and with timestamps
+        let plain = "--- a.c\t2024-01-01\n+++ a.c\t2024-01-02\n@@ -3 +3 @@\n-x;\n+y;\n";
+        let changes = parse_unified(plain)?;
+        assert_eq!(changes[0].path(), Path::new("a.c"));
+        assert_eq!(changes[0].ranges(), [3..4; 1]);
+
+        // malformed hunks report the line of the diff
+        let header = "--- a/a.c\n+++ b/a.c\n";
+        for (hunk, expected) in [
+            (
+                "@@ -1 +1,x @@\n",
+                "line 3: invalid hunk header `@@ -1 +1,x @@`",
+            ),
+            (
+                "@@ -1,2 +1,2 @@\n x;\n*y;\n",
+                "line 5: unexpected `*y;` within a hunk",
+            ),
+            (
+                "@@ -1,2 +1,3 @@\n x;\n+y;\n",
+                "line 3: hunk ends before its new lines",
+            ),
+        ] {
+            let Err(error) = parse_unified(&format!("{header}{hunk}")) else {
+                panic!("{hunk:?} accepted");
+            };
+            assert_eq!(error.to_string(), expected);
+        }
+
+        let Err(error) = parse_unified("@@ -1 +1 @@\n+x;\n") else {
+            panic!("hunk without a file accepted");
+        };
+        assert_eq!(error.to_string(), "line 1: hunk without a file header");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_scan_diff() -> Result<(), Box<dyn std::error::Error>> {
+        // edge-copy.c has findings on lines 1 and 5, and utf8-comments.c on
+        // lines 6 and 12
+        let diff = "\
+--- a/edge-copy.c
++++ b/edge-copy.c
+@@ -3,1 +3,1 @@
+-x
++y
+--- a/utf8-comments.c
++++ b/utf8-comments.c
+@@ -6,2 +6,2 @@
+-x
++y
+ z
+--- a/README.md
++++ b/README.md
+@@ -1 +1 @@
+-x
++y
+";
+        let rule = "id: copy\ncheck-pattern:\n  pattern: '{ strcpy($dst, _); }'\n";
+        let mut matcher = RuleMatcher::from_str(rule)?;
+        let matches = matcher.scan_diff("tests", diff)?;
+
+        let found = matches
+            .iter()
+            .map(|m| (m.origin().unwrap(), m.start_line()))
+            .collect::<Vec<_>>();
+        assert_eq!(found, [(Path::new("tests/utf8-comments.c"), 6)]);
+
+        assert!(matcher.scan_diff("missing", diff).is_err());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,410 @@
+use std::fmt::Display;
+use std::ops::Range;
+
+use serde::Serialize;
+
+use crate::location;
+use crate::matcher::{match_span, RuleMatch, RuleMatcher};
+use crate::rule::CandidateFilter;
+
+// Why a match was reported, e.g., for rule authors puzzled by a finding: the
+// check's pattern as written, what its variables captured, how its regex
+// constraints were evaluated, and which other candidates of the check around
+// the match were dropped by `unique`, `unique-by`, or `limit` (see
+// `RuleMatcher::explain`).
+#[derive(Debug, Clone, Serialize)]
+pub struct MatchExplanation {
+    rule_key: String,
+    rule: String,
+    check: String,
+    pattern: String,
+    start: usize,
+    end: usize,
+    start_line: usize,
+    end_line: usize,
+    captures: Vec<CaptureExplanation>,
+    constraints: Vec<ConstraintExplanation>,
+    candidates: Vec<CandidateExplanation>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CaptureExplanation {
+    variable: String,
+    text: String,
+    start: usize,
+    end: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ConstraintExplanation {
+    variable: String,
+    regex: String,
+    negative: bool,
+    // tested after matching, i.e., with a transform or a mangling
+    transformed: bool,
+    // `None` if the variable was not captured, which passes
+    passed: Option<bool>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CandidateExplanation {
+    text: String,
+    start: usize,
+    end: usize,
+    dropped_by: CandidateFilter,
+}
+
+impl MatchExplanation {
+    pub fn rule_key(&self) -> &str {
+        &self.rule_key
+    }
+
+    pub fn rule(&self) -> &str {
+        &self.rule
+    }
+
+    pub fn check(&self) -> &str {
+        &self.check
+    }
+
+    pub fn pattern(&self) -> &str {
+        &self.pattern
+    }
+
+    // NOTE: offsets are byte offsets into the scanned source (see
+    // `RuleMatch::start_offset`)
+    pub fn span(&self) -> Range<usize> {
+        self.start..self.end
+    }
+
+    pub fn captures(&self) -> &[CaptureExplanation] {
+        &self.captures
+    }
+
+    pub fn constraints(&self) -> &[ConstraintExplanation] {
+        &self.constraints
+    }
+
+    pub fn candidates(&self) -> &[CandidateExplanation] {
+        &self.candidates
+    }
+}
+
+impl CaptureExplanation {
+    pub fn variable(&self) -> &str {
+        &self.variable
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn span(&self) -> Range<usize> {
+        self.start..self.end
+    }
+}
+
+impl ConstraintExplanation {
+    pub fn variable(&self) -> &str {
+        &self.variable
+    }
+
+    pub fn regex(&self) -> &str {
+        &self.regex
+    }
+
+    pub fn is_negative(&self) -> bool {
+        self.negative
+    }
+
+    pub fn is_transformed(&self) -> bool {
+        self.transformed
+    }
+
+    pub fn passed(&self) -> Option<bool> {
+        self.passed
+    }
+}
+
+impl CandidateExplanation {
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn span(&self) -> Range<usize> {
+        self.start..self.end
+    }
+
+    pub fn dropped_by(&self) -> CandidateFilter {
+        self.dropped_by
+    }
+}
+
+impl Display for MatchExplanation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "rule `{}` ({}), check `{}`, lines {}-{} (bytes {}..{})",
+            self.rule,
+            self.rule_key,
+            self.check,
+            self.start_line,
+            self.end_line,
+            self.start,
+            self.end
+        )?;
+        writeln!(f, "pattern: {}", self.pattern)?;
+
+        if !self.captures.is_empty() {
+            writeln!(f, "captures:")?;
+        }
+        for c in &self.captures {
+            writeln!(
+                f,
+                "  {} = `{}` (bytes {}..{})",
+                c.variable,
+                one_line(&c.text),
+                c.start,
+                c.end
+            )?;
+        }
+
+        if !self.constraints.is_empty() {
+            writeln!(f, "regex constraints:")?;
+        }
+        for c in &self.constraints {
+            let outcome = match c.passed {
+                Some(true) => "passed",
+                Some(false) => "failed",
+                None => "not captured",
+            };
+            let after = if c.transformed {
+                ", after matching"
+            } else {
+                ""
+            };
+            let op = if c.negative { "!=" } else { "=" };
+
+            writeln!(f, "  {}{op}{}: {outcome}{after}", c.variable, c.regex)?;
+        }
+
+        if !self.candidates.is_empty() {
+            writeln!(f, "dropped candidates:")?;
+        }
+        for c in &self.candidates {
+            writeln!(
+                f,
+                "  `{}` (bytes {}..{}): {}",
+                one_line(&c.text),
+                c.start,
+                c.end,
+                c.dropped_by
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+// whitespace runs (including newlines) as single spaces
+fn one_line(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+impl RuleMatcher {
+    // Explains a match found by this matcher: the candidates are found anew,
+    // by running the match's check on its source (parsed again, if the check
+    // needs a tree), and those dropped by `unique`, `unique-by`, or `limit`
+    // are listed if they overlap the match or start where it does (as `limit`
+    // compares them).
+    //
+    // NOTE: candidates dropped by `unique-by` in `source` or `scan` scope, or
+    // by the matcher's filters, are not listed, and neither are any for
+    // matches that retain a snippet only (see `SourceRetention`)
+    pub fn explain(&self, m: &RuleMatch) -> MatchExplanation {
+        let checker = m.checker();
+        let source = m.source_ref();
+        let span = match_span(m.result());
+        let base = m.start_offset() - span.start;
+
+        let mut captures = m
+            .result()
+            .vars
+            .iter()
+            .filter_map(|(variable, i)| {
+                let range = m.result().captures.get(*i)?.range.clone();
+                Some(CaptureExplanation {
+                    variable: variable.to_owned(),
+                    text: source.get(range.clone())?.to_owned(),
+                    start: base + range.start,
+                    end: base + range.end,
+                })
+            })
+            .collect::<Vec<_>>();
+        captures.sort_by(|a, b| a.variable.cmp(&b.variable));
+
+        let constraints = checker
+            .regex_constraints()
+            .iter()
+            .map(|c| ConstraintExplanation {
+                variable: c.variable().to_owned(),
+                regex: c.regex().to_owned(),
+                negative: c.is_negative(),
+                transformed: checker.is_transformed(c),
+                passed: checker.regex_constraint_holds(c, m.result(), source),
+            })
+            .collect();
+
+        let tree = (checker.needs_parse() && m.source_base().is_none())
+            .then(|| weggli::parse(source, m.language().is_cxx()).ok())
+            .flatten();
+        let candidates = match m.source_base() {
+            Some(_) => Vec::new(),
+            None => checker.candidates(tree.as_ref(), source),
+        };
+
+        let candidates = candidates
+            .into_iter()
+            .filter_map(|(candidate, dropped)| {
+                let dropped_by = dropped.filter(|filter| {
+                    matches!(
+                        filter,
+                        CandidateFilter::Unique
+                            | CandidateFilter::UniqueBy
+                            | CandidateFilter::Limit
+                    )
+                })?;
+                let range = match_span(&candidate);
+                let neighbor = (range.start < span.end && span.start < range.end)
+                    || candidate.start_offset() == m.result().start_offset();
+
+                neighbor.then(|| CandidateExplanation {
+                    text: location::slice(source, range.clone()).to_owned(),
+                    start: range.start,
+                    end: range.end,
+                    dropped_by,
+                })
+            })
+            .collect();
+
+        MatchExplanation {
+            rule_key: m.rule_key().to_owned(),
+            rule: m.rule().id().to_owned(),
+            check: checker.name().to_owned(),
+            pattern: checker.raw_pattern().to_owned(),
+            start: m.start_offset(),
+            end: m.end_offset(),
+            start_line: m.start_line(),
+            end_line: m.end_line(),
+            captures,
+            constraints,
+            candidates,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::matcher::RuleMatcher;
+    use crate::rule::CandidateFilter;
+
+    const SOURCE: &str = r#"
+void f(char *buf, char *src)
+{
+  strcpy(buf, src);
+  strcpy(buf, buf);
+}
+"#;
+
+    #[test]
+    fn test_explain() -> Result<(), Box<dyn std::error::Error>> {
+        let rule = r#"
+id: strcpy
+check-pattern:
+  name: copy
+  regex:
+  - dst=^buf$
+  - {vars: [src], regex: ^tmp, negative: true, lowercase: true}
+  unique: true
+  pattern: '{ strcpy($dst, $src); }'
+"#;
+
+        let mut matcher = RuleMatcher::from_str(rule)?;
+        let found = matcher.matches(SOURCE)?;
+        assert_eq!(found.len(), 1);
+
+        let explanation = matcher.explain(&found[0]);
+        assert_eq!(explanation.check(), "copy");
+        assert_eq!(explanation.pattern(), "{ strcpy($dst, $src); }");
+        assert_eq!(
+            explanation
+                .captures()
+                .iter()
+                .map(|c| (c.variable(), c.text()))
+                .collect::<Vec<_>>(),
+            [("$dst", "buf"), ("$src", "src")]
+        );
+        let capture = &explanation.captures()[0];
+        assert_eq!(&SOURCE[capture.span()], "buf");
+
+        assert_eq!(
+            explanation
+                .constraints()
+                .iter()
+                .map(|c| (c.variable(), c.is_transformed(), c.passed()))
+                .collect::<Vec<_>>(),
+            [("$dst", false, Some(true)), ("$src", true, Some(true))]
+        );
+
+        // the call copying `buf` onto itself is not unique, but is on
+        // another line, and so not a neighbor
+        assert!(explanation.candidates().is_empty());
+
+        let display = explanation.to_string();
+        assert!(
+            display.contains("pattern: { strcpy($dst, $src); }"),
+            "{display}"
+        );
+        assert!(display.contains("$dst = `buf`"), "{display}");
+        assert!(
+            display.contains("$src!=^tmp: passed, after matching"),
+            "{display}"
+        );
+
+        let json = serde_json::to_value(&explanation)?;
+        assert_eq!(json["captures"][0]["variable"], "$dst");
+        assert_eq!(json["constraints"][1]["transformed"], true);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_explain_limit() -> Result<(), Box<dyn std::error::Error>> {
+        // both calls are matched at the start of the function body, where
+        // `limit` keeps the first only
+        let rule = r#"
+id: strcpy
+check-pattern:
+  pattern: '{ strcpy($dst, _); }'
+  limit: true
+"#;
+
+        let mut matcher = RuleMatcher::from_str(rule)?;
+        let found = matcher.matches(SOURCE)?;
+        let explanation = matcher.explain(&found[0]);
+
+        assert_eq!(found.len(), 1);
+        let [candidate] = explanation.candidates() else {
+            panic!("expected a single dropped candidate:\n{explanation}");
+        };
+        assert_eq!(candidate.dropped_by(), CandidateFilter::Limit);
+        assert!(candidate.text().starts_with("strcpy(buf"), "{explanation}");
+        assert!(candidate.span().start > explanation.span().start);
+
+        let display = explanation.to_string();
+        assert!(display.contains("dropped candidates:"), "{display}");
+        assert!(display.ends_with(": limit\n"), "{display}");
+
+        Ok(())
+    }
+}
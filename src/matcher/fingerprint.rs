@@ -0,0 +1,186 @@
+use std::ops::Range;
+use std::sync::Arc;
+
+use rustc_hash::FxHashMap;
+use tree_sitter::{Node, Tree};
+
+use crate::location;
+use crate::matcher::RuleMatch;
+
+// 64-bit FNV-1a; unlike `Hash` implementations, its output is fixed, and so
+// may be persisted
+pub(crate) struct Fnv(u64);
+
+impl Fnv {
+    pub(crate) fn new() -> Self {
+        Self(0xcbf2_9ce4_8422_2325)
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for b in bytes {
+            self.0 ^= u64::from(*b);
+            self.0 = self.0.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+
+    // fields are terminated, so that ("ab", "c") and ("a", "bc") differ
+    pub(crate) fn field(&mut self, field: &str) {
+        self.write(field.as_bytes());
+        self.write(&[0xff]);
+    }
+
+    pub(crate) fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
+// Assigns fingerprints to the matches found in `source`; matches with
+// the same fingerprint are disambiguated by an ordinal suffix (`-2`, `-3`,
+// ...) in order of their location.
+//
+// NOTE: those of text checks (see `Checker::needs_parse`) do not depend on
+// the enclosing function, since the source is not always parsed for them
+pub(crate) fn assign(matches: &mut [RuleMatch], source: &str, tree: Option<&Tree>) {
+    let mut order = (0..matches.len()).collect::<Vec<_>>();
+    order.sort_by_key(|i| {
+        let m = &matches[*i];
+        (m.start_offset(), m.end_offset(), m.rule_id, m.checker_id)
+    });
+
+    let mut seen = FxHashMap::<u64, usize>::default();
+
+    for i in order {
+        let m = &matches[i];
+
+        let function = tree
+            .filter(|_| m.checker().match_regex().is_none() && m.checker().match_string().is_none())
+            .zip(m.result.captures.first())
+            .and_then(|(tree, c)| enclosing_function(tree, source, c.range.clone()));
+
+        let mut hasher = Fnv::new();
+
+        hasher.field(m.rule().id());
+        hasher.field(m.checker().name());
+        hasher.field(&m.origin().map(|p| p.to_string_lossy()).unwrap_or_default());
+        hasher.field(function.unwrap_or_default());
+
+        for token in location::slice(source, m.source_span()).split_whitespace() {
+            hasher.field(token);
+        }
+
+        let ordinal = seen.entry(hasher.0).or_default();
+        *ordinal += 1;
+
+        matches[i].fingerprint = if *ordinal == 1 {
+            Arc::from(format!("{:016x}", hasher.0))
+        } else {
+            Arc::from(format!("{:016x}-{ordinal}", hasher.0))
+        };
+    }
+}
+
+// the function definition containing `range`, if any
+pub(crate) fn function_definition(tree: &Tree, range: Range<usize>) -> Option<Node<'_>> {
+    let mut node = tree
+        .root_node()
+        .descendant_for_byte_range(range.start, range.end)?;
+
+    while node.kind() != "function_definition" {
+        node = node.parent()?;
+    }
+
+    Some(node)
+}
+
+// whether the function definition containing `range` has at least `min` and
+// at most `max` lines; matches outside any function always do
+pub(crate) fn function_lines_within(
+    tree: &Tree,
+    range: Range<usize>,
+    min: Option<usize>,
+    max: Option<usize>,
+) -> bool {
+    function_definition(tree, range).is_none_or(|node| {
+        let lines = node.end_position().row - node.start_position().row + 1;
+        min.is_none_or(|min| lines >= min) && max.is_none_or(|max| lines <= max)
+    })
+}
+
+// the name of the function definition containing `range`, if any
+fn enclosing_function<'s>(tree: &Tree, source: &'s str, range: Range<usize>) -> Option<&'s str> {
+    let node = function_definition(tree, range)?;
+
+    let mut declarator = node.child_by_field_name("declarator")?;
+
+    while declarator.kind() != "function_declarator" {
+        declarator = inner_declarator(declarator)?;
+    }
+
+    let name = declarator.child_by_field_name("declarator")?;
+
+    source.get(name.byte_range())
+}
+
+// e.g., pointer and (C++) reference declarators wrapping a function
+// declarator
+fn inner_declarator(node: Node<'_>) -> Option<Node<'_>> {
+    node.child_by_field_name("declarator")
+        .or_else(|| node.named_child(node.named_child_count().checked_sub(1)?))
+}
+
+#[cfg(test)]
+mod test {
+    use crate::matcher::RuleMatcher;
+
+    #[test]
+    fn test_fingerprint() -> Result<(), Box<dyn std::error::Error>> {
+        let rule = r#"
+id: unbounded-copy
+check pattern:
+  regex: func=^strcpy$
+  pattern: '{ $func($dst, _); }'
+"#;
+
+        let source = "\
+void a(char *d, char *s) {
+    strcpy(d, s);
+    strcpy(d,  s);
+}
+
+void b(char *d, char *s) { strcpy(d, s); }
+";
+
+        let mut matcher = RuleMatcher::from_str(rule)?;
+
+        let fingerprints = |matcher: &mut RuleMatcher, source: &str| {
+            let mut matches = matcher.matches(source).unwrap();
+            matches.sort_by_key(|m| m.start_offset());
+            matches
+                .iter()
+                .map(|m| m.fingerprint().to_owned())
+                .collect::<Vec<_>>()
+        };
+
+        let original = fingerprints(&mut matcher, source);
+
+        assert_eq!(original.len(), 3);
+        assert_eq!(original[0].len(), 16);
+        assert_eq!(original[1], format!("{}-2", original[0]));
+        assert_ne!(original[0], original[2]);
+
+        // stable across runs and under edits elsewhere in the file
+        assert_eq!(fingerprints(&mut matcher, source), original);
+        assert_eq!(
+            fingerprints(&mut matcher, &format!("#include <string.h>\n\n{source}")),
+            original
+        );
+
+        // ...but not when the rule is renamed
+        let renamed = rule.replace("unbounded-copy", "strcpy");
+        let mut matcher = RuleMatcher::from_str(renamed)?;
+
+        assert_ne!(fingerprints(&mut matcher, source)[0], original[0]);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,312 @@
+use std::cmp::Ordering;
+use std::hash::Hash;
+use std::path::Path;
+use std::sync::Arc;
+
+use rustc_hash::FxHashMap;
+use serde::ser::SerializeStruct;
+use serde::{Serialize, Serializer};
+
+use crate::matcher::RuleMatch;
+use crate::reporting::RuleMatchReport;
+use crate::rule::{Rule, Severity};
+
+pub struct RuleGroup {
+    rule: Arc<Rule>,
+    matches: Vec<RuleMatch>,
+}
+
+impl RuleGroup {
+    pub fn rule(&self) -> &Rule {
+        &self.rule
+    }
+
+    pub fn matches(&self) -> &[RuleMatch] {
+        &self.matches
+    }
+
+    pub fn into_matches(self) -> Vec<RuleMatch> {
+        self.matches
+    }
+
+    pub fn count(&self) -> usize {
+        self.matches.len()
+    }
+
+    pub fn origins(&self) -> Vec<&Path> {
+        distinct_origins(&self.matches)
+    }
+
+    pub fn max_severity(&self) -> Severity {
+        max_severity(&self.matches)
+    }
+}
+
+impl Serialize for RuleGroup {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut tags = self.rule.tags().iter().collect::<Vec<_>>();
+        tags.sort();
+
+        let mut s = serializer.serialize_struct("RuleGroup", 7)?;
+
+        s.serialize_field("rule", self.rule.id())?;
+
+        if let Some(description) = self.rule.description() {
+            s.serialize_field("description", description)?;
+        } else {
+            s.skip_field("description")?;
+        }
+
+        s.serialize_field("severity", &self.max_severity())?;
+        s.serialize_field("tags", &tags)?;
+        s.serialize_field("count", &self.count())?;
+        s.serialize_field("origins", &self.origins())?;
+        s.serialize_field("matches", &reports(&self.matches))?;
+
+        s.end()
+    }
+}
+
+pub struct OriginGroup {
+    origin: Option<Arc<Path>>,
+    matches: Vec<RuleMatch>,
+}
+
+impl OriginGroup {
+    pub fn origin(&self) -> Option<&Path> {
+        self.origin.as_deref()
+    }
+
+    pub fn matches(&self) -> &[RuleMatch] {
+        &self.matches
+    }
+
+    pub fn into_matches(self) -> Vec<RuleMatch> {
+        self.matches
+    }
+
+    pub fn count(&self) -> usize {
+        self.matches.len()
+    }
+
+    pub fn rules(&self) -> Vec<&str> {
+        let mut rules = self
+            .matches
+            .iter()
+            .map(|m| m.rule().id())
+            .collect::<Vec<_>>();
+        rules.sort();
+        rules.dedup();
+        rules
+    }
+
+    pub fn max_severity(&self) -> Severity {
+        max_severity(&self.matches)
+    }
+}
+
+impl Serialize for OriginGroup {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut s = serializer.serialize_struct("OriginGroup", 5)?;
+
+        if let Some(ref origin) = self.origin {
+            s.serialize_field("origin", origin)?;
+        } else {
+            s.skip_field("origin")?;
+        }
+
+        s.serialize_field("severity", &self.max_severity())?;
+        s.serialize_field("count", &self.count())?;
+        s.serialize_field("rules", &self.rules())?;
+        s.serialize_field("matches", &reports(&self.matches))?;
+
+        s.end()
+    }
+}
+
+// groups are ordered by severity (highest first), then by rule id; matches
+// within each group are ordered by location
+pub fn group_by_rule(matches: Vec<RuleMatch>) -> Vec<RuleGroup> {
+    let mut groups = group_by(matches, |m| Arc::as_ptr(&m.rule))
+        .into_iter()
+        .map(|matches| RuleGroup {
+            rule: matches[0].rule.clone(),
+            matches,
+        })
+        .collect::<Vec<_>>();
+
+    groups.sort_by(|a, b| {
+        b.max_severity()
+            .cmp(&a.max_severity())
+            .then_with(|| a.rule.id().cmp(b.rule.id()))
+    });
+
+    groups
+}
+
+// groups are ordered by origin (matches without an origin first); matches
+// within each group are ordered by location
+pub fn group_by_origin(matches: Vec<RuleMatch>) -> Vec<OriginGroup> {
+    let mut groups = group_by(matches, |m| m.origin.clone())
+        .into_iter()
+        .map(|matches| OriginGroup {
+            origin: matches[0].origin.clone(),
+            matches,
+        })
+        .collect::<Vec<_>>();
+
+    groups.sort_by(|a, b| a.origin.cmp(&b.origin));
+
+    groups
+}
+
+pub(crate) fn location_order(a: &RuleMatch, b: &RuleMatch) -> Ordering {
+    a.origin()
+        .cmp(&b.origin())
+        .then_with(|| a.start_offset().cmp(&b.start_offset()))
+        .then_with(|| a.end_offset().cmp(&b.end_offset()))
+        .then_with(|| a.rule_id().cmp(&b.rule_id()))
+        .then_with(|| a.checker_id().cmp(&b.checker_id()))
+}
+
+fn group_by<K>(matches: Vec<RuleMatch>, key: impl Fn(&RuleMatch) -> K) -> Vec<Vec<RuleMatch>>
+where
+    K: Eq + Hash,
+{
+    let mut index = FxHashMap::default();
+    let mut groups = Vec::<Vec<RuleMatch>>::new();
+
+    for m in matches {
+        let i = *index.entry(key(&m)).or_insert_with(|| {
+            groups.push(Vec::new());
+            groups.len() - 1
+        });
+        groups[i].push(m);
+    }
+
+    for group in groups.iter_mut() {
+        group.sort_by(location_order);
+    }
+
+    groups
+}
+
+fn distinct_origins(matches: &[RuleMatch]) -> Vec<&Path> {
+    let mut origins = matches
+        .iter()
+        .filter_map(|m| m.origin())
+        .collect::<Vec<_>>();
+    origins.sort();
+    origins.dedup();
+    origins
+}
+
+fn max_severity(matches: &[RuleMatch]) -> Severity {
+    matches
+        .iter()
+        .map(|m| m.effective_severity())
+        .max()
+        .unwrap_or_default()
+}
+
+fn reports(matches: &[RuleMatch]) -> Vec<RuleMatchReport<'_>> {
+    matches.iter().map(RuleMatchReport::new).collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::{group_by_origin, group_by_rule};
+    use crate::matcher::RuleMatcher;
+    use crate::rule::Severity;
+    use crate::testing;
+
+    #[test]
+    fn test_group_by_rule() -> Result<(), Box<dyn std::error::Error>> {
+        let pack = testing::pack_with(&[(
+            "unbounded-copy.yml",
+            r#"
+id: unbounded-copy
+severity: high
+tags: [CWE-120]
+check-pattern:
+  pattern: '{ strcpy($dst, _); }'
+"#,
+        )])?;
+
+        let mut matcher = RuleMatcher::from_directory_with(pack.path(), false)?;
+
+        let mut matches = matcher.matches_file("tests/utf8-comments.c")?;
+        matches.reverse();
+
+        let groups = group_by_rule(matches);
+
+        assert_eq!(groups.len(), 2);
+
+        assert_eq!(groups[0].rule().id(), "unbounded-copy");
+        assert_eq!(groups[0].max_severity(), Severity::High);
+        assert_eq!(groups[0].count(), 2);
+        assert_eq!(groups[0].origins().len(), 1);
+
+        assert!(groups[0].matches()[0].start_offset() < groups[0].matches()[1].start_offset());
+
+        assert_eq!(groups[1].rule().id(), "local-buffer");
+        assert_eq!(groups[1].max_severity(), Severity::Low);
+        assert_eq!(groups[1].count(), 1);
+
+        let value = serde_yaml::to_value(&groups)?;
+
+        assert_eq!(value[0]["rule"].as_str(), Some("unbounded-copy"));
+        assert_eq!(value[0]["count"].as_u64(), Some(2));
+        assert_eq!(value[0]["tags"][0].as_str(), Some("CWE-120"));
+        assert_eq!(
+            value[0]["origins"][0].as_str(),
+            Some("tests/utf8-comments.c")
+        );
+        assert_eq!(value[0]["matches"].as_sequence().map(Vec::len), Some(2));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_group_by_origin() -> Result<(), Box<dyn std::error::Error>> {
+        let rule = r#"
+id: unbounded-copy
+severity: medium
+check pattern:
+  regex: func=^strcpy$
+  pattern: '{ $func(_, _); }'
+"#;
+
+        let mut matcher = RuleMatcher::from_str(rule)?;
+
+        let mut matches = matcher.matches_file("tests/utf8-comments.c")?;
+        matches.extend(matcher.matches_file("tests/ls-main.c")?);
+        matches.extend(matcher.matches(r#"void f(char *s) { strcpy(s, "x"); }"#)?);
+
+        let groups = group_by_origin(matches);
+
+        assert_eq!(groups.len(), 3);
+
+        assert_eq!(groups[0].origin(), None);
+        assert_eq!(
+            groups[1].origin().unwrap().to_str(),
+            Some("tests/ls-main.c")
+        );
+        assert_eq!(
+            groups[2].origin().unwrap().to_str(),
+            Some("tests/utf8-comments.c")
+        );
+
+        assert_eq!(groups[2].count(), 2);
+        assert_eq!(groups[2].rules(), ["unbounded-copy"]);
+        assert_eq!(groups[2].max_severity(), Severity::Medium);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,155 @@
+use std::fs;
+#[cfg(feature = "mmap")]
+use std::fs::File;
+use std::io;
+use std::ops::Deref;
+use std::path::Path;
+
+#[cfg(feature = "mmap")]
+use memmap2::Mmap;
+
+use crate::matcher::RuleMatcher;
+
+// The contents of a source file, read into a buffer or, with the `mmap`
+// feature, mapped into memory (see `RuleMatcher::set_mapped_input`).
+pub(crate) enum FileBytes {
+    Buffered(Vec<u8>),
+    #[cfg(feature = "mmap")]
+    Mapped(Mmap),
+}
+
+impl Deref for FileBytes {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        match self {
+            Self::Buffered(bytes) => bytes,
+            #[cfg(feature = "mmap")]
+            Self::Mapped(map) => map,
+        }
+    }
+}
+
+impl RuleMatcher {
+    // Maps source files into memory rather than reading them, e.g., for
+    // multi-hundred-megabyte decompiler dumps: they are validated as UTF-8,
+    // parsed, and matched in place, and only copied once a check matches
+    // them; with `SourceRetention::Snippet`, the matches retain their
+    // snippets only. Files that cannot be mapped (e.g., pipes) are read as
+    // usual.
+    //
+    // NOTE: a mapped file must not be modified while it is scanned; if it is
+    // truncated, reading past its new end raises `SIGBUS` (on Unix), which
+    // aborts the process
+    #[cfg(feature = "mmap")]
+    pub fn set_mapped_input(&mut self, enabled: bool) {
+        self.mapped_input = enabled;
+    }
+
+    #[cfg(feature = "mmap")]
+    pub fn maps_input(&self) -> bool {
+        self.mapped_input
+    }
+
+    pub(crate) fn read_file(&mut self, path: &Path) -> io::Result<FileBytes> {
+        #[cfg(feature = "mmap")]
+        if self.mapped_input {
+            if let Some(map) = map(path) {
+                self.metrics.record_mapped();
+                return Ok(FileBytes::Mapped(map));
+            }
+        }
+
+        fs::read(path).map(FileBytes::Buffered)
+    }
+}
+
+// NOTE: empty files cannot be mapped on all platforms, and are read instead
+#[cfg(feature = "mmap")]
+fn map(path: &Path) -> Option<Mmap> {
+    let file = File::open(path).ok()?;
+    if file.metadata().ok()?.len() == 0 {
+        return None;
+    }
+
+    // SAFETY: the mapping is read-only, and only read while the file is
+    // scanned, during which it must not be modified (see `set_mapped_input`)
+    unsafe { Mmap::map(&file) }.ok()
+}
+
+#[cfg(all(test, feature = "mmap"))]
+mod test {
+    use std::fmt::Write;
+    use std::fs;
+
+    use crate::matcher::{RuleMatcher, SourceRetention};
+
+    #[test]
+    fn test_mapped_input() -> Result<(), Box<dyn std::error::Error>> {
+        let rule = r#"
+id: copy
+check-pattern:
+  pattern: '{ strcpy($dst, $src); }'
+"#;
+
+        // a large dump, with a finding near its end
+        let mut source = String::new();
+        for i in 0..100_000 {
+            writeln!(
+                source,
+                "int sub_{i:x}(int a1)\n{{\n  return a1 + {i};\n}}\n"
+            )?;
+        }
+        writeln!(
+            source,
+            "void sub_end(char *a1, char *a2)\n{{\n  strcpy(a1, a2);\n}}"
+        )?;
+
+        let dir = std::env::temp_dir().join(format!("weggli-ruleset-mmap-{}", std::process::id()));
+        fs::create_dir_all(&dir)?;
+        let path = dir.join("dump.c");
+        fs::write(&path, &source)?;
+
+        let mut matcher = RuleMatcher::from_str(rule)?;
+        let expected = matcher.matches_file(&path)?;
+        assert_eq!(matcher.last_metrics().mapped(), 0);
+
+        matcher.set_mapped_input(true);
+        matcher.set_source_retention(SourceRetention::Snippet {
+            before: 1,
+            after: 1,
+        });
+        let found = matcher.matches_file(&path)?;
+        assert_eq!(matcher.last_metrics().mapped(), 1);
+
+        assert_eq!(found.len(), 1);
+        let (m, expected) = (&found[0], &expected[0]);
+        assert_eq!(m.matched_text(), expected.matched_text());
+        assert_eq!(
+            (m.start_offset(), m.start_line(), m.start_column()),
+            (
+                expected.start_offset(),
+                expected.start_line(),
+                expected.start_column()
+            )
+        );
+        assert_eq!(m.fingerprint(), expected.fingerprint());
+        assert_eq!(m.source_ref(), "{\n  strcpy(a1, a2);\n}");
+        assert_eq!(m.start_line(), source.lines().count() - 1);
+
+        // invalid UTF-8 is an error, as when reading
+        let invalid = dir.join("invalid.c");
+        fs::write(&invalid, b"int f(void) { return '\xff'; }\n")?;
+        assert!(matcher.matches_file(&invalid).is_err());
+
+        // files that cannot be mapped are read
+        let empty = dir.join("empty.c");
+        fs::write(&empty, "")?;
+        assert!(matcher.matches_file(&empty)?.is_empty());
+        assert_eq!(matcher.last_metrics().mapped(), 0);
+
+        fs::remove_dir_all(&dir)?;
+
+        Ok(())
+    }
+}
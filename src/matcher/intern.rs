@@ -0,0 +1,208 @@
+use std::hash::{Hash, Hasher};
+use std::sync::Arc;
+
+use rustc_hash::{FxHashMap, FxHasher};
+use tree_sitter::Tree;
+use weggli::result::QueryResult;
+
+use crate::matcher::Original;
+#[cfg(feature = "std-fs")]
+use crate::matcher::RuleMatcher;
+use crate::rule::Language;
+
+// The sources of a batch scan by content, so that identical files (e.g.,
+// vendored copies) share their source, and are parsed and matched once: as
+// offsets are the same in all copies, the results of each check are replicated
+// for each origin, whose matches are then assembled (and filtered) as usual.
+#[derive(Default)]
+pub(crate) struct SourceInterner {
+    entries: FxHashMap<u64, Vec<Entry>>,
+}
+
+struct Entry {
+    input: Arc<str>,
+    language: Language,
+    source: Arc<str>,
+    original: Option<Arc<Original>>,
+    tree: Option<Tree>,
+    // by rule and check index
+    results: FxHashMap<(usize, usize), Vec<QueryResult>>,
+}
+
+// an entry of the interner, as returned by `get` or `insert`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Handle(u64, usize);
+
+pub(crate) struct Interned {
+    pub(crate) handle: Handle,
+    pub(crate) source: Arc<str>,
+    pub(crate) original: Option<Arc<Original>>,
+    pub(crate) tree: Option<Tree>,
+}
+
+impl SourceInterner {
+    fn hash(input: &str) -> u64 {
+        let mut hasher = FxHasher::default();
+        input.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    // the entry of a source with the same contents, parsed the same way
+    pub(crate) fn get(&self, input: &str, language: Language) -> Option<Interned> {
+        let hash = Self::hash(input);
+
+        self.entries.get(&hash).and_then(|entries| {
+            entries
+                .iter()
+                .position(|e| e.language == language && *e.input == *input)
+                .map(|i| {
+                    let e = &entries[i];
+                    Interned {
+                        handle: Handle(hash, i),
+                        source: e.source.clone(),
+                        original: e.original.clone(),
+                        tree: e.tree.clone(),
+                    }
+                })
+        })
+    }
+
+    pub(crate) fn insert(
+        &mut self,
+        input: &str,
+        language: Language,
+        source: Arc<str>,
+        original: Option<Arc<Original>>,
+    ) -> Handle {
+        let hash = Self::hash(input);
+        let entries = self.entries.entry(hash).or_default();
+
+        entries.push(Entry {
+            // NOTE: without normalization, the input is the source
+            input: match original {
+                Some(_) => Arc::from(input),
+                None => source.clone(),
+            },
+            language,
+            source,
+            original,
+            tree: None,
+            results: FxHashMap::default(),
+        });
+
+        Handle(hash, entries.len() - 1)
+    }
+
+    fn entry(&mut self, handle: Handle) -> Option<&mut Entry> {
+        self.entries.get_mut(&handle.0)?.get_mut(handle.1)
+    }
+
+    pub(crate) fn set_tree(&mut self, handle: Handle, tree: &Tree) {
+        if let Some(e) = self.entry(handle) {
+            e.tree = Some(tree.clone());
+        }
+    }
+
+    pub(crate) fn results(
+        &mut self,
+        handle: Handle,
+        rule_id: usize,
+        checker_id: usize,
+    ) -> Option<Vec<QueryResult>> {
+        self.entry(handle)?
+            .results
+            .get(&(rule_id, checker_id))
+            .cloned()
+    }
+
+    pub(crate) fn set_results(
+        &mut self,
+        handle: Handle,
+        rule_id: usize,
+        checker_id: usize,
+        results: &[QueryResult],
+    ) {
+        if let Some(e) = self.entry(handle) {
+            e.results.insert((rule_id, checker_id), results.to_vec());
+        }
+    }
+}
+
+#[cfg(feature = "std-fs")]
+impl RuleMatcher {
+    // interns the sources scanned by `f` (see `SourceInterner`)
+    pub(crate) fn interning<T>(&mut self, f: impl FnOnce(&mut Self) -> T) -> T {
+        self.interner = Some(SourceInterner::default());
+        let result = f(self);
+        self.interner = None;
+        result
+    }
+}
+
+#[cfg(all(test, feature = "std-fs"))]
+mod test {
+    use std::sync::Arc;
+
+    use crate::matcher::RuleMatcher;
+
+    #[test]
+    fn test_interning() -> Result<(), Box<dyn std::error::Error>> {
+        let files = [
+            "tests/vendored/a/utf8-comments.c",
+            "tests/vendored/b/utf8-comments.c",
+            "tests/vendored/c/utf8-comments.c",
+        ];
+
+        let rule = r#"
+id: unbounded-copy
+check pattern:
+  regex: func=^strcpy$
+  pattern: |
+    { $func($dst, _); }
+"#;
+
+        let mut matcher = RuleMatcher::from_str(rule)?;
+        let single = matcher.matches_file("tests/utf8-comments.c")?;
+
+        let matches = matcher.matches_files(files)?;
+        let metrics = matcher.last_metrics();
+
+        // one parse, and three sets of results
+        assert_eq!(metrics.files(), 3);
+        assert_eq!(metrics.parsed(), 1);
+        assert_eq!(metrics.deduplicated(), 2);
+        assert_eq!(metrics.matches(), 3 * single.len());
+        assert_eq!(matches.len(), 3 * single.len());
+
+        for (file, copies) in files.iter().zip(matches.chunks(single.len())) {
+            assert!(copies.iter().all(|m| m.origin() == Some(file.as_ref())));
+            assert!(Arc::ptr_eq(&copies[0].source(), &matches[0].source()));
+
+            // the same findings, at the same offsets, but distinct fingerprints
+            let offsets = |ms: &[_]| {
+                let mut offsets = ms
+                    .iter()
+                    .map(|m: &crate::matcher::RuleMatch| (m.start_offset(), m.end_offset()))
+                    .collect::<Vec<_>>();
+                offsets.sort();
+                offsets
+            };
+            assert_eq!(offsets(copies), offsets(&single));
+        }
+        assert_ne!(
+            matches[0].fingerprint(),
+            matches[single.len()].fingerprint()
+        );
+
+        // the directory scan dedupes them too, and single files are not
+        // interned across scanning calls
+        matcher.matches_directory("tests/vendored")?;
+        assert_eq!(matcher.last_metrics().deduplicated(), 2);
+
+        matcher.matches_file(files[0])?;
+        assert_eq!(matcher.last_metrics().parsed(), 1);
+        assert_eq!(matcher.last_metrics().deduplicated(), 0);
+
+        Ok(())
+    }
+}
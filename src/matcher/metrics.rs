@@ -0,0 +1,312 @@
+use std::collections::BTreeMap;
+use std::fmt::Display;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use serde::{Serialize, Serializer};
+
+#[cfg(feature = "std-fs")]
+use crate::matcher::progress::SkipReason;
+use crate::rule::{Language, Severity};
+
+// Counters accumulated over a single scanning call (`matches_*`) of a
+// `RuleMatcher`; see `RuleMatcher::last_metrics`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct ScanMetrics {
+    files: usize,
+    skipped: SkippedFiles,
+    bytes: usize,
+    mapped: usize,
+    parsed: usize,
+    deduplicated: usize,
+    checks_evaluated: usize,
+    checks_prefiltered: usize,
+    checks_oversized: usize,
+    rules_too_large: usize,
+    sources_timed_out: usize,
+    over_budget: Vec<OverBudget>,
+    language_mismatches: Vec<LanguageMismatch>,
+    matches: BTreeMap<Severity, usize>,
+    #[serde(serialize_with = "serialize_seconds")]
+    elapsed: Duration,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct SkippedFiles {
+    pub unreadable: usize,
+    pub invalid_utf8: usize,
+    pub parse_failed: usize,
+    // files and directories (counted once, without their contents)
+    pub ignored: usize,
+}
+
+// A run of a check that took longer than the check budget (see
+// `RuleMatcher::set_check_budget`), and whether the check was disabled for the
+// rest of the scan as a result.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct OverBudget {
+    pub rule_key: String,
+    pub rule: String,
+    pub check: String,
+    pub origin: Option<PathBuf>,
+    #[serde(serialize_with = "serialize_seconds")]
+    pub elapsed: Duration,
+    pub disabled: bool,
+}
+
+// A check written for C++ that was skipped for a source parsed as C (see
+// `RuleMatcher::set_run_mismatched_languages`).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct LanguageMismatch {
+    pub rule_key: String,
+    pub rule: String,
+    pub check: String,
+    pub origin: Option<PathBuf>,
+    pub check_language: Language,
+    pub source_language: Language,
+}
+
+impl SkippedFiles {
+    pub fn total(&self) -> usize {
+        self.unreadable + self.invalid_utf8 + self.parse_failed + self.ignored
+    }
+}
+
+impl ScanMetrics {
+    // sources that were prefiltered and, if any check was viable, matched
+    pub fn files(&self) -> usize {
+        self.files
+    }
+
+    pub fn skipped(&self) -> SkippedFiles {
+        self.skipped
+    }
+
+    // the size of the scanned sources, after decoding and normalization
+    pub fn bytes(&self) -> usize {
+        self.bytes
+    }
+
+    // files that were mapped into memory rather than read (see
+    // `RuleMatcher::set_mapped_input`)
+    pub fn mapped(&self) -> usize {
+        self.mapped
+    }
+
+    // sources that were parsed, i.e., that were not identical to one scanned
+    // before in the same batch, and that a viable check needed the tree of
+    // (see `Checker::needs_parse`)
+    pub fn parsed(&self) -> usize {
+        self.parsed
+    }
+
+    // sources identical to one scanned before in the same batch, whose parse
+    // tree and results were reused
+    pub fn deduplicated(&self) -> usize {
+        self.deduplicated
+    }
+
+    // checks whose pattern was matched against a parsed source
+    pub fn checks_evaluated(&self) -> usize {
+        self.checks_evaluated
+    }
+
+    // checks skipped because the prefilter ruled them out
+    pub fn checks_prefiltered(&self) -> usize {
+        self.checks_prefiltered
+    }
+
+    // checks skipped because the source exceeded their `max-source-bytes`
+    pub fn checks_oversized(&self) -> usize {
+        self.checks_oversized
+    }
+
+    // rules skipped for sources larger than their `max-file-size` (counted
+    // once per source)
+    pub fn rules_too_large(&self) -> usize {
+        self.rules_too_large
+    }
+
+    // sources whose remaining checks were skipped once the source timeout
+    // expired
+    pub fn sources_timed_out(&self) -> usize {
+        self.sources_timed_out
+    }
+
+    pub fn over_budget(&self) -> &[OverBudget] {
+        &self.over_budget
+    }
+
+    // the checks the circuit breaker disabled, as (rule key, check name)
+    pub fn disabled_checks(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.over_budget
+            .iter()
+            .filter(|o| o.disabled)
+            .map(|o| (o.rule_key.as_str(), o.check.as_str()))
+    }
+
+    // the checks skipped because their language differs from that of the
+    // source, one entry per source
+    pub fn language_mismatches(&self) -> &[LanguageMismatch] {
+        &self.language_mismatches
+    }
+
+    pub fn matches(&self) -> usize {
+        self.matches.values().sum()
+    }
+
+    pub fn matches_by_severity(&self) -> &BTreeMap<Severity, usize> {
+        &self.matches
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+
+    pub(crate) fn record_source(&mut self, bytes: usize, evaluated: usize, prefiltered: usize) {
+        self.files += 1;
+        self.bytes += bytes;
+        self.checks_evaluated += evaluated;
+        self.checks_prefiltered += prefiltered;
+    }
+
+    pub(crate) fn record_match(&mut self, severity: Severity) {
+        *self.matches.entry(severity).or_default() += 1;
+    }
+
+    #[cfg(feature = "std-fs")]
+    pub(crate) fn record_skip(&mut self, reason: SkipReason) {
+        match reason {
+            SkipReason::Unreadable(_) => self.skipped.unreadable += 1,
+            SkipReason::InvalidUtf8 => self.skipped.invalid_utf8 += 1,
+            SkipReason::Ignored => self.skipped.ignored += 1,
+        }
+    }
+
+    pub(crate) fn record_oversized(&mut self) {
+        self.checks_oversized += 1;
+    }
+
+    pub(crate) fn record_too_large(&mut self, rules: usize) {
+        self.rules_too_large += rules;
+    }
+
+    pub(crate) fn record_timeout(&mut self) {
+        self.sources_timed_out += 1;
+    }
+
+    pub(crate) fn record_over_budget(
+        &mut self,
+        (rule_key, rule, check): (&str, &str, &str),
+        origin: Option<&Path>,
+        elapsed: Duration,
+        disabled: bool,
+    ) {
+        self.over_budget.push(OverBudget {
+            rule_key: rule_key.to_owned(),
+            rule: rule.to_owned(),
+            check: check.to_owned(),
+            origin: origin.map(Path::to_owned),
+            elapsed,
+            disabled,
+        });
+    }
+
+    pub(crate) fn record_language_mismatch(
+        &mut self,
+        (rule_key, rule, check): (&str, &str, &str),
+        origin: Option<&Path>,
+        check_language: Language,
+        source_language: Language,
+    ) {
+        self.language_mismatches.push(LanguageMismatch {
+            rule_key: rule_key.to_owned(),
+            rule: rule.to_owned(),
+            check: check.to_owned(),
+            origin: origin.map(Path::to_owned),
+            check_language,
+            source_language,
+        });
+    }
+
+    pub(crate) fn record_parse(&mut self) {
+        self.parsed += 1;
+    }
+
+    #[cfg(feature = "mmap")]
+    pub(crate) fn record_mapped(&mut self) {
+        self.mapped += 1;
+    }
+
+    pub(crate) fn record_duplicate(&mut self) {
+        self.deduplicated += 1;
+    }
+
+    pub(crate) fn record_parse_failure(&mut self) {
+        self.skipped.parse_failed += 1;
+    }
+
+    pub(crate) fn set_elapsed(&mut self, elapsed: Duration) {
+        self.elapsed = elapsed;
+    }
+}
+
+// e.g., "3 files (1 skipped), 2048 bytes, 4 checks evaluated (6 prefiltered),
+// 5 matches (high: 3, low: 2) in 0.012s"
+impl Display for ScanMetrics {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} files", self.files)?;
+
+        if self.skipped.total() > 0 {
+            write!(f, " ({} skipped)", self.skipped.total())?;
+        }
+
+        if self.deduplicated > 0 {
+            write!(f, " ({} deduplicated)", self.deduplicated)?;
+        }
+
+        if self.mapped > 0 {
+            write!(f, " ({} mapped)", self.mapped)?;
+        }
+
+        write!(
+            f,
+            ", {} bytes, {} checks evaluated ({} prefiltered), {} matches",
+            self.bytes,
+            self.checks_evaluated,
+            self.checks_prefiltered,
+            self.matches()
+        )?;
+
+        if !self.matches.is_empty() {
+            let severities = self
+                .matches
+                .iter()
+                .rev()
+                .map(|(severity, count)| format!("{severity}: {count}"))
+                .collect::<Vec<_>>();
+            write!(f, " ({})", severities.join(", "))?;
+        }
+
+        if !self.over_budget.is_empty() {
+            write!(f, ", {} checks over budget", self.over_budget.len())?;
+        }
+
+        if !self.language_mismatches.is_empty() {
+            write!(
+                f,
+                ", {} checks skipped for language",
+                self.language_mismatches.len()
+            )?;
+        }
+
+        write!(f, " in {:.3}s", self.elapsed.as_secs_f64())
+    }
+}
+
+fn serialize_seconds<S>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    serializer.serialize_f64(duration.as_secs_f64())
+}
@@ -0,0 +1,156 @@
+use std::path::Path;
+use std::sync::Arc;
+
+use weggli::result::QueryResult;
+
+use crate::matcher::{DecodePolicy, Original, RuleMatch, Segment, SuppressionReason};
+use crate::reporting::SourceBase;
+use crate::rule::{Language, Rule, Severity};
+
+// The attribution of a match besides its rule, source, and result (see
+// `RuleMatch::into_parts`): which check of the `RuleSet` it belongs to, and
+// where and how its source was matched, to be carried over to the match
+// rebuilt with `RuleMatch::from_parts`.
+#[derive(Debug, Clone)]
+pub struct MatchIds {
+    rule_key: Arc<str>,
+    rule_id: usize,
+    checker_id: usize,
+    language: Language,
+    origin: Option<Arc<Path>>,
+    conversion: Option<DecodePolicy>,
+    original: Option<Arc<Original>>,
+    base: Option<SourceBase>,
+    fingerprint: Arc<str>,
+    suppression: Option<SuppressionReason>,
+    segment: Option<Arc<Segment>>,
+    severity: Severity,
+    address: Option<u64>,
+}
+
+impl MatchIds {
+    // NOTE: as the accessors of `RuleMatch`
+
+    pub fn rule_key(&self) -> &str {
+        &self.rule_key
+    }
+
+    pub fn rule_id(&self) -> usize {
+        self.rule_id
+    }
+
+    pub fn checker_id(&self) -> usize {
+        self.checker_id
+    }
+
+    pub fn fingerprint(&self) -> &str {
+        &self.fingerprint
+    }
+}
+
+impl RuleMatch {
+    // the rule, source, and result of the match, and the rest of its
+    // attribution, without cloning any of them
+    pub fn into_parts(self) -> (Arc<Rule>, Arc<str>, QueryResult, MatchIds) {
+        let ids = MatchIds {
+            rule_key: self.rule_key,
+            rule_id: self.rule_id,
+            checker_id: self.checker_id,
+            language: self.language,
+            origin: self.origin,
+            conversion: self.conversion,
+            original: self.original,
+            base: self.base,
+            fingerprint: self.fingerprint,
+            suppression: self.suppression,
+            segment: self.segment,
+            severity: self.severity,
+            address: self.address,
+        };
+
+        (self.rule, self.source, self.result, ids)
+    }
+
+    // e.g., for a match whose result was enriched after `into_parts`
+    //
+    // NOTE: `rule` and `source` must be those the `ids` were taken along with,
+    // and `result` must refer to `source`; the fingerprint and severity are
+    // those of the original match
+    pub fn from_parts(
+        rule: Arc<Rule>,
+        source: Arc<str>,
+        result: QueryResult,
+        ids: MatchIds,
+    ) -> Self {
+        Self {
+            rule,
+            rule_key: ids.rule_key,
+            rule_id: ids.rule_id,
+            checker_id: ids.checker_id,
+            source,
+            language: ids.language,
+            origin: ids.origin,
+            conversion: ids.conversion,
+            original: ids.original,
+            base: ids.base,
+            fingerprint: ids.fingerprint,
+            suppression: ids.suppression,
+            segment: ids.segment,
+            severity: ids.severity,
+            address: ids.address,
+            result,
+        }
+    }
+
+    // the match with its result replaced by `f(result)`
+    pub fn map_result(self, f: impl FnOnce(QueryResult) -> QueryResult) -> Self {
+        Self {
+            result: f(self.result),
+            ..self
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use crate::matcher::{RuleMatch, RuleMatcher};
+    use crate::rule::Language;
+
+    #[test]
+    fn test_into_parts() -> Result<(), Box<dyn std::error::Error>> {
+        let rule = "id: copy\ncheck-pattern:\n  pattern: '{ strcpy($dst, $src); }'\n";
+        let source = "void f(char *s) {\n  char buf[8];\n  strcpy(buf, s);\n}\n";
+
+        let mut matcher = RuleMatcher::from_str(rule)?;
+        let m = matcher.matches_lang(source, Language::C)?.remove(0);
+        let display = m.display(1, 1, true);
+        let fingerprint = m.fingerprint().to_owned();
+
+        let (rule, source, result, ids) = m.into_parts();
+        assert_eq!(
+            (ids.rule_key(), ids.rule_id(), ids.checker_id()),
+            ("default", 0, 0)
+        );
+
+        // the source is shared, not copied
+        let shared = source.clone();
+        let m = RuleMatch::from_parts(rule, source, result, ids);
+        assert!(Arc::ptr_eq(&m.source(), &shared));
+        assert_eq!(m.display(1, 1, true), display);
+        assert_eq!(m.fingerprint(), fingerprint);
+        assert_eq!(m.check_ref(), ("default", "copy", "default", 0));
+
+        // e.g., a taint label for the source of the copy
+        let m = m.map_result(|mut result| {
+            let index = result.vars["$src"];
+            result.vars.insert(String::from("$tainted"), index);
+            result
+        });
+        assert_eq!(m.result().value("$tainted", m.source_ref()), Some("s"));
+        assert_eq!(m.display(1, 1, true), display);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,240 @@
+use rustc_hash::FxHashMap;
+use serde::Serialize;
+
+use crate::matcher::RuleMatcher;
+use crate::rule::RuleSet;
+
+// the number of buckets of `PrefilterStats::histogram`, each spanning a tenth
+// of the range of skip ratios
+pub const SKIP_RATIO_BUCKETS: usize = 10;
+
+// Counters per check over the sources of the current scanning call, keyed by
+// rule index and check index: the sources it was selected for, and those it
+// passed the prefilter for.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct PrefilterCounts {
+    checks: FxHashMap<(usize, usize), (usize, usize)>,
+}
+
+impl PrefilterCounts {
+    pub(crate) fn record_evaluated(&mut self, rule: usize, check: usize) {
+        self.checks.entry((rule, check)).or_default().0 += 1;
+    }
+
+    pub(crate) fn record_passed(&mut self, rule: usize, check: usize) {
+        self.checks.entry((rule, check)).or_default().1 += 1;
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.checks.clear();
+    }
+
+    fn report(&self, rules: &RuleSet) -> PrefilterStats {
+        let mut checks = rules
+            .iter()
+            .enumerate()
+            .flat_map(|(i, (key, rule))| {
+                rule.checks().iter().enumerate().map(move |(j, checker)| {
+                    let (evaluated, passed) = self.checks.get(&(i, j)).copied().unwrap_or_default();
+
+                    CheckPrefilterStats {
+                        rule_key: key.to_string(),
+                        rule: rule.id().to_owned(),
+                        check: checker.name().to_owned(),
+                        check_index: j,
+                        identifiers: checker.identifiers().to_vec(),
+                        evaluated,
+                        passed,
+                        skip_ratio: (evaluated > 0)
+                            .then(|| (evaluated - passed) as f64 / evaluated as f64),
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+
+        let mut histogram = [0; SKIP_RATIO_BUCKETS];
+        for ratio in checks.iter().filter_map(|c| c.skip_ratio) {
+            let bucket = (ratio * SKIP_RATIO_BUCKETS as f64) as usize;
+            histogram[bucket.min(SKIP_RATIO_BUCKETS - 1)] += 1;
+        }
+
+        // NOTE: the sort is stable, so ties stay in rule set order
+        checks.sort_by(|a, b| match (a.skip_ratio, b.skip_ratio) {
+            (Some(a), Some(b)) => a.total_cmp(&b),
+            (a, b) => b.is_some().cmp(&a.is_some()),
+        });
+
+        PrefilterStats { checks, histogram }
+    }
+}
+
+// How often the prefilter ruled out a check (see `RuleMatcher::prefilter_stats`).
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CheckPrefilterStats {
+    rule_key: String,
+    rule: String,
+    check: String,
+    check_index: usize,
+    identifiers: Vec<String>,
+    evaluated: usize,
+    passed: usize,
+    skip_ratio: Option<f64>,
+}
+
+impl CheckPrefilterStats {
+    pub fn rule_key(&self) -> &str {
+        &self.rule_key
+    }
+
+    pub fn rule(&self) -> &str {
+        &self.rule
+    }
+
+    pub fn check(&self) -> &str {
+        &self.check
+    }
+
+    pub fn check_index(&self) -> usize {
+        self.check_index
+    }
+
+    // the identifiers a source must contain for the check to pass
+    pub fn identifiers(&self) -> &[String] {
+        &self.identifiers
+    }
+
+    // sources the prefilter was evaluated on, i.e., those the rule was
+    // selected for
+    pub fn evaluated(&self) -> usize {
+        self.evaluated
+    }
+
+    // sources that contained all of the identifiers
+    pub fn passed(&self) -> usize {
+        self.passed
+    }
+
+    // the share of evaluated sources the check was skipped for, if any
+    pub fn skip_ratio(&self) -> Option<f64> {
+        self.skip_ratio
+    }
+}
+
+// The effectiveness of the prefilter over the most recent scanning call, with
+// an entry for each check of each rule, from the lowest skip ratio (i.e., the
+// checks it rarely rules out, such as those whose identifiers are as generic as
+// `len` or `buf`) to the highest; checks that were never evaluated come last.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct PrefilterStats {
+    checks: Vec<CheckPrefilterStats>,
+    histogram: [usize; SKIP_RATIO_BUCKETS],
+}
+
+impl PrefilterStats {
+    pub fn checks(&self) -> &[CheckPrefilterStats] {
+        &self.checks
+    }
+
+    // the number of evaluated checks by skip ratio: bucket `i` counts those
+    // from `i / 10` up to (but excluding) `(i + 1) / 10`, and the last one
+    // includes those that were always skipped
+    pub fn histogram(&self) -> &[usize; SKIP_RATIO_BUCKETS] {
+        &self.histogram
+    }
+
+    // the evaluated checks that were never skipped
+    pub fn never_skipped(&self) -> impl Iterator<Item = &CheckPrefilterStats> {
+        self.checks.iter().filter(|c| c.skip_ratio == Some(0.0))
+    }
+}
+
+impl RuleMatcher {
+    // the prefilter statistics of the most recent scanning call, which replace
+    // those of the previous one
+    pub fn prefilter_stats(&self) -> PrefilterStats {
+        self.prefilter_counts.report(&self.rules)
+    }
+}
+
+#[cfg(all(test, feature = "std-fs"))]
+mod test {
+    use std::fs;
+
+    use crate::matcher::RuleMatcher;
+
+    #[test]
+    fn test_prefilter_stats() -> Result<(), Box<dyn std::error::Error>> {
+        let rules = r#"
+id: copy
+check-patterns:
+- name: strcpy
+  pattern: '{ strcpy($dst, _); }'
+- name: generic
+  pattern: '{ $len = sizeof($buf); }'
+- name: never
+  pattern: '{ never_called_anywhere(); }'
+"#;
+
+        let mut matcher = RuleMatcher::from_str(rules)?;
+        assert!(matcher
+            .prefilter_stats()
+            .histogram()
+            .iter()
+            .all(|&n| n == 0));
+
+        let sources = [
+            "void f(char *d, char *s) { strcpy(d, s); }",
+            "int g(void) { return sizeof(int); }",
+            "int h(void) { return 0; }",
+            "void k(char *d) { strcpy(d, \"x\"); }",
+        ];
+
+        // the counters cover the sources of a single scanning call
+        for source in sources {
+            matcher.matches(source)?;
+        }
+        let stats = matcher.prefilter_stats();
+        assert!(stats.checks().iter().all(|c| c.evaluated() == 1));
+
+        let dir =
+            std::env::temp_dir().join(format!("weggli-ruleset-prefilter-{}", std::process::id()));
+        fs::create_dir_all(&dir)?;
+        let paths = sources
+            .iter()
+            .enumerate()
+            .map(|(i, source)| {
+                let path = dir.join(format!("{i}.c"));
+                fs::write(&path, source).map(|_| path)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        matcher.matches_files(&paths)?;
+        fs::remove_dir_all(&dir)?;
+
+        // without identifiers, the generic check is never skipped
+        let stats = matcher.prefilter_stats();
+        let order = stats
+            .checks()
+            .iter()
+            .map(|c| (c.check(), c.evaluated(), c.passed(), c.skip_ratio()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            order,
+            [
+                ("generic", 4, 4, Some(0.0)),
+                ("strcpy", 4, 2, Some(0.5)),
+                ("never", 4, 0, Some(1.0)),
+            ]
+        );
+        assert!(stats.checks()[0].identifiers().is_empty());
+        assert_eq!(stats.checks()[2].identifiers(), ["never_called_anywhere"]);
+        assert_eq!(stats.never_skipped().count(), 1);
+        assert_eq!(stats.histogram(), &[1, 0, 0, 0, 0, 1, 0, 0, 0, 1]);
+
+        let value = serde_json::to_value(&stats)?;
+        assert_eq!(value["checks"][1]["skip_ratio"], 0.5);
+        assert_eq!(value["histogram"][9], 1);
+
+        Ok(())
+    }
+}
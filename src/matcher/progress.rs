@@ -0,0 +1,56 @@
+use std::io;
+use std::path::Path;
+use std::time::Duration;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum SkipReason {
+    Unreadable(io::ErrorKind),
+    InvalidUtf8,
+    // by the ignore patterns of a directory scan (see `WalkOptions`)
+    Ignored,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum ScanEvent<'a> {
+    FileStarted(&'a Path),
+    FileFinished {
+        path: &'a Path,
+        matches: usize,
+        elapsed: Duration,
+    },
+    Skipped {
+        path: &'a Path,
+        reason: SkipReason,
+    },
+    Done {
+        files: usize,
+        matches: usize,
+    },
+}
+
+// Observer for directory and batch scans.
+//
+// NOTE: events are delivered from the thread that processed the file, which
+// for the scanning APIs of `RuleMatcher` is the calling thread; implementors
+// only get `&self`, so any state should live behind a `Mutex` or atomics.
+pub trait ScanProgress {
+    fn event(&self, event: ScanEvent<'_>);
+}
+
+impl<F> ScanProgress for F
+where
+    F: Fn(ScanEvent<'_>),
+{
+    fn event(&self, event: ScanEvent<'_>) {
+        self(event)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NoProgress;
+
+impl ScanProgress for NoProgress {
+    #[inline(always)]
+    fn event(&self, _event: ScanEvent<'_>) {}
+}
@@ -0,0 +1,158 @@
+use std::ops::Range;
+
+use tree_sitter::Tree;
+
+use crate::matcher::RuleMatch;
+use crate::rule::Checker;
+
+// Which matches `matches_in_ranges` keeps: by default, those starting on a
+// line within the ranges; with `Overlap`, those spanning any such line.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum RangeMode {
+    #[default]
+    Start,
+    Overlap,
+}
+
+impl RangeMode {
+    // NOTE: lines are those of the source as given, before normalization
+    pub(crate) fn contains(self, ranges: &[Range<usize>], m: &RuleMatch) -> bool {
+        let start = m.start_line_original();
+
+        match self {
+            Self::Start => ranges.iter().any(|r| r.contains(&start)),
+            Self::Overlap => {
+                let end = m.end_line_original();
+                ranges.iter().any(|r| r.start <= end && start < r.end)
+            }
+        }
+    }
+}
+
+// The byte ranges of the top-level nodes (e.g., function definitions) that
+// span a line within `ranges`: a match on such a line lies within one of them,
+// and so do the identifiers of its pattern.
+//
+// NOTE: weggli never matches the translation unit itself
+pub(crate) fn window(tree: &Tree, ranges: &[Range<usize>]) -> Vec<Range<usize>> {
+    let root = tree.root_node();
+    let mut cursor = root.walk();
+
+    root.children(&mut cursor)
+        .filter(|node| {
+            let start = node.start_position().row + 1;
+            let end = node.end_position().row + 1;
+            ranges.iter().any(|r| r.start <= end && start < r.end)
+        })
+        .map(|node| node.byte_range())
+        .collect()
+}
+
+// whether the checker may match within the window, i.e., whether all of its
+// identifiers occur in one of its nodes
+pub(crate) fn can_match_within(checker: &Checker, source: &str, window: &[Range<usize>]) -> bool {
+    window.iter().any(|r| {
+        source
+            .get(r.clone())
+            .is_some_and(|text| checker.can_match(text))
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use crate::matcher::{RangeMode, RuleMatcher};
+    use crate::rule::Language;
+
+    const RULE: &str = r#"
+id: copy
+check-patterns:
+- name: strcpy
+  pattern: '{ strcpy($dst, _); }'
+- name: memcpy
+  pattern: '{ memcpy($dst, _, _); }'
+  unique-by: [dst]
+"#;
+
+    #[test]
+    fn test_matches_in_ranges() -> Result<(), Box<dyn std::error::Error>> {
+        let files = [
+            "tests/utf8-comments.c",
+            "tests/edge-copy.c",
+            "tests/objdump-disas.c",
+        ];
+
+        for path in files {
+            let source = std::fs::read_to_string(path)?;
+            let lines = source.lines().count() + 1;
+
+            let mut matcher = RuleMatcher::from_str(RULE)?;
+            let all = matcher.matches_lang(&source, Language::C)?;
+
+            // the lines around each finding, chunks of the file, and ranges
+            // spanning several functions
+            let mut ranges = all
+                .iter()
+                .flat_map(|m| m.start_line() - 1..m.end_line() + 2)
+                .map(|l| l..l + 1)
+                .chain((1..lines).step_by(200).map(|l| l..l + 200))
+                .map(|r| vec![r; 1])
+                .collect::<Vec<_>>();
+            ranges.push(vec![1..3, 7..12, lines / 2..lines]);
+            ranges.push(Vec::new());
+
+            for mode in [RangeMode::Start, RangeMode::Overlap] {
+                matcher.set_range_mode(mode);
+
+                for r in &ranges {
+                    let expected = all
+                        .iter()
+                        .filter(|m| mode.contains(r, m))
+                        .map(|m| (m.start_offset(), m.checker_id(), m.fingerprint()))
+                        .collect::<Vec<_>>();
+
+                    let found = matcher.matches_in_ranges(&source, Language::C, r)?;
+                    let found = found
+                        .iter()
+                        .map(|m| (m.start_offset(), m.checker_id(), m.fingerprint()))
+                        .collect::<Vec<_>>();
+
+                    assert_eq!(found, expected, "{path}: {r:?} ({mode:?})");
+                }
+            }
+        }
+
+        // a multi-line match overlaps a range that does not contain its start
+        let source = "void f(char *d, char *s) {\n    strcpy(\n        d, s);\n}\n";
+        let mut matcher = RuleMatcher::from_str(RULE)?;
+        assert!(matcher
+            .matches_in_ranges(source, Language::C, &[3..4; 1])?
+            .is_empty());
+        matcher.set_range_mode(RangeMode::Overlap);
+        assert_eq!(matcher.range_mode(), RangeMode::Overlap);
+        assert_eq!(
+            matcher
+                .matches_in_ranges(source, Language::C, &[3..4; 1])?
+                .len(),
+            1
+        );
+
+        // conditions see the checks that matched anywhere in the source
+        let rule = r#"
+id: unchecked-alloc
+check-patterns:
+- name: alloc
+  pattern: '{ $p = malloc(_); }'
+- name: guarded
+  pattern: '{ if (!$p) return; }'
+condition: alloc and not guarded
+"#;
+        let source = "void f(int *p) {\n    if (!p) return;\n}\n\nvoid g(void) {\n    int *p;\n    p = malloc(4);\n}\n";
+        let mut matcher = RuleMatcher::from_str(rule)?;
+        assert!(matcher.matches(source)?.is_empty());
+        assert!(matcher
+            .matches_in_ranges(source, Language::C, &[5..9; 1])?
+            .is_empty());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,159 @@
+use std::io::Read;
+
+use crate::matcher::{DecodePolicy, RuleMatch, RuleMatcher, RuleMatcherError};
+use crate::rule::Language;
+
+// the default maximum size of sources read by `RuleMatcher::matches_reader`
+pub const READ_LIMIT: usize = 64 * 1024 * 1024;
+
+// Limits on reading sources (see `RuleMatcher::matches_reader`): at most
+// `max_bytes` are read, and the bytes are decoded according to a
+// `DecodePolicy` (by default, `READ_LIMIT` and `Strict`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ReadLimits {
+    max_bytes: usize,
+    decode: DecodePolicy,
+}
+
+impl Default for ReadLimits {
+    fn default() -> Self {
+        Self {
+            max_bytes: READ_LIMIT,
+            decode: DecodePolicy::default(),
+        }
+    }
+}
+
+impl ReadLimits {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn max_bytes(mut self, max_bytes: usize) -> Self {
+        self.max_bytes = max_bytes;
+        self
+    }
+
+    pub fn decode(mut self, policy: DecodePolicy) -> Self {
+        self.decode = policy;
+        self
+    }
+
+    pub fn max_bytes_limit(&self) -> usize {
+        self.max_bytes
+    }
+
+    pub fn decode_policy(&self) -> DecodePolicy {
+        self.decode
+    }
+}
+
+impl RuleMatcher {
+    // reads a source from `reader` (e.g., stdin, a socket, or an entry of an
+    // archive) until its end, then matches it as `matches_bytes` does; the
+    // matches have no origin
+    //
+    // NOTE: reading stops as soon as the source exceeds the limit, which is
+    // an error (`RuleMatcherError::TooLarge`)
+    pub fn matches_reader(
+        &mut self,
+        reader: impl Read,
+        language: Language,
+        limits: ReadLimits,
+    ) -> Result<Vec<RuleMatch>, RuleMatcherError> {
+        let mut bytes = Vec::new();
+
+        reader
+            .take((limits.max_bytes as u64).saturating_add(1))
+            .read_to_end(&mut bytes)
+            .map_err(RuleMatcherError::Read)?;
+
+        if bytes.len() > limits.max_bytes {
+            return Err(RuleMatcherError::TooLarge(limits.max_bytes));
+        }
+
+        self.matches_bytes(bytes, language, limits.decode)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::{self, Read};
+
+    use super::ReadLimits;
+    use crate::matcher::{DecodePolicy, RuleMatcher, RuleMatcherError};
+    use crate::rule::Language;
+
+    // yields its data a few bytes at a time, as pipes may
+    struct Chunked<'a> {
+        data: &'a [u8],
+        chunk: usize,
+    }
+
+    impl Read for Chunked<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let n = self.chunk.min(buf.len()).min(self.data.len());
+            buf[..n].copy_from_slice(&self.data[..n]);
+            self.data = &self.data[n..];
+            Ok(n)
+        }
+    }
+
+    #[test]
+    fn test_matches_reader() -> Result<(), Box<dyn std::error::Error>> {
+        let rule = r#"
+id: unbounded-copy
+check pattern:
+  regex: func=^strcpy$
+  pattern: |
+    { $func($dst, _); }
+"#;
+
+        let mut matcher = RuleMatcher::from_str(rule)?;
+        let source = std::fs::read("tests/utf8-comments.c")?;
+
+        // multi-byte characters are split across chunks
+        let chunked = Chunked {
+            data: &source,
+            chunk: 3,
+        };
+        let matches = matcher.matches_reader(chunked, Language::C, ReadLimits::new())?;
+        let expected = matcher.matches_file("tests/utf8-comments.c")?;
+
+        assert_eq!(matches.len(), expected.len());
+        assert_eq!(matches[0].source_ref().as_bytes(), source.as_slice());
+        assert!(matches.iter().all(|m| m.origin().is_none()));
+
+        // sources of exactly the limit are read, larger ones are not
+        let limits = ReadLimits::new().max_bytes(source.len());
+        assert!(matcher
+            .matches_reader(source.as_slice(), Language::C, limits)
+            .is_ok());
+
+        let limits = ReadLimits::new().max_bytes(source.len() - 1);
+        assert!(matches!(
+            matcher.matches_reader(source.as_slice(), Language::C, limits),
+            Err(RuleMatcherError::TooLarge(n)) if n == source.len() - 1
+        ));
+
+        // the decoding policy applies
+        let latin1 = std::fs::read("tests/latin1-comments.c")?;
+        assert!(matches!(
+            matcher.matches_reader(latin1.as_slice(), Language::C, ReadLimits::new()),
+            Err(RuleMatcherError::Utf8(_))
+        ));
+
+        let matches = matcher.matches_reader(
+            latin1.as_slice(),
+            Language::C,
+            ReadLimits::new().decode(DecodePolicy::Latin1),
+        )?;
+
+        assert!(!matches.is_empty());
+        assert!(matches
+            .iter()
+            .all(|m| m.conversion() == Some(DecodePolicy::Latin1)));
+
+        Ok(())
+    }
+}
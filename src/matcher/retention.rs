@@ -0,0 +1,224 @@
+use std::sync::Arc;
+
+use weggli::result::{CaptureResult, QueryResult};
+
+use crate::location;
+use crate::matcher::{match_span, RuleMatch};
+use crate::reporting::SourceBase;
+
+// How much of the scanned source the matches found in it retain (see
+// `RuleMatcher::set_source_retention`).
+//
+// `Full` shares the source among the matches of a scan. `Snippet` keeps only
+// the lines of each match and its variables, with `before` and `after` lines
+// of context, e.g., for very large sources (see `RuleMatcher::set_mapped_input`)
+// or long-running scans that accumulate matches; offsets, lines, and columns
+// still refer to the scanned source (see `RuleMatch::source_base`).
+//
+// NOTE: matches of sources that were preprocessed (e.g., normalized) retain
+// the full source, as their offsets map back to the input
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum SourceRetention {
+    #[default]
+    Full,
+    Snippet {
+        before: usize,
+        after: usize,
+    },
+}
+
+impl RuleMatch {
+    // the match with its source replaced by the lines of the match and its
+    // variables, and `before` and `after` lines of context (as far as the
+    // source extends, e.g., if it is itself a snippet); the outermost node of
+    // the result is clipped to them
+    pub fn into_snippet(self, before: usize, after: usize) -> Self {
+        let source = self.source.clone();
+        self.into_snippet_of(&source, before, after)
+    }
+
+    // as `into_snippet`, with `source` in place of that of the match, which
+    // it need not hold yet (e.g., when matching a source borrowed from the
+    // caller)
+    pub(crate) fn into_snippet_of(self, source: &str, before: usize, after: usize) -> Self {
+        if self.original.is_some() {
+            return self;
+        }
+
+        let covered = self
+            .result
+            .captures
+            .iter()
+            .skip(1)
+            .map(|c| c.range.clone())
+            .reduce(|a, b| a.start.min(b.start)..a.end.max(b.end))
+            .unwrap_or_else(|| match_span(&self.result));
+        let span = location::line_span(source, covered, before, after);
+
+        let (line, _) = location::line_column(source, span.start);
+        let previous = self.base.unwrap_or(SourceBase::new(0, 0, 1));
+
+        let captures = self
+            .result
+            .captures
+            .iter()
+            .map(|c| {
+                let clamp = |offset: usize| offset.clamp(span.start, span.end) - span.start;
+                CaptureResult {
+                    range: clamp(c.range.start)..clamp(c.range.end),
+                    ..c.clone()
+                }
+            })
+            .collect::<Vec<_>>();
+        let function = captures.first().map(|c| c.range.clone()).unwrap_or(0..0);
+
+        Self {
+            base: Some(SourceBase::new(
+                previous.offset() + span.start,
+                previous.char_offset() + location::char_offset(source, span.start),
+                previous.line() + line - 1,
+            )),
+            source: Arc::from(&source[span]),
+            result: QueryResult::new(captures, self.result.vars.clone(), function),
+            ..self
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use crate::matcher::{RuleMatcher, SourceRetention, SuppressionMode};
+    use crate::reporting::RuleMatchReport;
+
+    const SOURCE: &str = r#"int first(void) { return 0; }
+
+void copy(char *dst, const char *src)
+{
+  // one
+  // two
+  strcpy(dst,
+         src);
+  // three
+}
+
+int last(void) { return 1; }
+"#;
+
+    #[test]
+    fn test_snippet_retention() -> Result<(), Box<dyn std::error::Error>> {
+        let rule = r#"
+id: copy
+check-pattern:
+  pattern: '{ strcpy($dst, $src); }'
+"#;
+
+        let mut matcher = RuleMatcher::from_str(rule)?;
+        let full = matcher.matches(SOURCE)?;
+
+        matcher.set_source_retention(SourceRetention::Snippet {
+            before: 1,
+            after: 1,
+        });
+        assert_eq!(
+            matcher.source_retention(),
+            SourceRetention::Snippet {
+                before: 1,
+                after: 1
+            }
+        );
+        let found = matcher.matches(SOURCE)?;
+        let (full, snippet) = (&full[0], &found[0]);
+
+        // the snippet spans the match with a line of context...
+        assert_eq!(
+            snippet.source_ref(),
+            "  // two\n  strcpy(dst,\n         src);\n  // three"
+        );
+        let base = snippet.source_base().unwrap();
+        assert_eq!(
+            (base.line(), base.offset()),
+            (6, SOURCE.find("  // two").unwrap())
+        );
+        assert!(full.source_base().is_none());
+
+        // ...and positions are those in the source
+        assert_eq!(snippet.matched_text(), full.matched_text());
+        assert_eq!(
+            (snippet.start_offset(), snippet.end_offset()),
+            (full.start_offset(), full.end_offset())
+        );
+        assert_eq!(
+            (snippet.start_line(), snippet.start_column()),
+            (full.start_line(), full.start_column())
+        );
+        assert_eq!(
+            (snippet.end_line(), snippet.end_column()),
+            (full.end_line(), full.end_column())
+        );
+        assert_eq!(snippet.start_line_original(), 7);
+        assert_eq!(snippet.char_start(), full.char_start());
+        assert_eq!(snippet.capture("$src"), Some("src"));
+        assert_eq!(snippet.fingerprint(), full.fingerprint());
+
+        // reports number lines as in the source
+        let report = RuleMatchReport::new(snippet);
+        assert_eq!(report.start_line(), 7);
+        assert_eq!(report.source_base(), snippet.source_base());
+
+        // snippets of snippets are narrower still
+        let narrower = found.into_iter().next().unwrap().into_snippet(0, 0);
+        assert_eq!(narrower.source_ref(), "  strcpy(dst,\n         src);");
+        assert_eq!(narrower.source_base().unwrap().line(), 7);
+        assert_eq!(narrower.start_offset(), full.start_offset());
+
+        // the full source is retained by default
+        assert_eq!(full.source_ref(), SOURCE);
+        assert_eq!(full.start_line(), 7);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_snippet_retention_without_source() -> Result<(), Box<dyn std::error::Error>> {
+        let rule = r#"
+id: copy
+check-pattern:
+  pattern: '{ strcpy($dst, $src); }'
+"#;
+        let source = format!(
+            "{SOURCE}\nvoid again(char *d, char *s)\n{{\n  strcpy(d, s); // weggli-ignore\n}}\n"
+        );
+
+        let mut matcher = RuleMatcher::from_str(rule)?;
+        matcher.set_suppression_mode(SuppressionMode::Retain);
+        let full = matcher.matches(&source)?;
+        assert_eq!(full.len(), 2);
+
+        // the matches share a copy of the full source...
+        assert!(full.iter().all(|m| m.source_ref() == source));
+        assert!(Arc::ptr_eq(&full[0].source(), &full[1].source()));
+
+        // ...while snippets are all that is kept of it, each on its own
+        matcher.set_source_retention(SourceRetention::Snippet {
+            before: 0,
+            after: 0,
+        });
+        let found = matcher.matches(&source)?;
+        assert_eq!(found.len(), 2);
+        assert_eq!(found[0].source_ref(), "  strcpy(dst,\n         src);");
+        assert_eq!(found[1].source_ref(), "  strcpy(d, s); // weggli-ignore");
+        assert!(found.iter().all(|m| Arc::strong_count(&m.source()) == 2));
+
+        // fingerprints and suppressions are still those of the full source
+        for (snippet, full) in found.iter().zip(&full) {
+            assert_eq!(snippet.fingerprint(), full.fingerprint());
+            assert_eq!(snippet.suppression(), full.suppression());
+            assert_eq!(snippet.start_line(), full.start_line());
+        }
+        assert!(found[1].is_suppressed());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,374 @@
+use std::borrow::Cow;
+use std::ops::Range;
+use std::sync::Arc;
+
+use tree_sitter::Tree;
+
+use crate::matcher::{RuleMatch, RuleMatcher, RuleMatcherError, SourceContext};
+use crate::preprocess::comments;
+use crate::rule::Language;
+
+// The part of a larger input a match was found in, when the input was split
+// into functions (see `RuleMatcher::matches_split_functions`): a function
+// definition, along with the text preceding it since the end of the previous
+// one, or the catch-all segment.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Segment {
+    function: Option<String>,
+    address: Option<String>,
+    offset: usize,
+    line: usize,
+}
+
+impl Segment {
+    // the name of the function, or `None` for the catch-all segment
+    pub fn function(&self) -> Option<&str> {
+        self.function.as_deref()
+    }
+
+    // the address in the comment preceding the function, as written by
+    // Hex-Rays (e.g., `0000000000401040` for `//----- (0000000000401040) ---`)
+    pub fn address(&self) -> Option<&str> {
+        self.address.as_deref()
+    }
+
+    // the byte offset of the segment within the input
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    // the (1-based) line of the input the segment starts on
+    pub fn line(&self) -> usize {
+        self.line
+    }
+
+    fn catch_all() -> Self {
+        Self {
+            function: None,
+            address: None,
+            offset: 0,
+            line: 1,
+        }
+    }
+}
+
+impl RuleMatch {
+    pub fn segment(&self) -> Option<&Segment> {
+        self.segment.as_deref()
+    }
+
+    // NOTE: the `*_global` accessors mirror the `*_original` ones, but refer
+    // to the input the segment was split from, if any
+    pub fn start_offset_global(&self) -> usize {
+        self.segment().map_or(0, Segment::offset) + self.start_offset_original()
+    }
+
+    pub fn end_offset_global(&self) -> usize {
+        self.segment().map_or(0, Segment::offset) + self.end_offset_original()
+    }
+
+    pub fn start_line_global(&self) -> usize {
+        self.segment().map_or(1, Segment::line) + self.start_line_original() - 1
+    }
+
+    pub fn end_line_global(&self) -> usize {
+        self.segment().map_or(1, Segment::line) + self.end_line_original() - 1
+    }
+}
+
+// a function definition of the input, and the text preceding it
+struct Piece {
+    range: Range<usize>,
+    // the end of the header, i.e., the start of the body
+    body: usize,
+    function: Option<String>,
+}
+
+impl RuleMatcher {
+    // Matches a concatenation of many functions (e.g., the output of "decompile
+    // all" in Hex-Rays) one function at a time, rather than as a single
+    // source: the input is split after each top-level function definition,
+    // and each segment is matched on its own, so that findings carry the name
+    // (and address) of their function (see `RuleMatch::segment`) and parsing
+    // a segment stays cheap. Segments that do not parse on their own, and the
+    // text after the last function, are matched as a catch-all segment, in
+    // which the functions that did parse are blanked out.
+    //
+    // NOTE: offsets and lines are relative to the segment; the `*_global`
+    // accessors of `RuleMatch` are relative to the input. Matches are unique
+    // (see `unique-by`) within each segment
+    pub fn matches_split_functions(
+        &mut self,
+        source: impl AsRef<str>,
+        language: Language,
+    ) -> Result<Vec<RuleMatch>, RuleMatcherError> {
+        let source = source.as_ref();
+
+        self.scan(|matcher| {
+            let mut results = Vec::new();
+            let mut matched = Vec::new();
+
+            for piece in split_functions(source) {
+                let text = &source[piece.range.clone()];
+
+                let segment = Segment {
+                    function: piece.function,
+                    address: address(&source[piece.range.start..piece.body]),
+                    offset: piece.range.start,
+                    line: source[..piece.range.start].matches('\n').count() + 1,
+                };
+                let mut context = SourceContext {
+                    language,
+                    segment: Some(Arc::new(segment)),
+                    ..Default::default()
+                };
+
+                // NOTE: segments are only parsed here if a check may match,
+                // and their tree is reused for matching
+                if !matcher
+                    .viable_checkers(&matcher.rules.clone(), text, &context)
+                    .is_empty()
+                {
+                    match matcher.parse(text, language) {
+                        Some(tree) if parses_as_function(&tree) => context.tree = Some(tree),
+                        _ => continue,
+                    }
+                }
+
+                results.extend(matcher.matches_source(text, context)?);
+                matched.push(piece.range);
+            }
+
+            if let Some(rest) = catch_all(source, &matched) {
+                let context = SourceContext {
+                    language,
+                    segment: Some(Arc::new(Segment::catch_all())),
+                    ..Default::default()
+                };
+                results.extend(matcher.matches_source(&rest, context)?);
+            }
+
+            Ok(results)
+        })
+    }
+}
+
+// whether the segment parsed as a function definition, possibly preceded by
+// declarations
+//
+// NOTE: decompiler output rarely parses without errors (e.g., Hex-Rays'
+// `__noreturn`, or `char *__fastcall f()`), so only errors at the top level
+// count, e.g., when the end of a header is lost
+fn parses_as_function(tree: &Tree) -> bool {
+    let root = tree.root_node();
+    let mut cursor = root.walk();
+    let mut children = root.children(&mut cursor);
+
+    let mut function = false;
+    children.all(|node| {
+        function |= node.kind() == "function_definition";
+        !node.is_error()
+    }) && function
+}
+
+// the input with the `matched` ranges blanked out (retaining newlines, so that
+// offsets and lines are those of the input), unless only comments and
+// whitespace remain
+fn catch_all(source: &str, matched: &[Range<usize>]) -> Option<String> {
+    let mut rest = source.as_bytes().to_vec();
+    for range in matched {
+        for byte in &mut rest[range.clone()] {
+            if *byte != b'\n' {
+                *byte = b' ';
+            }
+        }
+    }
+
+    // NOTE: blanking whole segments keeps the rest valid UTF-8
+    let rest = String::from_utf8(rest).ok()?;
+    let code = comments::blank_comments(&rest).unwrap_or(Cow::Borrowed(&rest));
+    (!code.trim().is_empty()).then_some(rest)
+}
+
+// Splits the input after each top-level function definition, i.e., a block at
+// depth zero whose header has a parameter list and no initializer, and which
+// is not a type (or namespace) definition.
+//
+// NOTE: as `comments::blank_comments`, this is not a C lexer: braces are
+// counted outside of comments and literals only, and preprocessor
+// conditionals that open a block twice leave it unbalanced, in which case the
+// remaining text is left to the catch-all segment
+fn split_functions(source: &str) -> Vec<Piece> {
+    let code = comments::blank_comments(source).unwrap_or(Cow::Borrowed(source));
+    let bytes = code.as_bytes();
+
+    let mut pieces = Vec::new();
+    let mut start = 0;
+    // the end of the last declaration (or block) at depth zero
+    let mut declaration = 0;
+    let mut header = None;
+    let mut depth = 0usize;
+
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            quote @ (b'"' | b'\'') => {
+                i += 1;
+                while i < bytes.len() && bytes[i] != quote && bytes[i] != b'\n' {
+                    i += if bytes[i] == b'\\' { 2 } else { 1 };
+                }
+            }
+            b'{' => {
+                if depth == 0 {
+                    header = Some(declaration..i);
+                }
+                depth += 1;
+            }
+            b'}' if depth > 0 => {
+                depth -= 1;
+                if depth == 0 {
+                    if let Some(header) = header.take().filter(|h| is_function(&code[h.clone()])) {
+                        // NOTE: comments (e.g., Hex-Rays' address comments)
+                        // are blanked in `code`
+                        // up to the end of the line
+                        let end =
+                            memchr::memchr(b'\n', &bytes[i..]).map_or(bytes.len(), |n| i + n + 1);
+                        pieces.push(Piece {
+                            range: start..end,
+                            body: header.end,
+                            function: function_name(&code[header]),
+                        });
+                        start = end;
+                    }
+                    declaration = i + 1;
+                }
+            }
+            b';' if depth == 0 => declaration = i + 1,
+            _ => {}
+        }
+        i += 1;
+    }
+
+    pieces
+}
+
+// the lines of a header that are not preprocessor directives
+fn declarator(header: &str) -> String {
+    header
+        .lines()
+        .filter(|line| !line.trim_start().starts_with('#'))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn is_function(header: &str) -> bool {
+    let header = declarator(header);
+    let first = header.split_whitespace().next().unwrap_or_default();
+
+    header.contains('(')
+        && !header.contains('=')
+        && !matches!(
+            first,
+            "struct" | "union" | "enum" | "typedef" | "class" | "namespace" | "extern"
+        )
+}
+
+// e.g., `copy_name` for `char *__fastcall copy_name(char *dst, ...)`, or
+// `Foo::bar` for `void Foo::bar() const`; Hex-Rays' location annotations (as
+// in `copy_name@<eax>`) are dropped
+fn function_name(header: &str) -> Option<String> {
+    let header = declarator(header);
+    let (before, _) = header.split_once('(')?;
+    let before = before.trim_end();
+    let before = match before.rfind("@<") {
+        Some(i) if before.ends_with('>') => before[..i].trim_end(),
+        _ => before,
+    };
+
+    let start = before
+        .rfind(|c: char| !(c.is_alphanumeric() || matches!(c, '_' | ':' | '~')))
+        .map_or(0, |i| i + 1);
+    let name = &before[start..];
+
+    (!name.is_empty()).then(|| name.to_owned())
+}
+
+// the address in the last Hex-Rays function comment of `text`, if any
+fn address(text: &str) -> Option<String> {
+    let (_, rest) = text.rsplit_once("//----- (")?;
+    let (address, _) = rest.split_once(')')?;
+
+    address
+        .chars()
+        .all(|c| c.is_ascii_hexdigit())
+        .then(|| address.to_owned())
+}
+
+#[cfg(test)]
+mod test {
+    use super::{function_name, split_functions};
+    use crate::matcher::RuleMatcher;
+    use crate::rule::Language;
+
+    #[test]
+    fn test_split_functions() {
+        let source = "struct s { int a; };\nint t[] = { 1, 2 };\nint f(void)\n{\n  return '}';\n}\n\n#define X(a) (a)\nvoid Foo::bar() const { if (x) { g(\"{\"); } }\nint trailing;\n";
+
+        let names = split_functions(source)
+            .into_iter()
+            .map(|p| p.function)
+            .collect::<Vec<_>>();
+        assert_eq!(names, [Some("f".to_owned()), Some("Foo::bar".to_owned())]);
+
+        assert_eq!(
+            function_name("int __usercall copy_name@<eax>(char *dst@<edi>)"),
+            Some("copy_name".to_owned())
+        );
+    }
+
+    #[test]
+    fn test_matches_split_functions() -> Result<(), Box<dyn std::error::Error>> {
+        let rule = "id: copy\ncheck-pattern:\n  pattern: '{ strcpy($dst, _); }'\n";
+        let source = std::fs::read_to_string("tests/hexrays-dump.c")?;
+
+        let mut matcher = RuleMatcher::from_str(rule)?;
+        let whole = matcher.matches_lang(&source, Language::C)?;
+        assert_eq!(whole.len(), 1);
+
+        // the match of the middle function, attributed to it
+        let split = matcher.matches_split_functions(&source, Language::C)?;
+        assert_eq!(split.len(), 1);
+
+        let m = &split[0];
+        let segment = m.segment().ok_or("no segment")?;
+        assert_eq!(segment.function(), Some("copy_name"));
+        assert_eq!(segment.address(), Some("0000000000401040"));
+        assert_eq!(m.matched_text(), whole[0].matched_text());
+
+        // at the same location in the input, but relative to the function
+        assert_eq!(m.start_offset_global(), whole[0].start_offset());
+        assert_eq!(m.end_offset_global(), whole[0].end_offset());
+        assert_eq!(m.start_line_global(), whole[0].start_line());
+        assert!(m.start_line() < 15 && m.start_line() < m.start_line_global());
+
+        // three functions, of which only one may match, and no catch-all
+        assert_eq!(matcher.last_metrics().files(), 3);
+        assert_eq!(matcher.last_metrics().parsed(), 1);
+
+        // a function that does not parse on its own (e.g., after a stray
+        // brace) is matched in the catch-all segment, at its location in the
+        // input
+        let broken = source.replace(
+            "char *__fastcall copy_name(",
+            "} char *__fastcall copy_name(",
+        );
+        let whole = matcher.matches_lang(&broken, Language::C)?;
+        let split = matcher.matches_split_functions(&broken, Language::C)?;
+        assert_eq!((whole.len(), split.len()), (1, 1));
+        assert_eq!(split[0].segment().and_then(|s| s.function()), None);
+        assert_eq!(split[0].start_offset(), whole[0].start_offset());
+        assert_eq!(split[0].start_line_global(), whole[0].start_line());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,192 @@
+use std::fmt::Display;
+
+use serde::{Deserialize, Serialize};
+
+use crate::location;
+
+// the marker of suppression comments, e.g., `// weggli-ignore` (any rule) or
+// `/* weggli-ignore: unbounded-copy, local-buffer */` (the rules listed)
+pub const SUPPRESSION_MARKER: &str = "weggli-ignore";
+
+// Why a finding was suppressed: a comment in the source (on the line of the
+// match, or on its own on the line before), an entry of a baseline of known
+// findings, the configuration of the scan, or a suppression of the same
+// finding in earlier SARIF results (e.g., a dismissal in code scanning).
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum SuppressionReason {
+    Comment {
+        text: String,
+        line: usize,
+    },
+    Baseline {
+        fingerprint: String,
+    },
+    Config {
+        note: String,
+    },
+    Sarif {
+        fingerprint: String,
+        justification: String,
+    },
+}
+
+impl Display for SuppressionReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Comment { text, line } => {
+                write!(f, "suppressed by a comment on line {line}: {text}")
+            }
+            Self::Baseline { fingerprint } => {
+                write!(f, "suppressed by the baseline (fingerprint {fingerprint})")
+            }
+            Self::Config { note } if note.is_empty() => {
+                f.write_str("suppressed by the configuration")
+            }
+            Self::Config { note } => write!(f, "suppressed by the configuration: {note}"),
+            Self::Sarif {
+                fingerprint,
+                justification,
+            } if justification.is_empty() => {
+                write!(
+                    f,
+                    "suppressed in earlier results (fingerprint {fingerprint})"
+                )
+            }
+            Self::Sarif {
+                fingerprint,
+                justification,
+            } => write!(
+                f,
+                "suppressed in earlier results (fingerprint {fingerprint}): {justification}"
+            ),
+        }
+    }
+}
+
+// What the matcher does with findings suppressed by comments: by default,
+// comments are not looked for; otherwise, suppressed findings are dropped, or
+// retained with their `SuppressionReason` (e.g., for audits).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum SuppressionMode {
+    #[default]
+    Off,
+    Drop,
+    Retain,
+}
+
+// the suppression comment for a match of `rule` starting at `offset`, if any
+pub(crate) fn inline(source: &str, offset: usize, rule: &str) -> Option<SuppressionReason> {
+    let (line, _) = location::line_column(source, offset);
+    let span = location::line_span(source, offset..offset, 1, 0);
+    let mut lines = source[span].lines().rev();
+
+    let current = lines.next().unwrap_or_default();
+    if let Some(text) = comment(current, rule) {
+        return Some(SuppressionReason::Comment { text, line });
+    }
+
+    // a comment on the line before must be on its own
+    let previous = lines.next().filter(|_| line > 1)?;
+    let trimmed = previous.trim_start();
+    if !(trimmed.starts_with("//") || trimmed.starts_with("/*")) {
+        return None;
+    }
+
+    comment(previous, rule).map(|text| SuppressionReason::Comment {
+        text,
+        line: line - 1,
+    })
+}
+
+// the text of the suppression comment on `line` that applies to `rule`
+fn comment(line: &str, rule: &str) -> Option<String> {
+    let start = [line.find("//"), line.find("/*")]
+        .into_iter()
+        .flatten()
+        .min()?;
+    let comment = &line[start..];
+
+    let marker = comment.find(SUPPRESSION_MARKER)?;
+    let rest = &comment[marker + SUPPRESSION_MARKER.len()..];
+    let rest = rest.split("*/").next().unwrap_or_default();
+
+    let applies = match rest.chars().next() {
+        None => true,
+        Some(':') => {
+            let rules = rest[1..]
+                .split(|c: char| c == ',' || c.is_whitespace())
+                .filter(|id| !id.is_empty())
+                .collect::<Vec<_>>();
+            rules.is_empty() || rules.contains(&rule)
+        }
+        Some(c) if c.is_whitespace() => true,
+        // e.g., `weggli-ignored`
+        Some(_) => false,
+    };
+
+    applies.then(|| {
+        comment
+            .trim_end()
+            .trim_end_matches("*/")
+            .trim_end()
+            .to_owned()
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::{inline, SuppressionReason};
+
+    #[test]
+    fn test_inline_suppression() {
+        let source = "\
+void f(char *d, char *s) {
+    strcpy(d, s); // weggli-ignore
+    // weggli-ignore: unbounded-copy, other
+    strcpy(d, s);
+    /* weggli-ignore: other */
+    strcpy(d, s);
+    n = 0; // weggli-ignore
+    strcpy(d, s);
+    strcpy(d, s); // weggli-ignored
+}
+";
+        let offset = |line: usize| {
+            source
+                .split_inclusive('\n')
+                .take(line - 1)
+                .map(str::len)
+                .sum::<usize>()
+                + 4
+        };
+
+        assert_eq!(
+            inline(source, offset(2), "unbounded-copy"),
+            Some(SuppressionReason::Comment {
+                text: String::from("// weggli-ignore"),
+                line: 2
+            })
+        );
+        assert_eq!(
+            inline(source, offset(4), "unbounded-copy"),
+            Some(SuppressionReason::Comment {
+                text: String::from("// weggli-ignore: unbounded-copy, other"),
+                line: 3
+            })
+        );
+        // the comment lists other rules only
+        assert_eq!(inline(source, offset(6), "unbounded-copy"), None);
+        assert_eq!(
+            inline(source, offset(6), "other"),
+            Some(SuppressionReason::Comment {
+                text: String::from("/* weggli-ignore: other"),
+                line: 5
+            })
+        );
+        // the comment on the line before is not on its own
+        assert_eq!(inline(source, offset(8), "unbounded-copy"), None);
+        assert_eq!(inline(source, offset(9), "unbounded-copy"), None);
+        assert_eq!(inline(source, 0, "unbounded-copy"), None);
+    }
+}
@@ -0,0 +1,147 @@
+use rustc_hash::{FxHashMap, FxHashSet};
+
+use crate::matcher::{location_order, RuleMatch};
+use crate::rule::UniqueScope;
+
+// the values a match binds to the `unique-by` variables, in order
+type Values = Box<[Box<str>]>;
+
+// The values of the `unique-by` variables of the matches retained so far, by
+// rule and check index.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct SeenValues {
+    values: FxHashMap<(usize, usize), FxHashSet<Values>>,
+}
+
+impl SeenValues {
+    pub(crate) fn clear(&mut self) {
+        self.values.clear();
+    }
+
+    // whether the values are new for the check (and if so, records them)
+    fn insert(&mut self, rule_id: usize, checker_id: usize, values: Vec<&str>) -> bool {
+        self.values
+            .entry((rule_id, checker_id))
+            .or_default()
+            .insert(values.into_iter().map(Box::from).collect())
+    }
+}
+
+// Drops the matches of a source whose `unique-by` values repeat those of an
+// earlier match of the same check, in location order; `scan` holds the values
+// of earlier sources, for checks in `scan` scope.
+//
+// NOTE: suppressed matches are kept, and do not count as earlier matches
+pub(crate) fn retain(matches: &mut Vec<RuleMatch>, scan: &mut SeenValues) {
+    let mut order = (0..matches.len())
+        .filter(|i| {
+            let m = &matches[*i];
+            !m.is_suppressed()
+                && m.checker()
+                    .unique_by()
+                    .is_some_and(|by| by.scope() != UniqueScope::Match)
+        })
+        .collect::<Vec<_>>();
+
+    if order.is_empty() {
+        return;
+    }
+
+    order.sort_by(|a, b| location_order(&matches[*a], &matches[*b]));
+
+    let mut source = SeenValues::default();
+    let mut keep = vec![true; matches.len()];
+
+    for i in order {
+        let m = &matches[i];
+        let Some(by) = m.checker().unique_by() else {
+            continue;
+        };
+
+        let seen = match by.scope() {
+            UniqueScope::Scan => &mut *scan,
+            _ => &mut source,
+        };
+
+        keep[i] = seen.insert(
+            m.rule_id(),
+            m.checker_id(),
+            by.values(m.result(), m.source_ref()),
+        );
+    }
+
+    let mut keep = keep.into_iter();
+    matches.retain(|_| keep.next().unwrap_or(true));
+}
+
+#[cfg(test)]
+mod test {
+    use crate::matcher::RuleMatcher;
+    use crate::rule::{Rule, UniqueScope};
+
+    fn rule(unique_by: &str) -> String {
+        format!(
+            r#"
+id: banned-copy
+check-pattern:
+  regex: func=^st(r|p)cpy$
+  pattern: '{{ $func($dst, _); }}'
+  unique-by: {unique_by}
+"#
+        )
+    }
+
+    #[test]
+    fn test_unique_by() -> Result<(), Box<dyn std::error::Error>> {
+        let files = ["tests/utf8-comments.c", "tests/edge-copy.c"];
+
+        let mut matcher = RuleMatcher::from_str(rule("null"))?;
+        let all = matcher.matches_files(files)?;
+
+        // one finding per function and file, the first in the file
+        let mut matcher = RuleMatcher::from_str(rule("[func]"))?;
+        let checker = &matcher.rules().get_ref(0).unwrap().checks()[0];
+        assert_eq!(checker.unique_by().unwrap().variables(), ["$func"]);
+        assert_eq!(checker.unique_by().unwrap().scope(), UniqueScope::Source);
+
+        let matches = matcher.matches_file(files[0])?;
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].start_line(), 6);
+
+        let per_file = matcher.matches_files(files)?;
+        assert!(all.len() > per_file.len());
+        assert_eq!(per_file.len(), 2);
+
+        // ...or per scan, across files
+        let mut matcher = RuleMatcher::from_str(rule("{variables: [func], scope: scan}"))?;
+        let per_scan = matcher.matches_files(files)?;
+        assert_eq!(per_scan.len(), 1);
+        assert_eq!(per_scan[0].fingerprint(), per_file[0].fingerprint());
+
+        // each scanning call starts afresh
+        assert_eq!(matcher.matches_files(files)?.len(), 1);
+
+        // within a match, the values must differ
+        let mut matcher = RuleMatcher::from_str(
+            "id: x\ncheck-pattern:\n  pattern: '{ strcpy($dst, $src); }'\n  unique-by: {variables: [dst, $src], scope: match}\n",
+        )?;
+        let matches =
+            matcher.matches("void f(char *a, char *b) { strcpy(a, a); strcpy(a, b); }")?;
+        assert_eq!(matches.len(), 1);
+        assert!(matches[0].matched_text().starts_with("strcpy(a, b"));
+
+        // rules serialize with the option
+        let parsed = Rule::from_str(rule("[func]"))?;
+        let yaml = serde_yaml::to_string(&parsed)?;
+        assert!(yaml.contains("unique-by:"));
+        assert_eq!(
+            Rule::from_str(&yaml)?.checks()[0].unique_by(),
+            parsed.checks()[0].unique_by()
+        );
+
+        assert!(Rule::from_str(rule("[size]")).is_err());
+        assert!(Rule::from_str(rule("{variables: [func], scope: file}")).is_err());
+
+        Ok(())
+    }
+}
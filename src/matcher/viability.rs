@@ -0,0 +1,170 @@
+use std::borrow::Cow;
+
+use memchr::memmem;
+use serde::Serialize;
+
+use crate::matcher::RuleMatcher;
+
+// Why a check would (or would not) be run on a source: a check is viable when
+// all of its identifiers occur in the source, i.e., when `can_match` holds.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ViabilityEntry {
+    rule_key: String,
+    rule: String,
+    check: String,
+    check_index: usize,
+    viable: bool,
+    identifiers: Vec<String>,
+    found: Vec<String>,
+    missing: Vec<String>,
+}
+
+impl ViabilityEntry {
+    pub fn rule_key(&self) -> &str {
+        &self.rule_key
+    }
+
+    pub fn rule(&self) -> &str {
+        &self.rule
+    }
+
+    pub fn check(&self) -> &str {
+        &self.check
+    }
+
+    pub fn check_index(&self) -> usize {
+        self.check_index
+    }
+
+    pub fn viable(&self) -> bool {
+        self.viable
+    }
+
+    pub fn identifiers(&self) -> &[String] {
+        &self.identifiers
+    }
+
+    pub fn found(&self) -> &[String] {
+        &self.found
+    }
+
+    pub fn missing(&self) -> &[String] {
+        &self.missing
+    }
+}
+
+impl RuleMatcher {
+    // Lists the checks of the enabled rules that would be run on `source`,
+    // without parsing it; with `include_non_viable`, the checks that would be
+    // skipped are listed too, along with the identifiers they are missing.
+    //
+    // NOTE: the source is normalized and prefiltered as it would be for
+    // matching; path constraints are not considered, since a source has no
+    // origin. Entries are ordered by rule key, then by check index.
+    pub fn explain_viability(
+        &self,
+        source: impl AsRef<str>,
+        include_non_viable: bool,
+    ) -> Vec<ViabilityEntry> {
+        let source = source.as_ref();
+
+        let source = match self.normalizer {
+            Some(ref normalizer) => normalizer.normalize(source),
+            None => Cow::Borrowed(source),
+        };
+        let prefiltered = self.prefilter_source(&source);
+        let source = prefiltered.as_ref();
+
+        let mut entries = self
+            .rules
+            .iter()
+            .enumerate()
+            .filter(|(i, (_, rule))| self.filter.allows(*i, rule))
+            .flat_map(|(_, (key, rule))| {
+                rule.checks()
+                    .iter()
+                    .enumerate()
+                    .map(move |(check_index, checker)| {
+                        let (found, missing): (Vec<_>, Vec<_>) =
+                            checker.identifiers().iter().cloned().partition(|ident| {
+                                memmem::find(source.as_bytes(), ident.as_bytes()).is_some()
+                            });
+
+                        ViabilityEntry {
+                            rule_key: key.to_string(),
+                            rule: rule.id().to_owned(),
+                            check: checker.name().to_owned(),
+                            check_index,
+                            viable: missing.is_empty(),
+                            identifiers: checker.identifiers().to_vec(),
+                            found,
+                            missing,
+                        }
+                    })
+            })
+            .filter(|entry| include_non_viable || entry.viable)
+            .collect::<Vec<_>>();
+
+        entries.sort_by(|a, b| {
+            a.rule_key
+                .cmp(&b.rule_key)
+                .then_with(|| a.check_index.cmp(&b.check_index))
+        });
+
+        entries
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+
+    use crate::matcher::RuleMatcher;
+
+    #[test]
+    fn test_explain_viability() -> Result<(), Box<dyn std::error::Error>> {
+        let rules = r#"
+id: unbounded-copy
+check patterns:
+- name: strcpy
+  pattern: '{ strcpy($dst, _); }'
+- name: gets
+  pattern: '{ gets(_); }'
+- name: any
+  regex: func=^gets$
+  pattern: '{ $func(_); }'
+"#;
+
+        let matcher = RuleMatcher::from_str(rules)?;
+        let source = fs::read_to_string("tests/utf8-comments.c")?;
+
+        let viable = matcher.explain_viability(&source, false);
+
+        assert!(viable.iter().all(|entry| entry.viable()));
+        assert!(viable.iter().all(|entry| entry.missing().is_empty()));
+
+        let all = matcher.explain_viability(&source, true);
+
+        assert_eq!(viable.len(), 2);
+        assert_eq!(all.len(), 3);
+        assert!(all.windows(2).all(|w| {
+            (w[0].rule_key(), w[0].check_index()) <= (w[1].rule_key(), w[1].check_index())
+        }));
+
+        let gets = all
+            .iter()
+            .find(|entry| entry.rule() == "unbounded-copy" && entry.check() == "gets")
+            .unwrap();
+
+        assert!(!gets.viable());
+        assert_eq!(gets.missing(), ["gets"]);
+        assert!(gets.found().is_empty());
+
+        let value = serde_yaml::to_value(&all)?;
+
+        assert_eq!(value[0]["rule"].as_str(), Some(all[0].rule()));
+        assert!(value[0]["missing"].is_sequence());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,347 @@
+use std::path::{Path, PathBuf};
+
+#[cfg(not(feature = "gitignore"))]
+use globset::{GlobBuilder, GlobMatcher};
+#[cfg(feature = "gitignore")]
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+
+use crate::matcher::RuleMatcherError;
+
+// Options of directory scans (see `RuleMatcher::matches_directory_with`):
+// files and directories can be ignored by patterns in the syntax of
+// `.gitignore` files, relative to the root of the scan, and (with the
+// `gitignore` feature) by git's own ignore files.
+//
+// NOTE: ignored directories are not walked at all, and ignored files are not
+// read; both are reported as skipped (with `SkipReason::Ignored`)
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct WalkOptions {
+    respect_gitignore: bool,
+    ignore: Vec<String>,
+}
+
+impl WalkOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // honors, as git does, the `.gitignore` files of the root and of the
+    // directories above (up to the repository) and below it, the repository's
+    // `.git/info/exclude`, and the global excludes file (`core.excludesFile`);
+    // outside of a repository, git ignores nothing
+    #[cfg(feature = "gitignore")]
+    pub fn respect_gitignore(mut self, respect: bool) -> Self {
+        self.respect_gitignore = respect;
+        self
+    }
+
+    // e.g., `build/`, `third_party/**`, or `*.gen.c`; these apply on top of
+    // git's ignore files, and `!pattern` re-includes what they ignore (but not
+    // what git does)
+    pub fn ignore(mut self, pattern: impl Into<String>) -> Self {
+        self.ignore.push(pattern.into());
+        self
+    }
+
+    pub fn respects_gitignore(&self) -> bool {
+        self.respect_gitignore
+    }
+
+    pub fn ignored(&self) -> &[String] {
+        &self.ignore
+    }
+}
+
+// NOTE: without the `gitignore` feature, the patterns are matched by globs
+// of their own, which cover the syntax of `.gitignore` files but not every
+// corner of git's matching
+#[cfg(not(feature = "gitignore"))]
+struct IgnoreRule {
+    glob: GlobMatcher,
+    negated: bool,
+    directory_only: bool,
+}
+
+#[cfg(not(feature = "gitignore"))]
+impl IgnoreRule {
+    // `None` for blank lines and comments
+    fn parse(line: &str) -> Option<Result<Self, globset::Error>> {
+        let line = trim_trailing_spaces(line);
+        if line.is_empty() || line.starts_with('#') {
+            return None;
+        }
+
+        let (negated, line) = match line.strip_prefix('!') {
+            Some(line) => (true, line),
+            None => (false, line),
+        };
+
+        // e.g., `\#file` or `\!file`
+        let line = line.strip_prefix('\\').unwrap_or(line);
+
+        let (directory_only, line) = match line.strip_suffix('/') {
+            Some(line) => (true, line),
+            None => (false, line),
+        };
+
+        if line.is_empty() {
+            return None;
+        }
+
+        // patterns with a separator are relative to their base, others match
+        // at any depth
+        let pattern = if line.contains('/') {
+            line.trim_start_matches('/').to_owned()
+        } else {
+            format!("**/{line}")
+        };
+
+        Some(
+            GlobBuilder::new(&pattern)
+                .literal_separator(true)
+                .backslash_escape(true)
+                .build()
+                .map(|glob| Self {
+                    glob: glob.compile_matcher(),
+                    negated,
+                    directory_only,
+                }),
+        )
+    }
+}
+
+// as git does, trailing spaces are dropped unless escaped (e.g., `file\ `)
+#[cfg(not(feature = "gitignore"))]
+fn trim_trailing_spaces(line: &str) -> &str {
+    let trimmed = line.trim_end_matches(' ');
+    if trimmed.ends_with('\\') && trimmed.len() < line.len() {
+        &line[..trimmed.len() + 1]
+    } else {
+        trimmed
+    }
+}
+
+// The patterns of the options, relative to the root of the walk.
+pub(crate) struct IgnorePatterns {
+    root: PathBuf,
+    #[cfg(feature = "gitignore")]
+    matcher: Gitignore,
+    #[cfg(not(feature = "gitignore"))]
+    rules: Vec<IgnoreRule>,
+}
+
+impl IgnorePatterns {
+    #[cfg(feature = "gitignore")]
+    pub(crate) fn new(root: &Path, options: &WalkOptions) -> Result<Self, RuleMatcherError> {
+        let mut builder = GitignoreBuilder::new(root);
+        for pattern in &options.ignore {
+            builder
+                .add_line(None, pattern)
+                .map_err(|e| RuleMatcherError::IgnorePattern(pattern.to_owned(), e.into()))?;
+        }
+
+        let matcher = builder
+            .build()
+            .map_err(|e| RuleMatcherError::IgnorePattern(options.ignore.join(", "), e.into()))?;
+
+        Ok(Self {
+            root: root.to_owned(),
+            matcher,
+        })
+    }
+
+    #[cfg(not(feature = "gitignore"))]
+    pub(crate) fn new(root: &Path, options: &WalkOptions) -> Result<Self, RuleMatcherError> {
+        let rules = options
+            .ignore
+            .iter()
+            .filter_map(|pattern| {
+                IgnoreRule::parse(pattern).map(|rule| {
+                    rule.map_err(|e| RuleMatcherError::IgnorePattern(pattern.to_owned(), e.into()))
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Self {
+            root: root.to_owned(),
+            rules,
+        })
+    }
+
+    // whether the last pattern matching `path` ignores it (rather than
+    // re-including it); the root itself is never ignored
+    //
+    // NOTE: the directories above `path` are not checked, as ignored
+    // directories are not walked
+    pub(crate) fn is_ignored(&self, path: &Path, is_dir: bool) -> bool {
+        let Ok(relative) = path.strip_prefix(&self.root) else {
+            return false;
+        };
+
+        if relative.as_os_str().is_empty() {
+            return false;
+        }
+
+        #[cfg(feature = "gitignore")]
+        return self.matcher.matched(path, is_dir).is_ignore();
+
+        #[cfg(not(feature = "gitignore"))]
+        self.rules
+            .iter()
+            .rev()
+            .find(|rule| (is_dir || !rule.directory_only) && rule.glob.is_match(relative))
+            .is_some_and(|rule| !rule.negated)
+    }
+}
+
+// The ignore files of git that apply to the entries of a walk, the most
+// specific first: the `.gitignore` files of the directories being walked
+// (deepest first), those of the directories above the root (up to the
+// repository), the repository's `.git/info/exclude`, and the global excludes
+// file.
+#[cfg(feature = "gitignore")]
+pub(crate) struct GitIgnores {
+    // the root, as the paths of the walk start with it, and its canonical
+    // form, to which the ignore files outside of the walk are relative
+    root: PathBuf,
+    canonical: PathBuf,
+    // the `.gitignore` files of the directories being walked, by depth
+    walked: Vec<(usize, Gitignore)>,
+    outer: Vec<Gitignore>,
+}
+
+#[cfg(feature = "gitignore")]
+impl GitIgnores {
+    // `None` outside of a repository
+    pub(crate) fn new(root: &Path) -> Option<Self> {
+        let canonical = root.canonicalize().ok()?;
+        let repository = canonical
+            .ancestors()
+            .find(|dir| dir.join(".git").exists())?;
+
+        let mut outer = Vec::new();
+        for dir in canonical.ancestors().skip(1) {
+            if !dir.starts_with(repository) {
+                break;
+            }
+            outer.extend(gitignore(dir, &dir.join(".gitignore")));
+        }
+        outer.extend(gitignore(
+            repository,
+            &repository.join(".git").join("info").join("exclude"),
+        ));
+        outer.push(Gitignore::global().0);
+
+        Some(Self {
+            root: root.to_owned(),
+            canonical,
+            walked: Vec::new(),
+            outer,
+        })
+    }
+
+    // the `.gitignore` file of `dir`, a directory at `depth` of the walk,
+    // applies to the entries below it
+    pub(crate) fn enter(&mut self, dir: &Path, depth: usize) {
+        self.leave(depth);
+        if let Some(gitignore) = gitignore(dir, &dir.join(".gitignore")) {
+            self.walked.push((depth, gitignore));
+        }
+    }
+
+    // whether the most specific ignore file that matches `path`, an entry at
+    // `depth` of the walk, ignores it (rather than re-including it); the root
+    // itself is never ignored
+    pub(crate) fn is_ignored(&mut self, path: &Path, depth: usize, is_dir: bool) -> bool {
+        if depth == 0 {
+            return false;
+        }
+
+        self.leave(depth);
+
+        let walked = self
+            .walked
+            .iter()
+            .rev()
+            .map(|(_, gitignore)| gitignore.matched(path, is_dir));
+
+        let canonical = match path.strip_prefix(&self.root) {
+            Ok(relative) => self.canonical.join(relative),
+            Err(_) => path.to_owned(),
+        };
+        let outer = self
+            .outer
+            .iter()
+            .map(|gitignore| gitignore.matched(&canonical, is_dir));
+
+        walked
+            .chain(outer)
+            .find(|matched| !matched.is_none())
+            .is_some_and(|matched| matched.is_ignore())
+    }
+
+    // the directories at `depth` or deeper are no longer being walked
+    fn leave(&mut self, depth: usize) {
+        while self.walked.last().is_some_and(|(d, _)| *d >= depth) {
+            self.walked.pop();
+        }
+    }
+}
+
+// the patterns of `file`, relative to `dir`, if it exists; as git does, lines
+// that are not valid patterns are left out
+#[cfg(feature = "gitignore")]
+fn gitignore(dir: &Path, file: &Path) -> Option<Gitignore> {
+    if !file.is_file() {
+        return None;
+    }
+
+    let mut builder = GitignoreBuilder::new(dir);
+    let _ = builder.add(file);
+    builder.build().ok()
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::Path;
+
+    use super::{IgnorePatterns, WalkOptions};
+
+    #[test]
+    fn test_ignore_patterns() -> Result<(), Box<dyn std::error::Error>> {
+        let options = WalkOptions::new()
+            .ignore("# comment")
+            .ignore("build/")
+            .ignore("/generated")
+            .ignore("*.gen.c")
+            .ignore("!keep.gen.c")
+            .ignore("docs/**/*.c")
+            .ignore("spaces.c   ")
+            .ignore("escaped.c\\ ");
+
+        let patterns = IgnorePatterns::new(Path::new("root"), &options)?;
+        let ignored = |path: &str, is_dir| patterns.is_ignored(Path::new(path), is_dir);
+
+        assert!(ignored("root/build", true));
+        assert!(ignored("root/src/build", true));
+        // directory patterns do not match files
+        assert!(!ignored("root/src/build", false));
+        assert!(ignored("root/generated", true));
+        assert!(!ignored("root/src/generated", true));
+        assert!(ignored("root/src/x.gen.c", false));
+        assert!(!ignored("root/src/keep.gen.c", false));
+        assert!(ignored("root/docs/a/b/x.c", false));
+        assert!(!ignored("root/src/x.c", false));
+        // trailing spaces are dropped, unless escaped
+        assert!(ignored("root/spaces.c", false));
+        assert!(ignored("root/escaped.c ", false));
+        assert!(!ignored("root/escaped.c", false));
+        assert!(!ignored("root", true));
+
+        assert!(
+            IgnorePatterns::new(Path::new("root"), &WalkOptions::new().ignore("[z-a]")).is_err()
+        );
+
+        Ok(())
+    }
+}
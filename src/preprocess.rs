@@ -0,0 +1,10 @@
+pub mod addresses;
+
+pub mod comments;
+
+pub mod decompiler;
+
+pub mod macros;
+
+mod offsets;
+pub use offsets::{Edit, OffsetMap};
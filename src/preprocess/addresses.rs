@@ -0,0 +1,247 @@
+use std::collections::BTreeMap;
+use std::ops::Range;
+use std::sync::{Arc, LazyLock};
+
+use regex::{Captures, Regex};
+
+use crate::location;
+use crate::preprocess::decompiler::Dialect;
+
+// Mapping of findings in decompiler pseudocode back to addresses in the
+// binary, e.g., so that a plugin can jump from a finding to the disassembly.
+//
+// The address of a match is that of the nearest annotation preceding it (or
+// on the same line) within its enclosing function, including the comments
+// right above the function's definition (e.g., Hex-Rays' `//----- (...)`
+// headers); outside of any function, only the match's line is searched.
+// Annotations are found by a grammar of regexes, whose `address` group (or
+// first group, or whole match) is the address in hexadecimal, and by a map
+// from (1-based) lines to addresses supplied by the caller, which takes
+// precedence.
+#[derive(Debug, Clone)]
+pub struct AddressExtractor {
+    patterns: Arc<[Regex]>,
+    lines: Arc<BTreeMap<usize, u64>>,
+}
+
+// e.g., `//----- (0000000000401040) ---` and `v5 = sub_401000(a1); // 0x401234`
+static HEXRAYS_PATTERNS: LazyLock<Arc<[Regex]>> = LazyLock::new(|| {
+    Arc::from([
+        Regex::new(r"//-+ \((?P<address>[0-9A-Fa-f]+)\) -").unwrap(),
+        Regex::new(r"//[^\n]*?\b0x(?P<address>[0-9A-Fa-f]+)\b").unwrap(),
+    ])
+});
+
+// e.g., the definition of `FUN_00101189` (but not calls to it, which are
+// indented), labels such as `LAB_001011d4:`, `/* WARNING: ... at 0x... */`,
+// and listings whose lines are prefixed by their address
+static GHIDRA_PATTERNS: LazyLock<Arc<[Regex]>> = LazyLock::new(|| {
+    Arc::from([
+        Regex::new(r"(?m)^[^\s/][^\n;]*\bFUN_(?P<address>[0-9A-Fa-f]+)\s*\(").unwrap(),
+        Regex::new(r"(?m)^\s*LAB_(?P<address>[0-9A-Fa-f]+):").unwrap(),
+        Regex::new(r"/\*[^\n]*?\b0x(?P<address>[0-9A-Fa-f]+)\b").unwrap(),
+        Regex::new(r"(?m)^(?P<address>[0-9A-Fa-f]{8,16}):?[ \t]").unwrap(),
+    ])
+});
+
+impl AddressExtractor {
+    // the built-in grammar of `dialect`
+    pub fn new(dialect: Dialect) -> Self {
+        let patterns = match dialect {
+            Dialect::HexRays => &*HEXRAYS_PATTERNS,
+            Dialect::Ghidra => &*GHIDRA_PATTERNS,
+        };
+
+        Self {
+            patterns: patterns.clone(),
+            lines: Arc::default(),
+        }
+    }
+
+    // a custom grammar, e.g., for another decompiler's exports
+    pub fn with_patterns(
+        patterns: impl IntoIterator<Item = impl AsRef<str>>,
+    ) -> Result<Self, regex::Error> {
+        Ok(Self {
+            patterns: patterns
+                .into_iter()
+                .map(|pattern| Regex::new(pattern.as_ref()))
+                .collect::<Result<_, _>>()?,
+            lines: Arc::default(),
+        })
+    }
+
+    // the caller-supplied addresses of (1-based) lines of the source, e.g.,
+    // from the decompiler's own line mapping; with
+    // `RuleMatcher::matches_split_functions`, lines are those of the segment
+    pub fn lines(self, lines: impl IntoIterator<Item = (usize, u64)>) -> Self {
+        Self {
+            lines: Arc::new(lines.into_iter().collect()),
+            ..self
+        }
+    }
+
+    // the annotations of `text`, as their offsets and addresses, in order
+    pub fn annotations(&self, text: &str) -> Vec<(usize, u64)> {
+        let mut annotations = self
+            .patterns
+            .iter()
+            .flat_map(|regex| regex.captures_iter(text))
+            .filter_map(|c| Some((c.get(0)?.start(), parse_address(&c)?)))
+            .collect::<Vec<_>>();
+
+        annotations.sort_unstable();
+        annotations
+    }
+
+    // the address of a match at `offset` in `source`, where `function` is the
+    // span of its enclosing function definition, if any
+    pub fn address(
+        &self,
+        source: &str,
+        offset: usize,
+        function: Option<Range<usize>>,
+    ) -> Option<u64> {
+        let offset = location::floor_char_boundary(source, offset);
+        let end = source[offset..]
+            .find('\n')
+            .map_or(source.len(), |i| offset + i);
+        let start = match function {
+            Some(function) => header_start(source, function.start.min(offset)),
+            None => source[..offset].rfind('\n').map_or(0, |i| i + 1),
+        };
+
+        if !self.lines.is_empty() {
+            let first = location::line_column(source, start).0;
+            let line = location::line_column(source, offset).0;
+
+            if let Some((_, address)) = self.lines.range(first..=line).next_back() {
+                return Some(*address);
+            }
+        }
+
+        self.annotations(&source[start..end])
+            .last()
+            .map(|(_, address)| *address)
+    }
+}
+
+fn parse_address(c: &Captures) -> Option<u64> {
+    let address = c
+        .name("address")
+        .or_else(|| c.get(1))
+        .or_else(|| c.get(0))?;
+    let digits = address.as_str();
+    let digits = digits
+        .strip_prefix("0x")
+        .or_else(|| digits.strip_prefix("0X"))
+        .unwrap_or(digits);
+
+    u64::from_str_radix(digits, 16).ok()
+}
+
+// the start of the comments (and blank lines) right above `offset`, which
+// is at the start of a line
+fn header_start(source: &str, offset: usize) -> usize {
+    let mut start = source[..offset].rfind('\n').map_or(0, |i| i + 1);
+
+    while start > 0 {
+        let previous = source[..start - 1].rfind('\n').map_or(0, |i| i + 1);
+        let line = source[previous..start].trim();
+
+        if !(line.is_empty()
+            || line.starts_with("//")
+            || line.starts_with("/*")
+            || line.starts_with('*'))
+        {
+            break;
+        }
+        start = previous;
+    }
+
+    start
+}
+
+#[cfg(test)]
+mod test {
+    use super::AddressExtractor;
+    use crate::preprocess::decompiler::Dialect;
+
+    #[test]
+    fn test_annotations() -> Result<(), Box<dyn std::error::Error>> {
+        let hexrays = AddressExtractor::new(Dialect::HexRays);
+
+        let source = "\
+//----- (0000000000401000) ----------------------------------------------------
+int f(int a1)
+{
+  int v5; // rbx
+
+  v5 = sub_401100(a1); // 0x401234
+  return v5;
+}
+";
+
+        assert_eq!(
+            hexrays
+                .annotations(source)
+                .into_iter()
+                .map(|(_, address)| address)
+                .collect::<Vec<_>>(),
+            [0x401000, 0x401234]
+        );
+
+        let function = source.find("int f").unwrap()..source.len();
+        let call = source.find("sub_401100").unwrap();
+        let ret = source.find("return").unwrap();
+        let local = source.find("int v5").unwrap();
+
+        // the same line, a preceding one, and the function header
+        assert_eq!(
+            hexrays.address(source, call, Some(function.clone())),
+            Some(0x401234)
+        );
+        assert_eq!(
+            hexrays.address(source, ret, Some(function.clone())),
+            Some(0x401234)
+        );
+        assert_eq!(
+            hexrays.address(source, local, Some(function.clone())),
+            Some(0x401000)
+        );
+        assert_eq!(hexrays.address(source, local, None), None);
+
+        // caller-supplied lines take precedence
+        let lines = hexrays.clone().lines([(2, 0x401000), (5, 0x401230)]);
+        assert_eq!(
+            lines.address(source, ret, Some(function.clone())),
+            Some(0x401230)
+        );
+        assert_eq!(lines.address(source, local, Some(function)), Some(0x401000));
+
+        let ghidra = AddressExtractor::new(Dialect::Ghidra);
+        let source = "\
+void FUN_00101189(void)
+{
+  FUN_00102000();
+  /* WARNING: Could not recover jumptable at 0x001011a0. Too many branches */
+  return;
+}
+";
+
+        assert_eq!(
+            ghidra
+                .annotations(source)
+                .into_iter()
+                .map(|(_, address)| address)
+                .collect::<Vec<_>>(),
+            [0x101189, 0x1011a0]
+        );
+
+        let custom = AddressExtractor::with_patterns([r"@ (?P<address>[0-9a-f]+)"])?;
+        assert_eq!(custom.address("x = 1; // @ 1f00", 0, None), Some(0x1f00));
+        assert!(AddressExtractor::with_patterns(["("]).is_err());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,183 @@
+use std::borrow::Cow;
+
+use thiserror::Error;
+
+#[derive(Debug, Error, Clone, Copy, PartialEq, Eq)]
+pub enum CommentError {
+    #[error("unterminated comment starting at offset {0}")]
+    UnterminatedComment(usize),
+    #[error("unterminated literal starting at offset {0}")]
+    UnterminatedLiteral(usize),
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum State {
+    Code,
+    LineComment,
+    BlockComment(usize),
+    Literal(u8, usize),
+    Escape(u8, usize),
+}
+
+// Replaces the contents of comments with spaces, retaining newlines, so that
+// all offsets (and line numbers) are unchanged. String and character literals
+// are left intact, but are tracked so that comment markers within them are
+// not treated as comments.
+//
+// NOTE: this is a single pass over the input, and it is not a C lexer: it
+// does not handle trigraphs, line continuations within line comments, or raw
+// string literals.
+pub fn blank_comments(source: &str) -> Result<Cow<'_, str>, CommentError> {
+    blank_tokens(source, false)
+}
+
+// As `blank_comments`, but the contents of string and character literals are
+// blanked too (their quotes are retained), e.g., to search code only.
+pub fn blank_comments_and_literals(source: &str) -> Result<Cow<'_, str>, CommentError> {
+    blank_tokens(source, true)
+}
+
+fn blank_tokens(source: &str, literals: bool) -> Result<Cow<'_, str>, CommentError> {
+    let bytes = source.as_bytes();
+
+    let mut state = State::Code;
+    let mut output: Option<Vec<u8>> = None;
+
+    let mut i = 0;
+
+    while i < bytes.len() {
+        let b = bytes[i];
+
+        state = match state {
+            State::Code => match (b, bytes.get(i + 1)) {
+                (b'/', Some(b'/')) => {
+                    let output = output.get_or_insert_with(|| bytes.to_vec());
+                    output[i] = b' ';
+                    output[i + 1] = b' ';
+                    i += 1;
+                    State::LineComment
+                }
+                (b'/', Some(b'*')) => {
+                    let output = output.get_or_insert_with(|| bytes.to_vec());
+                    output[i] = b' ';
+                    output[i + 1] = b' ';
+                    i += 1;
+                    State::BlockComment(i - 1)
+                }
+                (b'"' | b'\'', _) => {
+                    if literals {
+                        output.get_or_insert_with(|| bytes.to_vec());
+                    }
+                    State::Literal(b, i)
+                }
+                _ => State::Code,
+            },
+            State::LineComment => {
+                if b == b'\n' {
+                    State::Code
+                } else {
+                    blank(&mut output, i);
+                    State::LineComment
+                }
+            }
+            State::BlockComment(start) => {
+                if b == b'*' && bytes.get(i + 1) == Some(&b'/') {
+                    blank(&mut output, i);
+                    blank(&mut output, i + 1);
+                    i += 1;
+                    State::Code
+                } else {
+                    if b != b'\n' {
+                        blank(&mut output, i);
+                    }
+                    State::BlockComment(start)
+                }
+            }
+            State::Literal(quote, start) => match b {
+                b'\n' => return Err(CommentError::UnterminatedLiteral(start)),
+                _ if b == quote => State::Code,
+                _ => {
+                    if literals {
+                        blank(&mut output, i);
+                    }
+                    if b == b'\\' {
+                        State::Escape(quote, start)
+                    } else {
+                        State::Literal(quote, start)
+                    }
+                }
+            },
+            State::Escape(quote, start) => {
+                if literals {
+                    blank(&mut output, i);
+                }
+                State::Literal(quote, start)
+            }
+        };
+
+        i += 1;
+    }
+
+    match state {
+        State::BlockComment(start) => return Err(CommentError::UnterminatedComment(start)),
+        State::Literal(_, start) | State::Escape(_, start) => {
+            return Err(CommentError::UnterminatedLiteral(start))
+        }
+        State::Code | State::LineComment => (),
+    }
+
+    Ok(match output {
+        // NOTE: only ASCII bytes outside of literals (or, with `literals`,
+        // within their quotes) are modified, and all bytes of a multi-byte
+        // sequence are replaced together
+        Some(output) => Cow::Owned(String::from_utf8(output).expect("valid UTF-8")),
+        None => Cow::Borrowed(source),
+    })
+}
+
+fn blank(output: &mut Option<Vec<u8>>, i: usize) {
+    if let Some(output) = output {
+        output[i] = b' ';
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::borrow::Cow;
+
+    use super::{blank_comments, blank_comments_and_literals, CommentError};
+
+    #[test]
+    fn test_blank_comments() {
+        let source = "a(); // strcpy 🦀\nb(\"/* x */\", '\"'); /* gets\n */ c();";
+        let blanked = blank_comments(source).unwrap();
+
+        assert_eq!(blanked.len(), source.len());
+        assert_eq!(
+            blanked.lines().map(str::trim_end).collect::<Vec<_>>(),
+            ["a();", "b(\"/* x */\", '\"');", "    c();"]
+        );
+
+        assert!(matches!(blank_comments("a(\"//\");"), Ok(Cow::Borrowed(_))));
+
+        assert_eq!(
+            blank_comments("a(); /* b();").unwrap_err(),
+            CommentError::UnterminatedComment(5)
+        );
+        assert_eq!(
+            blank_comments("a(\"b);\nc();").unwrap_err(),
+            CommentError::UnterminatedLiteral(2)
+        );
+
+        let blanked = blank_comments_and_literals(source).unwrap();
+        assert_eq!(blanked.len(), source.len());
+        assert_eq!(
+            blanked.lines().map(str::trim_end).collect::<Vec<_>>(),
+            ["a();", "b(\"       \", ' ');", "    c();"]
+        );
+        assert_eq!(
+            blank_comments_and_literals("s = \"\\\"🦀\";").unwrap(),
+            "s = \"      \";"
+        );
+    }
+}
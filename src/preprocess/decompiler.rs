@@ -0,0 +1,341 @@
+use std::borrow::Cow;
+use std::ops::Range;
+use std::sync::LazyLock;
+
+use regex::{Captures, Regex};
+
+use crate::preprocess::{Edit, OffsetMap};
+
+// Normalization of decompiler pseudocode into something closer to the C that
+// the grammar (and rule authors) expect: calling conventions and register
+// annotations are stripped, decompiler-specific typedefs are rewritten to
+// their standard equivalents, segment prefixes are dropped, and labels at the
+// end of a block are given an empty statement.
+//
+// NOTE: the normalized text differs in length from the input, and so offsets
+// of matches produced from it refer to the normalized text; use
+// `normalize_mapped` to obtain a map back to the input.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Dialect {
+    HexRays,
+    Ghidra,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Normalizer {
+    dialect: Dialect,
+    keep_intrinsics: bool,
+}
+
+const HEXRAYS_TYPES: &[(&str, &str)] = &[
+    ("_BYTE", "unsigned char"),
+    ("_WORD", "unsigned short"),
+    ("_DWORD", "unsigned int"),
+    ("_QWORD", "unsigned long long"),
+    ("_OWORD", "unsigned __int128"),
+    ("_BOOL1", "char"),
+    ("_BOOL2", "short"),
+    ("_BOOL4", "int"),
+    ("_BOOL8", "long long"),
+    ("__int8", "char"),
+    ("__int16", "short"),
+    ("__int32", "int"),
+    ("__int64", "long long"),
+];
+
+const GHIDRA_TYPES: &[(&str, &str)] = &[
+    ("undefined", "unsigned char"),
+    ("undefined1", "unsigned char"),
+    ("undefined2", "unsigned short"),
+    ("undefined4", "unsigned int"),
+    ("undefined8", "unsigned long long"),
+    ("byte", "unsigned char"),
+    ("uchar", "unsigned char"),
+    ("ushort", "unsigned short"),
+    ("uint", "unsigned int"),
+    ("ulong", "unsigned long"),
+    ("longlong", "long long"),
+    ("ulonglong", "unsigned long long"),
+    ("word", "unsigned short"),
+    ("dword", "unsigned int"),
+    ("qword", "unsigned long long"),
+];
+
+static ATTRIBUTES: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(concat!(
+        r"\b(?:__usercall|__userpurge|__fastcall|__cdecl|__stdcall|__thiscall|",
+        r"__vectorcall|__pascal|__noreturn|__hidden|__return_ptr|__struct_ptr|",
+        r"__ptr32|__ptr64|__unaligned|__high|__far|__near)\b[ \t]*|",
+        r"[ \t]*@<[^<>\n]*>|",
+        r"\b__spoils<[^<>\n]*>[ \t]*|",
+        r"\b__shifted\([^()\n]*\)[ \t]*"
+    ))
+    .unwrap()
+});
+
+static SEGMENT_PREFIX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(\)\s*)(?:[cdefgs]s):").unwrap());
+
+static TRAILING_LABEL: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\b((?:LABEL|LAB)_[0-9A-Za-z_]+:)(\s*\})").unwrap());
+
+static INTRINSICS: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"\b__(?:OF|CF|SF|PF)[A-Z]+__\((?:[^()]|\([^()]*\))*\)|\b__SETP__\([^()]*\)")
+        .unwrap()
+});
+
+static HEXRAYS_TYPES_REGEX: LazyLock<Regex> = LazyLock::new(|| types_regex(HEXRAYS_TYPES));
+
+static GHIDRA_TYPES_REGEX: LazyLock<Regex> = LazyLock::new(|| types_regex(GHIDRA_TYPES));
+
+fn types_regex(types: &[(&str, &str)]) -> Regex {
+    let alternatives = types
+        .iter()
+        .map(|(name, _)| regex::escape(name))
+        .collect::<Vec<_>>()
+        .join("|");
+
+    // comments, literals and member accesses are matched as well (and left
+    // alone), so that a type name is only found where it stands on its own
+    Regex::new(&format!(
+        concat!(
+            r#"//[^\n]*|/\*(?s:.*?)\*/|"(?:[^"\\\n]|\\.)*"|'(?:[^'\\\n]|\\.)*'|"#,
+            r"(?:->|\.)\s*[0-9A-Za-z_]+|",
+            r"\b(?P<type>{})\b"
+        ),
+        alternatives
+    ))
+    .unwrap()
+}
+
+// whether the type name at `range` is in a type position: followed by a
+// declarator or a pointer, or the only thing in parentheses (a cast or a
+// `sizeof`)
+//
+// NOTE: this is a heuristic, `byte * 2` is still taken for a pointer type
+fn is_type_position(source: &str, range: Range<usize>) -> bool {
+    match source[range.end..].trim_start().chars().next() {
+        Some('*') => true,
+        Some(c) if c == '_' || c.is_alphabetic() => true,
+        Some(')') => source[..range.start].trim_end().ends_with('('),
+        _ => false,
+    }
+}
+
+fn replace<'a>(
+    source: Cow<'a, str>,
+    regex: &Regex,
+    map: &mut OffsetMap,
+    mut f: impl FnMut(&str, &Captures) -> String,
+) -> Cow<'a, str> {
+    let mut edits = Vec::new();
+    let mut output = String::new();
+    let mut last = 0;
+
+    for c in regex.captures_iter(&source) {
+        let m = c.get(0).unwrap();
+        let replacement = f(&source, &c);
+
+        if replacement == m.as_str() {
+            continue;
+        }
+
+        output.push_str(&source[last..m.start()]);
+        output.push_str(&replacement);
+        last = m.end();
+
+        edits.push(Edit::new(m.range(), replacement.len()));
+    }
+
+    if edits.is_empty() {
+        return source;
+    }
+
+    output.push_str(&source[last..]);
+    map.push_layer(edits);
+
+    Cow::Owned(output)
+}
+
+impl Normalizer {
+    pub fn new(dialect: Dialect) -> Self {
+        Self {
+            dialect,
+            keep_intrinsics: true,
+        }
+    }
+
+    // whether flag intrinsics such as `__OFSUB__(a, b)` are retained; when
+    // not, they are replaced by `0`
+    pub fn keep_intrinsics(self, keep_intrinsics: bool) -> Self {
+        Self {
+            keep_intrinsics,
+            ..self
+        }
+    }
+
+    pub fn dialect(&self) -> Dialect {
+        self.dialect
+    }
+
+    pub fn normalize<'a>(&self, source: &'a str) -> Cow<'a, str> {
+        self.normalize_mapped(source).0
+    }
+
+    pub fn normalize_mapped<'a>(&self, source: &'a str) -> (Cow<'a, str>, OffsetMap) {
+        let (types, types_regex) = match self.dialect {
+            Dialect::HexRays => (HEXRAYS_TYPES, &*HEXRAYS_TYPES_REGEX),
+            Dialect::Ghidra => (GHIDRA_TYPES, &*GHIDRA_TYPES_REGEX),
+        };
+
+        let mut map = OffsetMap::new();
+
+        let source = Cow::Borrowed(source);
+        let source = replace(source, &ATTRIBUTES, &mut map, |_, _| String::new());
+        let source = replace(source, &SEGMENT_PREFIX, &mut map, |_, c| c[1].to_owned());
+        let source = replace(source, &TRAILING_LABEL, &mut map, |_, c| {
+            format!("{};{}", &c[1], &c[2])
+        });
+
+        let source = if self.keep_intrinsics {
+            source
+        } else {
+            replace(source, &INTRINSICS, &mut map, |_, _| String::from("0"))
+        };
+
+        let source = replace(source, types_regex, &mut map, |source, c| {
+            match c.name("type") {
+                Some(m) if is_type_position(source, m.range()) => types
+                    .iter()
+                    .find_map(|(name, ty)| (*name == m.as_str()).then(|| String::from(*ty)))
+                    .unwrap_or_else(|| c[0].to_owned()),
+                _ => c[0].to_owned(),
+            }
+        });
+
+        (source, map)
+    }
+}
+
+pub fn normalize(source: &str, dialect: Dialect) -> Cow<'_, str> {
+    Normalizer::new(dialect).normalize(source)
+}
+
+#[cfg(test)]
+mod test {
+    use std::borrow::Cow;
+    use std::fs;
+
+    use super::{normalize, Dialect, Normalizer};
+
+    #[test]
+    fn test_normalize_hexrays() -> Result<(), Box<dyn std::error::Error>> {
+        let source = fs::read_to_string("tests/hexrays-usercall.c")?;
+        let normalized = normalize(&source, Dialect::HexRays);
+
+        assert!(weggli::parse(&source, false)?.root_node().has_error());
+        assert!(!weggli::parse(&normalized, false)?.root_node().has_error());
+
+        assert!(normalized.contains("int copy_name(char *dst, const char *src)"));
+        assert!(normalized.contains("unsigned int *v2; // ebx"));
+        assert!(normalized.contains("unsigned char v3;"));
+        assert!(normalized.contains("v3 = __OFSUB__(*v2, 16);"));
+        assert!(normalized.contains("void fatal(const char *msg)"));
+        assert!(normalized.contains("result = *(unsigned long long *)0x28;"));
+        assert!(normalized.contains("LABEL_4:;\n}"));
+
+        let normalized = Normalizer::new(Dialect::HexRays)
+            .keep_intrinsics(false)
+            .normalize(&source);
+
+        assert!(normalized.contains("v3 = 0;"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_normalize_ghidra() -> Result<(), Box<dyn std::error::Error>> {
+        let source = fs::read_to_string("tests/ghidra-output.c")?;
+        let normalized = normalize(&source, Dialect::Ghidra);
+
+        assert!(weggli::parse(&source, false)?.root_node().has_error());
+        assert!(!weggli::parse(&normalized, false)?.root_node().has_error());
+
+        assert!(normalized
+            .contains("unsigned long long FUN_00101189(char *param_1,unsigned int param_2)"));
+        assert!(normalized.contains("unsigned char local_28 [16];"));
+        assert!(normalized.contains("(unsigned long)param_2"));
+        assert!(normalized.contains("LAB_001011d4:;\n}"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_normalize_type_positions() {
+        let source = concat!(
+            "uint f(struct s *s, byte *buf)\n",
+            "{\n",
+            "  dword n = s->byte + s->dword;\n",
+            "  word w = (word)sizeof(uint);\n",
+            "  // copy a byte at a time\n",
+            "  /* undefined behaviour */\n",
+            "  puts(\"uint byte\");\n",
+            "  return s.word;\n",
+            "}\n"
+        );
+        let normalized = normalize(source, Dialect::Ghidra);
+
+        assert_eq!(
+            normalized,
+            concat!(
+                "unsigned int f(struct s *s, unsigned char *buf)\n",
+                "{\n",
+                "  unsigned int n = s->byte + s->dword;\n",
+                "  unsigned short w = (unsigned short)sizeof(unsigned int);\n",
+                "  // copy a byte at a time\n",
+                "  /* undefined behaviour */\n",
+                "  puts(\"uint byte\");\n",
+                "  return s.word;\n",
+                "}\n"
+            )
+        );
+    }
+
+    #[test]
+    fn test_normalize_mapped() -> Result<(), Box<dyn std::error::Error>> {
+        let source = fs::read_to_string("tests/hexrays-usercall.c")?;
+        let (normalized, map) = Normalizer::new(Dialect::HexRays).normalize_mapped(&source);
+
+        for needle in [
+            "copy_name",
+            "dst, src",
+            "v3 = __OFSUB__",
+            "LABEL_4:",
+            "memcpy(",
+        ] {
+            let offset = normalized.find(needle).unwrap();
+            let original = map.to_original(offset);
+
+            assert_eq!(&source[original..original + needle.len()], needle);
+            assert_eq!(map.to_processed(original), offset);
+        }
+
+        assert_eq!(map.to_original(normalized.len()), source.len());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_normalize_unchanged() {
+        let source = "int main(void) { return 0; }";
+
+        assert!(matches!(
+            normalize(source, Dialect::HexRays),
+            Cow::Borrowed(_)
+        ));
+        assert!(matches!(
+            normalize(source, Dialect::Ghidra),
+            Cow::Borrowed(_)
+        ));
+    }
+}
@@ -0,0 +1,625 @@
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+
+use crate::preprocess::comments;
+use crate::preprocess::{Edit, OffsetMap};
+
+// Naive expansion of the macros a source defines, so that code hidden behind
+// them (e.g., `#define COPY(d, s) strcpy(d, s)`) is matched as written out:
+// `#define` directives are collected from the whole source, and their uses
+// outside of directives, comments, and literals are replaced by their bodies,
+// which are rescanned for further macros. Function-like macros support
+// stringification (`#x`), token pasting (`##`), and `__VA_ARGS__`.
+//
+// NOTE: this is not a preprocessor: conditional compilation is ignored (and
+// its directives are left alone), as are `#undef` and includes, and a macro
+// defined more than once with different bodies (e.g., in both branches of an
+// `#if`) is not expanded at all. Expansions are written on a single line, and
+// followed by the newlines of the use they replace, so that lines are
+// unchanged; offsets within an expansion map onto the use (see `OffsetMap`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct MacroExpander {
+    function_like: bool,
+    max_depth: usize,
+    max_expansion_bytes: usize,
+}
+
+// the default nesting of macros within the expansion of a use
+pub const MAX_EXPANSION_DEPTH: usize = 16;
+// the default size of the expansion of a use
+pub const MAX_EXPANSION_BYTES: usize = 4096;
+
+impl Default for MacroExpander {
+    fn default() -> Self {
+        Self {
+            function_like: true,
+            max_depth: MAX_EXPANSION_DEPTH,
+            max_expansion_bytes: MAX_EXPANSION_BYTES,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Macro {
+    // `None` for object-like macros
+    params: Option<Vec<String>>,
+    variadic: bool,
+    body: String,
+}
+
+// the macros of a source, where `None` marks those defined more than once
+type Macros = HashMap<String, Option<Macro>>;
+
+impl MacroExpander {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // whether macros with parameters are expanded, besides object-like ones
+    pub fn function_like(self, function_like: bool) -> Self {
+        Self {
+            function_like,
+            ..self
+        }
+    }
+
+    // uses whose expansion nests deeper, or grows larger, are left unexpanded
+    pub fn max_depth(self, max_depth: usize) -> Self {
+        Self { max_depth, ..self }
+    }
+
+    pub fn max_expansion_bytes(self, max_expansion_bytes: usize) -> Self {
+        Self {
+            max_expansion_bytes,
+            ..self
+        }
+    }
+
+    pub fn expand<'a>(&self, source: &'a str) -> Cow<'a, str> {
+        self.expand_mapped(source).0
+    }
+
+    pub fn expand_mapped<'a>(&self, source: &'a str) -> (Cow<'a, str>, OffsetMap) {
+        // NOTE: an unterminated comment or literal leaves the source as is
+        let Ok(code) = comments::blank_comments(source) else {
+            return (Cow::Borrowed(source), OffsetMap::new());
+        };
+
+        let (macros, directives) = self.definitions(&code);
+        if macros.values().all(Option::is_none) {
+            return (Cow::Borrowed(source), OffsetMap::new());
+        }
+
+        let bytes = code.as_bytes();
+        let mut edits = Vec::new();
+        let mut output = String::new();
+        let mut last = 0;
+        let mut directives = directives.into_iter().peekable();
+
+        let mut i = 0;
+        while i < bytes.len() {
+            if let Some(directive) = directives.next_if(|d| d.start <= i) {
+                i = directive.end.max(i);
+                continue;
+            }
+
+            let (token, end) = next_token(&code, i);
+            i = end;
+            let Token::Identifier(name) = token else {
+                continue;
+            };
+            let Some(Some(m)) = macros.get(name) else {
+                continue;
+            };
+
+            let start = end - name.len();
+            let Some((expansion, end)) = self.expand_use(&code, end, name, m, &macros) else {
+                continue;
+            };
+
+            // NOTE: uses whose arguments span a directive are left alone
+            if directives.peek().is_some_and(|d| d.start < end) {
+                continue;
+            }
+
+            let newlines = code[start..end].matches('\n').count();
+            let replacement = format!("{expansion}{}", "\n".repeat(newlines));
+
+            output.push_str(&source[last..start]);
+            output.push_str(&replacement);
+            edits.push(Edit::new(start..end, replacement.len()));
+            last = end;
+            i = end;
+        }
+
+        if edits.is_empty() {
+            return (Cow::Borrowed(source), OffsetMap::new());
+        }
+
+        output.push_str(&source[last..]);
+        (Cow::Owned(output), OffsetMap::from_edits(edits))
+    }
+
+    // the macros defined in `code`, and the ranges of its directives
+    fn definitions(&self, code: &str) -> (Macros, Vec<std::ops::Range<usize>>) {
+        let mut macros = Macros::new();
+        let mut directives = Vec::new();
+
+        let mut start = 0;
+        while start < code.len() {
+            let end = logical_line_end(code, start);
+            let line = &code[start..end];
+
+            if let Some(directive) = line.trim_start().strip_prefix('#') {
+                directives.push(start..end);
+
+                if let Some((name, m)) = self.parse_define(directive) {
+                    macros
+                        .entry(name)
+                        .and_modify(|previous| {
+                            if previous.as_ref() != Some(&m) {
+                                *previous = None;
+                            }
+                        })
+                        .or_insert(Some(m));
+                }
+            }
+
+            start = end;
+        }
+
+        (macros, directives)
+    }
+
+    // e.g., `define COPY(d, s) strcpy(d, s)`, after the `#`
+    fn parse_define(&self, directive: &str) -> Option<(String, Macro)> {
+        let rest = directive.trim_start().strip_prefix("define")?;
+        if !rest.starts_with([' ', '\t']) {
+            return None;
+        }
+
+        let rest = rest.trim_start();
+        let name_end = rest
+            .find(|c: char| !is_identifier_char(c))
+            .unwrap_or(rest.len());
+        let (name, rest) = rest.split_at(name_end);
+        if name.is_empty() || name.starts_with(|c: char| c.is_ascii_digit()) {
+            return None;
+        }
+
+        // NOTE: parameters must follow the name immediately
+        let (params, variadic, body) = match rest.strip_prefix('(') {
+            Some(rest) => {
+                if !self.function_like {
+                    return None;
+                }
+                let (params, body) = rest.split_once(')')?;
+                let mut params = params
+                    .split(',')
+                    .map(|p| p.trim().to_owned())
+                    .filter(|p| !p.is_empty())
+                    .collect::<Vec<_>>();
+                let variadic = params.last().is_some_and(|p| p == "...");
+                if variadic {
+                    params.pop();
+                }
+                if !params.iter().all(|p| p.chars().all(is_identifier_char)) {
+                    return None;
+                }
+                (Some(params), variadic, body)
+            }
+            None => (None, false, rest),
+        };
+
+        let m = Macro {
+            params,
+            variadic,
+            body: collapse_whitespace(&body.replace("\\\n", " ")),
+        };
+        Some((name.to_owned(), m))
+    }
+
+    // the expansion of a use of `m` (named `name`) ending at `end` in `text`,
+    // and the end of the use, i.e., past its arguments
+    fn expand_use(
+        &self,
+        text: &str,
+        end: usize,
+        name: &str,
+        m: &Macro,
+        macros: &Macros,
+    ) -> Option<(String, usize)> {
+        let mut active = HashSet::from([name.to_owned()]);
+
+        match m.params {
+            None => {
+                let expansion = self.rescan(&m.body, macros, &mut active, 1)?;
+                Some((expansion, end))
+            }
+            Some(ref params) => {
+                let (args, end) = arguments(text, end)?;
+                let expansion = self.substitute(m, params, &args, macros, &mut active, 1)?;
+                Some((expansion, end))
+            }
+        }
+    }
+
+    // the body of `m`, with its parameters replaced by `args`
+    fn substitute(
+        &self,
+        m: &Macro,
+        params: &[String],
+        args: &[String],
+        macros: &Macros,
+        active: &mut HashSet<String>,
+        depth: usize,
+    ) -> Option<String> {
+        let variadic = m.variadic && args.len() >= params.len();
+        if args.len() != params.len() && !variadic && !(params.is_empty() && args == [""]) {
+            return None;
+        }
+
+        let raw = |name: &str| {
+            if let Some(i) = params.iter().position(|p| p == name) {
+                return Some(args[i].clone());
+            }
+            (m.variadic && name == "__VA_ARGS__")
+                .then(|| args[params.len().min(args.len())..].join(", "))
+        };
+
+        let tokens = tokens(&m.body);
+        let mut output = String::new();
+        let mut paste = false;
+
+        let mut k = 0;
+        while k < tokens.len() {
+            let token = tokens[k];
+            let pasted_after = tokens[k + 1..]
+                .iter()
+                .find(|t| !matches!(t, Token::Space(_)))
+                .is_some_and(|t| *t == Token::Other("##"));
+
+            match token {
+                Token::Other("##") => {
+                    output.truncate(output.trim_end().len());
+                    paste = true;
+                }
+                Token::Space(_) if paste => {}
+                Token::Other("#") => {
+                    let next = tokens[k + 1..]
+                        .iter()
+                        .position(|t| !matches!(t, Token::Space(_)))
+                        .map(|n| k + 1 + n);
+                    match next.and_then(|n| match tokens[n] {
+                        Token::Identifier(name) => raw(name).map(|arg| (n, arg)),
+                        _ => None,
+                    }) {
+                        Some((n, arg)) => {
+                            output.push_str(&stringify(&arg));
+                            k = n;
+                        }
+                        None => output.push('#'),
+                    }
+                    paste = false;
+                }
+                Token::Identifier(name) => {
+                    match raw(name) {
+                        // NOTE: as in C, arguments are expanded before being
+                        // substituted, unless they are pasted
+                        Some(arg) if paste || pasted_after => output.push_str(&arg),
+                        Some(arg) => output.push_str(&self.rescan(&arg, macros, active, depth)?),
+                        None => output.push_str(name),
+                    }
+                    paste = false;
+                }
+                token => {
+                    output.push_str(token.as_str());
+                    paste = false;
+                }
+            }
+            k += 1;
+        }
+
+        self.rescan(output.trim(), macros, active, depth)
+    }
+
+    // `text` with the macros it uses expanded, except for the `active` ones
+    fn rescan(
+        &self,
+        text: &str,
+        macros: &Macros,
+        active: &mut HashSet<String>,
+        depth: usize,
+    ) -> Option<String> {
+        if depth > self.max_depth {
+            return None;
+        }
+
+        let mut output = String::new();
+        let mut i = 0;
+        while i < text.len() {
+            let (token, end) = next_token(text, i);
+            let start = i;
+            i = end;
+
+            let m = match token {
+                Token::Identifier(name) if !active.contains(name) => match macros.get(name) {
+                    Some(Some(m)) => m,
+                    _ => {
+                        output.push_str(name);
+                        continue;
+                    }
+                },
+                _ => {
+                    output.push_str(&text[start..end]);
+                    continue;
+                }
+            };
+
+            let name = &text[start..end];
+            active.insert(name.to_owned());
+            let expansion = match m.params {
+                None => Some((self.rescan(&m.body, macros, active, depth + 1)?, end)),
+                Some(ref params) => match arguments(text, end) {
+                    Some((args, end)) => Some((
+                        self.substitute(m, params, &args, macros, active, depth + 1)?,
+                        end,
+                    )),
+                    None => None,
+                },
+            };
+            active.remove(name);
+
+            match expansion {
+                Some((expansion, end)) => {
+                    output.push_str(&expansion);
+                    i = end;
+                }
+                None => output.push_str(name),
+            }
+
+            if output.len() > self.max_expansion_bytes {
+                return None;
+            }
+        }
+
+        (output.len() <= self.max_expansion_bytes).then_some(output)
+    }
+}
+
+pub fn expand(source: &str) -> Cow<'_, str> {
+    MacroExpander::new().expand(source)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Token<'a> {
+    Identifier(&'a str),
+    Space(&'a str),
+    // numbers, literals, and punctuation, where `#` and `##` are tokens of
+    // their own
+    Other(&'a str),
+}
+
+impl<'a> Token<'a> {
+    fn as_str(&self) -> &'a str {
+        match self {
+            Self::Identifier(s) | Self::Space(s) | Self::Other(s) => s,
+        }
+    }
+}
+
+fn is_identifier_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_'
+}
+
+// the token starting at `i`, and its end
+fn next_token(text: &str, i: usize) -> (Token<'_>, usize) {
+    let bytes = text.as_bytes();
+    let b = bytes[i];
+
+    let end = |i: usize, f: &dyn Fn(u8) -> bool| {
+        bytes[i..]
+            .iter()
+            .position(|&b| !f(b))
+            .map_or(bytes.len(), |n| i + n)
+    };
+
+    match b {
+        b'_' | b'a'..=b'z' | b'A'..=b'Z' => {
+            let end = end(i, &|b| is_identifier_char(b as char));
+            (Token::Identifier(&text[i..end]), end)
+        }
+        b'0'..=b'9' => {
+            let end = end(i, &|b| is_identifier_char(b as char) || b == b'.');
+            (Token::Other(&text[i..end]), end)
+        }
+        b' ' | b'\t' | b'\r' | b'\n' => {
+            let end = end(i, &|b| b.is_ascii_whitespace());
+            (Token::Space(&text[i..end]), end)
+        }
+        b'"' | b'\'' => {
+            let mut j = i + 1;
+            while j < bytes.len() && bytes[j] != b && bytes[j] != b'\n' {
+                j += if bytes[j] == b'\\' { 2 } else { 1 };
+            }
+            let end = (j + 1).min(bytes.len());
+            (Token::Other(&text[i..end]), end)
+        }
+        b'#' if bytes.get(i + 1) == Some(&b'#') => (Token::Other(&text[i..i + 2]), i + 2),
+        _ => {
+            let end = i + text[i..].chars().next().map_or(1, char::len_utf8);
+            (Token::Other(&text[i..end]), end)
+        }
+    }
+}
+
+fn tokens(text: &str) -> Vec<Token<'_>> {
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < text.len() {
+        let (token, end) = next_token(text, i);
+        tokens.push(token);
+        i = end;
+    }
+    tokens
+}
+
+// the arguments of a use of a function-like macro whose name ends at `i`,
+// and the end of the use, or `None` if the name is not followed by `(`
+fn arguments(text: &str, i: usize) -> Option<(Vec<String>, usize)> {
+    let open = i + text[i..].find(|c: char| !c.is_ascii_whitespace())?;
+    if text.as_bytes()[open] != b'(' {
+        return None;
+    }
+
+    let mut args = Vec::new();
+    let mut depth = 0;
+    let mut arg = open + 1;
+
+    let mut j = open + 1;
+    while j < text.len() {
+        let (token, end) = next_token(text, j);
+        match token.as_str() {
+            "(" | "[" | "{" => depth += 1,
+            ")" if depth == 0 => {
+                args.push(collapse_whitespace(&text[arg..j]));
+                return Some((args, end));
+            }
+            ")" | "]" | "}" => depth -= 1,
+            "," if depth == 0 => {
+                args.push(collapse_whitespace(&text[arg..j]));
+                arg = end;
+            }
+            _ => {}
+        }
+        j = end;
+    }
+
+    None
+}
+
+// the end of the line starting at `start`, past its newline, including the
+// lines it continues onto
+fn logical_line_end(code: &str, start: usize) -> usize {
+    let bytes = code.as_bytes();
+    let mut i = start;
+    while let Some(n) = memchr::memchr(b'\n', &bytes[i..]) {
+        let newline = i + n;
+        let continued = code[..newline].trim_end_matches('\r').ends_with('\\');
+        i = newline + 1;
+        if !continued {
+            return i;
+        }
+    }
+    bytes.len()
+}
+
+fn collapse_whitespace(text: &str) -> String {
+    text.split_ascii_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn stringify(arg: &str) -> String {
+    format!("\"{}\"", arg.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+#[cfg(test)]
+mod test {
+    use std::borrow::Cow;
+
+    use super::{expand, MacroExpander};
+
+    #[test]
+    fn test_expand() {
+        let source = r#"#define COPY(d, s) strcpy(d, s)
+#define LEN 16
+#define SIZE (LEN + 1)
+/* COPY(a, b) */ char buf[SIZE];
+void f(const char *s) { COPY(buf, s); puts("COPY(a, b)"); COPY; }
+"#;
+        let expanded = expand(source);
+
+        // directives, comments, literals, and uses without arguments are
+        // left alone
+        assert!(expanded.starts_with("#define COPY(d, s) strcpy(d, s)\n#define LEN 16\n"));
+        assert!(expanded.contains("/* COPY(a, b) */ char buf[(16 + 1)];"));
+        assert!(expanded.contains(r#"{ strcpy(buf, s); puts("COPY(a, b)"); COPY; }"#));
+
+        let object_like = MacroExpander::new().function_like(false).expand(source);
+        assert!(object_like.contains("{ COPY(buf, s);"));
+        assert!(object_like.contains("buf[(16 + 1)]"));
+
+        assert!(matches!(
+            expand("int main(void) { return 0; }"),
+            Cow::Borrowed(_)
+        ));
+    }
+
+    #[test]
+    fn test_expand_function_like() {
+        for (source, expected) in [
+            // stringification and pasting
+            ("#define S(x) #x\nS(a \"b\")", r#""a \"b\"""#),
+            (
+                "#define CAT(a, b) a ## b\nCAT(mem, cpy)(d, s, n)",
+                "memcpy(d, s, n)",
+            ),
+            // arguments are expanded, unless pasted
+            (
+                "#define N 1\n#define ID(x) x\n#define P(x) x ## N\nID(N) P(N)",
+                "1 NN",
+            ),
+            // variadic macros, and nested parentheses in arguments
+            (
+                "#define LOG(fmt, ...) printf(fmt, __VA_ARGS__)\nLOG(\"%d %d\", f(a, b), c)",
+                r#"printf("%d %d", f(a, b), c)"#,
+            ),
+            ("#define F() f()\nF()", "f()"),
+            // a wrong number of arguments leaves the use as is
+            ("#define F(a, b) f(a, b)\nF(x)", "F(x)"),
+            // self-referential and mutually recursive macros stop expanding
+            ("#define foo foo + 1\nfoo", "foo + 1"),
+            ("#define A B\n#define B A\nA", "A"),
+            // macros defined twice with different bodies are not expanded
+            ("#ifdef X\n#define M 1\n#else\n#define M 2\n#endif\nM", "M"),
+            ("#define M 1\n#define M 1\nM", "1"),
+            // continuation lines
+            ("#define M \\\n  g(1)\nM", "g(1)"),
+        ] {
+            let expanded = expand(source);
+            let last = expanded.lines().last().unwrap_or_default();
+            assert_eq!(last, expected, "{source:?}");
+        }
+    }
+
+    #[test]
+    fn test_expand_limits() {
+        let source = "#define A B B\n#define B C C\n#define C x\nA\n";
+
+        assert!(expand(source).ends_with("\nx x x x\n"));
+        assert!(MacroExpander::new()
+            .max_depth(2)
+            .expand(source)
+            .ends_with("\nA\n"));
+        assert!(MacroExpander::new()
+            .max_expansion_bytes(4)
+            .expand(source)
+            .ends_with("\nA\n"));
+    }
+
+    #[test]
+    fn test_expand_mapped() {
+        let source = "#define COPY(d, s) strcpy(d, s)\nvoid f(void) {\n  COPY(buf,\n       name);\n  g();\n}\n";
+        let (expanded, map) = MacroExpander::new().expand_mapped(source);
+
+        // lines are unchanged
+        assert_eq!(expanded.lines().count(), source.lines().count());
+        assert_eq!(expanded.lines().nth(2), Some("  strcpy(buf, name)"));
+        assert_eq!(expanded.lines().nth(3), Some(";"));
+
+        // offsets past the use, and of its start, map back onto the source
+        let g = expanded.find("g()").unwrap();
+        assert_eq!(map.to_original(g), source.find("g()").unwrap());
+        let call = expanded.find("strcpy(buf").unwrap();
+        assert_eq!(map.to_original(call), source.find("COPY(buf").unwrap());
+    }
+}
@@ -0,0 +1,173 @@
+use std::ops::Range;
+
+// A replacement of `range` in the input of a pass with `len` bytes of output.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Edit {
+    pub range: Range<usize>,
+    pub len: usize,
+}
+
+impl Edit {
+    pub fn new(range: Range<usize>, len: usize) -> Self {
+        Self { range, len }
+    }
+}
+
+// Mapping between offsets in the text produced by a sequence of preprocessing
+// passes and offsets in the original input.
+//
+// Each pass contributes a layer of sorted, non-overlapping edits; composing
+// maps appends layers, and offsets are translated through each layer in turn.
+// Offsets that fall within a replacement are mapped to the corresponding
+// position in the replaced text, clamped to its end.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct OffsetMap {
+    layers: Vec<Box<[Edit]>>,
+}
+
+impl OffsetMap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // `edits` must be sorted and must not overlap
+    pub fn from_edits(edits: impl IntoIterator<Item = Edit>) -> Self {
+        let mut map = Self::new();
+        map.push_layer(edits);
+        map
+    }
+
+    pub fn push_layer(&mut self, edits: impl IntoIterator<Item = Edit>) {
+        let edits = edits
+            .into_iter()
+            .filter(|e| !e.range.is_empty() || e.len != 0)
+            .collect::<Box<[_]>>();
+
+        debug_assert!(edits.windows(2).all(|w| w[0].range.end <= w[1].range.start));
+
+        if !edits.is_empty() {
+            self.layers.push(edits);
+        }
+    }
+
+    // maps produced by `self`'s passes, followed by those of `next`
+    pub fn compose(mut self, next: OffsetMap) -> Self {
+        self.layers.extend(next.layers);
+        self
+    }
+
+    pub fn is_identity(&self) -> bool {
+        self.layers.is_empty()
+    }
+
+    pub fn to_original(&self, offset: usize) -> usize {
+        self.layers
+            .iter()
+            .rev()
+            .fold(offset, |offset, edits| backward(edits, offset))
+    }
+
+    pub fn to_processed(&self, offset: usize) -> usize {
+        self.layers
+            .iter()
+            .fold(offset, |offset, edits| forward(edits, offset))
+    }
+
+    pub fn range_to_original(&self, range: Range<usize>) -> Range<usize> {
+        let start = self.to_original(range.start);
+        start..self.to_original(range.end).max(start)
+    }
+}
+
+fn backward(edits: &[Edit], offset: usize) -> usize {
+    let mut delta = 0isize;
+
+    for edit in edits {
+        let start = edit.range.start.saturating_add_signed(delta);
+
+        if offset < start {
+            break;
+        }
+
+        if offset < start + edit.len {
+            return edit.range.start + (offset - start).min(edit.range.len());
+        }
+
+        delta += edit.len as isize - edit.range.len() as isize;
+    }
+
+    offset.saturating_add_signed(-delta)
+}
+
+fn forward(edits: &[Edit], offset: usize) -> usize {
+    let mut delta = 0isize;
+
+    for edit in edits {
+        if offset < edit.range.start {
+            break;
+        }
+
+        if offset < edit.range.end {
+            let start = edit.range.start.saturating_add_signed(delta);
+            return start + (offset - edit.range.start).min(edit.len);
+        }
+
+        delta += edit.len as isize - edit.range.len() as isize;
+    }
+
+    offset.saturating_add_signed(delta)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Edit, OffsetMap};
+
+    #[test]
+    fn test_offset_map() {
+        // "int __fastcall f(_DWORD x);" -> "int f(unsigned int x);"
+        let original = "int __fastcall f(_DWORD x);";
+        let processed = "int f(unsigned int x);";
+
+        let map = OffsetMap::from_edits([Edit::new(4..15, 0), Edit::new(17..23, 12)]);
+
+        let f = processed.find("f(").unwrap();
+        assert_eq!(map.to_original(f), original.find("f(").unwrap());
+
+        let x = processed.find('x').unwrap();
+        assert_eq!(map.to_original(x), original.find('x').unwrap());
+        assert_eq!(map.to_processed(original.find('x').unwrap()), x);
+
+        // start/end of a replacement map onto the start/end of what it replaced
+        let ty = processed.find("unsigned").unwrap();
+        assert_eq!(map.range_to_original(ty..ty + 12), 17..23);
+
+        // offsets within a deletion map onto the start of the following text
+        assert_eq!(map.to_processed(8), 4);
+
+        assert_eq!(map.to_original(processed.len()), original.len());
+        assert_eq!(map.to_processed(original.len()), processed.len());
+    }
+
+    #[test]
+    fn test_offset_map_compose() {
+        // "a  __x b;" -[strip "__x "]-> "a  b;" -[insert "/**/" at 0]-> "/**/a  b;"
+        let original = "a  __x b;";
+
+        let first = OffsetMap::from_edits([Edit::new(3..7, 0)]);
+        let second = OffsetMap::from_edits([Edit::new(0..0, 4)]);
+
+        let map = first.compose(second);
+        let processed = "/**/a  b;";
+
+        let b = processed.find('b').unwrap();
+        assert_eq!(map.to_original(b), original.find('b').unwrap());
+        assert_eq!(map.to_processed(original.find('b').unwrap()), b);
+
+        let a = processed.find('a').unwrap();
+        assert_eq!(map.to_original(a), 0);
+        assert_eq!(map.to_original(0), 0);
+
+        assert!(OffsetMap::new().is_identity());
+        assert_eq!(OffsetMap::new().to_original(42), 42);
+    }
+}
@@ -1,34 +1,429 @@
 use std::borrow::Cow;
+use std::collections::BTreeMap;
 use std::fmt::Debug;
+use std::ops::Range;
+use std::path::{Component, Path, PathBuf, Prefix};
 use std::sync::Arc;
 
 use rustc_hash::FxHashSet;
-use serde::{Deserialize, Serialize};
-use weggli::result::QueryResult;
+use serde::ser::Error;
+use serde::{Deserialize, Serialize, Serializer};
+use serde_json::Value;
+use weggli::result::{CaptureResult, QueryResult};
 
-use crate::matcher::RuleMatch;
+use crate::location;
+use crate::matcher::{self, RuleMatch, SuppressionReason};
 use crate::rule::Severity;
 
+mod baseline;
+pub use baseline::{Baseline, BaselineEntry, BaselineError, BaselineMode, BASELINE_VERSION};
+
+pub mod codeclimate;
+
+mod counts;
+pub use counts::SeverityCounts;
+
+mod dedup;
+pub use dedup::{dedup, dedup_counting, ReportKey};
+
+mod diff;
+pub use diff::{diff, ReportDiff};
+
+mod exit;
+pub use exit::{ExitDecision, ExitPolicy, ExitStatus};
+
+mod files;
+pub use files::{by_file, by_file_with, filter_files, FileReport};
+
+mod format;
+pub use format::{FormattedReport, ReportFormat};
+
+pub mod github;
+pub mod grouped;
+pub mod html;
+pub mod jsonl;
+pub mod lsp;
+
+mod load;
+pub use load::{load_json, load_jsonl, load_set, ReportError};
+
+pub mod markdown;
+
+mod merge;
+pub use merge::{merge, merge_files, MergeConflict, MergeError, MergeSummary, Merged};
+
+mod metadata;
+pub use metadata::ReportMetadata;
+
+mod oneline;
+pub use oneline::{write_oneline, write_oneline_with, ONELINE_WIDTH};
+
+pub mod sarif;
+
+mod schema;
+pub use schema::{migrate, SCHEMA_VERSION};
+
+mod set;
+pub use set::ReportSet;
+
+mod severity_map;
+pub use severity_map::SeverityMap;
+
+pub mod sonar;
+pub mod stream;
+pub mod terminal;
+pub use terminal::{ColorChoice, DisplayOptions};
+
 #[derive(Deserialize, Serialize)]
 pub struct RuleMatchReport<'a> {
     rule: Cow<'a, str>,
+    #[serde(default, skip_serializing_if = "str::is_empty")]
+    rule_key: Cow<'a, str>,
     checker: Cow<'a, str>,
-    #[serde(skip_serializing_if = "str::is_empty")]
+    #[serde(default)]
+    checker_index: usize,
+    #[serde(default, skip_serializing_if = "str::is_empty")]
     description: Cow<'a, str>,
-    #[serde(skip_serializing_if = "FxHashSet::<String>::is_empty")]
+    #[serde(default, skip_serializing_if = "str::is_empty")]
+    author: Cow<'a, str>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    rule_path: Option<Cow<'a, Path>>,
+    #[serde(
+        default,
+        skip_serializing_if = "FxHashSet::<String>::is_empty",
+        serialize_with = "serialize_tags"
+    )]
     tags: Cow<'a, FxHashSet<String>>,
+    // NOTE: keys are sorted, at any depth, as maps of `Value` are ordered
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    metadata: Cow<'a, BTreeMap<String, Value>>,
     severity: Severity,
+    #[serde(default, skip_serializing_if = "str::is_empty")]
+    fingerprint: Cow<'a, str>,
+    #[serde(default, skip_serializing_if = "str::is_empty")]
     source: Arc<str>,
-    #[serde(rename = "match")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    source_base: Option<SourceBase>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    location: Option<ReportLocation>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    address: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    snippet: Option<ReportSnippet>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    captures: Cow<'a, BTreeMap<String, String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    suppression: Option<SuppressionReason>,
+    #[serde(default, skip_serializing_if = "is_zero")]
+    duplicates: usize,
+    #[serde(
+        rename = "match",
+        default = "empty_result",
+        skip_serializing_if = "is_empty_result",
+        serialize_with = "serialize_result"
+    )]
     match_result: Cow<'a, QueryResult>,
 }
 
+// Where a match was found, computed when the report is created, so that
+// consumers need not recompute positions from offsets into `source`.
+//
+// NOTE: offsets are byte offsets; lines and columns are 1-based, with columns
+// counting characters
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize, Serialize)]
+pub struct ReportLocation {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    path: Option<PathBuf>,
+    start_offset: usize,
+    end_offset: usize,
+    start_line: usize,
+    start_column: usize,
+    end_line: usize,
+    end_column: usize,
+}
+
+impl ReportLocation {
+    fn new(m: &RuleMatch) -> Self {
+        Self {
+            path: m.origin().map(Path::to_path_buf),
+            start_offset: m.start_offset(),
+            end_offset: m.end_offset(),
+            start_line: m.start_line(),
+            start_column: m.start_column(),
+            end_line: m.end_line(),
+            end_column: m.end_column(),
+        }
+    }
+
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    pub fn start_offset(&self) -> usize {
+        self.start_offset
+    }
+
+    pub fn end_offset(&self) -> usize {
+        self.end_offset
+    }
+
+    pub fn start_line(&self) -> usize {
+        self.start_line
+    }
+
+    pub fn start_column(&self) -> usize {
+        self.start_column
+    }
+
+    pub fn end_line(&self) -> usize {
+        self.end_line
+    }
+
+    pub fn end_column(&self) -> usize {
+        self.end_column
+    }
+}
+
+// How much of the scanned source a report embeds: all of it (the default),
+// only the lines of the match and some lines of context around them, or none.
+//
+// NOTE: with `Snippet`, `source` starts at `SourceBase`, and the ranges of the
+// report's `QueryResult` are relative to it; with `Omit`, `matched_text` and
+// `display` are empty
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum SourceMode {
+    Omit,
+    Snippet {
+        before: usize,
+        after: usize,
+    },
+    #[default]
+    Full,
+}
+
+// Where the embedded source of a snippet starts in the scanned source.
+//
+// NOTE: `line` is 1-based
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize)]
+pub struct SourceBase {
+    offset: usize,
+    char_offset: usize,
+    line: usize,
+}
+
+impl SourceBase {
+    pub(crate) fn new(offset: usize, char_offset: usize, line: usize) -> Self {
+        Self {
+            offset,
+            char_offset,
+            line,
+        }
+    }
+
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    pub fn char_offset(&self) -> usize {
+        self.char_offset
+    }
+
+    pub fn line(&self) -> usize {
+        self.line
+    }
+}
+
+// the lines of context around a match in the snippet of a new report
+pub const SNIPPET_CONTEXT: usize = 2;
+
+// the path under which matches without an origin are grouped and reported
+pub const MEMORY_PATH: &str = "<memory>";
+
+// The lines of a match and some lines of context around them, as plain text,
+// so that consumers can show code without the scanned source (see
+// `RuleMatchReport::with_snippet`).
+//
+// NOTE: `start_line` is 1-based, and refers to the scanned source
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize, Serialize)]
+pub struct ReportSnippet {
+    start_line: usize,
+    text: String,
+}
+
+impl ReportSnippet {
+    pub fn start_line(&self) -> usize {
+        self.start_line
+    }
+
+    pub fn end_line(&self) -> usize {
+        self.start_line + self.text.split('\n').count() - 1
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    // the lines of the snippet with their (absolute) line numbers
+    pub fn lines(&self) -> impl Iterator<Item = (usize, &str)> {
+        (self.start_line..).zip(self.text.split('\n'))
+    }
+
+    // the lines of the snippet, prefixed with their line numbers as by
+    // `QueryResult::display`
+    pub fn numbered(&self) -> String {
+        self.lines()
+            .map(|(line, text)| format!("{line:>4}: {text}\n"))
+            .collect()
+    }
+}
+
+fn empty_result() -> Cow<'static, QueryResult> {
+    Cow::Owned(QueryResult::new(Vec::new(), Default::default(), 0..0))
+}
+
+// the (merged, disjoint) ranges highlighted by `QueryResult::display`
+pub(crate) fn highlights(source: &str, result: &QueryResult) -> Vec<Range<usize>> {
+    let mut ranges = result
+        .captures
+        .iter()
+        .skip(1)
+        .map(|c| location::clamp_range(source, c.range.clone()))
+        .filter(|r| !r.is_empty())
+        .collect::<Vec<_>>();
+    ranges.sort_by_key(|r| r.start);
+
+    let mut merged = Vec::<Range<usize>>::with_capacity(ranges.len());
+    for range in ranges {
+        match merged.last_mut() {
+            Some(last) if range.start <= last.end => last.end = last.end.max(range.end),
+            _ => merged.push(range),
+        }
+    }
+    merged
+}
+
+// the values of the variables of `result` (e.g., `$dst`), without those whose
+// ranges do not fit `source` (e.g., of a report deserialized from elsewhere)
+pub(crate) fn captures<'s>(source: &'s str, result: &'s QueryResult) -> BTreeMap<&'s str, &'s str> {
+    result
+        .vars
+        .iter()
+        .filter_map(|(var, i)| {
+            let range = result.captures.get(*i)?.range.clone();
+            Some((var.as_str(), source.get(range)?))
+        })
+        .collect()
+}
+
+// a URI reference to `path`: relative paths become relative references, and
+// absolute ones `file` URIs (e.g., `file:///C:/src/a.c`, or
+// `file://server/share/a.c`), with the bytes of each segment outside of the
+// unreserved set percent-encoded
+pub(crate) fn path_uri(path: &Path) -> String {
+    let mut host = String::new();
+    let mut segments = Vec::new();
+    let mut absolute = path.has_root();
+
+    for component in path.components() {
+        match component {
+            Component::Prefix(prefix) => {
+                absolute = true;
+                match prefix.kind() {
+                    Prefix::Disk(drive) | Prefix::VerbatimDisk(drive) => {
+                        segments.push(format!("{}:", drive as char));
+                    }
+                    Prefix::UNC(server, share) | Prefix::VerbatimUNC(server, share) => {
+                        host = percent_encode(&server.to_string_lossy());
+                        segments.push(percent_encode(&share.to_string_lossy()));
+                    }
+                    Prefix::Verbatim(name) | Prefix::DeviceNS(name) => {
+                        segments.push(percent_encode(&name.to_string_lossy()));
+                    }
+                }
+            }
+            Component::Normal(name) => segments.push(percent_encode(&name.to_string_lossy())),
+            Component::ParentDir => segments.push(String::from("..")),
+            Component::CurDir | Component::RootDir => {}
+        }
+    }
+
+    let segments = segments.join("/");
+    if absolute {
+        format!("file://{host}/{segments}")
+    } else {
+        segments
+    }
+}
+
+fn percent_encode(segment: &str) -> String {
+    let mut encoded = String::with_capacity(segment.len());
+    for b in segment.bytes() {
+        if b.is_ascii_alphanumeric() || b"-._~".contains(&b) {
+            encoded.push(b as char);
+        } else {
+            encoded.push_str(&format!("%{b:02X}"));
+        }
+    }
+    encoded
+}
+
+// NOTE: reports are serialized deterministically, so that their output can be
+// diffed; the iteration order of hash sets and maps depends on their history
+fn serialize_tags<S>(tags: &FxHashSet<String>, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    let mut tags = tags.iter().collect::<Vec<_>>();
+    tags.sort();
+    tags.serialize(serializer)
+}
+
+// `QueryResult` keeps its variables in a hash map, and some of its fields are
+// private, so it is serialized via a `Value` with the variables sorted
+fn serialize_result<S>(result: &QueryResult, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    let mut value = serde_json::to_value(result).map_err(S::Error::custom)?;
+
+    if let Some(vars) = value.get_mut("vars").and_then(Value::as_object_mut) {
+        vars.sort_keys();
+    }
+
+    value.serialize(serializer)
+}
+
+fn is_zero(n: &usize) -> bool {
+    *n == 0
+}
+
+fn is_empty_result(result: &QueryResult) -> bool {
+    result.captures.is_empty()
+}
+
+// A match serializes as its report (`RuleMatchReport::new`) would, byte for
+// byte, e.g., with `serde_json::to_writer(writer, &m)`: use it to write
+// matches as they are found, and `RuleMatchReport` to configure what reports
+// include (e.g., `verbose` or `with_source_mode`), or to read them back.
+//
+// NOTE: the report is a view of the match, which borrows all but its
+// location, snippet, and captures
+impl Serialize for RuleMatch {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        RuleMatchReport::new(self).serialize(serializer)
+    }
+}
+
 impl<'a> Debug for RuleMatchReport<'a> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let mut m = f.debug_struct("RuleMatchReport");
 
         m.field("rule", &self.rule as _);
         m.field("checker", &self.checker as _);
+        m.field("checker_index", &self.checker_index as _);
 
         if let Some(ref description) = self.description() {
             m.field("description", description as _);
@@ -36,6 +431,11 @@ impl<'a> Debug for RuleMatchReport<'a> {
 
         m.field("tags", self.tags());
         m.field("severity", &self.severity as _);
+
+        if let Some(ref location) = self.location {
+            m.field("location", location as _);
+        }
+
         m.field("matches", &self.match_result as _);
 
         m.finish_non_exhaustive()
@@ -46,23 +446,150 @@ impl<'a> RuleMatchReport<'a> {
     pub fn new(m: &'a RuleMatch) -> Self {
         Self {
             rule: Cow::Borrowed(m.rule().id()),
+            rule_key: Cow::Borrowed(m.rule_key()),
+            checker_index: m.checker_id(),
             description: Cow::Borrowed(m.rule().description().unwrap_or_default()),
+            author: Cow::Borrowed(""),
+            rule_path: None,
             checker: Cow::Borrowed(m.checker().name()),
             tags: Cow::Borrowed(m.rule().tags()),
-            severity: m.rule().severity(),
+            metadata: Cow::Owned(BTreeMap::new()),
+            severity: m.effective_severity(),
+            fingerprint: Cow::Borrowed(m.fingerprint()),
             source: m.source(),
+            source_base: m.source_base().copied(),
+            location: Some(ReportLocation::new(m)),
+            address: m.address(),
+            snippet: None,
+            captures: Cow::Owned(
+                captures(m.source_ref(), m.result())
+                    .into_iter()
+                    .map(|(var, value)| (var.to_owned(), value.to_owned()))
+                    .collect(),
+            ),
+            suppression: m.suppression().cloned(),
+            duplicates: 0,
             match_result: Cow::Borrowed(m.result()),
         }
+        .with_snippet(SNIPPET_CONTEXT, SNIPPET_CONTEXT)
+    }
+
+    // like `new`, but also records who wrote the rule, the file it was
+    // loaded from (unless it was loaded from a string), and its metadata (in
+    // full)
+    pub fn verbose(m: &'a RuleMatch) -> Self {
+        Self {
+            author: Cow::Borrowed(m.rule().author().unwrap_or_default()),
+            metadata: Cow::Borrowed(m.rule().metadata()),
+            rule_path: m.rule().source_path().map(Cow::Borrowed),
+            ..Self::new(m)
+        }
+    }
+
+    // drops the raw `QueryResult` from the report, e.g., to reduce the size
+    // of serialized reports; positions remain available via `location`
+    pub fn omit_result(mut self) -> Self {
+        self.match_result = empty_result();
+        self
+    }
+
+    // replaces the snippet with the lines of the match and `before` and
+    // `after` lines of context, as far as the embedded source extends (e.g.,
+    // if it is itself a snippet); without a source, the snippet is kept
+    pub fn with_snippet(mut self, before: usize, after: usize) -> Self {
+        if self.source.is_empty() {
+            return self;
+        }
+
+        let span = location::line_span(&self.source, self.source_span(), before, after);
+
+        self.snippet = Some(ReportSnippet {
+            start_line: self.line_column(span.start).0,
+            text: self.source[span].to_owned(),
+        });
+        self
+    }
+
+    pub fn without_snippet(mut self) -> Self {
+        self.snippet = None;
+        self
+    }
+
+    pub fn with_source_mode(mut self, mode: SourceMode) -> Self {
+        match mode {
+            SourceMode::Full => (),
+            SourceMode::Omit => {
+                self.source = Arc::from("");
+                self.source_base = None;
+            }
+            SourceMode::Snippet { before, after } => {
+                let base = self.base_offset();
+                let span = location::line_span(
+                    &self.source,
+                    self.start_offset().saturating_sub(base)
+                        ..self.end_offset().saturating_sub(base),
+                    before,
+                    after,
+                );
+
+                let (line, _) = location::line_column(&self.source, span.start);
+                let previous = self.source_base.unwrap_or(SourceBase {
+                    offset: 0,
+                    char_offset: 0,
+                    line: 1,
+                });
+
+                let captures = self
+                    .match_result
+                    .captures
+                    .iter()
+                    .map(|c| {
+                        let clamp = |offset: usize| offset.clamp(span.start, span.end) - span.start;
+                        CaptureResult {
+                            range: clamp(c.range.start)..clamp(c.range.end),
+                            ..c.clone()
+                        }
+                    })
+                    .collect::<Vec<_>>();
+                let function = captures.first().map(|c| c.range.clone()).unwrap_or(0..0);
+
+                self.match_result = Cow::Owned(QueryResult::new(
+                    captures,
+                    self.match_result.vars.clone(),
+                    function,
+                ));
+                self.source_base = Some(SourceBase {
+                    offset: previous.offset + span.start,
+                    char_offset: previous.char_offset
+                        + location::char_offset(&self.source, span.start),
+                    line: previous.line + line - 1,
+                });
+                self.source = Arc::from(&self.source[span]);
+            }
+        }
+        self
     }
 
     pub fn rule(&self) -> &str {
         &self.rule
     }
 
+    pub fn rule_key(&self) -> Option<&str> {
+        if self.rule_key.is_empty() {
+            None
+        } else {
+            Some(&self.rule_key)
+        }
+    }
+
     pub fn checker(&self) -> &str {
         &self.checker
     }
 
+    pub fn checker_index(&self) -> usize {
+        self.checker_index
+    }
+
     pub fn description(&self) -> Option<&str> {
         if self.description.is_empty() {
             None
@@ -75,32 +602,1003 @@ impl<'a> RuleMatchReport<'a> {
         self.severity
     }
 
+    // only recorded by `verbose`
+    pub fn author(&self) -> Option<&str> {
+        if self.author.is_empty() {
+            None
+        } else {
+            Some(&self.author)
+        }
+    }
+
+    // the file the rule was loaded from; only recorded by `verbose`
+    pub fn rule_path(&self) -> Option<&Path> {
+        self.rule_path.as_deref()
+    }
+
+    // see `RuleMatch::fingerprint`; absent for reports created before
+    // fingerprints were recorded
+    pub fn fingerprint(&self) -> Option<&str> {
+        if self.fingerprint.is_empty() {
+            None
+        } else {
+            Some(&self.fingerprint)
+        }
+    }
+
     pub fn tags(&self) -> &FxHashSet<String> {
         &self.tags
     }
 
+    // the metadata of the rule; only recorded by `verbose`
+    pub fn metadata(&self) -> &BTreeMap<String, Value> {
+        &self.metadata
+    }
+
+    // e.g., `metadata_get("owner")` to route findings
+    pub fn metadata_get(&self, key: &str) -> Option<&Value> {
+        self.metadata.get(key)
+    }
+
     pub fn source(&self) -> &str {
         &self.source
     }
 
+    // absent unless the report embeds a snippet of the source
+    pub fn source_base(&self) -> Option<&SourceBase> {
+        self.source_base.as_ref()
+    }
+
+    fn base_offset(&self) -> usize {
+        self.source_base.map_or(0, |base| base.offset)
+    }
+
     pub fn result(&self) -> &QueryResult {
         &self.match_result
     }
 
+    // absent for reports created before locations were recorded
+    pub fn location(&self) -> Option<&ReportLocation> {
+        self.location.as_ref()
+    }
+
+    // see `RuleMatch::address`
+    pub fn address(&self) -> Option<u64> {
+        self.address
+    }
+
+    // the values of the rule's variables, resolved when the report was created
+    // (and so available whatever its source mode); absent for reports created
+    // before captures were recorded
+    pub fn captures(&self) -> &BTreeMap<String, String> {
+        &self.captures
+    }
+
+    // absent for reports without a snippet, e.g., created before snippets
+    // were recorded
+    pub fn snippet(&self) -> Option<&ReportSnippet> {
+        self.snippet.as_ref()
+    }
+
+    // why the finding was suppressed, if it was retained nonetheless
+    pub fn suppression(&self) -> Option<&SuppressionReason> {
+        self.suppression.as_ref()
+    }
+
+    pub fn is_suppressed(&self) -> bool {
+        self.suppression.is_some()
+    }
+
+    // e.g., for findings suppressed by a baseline or by the configuration
+    pub fn with_suppression(mut self, reason: SuppressionReason) -> Self {
+        self.suppression = Some(reason);
+        self
+    }
+
+    // the identity of the finding (see `ReportKey`)
+    pub fn key(&self) -> ReportKey<'_> {
+        ReportKey::new(self)
+    }
+
+    // the number of duplicates of this report removed by `dedup_counting`
+    pub fn duplicates(&self) -> usize {
+        self.duplicates
+    }
+
+    // the path of the file the match was found in, if any
+    pub fn path(&self) -> Option<&Path> {
+        self.location.as_ref()?.path()
+    }
+
+    // NOTE: offsets are byte offsets into the scanned source, which is
+    // `source` unless the report embeds a snippet
+    pub fn start_offset(&self) -> usize {
+        match self.location {
+            Some(ref location) => location.start_offset,
+            None => self.base_offset() + matcher::match_span(&self.match_result).start,
+        }
+    }
+
+    pub fn end_offset(&self) -> usize {
+        match self.location {
+            Some(ref location) => location.end_offset,
+            None => self.base_offset() + matcher::match_span(&self.match_result).end,
+        }
+    }
+
+    // the span of the match within `source`
+    fn source_span(&self) -> Range<usize> {
+        let base = self.base_offset();
+        self.start_offset().saturating_sub(base)..self.end_offset().saturating_sub(base)
+    }
+
+    pub fn matched_text(&self) -> &str {
+        location::slice(&self.source, self.source_span())
+    }
+
+    pub fn char_start(&self) -> usize {
+        self.source_base.map_or(0, |base| base.char_offset)
+            + location::char_offset(&self.source, self.source_span().start)
+    }
+
+    pub fn char_end(&self) -> usize {
+        self.source_base.map_or(0, |base| base.char_offset)
+            + location::char_offset(&self.source, self.source_span().end)
+    }
+
+    fn line_column(&self, offset: usize) -> (usize, usize) {
+        let (line, column) = location::line_column(&self.source, offset);
+        (
+            self.source_base.map_or(1, |base| base.line) + line - 1,
+            column,
+        )
+    }
+
+    // NOTE: lines and columns are 1-based; columns count characters
+    pub fn start_line(&self) -> usize {
+        match self.location {
+            Some(ref location) => location.start_line,
+            None => self.line_column(self.source_span().start).0,
+        }
+    }
+
+    pub fn start_column(&self) -> usize {
+        match self.location {
+            Some(ref location) => location.start_column,
+            None => self.line_column(self.source_span().start).1,
+        }
+    }
+
+    pub fn end_line(&self) -> usize {
+        match self.location {
+            Some(ref location) => location.end_line,
+            None => self.line_column(self.source_span().end).0,
+        }
+    }
+
+    pub fn end_column(&self) -> usize {
+        match self.location {
+            Some(ref location) => location.end_column,
+            None => self.line_column(self.source_span().end).1,
+        }
+    }
+
     pub fn display(&self, before: usize, after: usize, line_numbers: bool) -> String {
-        self.match_result
-            .display(&self.source, before, after, line_numbers)
+        let display = self.display_with(&DisplayOptions::positional(before, after, line_numbers));
+
+        // like grep, prefix the context with where it was found
+        match self.path() {
+            Some(path) if !display.is_empty() => {
+                format!("{}:{}:\n{display}", path.display(), self.start_line())
+            }
+            _ => display,
+        }
+    }
+
+    // a view of the report that omits the (potentially large) source
+    pub(crate) fn without_source(&self) -> RuleMatchReport<'_> {
+        RuleMatchReport {
+            rule: Cow::Borrowed(&self.rule),
+            rule_key: Cow::Borrowed(&self.rule_key),
+            checker_index: self.checker_index,
+            description: Cow::Borrowed(&self.description),
+            author: Cow::Borrowed(&self.author),
+            rule_path: self.rule_path.as_deref().map(Cow::Borrowed),
+            checker: Cow::Borrowed(&self.checker),
+            tags: Cow::Borrowed(&self.tags),
+            metadata: Cow::Borrowed(&self.metadata),
+            severity: self.severity,
+            fingerprint: Cow::Borrowed(&self.fingerprint),
+            source: Arc::from(""),
+            source_base: None,
+            location: self.location.clone(),
+            address: self.address,
+            snippet: self.snippet.clone(),
+            captures: Cow::Borrowed(&self.captures),
+            suppression: self.suppression.clone(),
+            duplicates: self.duplicates,
+            match_result: Cow::Borrowed(&self.match_result),
+        }
     }
 
     pub fn into_owned(self) -> RuleMatchReport<'static> {
         RuleMatchReport {
             rule: self.rule.into_owned().into(),
+            rule_key: self.rule_key.into_owned().into(),
+            checker_index: self.checker_index,
             description: self.description.into_owned().into(),
+            author: self.author.into_owned().into(),
+            rule_path: self.rule_path.map(|path| Cow::Owned(path.into_owned())),
             checker: self.checker.into_owned().into(),
             tags: Cow::Owned(self.tags.into_owned()),
+            metadata: Cow::Owned(self.metadata.into_owned()),
             severity: self.severity,
+            fingerprint: self.fingerprint.into_owned().into(),
             source: self.source,
+            source_base: self.source_base,
+            location: self.location,
+            address: self.address,
+            snippet: self.snippet,
+            captures: Cow::Owned(self.captures.into_owned()),
+            suppression: self.suppression,
+            duplicates: self.duplicates,
             match_result: Cow::Owned(self.match_result.into_owned()),
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+
+    use std::borrow::Cow;
+
+    use rustc_hash::{FxHashMap, FxHashSet};
+
+    use weggli::result::{CaptureResult, QueryResult};
+
+    use super::{path_uri, RuleMatchReport, SourceMode};
+    use crate::matcher::RuleMatcher;
+
+    #[test]
+    fn test_path_uri() {
+        assert_eq!(path_uri("src/a b.c".as_ref()), "src/a%20b.c");
+        assert_eq!(path_uri("../src/./a.c".as_ref()), "../src/a.c");
+        assert_eq!(
+            path_uri("/src/a b/名.c".as_ref()),
+            "file:///src/a%20b/%E5%90%8D.c"
+        );
+
+        #[cfg(windows)]
+        {
+            assert_eq!(path_uri(r"C:\src\a.c".as_ref()), "file:///C:/src/a.c");
+            assert_eq!(
+                path_uri(r"\\server\share\a.c".as_ref()),
+                "file://server/share/a.c"
+            );
+        }
+    }
+
+    #[test]
+    fn test_utf8_offsets() -> Result<(), Box<dyn std::error::Error>> {
+        let rule = r#"
+id: unbounded-copy
+check pattern:
+  regex: func=^strcpy$
+  pattern: |
+    { $func($dst, _); }
+"#;
+
+        let source = fs::read_to_string("tests/utf8-comments.c")?;
+
+        let mut matcher = RuleMatcher::from_str(rule)?;
+        let matches = matcher.matches(&source)?;
+
+        assert_eq!(matches.len(), 2);
+
+        let first = &matches[0];
+        let report = RuleMatchReport::new(first);
+
+        assert_eq!(first.matched_text(), "strcpy(dst");
+        assert_eq!(report.matched_text(), first.matched_text());
+
+        assert_eq!((first.start_line(), first.start_column()), (6, 5));
+        assert_eq!((first.end_line(), first.end_column()), (6, 15));
+        assert_eq!((report.end_line(), report.end_column()), (6, 15));
+
+        let char_start = source
+            .char_indices()
+            .position(|(i, _)| i == first.start_offset())
+            .unwrap();
+
+        assert_eq!(first.char_start(), char_start);
+        assert_eq!(report.char_start(), char_start);
+        assert!(first.start_offset() > first.char_start());
+
+        let second = &matches[1];
+
+        assert_eq!(second.matched_text(), "strcpy(名前");
+        assert_eq!(second.end_offset() - second.start_offset(), 13);
+        assert_eq!(second.char_end() - second.char_start(), 9);
+        assert_eq!((second.start_line(), second.start_column()), (12, 13));
+        assert_eq!((second.end_line(), second.end_column()), (12, 22));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_report_location() -> Result<(), Box<dyn std::error::Error>> {
+        let rule = r#"
+id: unbounded-copy
+check pattern:
+  regex: func=^strcpy$
+  pattern: |
+    { $func($dst, _); }
+"#;
+
+        let mut matcher = RuleMatcher::from_str(rule)?;
+        let matches = matcher.matches_file("tests/utf8-comments.c")?;
+
+        let m = &matches[0];
+        let report = RuleMatchReport::new(m);
+        let location = report.location().unwrap();
+
+        assert_eq!(location.path(), m.origin());
+        assert_eq!(
+            (location.start_line(), location.start_column()),
+            (m.start_line(), m.start_column())
+        );
+        assert_eq!(location.end_offset(), m.end_offset());
+
+        let value = serde_yaml::to_value(&report)?;
+
+        assert_eq!(
+            value["location"]["path"].as_str(),
+            Some("tests/utf8-comments.c")
+        );
+        assert_eq!(value["location"]["start_line"].as_u64(), Some(6));
+        assert!(value.get("match").is_some());
+
+        // reports without the raw result retain their positions
+        let value = serde_yaml::to_value(RuleMatchReport::new(m).omit_result())?;
+
+        assert!(value.get("match").is_none());
+
+        let omitted = serde_yaml::from_value::<RuleMatchReport>(value)?;
+
+        assert_eq!(omitted.start_line(), 6);
+        assert_eq!(omitted.matched_text(), m.matched_text());
+        assert_eq!(omitted.display(0, 0, false), "");
+
+        // reports written before locations were recorded
+        let mut value = serde_yaml::to_value(&report)?;
+        value.as_mapping_mut().unwrap().remove("location");
+
+        let legacy = serde_yaml::from_value::<RuleMatchReport>(value)?;
+
+        assert!(legacy.location().is_none());
+        assert_eq!(legacy.start_offset(), m.start_offset());
+        assert_eq!(legacy.end_column(), m.end_column());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_report_path() -> Result<(), Box<dyn std::error::Error>> {
+        let rule = r#"
+id: unbounded-copy
+check pattern:
+  regex: func=^strcpy$
+  pattern: |
+    { $func($dst, _); }
+"#;
+
+        let mut matcher = RuleMatcher::from_str(rule)?;
+
+        let source = fs::read_to_string("tests/utf8-comments.c")?;
+        let from_file = matcher.matches_file("tests/utf8-comments.c")?;
+        let from_source = matcher.matches(&source)?;
+
+        let with_path = RuleMatchReport::new(&from_file[0]);
+        let without_path = RuleMatchReport::new(&from_source[0]);
+
+        assert_eq!(
+            with_path.path().and_then(|p| p.to_str()),
+            Some("tests/utf8-comments.c")
+        );
+        assert_eq!(without_path.path(), None);
+
+        let value = serde_yaml::to_value(&with_path)?;
+        let owned = serde_yaml::from_value::<RuleMatchReport>(value)?.into_owned();
+
+        assert_eq!(owned.path(), with_path.path());
+
+        let value = serde_yaml::to_value(&without_path)?;
+
+        assert!(value["location"].get("path").is_none());
+        assert_eq!(
+            serde_yaml::from_value::<RuleMatchReport>(value)?.path(),
+            None
+        );
+
+        let display = with_path.display(0, 0, false);
+
+        assert_eq!(
+            display.lines().next(),
+            Some(format!("tests/utf8-comments.c:{}:", with_path.start_line()).as_str())
+        );
+        assert_eq!(
+            display.split_once('\n').map(|(_, rest)| rest),
+            Some(without_path.display(0, 0, false).as_str())
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_report_display_out_of_bounds() -> Result<(), Box<dyn std::error::Error>> {
+        let rule = r#"
+id: unbounded-copy
+check pattern:
+  regex: func=^strcpy$
+  pattern: |
+    { $func(_, _); }
+"#;
+
+        let source = fs::read_to_string("tests/utf8-comments.c")?;
+
+        let mut matcher = RuleMatcher::from_str(rule)?;
+        let matches = matcher.matches(&source)?;
+
+        let mut report = serde_yaml::to_value(RuleMatchReport::new(&matches[0]))?;
+        report["source"] = serde_yaml::Value::from("// 🦀");
+
+        let report = serde_yaml::from_value::<RuleMatchReport>(report)?;
+
+        assert_eq!(report.display(1, 1, true), "");
+        assert_eq!(report.matched_text(), "");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_check_identity() -> Result<(), Box<dyn std::error::Error>> {
+        let source = r#"
+void f(char *dst, const char *src) {
+    char line[32];
+    gets(line);
+    strcpy(dst, src);
+}
+"#;
+
+        let rule = r#"
+id: unbounded-copy
+check-patterns:
+- name: strcpy
+  regex: func=^st(r|p)cpy$
+  pattern: |
+    { $func($dst, _); }
+- name: gets
+  regex: func=^gets$
+  pattern: |
+    { $func(_); }
+"#;
+
+        let mut matcher = RuleMatcher::from_str(rule)?;
+        let matches = matcher.matches(source)?;
+
+        assert_eq!(matches.len(), 2);
+
+        let key = matcher.rules().key(0).unwrap();
+
+        let mut checks = matches.iter().map(|m| m.check_ref()).collect::<Vec<_>>();
+        checks.sort_by_key(|c| c.3);
+
+        assert_eq!(checks[0], (key, "unbounded-copy", "strcpy", 0));
+        assert_eq!(checks[1], (key, "unbounded-copy", "gets", 1));
+
+        for m in matches.iter() {
+            assert_eq!(m.checker().name(), m.check_ref().2);
+            assert_eq!(matcher.rules().key(m.rule_id()), Some(m.rule_key()));
+
+            let report = serde_yaml::to_string(&RuleMatchReport::new(m))?;
+            let report = serde_yaml::from_str::<RuleMatchReport>(&report)?.into_owned();
+
+            assert_eq!(report.rule(), m.rule().id());
+            assert_eq!(report.rule_key(), Some(m.rule_key()));
+            assert_eq!(report.checker(), m.checker().name());
+            assert_eq!(report.checker_index(), m.checker_id());
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_source_mode() -> Result<(), Box<dyn std::error::Error>> {
+        let rule = r#"
+id: unbounded-copy
+check pattern:
+  regex: func=^strcpy$
+  pattern: |
+    { $func($dst, _); }
+"#;
+
+        let mut matcher = RuleMatcher::from_str(rule)?;
+        let matches = matcher.matches_file("tests/ls-main.c")?;
+
+        let m = matches.first().unwrap();
+        let full = RuleMatchReport::new(m);
+
+        let serialized = |report: &RuleMatchReport| serde_json::to_string(report).unwrap();
+        let sizes = [
+            SourceMode::Full,
+            SourceMode::Snippet {
+                before: 2,
+                after: 2,
+            },
+            SourceMode::Omit,
+        ]
+        .map(|mode| {
+            let report = RuleMatchReport::new(m).without_snippet();
+            serialized(&report.with_source_mode(mode)).len()
+        });
+
+        // the full source dominates the size of the serialized report
+        assert!(sizes[0] > 30_000);
+        assert!(sizes[1] < 1_000);
+        assert!(sizes[2] < sizes[1]);
+
+        // the snippet remains without the source
+        let omitted = RuleMatchReport::new(m).with_source_mode(SourceMode::Omit);
+
+        assert!(serialized(&omitted).len() < 1_000);
+        assert!(omitted.snippet().is_some());
+
+        let snippet = RuleMatchReport::new(m).with_source_mode(SourceMode::Snippet {
+            before: 2,
+            after: 2,
+        });
+        let base = *snippet.source_base().unwrap();
+
+        assert_eq!(
+            snippet.source().split('\n').count(),
+            full.end_line() - full.start_line() + 5
+        );
+        assert_eq!(base.line(), full.start_line() - 2);
+        assert_eq!(
+            &full.source()[base.offset()..][..snippet.source().len()],
+            snippet.source()
+        );
+
+        for report in [
+            serde_json::from_str::<RuleMatchReport>(&serialized(&snippet))?,
+            snippet,
+        ] {
+            assert_eq!(report.matched_text(), full.matched_text());
+            assert_eq!(report.start_offset(), full.start_offset());
+            assert_eq!(report.char_start(), full.char_start());
+            assert_eq!(report.char_end(), full.char_end());
+            assert_eq!(report.source_base(), Some(&base));
+
+            // the match is displayed with its line number in the scanned file
+            let line = format!(
+                "{:>4}: {}",
+                full.start_line(),
+                full.source().lines().nth(full.start_line() - 1).unwrap()
+            );
+            assert!(report.display(1, 1, true).lines().any(|l| l == line));
+        }
+
+        let omitted = RuleMatchReport::new(m).with_source_mode(SourceMode::Omit);
+        let omitted = serde_json::from_str::<RuleMatchReport>(&serialized(&omitted))?;
+
+        assert!(omitted.source().is_empty());
+        assert_eq!(omitted.source_base(), None);
+        assert_eq!(omitted.matched_text(), "");
+        assert_eq!(omitted.start_line(), full.start_line());
+
+        let full = serde_json::from_str::<RuleMatchReport>(&serialized(&full))?;
+
+        assert_eq!(full.source(), m.source().as_ref());
+        assert_eq!(full.source_base(), None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_deterministic_serialization() -> Result<(), Box<dyn std::error::Error>> {
+        let rule = r#"
+id: unbounded-copy
+tags: [CWE-120, CWE-676, CWE-787, CWE-20, memory, copy, strings]
+check pattern:
+  regex: func=^strcpy$
+  pattern: '{ $func($dst, $src); }'
+"#;
+
+        let mut matcher = RuleMatcher::from_str(rule)?;
+        let matches = matcher.matches("void f(char *d, char *s) { strcpy(d, s); }")?;
+        let m = &matches[0];
+
+        // the same tags and variables, stored in hash tables with a different
+        // history (and so, likely, a different iteration order)
+        let reshuffled = || {
+            let mut report = RuleMatchReport::new(m);
+
+            let mut tags = FxHashSet::with_capacity_and_hasher(1024, Default::default());
+            let mut entries = m.rule().tags().iter().cloned().collect::<Vec<_>>();
+            entries.sort();
+            entries.reverse();
+            tags.extend(entries);
+            report.tags = Cow::Owned(tags);
+
+            let mut vars = FxHashMap::with_capacity_and_hasher(1024, Default::default());
+            let mut entries = m.result().vars.iter().collect::<Vec<_>>();
+            entries.sort();
+            entries.reverse();
+            vars.extend(entries.into_iter().map(|(k, v)| (k.clone(), *v)));
+
+            let mut result = m.result().clone();
+            result.vars = vars;
+            report.match_result = Cow::Owned(result);
+
+            report
+        };
+
+        let original = serde_json::to_string(&RuleMatchReport::new(m))?;
+
+        assert_eq!(serde_json::to_string(&reshuffled())?, original);
+        assert_eq!(
+            serde_yaml::to_string(&reshuffled())?,
+            serde_yaml::to_string(&RuleMatchReport::new(m))?
+        );
+
+        let value = serde_json::from_str::<serde_json::Value>(&original)?;
+
+        assert_eq!(value["tags"][0], "CWE-120");
+        assert_eq!(value["tags"][6], "strings");
+
+        let vars = value["match"]["vars"]
+            .as_object()
+            .unwrap()
+            .keys()
+            .collect::<Vec<_>>();
+
+        assert_eq!(vars, ["$dst", "$func", "$src"]);
+
+        // ...and still round-trips
+        let report = serde_json::from_str::<RuleMatchReport>(&original)?;
+
+        assert_eq!(report.result(), m.result());
+        assert_eq!(report.tags(), m.rule().tags());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_verbose_report() -> Result<(), Box<dyn std::error::Error>> {
+        let dir =
+            std::env::temp_dir().join(format!("weggli-ruleset-verbose-{}", std::process::id()));
+        fs::create_dir_all(&dir)?;
+
+        let path = dir.join("unbounded-copy.yml");
+        fs::write(
+            &path,
+            r#"
+id: unbounded-copy
+author: appsec@example.com
+check pattern:
+  regex: func=^strcpy$
+  pattern: |
+    { $func($dst, _); }
+"#,
+        )?;
+
+        let mut matcher = RuleMatcher::from_directory_with(&dir, false)?;
+        let matches = matcher.matches_file("tests/utf8-comments.c")?;
+
+        let m = &matches[0];
+
+        let terse = RuleMatchReport::new(m);
+        let verbose = RuleMatchReport::verbose(m);
+
+        assert_eq!(terse.author(), None);
+        assert_eq!(terse.rule_path(), None);
+        assert_eq!(verbose.author(), Some("appsec@example.com"));
+        assert_eq!(verbose.rule_path(), Some(path.as_path()));
+
+        let value = serde_json::to_value(&terse)?;
+
+        assert!(value.get("author").is_none());
+        assert!(value.get("rule_path").is_none());
+
+        let value = serde_json::to_value(&verbose)?;
+
+        assert_eq!(value["author"], "appsec@example.com");
+        assert_eq!(value["rule_path"], path.to_str().unwrap());
+
+        for report in [&terse, &verbose] {
+            let restored =
+                serde_yaml::from_str::<RuleMatchReport>(&serde_yaml::to_string(report)?)?;
+
+            assert_eq!(restored.author(), report.author());
+            assert_eq!(restored.rule_path(), report.rule_path());
+            assert_eq!(restored.rule(), report.rule());
+            assert_eq!(
+                serde_json::to_string(&restored.into_owned())?,
+                serde_json::to_string(report)?
+            );
+        }
+
+        // rules loaded from a string have no path
+        let mut matcher =
+            RuleMatcher::from_str("id: copy\ncheck pattern:\n  pattern: 'strcpy(_, _);'\n")?;
+        let matches = matcher.matches("void f(char *d, char *s) { strcpy(d, s); }")?;
+
+        assert_eq!(RuleMatchReport::verbose(&matches[0]).rule_path(), None);
+
+        fs::remove_dir_all(&dir)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_rule_path_round_trip() -> Result<(), Box<dyn std::error::Error>> {
+        let dir =
+            std::env::temp_dir().join(format!("weggli-ruleset-rule-path-{}", std::process::id()));
+        fs::create_dir_all(&dir)?;
+
+        let path = dir.join("copy.yml");
+        fs::write(
+            &path,
+            "id: copy\ncheck pattern:\n  pattern: 'strcpy(_, _);'\n",
+        )?;
+
+        // the key is relative to the directory, but the path is not
+        let mut matcher = RuleMatcher::from_directory_with(&dir, false)?;
+        let matches = matcher.matches("void f(char *d, char *s) { strcpy(d, s); }")?;
+
+        assert_eq!(matches[0].rule_key(), "copy.yml");
+
+        let json = serde_json::to_string(&RuleMatchReport::verbose(&matches[0]))?;
+        let report = serde_json::from_str::<RuleMatchReport>(&json)?.into_owned();
+
+        assert_eq!(report.rule_path(), Some(path.as_path()));
+        assert_eq!(report.rule_key(), Some("copy.yml"));
+
+        fs::remove_dir_all(&dir)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_serialize_match() -> Result<(), Box<dyn std::error::Error>> {
+        let rule = r#"
+id: unbounded-copy
+check pattern:
+  regex: func=^strcpy$
+  pattern: |
+    { $func($dst, _); }
+"#;
+
+        let mut matcher = RuleMatcher::from_str(rule)?;
+        let matches = matcher.matches_file("tests/utf8-comments.c")?;
+
+        for m in &matches {
+            assert_eq!(
+                serde_json::to_vec(m)?,
+                serde_json::to_vec(&RuleMatchReport::new(m))?
+            );
+        }
+
+        // e.g., as JSONL
+        let mut output = Vec::new();
+        for m in &matches {
+            serde_json::to_writer(&mut output, m)?;
+            output.push(b'\n');
+        }
+
+        let reports = output
+            .split(|b| *b == b'\n')
+            .filter(|line| !line.is_empty())
+            .map(serde_json::from_slice::<RuleMatchReport>)
+            .collect::<Result<Vec<_>, _>>()?;
+
+        assert_eq!(reports.len(), matches.len());
+        assert_eq!(reports[0].fingerprint(), Some(matches[0].fingerprint()));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_report_metadata() -> Result<(), Box<dyn std::error::Error>> {
+        let large = "x".repeat(100_000);
+        let rule = format!(
+            r#"
+id: copy
+metadata:
+  owner: platform-security
+  references:
+  - https://cwe.mitre.org/data/definitions/120.html
+  - id: 42
+    verified: true
+  routing:
+    teams: [firmware, kernel]
+    escalate: {{after: 3, to: oncall}}
+  notes: {large}
+check pattern:
+  pattern: 'strcpy(_, _);'
+"#
+        );
+
+        let mut matcher = RuleMatcher::from_str(&rule)?;
+        let matches = matcher.matches("void f(char *d, char *s) { strcpy(d, s); }")?;
+
+        let terse = RuleMatchReport::new(&matches[0]);
+        let verbose = RuleMatchReport::verbose(&matches[0]);
+
+        assert!(terse.metadata().is_empty());
+        assert!(serde_json::to_value(&terse)?.get("metadata").is_none());
+        assert_eq!(
+            verbose.metadata_get("owner"),
+            Some(&"platform-security".into())
+        );
+        assert_eq!(verbose.metadata_get("missing"), None);
+
+        // keys are sorted at any depth, and values are not truncated
+        let json = serde_json::to_string(&verbose)?;
+        let keys = ["\"notes\"", "\"owner\"", "\"references\"", "\"routing\""];
+        let positions = keys.map(|key| json.find(key).unwrap());
+        assert!(positions.is_sorted());
+        assert!(json.find("\"escalate\"").unwrap() < json.find("\"teams\"").unwrap());
+        assert!(json.contains(&large));
+
+        for restored in [
+            serde_json::from_str::<RuleMatchReport>(&json)?,
+            serde_yaml::from_str::<RuleMatchReport>(&serde_yaml::to_string(&verbose)?)?,
+        ] {
+            assert_eq!(restored.metadata(), verbose.metadata());
+            assert_eq!(
+                restored.metadata_get("routing").unwrap()["escalate"]["after"],
+                3
+            );
+            assert_eq!(
+                restored.metadata_get("references").unwrap()[1]["verified"],
+                true
+            );
+        }
+
+        // ...and rules serialize with their metadata
+        let yaml = serde_yaml::to_string(matches[0].rule())?;
+        let reloaded = crate::rule::Rule::from_str(&yaml)?;
+        assert_eq!(reloaded.metadata(), matches[0].rule().metadata());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_snippet() -> Result<(), Box<dyn std::error::Error>> {
+        let rule = r#"
+id: unbounded-copy
+check pattern:
+  regex: func=^strcpy$
+  pattern: |
+    { $func($dst, _); }
+"#;
+
+        let mut matcher = RuleMatcher::from_str(rule)?;
+        let matches = matcher.matches_file("tests/edge-copy.c")?;
+
+        assert_eq!(matches.len(), 2);
+
+        let (first, last) = if matches[0].start_line() == 1 {
+            (&matches[0], &matches[1])
+        } else {
+            (&matches[1], &matches[0])
+        };
+
+        // the context is clipped at the start of the file
+        let report = RuleMatchReport::new(first);
+        let snippet = report.snippet().unwrap();
+
+        assert_eq!(snippet.start_line(), 1);
+        assert_eq!(snippet.end_line(), 3);
+        assert!(snippet.text().starts_with("void first"));
+        assert!(!snippet.text().contains('\x1b'));
+        assert_eq!(
+            snippet.numbered().lines().next(),
+            Some("   1: void first(char *s) { strcpy(s, \"first\"); }")
+        );
+
+        // ... and at its end, which has no final newline
+        let report = RuleMatchReport::new(last).with_snippet(1, 10);
+        let snippet = report.snippet().unwrap();
+
+        assert_eq!(snippet.start_line(), 4);
+        assert_eq!(snippet.end_line(), 5);
+        assert_eq!(
+            snippet.lines().collect::<Vec<_>>(),
+            [(4, ""), (5, "void last(char *s) { strcpy(s, \"last\"); }")]
+        );
+
+        // the snippet outlives the source, and is limited to the embedded one
+        let omitted = RuleMatchReport::new(last).with_source_mode(SourceMode::Omit);
+
+        assert_eq!(omitted.snippet(), RuleMatchReport::new(last).snippet());
+        assert_eq!(
+            RuleMatchReport::new(last)
+                .with_source_mode(SourceMode::Omit)
+                .with_snippet(0, 0)
+                .snippet(),
+            omitted.snippet()
+        );
+
+        let narrow = RuleMatchReport::new(last)
+            .with_source_mode(SourceMode::Snippet {
+                before: 0,
+                after: 0,
+            })
+            .with_snippet(2, 2);
+
+        assert_eq!(narrow.snippet().unwrap().start_line(), 5);
+        assert_eq!(narrow.snippet().unwrap().end_line(), 5);
+
+        let json = serde_json::to_string(&omitted)?;
+        let restored = serde_json::from_str::<RuleMatchReport>(&json)?;
+
+        assert_eq!(restored.snippet(), omitted.snippet());
+        assert_eq!(restored.snippet().unwrap().start_line(), 3);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_captures() -> Result<(), Box<dyn std::error::Error>> {
+        let rule = r#"
+id: unbounded-copy
+check pattern:
+  regex: func=^strcpy$
+  pattern: |
+    { $func($dst, _); }
+"#;
+
+        let mut matcher = RuleMatcher::from_str(rule)?;
+        let matches = matcher.matches_file("tests/utf8-comments.c")?;
+
+        let m = matches.iter().find(|m| m.start_line() == 12).unwrap();
+        let report = RuleMatchReport::new(m).with_source_mode(SourceMode::Omit);
+
+        assert_eq!(report.captures()["$func"], "strcpy");
+        assert_eq!(report.captures()["$dst"], "名前");
+
+        let value = serde_json::to_value(&report)?;
+        let vars = value["captures"]
+            .as_object()
+            .unwrap()
+            .keys()
+            .collect::<Vec<_>>();
+
+        assert_eq!(vars, ["$dst", "$func"]);
+
+        let restored = serde_json::from_value::<RuleMatchReport>(value)?;
+
+        assert_eq!(restored.captures(), report.captures());
+
+        // variables outside the source are left out
+        let capture = |range| CaptureResult {
+            range,
+            query_id: 0,
+            capture_idx: 0,
+        };
+        let vars = FxHashMap::from_iter([(String::from("$a"), 1), (String::from("$b"), 2)]);
+        let result = QueryResult::new(
+            vec![capture(0..3), capture(0..3), capture(2..99)],
+            vars,
+            0..3,
+        );
+        let captures = super::captures("名", &result);
+
+        assert_eq!(captures.into_iter().collect::<Vec<_>>(), [("$a", "名")]);
+
+        Ok(())
+    }
+}
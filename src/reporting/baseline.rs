@@ -0,0 +1,362 @@
+use std::collections::BTreeMap;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::matcher::SuppressionReason;
+use crate::reporting::metadata::rfc3339;
+use crate::reporting::RuleMatchReport;
+
+// the version of the format of baseline files, bumped whenever it changes
+pub const BASELINE_VERSION: u32 = 1;
+
+#[derive(Debug, Error)]
+pub enum BaselineError {
+    #[error("cannot read or write baseline: {0}")]
+    Io(#[from] io::Error),
+    #[error("cannot parse baseline: {0}")]
+    Parse(serde_json::Error),
+    #[error("cannot serialize baseline: {0}")]
+    Serialize(serde_json::Error),
+    #[error("unsupported baseline version {0} (at most {BASELINE_VERSION} is supported)")]
+    UnsupportedVersion(u32),
+}
+
+// A known finding, identified by its fingerprint; the rule and path are
+// recorded for the reader of the baseline, and are not used for matching.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct BaselineEntry {
+    fingerprint: String,
+    rule: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    path: Option<PathBuf>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    note: Option<String>,
+    // RFC 3339, in UTC
+    created: String,
+}
+
+impl BaselineEntry {
+    pub fn fingerprint(&self) -> &str {
+        &self.fingerprint
+    }
+
+    pub fn rule(&self) -> &str {
+        &self.rule
+    }
+
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    pub fn note(&self) -> Option<&str> {
+        self.note.as_deref()
+    }
+
+    pub fn created(&self) -> &str {
+        &self.created
+    }
+}
+
+// How `Baseline::update` combines a baseline with the findings of a new scan.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum BaselineMode {
+    // keeps the entries of the baseline, and adds those of new findings
+    // (e.g., for a scan whose baselined findings were dropped)
+    #[default]
+    Union,
+    // keeps only the entries of the findings of the scan, e.g., to forget
+    // fixed findings (the scan must retain the baselined findings)
+    Replace,
+}
+
+// A set of known findings, to be suppressed in later scans (see `apply`),
+// serialized as a versioned JSON object with the entries ordered by path,
+// rule, and fingerprint:
+//
+//   {"version": 1, "entries": [{"fingerprint": "...", "rule": "...", ...}]}
+//
+// NOTE: reports without a fingerprint (e.g., of the first version of the
+// report schema) cannot be baselined, and are never suppressed
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Baseline {
+    entries: BTreeMap<String, BaselineEntry>,
+}
+
+#[derive(Deserialize, Serialize)]
+struct BaselineFile<E> {
+    version: u32,
+    entries: Vec<E>,
+}
+
+impl Baseline {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // the findings of `reports`, except those suppressed otherwise than by a
+    // baseline (e.g., by comments)
+    pub fn from_reports(reports: &[RuleMatchReport]) -> Self {
+        Self::from_reports_at(reports, SystemTime::now())
+    }
+
+    pub fn from_reports_at(reports: &[RuleMatchReport], created: SystemTime) -> Self {
+        let created = rfc3339(created);
+
+        let entries = reports
+            .iter()
+            .filter(|report| {
+                matches!(
+                    report.suppression(),
+                    None | Some(SuppressionReason::Baseline { .. })
+                )
+            })
+            .filter_map(|report| {
+                let fingerprint = report.fingerprint()?;
+
+                Some((
+                    fingerprint.to_owned(),
+                    BaselineEntry {
+                        fingerprint: fingerprint.to_owned(),
+                        rule: report.rule().to_owned(),
+                        path: report.path().map(Path::to_path_buf),
+                        note: None,
+                        created: created.clone(),
+                    },
+                ))
+            })
+            .collect();
+
+        Self { entries }
+    }
+
+    // combines the baseline with the findings of `reports` (see
+    // `BaselineMode`); the entries of known findings keep their note and
+    // creation time
+    pub fn update(mut self, reports: &[RuleMatchReport], mode: BaselineMode) -> Self {
+        let found = Self::from_reports(reports);
+
+        if mode == BaselineMode::Replace {
+            self.entries
+                .retain(|fingerprint, _| found.entries.contains_key(fingerprint));
+        }
+
+        for (fingerprint, entry) in found.entries {
+            self.entries.entry(fingerprint).or_insert(entry);
+        }
+
+        self
+    }
+
+    // sets the note of the entry of `fingerprint`, e.g., why the finding was
+    // accepted; false if there is no such entry
+    pub fn annotate(&mut self, fingerprint: &str, note: impl Into<String>) -> bool {
+        match self.entries.get_mut(fingerprint) {
+            Some(entry) => {
+                entry.note = Some(note.into());
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn contains(&self, fingerprint: &str) -> bool {
+        self.entries.contains_key(fingerprint)
+    }
+
+    pub fn get(&self, fingerprint: &str) -> Option<&BaselineEntry> {
+        self.entries.get(fingerprint)
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn entries(&self) -> impl Iterator<Item = &BaselineEntry> {
+        self.entries.values()
+    }
+
+    // why `report` is suppressed by the baseline, if it is (e.g., for
+    // `ReportSet::suppress`)
+    pub fn reason(&self, report: &RuleMatchReport) -> Option<SuppressionReason> {
+        let fingerprint = report.fingerprint()?;
+
+        self.contains(fingerprint)
+            .then(|| SuppressionReason::Baseline {
+                fingerprint: fingerprint.to_owned(),
+            })
+    }
+
+    // splits `reports` into the active and the suppressed findings, marking
+    // those in the baseline as suppressed (reports already suppressed are
+    // kept as they are)
+    pub fn apply<'a>(
+        &self,
+        reports: impl IntoIterator<Item = RuleMatchReport<'a>>,
+    ) -> (Vec<RuleMatchReport<'a>>, Vec<RuleMatchReport<'a>>) {
+        let mut active = Vec::new();
+        let mut suppressed = Vec::new();
+
+        for report in reports {
+            match self.reason(&report) {
+                _ if report.is_suppressed() => suppressed.push(report),
+                Some(reason) => suppressed.push(report.with_suppression(reason)),
+                None => active.push(report),
+            }
+        }
+
+        (active, suppressed)
+    }
+
+    pub fn write(&self, writer: impl Write) -> Result<(), BaselineError> {
+        let mut entries = self.entries.values().collect::<Vec<_>>();
+        entries.sort_by(|a, b| {
+            (&a.path, &a.rule, &a.fingerprint).cmp(&(&b.path, &b.rule, &b.fingerprint))
+        });
+
+        let file = BaselineFile {
+            version: BASELINE_VERSION,
+            entries,
+        };
+
+        let mut writer = io::BufWriter::new(writer);
+        serde_json::to_writer_pretty(&mut writer, &file).map_err(BaselineError::Serialize)?;
+        writer.write_all(b"\n")?;
+        writer.flush()?;
+
+        Ok(())
+    }
+
+    pub fn read(reader: impl Read) -> Result<Self, BaselineError> {
+        let file =
+            serde_json::from_reader::<_, BaselineFile<BaselineEntry>>(io::BufReader::new(reader))
+                .map_err(|e| {
+                if e.is_io() {
+                    BaselineError::Io(e.into())
+                } else {
+                    BaselineError::Parse(e)
+                }
+            })?;
+
+        if file.version > BASELINE_VERSION {
+            return Err(BaselineError::UnsupportedVersion(file.version));
+        }
+
+        Ok(Self {
+            entries: file
+                .entries
+                .into_iter()
+                .map(|entry| (entry.fingerprint.clone(), entry))
+                .collect(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs::File;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    use super::{Baseline, BaselineError, BaselineMode};
+    use crate::matcher::{RuleMatcher, SuppressionMode, SuppressionReason};
+    use crate::reporting::{ReportSet, RuleMatchReport};
+
+    #[test]
+    fn test_baseline() -> Result<(), Box<dyn std::error::Error>> {
+        let rule = r#"
+id: unbounded-copy
+check-patterns:
+- name: strcpy
+  regex: func=^st(r|p)cpy$
+  pattern: |
+    { $func($dst, _); }
+"#;
+
+        let mut matcher = RuleMatcher::from_str(rule)?;
+        let utf8 = matcher.matches_file("tests/utf8-comments.c")?;
+        let ls = matcher.matches_file("tests/ls-main.c")?;
+
+        let reports = |matches: &[_]| {
+            matches
+                .iter()
+                .map(|m| RuleMatchReport::new(m).into_owned())
+                .collect::<Vec<_>>()
+        };
+
+        let created = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        let mut baseline = Baseline::from_reports_at(&reports(&utf8), created);
+
+        assert_eq!(baseline.len(), utf8.len());
+        assert!(baseline.annotate(utf8[0].fingerprint(), "reviewed"));
+        assert!(!baseline.annotate("unknown", "reviewed"));
+
+        // the baseline round-trips, and matches the fixture
+        let mut output = Vec::new();
+        baseline.write(&mut output)?;
+
+        assert_eq!(Baseline::read(output.as_slice())?, baseline);
+        assert_eq!(
+            Baseline::read(File::open("tests/baselines/utf8-comments.json")?)?,
+            baseline
+        );
+
+        let entry = baseline.get(utf8[0].fingerprint()).unwrap();
+
+        assert_eq!(entry.rule(), utf8[0].rule().id());
+        assert_eq!(entry.note(), Some("reviewed"));
+        assert_eq!(entry.created(), "2023-11-14T22:13:20Z");
+
+        // only new findings remain active
+        let mut scan = reports(&utf8);
+        scan.extend(reports(&ls));
+
+        let (active, suppressed) = baseline.apply(scan);
+
+        assert_eq!(active.len(), ls.len());
+        assert_eq!(suppressed.len(), utf8.len());
+        assert!(suppressed.iter().all(|r| matches!(
+            r.suppression(),
+            Some(SuppressionReason::Baseline { fingerprint }) if Some(fingerprint.as_str()) == r.fingerprint()
+        )));
+
+        let set =
+            ReportSet::new(reports(&ls)).suppress(SuppressionMode::Retain, |r| baseline.reason(r));
+
+        assert_eq!(set.suppressed().count(), 0);
+
+        // a scan that dropped the baselined findings only extends the baseline
+        // in union mode, while replacing forgets the missing findings
+        let union = baseline.clone().update(&active, BaselineMode::Union);
+
+        assert_eq!(union.len(), utf8.len() + ls.len());
+        assert_eq!(
+            union.get(utf8[0].fingerprint()).and_then(|e| e.note()),
+            Some("reviewed")
+        );
+
+        let replaced = union.clone().update(&active, BaselineMode::Replace);
+
+        assert_eq!(replaced.len(), ls.len());
+
+        // ... unless the scan retained them
+        let mut retained = active;
+        retained.extend(suppressed);
+        let replaced = union.clone().update(&retained, BaselineMode::Replace);
+
+        assert_eq!(replaced, union);
+
+        assert!(matches!(
+            Baseline::read(&b"{\"version\": 99, \"entries\": []}"[..]),
+            Err(BaselineError::UnsupportedVersion(99))
+        ));
+
+        Ok(())
+    }
+}
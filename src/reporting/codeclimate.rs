@@ -0,0 +1,161 @@
+use std::io::Write;
+
+use serde::Serialize;
+
+use crate::matcher::RuleMatch;
+use crate::reporting::MEMORY_PATH;
+use crate::rule::Severity;
+
+#[derive(Serialize)]
+struct Issue<'a> {
+    #[serde(rename = "type")]
+    kind: &'static str,
+    check_name: &'a str,
+    description: String,
+    categories: [&'static str; 1],
+    fingerprint: String,
+    severity: &'static str,
+    location: Location,
+}
+
+#[derive(Serialize)]
+struct Location {
+    path: String,
+    lines: Lines,
+}
+
+#[derive(Serialize)]
+struct Lines {
+    begin: usize,
+    end: usize,
+}
+
+// Writes matches as a Code Climate (GitLab Code Quality) JSON array of
+// issues; matches without an origin are reported against `MEMORY_PATH`.
+pub fn write(writer: impl Write, matches: &[RuleMatch]) -> Result<(), serde_json::Error> {
+    let issues = matches
+        .iter()
+        .map(|m| {
+            let rule = m.rule();
+            let path = m
+                .origin()
+                .map(|path| path.to_string_lossy().replace('\\', "/"))
+                .unwrap_or_else(|| String::from(MEMORY_PATH));
+
+            let description = match rule.description() {
+                Some(description) => format!(
+                    "{} ({}/{})",
+                    description.trim(),
+                    rule.id(),
+                    m.checker().name()
+                ),
+                None => format!("{}/{}", rule.id(), m.checker().name()),
+            };
+
+            Issue {
+                kind: "issue",
+                check_name: rule.id(),
+                description,
+                categories: ["Security"],
+                fingerprint: m.fingerprint().to_owned(),
+                severity: severity(m.effective_severity()),
+                location: Location {
+                    path,
+                    lines: Lines {
+                        begin: m.start_line(),
+                        end: m.end_line(),
+                    },
+                },
+            }
+        })
+        .collect::<Vec<_>>();
+
+    serde_json::to_writer_pretty(writer, &issues)
+}
+
+fn severity(severity: Severity) -> &'static str {
+    match severity {
+        Severity::None => "info",
+        Severity::Low => "minor",
+        Severity::Medium => "major",
+        Severity::High => "critical",
+        Severity::Critical => "blocker",
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::Value;
+
+    use super::write;
+    use crate::matcher::RuleMatcher;
+    use crate::reporting::MEMORY_PATH;
+
+    #[test]
+    fn test_codeclimate() -> Result<(), Box<dyn std::error::Error>> {
+        let rule = r#"
+id: unbounded-copy
+severity: high
+check-patterns:
+- name: strcpy
+  regex: func=^strcpy$
+  pattern: |
+    { $func($dst, _); }
+"#;
+
+        let mut matcher = RuleMatcher::from_str(rule)?;
+
+        let mut matches = matcher.matches_file("tests/utf8-comments.c")?;
+        matches.extend(matcher.matches("void f(char *s) { strcpy(s, \"x\"); }")?);
+
+        let mut output = Vec::new();
+        write(&mut output, &matches)?;
+
+        let issues = serde_json::from_slice::<Value>(&output)?;
+        let issues = issues.as_array().unwrap();
+
+        assert_eq!(issues.len(), matches.len());
+
+        let issue = issues
+            .iter()
+            .find(|i| i["check_name"] == "unbounded-copy")
+            .unwrap();
+
+        assert_eq!(issue["type"], "issue");
+        assert_eq!(issue["severity"], "critical");
+        assert_eq!(issue["location"]["path"], "tests/utf8-comments.c");
+        assert_eq!(issue["location"]["lines"]["begin"], 6);
+        assert!(issue["description"]
+            .as_str()
+            .unwrap()
+            .ends_with("(unbounded-copy/strcpy)"));
+        assert_eq!(issues.last().unwrap()["location"]["path"], MEMORY_PATH);
+
+        let fingerprints = |issues: &[Value]| {
+            issues
+                .iter()
+                .map(|i| i["fingerprint"].as_str().unwrap().to_owned())
+                .collect::<Vec<_>>()
+        };
+
+        let mut rerun = Vec::new();
+        let mut matches = matcher.matches_file("tests/utf8-comments.c")?;
+        matches.extend(matcher.matches("void f(char *s) { strcpy(s, \"x\"); }")?);
+        write(&mut rerun, &matches)?;
+
+        let rerun = serde_json::from_slice::<Value>(&rerun)?;
+
+        assert_eq!(
+            fingerprints(issues),
+            fingerprints(rerun.as_array().unwrap())
+        );
+
+        let mut distinct = fingerprints(issues);
+        distinct.sort();
+        distinct.dedup();
+
+        assert_eq!(distinct.len(), issues.len());
+
+        Ok(())
+    }
+}
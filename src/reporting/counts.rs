@@ -0,0 +1,242 @@
+use std::collections::BTreeMap;
+use std::fmt::Display;
+use std::ops::{Add, AddAssign, Index};
+
+use serde::ser::SerializeMap;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::matcher::RuleMatch;
+use crate::reporting::RuleMatchReport;
+use crate::rule::Severity;
+
+const SEVERITIES: [Severity; 5] = [
+    Severity::None,
+    Severity::Low,
+    Severity::Medium,
+    Severity::High,
+    Severity::Critical,
+];
+
+// The number of findings at each severity, e.g., of a scan or of a shard of
+// one (counts of shards add up with `+=`).
+//
+// NOTE: serialized as a map of the nonzero counts, highest severity first
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct SeverityCounts {
+    counts: [usize; SEVERITIES.len()],
+}
+
+impl SeverityCounts {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // NOTE: suppressed findings are not counted
+    pub fn from_matches(matches: &[RuleMatch]) -> Self {
+        matches
+            .iter()
+            .filter(|m| !m.is_suppressed())
+            .map(|m| m.effective_severity())
+            .collect()
+    }
+
+    pub fn from_reports(reports: &[RuleMatchReport]) -> Self {
+        reports
+            .iter()
+            .filter(|r| !r.is_suppressed())
+            .map(|r| r.severity())
+            .collect()
+    }
+
+    pub fn add(&mut self, severity: Severity) {
+        self.counts[severity as usize] += 1;
+    }
+
+    pub fn get(&self, severity: Severity) -> usize {
+        self.counts[severity as usize]
+    }
+
+    pub fn total(&self) -> usize {
+        self.counts.iter().sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.total() == 0
+    }
+
+    // the number of findings at least as severe as `severity`
+    pub fn at_least(&self, severity: Severity) -> usize {
+        self.counts[severity as usize..].iter().sum()
+    }
+
+    // the highest severity with any findings, or `None` without findings
+    //
+    // NOTE: `Some(Severity::None)` means that there are only findings of rules
+    // without a severity
+    pub fn max_severity(&self) -> Option<Severity> {
+        self.iter().next().map(|(severity, _)| severity)
+    }
+
+    // the nonzero counts, highest severity first
+    pub fn iter(&self) -> impl Iterator<Item = (Severity, usize)> + '_ {
+        SEVERITIES
+            .iter()
+            .rev()
+            .map(|severity| (*severity, self.get(*severity)))
+            .filter(|(_, count)| *count > 0)
+    }
+}
+
+impl Index<Severity> for SeverityCounts {
+    type Output = usize;
+
+    fn index(&self, severity: Severity) -> &usize {
+        &self.counts[severity as usize]
+    }
+}
+
+impl AddAssign<&SeverityCounts> for SeverityCounts {
+    fn add_assign(&mut self, other: &SeverityCounts) {
+        for (count, other) in self.counts.iter_mut().zip(other.counts) {
+            *count += other;
+        }
+    }
+}
+
+impl AddAssign for SeverityCounts {
+    fn add_assign(&mut self, other: SeverityCounts) {
+        *self += &other;
+    }
+}
+
+impl Add for SeverityCounts {
+    type Output = Self;
+
+    fn add(mut self, other: Self) -> Self {
+        self += &other;
+        self
+    }
+}
+
+impl FromIterator<Severity> for SeverityCounts {
+    fn from_iter<I: IntoIterator<Item = Severity>>(iter: I) -> Self {
+        let mut counts = Self::new();
+        counts.extend(iter);
+        counts
+    }
+}
+
+impl Extend<Severity> for SeverityCounts {
+    fn extend<I: IntoIterator<Item = Severity>>(&mut self, iter: I) {
+        for severity in iter {
+            self.add(severity);
+        }
+    }
+}
+
+// e.g., `critical:2 high:7 medium:31 low:4`, or `none` without findings
+impl Display for SeverityCounts {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.is_empty() {
+            return f.write_str("none");
+        }
+
+        for (i, (severity, count)) in self.iter().enumerate() {
+            if i > 0 {
+                f.write_str(" ")?;
+            }
+            write!(f, "{severity}:{count}")?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Serialize for SeverityCounts {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut map = serializer.serialize_map(Some(self.iter().count()))?;
+
+        for (severity, count) in self.iter() {
+            map.serialize_entry(&severity, &count)?;
+        }
+
+        map.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for SeverityCounts {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let mut counts = Self::new();
+
+        for (severity, count) in BTreeMap::<Severity, usize>::deserialize(deserializer)? {
+            counts.counts[severity as usize] += count;
+        }
+
+        Ok(counts)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::SeverityCounts;
+    use crate::matcher::RuleMatcher;
+    use crate::reporting::RuleMatchReport;
+    use crate::rule::Severity;
+    use crate::testing;
+
+    #[test]
+    fn test_severity_counts() -> Result<(), Box<dyn std::error::Error>> {
+        let pack = testing::pack()?;
+
+        let mut matcher = RuleMatcher::from_directory_with(pack.path(), false)?;
+        let matches = matcher.matches_file("tests/utf8-comments.c")?;
+
+        let counts = SeverityCounts::from_matches(&matches);
+
+        assert_eq!(counts[Severity::High], 2);
+        assert_eq!(counts[Severity::Low], 1);
+        assert_eq!(counts[Severity::Critical], 0);
+        assert_eq!(counts.total(), 3);
+        assert_eq!(counts.at_least(Severity::Medium), 2);
+        assert_eq!(counts.max_severity(), Some(Severity::High));
+        assert_eq!(counts.to_string(), "high:2 low:1");
+
+        let reports = matches.iter().map(RuleMatchReport::new).collect::<Vec<_>>();
+
+        assert_eq!(SeverityCounts::from_reports(&reports), counts);
+
+        // shards add up
+        let mut merged = SeverityCounts::from_matches(&matches[..1]);
+        merged += SeverityCounts::from_matches(&matches[1..]);
+
+        assert_eq!(merged, counts);
+        assert_eq!((counts + counts).total(), 6);
+
+        let json = serde_json::to_string(&counts)?;
+
+        assert_eq!(json, r#"{"high":2,"low":1}"#);
+        assert_eq!(serde_json::from_str::<SeverityCounts>(&json)?, counts);
+
+        let empty = SeverityCounts::from_matches(&[]);
+
+        assert!(empty.is_empty());
+        assert_eq!(empty.max_severity(), None);
+        assert_eq!(empty.to_string(), "none");
+        assert_eq!(serde_json::to_string(&empty)?, "{}");
+
+        let unrated = [Severity::None, Severity::None]
+            .into_iter()
+            .collect::<SeverityCounts>();
+
+        assert_eq!(unrated.max_severity(), Some(Severity::None));
+        assert_eq!(unrated.to_string(), "n/a:2");
+
+        Ok(())
+    }
+}
@@ -0,0 +1,225 @@
+use std::collections::hash_map::Entry;
+use std::path::Path;
+
+use rustc_hash::FxHashMap;
+
+use crate::matcher::RuleMatch;
+use crate::reporting::RuleMatchReport;
+
+// What makes two reports the same finding: their fingerprints, or, for
+// reports without one, their rule, checker, path, and matched text (with
+// whitespace normalized). Reports (and matches) with equal keys are considered
+// the same finding by `dedup`, `diff`, and `merge`, wherever they are in the
+// source: as fingerprints do not depend on offsets, edits elsewhere in a file
+// leave the keys of its findings unchanged.
+//
+// NOTE: the key of a match (`RuleMatch::key`) equals that of its report
+// (`RuleMatchReport::key`)
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum ReportKey<'r> {
+    Fingerprint(&'r str),
+    Content {
+        rule: &'r str,
+        checker: &'r str,
+        path: Option<&'r Path>,
+        text: String,
+    },
+}
+
+impl<'r> ReportKey<'r> {
+    pub fn new(report: &'r RuleMatchReport<'_>) -> Self {
+        match report.fingerprint() {
+            Some(fingerprint) => Self::Fingerprint(fingerprint),
+            None => Self::Content {
+                rule: report.rule(),
+                checker: report.checker(),
+                path: report.path(),
+                text: report
+                    .matched_text()
+                    .split_whitespace()
+                    .collect::<Vec<_>>()
+                    .join(" "),
+            },
+        }
+    }
+
+    pub fn from_match(m: &'r RuleMatch) -> Self {
+        match m.fingerprint() {
+            "" => Self::Content {
+                rule: m.rule().id(),
+                checker: m.checker().name(),
+                path: m.origin(),
+                text: m
+                    .matched_text()
+                    .split_whitespace()
+                    .collect::<Vec<_>>()
+                    .join(" "),
+            },
+            fingerprint => Self::Fingerprint(fingerprint),
+        }
+    }
+}
+
+// Removes the reports that repeat an earlier one (see `ReportKey`), keeping
+// the first occurrence of each finding in its original position.
+pub fn dedup<'a>(reports: Vec<RuleMatchReport<'a>>) -> Vec<RuleMatchReport<'a>> {
+    dedup_impl(reports, false)
+}
+
+// Like `dedup`, but records on each survivor how many duplicates of it were
+// removed (see `RuleMatchReport::duplicates`).
+pub fn dedup_counting<'a>(reports: Vec<RuleMatchReport<'a>>) -> Vec<RuleMatchReport<'a>> {
+    dedup_impl(reports, true)
+}
+
+fn dedup_impl(reports: Vec<RuleMatchReport<'_>>, count: bool) -> Vec<RuleMatchReport<'_>> {
+    let mut survivors = FxHashMap::default();
+    let mut duplicates = vec![0; reports.len()];
+    let mut keep = vec![true; reports.len()];
+
+    for (i, report) in reports.iter().enumerate() {
+        match survivors.entry(ReportKey::new(report)) {
+            Entry::Vacant(entry) => {
+                entry.insert(i);
+            }
+            Entry::Occupied(entry) => {
+                // duplicates of duplicates (e.g., of an already deduplicated
+                // shard) are counted as well
+                duplicates[*entry.get()] += 1 + report.duplicates();
+                keep[i] = false;
+            }
+        }
+    }
+
+    drop(survivors);
+
+    reports
+        .into_iter()
+        .zip(keep)
+        .zip(duplicates)
+        .filter_map(|((mut report, keep), duplicates)| {
+            if count {
+                report.duplicates += duplicates;
+            }
+            keep.then_some(report)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::{dedup, dedup_counting, ReportKey};
+    use crate::matcher::RuleMatcher;
+    use crate::reporting::RuleMatchReport;
+
+    #[test]
+    fn test_dedup() -> Result<(), Box<dyn std::error::Error>> {
+        let rule = r#"
+id: unbounded-copy
+check pattern:
+  regex: func=^strcpy$
+  pattern: |
+    { $func($dst, _); }
+"#;
+
+        let mut matcher = RuleMatcher::from_str(rule)?;
+
+        // two overlapping shards
+        let first = matcher.matches_files(["tests/ghidra-output.c", "tests/utf8-comments.c"]);
+        let second = matcher.matches_files(["tests/utf8-comments.c", "tests/hexrays-usercall.c"]);
+
+        let (first, second) = (first?, second?);
+
+        let merged = || {
+            first
+                .iter()
+                .chain(&second)
+                .map(RuleMatchReport::new)
+                .collect::<Vec<_>>()
+        };
+
+        let shared = first
+            .iter()
+            .filter(|m| m.origin().is_some_and(|p| p.ends_with("utf8-comments.c")))
+            .count();
+
+        assert!(shared > 0);
+
+        let deduped = dedup(merged());
+
+        assert_eq!(deduped.len(), first.len() + second.len() - shared);
+        assert!(deduped.iter().all(|r| r.duplicates() == 0));
+
+        // survivors keep their order
+        let expected = merged()
+            .into_iter()
+            .take(first.len())
+            .map(|r| r.fingerprint().map(String::from))
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            deduped[..first.len()]
+                .iter()
+                .map(|r| r.fingerprint().map(String::from))
+                .collect::<Vec<_>>(),
+            expected
+        );
+
+        let counted = dedup_counting(merged());
+
+        assert_eq!(
+            counted.iter().map(|r| r.duplicates()).sum::<usize>(),
+            shared
+        );
+
+        // counts survive serialization and accumulate across merges
+        let value = serde_json::to_value(&counted)?;
+        let restored = serde_json::from_value::<Vec<RuleMatchReport>>(value)?;
+        let twice = dedup_counting(restored.into_iter().chain(merged()).collect());
+
+        assert_eq!(twice.len(), counted.len());
+        assert_eq!(
+            twice.iter().map(|r| r.duplicates()).sum::<usize>(),
+            shared + merged().len()
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_report_key() -> Result<(), Box<dyn std::error::Error>> {
+        let rule = r#"
+id: unbounded-copy
+check pattern:
+  regex: func=^strcpy$
+  pattern: |
+    { $func($dst, _); }
+"#;
+
+        let mut matcher = RuleMatcher::from_str(rule)?;
+
+        let source = std::fs::read_to_string("tests/utf8-comments.c")?;
+        let edited = format!("#include <string.h>\n\n{}", source.replace("🚨", "TODO"));
+
+        let before = matcher.matches(&source)?;
+        let after = matcher.matches(&edited)?;
+
+        assert_eq!(before.len(), after.len());
+
+        for (old, new) in before.iter().zip(&after) {
+            assert_ne!(old.start_line(), new.start_line());
+            assert_eq!(old.key(), new.key());
+            assert_eq!(RuleMatchReport::new(old).key(), old.key());
+        }
+
+        // reports without fingerprints are keyed by their content
+        let mut value = serde_json::to_value(RuleMatchReport::new(&before[0]))?;
+        value.as_object_mut().unwrap().remove("fingerprint");
+        let report = serde_json::from_value::<RuleMatchReport>(value)?;
+
+        assert!(matches!(report.key(), ReportKey::Content { .. }));
+        assert_ne!(report.key(), before[0].key());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,199 @@
+use std::fmt::Display;
+
+use rustc_hash::FxHashMap;
+use serde::ser::SerializeStruct;
+use serde::{Serialize, Serializer};
+
+use crate::reporting::{ReportKey, RuleMatchReport};
+
+// The findings of `new` that are not in `old` (new), those of `old` that are
+// not in `new` (fixed), and those in both (unchanged, as reported in `new`).
+pub struct ReportDiff<'r, 'a> {
+    new: Vec<&'r RuleMatchReport<'a>>,
+    fixed: Vec<&'r RuleMatchReport<'a>>,
+    unchanged: Vec<&'r RuleMatchReport<'a>>,
+}
+
+impl<'r, 'a> ReportDiff<'r, 'a> {
+    pub fn new_findings(&self) -> &[&'r RuleMatchReport<'a>] {
+        &self.new
+    }
+
+    pub fn fixed_findings(&self) -> &[&'r RuleMatchReport<'a>] {
+        &self.fixed
+    }
+
+    pub fn unchanged_findings(&self) -> &[&'r RuleMatchReport<'a>] {
+        &self.unchanged
+    }
+
+    pub fn new_count(&self) -> usize {
+        self.new.len()
+    }
+
+    pub fn fixed_count(&self) -> usize {
+        self.fixed.len()
+    }
+
+    pub fn unchanged_count(&self) -> usize {
+        self.unchanged.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.new.is_empty() && self.fixed.is_empty()
+    }
+}
+
+impl Display for ReportDiff<'_, '_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} new, {} fixed, {} unchanged",
+            self.new.len(),
+            self.fixed.len(),
+            self.unchanged.len()
+        )
+    }
+}
+
+impl Serialize for ReportDiff<'_, '_> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut s = serializer.serialize_struct("ReportDiff", 6)?;
+
+        s.serialize_field("new_count", &self.new.len())?;
+        s.serialize_field("fixed_count", &self.fixed.len())?;
+        s.serialize_field("unchanged_count", &self.unchanged.len())?;
+        s.serialize_field("new", &self.new)?;
+        s.serialize_field("fixed", &self.fixed)?;
+        s.serialize_field("unchanged", &self.unchanged)?;
+
+        s.end()
+    }
+}
+
+// Findings are compared by `ReportKey`, as multisets: if `old` contains two
+// findings with some fingerprint and `new` three, one of the latter is new.
+pub fn diff<'r, 'a>(
+    old: &'r [RuleMatchReport<'a>],
+    new: &'r [RuleMatchReport<'a>],
+) -> ReportDiff<'r, 'a> {
+    let mut remaining = FxHashMap::<_, Vec<(usize, &'r RuleMatchReport<'a>)>>::default();
+
+    for (i, report) in old.iter().enumerate() {
+        remaining
+            .entry(ReportKey::new(report))
+            .or_default()
+            .push((i, report));
+    }
+
+    let mut result = ReportDiff {
+        new: Vec::new(),
+        fixed: Vec::new(),
+        unchanged: Vec::new(),
+    };
+
+    for report in new {
+        let matched = remaining
+            .get_mut(&ReportKey::new(report))
+            .and_then(|reports| reports.pop());
+
+        if matched.is_some() {
+            result.unchanged.push(report);
+        } else {
+            result.new.push(report);
+        }
+    }
+
+    // fixed findings are in the order of `old`
+    let mut fixed = remaining.into_values().flatten().collect::<Vec<_>>();
+    fixed.sort_by_key(|(i, _)| *i);
+    result.fixed = fixed.into_iter().map(|(_, report)| report).collect();
+
+    result
+}
+
+#[cfg(test)]
+mod test {
+    use super::diff;
+    use crate::matcher::RuleMatcher;
+    use crate::reporting::RuleMatchReport;
+
+    #[test]
+    fn test_diff() -> Result<(), Box<dyn std::error::Error>> {
+        let rule = r#"
+id: unbounded-copy
+check pattern:
+  regex: func=^(strcpy|strcat)$
+  pattern: '{ $func($dst, _); }'
+"#;
+
+        let old_source = "\
+void f(char *d, char *s) {
+    strcpy(d, s);
+    strcpy(d, s);
+    strcat(d, s);
+}
+";
+
+        let new_source = "\
+void f(char *d, char *s) {
+    strcpy(d, s);
+    strcpy(d, s);
+    strcpy(d, s);
+}
+
+void g(char *d, char *s) { strcpy(d, s); }
+";
+
+        let mut matcher = RuleMatcher::from_str(rule)?;
+
+        let old_matches = matcher.matches(old_source)?;
+        let new_matches = matcher.matches(new_source)?;
+
+        let old = old_matches
+            .iter()
+            .map(RuleMatchReport::new)
+            .collect::<Vec<_>>();
+        let new = new_matches
+            .iter()
+            .map(RuleMatchReport::new)
+            .collect::<Vec<_>>();
+
+        let result = diff(&old, &new);
+
+        assert_eq!(result.new_count(), 2);
+        assert_eq!(result.fixed_count(), 1);
+        assert_eq!(result.unchanged_count(), 2);
+        assert_eq!(result.to_string(), "2 new, 1 fixed, 2 unchanged");
+        assert_eq!(result.fixed_findings()[0].matched_text(), "strcat(d");
+
+        // without fingerprints, identical findings are counted as a multiset
+        let strip = |reports: &[RuleMatchReport]| {
+            reports
+                .iter()
+                .map(|r| {
+                    let mut value = serde_yaml::to_value(r).unwrap();
+                    value.as_mapping_mut().unwrap().remove("fingerprint");
+                    serde_yaml::from_value::<RuleMatchReport>(value).unwrap()
+                })
+                .collect::<Vec<_>>()
+        };
+
+        let (old, new) = (strip(&old), strip(&new));
+        let result = diff(&old, &new);
+
+        assert_eq!(result.new_count(), 2);
+        assert_eq!(result.fixed_count(), 1);
+        assert_eq!(result.unchanged_count(), 2);
+
+        let value = serde_yaml::to_value(&result)?;
+
+        assert_eq!(value["new_count"].as_u64(), Some(2));
+        assert_eq!(value["fixed"].as_sequence().map(Vec::len), Some(1));
+
+        Ok(())
+    }
+}
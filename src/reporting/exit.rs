@@ -0,0 +1,274 @@
+use std::fmt::Display;
+
+use crate::matcher::RuleMatch;
+use crate::reporting::{ReportSet, RuleMatchReport, SeverityCounts};
+use crate::rule::Severity;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ExitStatus {
+    Pass,
+    Warn,
+    Fail,
+}
+
+impl ExitStatus {
+    // NOTE: codes increase with the outcome, so that wrappers may treat any
+    // nonzero code as a failure or only the highest one
+    pub fn code(self) -> i32 {
+        match self {
+            Self::Pass => 0,
+            Self::Warn => 1,
+            Self::Fail => 2,
+        }
+    }
+}
+
+impl Display for ExitStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Pass => "pass",
+            Self::Warn => "warn",
+            Self::Fail => "fail",
+        })
+    }
+}
+
+// Decides the outcome of a scan: it fails if any finding is at least as
+// severe as `fail_on`, and otherwise warns if any is at least as severe as
+// `warn_on`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ExitPolicy {
+    fail_on: Severity,
+    warn_on: Option<Severity>,
+}
+
+impl Default for ExitPolicy {
+    fn default() -> Self {
+        Self::fail_on_high()
+    }
+}
+
+impl ExitPolicy {
+    pub fn new(fail_on: Severity) -> Self {
+        Self {
+            fail_on,
+            warn_on: None,
+        }
+    }
+
+    pub fn fail_on_any() -> Self {
+        Self::new(Severity::None)
+    }
+
+    pub fn fail_on_medium() -> Self {
+        Self::new(Severity::Medium)
+    }
+
+    pub fn fail_on_high() -> Self {
+        Self::new(Severity::High)
+    }
+
+    pub fn fail_on_critical() -> Self {
+        Self::new(Severity::Critical)
+    }
+
+    pub fn warn_on(mut self, severity: Severity) -> Self {
+        self.warn_on = Some(severity);
+        self
+    }
+
+    pub fn fail_threshold(&self) -> Severity {
+        self.fail_on
+    }
+
+    pub fn warn_threshold(&self) -> Option<Severity> {
+        self.warn_on
+    }
+
+    // NOTE: suppressed findings never affect the decision
+    pub fn evaluate<'m>(&self, matches: &'m [RuleMatch]) -> ExitDecision<&'m RuleMatch> {
+        self.decide(matches.iter().filter(|m| !m.is_suppressed()), |m| {
+            m.effective_severity()
+        })
+    }
+
+    pub fn evaluate_set<'r, 'a>(
+        &self,
+        set: &'r ReportSet<'a>,
+    ) -> ExitDecision<&'r RuleMatchReport<'a>> {
+        self.decide(set.iter().filter(|r| !r.is_suppressed()), |r| r.severity())
+    }
+
+    fn decide<T: Copy>(
+        &self,
+        findings: impl Iterator<Item = T>,
+        severity: impl Fn(&T) -> Severity,
+    ) -> ExitDecision<T> {
+        let findings = findings.collect::<Vec<_>>();
+
+        let counts = findings.iter().map(&severity).collect::<SeverityCounts>();
+
+        let above = |threshold: Severity| {
+            findings
+                .iter()
+                .filter(|finding| severity(finding) >= threshold)
+                .copied()
+                .collect::<Vec<_>>()
+        };
+
+        let failing = above(self.fail_on);
+
+        let (status, threshold, triggering) = if !failing.is_empty() {
+            (ExitStatus::Fail, Some(self.fail_on), failing)
+        } else {
+            match self.warn_on.map(|warn_on| (warn_on, above(warn_on))) {
+                Some((warn_on, warning)) if !warning.is_empty() => {
+                    (ExitStatus::Warn, Some(warn_on), warning)
+                }
+                _ => (ExitStatus::Pass, None, Vec::new()),
+            }
+        };
+
+        ExitDecision {
+            status,
+            threshold,
+            fail_on: self.fail_on,
+            triggering,
+            counts,
+        }
+    }
+}
+
+// The outcome of an `ExitPolicy`, with the findings that triggered it (none,
+// if it passed).
+#[derive(Debug, Clone)]
+pub struct ExitDecision<T> {
+    status: ExitStatus,
+    threshold: Option<Severity>,
+    fail_on: Severity,
+    triggering: Vec<T>,
+    counts: SeverityCounts,
+}
+
+impl<T> ExitDecision<T> {
+    pub fn status(&self) -> ExitStatus {
+        self.status
+    }
+
+    pub fn exit_code(&self) -> i32 {
+        self.status.code()
+    }
+
+    pub fn is_failure(&self) -> bool {
+        self.status == ExitStatus::Fail
+    }
+
+    pub fn triggering(&self) -> &[T] {
+        &self.triggering
+    }
+
+    pub fn counts(&self) -> &SeverityCounts {
+        &self.counts
+    }
+
+    pub fn total(&self) -> usize {
+        self.counts.total()
+    }
+}
+
+impl<T> Display for ExitDecision<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let threshold = |severity: Severity| match severity {
+            Severity::None => String::from("any severity"),
+            severity => format!("severity {severity} or above"),
+        };
+
+        match self.threshold {
+            Some(severity) => write!(
+                f,
+                "{}: {} of {} findings at {}",
+                self.status,
+                self.triggering.len(),
+                self.total(),
+                threshold(severity)
+            )?,
+            None => write!(
+                f,
+                "{}: no findings at {} ({} total)",
+                self.status,
+                threshold(self.fail_on),
+                self.total()
+            )?,
+        }
+
+        if !self.counts.is_empty() {
+            let counts = self
+                .counts
+                .iter()
+                .map(|(severity, count)| format!("{severity}: {count}"))
+                .collect::<Vec<_>>();
+
+            write!(f, " ({})", counts.join(", "))?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{ExitPolicy, ExitStatus};
+    use crate::matcher::RuleMatcher;
+    use crate::reporting::ReportSet;
+    use crate::rule::Severity;
+    use crate::testing;
+
+    #[test]
+    fn test_exit_policy() -> Result<(), Box<dyn std::error::Error>> {
+        let pack = testing::pack()?;
+
+        let mut matcher = RuleMatcher::from_directory_with(pack.path(), false)?;
+        let matches = matcher.matches_file("tests/utf8-comments.c")?;
+
+        let decision = ExitPolicy::fail_on_high().evaluate(&matches);
+
+        assert_eq!(decision.status(), ExitStatus::Fail);
+        assert_eq!(decision.exit_code(), 2);
+        assert_eq!(decision.triggering().len(), 2);
+        assert_eq!(
+            decision.to_string(),
+            "fail: 2 of 3 findings at severity high or above (high: 2, low: 1)"
+        );
+
+        let decision = ExitPolicy::fail_on_critical()
+            .warn_on(Severity::Low)
+            .evaluate(&matches);
+
+        assert_eq!(decision.status(), ExitStatus::Warn);
+        assert_eq!(decision.exit_code(), 1);
+        assert_eq!(decision.triggering().len(), 3);
+
+        let set = ReportSet::from_matches(&matches).filter_min_severity(Severity::Medium);
+        let decision = ExitPolicy::fail_on_critical()
+            .warn_on(Severity::Critical)
+            .evaluate_set(&set);
+
+        assert_eq!(decision.status(), ExitStatus::Pass);
+        assert_eq!(decision.exit_code(), 0);
+        assert!(decision.triggering().is_empty());
+        assert_eq!(
+            decision.to_string(),
+            "pass: no findings at severity critical or above (2 total) (high: 2)"
+        );
+
+        let decision = ExitPolicy::fail_on_any().evaluate(&[]);
+
+        assert_eq!(decision.status(), ExitStatus::Pass);
+        assert_eq!(
+            decision.to_string(),
+            "pass: no findings at any severity (0 total)"
+        );
+
+        Ok(())
+    }
+}
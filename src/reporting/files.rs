@@ -0,0 +1,185 @@
+use std::path::Path;
+
+use rustc_hash::FxHashMap;
+use serde::ser::SerializeStruct;
+use serde::{Serialize, Serializer};
+
+use crate::matcher::{location_order, RuleMatch};
+use crate::reporting::{RuleMatchReport, SeverityCounts, SourceMode, MEMORY_PATH};
+use crate::rule::Severity;
+
+// The findings in a single file, ordered by location.
+#[derive(Debug)]
+pub struct FileReport<'m> {
+    path: &'m Path,
+    in_memory: bool,
+    findings: Vec<&'m RuleMatch>,
+    counts: SeverityCounts,
+}
+
+impl<'m> FileReport<'m> {
+    // the origin of the findings, or the pseudo-path of matches without one
+    pub fn path(&self) -> &'m Path {
+        self.path
+    }
+
+    pub fn origin(&self) -> Option<&'m Path> {
+        if self.in_memory {
+            None
+        } else {
+            Some(self.path)
+        }
+    }
+
+    pub fn findings(&self) -> &[&'m RuleMatch] {
+        &self.findings
+    }
+
+    pub fn count(&self) -> usize {
+        self.findings.len()
+    }
+
+    pub fn counts(&self) -> &SeverityCounts {
+        &self.counts
+    }
+
+    // NOTE: files have at least one finding
+    pub fn max_severity(&self) -> Severity {
+        self.counts.max_severity().unwrap_or_default()
+    }
+}
+
+impl Serialize for FileReport<'_> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        // the file is the same for all findings, so each embeds only the
+        // lines of its match
+        let findings = self
+            .findings
+            .iter()
+            .map(|m| {
+                RuleMatchReport::new(m).with_source_mode(SourceMode::Snippet {
+                    before: 0,
+                    after: 0,
+                })
+            })
+            .collect::<Vec<_>>();
+
+        let mut s = serializer.serialize_struct("FileReport", 5)?;
+
+        s.serialize_field("path", self.path)?;
+        s.serialize_field("max_severity", &self.max_severity())?;
+        s.serialize_field("count", &self.count())?;
+        s.serialize_field("counts", &self.counts)?;
+        s.serialize_field("findings", &findings)?;
+
+        s.end()
+    }
+}
+
+// Groups matches by file, ordered by path, with the matches without an origin
+// grouped under `MEMORY_PATH`.
+pub fn by_file(matches: &[RuleMatch]) -> Vec<FileReport<'_>> {
+    by_file_with(matches, Path::new(MEMORY_PATH))
+}
+
+// see `by_file`; matches without an origin are grouped under `memory_path`,
+// which sorts before all files
+pub fn by_file_with<'m>(matches: &'m [RuleMatch], memory_path: &'m Path) -> Vec<FileReport<'m>> {
+    let mut index = FxHashMap::default();
+    let mut files = Vec::<FileReport>::new();
+
+    for m in matches {
+        let i = *index.entry(m.origin()).or_insert_with(|| {
+            files.push(FileReport {
+                path: m.origin().unwrap_or(memory_path),
+                in_memory: m.origin().is_none(),
+                findings: Vec::new(),
+                counts: SeverityCounts::new(),
+            });
+            files.len() - 1
+        });
+
+        let file = &mut files[i];
+        file.findings.push(m);
+        if !m.is_suppressed() {
+            file.counts.add(m.effective_severity());
+        }
+    }
+
+    for file in files.iter_mut() {
+        file.findings.sort_by(|a, b| location_order(a, b));
+    }
+
+    files.sort_by(|a, b| a.origin().cmp(&b.origin()));
+
+    files
+}
+
+// keeps only the files with findings at least as severe as `severity`
+pub fn filter_files(files: Vec<FileReport<'_>>, severity: Severity) -> Vec<FileReport<'_>> {
+    files
+        .into_iter()
+        .filter(|file| file.max_severity() >= severity)
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::Path;
+
+    use super::{by_file, by_file_with, filter_files};
+    use crate::matcher::RuleMatcher;
+    use crate::rule::Severity;
+    use crate::testing;
+
+    #[test]
+    fn test_by_file() -> Result<(), Box<dyn std::error::Error>> {
+        let pack = testing::pack()?;
+
+        let mut matcher = RuleMatcher::from_directory_with(pack.path(), false)?;
+
+        let mut matches = matcher.matches_files(["tests/utf8-comments.c", "tests/ls-main.c"])?;
+        matches.extend(matcher.matches("void f(char *s) { strcpy(s, \"x\"); }")?);
+        matches.reverse();
+
+        let files = by_file(&matches);
+
+        assert_eq!(files.len(), 3);
+        assert_eq!(files[0].path(), Path::new("<memory>"));
+        assert_eq!(files[0].origin(), None);
+        assert_eq!(files[1].path(), Path::new("tests/ls-main.c"));
+        assert_eq!(files[2].path(), Path::new("tests/utf8-comments.c"));
+
+        let utf8 = &files[2];
+
+        assert_eq!(utf8.count(), 3);
+        assert_eq!(utf8.max_severity(), Severity::High);
+        assert_eq!(utf8.counts()[Severity::Low], 1);
+        assert!(utf8
+            .findings()
+            .windows(2)
+            .all(|w| w[0].start_line() <= w[1].start_line()));
+
+        let value = serde_json::to_value(utf8)?;
+
+        assert_eq!(value["path"], "tests/utf8-comments.c");
+        assert_eq!(value["max_severity"], "high");
+        assert_eq!(value["counts"]["high"], 2);
+        assert_eq!(value["findings"].as_array().map(Vec::len), Some(3));
+        assert!(value["findings"][0]["source"].as_str().unwrap().len() < 100);
+
+        let files = by_file_with(&matches, Path::new("stdin"));
+
+        assert_eq!(files[0].path(), Path::new("stdin"));
+
+        let high = filter_files(files, Severity::High);
+
+        assert!(high.iter().all(|f| f.max_severity() >= Severity::High));
+        assert!(high.iter().any(|f| f.origin().is_none()));
+
+        Ok(())
+    }
+}
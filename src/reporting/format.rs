@@ -0,0 +1,309 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use serde::{Serialize, Serializer};
+use weggli::result::QueryResult;
+
+use crate::matcher::SuppressionReason;
+use crate::reporting::{
+    self, serialize_result, ReportLocation, ReportSnippet, RuleMatchReport, SourceBase,
+};
+use crate::rule::Severity;
+
+// Selects the fields of serialized reports; by default, reports serialize as
+// they do on their own.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ReportFormat {
+    source: bool,
+    snippet: bool,
+    captures: bool,
+    query_result: bool,
+    location: bool,
+}
+
+impl Default for ReportFormat {
+    fn default() -> Self {
+        Self {
+            source: true,
+            snippet: true,
+            captures: true,
+            query_result: true,
+            location: true,
+        }
+    }
+}
+
+impl ReportFormat {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn include_source(mut self, source: bool) -> Self {
+        self.source = source;
+        self
+    }
+
+    pub fn include_snippet(mut self, snippet: bool) -> Self {
+        self.snippet = snippet;
+        self
+    }
+
+    // the values of the rule's variables (e.g., `$dst`), available even if
+    // the source is not included (see `RuleMatchReport::captures`)
+    pub fn include_captures(mut self, captures: bool) -> Self {
+        self.captures = captures;
+        self
+    }
+
+    pub fn include_query_result(mut self, query_result: bool) -> Self {
+        self.query_result = query_result;
+        self
+    }
+
+    pub fn include_location(mut self, location: bool) -> Self {
+        self.location = location;
+        self
+    }
+
+    pub fn apply<'f, 'r, 'a>(
+        &'f self,
+        report: &'r RuleMatchReport<'a>,
+    ) -> FormattedReport<'f, 'r, 'a> {
+        FormattedReport {
+            format: self,
+            report,
+        }
+    }
+}
+
+// A report serialized according to a `ReportFormat`.
+pub struct FormattedReport<'f, 'r, 'a> {
+    format: &'f ReportFormat,
+    report: &'r RuleMatchReport<'a>,
+}
+
+struct SortedResult<'r>(&'r QueryResult);
+
+impl Serialize for SortedResult<'_> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serialize_result(self.0, serializer)
+    }
+}
+
+// NOTE: mirrors the fields of `RuleMatchReport`, so that its output can be
+// deserialized as one
+#[derive(Serialize)]
+struct Fields<'r> {
+    rule: &'r str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    rule_key: Option<&'r str>,
+    checker: &'r str,
+    checker_index: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    description: Option<&'r str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    author: Option<&'r str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    rule_path: Option<&'r Path>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    tags: Vec<&'r str>,
+    severity: Severity,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    fingerprint: Option<&'r str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    source: Option<&'r str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    source_base: Option<&'r SourceBase>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    location: Option<&'r ReportLocation>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    address: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    snippet: Option<&'r ReportSnippet>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    captures: BTreeMap<&'r str, &'r str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    suppression: Option<&'r SuppressionReason>,
+    #[serde(skip_serializing_if = "is_zero")]
+    duplicates: usize,
+    #[serde(rename = "match", skip_serializing_if = "Option::is_none")]
+    match_result: Option<SortedResult<'r>>,
+}
+
+fn is_zero(n: &usize) -> bool {
+    *n == 0
+}
+
+impl Serialize for FormattedReport<'_, '_, '_> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let (format, report) = (self.format, self.report);
+        let source = report.source();
+
+        let mut tags = report.tags().iter().map(String::as_str).collect::<Vec<_>>();
+        tags.sort();
+
+        // reports created before captures were recorded have them resolved
+        // from the embedded source, as far as it extends
+        let captures = match report.captures() {
+            _ if !format.captures => BTreeMap::new(),
+            captures if captures.is_empty() => reporting::captures(source, report.result()),
+            captures => captures
+                .iter()
+                .map(|(var, value)| (var.as_str(), value.as_str()))
+                .collect(),
+        };
+
+        Fields {
+            rule: report.rule(),
+            rule_key: report.rule_key(),
+            checker: report.checker(),
+            checker_index: report.checker_index(),
+            description: report.description(),
+            author: report.author(),
+            rule_path: report.rule_path(),
+            tags,
+            severity: report.severity(),
+            fingerprint: report.fingerprint(),
+            source: (format.source && !source.is_empty()).then_some(source),
+            source_base: report.source_base().filter(|_| format.source),
+            location: report.location().filter(|_| format.location),
+            address: report.address(),
+            snippet: report.snippet().filter(|_| format.snippet),
+            captures,
+            suppression: report.suppression(),
+            duplicates: report.duplicates(),
+            match_result: (format.query_result && !report.result().captures.is_empty())
+                .then(|| SortedResult(report.result())),
+        }
+        .serialize(serializer)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::ReportFormat;
+    use crate::matcher::RuleMatcher;
+    use crate::reporting::{RuleMatchReport, SourceMode};
+
+    #[test]
+    fn test_report_format() -> Result<(), Box<dyn std::error::Error>> {
+        let rule = r#"
+id: unbounded-copy
+description: call to unbounded copy function
+severity: high
+tags: [CWE-120]
+check pattern:
+  regex: func=^strcpy$
+  pattern: |
+    { $func($dst, _); }
+"#;
+
+        let mut matcher = RuleMatcher::from_str(rule)?;
+        let matches = matcher.matches_file("tests/utf8-comments.c")?;
+
+        let m = matches.iter().find(|m| m.start_line() == 12).unwrap();
+        let report = RuleMatchReport::new(m);
+
+        let keys = |format: ReportFormat| {
+            let value = serde_json::to_value(format.apply(&report)).unwrap();
+            let mut keys = value
+                .as_object()
+                .unwrap()
+                .keys()
+                .cloned()
+                .collect::<Vec<_>>();
+            keys.sort();
+            (keys, value)
+        };
+
+        // by default, the output is that of the report itself
+        assert_eq!(
+            serde_json::to_string(&ReportFormat::new().apply(&report))?,
+            serde_json::to_string(&report)?
+        );
+
+        let (dashboard, value) = keys(
+            ReportFormat::new()
+                .include_source(false)
+                .include_captures(true)
+                .include_query_result(false),
+        );
+
+        assert!(dashboard.contains(&String::from("captures")));
+        assert!(dashboard.contains(&String::from("location")));
+        assert!(!dashboard.contains(&String::from("source")));
+        assert!(!dashboard.contains(&String::from("match")));
+        assert_eq!(value["captures"]["$dst"], "名前");
+        assert_eq!(value["captures"]["$func"], "strcpy");
+
+        let (siem, _) = keys(
+            ReportFormat::new()
+                .include_query_result(false)
+                .include_captures(false),
+        );
+
+        assert!(siem.contains(&String::from("source")));
+        assert!(!siem.contains(&String::from("match")));
+        assert!(!siem.contains(&String::from("captures")));
+
+        let (minimal, value) = keys(
+            ReportFormat::new()
+                .include_source(false)
+                .include_snippet(false)
+                .include_captures(false)
+                .include_query_result(false)
+                .include_location(false),
+        );
+
+        assert_eq!(
+            minimal,
+            [
+                "checker",
+                "checker_index",
+                "description",
+                "fingerprint",
+                "rule",
+                "rule_key",
+                "severity",
+                "tags"
+            ]
+        );
+
+        // the output can be read back as a report
+        let restored = serde_json::from_value::<RuleMatchReport>(value)?;
+
+        assert_eq!(restored.rule(), "unbounded-copy");
+
+        // captures are recorded with the report, and so outlive the source
+        let format = ReportFormat::new().include_query_result(false);
+        let omitted = RuleMatchReport::new(m).with_source_mode(SourceMode::Omit);
+        let value = serde_json::to_value(format.apply(&omitted))?;
+
+        assert_eq!(value["captures"]["$dst"], "名前");
+
+        // ... but reports created before that have them resolved from the
+        // embedded source, as far as it extends
+        let older = |mode: SourceMode| -> Result<_, Box<dyn std::error::Error>> {
+            let mut value = serde_json::to_value(RuleMatchReport::new(m).with_source_mode(mode))?;
+            value.as_object_mut().unwrap().remove("captures");
+            let report = serde_json::from_value::<RuleMatchReport>(value)?;
+            Ok(serde_json::to_value(format.apply(&report))?)
+        };
+
+        let snippet = older(SourceMode::Snippet {
+            before: 0,
+            after: 0,
+        })?;
+
+        assert_eq!(snippet["captures"]["$dst"], "名前");
+        assert_eq!(older(SourceMode::Omit)?.get("captures"), None);
+
+        Ok(())
+    }
+}
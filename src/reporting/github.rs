@@ -0,0 +1,129 @@
+use std::io::{self, Write};
+
+use crate::matcher::RuleMatch;
+use crate::rule::Severity;
+
+// Writes a GitHub Actions workflow command per match, e.g.:
+//
+//   ::error file=src/a.c,line=6,endLine=6,col=5,endColumn=15,title=unbounded-copy::...
+//
+// Matches without an origin are emitted without a location, and so appear as
+// annotations on the workflow run rather than on a file.
+pub fn write_annotations(mut writer: impl Write, matches: &[RuleMatch]) -> io::Result<()> {
+    for m in matches {
+        let rule = m.rule();
+
+        let command = match m.effective_severity() {
+            Severity::None | Severity::Low => "notice",
+            Severity::Medium => "warning",
+            Severity::High | Severity::Critical => "error",
+        };
+
+        let mut properties = Vec::with_capacity(6);
+
+        if let Some(path) = m.origin() {
+            properties.push(format!(
+                "file={}",
+                escape_property(&path.to_string_lossy().replace('\\', "/"))
+            ));
+            properties.push(format!("line={}", m.start_line()));
+            properties.push(format!("endLine={}", m.end_line()));
+            properties.push(format!("col={}", m.start_column()));
+            properties.push(format!("endColumn={}", m.end_column()));
+        }
+
+        properties.push(format!("title={}", escape_property(rule.id())));
+
+        let message = match rule.description() {
+            Some(description) => format!(
+                "{} ({}/{})",
+                description.trim(),
+                rule.id(),
+                m.checker().name()
+            ),
+            None => format!("{}/{}", rule.id(), m.checker().name()),
+        };
+
+        writeln!(
+            writer,
+            "::{command} {}::{}",
+            properties.join(","),
+            escape_data(&message)
+        )?;
+    }
+
+    writer.flush()
+}
+
+fn escape_data(text: &str) -> String {
+    text.replace('%', "%25")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A")
+}
+
+fn escape_property(text: &str) -> String {
+    escape_data(text).replace(':', "%3A").replace(',', "%2C")
+}
+
+#[cfg(test)]
+mod test {
+    use super::write_annotations;
+    use crate::matcher::RuleMatcher;
+    use crate::testing;
+
+    #[test]
+    fn test_annotations() -> Result<(), Box<dyn std::error::Error>> {
+        let pack = testing::pack_with(&[(
+            "unbounded-copy.yml",
+            r#"
+id: unbounded-copy
+description: call to unbounded copy function
+severity: high
+check-patterns:
+- name: strcpy
+  regex: func=^st(r|p)cpy$
+  pattern: |
+    { $func($dst, _); }
+"#,
+        )])?;
+
+        let mut matcher = RuleMatcher::from_directory_with(pack.path(), false)?;
+        let matches = matcher.matches_file("tests/utf8-comments.c")?;
+
+        let mut output = Vec::new();
+        write_annotations(&mut output, &matches)?;
+        let output = String::from_utf8(output)?;
+
+        let lines = output.lines().collect::<Vec<_>>();
+
+        assert_eq!(lines.len(), matches.len());
+        assert!(lines.contains(
+            &"::error file=tests/utf8-comments.c,line=6,endLine=6,col=5,endColumn=15,\
+              title=unbounded-copy::call to unbounded copy function (unbounded-copy/strcpy)"
+        ));
+        assert!(lines.iter().any(|l| l.starts_with("::notice ")));
+
+        let rule = r#"
+id: 'copy: 100%'
+description: |
+  unbounded
+  copy
+check pattern:
+  regex: func=^strcpy$
+  pattern: '{ $func(_, _); }'
+"#;
+
+        let mut matcher = RuleMatcher::from_str(rule)?;
+        let matches = matcher.matches("void f(char *s) { strcpy(s, s); }")?;
+
+        let mut output = Vec::new();
+        write_annotations(&mut output, &matches)?;
+
+        assert_eq!(
+            String::from_utf8(output)?,
+            "::notice title=copy%3A 100%25::unbounded%0Acopy (copy: 100%25/default)\n"
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,464 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+
+use rustc_hash::FxHashMap;
+use serde::de::{Error, MapAccess, Visitor};
+use serde::ser::SerializeMap;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::location;
+use crate::matcher::{RuleMatch, SuppressionReason};
+use crate::reporting::{self, ReportMetadata, SeverityCounts, SCHEMA_VERSION};
+use crate::rule::Severity;
+
+// Findings grouped by rule, serialized as a map from rule ids to the rule's
+// metadata and its findings, so that the metadata is not repeated for every
+// finding.
+//
+// NOTE: rules are ordered by severity (highest first), then by id; findings
+// are ordered by path (findings without one first), then by position. The
+// version of the schema and the metadata of the scan, if any, precede the
+// rules under `VERSION_KEY` and `METADATA_KEY`
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct GroupedReport {
+    rules: Vec<(String, GroupedRule)>,
+    metadata: Option<ReportMetadata>,
+}
+
+// NOTE: rule ids are not expected to start with `$`, which marks variables
+const VERSION_KEY: &str = "$schema_version";
+const METADATA_KEY: &str = "$metadata";
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct GroupedRule {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    description: Option<String>,
+    severity: Severity,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    tags: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    author: Option<String>,
+    findings: Vec<GroupedFinding>,
+}
+
+// A finding reduced to what is not shared with the other findings of its
+// rule; `snippet` holds the full lines of the match, and `severity` is only
+// recorded if a check escalated it from that of the rule.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct GroupedFinding {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    path: Option<PathBuf>,
+    start_line: usize,
+    start_column: usize,
+    end_line: usize,
+    end_column: usize,
+    checker: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    severity: Option<Severity>,
+    #[serde(default, skip_serializing_if = "str::is_empty")]
+    fingerprint: String,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    captures: BTreeMap<String, String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    suppression: Option<SuppressionReason>,
+    snippet: String,
+}
+
+impl GroupedReport {
+    // matches of distinct rules with the same id (e.g., from different rule
+    // files) are grouped together, under the metadata of the first
+    pub fn from_matches(matches: &[RuleMatch]) -> Self {
+        let mut index = FxHashMap::default();
+        let mut rules = Vec::<(String, GroupedRule)>::new();
+
+        for m in matches {
+            let rule = m.rule();
+            let i = *index.entry(rule.id()).or_insert_with(|| {
+                let mut tags = rule.tags().iter().cloned().collect::<Vec<_>>();
+                tags.sort();
+
+                rules.push((
+                    rule.id().to_owned(),
+                    GroupedRule {
+                        description: rule.description().map(|d| d.trim().to_owned()),
+                        severity: rule.severity(),
+                        tags,
+                        author: rule.author().map(String::from),
+                        findings: Vec::new(),
+                    },
+                ));
+                rules.len() - 1
+            });
+
+            let rule = &mut rules[i].1;
+            rule.findings.push(GroupedFinding::new(m, rule.severity));
+        }
+
+        let mut report = Self {
+            rules,
+            metadata: None,
+        };
+        report.sort();
+        report
+    }
+
+    pub fn with_metadata(mut self, metadata: ReportMetadata) -> Self {
+        self.metadata = Some(metadata);
+        self
+    }
+
+    pub fn metadata(&self) -> Option<&ReportMetadata> {
+        self.metadata.as_ref()
+    }
+
+    fn sort(&mut self) {
+        self.rules.sort_by(|(a_id, a), (b_id, b)| {
+            b.severity.cmp(&a.severity).then_with(|| a_id.cmp(b_id))
+        });
+
+        for (_, rule) in self.rules.iter_mut() {
+            rule.findings.sort_by(|a, b| {
+                a.path
+                    .cmp(&b.path)
+                    .then_with(|| {
+                        (a.start_line, a.start_column).cmp(&(b.start_line, b.start_column))
+                    })
+                    .then_with(|| (a.end_line, a.end_column).cmp(&(b.end_line, b.end_column)))
+                    .then_with(|| a.checker.cmp(&b.checker))
+            });
+        }
+    }
+
+    pub fn rules(&self) -> impl Iterator<Item = (&str, &GroupedRule)> {
+        self.rules.iter().map(|(id, rule)| (id.as_str(), rule))
+    }
+
+    pub fn rule(&self, id: &str) -> Option<&GroupedRule> {
+        self.rules
+            .iter()
+            .find_map(|(rule_id, rule)| (rule_id == id).then_some(rule))
+    }
+
+    pub fn len(&self) -> usize {
+        self.rules.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    // the number of findings across all rules
+    pub fn count(&self) -> usize {
+        self.rules.iter().map(|(_, rule)| rule.findings.len()).sum()
+    }
+
+    // NOTE: not part of the serialized report, whose keys are all rule ids;
+    // suppressed findings are not counted
+    pub fn counts(&self) -> SeverityCounts {
+        let mut counts = SeverityCounts::new();
+        for (_, rule) in &self.rules {
+            counts.extend(
+                rule.findings
+                    .iter()
+                    .filter(|finding| finding.suppression.is_none())
+                    .map(|finding| finding.severity.unwrap_or(rule.severity)),
+            );
+        }
+        counts
+    }
+
+    pub fn to_writer(&self, writer: impl Write) -> Result<(), serde_json::Error> {
+        serde_json::to_writer_pretty(writer, self)
+    }
+
+    pub fn from_reader(reader: impl Read) -> Result<Self, serde_json::Error> {
+        serde_json::from_reader(reader)
+    }
+}
+
+impl GroupedRule {
+    pub fn description(&self) -> Option<&str> {
+        self.description.as_deref()
+    }
+
+    pub fn severity(&self) -> Severity {
+        self.severity
+    }
+
+    pub fn tags(&self) -> &[String] {
+        &self.tags
+    }
+
+    pub fn author(&self) -> Option<&str> {
+        self.author.as_deref()
+    }
+
+    pub fn findings(&self) -> &[GroupedFinding] {
+        &self.findings
+    }
+}
+
+impl GroupedFinding {
+    fn new(m: &RuleMatch, rule_severity: Severity) -> Self {
+        let source = m.source_ref();
+
+        let captures = reporting::captures(source, m.result())
+            .into_iter()
+            .map(|(var, value)| (var.to_owned(), value.to_owned()))
+            .collect();
+
+        let snippet = location::line_span(source, m.source_span(), 0, 0);
+
+        Self {
+            path: m.origin().map(Path::to_path_buf),
+            start_line: m.start_line(),
+            start_column: m.start_column(),
+            end_line: m.end_line(),
+            end_column: m.end_column(),
+            checker: m.checker().name().to_owned(),
+            severity: Some(m.effective_severity()).filter(|s| *s != rule_severity),
+            fingerprint: m.fingerprint().to_owned(),
+            captures,
+            suppression: m.suppression().cloned(),
+            snippet: source[snippet].to_owned(),
+        }
+    }
+
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    // NOTE: lines and columns are 1-based; columns count characters
+    pub fn start_line(&self) -> usize {
+        self.start_line
+    }
+
+    pub fn start_column(&self) -> usize {
+        self.start_column
+    }
+
+    pub fn end_line(&self) -> usize {
+        self.end_line
+    }
+
+    pub fn end_column(&self) -> usize {
+        self.end_column
+    }
+
+    pub fn checker(&self) -> &str {
+        &self.checker
+    }
+
+    // the severity the finding was escalated to, if it differs from that of
+    // its rule
+    pub fn severity(&self) -> Option<Severity> {
+        self.severity
+    }
+
+    pub fn fingerprint(&self) -> Option<&str> {
+        if self.fingerprint.is_empty() {
+            None
+        } else {
+            Some(&self.fingerprint)
+        }
+    }
+
+    // the values of the rule's variables, e.g., `$dst`
+    pub fn captures(&self) -> &BTreeMap<String, String> {
+        &self.captures
+    }
+
+    pub fn suppression(&self) -> Option<&SuppressionReason> {
+        self.suppression.as_ref()
+    }
+
+    pub fn snippet(&self) -> &str {
+        &self.snippet
+    }
+}
+
+impl Serialize for GroupedReport {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let len = 1 + self.rules.len() + usize::from(self.metadata.is_some());
+        let mut map = serializer.serialize_map(Some(len))?;
+
+        map.serialize_entry(VERSION_KEY, &SCHEMA_VERSION)?;
+
+        if let Some(ref metadata) = self.metadata {
+            map.serialize_entry(METADATA_KEY, metadata)?;
+        }
+
+        for (id, rule) in &self.rules {
+            map.serialize_entry(id, rule)?;
+        }
+
+        map.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for GroupedReport {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct RulesVisitor;
+
+        impl<'de> Visitor<'de> for RulesVisitor {
+            type Value = GroupedReport;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a map from rule ids to rules")
+            }
+
+            fn visit_map<A>(self, mut access: A) -> Result<Self::Value, A::Error>
+            where
+                A: MapAccess<'de>,
+            {
+                let mut rules = Vec::with_capacity(access.size_hint().unwrap_or_default());
+                let mut metadata = None;
+
+                // NOTE: reports without a version predate it
+                while let Some(key) = access.next_key::<String>()? {
+                    match key.as_str() {
+                        VERSION_KEY => {
+                            let version = access.next_value::<u32>()?;
+                            if version > SCHEMA_VERSION {
+                                return Err(A::Error::custom(format!(
+                                    "unsupported report schema version {version}"
+                                )));
+                            }
+                        }
+                        METADATA_KEY => metadata = Some(access.next_value()?),
+                        _ => rules.push((key, access.next_value()?)),
+                    }
+                }
+
+                // the input is not necessarily in order
+                let mut report = GroupedReport { rules, metadata };
+                report.sort();
+                Ok(report)
+            }
+        }
+
+        deserializer.deserialize_map(RulesVisitor)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::Value;
+
+    use super::GroupedReport;
+    use crate::matcher::RuleMatcher;
+    use crate::reporting::{ReportMetadata, SeverityCounts, SCHEMA_VERSION};
+    use crate::rule::Severity;
+    use crate::testing;
+
+    #[test]
+    fn test_grouped_report() -> Result<(), Box<dyn std::error::Error>> {
+        let pack = testing::pack_with(&[
+            (
+                "local-buffer.yml",
+                r#"
+id: local-buffer
+severity: low
+check-pattern:
+  pattern: '{ char $buf[_]; }'
+  escalate:
+  - when: {var: buf, regex: '名前'}
+    severity: medium
+"#,
+            ),
+            ("unbounded-copy.yml", testing::UNBOUNDED_COPY_DESCRIBED),
+        ])?;
+
+        let mut matcher = RuleMatcher::from_directory_with(pack.path(), false)?;
+
+        let mut matches =
+            matcher.matches_files(["tests/utf8-comments.c", "tests/commented-copy.c"])?;
+        matches.reverse();
+
+        let report = GroupedReport::from_matches(&matches);
+
+        assert_eq!(report.count(), matches.len());
+        assert_eq!(report.counts(), SeverityCounts::from_matches(&matches));
+
+        let ids = report.rules().map(|(id, _)| id).collect::<Vec<_>>();
+
+        assert_eq!(ids, ["unbounded-copy", "local-buffer"]);
+
+        let copy = report.rule("unbounded-copy").unwrap();
+
+        assert_eq!(copy.severity(), Severity::High);
+        assert_eq!(copy.tags()[0], "CWE-120");
+        assert!(copy
+            .findings()
+            .windows(2)
+            .all(|w| (w[0].path(), w[0].start_line()) <= (w[1].path(), w[1].start_line())));
+
+        let finding = copy
+            .findings()
+            .iter()
+            .find(|f| f.captures().get("$dst").map(String::as_str) == Some("名前"))
+            .unwrap();
+
+        assert_eq!(finding.snippet().trim(), "/* 🔥 */ strcpy(名前, src);");
+        assert_eq!(finding.checker(), "strcpy");
+        assert!(finding.fingerprint().is_some());
+        assert_eq!(finding.severity(), None);
+
+        // escalated findings record their own severity
+        let buffer = report.rule("local-buffer").unwrap();
+        let escalated = buffer
+            .findings()
+            .iter()
+            .find(|f| f.captures().get("$buf").map(String::as_str) == Some("名前"))
+            .unwrap();
+
+        assert_eq!(buffer.severity(), Severity::Low);
+        assert_eq!(escalated.severity(), Some(Severity::Medium));
+
+        let mut output = Vec::new();
+        report.to_writer(&mut output)?;
+
+        let value = serde_json::from_slice::<Value>(&output)?;
+
+        assert_eq!(value["unbounded-copy"]["severity"], "high");
+        assert!(value["unbounded-copy"]["findings"][0].get("rule").is_none());
+        assert!(value["unbounded-copy"]["findings"][0]
+            .get("severity")
+            .is_none());
+        assert_eq!(
+            value["local-buffer"]["findings"].as_array().map(Vec::len),
+            Some(report.rule("local-buffer").unwrap().findings().len())
+        );
+
+        let text = String::from_utf8(output.clone())?;
+
+        assert!(text.find("\"unbounded-copy\"").unwrap() < text.find("\"local-buffer\"").unwrap());
+
+        assert_eq!(GroupedReport::from_reader(output.as_slice())?, report);
+
+        // the metadata precedes the rules, and is optional on read
+        let report = report.with_metadata(ReportMetadata::from_ruleset(matcher.rules()));
+        let output = serde_json::to_string(&report)?;
+
+        assert!(output.starts_with(&format!(
+            "{{\"$schema_version\":{SCHEMA_VERSION},\"$metadata\":{{"
+        )));
+        assert_eq!(GroupedReport::from_reader(output.as_bytes())?, report);
+        assert_eq!(report.metadata().unwrap().rules(), 2);
+        assert_eq!(report.len(), 2);
+
+        let future = output.replacen(&format!(":{SCHEMA_VERSION},"), ":99,", 1);
+
+        assert!(GroupedReport::from_reader(future.as_bytes()).is_err());
+
+        Ok(())
+    }
+}
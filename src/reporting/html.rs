@@ -0,0 +1,319 @@
+use std::fmt::Write;
+
+use crate::matcher::{location_order, RuleMatch};
+use crate::reporting;
+use crate::rule::Severity;
+
+const SEVERITIES: [Severity; 5] = [
+    Severity::Critical,
+    Severity::High,
+    Severity::Medium,
+    Severity::Low,
+    Severity::None,
+];
+
+const STYLE: &str = "\
+body { font-family: sans-serif; margin: 2em; color: #222; }
+table { border-collapse: collapse; width: 100%; }
+th, td { border-bottom: 1px solid #ddd; padding: 0.3em 0.6em; text-align: left; vertical-align: top; }
+pre { background: #f6f8fa; padding: 0.6em; overflow-x: auto; }
+mark { background: #ffd33d; }
+.summary span { margin-right: 1.5em; }
+.sev { font-weight: bold; text-transform: uppercase; font-size: 0.8em; }
+.sev-critical { color: #8b0000; } .sev-high { color: #d73a49; }
+.sev-medium { color: #e36209; } .sev-low { color: #0366d6; } .sev-none { color: #6a737d; }
+.ln { color: #6a737d; user-select: none; }
+.tag { background: #eef; border-radius: 3px; padding: 0 0.3em; margin-right: 0.3em; }
+";
+
+const SCRIPT: &str = "\
+function filterFindings() {
+  var text = document.getElementById('filter').value.toLowerCase();
+  var severity = document.getElementById('severity').value;
+  document.querySelectorAll('tr.finding').forEach(function (row) {
+    var visible = row.textContent.toLowerCase().indexOf(text) >= 0
+      && (severity === '' || row.dataset.severity === severity);
+    row.style.display = visible ? '' : 'none';
+  });
+}
+";
+
+#[derive(Debug, Clone)]
+pub struct HtmlOptions {
+    title: String,
+    before: usize,
+    after: usize,
+}
+
+impl Default for HtmlOptions {
+    fn default() -> Self {
+        Self {
+            title: String::from("weggli-ruleset report"),
+            before: 3,
+            after: 3,
+        }
+    }
+}
+
+impl HtmlOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn title(mut self, title: impl Into<String>) -> Self {
+        self.title = title.into();
+        self
+    }
+
+    // the number of lines shown before and after each match
+    pub fn context(mut self, before: usize, after: usize) -> Self {
+        self.before = before;
+        self.after = after;
+        self
+    }
+}
+
+// A single self-contained HTML page (inline CSS and script, no external
+// assets); findings are ordered by severity (highest first), then location.
+pub fn render(matches: &[RuleMatch], options: &HtmlOptions) -> String {
+    let mut matches = matches.iter().collect::<Vec<_>>();
+    matches.sort_by(|a, b| {
+        b.effective_severity()
+            .cmp(&a.effective_severity())
+            .then_with(|| location_order(a, b))
+    });
+
+    let mut output = String::new();
+    let title = escape(&options.title);
+
+    let _ = write!(
+        output,
+        "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n\
+         <title>{title}</title>\n<style>\n{STYLE}</style>\n<script>\n{SCRIPT}</script>\n\
+         </head>\n<body>\n<h1>{title}</h1>\n"
+    );
+
+    output.push_str("<p class=\"summary\">");
+    let _ = write!(output, "<span>{} findings</span>", matches.len());
+    for severity in SEVERITIES {
+        let count = matches
+            .iter()
+            .filter(|m| m.effective_severity() == severity)
+            .count();
+        if count > 0 {
+            let _ = write!(
+                output,
+                "<span class=\"sev sev-{}\">{severity}: {count}</span>",
+                class(severity)
+            );
+        }
+    }
+    output.push_str("</p>\n");
+
+    output.push_str(
+        "<p><input id=\"filter\" type=\"search\" placeholder=\"Filter\" oninput=\"filterFindings()\"> \
+         <select id=\"severity\" onchange=\"filterFindings()\"><option value=\"\">all</option>",
+    );
+    for severity in SEVERITIES {
+        let _ = write!(
+            output,
+            "<option value=\"{}\">{severity}</option>",
+            class(severity)
+        );
+    }
+    output.push_str("</select></p>\n");
+
+    output.push_str(
+        "<table>\n<thead><tr><th>Severity</th><th>Rule</th><th>Location</th><th>Details</th></tr></thead>\n<tbody>\n",
+    );
+
+    for m in matches {
+        render_finding(&mut output, m, options);
+    }
+
+    output.push_str("</tbody>\n</table>\n</body>\n</html>\n");
+
+    output
+}
+
+fn render_finding(output: &mut String, m: &RuleMatch, options: &HtmlOptions) {
+    let rule = m.rule();
+    let severity = m.effective_severity();
+
+    let location = match m.origin() {
+        Some(path) => format!("{}:{}:{}", path.display(), m.start_line(), m.start_column()),
+        None => format!("<source>:{}:{}", m.start_line(), m.start_column()),
+    };
+    let location = escape(&location);
+
+    let _ = write!(
+        output,
+        "<tr class=\"finding\" data-severity=\"{}\"><td class=\"sev sev-{0}\">{severity}</td>\
+         <td>{}<br><small>{}</small></td><td><code>{location}</code></td><td><details><summary>",
+        class(severity),
+        escape(rule.id()),
+        escape(m.checker().name()),
+    );
+
+    output.push_str(&escape(rule.description().unwrap_or("show")));
+    output.push_str("</summary>\n");
+
+    let mut tags = rule.tags().iter().collect::<Vec<_>>();
+    tags.sort();
+    if !tags.is_empty() {
+        output.push_str("<p>");
+        for tag in tags {
+            let _ = write!(output, "<span class=\"tag\">{}</span>", escape(tag));
+        }
+        output.push_str("</p>\n");
+    }
+
+    let vars = reporting::captures(m.source_ref(), m.result());
+    if !vars.is_empty() {
+        output.push_str("<dl>");
+        for (var, value) in vars {
+            let _ = write!(
+                output,
+                "<dt><code>{}</code></dt><dd><code>{}</code></dd>",
+                escape(var),
+                escape(value)
+            );
+        }
+        output.push_str("</dl>\n");
+    }
+
+    output.push_str("<pre>");
+    render_snippet(output, m, options);
+    output.push_str("</pre>\n");
+
+    if let Some(path) = rule.source_path() {
+        let _ = writeln!(
+            output,
+            "<p><small>Defined in <code>{}</code></small></p>",
+            escape(&path.to_string_lossy())
+        );
+    }
+
+    output.push_str("</details></td></tr>\n");
+}
+
+fn render_snippet(output: &mut String, m: &RuleMatch, options: &HtmlOptions) {
+    let source = m.source_ref();
+    let marks = reporting::highlights(source, m.result());
+
+    let first = m.start_line().saturating_sub(options.before).max(1);
+    let last = m.end_line() + options.after;
+
+    let mut offset = 0;
+    for (i, line) in source.split_inclusive('\n').enumerate() {
+        let number = m.first_line() + i;
+        let range = offset..offset + line.len();
+        offset = range.end;
+
+        if number < first {
+            continue;
+        }
+        if number > last {
+            break;
+        }
+
+        let _ = write!(output, "<span class=\"ln\">{number:>5} </span>");
+
+        let mut position = range.start;
+        for mark in marks
+            .iter()
+            .filter(|r| r.start < range.end && r.end > range.start)
+        {
+            let start = mark.start.max(range.start);
+            let end = mark.end.min(range.end);
+
+            output.push_str(&escape(&source[position..start]));
+            output.push_str("<mark>");
+            output.push_str(&escape(&source[start..end]));
+            output.push_str("</mark>");
+
+            position = end;
+        }
+        output.push_str(&escape(
+            source[position..range.end].trim_end_matches(['\r', '\n']),
+        ));
+        output.push('\n');
+    }
+}
+
+fn class(severity: Severity) -> &'static str {
+    match severity {
+        Severity::None => "none",
+        Severity::Low => "low",
+        Severity::Medium => "medium",
+        Severity::High => "high",
+        Severity::Critical => "critical",
+    }
+}
+
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod test {
+    use super::{render, HtmlOptions};
+    use crate::matcher::RuleMatcher;
+    use crate::testing;
+
+    #[test]
+    fn test_render_html() -> Result<(), Box<dyn std::error::Error>> {
+        let pack = testing::pack_with(&[(
+            "unbounded-copy.yml",
+            r#"
+id: unbounded-copy
+severity: high
+tags: [CWE-120]
+check pattern:
+  regex: func=^strcpy$
+  pattern: |
+    { $func($dst, _); }
+"#,
+        )])?;
+
+        let mut matcher = RuleMatcher::from_directory_with(pack.path(), false)?;
+        let matches = matcher.matches_file("tests/utf8-comments.c")?;
+
+        let html = render(&matches, &HtmlOptions::new().title("<scan>"));
+
+        assert!(html.starts_with("<!DOCTYPE html>"));
+        assert!(!html.contains("src=") && !html.contains("href="));
+        assert!(html.contains("<title>&lt;scan&gt;</title>"));
+        assert!(html.contains("<span>3 findings</span>"));
+        assert!(html.contains("high: 2</span>"));
+        assert!(html.contains("<span class=\"tag\">CWE-120</span>"));
+        assert!(html.contains("<dt><code>$dst</code></dt><dd><code>名前</code></dd>"));
+        assert!(html.contains("<mark>strcpy</mark>(<mark>dst</mark>"));
+        assert!(html.find("sev-high").unwrap() < html.find("data-severity=\"low\"").unwrap());
+        assert!(html.contains(&format!(
+            "<p><small>Defined in <code>{}</code></small></p>",
+            pack.path().join("unbounded-copy.yml").display()
+        )));
+
+        let source = "void f(char *s, int n) { if (n < 2) strcpy(s, \"<b>\"); }";
+        let matches = matcher.matches(source)?;
+        let html = render(&matches, &HtmlOptions::new());
+
+        assert!(html.contains("if (n &lt; 2)"));
+        assert!(html.contains("&quot;&lt;b&gt;&quot;"));
+        assert!(!html.contains("<b>"));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,171 @@
+use std::io::{self, BufRead, Write};
+
+use thiserror::Error;
+
+use crate::matcher::RuleMatch;
+use crate::reporting::RuleMatchReport;
+
+#[derive(Debug, Error)]
+pub enum JsonlError {
+    #[error("cannot read or write reports: {0}")]
+    Io(#[from] io::Error),
+    #[error("cannot parse report on line {0}: {1}")]
+    Parse(usize, serde_json::Error),
+    #[error("cannot serialize report: {0}")]
+    Serialize(serde_json::Error),
+}
+
+// Writes one report per line, each a compact JSON object terminated by a
+// newline, so that output can be consumed while a scan is still running.
+pub struct Writer<W: Write> {
+    writer: W,
+    include_source: bool,
+}
+
+impl<W: Write> Writer<W> {
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer,
+            include_source: true,
+        }
+    }
+
+    // when disabled, the `source` field is omitted from each line; reports
+    // read back from such output have an empty source
+    pub fn include_source(mut self, include_source: bool) -> Self {
+        self.include_source = include_source;
+        self
+    }
+
+    pub fn write(&mut self, report: &RuleMatchReport<'_>) -> Result<(), JsonlError> {
+        if self.include_source {
+            serde_json::to_writer(&mut self.writer, report)
+        } else {
+            serde_json::to_writer(&mut self.writer, &report.without_source())
+        }
+        .map_err(JsonlError::Serialize)?;
+
+        self.writer.write_all(b"\n")?;
+
+        Ok(())
+    }
+
+    pub fn finish(mut self) -> Result<W, JsonlError> {
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+}
+
+pub fn write_matches<'a, W: Write>(
+    writer: W,
+    matches: impl IntoIterator<Item = &'a RuleMatch>,
+) -> Result<W, JsonlError> {
+    let mut writer = Writer::new(writer);
+
+    for m in matches {
+        writer.write(&RuleMatchReport::new(m))?;
+    }
+
+    writer.finish()
+}
+
+// blank lines are skipped; line numbers in errors are 1-based
+pub fn read_jsonl(
+    reader: impl BufRead,
+) -> impl Iterator<Item = Result<RuleMatchReport<'static>, JsonlError>> {
+    reader
+        .lines()
+        .enumerate()
+        .filter_map(|(i, line)| match line {
+            Ok(line) if line.trim().is_empty() => None,
+            Ok(line) => Some(
+                serde_json::from_str::<RuleMatchReport<'_>>(&line)
+                    .map(RuleMatchReport::into_owned)
+                    .map_err(|e| JsonlError::Parse(i + 1, e)),
+            ),
+            Err(e) => Some(Err(JsonlError::Io(e))),
+        })
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::BufReader;
+
+    use super::{read_jsonl, write_matches, JsonlError, Writer};
+    use crate::matcher::RuleMatcher;
+    use crate::reporting::RuleMatchReport;
+
+    #[test]
+    fn test_jsonl_round_trip() -> Result<(), Box<dyn std::error::Error>> {
+        let rule = r#"
+id: unbounded-copy
+check pattern:
+  regex: func=^strcpy$
+  pattern: |
+    { $func($dst, _); }
+"#;
+
+        let mut matcher = RuleMatcher::from_str(rule)?;
+        let matches = matcher.matches_file("tests/utf8-comments.c")?;
+
+        let output = write_matches(Vec::new(), &matches)?;
+        let output = String::from_utf8(output)?;
+
+        assert!(output.ends_with('\n'));
+        assert_eq!(output.lines().count(), matches.len());
+
+        for line in output.lines() {
+            serde_json::from_str::<serde_json::Value>(line)?;
+        }
+
+        let reports =
+            read_jsonl(BufReader::new(output.as_bytes())).collect::<Result<Vec<_>, _>>()?;
+
+        assert_eq!(reports.len(), matches.len());
+
+        for (report, m) in reports.iter().zip(&matches) {
+            assert_eq!(report.rule(), m.rule().id());
+            assert_eq!(report.checker(), m.checker().name());
+            assert_eq!(report.start_offset(), m.start_offset());
+            assert_eq!(report.start_line(), m.start_line());
+            assert_eq!(report.source(), m.source_ref());
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_jsonl_without_source() -> Result<(), Box<dyn std::error::Error>> {
+        let rule = r#"
+id: unbounded-copy
+check pattern:
+  regex: func=^strcpy$
+  pattern: |
+    { $func($dst, _); }
+"#;
+
+        let mut matcher = RuleMatcher::from_str(rule)?;
+        let matches = matcher.matches_file("tests/utf8-comments.c")?;
+
+        let mut writer = Writer::new(Vec::new()).include_source(false);
+        for m in &matches {
+            writer.write(&RuleMatchReport::new(m))?;
+        }
+        let output = writer.finish()?;
+
+        assert!(!String::from_utf8_lossy(&output).contains("\"source\""));
+
+        let reports = read_jsonl(&output[..]).collect::<Result<Vec<_>, _>>()?;
+
+        assert_eq!(reports.len(), matches.len());
+        assert!(reports.iter().all(|r| r.source().is_empty()));
+        assert_eq!(reports[0].start_offset(), matches[0].start_offset());
+
+        let mut lines = read_jsonl("\n{\"rule\": 1}\n".as_bytes());
+
+        assert!(matches!(lines.next(), Some(Err(JsonlError::Parse(2, _)))));
+        assert!(lines.next().is_none());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,144 @@
+use std::io::{self, BufRead, Read};
+
+use thiserror::Error;
+
+use crate::reporting::jsonl::{self, JsonlError};
+use crate::reporting::schema::Document;
+use crate::reporting::{migrate, ReportSet, RuleMatchReport, SCHEMA_VERSION};
+
+#[derive(Debug, Error)]
+pub enum ReportError {
+    #[error("cannot read reports: {0}")]
+    Io(#[from] io::Error),
+    #[error("cannot parse reports: {0}")]
+    Parse(serde_json::Error),
+    #[error("cannot parse report on line {0}: {1}")]
+    ParseLine(usize, serde_json::Error),
+    #[error("unsupported report schema version {0} (at most {SCHEMA_VERSION} is supported)")]
+    UnsupportedVersion(u32),
+}
+
+impl From<JsonlError> for ReportError {
+    fn from(e: JsonlError) -> Self {
+        match e {
+            JsonlError::Io(e) => Self::Io(e),
+            JsonlError::Parse(line, e) => Self::ParseLine(line, e),
+            JsonlError::Serialize(e) => Self::Parse(e),
+        }
+    }
+}
+
+// Loads reports serialized as a JSON array or as a `ReportSet`, e.g., by a
+// previous scan, of any version of the schema (see `SCHEMA_VERSION`); the
+// reports own their data, and so outlive `reader`.
+pub fn load_json(reader: impl Read) -> Result<Vec<RuleMatchReport<'static>>, ReportError> {
+    load_set(reader).map(ReportSet::into_reports)
+}
+
+// see `load_json`; the metadata of the set, if any, is kept
+pub fn load_set(reader: impl Read) -> Result<ReportSet<'static>, ReportError> {
+    let document =
+        serde_json::from_reader::<_, Document>(io::BufReader::new(reader)).map_err(|e| {
+            if e.is_io() {
+                ReportError::Io(e.into())
+            } else {
+                ReportError::Parse(e)
+            }
+        })?;
+
+    if document.schema_version > SCHEMA_VERSION {
+        return Err(ReportError::UnsupportedVersion(document.schema_version));
+    }
+
+    let set = document
+        .findings
+        .into_iter()
+        .map(migrate)
+        .collect::<ReportSet>();
+
+    Ok(match document.metadata {
+        Some(metadata) => set.with_metadata(metadata),
+        None => set,
+    })
+}
+
+// see `load_json`; reports are serialized one per line (see `jsonl`)
+pub fn load_jsonl(reader: impl BufRead) -> Result<Vec<RuleMatchReport<'static>>, ReportError> {
+    jsonl::read_jsonl(reader)
+        .map(|report| report.map(migrate).map_err(ReportError::from))
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::{load_json, load_jsonl, ReportError};
+    use crate::matcher::RuleMatcher;
+    use crate::reporting::jsonl::write_matches;
+    use crate::reporting::{RuleMatchReport, SourceMode};
+
+    #[test]
+    fn test_load_reports() -> Result<(), Box<dyn std::error::Error>> {
+        let rule = r#"
+id: unbounded-copy
+check pattern:
+  regex: func=^strcpy$
+  pattern: |
+    { $func($dst, _); }
+"#;
+
+        let mut matcher = RuleMatcher::from_str(rule)?;
+        let matches = matcher.matches_files(["tests/utf8-comments.c", "tests/ls-main.c"])?;
+
+        let reports = matches.iter().map(RuleMatchReport::new).collect::<Vec<_>>();
+        let json = serde_json::to_vec(&reports)?;
+
+        let loaded = load_json(json.as_slice())?;
+        drop(json);
+
+        assert_eq!(loaded.len(), matches.len());
+
+        for (report, m) in loaded.iter().zip(&matches) {
+            assert_eq!(
+                report.display(2, 2, true),
+                RuleMatchReport::new(m).display(2, 2, true)
+            );
+            assert_eq!(report.matched_text(), m.matched_text());
+            assert_eq!(report.path(), m.origin());
+        }
+
+        // loaded reports can be filtered and re-rendered like fresh ones
+        let high = loaded
+            .into_iter()
+            .filter(|r| r.rule() == "unbounded-copy")
+            .map(|r| {
+                r.with_source_mode(SourceMode::Snippet {
+                    before: 2,
+                    after: 2,
+                })
+            })
+            .collect::<Vec<_>>();
+
+        assert!(!high.is_empty());
+        assert!(high.iter().all(|r| !r.display(2, 2, true).is_empty()));
+
+        let jsonl = write_matches(Vec::new(), &matches)?;
+        let loaded = load_jsonl(jsonl.as_slice())?;
+
+        assert_eq!(loaded.len(), matches.len());
+        assert_eq!(
+            loaded[0].display(1, 1, false),
+            RuleMatchReport::new(&matches[0]).display(1, 1, false)
+        );
+
+        assert!(matches!(
+            load_json(&b"[{\"rule\": 1}]"[..]),
+            Err(ReportError::Parse(_))
+        ));
+        assert!(matches!(
+            load_jsonl(&b"\n{}\n"[..]),
+            Err(ReportError::ParseLine(2, _))
+        ));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,326 @@
+use std::path::Path;
+
+use serde::Serialize;
+use serde_json::{json, Value};
+
+use crate::location;
+use crate::matcher::RuleMatch;
+use crate::reporting::path_uri;
+use crate::rule::Severity;
+
+// Minimal counterparts of the types of the Language Server Protocol (3.17),
+// serialized as LSP clients expect them, so that a language server can publish
+// findings without depending on a full LSP implementation.
+
+// the unit of `Position::character` negotiated with the client (LSP's
+// `PositionEncodingKind`); clients that do not negotiate expect UTF-16
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize)]
+pub enum PositionEncoding {
+    #[serde(rename = "utf-8")]
+    Utf8,
+    #[default]
+    #[serde(rename = "utf-16")]
+    Utf16,
+}
+
+// NOTE: lines and characters are 0-based; characters are counted in code
+// units of the encoding, from the start of the line
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+pub struct Position {
+    pub line: u32,
+    pub character: u32,
+}
+
+impl Position {
+    // the position of a byte offset into `source` (offsets in the middle of a
+    // codepoint refer to its start)
+    pub fn from_offset(source: &str, offset: usize, encoding: PositionEncoding) -> Self {
+        let offset = location::floor_char_boundary(source, offset);
+        let prefix = &source[..offset];
+
+        let line_start = prefix.rfind('\n').map(|i| i + 1).unwrap_or(0);
+        let line = memchr::memchr_iter(b'\n', prefix.as_bytes()).count();
+        let character = match encoding {
+            PositionEncoding::Utf8 => prefix.len() - line_start,
+            PositionEncoding::Utf16 => prefix[line_start..].encode_utf16().count(),
+        };
+
+        Self {
+            line: line as u32,
+            character: character as u32,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize)]
+pub struct Range {
+    pub start: Position,
+    pub end: Position,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum DiagnosticSeverity {
+    Error = 1,
+    Warning = 2,
+    Information = 3,
+    Hint = 4,
+}
+
+impl From<Severity> for DiagnosticSeverity {
+    fn from(severity: Severity) -> Self {
+        match severity {
+            Severity::Critical | Severity::High => Self::Error,
+            Severity::Medium => Self::Warning,
+            Severity::Low => Self::Information,
+            Severity::None => Self::Hint,
+        }
+    }
+}
+
+// NOTE: serialized as a number, as in the protocol
+impl Serialize for DiagnosticSeverity {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u8(*self as u8)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize)]
+pub struct CodeDescription {
+    pub href: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize)]
+pub struct Location {
+    pub uri: String,
+    pub range: Range,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize)]
+pub struct DiagnosticRelatedInformation {
+    pub location: Location,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Diagnostic {
+    pub range: Range,
+    pub severity: DiagnosticSeverity,
+    // the rule id
+    pub code: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub code_description: Option<CodeDescription>,
+    pub source: String,
+    pub message: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub related_information: Vec<DiagnosticRelatedInformation>,
+    // the tags of the rule (sorted) and the fingerprint of the finding, e.g.,
+    // for code actions
+    pub data: Value,
+}
+
+// Converts matches into diagnostics, with positions in `encoding`: the
+// message is the description of the rule (or its id) followed by the checker
+// name, and the first CWE tag of the rule (in sorted order) links to its
+// definition. When the match has an origin, each tag is also reported as
+// related information at the location of the match.
+//
+// NOTE: only `\n` terminates lines, as everywhere else in this crate; a lone
+// `\r` (which LSP also treats as a terminator) does not
+pub fn to_diagnostics(matches: &[RuleMatch], encoding: PositionEncoding) -> Vec<Diagnostic> {
+    matches.iter().map(|m| to_diagnostic(m, encoding)).collect()
+}
+
+fn to_diagnostic(m: &RuleMatch, encoding: PositionEncoding) -> Diagnostic {
+    let rule = m.rule();
+    let source = m.source_ref();
+    let span = m.source_span();
+
+    // NOTE: a snippet starts at the start of a line (see `SourceRetention`)
+    let position = |offset| {
+        let position = Position::from_offset(source, offset, encoding);
+        Position {
+            line: position.line + (m.first_line() - 1) as u32,
+            ..position
+        }
+    };
+
+    let range = Range {
+        start: position(span.start),
+        end: position(span.end),
+    };
+
+    let mut tags = rule.tags().iter().map(String::as_str).collect::<Vec<_>>();
+    tags.sort_unstable();
+
+    let message = match rule.description() {
+        Some(description) => format!("{} ({})", description.trim(), m.checker().name()),
+        None => format!("{} ({})", rule.id(), m.checker().name()),
+    };
+
+    let related_information = match m.origin() {
+        Some(origin) => {
+            let uri = file_uri(origin);
+            tags.iter()
+                .map(|tag| DiagnosticRelatedInformation {
+                    location: Location {
+                        uri: uri.clone(),
+                        range,
+                    },
+                    message: format!("tag: {tag}"),
+                })
+                .collect()
+        }
+        None => Vec::new(),
+    };
+
+    Diagnostic {
+        range,
+        severity: m.effective_severity().into(),
+        code: rule.id().to_owned(),
+        code_description: tags.iter().find_map(|tag| cwe_href(tag)),
+        source: String::from(env!("CARGO_PKG_NAME")),
+        message,
+        related_information,
+        data: json!({
+            "tags": tags,
+            "fingerprint": m.fingerprint(),
+        }),
+    }
+}
+
+// e.g., `CWE-120` (in any case)
+fn cwe_href(tag: &str) -> Option<CodeDescription> {
+    let id = tag
+        .get(..4)?
+        .eq_ignore_ascii_case("CWE-")
+        .then(|| &tag[4..])?;
+    if id.is_empty() || !id.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+
+    Some(CodeDescription {
+        href: format!("https://cwe.mitre.org/data/definitions/{id}.html"),
+    })
+}
+
+// a `file://` URI (see `path_uri`)
+//
+// NOTE: relative paths are made absolute against the current directory, if
+// it is available
+fn file_uri(path: &Path) -> String {
+    path_uri(&std::path::absolute(path).unwrap_or_else(|_| path.to_owned()))
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::Path;
+
+    use super::{file_uri, to_diagnostics, DiagnosticSeverity, Position, PositionEncoding};
+    use crate::matcher::RuleMatcher;
+
+    #[test]
+    fn test_positions() {
+        let position = |source, offset, encoding| {
+            let Position { line, character } = Position::from_offset(source, offset, encoding);
+            (line, character)
+        };
+
+        // 🔥 is a single character, but 4 bytes and 2 UTF-16 code units
+        let source = "a\n/* 🔥 */ x = 名前;\n";
+        let x = source.find('x').unwrap();
+        assert_eq!(position(source, x, PositionEncoding::Utf8), (1, 11));
+        assert_eq!(position(source, x, PositionEncoding::Utf16), (1, 9));
+
+        // characters of the BMP are a single UTF-16 code unit
+        let end = source.find(';').unwrap();
+        assert_eq!(position(source, end, PositionEncoding::Utf8), (1, 21));
+        assert_eq!(position(source, end, PositionEncoding::Utf16), (1, 15));
+
+        // offsets in the middle of a codepoint refer to its start
+        let fire = source.find('🔥').unwrap();
+        assert_eq!(position(source, fire + 2, PositionEncoding::Utf16), (1, 3));
+
+        // the end of the source, after the last line terminator
+        assert_eq!(
+            position(source, source.len(), PositionEncoding::Utf16),
+            (2, 0)
+        );
+        assert_eq!(position("", 0, PositionEncoding::Utf16), (0, 0));
+    }
+
+    #[test]
+    fn test_to_diagnostics() -> Result<(), Box<dyn std::error::Error>> {
+        let rule = r#"
+id: unbounded-copy
+severity: high
+tags: [CWE-120, CWE-676]
+check pattern:
+  regex: func=^strcpy$
+  pattern: |
+    { $func($dst, _); }
+"#;
+
+        let mut matcher = RuleMatcher::from_str(rule)?;
+        let matches = matcher.matches_file("tests/utf8-comments.c")?;
+
+        let utf8 = to_diagnostics(&matches, PositionEncoding::Utf8);
+        let utf16 = to_diagnostics(&matches, PositionEncoding::Utf16);
+        assert_eq!(utf16.len(), matches.len());
+
+        // `/* 🔥 */ strcpy(名前, src);` on line 12
+        let i = utf16.iter().position(|d| d.range.start.line == 11).unwrap();
+        assert_eq!(utf8[i].range.start.character, 15);
+        assert_eq!(utf16[i].range.start.character, 13);
+        assert_eq!(utf16[i].range.end.line, 11);
+        // the match contains 名前 (2 code units, but 6 bytes)
+        let text = matches[i].matched_text();
+        assert!(text.contains("名前"));
+        assert_eq!(
+            utf16[i].range.end.character,
+            13 + text.encode_utf16().count() as u32
+        );
+        assert_eq!(utf8[i].range.end.character, 15 + text.len() as u32);
+
+        let m = &matches[i];
+        let diagnostic = &utf16[i];
+        assert_eq!(diagnostic.code, m.rule().id());
+        assert_eq!(
+            diagnostic.severity,
+            DiagnosticSeverity::from(m.effective_severity())
+        );
+        assert!(diagnostic
+            .message
+            .ends_with(&format!("({})", m.checker().name())));
+        assert_eq!(diagnostic.related_information.len(), m.rule().tags().len());
+        assert_eq!(
+            diagnostic.related_information[0].location.uri,
+            file_uri(Path::new("tests/utf8-comments.c"))
+        );
+
+        let json = serde_json::to_value(diagnostic)?;
+        assert_eq!(json["range"]["start"]["character"], 13);
+        assert!(json["severity"].is_u64());
+        assert_eq!(json["source"], "weggli-ruleset");
+        assert_eq!(json["data"]["fingerprint"], m.fingerprint());
+        assert!(json["codeDescription"]["href"]
+            .as_str()
+            .unwrap()
+            .starts_with("https://cwe.mitre.org/data/definitions/"));
+
+        // matches without an origin have no related information
+        let source = std::fs::read_to_string("tests/utf8-comments.c")?;
+        let matches = matcher.matches(&source)?;
+        let diagnostics = to_diagnostics(&matches, PositionEncoding::Utf16);
+        assert!(diagnostics.iter().all(|d| d.related_information.is_empty()));
+        assert_eq!(diagnostics[i].range, utf16[i].range);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_file_uri() {
+        let uri = file_uri(Path::new("/src/a b/名.c"));
+        assert_eq!(uri, "file:///src/a%20b/%E5%90%8D.c");
+    }
+}
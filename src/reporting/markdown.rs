@@ -0,0 +1,380 @@
+use std::fmt::Write;
+
+use crate::matcher::{location_order, RuleMatch};
+use crate::reporting::{ColorChoice, DisplayOptions, FileReport};
+use crate::rule::{Rule, Severity};
+
+#[derive(Debug, Clone)]
+pub struct MarkdownOptions {
+    before: usize,
+    after: usize,
+    line_numbers: bool,
+    max_per_rule: Option<usize>,
+    collapse: bool,
+    min_severity: Option<Severity>,
+}
+
+impl Default for MarkdownOptions {
+    fn default() -> Self {
+        Self {
+            before: 2,
+            after: 2,
+            line_numbers: true,
+            max_per_rule: None,
+            collapse: false,
+            min_severity: None,
+        }
+    }
+}
+
+impl MarkdownOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // the context passed to `RuleMatch::display` for each snippet
+    pub fn context(mut self, before: usize, after: usize, line_numbers: bool) -> Self {
+        self.before = before;
+        self.after = after;
+        self.line_numbers = line_numbers;
+        self
+    }
+
+    // findings beyond `max` are summarised as "and N more…" (per file, with
+    // `render_files`)
+    pub fn max_per_rule(mut self, max: usize) -> Self {
+        self.max_per_rule = Some(max);
+        self
+    }
+
+    // wraps the findings of each rule (or file) in a `<details>` block
+    pub fn collapse(mut self, collapse: bool) -> Self {
+        self.collapse = collapse;
+        self
+    }
+
+    pub fn min_severity(mut self, severity: Severity) -> Self {
+        self.min_severity = Some(severity);
+        self
+    }
+}
+
+// A summary table of rules (by severity, highest first, then by id) followed
+// by a section per rule listing its findings in location order.
+pub fn render(matches: &[RuleMatch], options: &MarkdownOptions) -> String {
+    let mut matches = matches
+        .iter()
+        .filter(|m| {
+            options
+                .min_severity
+                .is_none_or(|severity| m.effective_severity() >= severity)
+        })
+        .collect::<Vec<_>>();
+
+    matches.sort_by(|a, b| {
+        b.rule()
+            .severity()
+            .cmp(&a.rule().severity())
+            .then_with(|| a.rule().id().cmp(b.rule().id()))
+            .then_with(|| location_order(a, b))
+    });
+
+    let groups = matches
+        .chunk_by(|a, b| a.rule().id() == b.rule().id())
+        .collect::<Vec<_>>();
+
+    let mut output = String::new();
+
+    if groups.is_empty() {
+        output.push_str("No findings.\n");
+        return output;
+    }
+
+    output.push_str("| Rule | Severity | Count |\n| --- | --- | ---: |\n");
+
+    for group in &groups {
+        let rule = group[0].rule();
+        let _ = writeln!(
+            output,
+            "| {} | {} | {} |",
+            code(&escape_cell(rule.id())),
+            rule.severity(),
+            group.len()
+        );
+    }
+
+    for group in &groups {
+        render_rule(&mut output, group[0].rule(), group, options);
+    }
+
+    output
+}
+
+// A summary table of files (see `reporting::by_file`) followed by a section
+// per file listing its findings in location order.
+pub fn render_files(files: &[FileReport], options: &MarkdownOptions) -> String {
+    let files = files
+        .iter()
+        .map(|file| {
+            let findings = file
+                .findings()
+                .iter()
+                .copied()
+                .filter(|m| {
+                    options
+                        .min_severity
+                        .is_none_or(|severity| m.effective_severity() >= severity)
+                })
+                .collect::<Vec<_>>();
+            (file, findings)
+        })
+        .filter(|(_, findings)| !findings.is_empty())
+        .collect::<Vec<_>>();
+
+    let mut output = String::new();
+
+    if files.is_empty() {
+        output.push_str("No findings.\n");
+        return output;
+    }
+
+    output.push_str("| File | Max severity | Count |\n| --- | --- | ---: |\n");
+
+    for (file, findings) in &files {
+        let severity = findings
+            .iter()
+            .map(|m| m.effective_severity())
+            .max()
+            .unwrap_or_default();
+
+        let _ = writeln!(
+            output,
+            "| {} | {severity} | {} |",
+            code(&escape_cell(&file.path().to_string_lossy())),
+            findings.len()
+        );
+    }
+
+    for (file, findings) in &files {
+        let _ = write!(output, "\n### {}\n\n", code(&file.path().to_string_lossy()));
+
+        render_findings(&mut output, findings, options, |m| {
+            format!(
+                "{} {} ({}/{})",
+                code(&format!("line {}", m.start_line())),
+                m.effective_severity(),
+                m.rule().id(),
+                m.checker().name()
+            )
+        });
+    }
+
+    output
+}
+
+fn render_rule(
+    output: &mut String,
+    rule: &Rule,
+    matches: &[&RuleMatch],
+    options: &MarkdownOptions,
+) {
+    let _ = write!(
+        output,
+        "\n### {} ({})\n\n",
+        code(rule.id()),
+        rule.severity()
+    );
+
+    if let Some(description) = rule.description() {
+        let _ = write!(output, "{}\n\n", description.trim());
+    }
+
+    render_findings(output, matches, options, |m| {
+        let location = match m.origin() {
+            Some(path) => format!("{}:{}", path.display(), m.start_line()),
+            None => format!("<source>:{}", m.start_line()),
+        };
+
+        format!("{} ({})", code(&location), m.checker().name())
+    });
+
+    if let Some(path) = rule.source_path() {
+        let _ = write!(
+            output,
+            "<sub>Defined in {}</sub>\n\n",
+            code(&path.to_string_lossy())
+        );
+    }
+}
+
+fn render_findings(
+    output: &mut String,
+    matches: &[&RuleMatch],
+    options: &MarkdownOptions,
+    heading: impl Fn(&RuleMatch) -> String,
+) {
+    if options.collapse {
+        let _ = write!(
+            output,
+            "<details>\n<summary>{} finding{}</summary>\n\n",
+            matches.len(),
+            if matches.len() == 1 { "" } else { "s" }
+        );
+    }
+
+    let shown = options
+        .max_per_rule
+        .unwrap_or(matches.len())
+        .min(matches.len());
+
+    for m in &matches[..shown] {
+        // NOTE: escape codes would end up in the fenced block verbatim
+        let snippet = m.display_with(
+            &DisplayOptions::new()
+                .context(options.before, options.after)
+                .line_numbers(options.line_numbers)
+                .color(ColorChoice::Never),
+        );
+        let fence = fence(&snippet);
+
+        let _ = write!(
+            output,
+            "#### {}\n\n{fence}c\n{}\n{fence}\n\n",
+            heading(m),
+            snippet.trim_end()
+        );
+    }
+
+    if shown < matches.len() {
+        let _ = write!(output, "_and {} more…_\n\n", matches.len() - shown);
+    }
+
+    if options.collapse {
+        output.push_str("</details>\n");
+    }
+}
+
+// inline code containing backticks needs a longer delimiter
+fn code(text: &str) -> String {
+    let ticks = "`".repeat(longest_run(text, '`') + 1);
+    if text.starts_with('`') || text.ends_with('`') {
+        format!("{ticks} {text} {ticks}")
+    } else {
+        format!("{ticks}{text}{ticks}")
+    }
+}
+
+fn fence(snippet: &str) -> String {
+    "`".repeat(longest_run(snippet, '`').max(2) + 1)
+}
+
+fn longest_run(text: &str, c: char) -> usize {
+    text.split(|x| x != c).map(str::len).max().unwrap_or(0)
+}
+
+fn escape_cell(text: &str) -> String {
+    text.replace('|', "\\|")
+}
+
+#[cfg(test)]
+mod test {
+    use super::{render, render_files, MarkdownOptions};
+    use crate::matcher::RuleMatcher;
+    use crate::reporting::by_file;
+    use crate::rule::Severity;
+    use crate::testing;
+
+    #[test]
+    fn test_render_markdown() -> Result<(), Box<dyn std::error::Error>> {
+        let pack =
+            testing::pack_with(&[("unbounded-copy.yml", testing::UNBOUNDED_COPY_DESCRIBED)])?;
+
+        let mut matcher = RuleMatcher::from_directory_with(pack.path(), false)?;
+        let matches = matcher.matches_file("tests/utf8-comments.c")?;
+
+        let options = MarkdownOptions::new().context(0, 0, false);
+        let markdown = render(&matches, &options);
+
+        assert_eq!(
+            markdown.lines().take(4).collect::<Vec<_>>(),
+            [
+                "| Rule | Severity | Count |",
+                "| --- | --- | ---: |",
+                "| `unbounded-copy` | high | 2 |",
+                "| `local-buffer` | low | 1 |",
+            ]
+        );
+        assert!(
+            markdown.contains("### `unbounded-copy` (high)\n\ncall to unbounded copy function\n")
+        );
+        assert!(markdown.contains("#### `tests/utf8-comments.c:6` (strcpy)\n\n```c\n"));
+        assert!(markdown.contains(&format!(
+            "<sub>Defined in `{}`</sub>\n",
+            pack.path().join("unbounded-copy.yml").display()
+        )));
+        assert!(
+            markdown.find("utf8-comments.c:6").unwrap()
+                < markdown.find("utf8-comments.c:12").unwrap()
+        );
+
+        let mut reversed = matcher.matches_file("tests/utf8-comments.c")?;
+        reversed.reverse();
+        assert_eq!(render(&reversed, &options), markdown);
+
+        let options = MarkdownOptions::new()
+            .max_per_rule(1)
+            .collapse(true)
+            .min_severity(Severity::Medium);
+        let markdown = render(&matches, &options);
+
+        assert!(!markdown.contains("local-buffer"));
+        assert!(markdown.contains("<details>\n<summary>2 findings</summary>\n"));
+        assert!(markdown.contains("_and 1 more…_\n\n</details>\n"));
+        assert!(!markdown.contains("utf8-comments.c:12"));
+
+        assert_eq!(render(&[], &options), "No findings.\n");
+
+        // rules loaded from a string have no file to refer to
+        let mut matcher =
+            RuleMatcher::from_str("id: copy\ncheck pattern:\n  pattern: 'strcpy(_, _);'\n")?;
+        let matches = matcher.matches("void f(char *d, char *s) { strcpy(d, s); }")?;
+
+        assert!(!render(&matches, &MarkdownOptions::new()).contains("Defined in"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_render_files() -> Result<(), Box<dyn std::error::Error>> {
+        let pack =
+            testing::pack_with(&[("unbounded-copy.yml", testing::UNBOUNDED_COPY_DESCRIBED)])?;
+
+        let mut matcher = RuleMatcher::from_directory_with(pack.path(), false)?;
+
+        let mut matches = matcher.matches_file("tests/utf8-comments.c")?;
+        matches.extend(matcher.matches("void f(char *s) { strcpy(s, \"x\"); }")?);
+
+        let files = by_file(&matches);
+        let markdown = render_files(&files, &MarkdownOptions::new().context(0, 0, false));
+
+        assert_eq!(
+            markdown.lines().take(4).collect::<Vec<_>>(),
+            [
+                "| File | Max severity | Count |",
+                "| --- | --- | ---: |",
+                "| `<memory>` | high | 1 |",
+                "| `tests/utf8-comments.c` | high | 3 |",
+            ]
+        );
+        assert!(markdown.contains(
+            "### `tests/utf8-comments.c`\n\n#### `line 6` high (unbounded-copy/strcpy)\n"
+        ));
+        assert!(markdown.find("`line 6`").unwrap() < markdown.find("`line 12`").unwrap());
+
+        let options = MarkdownOptions::new().min_severity(Severity::Critical);
+
+        assert_eq!(render_files(&files, &options), "No findings.\n");
+
+        Ok(())
+    }
+}
@@ -0,0 +1,391 @@
+use std::collections::hash_map::Entry;
+use std::fmt::Display;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+use rustc_hash::FxHashMap;
+use thiserror::Error;
+
+use crate::reporting::{ReportKey, ReportSet, RuleMatchReport};
+use crate::rule::Severity;
+
+#[derive(Debug, Error)]
+pub enum MergeError {
+    #[error("cannot read report shard {}: {1}", _0.display())]
+    Read(PathBuf, io::Error),
+    #[error("cannot parse report on line {1} of shard {}: {2}", _0.display())]
+    Parse(PathBuf, usize, serde_json::Error),
+    #[error("cannot write merged reports: {0}")]
+    Write(io::Error),
+}
+
+// The same finding reported with different severities by different shards
+// (e.g., scanned with diverging versions of a rule); the more severe report
+// is kept.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MergeConflict {
+    rule: String,
+    path: Option<PathBuf>,
+    fingerprint: Option<String>,
+    kept: Severity,
+    discarded: Severity,
+}
+
+impl MergeConflict {
+    fn new(report: &RuleMatchReport, a: Severity, b: Severity) -> Self {
+        Self {
+            rule: report.rule().to_owned(),
+            path: report.path().map(Path::to_path_buf),
+            fingerprint: report.fingerprint().map(String::from),
+            kept: a.max(b),
+            discarded: a.min(b),
+        }
+    }
+
+    pub fn rule(&self) -> &str {
+        &self.rule
+    }
+
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    pub fn fingerprint(&self) -> Option<&str> {
+        self.fingerprint.as_deref()
+    }
+
+    pub fn kept(&self) -> Severity {
+        self.kept
+    }
+
+    pub fn discarded(&self) -> Severity {
+        self.discarded
+    }
+}
+
+impl Display for MergeConflict {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "conflicting severities for rule {}", self.rule)?;
+
+        if let Some(ref fingerprint) = self.fingerprint {
+            write!(f, " (fingerprint {fingerprint})")?;
+        }
+        if let Some(ref path) = self.path {
+            write!(f, " in {}", path.display())?;
+        }
+
+        write!(f, ": kept {} over {}", self.kept, self.discarded)
+    }
+}
+
+// The result of `merge`: the deduplicated reports, ordered as by
+// `ReportSet::sort`, and the conflicts between shards.
+#[derive(Debug, Default)]
+pub struct Merged {
+    pub reports: Vec<RuleMatchReport<'static>>,
+    pub conflicts: Vec<MergeConflict>,
+}
+
+// The result of `merge_files`: how many reports were written, and the
+// conflicts between shards.
+#[derive(Debug, Default)]
+pub struct MergeSummary {
+    pub reports: usize,
+    pub conflicts: Vec<MergeConflict>,
+}
+
+// Merges the reports of several shards of a scan into one, keeping a single
+// report of each finding (see `ReportKey`).
+//
+// NOTE: the `duplicates` of the kept report are those of its own shard, since
+// shards may overlap
+pub fn merge(shards: impl IntoIterator<Item = Vec<RuleMatchReport<'static>>>) -> Merged {
+    let reports = shards.into_iter().flatten().collect::<Vec<_>>();
+
+    let mut survivors = FxHashMap::default();
+    let mut keep = vec![true; reports.len()];
+    let mut conflicts = Vec::new();
+
+    for (i, report) in reports.iter().enumerate() {
+        match survivors.entry(ReportKey::new(report)) {
+            Entry::Vacant(entry) => {
+                entry.insert(i);
+            }
+            Entry::Occupied(mut entry) => {
+                let kept = &reports[*entry.get()];
+
+                if kept.severity() != report.severity() {
+                    conflicts.push(MergeConflict::new(kept, kept.severity(), report.severity()));
+                }
+
+                if report.severity() > kept.severity() {
+                    keep[*entry.get()] = false;
+                    entry.insert(i);
+                } else {
+                    keep[i] = false;
+                }
+            }
+        }
+    }
+
+    drop(survivors);
+
+    let mut set = reports
+        .into_iter()
+        .zip(keep)
+        .filter_map(|(report, keep)| keep.then_some(report))
+        .collect::<ReportSet>();
+    set.sort();
+
+    Merged {
+        reports: set.into_reports(),
+        conflicts,
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum OwnedKey {
+    Fingerprint(String),
+    Content(String, String, Option<PathBuf>, String),
+}
+
+impl From<ReportKey<'_>> for OwnedKey {
+    fn from(key: ReportKey<'_>) -> Self {
+        match key {
+            ReportKey::Fingerprint(fingerprint) => Self::Fingerprint(fingerprint.to_owned()),
+            ReportKey::Content {
+                rule,
+                checker,
+                path,
+                text,
+            } => Self::Content(
+                rule.to_owned(),
+                checker.to_owned(),
+                path.map(Path::to_path_buf),
+                text,
+            ),
+        }
+    }
+}
+
+// NOTE: the order of `ReportSet::sort`
+type SortKey = (Option<PathBuf>, usize, usize, String, usize);
+
+// where to find the line of a report
+struct Record {
+    sort: SortKey,
+    severity: Severity,
+    shard: usize,
+    offset: u64,
+    len: usize,
+}
+
+// Like `merge`, but for shards serialized as JSONL (see `jsonl`), writing the
+// merged reports to `writer` as JSONL.
+//
+// NOTE: only the keys of the reports are kept in memory; the selected lines
+// are copied as they are from the shards, which are read twice
+pub fn merge_files(
+    paths: impl IntoIterator<Item = impl AsRef<Path>>,
+    mut writer: impl Write,
+) -> Result<MergeSummary, MergeError> {
+    let paths = paths
+        .into_iter()
+        .map(|path| path.as_ref().to_path_buf())
+        .collect::<Vec<_>>();
+
+    let mut survivors = FxHashMap::<OwnedKey, Record>::default();
+    let mut conflicts = Vec::new();
+
+    for (shard, path) in paths.iter().enumerate() {
+        let file = File::open(path).map_err(|e| MergeError::Read(path.clone(), e))?;
+        let mut reader = BufReader::new(file);
+
+        let mut line = String::new();
+        let (mut offset, mut number) = (0, 0);
+
+        loop {
+            line.clear();
+            let n = reader
+                .read_line(&mut line)
+                .map_err(|e| MergeError::Read(path.clone(), e))?;
+            if n == 0 {
+                break;
+            }
+
+            let start = offset;
+            offset += n as u64;
+            number += 1;
+
+            let trimmed = line.trim_end();
+            if trimmed.trim_start().is_empty() {
+                continue;
+            }
+
+            let report = serde_json::from_str::<RuleMatchReport>(trimmed)
+                .map_err(|e| MergeError::Parse(path.clone(), number, e))?;
+
+            let record = Record {
+                sort: (
+                    report.path().map(Path::to_path_buf),
+                    report.start_offset(),
+                    report.end_offset(),
+                    report.rule().to_owned(),
+                    report.checker_index(),
+                ),
+                severity: report.severity(),
+                shard,
+                offset: start,
+                len: trimmed.len(),
+            };
+
+            match survivors.entry(ReportKey::new(&report).into()) {
+                Entry::Vacant(entry) => {
+                    entry.insert(record);
+                }
+                Entry::Occupied(mut entry) => {
+                    let kept = entry.get().severity;
+
+                    if kept != record.severity {
+                        conflicts.push(MergeConflict::new(&report, kept, record.severity));
+                    }
+                    if record.severity > kept {
+                        entry.insert(record);
+                    }
+                }
+            }
+        }
+    }
+
+    let mut records = survivors.into_values().collect::<Vec<_>>();
+    records.sort_by(|a, b| a.sort.cmp(&b.sort));
+
+    let mut shards = paths
+        .iter()
+        .map(|path| {
+            File::open(path)
+                .map(BufReader::new)
+                .map_err(|e| MergeError::Read(path.clone(), e))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut buf = Vec::new();
+
+    for record in &records {
+        let path = &paths[record.shard];
+        let reader = &mut shards[record.shard];
+
+        buf.resize(record.len, 0);
+        reader
+            .seek(SeekFrom::Start(record.offset))
+            .and_then(|_| reader.read_exact(&mut buf))
+            .map_err(|e| MergeError::Read(path.clone(), e))?;
+
+        writer
+            .write_all(&buf)
+            .and_then(|_| writer.write_all(b"\n"))
+            .map_err(MergeError::Write)?;
+    }
+
+    writer.flush().map_err(MergeError::Write)?;
+
+    Ok(MergeSummary {
+        reports: records.len(),
+        conflicts,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+
+    use super::{merge, merge_files};
+    use crate::matcher::RuleMatcher;
+    use crate::reporting::jsonl::write_matches;
+    use crate::reporting::{load_jsonl, RuleMatchReport};
+    use crate::rule::Severity;
+
+    #[test]
+    fn test_merge() -> Result<(), Box<dyn std::error::Error>> {
+        let rule = r#"
+id: unbounded-copy
+severity: high
+check pattern:
+  regex: func=^strcpy$
+  pattern: |
+    { $func($dst, _); }
+"#;
+
+        let mut matcher = RuleMatcher::from_str(rule)?;
+        let utf8 = matcher.matches_file("tests/utf8-comments.c")?;
+        let ls = matcher.matches_file("tests/ls-main.c")?;
+
+        let reports = |matches: &[_]| {
+            matches
+                .iter()
+                .map(|m| RuleMatchReport::new(m).into_owned())
+                .collect::<Vec<_>>()
+        };
+
+        // the shards overlap on one file, whose findings one of them reports
+        // with a diverging severity
+        let a = reports(&ls);
+        let mut b = reports(&utf8);
+        b.extend(reports(&ls[..1]));
+        b.reverse();
+        let mut c = reports(&utf8);
+        c[0].severity = Severity::Critical;
+
+        let merged = merge([a, b, c]);
+
+        assert_eq!(merged.reports.len(), utf8.len() + ls.len());
+        assert_eq!(merged.conflicts.len(), 1);
+        assert_eq!(merged.conflicts[0].kept(), Severity::Critical);
+        assert_eq!(merged.conflicts[0].discarded(), utf8[0].rule().severity());
+        assert_eq!(merged.conflicts[0].rule(), utf8[0].rule().id());
+        assert!(merged
+            .reports
+            .iter()
+            .any(|r| r.severity() == Severity::Critical));
+        assert!(merged
+            .reports
+            .windows(2)
+            .all(|w| (w[0].path(), w[0].start_offset()) <= (w[1].path(), w[1].start_offset())));
+
+        // the merge of serialized shards agrees
+        let dir = std::env::temp_dir();
+        let paths = ["a", "b"].map(|name| {
+            dir.join(format!(
+                "weggli-ruleset-merge-{}-{name}.jsonl",
+                std::process::id()
+            ))
+        });
+
+        let shard = write_matches(write_matches(Vec::new(), &utf8)?, &ls[..1])?;
+        fs::write(&paths[0], write_matches(Vec::new(), &ls)?)?;
+        fs::write(&paths[1], shard)?;
+
+        let mut output = Vec::new();
+        let summary = merge_files(&paths, &mut output);
+
+        for path in &paths {
+            fs::remove_file(path)?;
+        }
+
+        let summary = summary?;
+        let loaded = load_jsonl(output.as_slice())?;
+
+        assert_eq!(summary.reports, utf8.len() + ls.len());
+        assert!(summary.conflicts.is_empty());
+        assert_eq!(loaded.len(), summary.reports);
+
+        let expected = merge([reports(&ls), reports(&utf8)]);
+
+        for (loaded, expected) in loaded.iter().zip(&expected.reports) {
+            assert_eq!(loaded.fingerprint(), expected.fingerprint());
+        }
+
+        Ok(())
+    }
+}
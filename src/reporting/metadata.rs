@@ -0,0 +1,208 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+use crate::rule::RuleSet;
+
+// What produced a report, from which rules, and when: the version of the tool,
+// the rule packs (the directories the rules were loaded from, or `default` for
+// rules loaded from strings) with how many rules each, and when the scan of
+// `target` started and finished.
+//
+// NOTE: timestamps are RFC 3339 strings in UTC; all fields default to empty,
+// so that reports written before metadata was recorded can still be read
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct ReportMetadata {
+    tool: String,
+    version: String,
+    rules: usize,
+    checkers: usize,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    rule_packs: BTreeMap<String, usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    target: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    started: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    finished: Option<String>,
+}
+
+impl ReportMetadata {
+    pub fn new() -> Self {
+        Self {
+            tool: String::from(env!("CARGO_PKG_NAME")),
+            version: String::from(env!("CARGO_PKG_VERSION")),
+            ..Self::default()
+        }
+    }
+
+    pub fn from_ruleset(rules: &RuleSet) -> Self {
+        let mut metadata = Self::new();
+
+        for (key, rule) in rules.iter() {
+            let pack = match key {
+                "default" => String::from(key),
+                key => Path::new(key)
+                    .parent()
+                    .map(|parent| parent.display().to_string())
+                    .unwrap_or_default(),
+            };
+
+            *metadata.rule_packs.entry(pack).or_default() += 1;
+            metadata.rules += 1;
+            metadata.checkers += rule.checks().len();
+        }
+
+        metadata
+    }
+
+    // e.g., for a wrapper reporting under its own name
+    pub fn with_tool(mut self, name: impl Into<String>, version: impl Into<String>) -> Self {
+        self.tool = name.into();
+        self.version = version.into();
+        self
+    }
+
+    // a description of what was scanned, e.g., a path or a revision
+    pub fn with_target(mut self, target: impl Into<String>) -> Self {
+        self.target = Some(target.into());
+        self
+    }
+
+    pub fn started_at(mut self, time: SystemTime) -> Self {
+        self.started = Some(rfc3339(time));
+        self
+    }
+
+    pub fn finished_at(mut self, time: SystemTime) -> Self {
+        self.finished = Some(rfc3339(time));
+        self
+    }
+
+    pub fn tool(&self) -> &str {
+        &self.tool
+    }
+
+    pub fn version(&self) -> &str {
+        &self.version
+    }
+
+    pub fn rules(&self) -> usize {
+        self.rules
+    }
+
+    pub fn checkers(&self) -> usize {
+        self.checkers
+    }
+
+    pub fn rule_packs(&self) -> &BTreeMap<String, usize> {
+        &self.rule_packs
+    }
+
+    pub fn target(&self) -> Option<&str> {
+        self.target.as_deref()
+    }
+
+    pub fn started(&self) -> Option<&str> {
+        self.started.as_deref()
+    }
+
+    pub fn finished(&self) -> Option<&str> {
+        self.finished.as_deref()
+    }
+}
+
+// formats `time` as an RFC 3339 timestamp in UTC, to the second (times before
+// the epoch are clamped to it)
+pub(crate) fn rfc3339(time: SystemTime) -> String {
+    let secs = time
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs());
+    let (days, secs) = (secs / 86_400, secs % 86_400);
+
+    // see http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+    let z = days + 719_468;
+    let era = z / 146_097;
+    let doe = z % 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + u64::from(month <= 2);
+
+    format!(
+        "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}Z",
+        secs / 3600,
+        secs / 60 % 60,
+        secs % 60
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::{Duration, UNIX_EPOCH};
+
+    use super::{rfc3339, ReportMetadata};
+    use crate::rule::RuleSet;
+    use crate::testing::{self, TempDir};
+
+    #[test]
+    fn test_report_metadata() -> Result<(), Box<dyn std::error::Error>> {
+        assert_eq!(rfc3339(UNIX_EPOCH), "1970-01-01T00:00:00Z");
+        assert_eq!(
+            rfc3339(UNIX_EPOCH + Duration::from_secs(951_782_400)),
+            "2000-02-29T00:00:00Z"
+        );
+        assert_eq!(
+            rfc3339(UNIX_EPOCH + Duration::from_secs(1_700_000_000)),
+            "2023-11-14T22:13:20Z"
+        );
+        assert_eq!(
+            rfc3339(UNIX_EPOCH - Duration::from_secs(1)),
+            "1970-01-01T00:00:00Z"
+        );
+
+        // the rules of the pack in a directory of their own, which names it
+        let pack = TempDir::with_files(&[
+            ("pack/local-buffer.yml", testing::LOCAL_BUFFER),
+            ("pack/unbounded-copy.yml", testing::UNBOUNDED_COPY_GETS),
+        ])?;
+
+        let rules = RuleSet::from_directory(pack.path(), false)?;
+
+        let metadata = ReportMetadata::from_ruleset(&rules)
+            .with_target("tests")
+            .started_at(UNIX_EPOCH + Duration::from_secs(1_700_000_000))
+            .finished_at(UNIX_EPOCH + Duration::from_secs(1_700_000_060));
+
+        assert_eq!(metadata.tool(), env!("CARGO_PKG_NAME"));
+        assert_eq!(metadata.version(), env!("CARGO_PKG_VERSION"));
+        assert_eq!(metadata.rules(), rules.len());
+        assert_eq!(metadata.checkers(), 3);
+        assert_eq!(metadata.rule_packs()["pack"], rules.len());
+        assert_eq!(metadata.finished(), Some("2023-11-14T22:14:20Z"));
+
+        let json = serde_json::to_string(&metadata)?;
+
+        assert_eq!(serde_json::from_str::<ReportMetadata>(&json)?, metadata);
+
+        let strings = ReportMetadata::from_ruleset(&RuleSet::from_str(
+            "id: x\ncheck-patterns:\n- name: x\n  pattern: '{ gets(_); }'\n",
+        )?);
+
+        assert_eq!(strings.rule_packs()["default"], 1);
+
+        // metadata of other versions may lack fields
+        let partial = serde_json::from_str::<ReportMetadata>(r#"{"tool": "other"}"#)?;
+
+        assert_eq!(partial.tool(), "other");
+        assert_eq!(partial.version(), "");
+        assert_eq!(partial.started(), None);
+
+        Ok(())
+    }
+}
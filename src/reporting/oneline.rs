@@ -0,0 +1,238 @@
+use std::io::{self, Write};
+use std::path::Path;
+
+use crate::location;
+use crate::matcher::{location_order, RuleMatch};
+use crate::reporting::RuleMatchReport;
+use crate::rule::Severity;
+
+// the default maximum width (in characters) of the text of a finding
+pub const ONELINE_WIDTH: usize = 120;
+
+// Findings are displayed one per line, like the diagnostics of compilers, so
+// that they can be read by `grep`, `sort`, and the default `errorformat` of
+// Vim and `compilation-mode` of Emacs:
+//
+//   tests/utf8-comments.c:6:5: high unbounded-copy/default: strcpy(dst, src); ...
+//
+// The text is the first line of the match, with its whitespace collapsed and
+// truncated (with an ellipsis) to a maximum width.
+//
+// NOTE: paths are written as they are, except for line breaks and tabs, which
+// are escaped as `\n`, `\r`, and `\t`; paths may contain spaces and colons,
+// as the location is delimited by the first `:<line>:<column>: `, unless they
+// contain such a sequence themselves (matches without a path are attributed
+// to `<source>`)
+impl RuleMatch {
+    pub fn display_oneline(&self) -> String {
+        self.display_oneline_with(Some(ONELINE_WIDTH))
+    }
+
+    // see `display_oneline`; without a width, the text is not truncated
+    pub fn display_oneline_with(&self, width: Option<usize>) -> String {
+        let start = self.source_span().start;
+        let line = location::line_span(self.source_ref(), start..start, 0, 0);
+
+        Oneline {
+            path: self.origin(),
+            start: (self.start_line(), self.start_column()),
+            severity: self.effective_severity(),
+            rule: self.rule().id(),
+            checker: self.checker().name(),
+            text: &self.source_ref()[line],
+        }
+        .render(width)
+    }
+}
+
+impl RuleMatchReport<'_> {
+    // see `RuleMatch::display_oneline`; reports that omit their source (and
+    // snippet) are displayed without text
+    pub fn display_oneline(&self) -> String {
+        self.display_oneline_with(Some(ONELINE_WIDTH))
+    }
+
+    pub fn display_oneline_with(&self, width: Option<usize>) -> String {
+        let text = if self.source().is_empty() {
+            self.snippet()
+                .and_then(|snippet| snippet.lines().find(|(line, _)| *line == self.start_line()))
+                .map_or("", |(_, text)| text)
+        } else {
+            let start = self.source_span().start;
+            &self.source()[location::line_span(self.source(), start..start, 0, 0)]
+        };
+
+        Oneline {
+            path: self.path(),
+            start: (self.start_line(), self.start_column()),
+            severity: self.severity(),
+            rule: self.rule(),
+            checker: self.checker(),
+            text,
+        }
+        .render(width)
+    }
+}
+
+// Writes a line per match (see `RuleMatch::display_oneline`), ordered by path
+// and position.
+pub fn write_oneline(writer: impl Write, matches: &[RuleMatch]) -> io::Result<()> {
+    write_oneline_with(writer, matches, Some(ONELINE_WIDTH))
+}
+
+pub fn write_oneline_with(
+    mut writer: impl Write,
+    matches: &[RuleMatch],
+    width: Option<usize>,
+) -> io::Result<()> {
+    let mut sorted = matches.iter().collect::<Vec<_>>();
+    sorted.sort_by(|a, b| location_order(a, b));
+
+    for m in sorted {
+        writeln!(writer, "{}", m.display_oneline_with(width))?;
+    }
+
+    writer.flush()
+}
+
+struct Oneline<'a> {
+    path: Option<&'a Path>,
+    start: (usize, usize),
+    severity: Severity,
+    rule: &'a str,
+    checker: &'a str,
+    text: &'a str,
+}
+
+impl Oneline<'_> {
+    fn render(&self, width: Option<usize>) -> String {
+        let path = match self.path {
+            Some(path) => escape_path(&path.to_string_lossy()),
+            None => String::from("<source>"),
+        };
+
+        let mut output = format!(
+            "{path}:{}:{}: {} {}/{}",
+            self.start.0, self.start.1, self.severity, self.rule, self.checker
+        );
+
+        let text = collapse(self.text, width);
+        if !text.is_empty() {
+            output.push_str(": ");
+            output.push_str(&text);
+        }
+
+        output
+    }
+}
+
+fn escape_path(path: &str) -> String {
+    path.replace('\n', "\\n")
+        .replace('\r', "\\r")
+        .replace('\t', "\\t")
+}
+
+// collapses runs of whitespace to a single space, and truncates the result to
+// `width` characters (including the ellipsis)
+fn collapse(text: &str, width: Option<usize>) -> String {
+    let collapsed = text.split_whitespace().collect::<Vec<_>>().join(" ");
+
+    match width {
+        Some(width) if collapsed.chars().count() > width => {
+            let mut truncated = collapsed
+                .chars()
+                .take(width.saturating_sub(1))
+                .collect::<String>();
+            truncated.push('…');
+            truncated
+        }
+        _ => collapsed,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::Path;
+
+    use super::{collapse, write_oneline, write_oneline_with, Oneline};
+    use crate::matcher::RuleMatcher;
+    use crate::reporting::{RuleMatchReport, SourceMode};
+    use crate::rule::Severity;
+    use crate::testing;
+
+    #[test]
+    fn test_oneline() -> Result<(), Box<dyn std::error::Error>> {
+        let pack = testing::pack()?;
+
+        let mut matcher = RuleMatcher::from_directory_with(pack.path(), false)?;
+        let mut matches = matcher.matches_file("tests/utf8-comments.c")?;
+        matches.reverse();
+
+        let mut output = Vec::new();
+        write_oneline(&mut output, &matches)?;
+        let output = String::from_utf8(output)?;
+        let lines = output.lines().collect::<Vec<_>>();
+
+        assert_eq!(lines.len(), 3);
+        assert!(lines[0].starts_with("tests/utf8-comments.c:6:5: high unbounded-copy/"));
+        assert!(lines[0].ends_with(": strcpy(dst, src); /* 注意: 境界なし 🚨 */"));
+        assert!(lines[1].starts_with("tests/utf8-comments.c:10:"));
+        assert!(lines[1].contains(" low local-buffer/"));
+        assert!(lines[2].ends_with(": /* 🔥 */ strcpy(名前, src);"));
+
+        // the text is truncated to the width, in characters
+        let mut output = Vec::new();
+        write_oneline_with(&mut output, &matches, Some(10))?;
+        let output = String::from_utf8(output)?;
+
+        assert!(output.lines().next().unwrap().ends_with(": strcpy(ds…"));
+
+        // reports display the same, from their source or snippet
+        for m in &matches {
+            let line = m.display_oneline();
+
+            assert_eq!(RuleMatchReport::new(m).display_oneline(), line);
+            assert_eq!(
+                RuleMatchReport::new(m)
+                    .with_source_mode(SourceMode::Snippet {
+                        before: 0,
+                        after: 0
+                    })
+                    .display_oneline(),
+                line
+            );
+            assert_eq!(
+                RuleMatchReport::new(m)
+                    .with_source_mode(SourceMode::Omit)
+                    .display_oneline(),
+                line
+            );
+        }
+
+        // paths with spaces and colons are kept, line breaks escaped
+        let oneline = Oneline {
+            path: Some(Path::new("a b:c\nd.c")),
+            start: (2, 5),
+            severity: Severity::None,
+            rule: "x",
+            checker: "y",
+            text: "\tgets(s);",
+        };
+
+        assert_eq!(oneline.render(None), "a b:c\\nd.c:2:5: n/a x/y: gets(s);");
+        assert_eq!(
+            Oneline {
+                text: "",
+                ..oneline
+            }
+            .render(None),
+            "a b:c\\nd.c:2:5: n/a x/y"
+        );
+
+        assert_eq!(collapse("  a \t b\n c  ", None), "a b c");
+        assert_eq!(collapse("abcdef", Some(4)), "abc…");
+        assert_eq!(collapse("abcd", Some(4)), "abcd");
+
+        Ok(())
+    }
+}
@@ -0,0 +1,803 @@
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+
+use rustc_hash::FxHashMap;
+use serde::{Deserialize, Serialize};
+
+use crate::matcher::{RuleMatch, SuppressionReason};
+use crate::reporting::{path_uri, ReportMetadata, SeverityMap};
+use crate::rule::Severity;
+
+const SARIF_SCHEMA: &str = "https://json.schemastore.org/sarif-2.1.0.json";
+const SARIF_VERSION: &str = "2.1.0";
+
+const FINGERPRINT_KEY: &str = "weggliRuleset/v1";
+
+// the `level` of SARIF results and of the default configuration of rules
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Level {
+    None,
+    Note,
+    Warning,
+    Error,
+}
+
+impl Default for SeverityMap<Level> {
+    fn default() -> Self {
+        Self::new(
+            Level::None,
+            Level::Note,
+            Level::Warning,
+            Level::Error,
+            Level::Error,
+        )
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SarifOptions {
+    tool_name: String,
+    tool_version: String,
+    base: Option<PathBuf>,
+    placeholder_uri: String,
+    metadata: Option<ReportMetadata>,
+    levels: SeverityMap<Level>,
+}
+
+impl Default for SarifOptions {
+    fn default() -> Self {
+        Self {
+            tool_name: String::from(env!("CARGO_PKG_NAME")),
+            tool_version: String::from(env!("CARGO_PKG_VERSION")),
+            base: None,
+            placeholder_uri: String::from("stdin"),
+            metadata: None,
+            levels: SeverityMap::default(),
+        }
+    }
+}
+
+impl SarifOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn tool_name(mut self, name: impl Into<String>) -> Self {
+        self.tool_name = name.into();
+        self
+    }
+
+    pub fn tool_version(mut self, version: impl Into<String>) -> Self {
+        self.tool_version = version.into();
+        self
+    }
+
+    // origins below `base` are reported relative to it
+    pub fn base(mut self, base: impl Into<PathBuf>) -> Self {
+        self.base = Some(base.into());
+        self
+    }
+
+    // the URI reported for matches without an origin
+    pub fn placeholder_uri(mut self, uri: impl Into<String>) -> Self {
+        self.placeholder_uri = uri.into();
+        self
+    }
+
+    // recorded in the run's properties, with the start and end of the scan
+    // (if known) as its invocation
+    pub fn metadata(mut self, metadata: ReportMetadata) -> Self {
+        self.metadata = Some(metadata);
+        self
+    }
+
+    // the levels of rules and results, by the severity of the rule
+    pub fn with_severity_map(mut self, levels: SeverityMap<Level>) -> Self {
+        self.levels = levels;
+        self
+    }
+}
+
+// A SARIF 2.1.0 log with a single run; rules are emitted as reporting
+// descriptors (ordered by id), and matches as results (in the order given).
+//
+// NOTE: columns are counted in Unicode code points, as for `RuleMatch`, and
+// the run's `columnKind` states so; end columns are exclusive.
+#[derive(Debug, Clone, Serialize)]
+pub struct SarifReport {
+    #[serde(rename = "$schema")]
+    schema: &'static str,
+    version: &'static str,
+    runs: [Run; 1],
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Run {
+    tool: Tool,
+    column_kind: &'static str,
+    results: Vec<SarifResult>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    invocations: Vec<Invocation>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    properties: Option<RunProperties>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Invocation {
+    execution_successful: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    start_time_utc: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    end_time_utc: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct RunProperties {
+    metadata: ReportMetadata,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct Tool {
+    driver: Driver,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct Driver {
+    name: String,
+    version: String,
+    rules: Vec<ReportingDescriptor>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ReportingDescriptor {
+    id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    short_description: Option<Message>,
+    default_configuration: Configuration,
+    properties: Properties,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct Configuration {
+    level: Level,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct Properties {
+    tags: Vec<String>,
+    severity: Severity,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct Message {
+    text: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SarifResult {
+    rule_id: String,
+    rule_index: usize,
+    level: Level,
+    message: Message,
+    locations: [Location; 1],
+    partial_fingerprints: FxHashMap<&'static str, String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    suppressions: Vec<Suppression>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct Suppression {
+    kind: &'static str,
+    status: &'static str,
+    justification: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Location {
+    physical_location: PhysicalLocation,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct PhysicalLocation {
+    artifact_location: ArtifactLocation,
+    region: Region,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct ArtifactLocation {
+    uri: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Region {
+    start_line: usize,
+    start_column: usize,
+    end_line: usize,
+    end_column: usize,
+    snippet: Message,
+}
+
+impl SarifReport {
+    pub fn from_matches(matches: &[RuleMatch], options: &SarifOptions) -> Self {
+        let mut rules = matches.iter().map(|m| m.rule()).collect::<Vec<_>>();
+        rules.sort_by(|a, b| a.id().cmp(b.id()));
+        rules.dedup_by(|a, b| a.id() == b.id());
+
+        let index = rules
+            .iter()
+            .enumerate()
+            .map(|(i, rule)| (rule.id(), i))
+            .collect::<FxHashMap<_, _>>();
+
+        let descriptors = rules
+            .iter()
+            .map(|rule| {
+                let mut tags = rule.tags().iter().cloned().collect::<Vec<_>>();
+                tags.sort();
+
+                ReportingDescriptor {
+                    id: rule.id().to_owned(),
+                    short_description: rule.description().map(|text| Message {
+                        text: text.to_owned(),
+                    }),
+                    default_configuration: Configuration {
+                        level: options.levels[rule.severity()],
+                    },
+                    properties: Properties {
+                        tags,
+                        severity: rule.severity(),
+                    },
+                }
+            })
+            .collect();
+
+        let results = matches
+            .iter()
+            .map(|m| {
+                let rule = m.rule();
+                let uri = m
+                    .origin()
+                    .map(|path| uri(path, options.base.as_deref()))
+                    .unwrap_or_else(|| options.placeholder_uri.clone());
+
+                let text = match rule.description() {
+                    Some(description) => {
+                        format!("{description} ({}/{})", rule.id(), m.checker().name())
+                    }
+                    None => format!("{}/{}", rule.id(), m.checker().name()),
+                };
+
+                SarifResult {
+                    rule_id: rule.id().to_owned(),
+                    rule_index: index[rule.id()],
+                    level: options.levels[m.effective_severity()],
+                    message: Message { text },
+                    locations: [Location {
+                        physical_location: PhysicalLocation {
+                            artifact_location: ArtifactLocation { uri },
+                            region: Region {
+                                start_line: m.start_line(),
+                                start_column: m.start_column(),
+                                end_line: m.end_line(),
+                                end_column: m.end_column(),
+                                snippet: Message {
+                                    text: m.matched_text().to_owned(),
+                                },
+                            },
+                        },
+                    }],
+                    partial_fingerprints: FxHashMap::from_iter([(
+                        FINGERPRINT_KEY,
+                        m.fingerprint().to_owned(),
+                    )]),
+                    suppressions: m.suppression().map(suppression).into_iter().collect(),
+                }
+            })
+            .collect();
+
+        let invocations = options
+            .metadata
+            .iter()
+            .filter(|metadata| metadata.started().is_some() || metadata.finished().is_some())
+            .map(|metadata| Invocation {
+                execution_successful: true,
+                start_time_utc: metadata.started().map(String::from),
+                end_time_utc: metadata.finished().map(String::from),
+            })
+            .collect();
+
+        Self {
+            schema: SARIF_SCHEMA,
+            version: SARIF_VERSION,
+            runs: [Run {
+                tool: Tool {
+                    driver: Driver {
+                        name: options.tool_name.clone(),
+                        version: options.tool_version.clone(),
+                        rules: descriptors,
+                    },
+                },
+                column_kind: "unicodeCodePoints",
+                results,
+                invocations,
+                properties: options
+                    .metadata
+                    .clone()
+                    .map(|metadata| RunProperties { metadata }),
+            }],
+        }
+    }
+
+    pub fn to_writer(&self, writer: impl Write) -> Result<(), serde_json::Error> {
+        serde_json::to_writer_pretty(writer, self)
+    }
+}
+
+// NOTE: suppressions loaded from earlier results keep their justification,
+// so that it does not grow with each round trip
+fn suppression(reason: &SuppressionReason) -> Suppression {
+    Suppression {
+        kind: match reason {
+            SuppressionReason::Comment { .. } => "inSource",
+            SuppressionReason::Baseline { .. }
+            | SuppressionReason::Config { .. }
+            | SuppressionReason::Sarif { .. } => "external",
+        },
+        status: "accepted",
+        justification: match reason {
+            SuppressionReason::Sarif { justification, .. } => justification.clone(),
+            _ => reason.to_string(),
+        },
+    }
+}
+
+// The findings suppressed in earlier SARIF results (e.g., those dismissed in
+// GitHub code scanning), by the fingerprint this exporter writes, with the
+// justification of their suppression.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SuppressionSet {
+    justifications: FxHashMap<String, String>,
+    ignored: usize,
+}
+
+impl SuppressionSet {
+    pub fn len(&self) -> usize {
+        self.justifications.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.justifications.is_empty()
+    }
+
+    // the suppressed results without a fingerprint of ours (e.g., of other
+    // tools, or with only the fingerprints code scanning computes)
+    pub fn ignored(&self) -> usize {
+        self.ignored
+    }
+
+    pub fn contains(&self, fingerprint: &str) -> bool {
+        self.justifications.contains_key(fingerprint)
+    }
+
+    // e.g., for `ReportSet::suppress`
+    pub fn reason(&self, fingerprint: &str) -> Option<SuppressionReason> {
+        self.justifications
+            .get(fingerprint)
+            .map(|justification| SuppressionReason::Sarif {
+                fingerprint: fingerprint.to_owned(),
+                justification: justification.clone(),
+            })
+    }
+
+    // the matches that were not suppressed
+    pub fn filter<'m>(&self, matches: &'m [RuleMatch]) -> Vec<&'m RuleMatch> {
+        matches
+            .iter()
+            .filter(|m| !self.contains(m.fingerprint()))
+            .collect()
+    }
+
+    // the matches, with those that were suppressed marked as such (unless
+    // they already are)
+    pub fn flag(&self, matches: impl IntoIterator<Item = RuleMatch>) -> Vec<RuleMatch> {
+        matches
+            .into_iter()
+            .map(|m| match self.reason(m.fingerprint()) {
+                Some(reason) if !m.is_suppressed() => m.with_suppression(reason),
+                _ => m,
+            })
+            .collect()
+    }
+}
+
+#[derive(Deserialize)]
+struct SarifLog {
+    #[serde(default)]
+    runs: Vec<RunT>,
+}
+
+#[derive(Deserialize)]
+struct RunT {
+    #[serde(default)]
+    results: Vec<ResultT>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ResultT {
+    #[serde(default)]
+    partial_fingerprints: FxHashMap<String, String>,
+    #[serde(default)]
+    suppressions: Vec<SuppressionT>,
+}
+
+#[derive(Deserialize)]
+struct SuppressionT {
+    #[serde(default)]
+    status: Option<String>,
+    #[serde(default)]
+    justification: Option<String>,
+}
+
+// Reads the suppressions of a SARIF log (e.g., one downloaded from code
+// scanning). Per SARIF, a result is suppressed if one of its suppressions is
+// accepted (the default status); those `underReview` or `rejected` are not.
+pub fn load_suppressions(reader: impl Read) -> Result<SuppressionSet, serde_json::Error> {
+    let log = serde_json::from_reader::<_, SarifLog>(reader)?;
+    let mut set = SuppressionSet::default();
+
+    for result in log.runs.into_iter().flat_map(|run| run.results) {
+        let Some(suppression) = result.suppressions.into_iter().find(|s| {
+            s.status
+                .as_deref()
+                .is_none_or(|status| status == "accepted")
+        }) else {
+            continue;
+        };
+
+        match result.partial_fingerprints.get(FINGERPRINT_KEY) {
+            Some(fingerprint) => {
+                set.justifications.insert(
+                    fingerprint.clone(),
+                    suppression.justification.unwrap_or_default(),
+                );
+            }
+            None => set.ignored += 1,
+        }
+    }
+
+    Ok(set)
+}
+
+// relative paths become relative references; absolute paths become `file`
+// URIs
+fn uri(path: &Path, base: Option<&Path>) -> String {
+    let path = base
+        .and_then(|base| path.strip_prefix(base).ok())
+        .unwrap_or(path);
+
+    path_uri(path)
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    use regex::Regex;
+    use serde_json::Value;
+
+    use super::{load_suppressions, uri, Level, SarifOptions, SarifReport};
+    use crate::matcher::{RuleMatcher, SuppressionReason};
+    use crate::reporting::{ReportMetadata, SeverityMap};
+    use crate::rule::Severity;
+    use crate::testing;
+
+    // a validator for the subset of JSON Schema (draft-07) used by the SARIF
+    // schema; `format` is not checked
+    fn validate(root: &Value, schema: &Value, value: &Value, at: &str, errors: &mut Vec<String>) {
+        if let Some(reference) = schema["$ref"].as_str() {
+            let name = reference.trim_start_matches("#/definitions/");
+            return validate(root, &root["definitions"][name], value, at, errors);
+        }
+
+        if let Some(ty) = schema["type"].as_str() {
+            let valid = match ty {
+                "object" => value.is_object(),
+                "array" => value.is_array(),
+                "string" => value.is_string(),
+                "integer" => value.is_i64() || value.is_u64(),
+                "number" => value.is_number(),
+                "boolean" => value.is_boolean(),
+                "null" => value.is_null(),
+                _ => true,
+            };
+            if !valid {
+                errors.push(format!("{at}: expected {ty}"));
+                return;
+            }
+        }
+
+        if let Some(variants) = schema["enum"].as_array() {
+            if !variants.contains(value) {
+                errors.push(format!("{at}: {value} not in enum"));
+            }
+        }
+
+        if let Some(pattern) = schema["pattern"].as_str() {
+            if let Some(s) = value.as_str() {
+                if !Regex::new(pattern).unwrap().is_match(s) {
+                    errors.push(format!("{at}: {s:?} does not match {pattern}"));
+                }
+            }
+        }
+
+        if let (Some(minimum), Some(n)) = (schema["minimum"].as_f64(), value.as_f64()) {
+            if n < minimum {
+                errors.push(format!("{at}: {n} < {minimum}"));
+            }
+        }
+
+        for key in ["anyOf", "oneOf"] {
+            if let Some(alternatives) = schema[key].as_array() {
+                let valid = alternatives
+                    .iter()
+                    .filter(|alternative| {
+                        let mut errors = Vec::new();
+                        validate(root, alternative, value, at, &mut errors);
+                        errors.is_empty()
+                    })
+                    .count();
+                if valid == 0 || (key == "oneOf" && valid > 1) {
+                    errors.push(format!("{at}: {key} not satisfied"));
+                }
+            }
+        }
+
+        if let Some(items) = value.as_array() {
+            if let Some(min) = schema["minItems"].as_u64() {
+                if (items.len() as u64) < min {
+                    errors.push(format!("{at}: fewer than {min} items"));
+                }
+            }
+            for (i, item) in items.iter().enumerate() {
+                validate(root, &schema["items"], item, &format!("{at}[{i}]"), errors);
+            }
+        }
+
+        if let Some(object) = value.as_object() {
+            for required in schema["required"].as_array().into_iter().flatten() {
+                let required = required.as_str().unwrap();
+                if !object.contains_key(required) {
+                    errors.push(format!("{at}: missing {required}"));
+                }
+            }
+
+            for (key, value) in object {
+                let at = format!("{at}.{key}");
+                match schema["properties"].get(key) {
+                    Some(property) => validate(root, property, value, &at, errors),
+                    None => match schema["additionalProperties"] {
+                        Value::Bool(false) => errors.push(format!("{at}: not allowed")),
+                        Value::Object(_) => {
+                            validate(root, &schema["additionalProperties"], value, &at, errors)
+                        }
+                        _ => (),
+                    },
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_sarif_report() -> Result<(), Box<dyn std::error::Error>> {
+        let pack = testing::pack_with(&[(
+            "unbounded-copy.yml",
+            r#"
+id: unbounded-copy
+severity: high
+tags: [CWE-120]
+check-pattern:
+  pattern: '{ strcpy($dst, _); }'
+"#,
+        )])?;
+
+        let mut matcher = RuleMatcher::from_directory_with(pack.path(), false)?;
+
+        let mut matches = matcher.matches_file("tests/utf8-comments.c")?;
+        matches.extend(
+            matcher
+                .matches("void f(char *s) { strcpy(s, \"x\"); }")?
+                .into_iter()
+                .map(|m| {
+                    m.with_suppression(SuppressionReason::Config {
+                        note: String::from("constant source"),
+                    })
+                }),
+        );
+
+        let options = SarifOptions::new()
+            .tool_version("1.0.0")
+            .placeholder_uri("memory")
+            .metadata(
+                ReportMetadata::from_ruleset(matcher.rules())
+                    .started_at(UNIX_EPOCH + Duration::from_secs(1_700_000_000)),
+            );
+
+        let mut output = Vec::new();
+        SarifReport::from_matches(&matches, &options).to_writer(&mut output)?;
+
+        let report = serde_json::from_slice::<Value>(&output)?;
+        let schema =
+            serde_json::from_str::<Value>(&fs::read_to_string("tests/sarif-schema-2.1.0.json")?)?;
+
+        let mut errors = Vec::new();
+        validate(&schema, &schema, &report, "$", &mut errors);
+
+        assert!(errors.is_empty(), "{errors:#?}");
+
+        let run = &report["runs"][0];
+        let rules = run["tool"]["driver"]["rules"].as_array().unwrap();
+        let results = run["results"].as_array().unwrap();
+
+        assert_eq!(
+            run["invocations"][0]["startTimeUtc"],
+            "2023-11-14T22:13:20Z"
+        );
+        assert!(run["invocations"][0].get("endTimeUtc").is_none());
+        assert_eq!(run["properties"]["metadata"]["rules"], 2);
+
+        assert_eq!(rules.len(), 2);
+        assert_eq!(rules[0]["id"], "local-buffer");
+        assert_eq!(rules[0]["defaultConfiguration"]["level"], "note");
+        assert_eq!(rules[1]["id"], "unbounded-copy");
+        assert_eq!(rules[1]["defaultConfiguration"]["level"], "error");
+        assert_eq!(rules[1]["properties"]["tags"][0], "CWE-120");
+
+        assert_eq!(results.len(), 4);
+
+        let strcpy = results
+            .iter()
+            .find(|r| r["ruleId"] == "unbounded-copy")
+            .unwrap();
+        let location = &strcpy["locations"][0]["physicalLocation"];
+
+        assert_eq!(strcpy["ruleIndex"], 1);
+        assert_eq!(location["artifactLocation"]["uri"], "tests/utf8-comments.c");
+        assert_eq!(location["region"]["startLine"], 6);
+        assert_eq!(location["region"]["startColumn"], 5);
+        assert!(location["region"]["snippet"]["text"]
+            .as_str()
+            .unwrap()
+            .starts_with("strcpy"));
+        assert!(strcpy["partialFingerprints"]["weggliRuleset/v1"].is_string());
+
+        assert_eq!(
+            results[3]["locations"][0]["physicalLocation"]["artifactLocation"]["uri"],
+            "memory"
+        );
+
+        // suppressed findings are reported as such
+        assert!(results[..3].iter().all(|r| r.get("suppressions").is_none()));
+        assert_eq!(results[3]["suppressions"][0]["kind"], "external");
+        assert_eq!(results[3]["suppressions"][0]["status"], "accepted");
+        assert_eq!(
+            results[3]["suppressions"][0]["justification"],
+            "suppressed by the configuration: constant source"
+        );
+
+        // levels can be remapped, e.g., for a stricter policy
+        let levels = SeverityMap::default().set(Severity::Low, Level::Error);
+        let report = SarifReport::from_matches(&matches, &options.with_severity_map(levels));
+        let report = serde_json::to_value(&report)?;
+
+        let run = &report["runs"][0];
+        assert_eq!(
+            run["tool"]["driver"]["rules"][0]["defaultConfiguration"]["level"],
+            "error"
+        );
+        assert!(run["results"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .all(|r| r["level"] == "error"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_sarif_uri() {
+        assert_eq!(uri("src/a b.c".as_ref(), None), "src/a%20b.c");
+        assert_eq!(uri("/tmp/src/a.c".as_ref(), None), "file:///tmp/src/a.c");
+        assert_eq!(
+            uri("/tmp/src/a.c".as_ref(), Some("/tmp".as_ref())),
+            "src/a.c"
+        );
+    }
+
+    #[test]
+    fn test_load_suppressions() -> Result<(), Box<dyn std::error::Error>> {
+        let rule = r#"
+id: unbounded-copy
+check-patterns:
+- name: strcpy
+  pattern: '{ strcpy($dst, _); }'
+- name: buffer
+  pattern: '{ char $buf[_]; }'
+"#;
+
+        let mut matcher = RuleMatcher::from_str(rule)?;
+        let matches = matcher.matches_file("tests/utf8-comments.c")?;
+        assert_eq!(matches.len(), 3);
+
+        let options = SarifOptions::new();
+        let mut report = serde_json::to_value(SarifReport::from_matches(&matches, &options))?;
+
+        // as code scanning would write them: a dismissal, one of a result
+        // without our fingerprint, and a rejected suppression
+        let results = report["runs"][0]["results"].as_array_mut().unwrap();
+        let dismissed = results[0]["partialFingerprints"]["weggliRuleset/v1"].clone();
+        results[0]["suppressions"] = serde_json::json!([
+            {"kind": "external", "status": "accepted", "justification": "false positive"}
+        ]);
+        results[1]["partialFingerprints"] = serde_json::json!({"primaryLocationLineHash": "1:2"});
+        results[1]["suppressions"] = serde_json::json!([{"kind": "external"}]);
+        results[2]["suppressions"] = serde_json::json!([
+            {"kind": "external", "status": "rejected"}
+        ]);
+
+        let suppressions = load_suppressions(serde_json::to_vec(&report)?.as_slice())?;
+        assert_eq!(suppressions.len(), 1);
+        assert_eq!(suppressions.ignored(), 1);
+        assert!(suppressions.contains(dismissed.as_str().unwrap()));
+
+        // later scans compute the same fingerprints
+        let matches = matcher.matches_file("tests/utf8-comments.c")?;
+        let active = suppressions.filter(&matches);
+        assert_eq!(active.len(), 2);
+        assert!(active.iter().all(|m| m.fingerprint() != dismissed));
+
+        let flagged = suppressions.flag(matches);
+        let reasons = flagged
+            .iter()
+            .filter_map(|m| m.suppression())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            reasons,
+            [&SuppressionReason::Sarif {
+                fingerprint: dismissed.as_str().unwrap().to_owned(),
+                justification: String::from("false positive"),
+            }]
+        );
+
+        // ...and export them with their justification, which closes the loop
+        let report = serde_json::to_value(SarifReport::from_matches(&flagged, &options))?;
+        let suppressed = report["runs"][0]["results"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .filter(|r| r.get("suppressions").is_some())
+            .collect::<Vec<_>>();
+        assert_eq!(suppressed.len(), 1);
+        assert_eq!(
+            suppressed[0]["suppressions"][0]["justification"],
+            "false positive"
+        );
+        let reloaded = load_suppressions(serde_json::to_vec(&report)?.as_slice())?;
+        assert_eq!(reloaded.len(), 1);
+        assert_eq!(
+            reloaded.reason(dismissed.as_str().unwrap()),
+            Some(reasons[0].clone())
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,259 @@
+use std::borrow::Cow;
+use std::fmt;
+
+use serde::de::{IgnoredAny, MapAccess, SeqAccess, Visitor};
+use serde::{Deserialize, Deserializer};
+
+use crate::location;
+use crate::matcher;
+use crate::reporting::{self, ReportLocation, ReportMetadata, RuleMatchReport, SNIPPET_CONTEXT};
+
+// The version of the serialized shape of reports, recorded by the containers
+// of reports (e.g., `ReportSet`) and bumped whenever the shape changes:
+//
+// 1. bare arrays of reports, with positions only as offsets into `source`
+// 2. locations, fingerprints, duplicates, source modes, and sets of reports
+//    with a `summary` of their `findings`
+// 3. snippets, captures, metadata, and `schema_version` itself
+// 4. suppressions of findings, and the `suppressed` counts of summaries
+// 5. the `metadata` of the rules of findings (in verbose reports), their
+//    binary `address` (if annotated), and the escalated `severity` of
+//    grouped findings
+//
+// NOTE: documents without a version predate it, and are read as version 1;
+// older reports are upgraded by `migrate`
+pub const SCHEMA_VERSION: u32 = 5;
+
+// Upgrades a report read from an older version of the schema to the current
+// one, filling in what can be recomputed from its embedded source: its
+// location (without a path), captures, and snippet.
+//
+// NOTE: fingerprints cannot be recomputed without the syntax tree, and so
+// remain absent
+pub fn migrate(mut report: RuleMatchReport<'_>) -> RuleMatchReport<'_> {
+    // positions are only meaningful if the match fits the source
+    let span = matcher::match_span(&report.match_result);
+    let fits = !report.match_result.captures.is_empty()
+        && span.end <= report.source.len()
+        && location::clamp_range(&report.source, span.clone()) == span;
+
+    if !fits {
+        return report;
+    }
+
+    if report.location.is_none() {
+        report.location = Some(ReportLocation {
+            path: None,
+            start_offset: report.start_offset(),
+            end_offset: report.end_offset(),
+            start_line: report.start_line(),
+            start_column: report.start_column(),
+            end_line: report.end_line(),
+            end_column: report.end_column(),
+        });
+    }
+
+    if report.captures.is_empty() {
+        let captures = reporting::captures(&report.source, &report.match_result)
+            .into_iter()
+            .map(|(var, value)| (var.to_owned(), value.to_owned()))
+            .collect();
+        report.captures = Cow::Owned(captures);
+    }
+
+    if report.snippet.is_none() {
+        report = report.with_snippet(SNIPPET_CONTEXT, SNIPPET_CONTEXT);
+    }
+
+    report
+}
+
+// A serialized collection of reports of any version: a bare array (version
+// 1), or an object with its `findings` (and possibly its version and metadata).
+pub(crate) struct Document {
+    pub(crate) schema_version: u32,
+    pub(crate) metadata: Option<ReportMetadata>,
+    pub(crate) findings: Vec<RuleMatchReport<'static>>,
+}
+
+impl<'de> Deserialize<'de> for Document {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct DocumentVisitor;
+
+        impl<'de> Visitor<'de> for DocumentVisitor {
+            type Value = Document;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("an array of reports, or an object with `findings`")
+            }
+
+            fn visit_seq<A>(self, mut access: A) -> Result<Self::Value, A::Error>
+            where
+                A: SeqAccess<'de>,
+            {
+                let mut findings = Vec::with_capacity(access.size_hint().unwrap_or_default());
+
+                while let Some(report) = access.next_element()? {
+                    findings.push(report);
+                }
+
+                Ok(Document {
+                    schema_version: 1,
+                    metadata: None,
+                    findings,
+                })
+            }
+
+            fn visit_map<A>(self, mut access: A) -> Result<Self::Value, A::Error>
+            where
+                A: MapAccess<'de>,
+            {
+                let mut document = Document {
+                    schema_version: 1,
+                    metadata: None,
+                    findings: Vec::new(),
+                };
+                let mut findings = false;
+
+                // the summary, in particular, is recomputed from the findings
+                while let Some(key) = access.next_key::<Cow<str>>()? {
+                    match key.as_ref() {
+                        "schema_version" => document.schema_version = access.next_value()?,
+                        "metadata" => document.metadata = access.next_value()?,
+                        "findings" => {
+                            document.findings = access.next_value()?;
+                            findings = true;
+                        }
+                        _ => {
+                            access.next_value::<IgnoredAny>()?;
+                        }
+                    }
+                }
+
+                if !findings {
+                    return Err(serde::de::Error::missing_field("findings"));
+                }
+
+                Ok(document)
+            }
+        }
+
+        deserializer.deserialize_any(DocumentVisitor)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs::File;
+
+    use serde_json::{json, Value};
+
+    use super::{migrate, SCHEMA_VERSION};
+    use crate::matcher::RuleMatcher;
+    use crate::reporting::{load_json, load_set, ReportError, ReportSet, RuleMatchReport};
+    use crate::testing;
+
+    #[test]
+    fn test_schema_versions() -> Result<(), Box<dyn std::error::Error>> {
+        // the rules the fixtures were written with
+        let pack = testing::pack_with(&testing::DESCRIBED_PACK)?;
+
+        let mut matcher = RuleMatcher::from_directory_with(pack.path(), false)?;
+        let matches = matcher.matches_file("tests/utf8-comments.c")?;
+
+        let current = |m| RuleMatchReport::new(m);
+
+        for version in 1..=SCHEMA_VERSION {
+            let path = format!("tests/reports/v{version}.json");
+            let reports = load_json(File::open(&path)?)?;
+
+            assert_eq!(reports.len(), matches.len(), "{path}");
+
+            for (report, m) in reports.iter().zip(&matches) {
+                let expected = current(m);
+
+                assert_eq!(report.rule(), expected.rule(), "{path}");
+                assert_eq!(report.severity(), expected.severity(), "{path}");
+                assert_eq!(report.matched_text(), expected.matched_text(), "{path}");
+                assert_eq!(report.start_line(), expected.start_line(), "{path}");
+                assert_eq!(report.end_column(), expected.end_column(), "{path}");
+                assert_eq!(report.captures(), expected.captures(), "{path}");
+                assert_eq!(
+                    report.snippet().map(|s| s.start_line()),
+                    expected.snippet().map(|s| s.start_line()),
+                    "{path}"
+                );
+                assert!(report.location().is_some(), "{path}");
+            }
+        }
+
+        // only reports of version 1 lack fingerprints, which cannot be
+        // recomputed
+        let v1 = load_json(File::open("tests/reports/v1.json")?)?;
+
+        assert!(v1.iter().all(|r| r.fingerprint().is_none()));
+
+        let v2 = load_json(File::open("tests/reports/v2.json")?)?;
+
+        assert!(v2.iter().all(|r| r.fingerprint().is_some()));
+
+        let v3 = load_set(File::open("tests/reports/v3.json")?)?;
+
+        assert!(v3.metadata().is_some());
+
+        let v4 = load_set(File::open("tests/reports/v4.json")?)?;
+
+        assert!(v4.reports()[0].is_suppressed());
+        assert_eq!(v4.active().count(), 2);
+
+        let v5 = load_set(File::open("tests/reports/v5.json")?)?;
+
+        assert!(v5.reports()[0].metadata().is_empty());
+        assert_eq!(v5.reports()[0].address(), None);
+        assert_eq!(v5.reports()[1].address(), Some(0x401234));
+        assert_eq!(
+            v5.reports()[1].metadata_get("owner"),
+            Some(&Value::from("appsec"))
+        );
+        assert_eq!(
+            v5.reports()[2].metadata_get("references"),
+            Some(&json!({ "cwe": [120, 676] }))
+        );
+
+        // the current version is written, and read back as is
+        let set = ReportSet::from_matches(&matches);
+        let value = serde_json::to_value(&set)?;
+
+        assert_eq!(value["schema_version"], SCHEMA_VERSION);
+
+        let json = serde_json::to_vec(&set)?;
+        let loaded = load_json(json.as_slice())?;
+
+        for (loaded, report) in loaded.iter().zip(set.iter()) {
+            assert_eq!(loaded.snippet(), report.snippet());
+            assert_eq!(loaded.location(), report.location());
+        }
+
+        // migrating a current report changes nothing
+        let report = RuleMatchReport::new(&matches[0]);
+
+        assert_eq!(
+            serde_json::to_value(migrate(RuleMatchReport::new(&matches[0])))?,
+            serde_json::to_value(report)?
+        );
+
+        assert!(matches!(
+            load_json(&b"{\"schema_version\": 99, \"findings\": []}"[..]),
+            Err(ReportError::UnsupportedVersion(99))
+        ));
+        assert!(matches!(
+            load_json(&b"{\"summary\": {}}"[..]),
+            Err(ReportError::Parse(_))
+        ));
+
+        Ok(())
+    }
+}
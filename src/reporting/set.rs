@@ -0,0 +1,418 @@
+use std::collections::BTreeMap;
+use std::io::Write;
+
+use serde::ser::SerializeStruct;
+use serde::{Serialize, Serializer};
+
+use crate::matcher::{RuleMatch, SuppressionMode, SuppressionReason};
+use crate::reporting::{
+    self, ReportMetadata, RuleMatchReport, SeverityCounts, SourceMode, SCHEMA_VERSION,
+};
+use crate::rule::Severity;
+
+// A collection of reports, serialized as an object with the version of its
+// schema (see `SCHEMA_VERSION`), the `metadata` of the scan (if any), a
+// `summary` of the findings, and the `findings` themselves (see `load_set`).
+#[derive(Debug, Default)]
+pub struct ReportSet<'a> {
+    reports: Vec<RuleMatchReport<'a>>,
+    metadata: Option<ReportMetadata>,
+}
+
+#[derive(Serialize)]
+struct Summary<'s> {
+    total: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max_severity: Option<Severity>,
+    severities: SeverityCounts,
+    rules: BTreeMap<&'s str, usize>,
+    #[serde(skip_serializing_if = "SeverityCounts::is_empty")]
+    suppressed: SeverityCounts,
+}
+
+impl<'a> ReportSet<'a> {
+    pub fn new(reports: Vec<RuleMatchReport<'a>>) -> Self {
+        Self {
+            reports,
+            metadata: None,
+        }
+    }
+
+    pub fn with_metadata(mut self, metadata: ReportMetadata) -> Self {
+        self.metadata = Some(metadata);
+        self
+    }
+
+    pub fn metadata(&self) -> Option<&ReportMetadata> {
+        self.metadata.as_ref()
+    }
+
+    pub fn from_matches(matches: &'a [RuleMatch]) -> Self {
+        Self::new(matches.iter().map(RuleMatchReport::new).collect())
+    }
+
+    pub fn reports(&self) -> &[RuleMatchReport<'a>] {
+        &self.reports
+    }
+
+    pub fn into_reports(self) -> Vec<RuleMatchReport<'a>> {
+        self.reports
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &RuleMatchReport<'a>> {
+        self.reports.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.reports.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.reports.is_empty()
+    }
+
+    pub fn push(&mut self, report: RuleMatchReport<'a>) {
+        self.reports.push(report);
+    }
+
+    // NOTE: counts only active findings; see `suppressed_counts`
+    pub fn counts_by_severity(&self) -> SeverityCounts {
+        SeverityCounts::from_reports(&self.reports)
+    }
+
+    // the number of suppressed findings retained in the set
+    pub fn suppressed_counts(&self) -> SeverityCounts {
+        self.suppressed().map(|report| report.severity()).collect()
+    }
+
+    pub fn active(&self) -> impl Iterator<Item = &RuleMatchReport<'a>> {
+        self.reports.iter().filter(|report| !report.is_suppressed())
+    }
+
+    pub fn suppressed(&self) -> impl Iterator<Item = &RuleMatchReport<'a>> {
+        self.reports.iter().filter(|report| report.is_suppressed())
+    }
+
+    pub fn counts_by_rule(&self) -> BTreeMap<&str, usize> {
+        let mut counts = BTreeMap::new();
+        for report in self.active() {
+            *counts.entry(report.rule()).or_default() += 1;
+        }
+        counts
+    }
+
+    pub fn max_severity(&self) -> Option<Severity> {
+        self.counts_by_severity().max_severity()
+    }
+
+    // orders reports by path (reports without one first), position, rule,
+    // and checker
+    pub fn sort(&mut self) {
+        self.reports.sort_by(|a, b| {
+            a.path()
+                .cmp(&b.path())
+                .then_with(|| a.start_offset().cmp(&b.start_offset()))
+                .then_with(|| a.end_offset().cmp(&b.end_offset()))
+                .then_with(|| a.rule().cmp(b.rule()))
+                .then_with(|| a.checker_index().cmp(&b.checker_index()))
+        });
+    }
+
+    // orders reports by severity (highest first), then as `sort`
+    pub fn sort_by_severity(&mut self) {
+        self.sort();
+        self.reports
+            .sort_by_key(|report| std::cmp::Reverse(report.severity()));
+    }
+
+    pub fn retain(&mut self, f: impl FnMut(&RuleMatchReport<'a>) -> bool) {
+        self.reports.retain(f);
+    }
+
+    pub fn filter_min_severity(mut self, severity: Severity) -> Self {
+        self.retain(|report| report.severity() >= severity);
+        self
+    }
+
+    pub fn filter_tag(mut self, tag: &str) -> Self {
+        self.retain(|report| report.tags().contains(tag));
+        self
+    }
+
+    // suppresses the reports for which `reason` gives a reason (e.g., those
+    // in a baseline): they are dropped, or retained and marked as suppressed,
+    // according to `mode` (`Off` leaves the set as it is)
+    pub fn suppress(
+        mut self,
+        mode: SuppressionMode,
+        mut reason: impl FnMut(&RuleMatchReport<'a>) -> Option<SuppressionReason>,
+    ) -> Self {
+        match mode {
+            SuppressionMode::Off => {}
+            SuppressionMode::Drop => {
+                self.retain(|report| report.is_suppressed() || reason(report).is_none())
+            }
+            SuppressionMode::Retain => {
+                self.reports = self
+                    .reports
+                    .into_iter()
+                    .map(|report| match reason(&report) {
+                        Some(reason) if !report.is_suppressed() => report.with_suppression(reason),
+                        _ => report,
+                    })
+                    .collect();
+            }
+        }
+        self
+    }
+
+    // see `reporting::dedup`
+    pub fn dedup(self) -> Self {
+        Self {
+            reports: reporting::dedup(self.reports),
+            metadata: self.metadata,
+        }
+    }
+
+    pub fn dedup_counting(self) -> Self {
+        Self {
+            reports: reporting::dedup_counting(self.reports),
+            metadata: self.metadata,
+        }
+    }
+
+    pub fn with_source_mode(self, mode: SourceMode) -> Self {
+        Self {
+            reports: self
+                .reports
+                .into_iter()
+                .map(|report| report.with_source_mode(mode))
+                .collect(),
+            metadata: self.metadata,
+        }
+    }
+
+    pub fn to_json(&self, writer: impl Write) -> Result<(), serde_json::Error> {
+        serde_json::to_writer_pretty(writer, self)
+    }
+
+    pub fn to_yaml(&self, writer: impl Write) -> Result<(), serde_yaml::Error> {
+        serde_yaml::to_writer(writer, self)
+    }
+
+    pub fn into_owned(self) -> ReportSet<'static> {
+        ReportSet {
+            reports: self
+                .reports
+                .into_iter()
+                .map(RuleMatchReport::into_owned)
+                .collect(),
+            metadata: self.metadata,
+        }
+    }
+}
+
+impl<'a> FromIterator<RuleMatchReport<'a>> for ReportSet<'a> {
+    fn from_iter<I: IntoIterator<Item = RuleMatchReport<'a>>>(iter: I) -> Self {
+        Self::new(iter.into_iter().collect())
+    }
+}
+
+impl<'a> IntoIterator for ReportSet<'a> {
+    type Item = RuleMatchReport<'a>;
+    type IntoIter = std::vec::IntoIter<RuleMatchReport<'a>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.reports.into_iter()
+    }
+}
+
+impl Serialize for ReportSet<'_> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let severities = self.counts_by_severity();
+        let summary = Summary {
+            total: severities.total(),
+            max_severity: severities.max_severity(),
+            severities,
+            rules: self.counts_by_rule(),
+            suppressed: self.suppressed_counts(),
+        };
+
+        let mut s = serializer.serialize_struct("ReportSet", 4)?;
+
+        s.serialize_field("schema_version", &SCHEMA_VERSION)?;
+        match self.metadata {
+            Some(ref metadata) => s.serialize_field("metadata", metadata)?,
+            None => s.skip_field("metadata")?,
+        }
+        s.serialize_field("summary", &summary)?;
+        s.serialize_field("findings", &self.reports)?;
+
+        s.end()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::Value;
+
+    use super::ReportSet;
+    use crate::matcher::{RuleMatcher, SuppressionMode, SuppressionReason};
+    use crate::reporting::{load_set, ReportMetadata, RuleMatchReport, SourceMode};
+    use crate::rule::Severity;
+    use crate::testing;
+
+    #[test]
+    fn test_report_set() -> Result<(), Box<dyn std::error::Error>> {
+        let pack = testing::pack_with(&[(
+            "unbounded-copy.yml",
+            r#"
+id: unbounded-copy
+severity: high
+tags: [CWE-120, CWE-676]
+check-pattern:
+  pattern: '{ strcpy($dst, _); }'
+"#,
+        )])?;
+
+        let mut matcher = RuleMatcher::from_directory_with(pack.path(), false)?;
+        let matches = matcher.matches_file("tests/utf8-comments.c")?;
+
+        let mut set = ReportSet::from_matches(&matches);
+
+        assert_eq!(set.len(), 3);
+        assert_eq!(set.max_severity(), Some(Severity::High));
+        assert_eq!(set.counts_by_severity()[Severity::High], 2);
+        assert_eq!(set.counts_by_rule()["local-buffer"], 1);
+
+        set.sort();
+
+        assert!(set
+            .reports()
+            .windows(2)
+            .all(|w| w[0].start_offset() <= w[1].start_offset()));
+
+        set.sort_by_severity();
+
+        assert_eq!(set.reports()[2].severity(), Severity::Low);
+
+        let mut output = Vec::new();
+        set.to_json(&mut output)?;
+
+        let value = serde_json::from_slice::<Value>(&output)?;
+
+        assert_eq!(value["summary"]["total"], 3);
+        assert_eq!(value["summary"]["max_severity"], "high");
+        assert_eq!(value["summary"]["severities"]["low"], 1);
+        assert_eq!(value["summary"]["rules"]["unbounded-copy"], 2);
+        assert_eq!(value["findings"].as_array().map(Vec::len), Some(3));
+        assert!(value.get("metadata").is_none());
+
+        let mut output = Vec::new();
+        set.to_yaml(&mut output)?;
+
+        let value = serde_yaml::from_slice::<serde_yaml::Value>(&output)?;
+
+        assert_eq!(value["summary"]["total"].as_u64(), Some(3));
+
+        let omitted = ReportSet::from_matches(&matches)
+            .with_metadata(ReportMetadata::from_ruleset(matcher.rules()).with_target("tests"))
+            .with_source_mode(SourceMode::Omit);
+
+        assert_eq!(omitted.len(), 3);
+        assert!(omitted.iter().all(|r| r.source().is_empty()));
+
+        let value = serde_json::to_value(omitted.dedup())?;
+
+        assert_eq!(value["metadata"]["target"], "tests");
+        assert_eq!(value["metadata"]["rules"], 2);
+
+        let owned: ReportSet<'static> = ReportSet::from_matches(&matches)
+            .filter_min_severity(Severity::Medium)
+            .into_owned();
+
+        drop(matches);
+
+        assert_eq!(owned.len(), 2);
+        assert!(owned.iter().all(|r| r.rule() == "unbounded-copy"));
+
+        let tagged = owned.filter_tag("CWE-676");
+
+        assert_eq!(tagged.len(), 2);
+        assert!(tagged.filter_tag("CWE-787").is_empty());
+
+        let empty = ReportSet::from_iter(Vec::<RuleMatchReport>::new());
+
+        assert_eq!(empty.max_severity(), None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_suppressed_reports() -> Result<(), Box<dyn std::error::Error>> {
+        let rule = r#"
+id: unbounded-copy
+severity: high
+check pattern:
+  pattern: '{ strcpy($dst, _); }'
+"#;
+
+        let mut matcher = RuleMatcher::from_str(rule)?;
+        matcher.set_suppression_mode(SuppressionMode::Retain);
+        let matches = matcher.matches_file("tests/suppressed-copy.c")?;
+
+        let set = ReportSet::from_matches(&matches);
+
+        assert_eq!(set.len(), 3);
+        assert_eq!(set.active().count(), 1);
+        assert_eq!(set.counts_by_severity()[Severity::High], 1);
+        assert_eq!(set.suppressed_counts()[Severity::High], 2);
+
+        let value = serde_json::to_value(&set)?;
+
+        assert_eq!(value["summary"]["total"], 1);
+        assert_eq!(value["summary"]["severities"]["high"], 1);
+        assert_eq!(value["summary"]["suppressed"]["high"], 2);
+        assert_eq!(value["findings"][0]["suppression"]["kind"], "comment");
+        assert_eq!(value["findings"][0]["suppression"]["line"], 2);
+        assert!(value["findings"][2].get("suppression").is_none());
+
+        // the suppression survives a round trip
+        let loaded = load_set(serde_json::to_vec(&set)?.as_slice())?;
+
+        assert_eq!(
+            loaded.iter().map(|r| r.suppression()).collect::<Vec<_>>(),
+            set.iter().map(|r| r.suppression()).collect::<Vec<_>>()
+        );
+
+        // other sources of suppressions apply to sets of reports
+        let accepted = |report: &RuleMatchReport| {
+            (report.start_line() == 11).then(|| SuppressionReason::Config {
+                note: String::from("accepted"),
+            })
+        };
+
+        let retained =
+            ReportSet::from_matches(&matches).suppress(SuppressionMode::Retain, accepted);
+
+        assert_eq!(retained.len(), 3);
+        assert_eq!(retained.active().count(), 0);
+        assert!(serde_json::to_value(&retained)?["summary"]
+            .get("max_severity")
+            .is_none());
+
+        let dropped = ReportSet::from_matches(&matches).suppress(SuppressionMode::Drop, accepted);
+
+        assert_eq!(dropped.len(), 2);
+        assert_eq!(dropped.suppressed().count(), 2);
+
+        let unchanged = ReportSet::from_matches(&matches).suppress(SuppressionMode::Off, accepted);
+
+        assert_eq!(unchanged.active().count(), 1);
+
+        Ok(())
+    }
+}
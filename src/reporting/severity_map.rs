@@ -0,0 +1,70 @@
+use std::ops::Index;
+
+use crate::rule::Severity;
+
+// A value for each severity, e.g., the levels of an export format or the
+// scale of a downstream system; maps are total, as the constructors require a
+// value for every severity.
+//
+// NOTE: the defaults of the export formats are `SeverityMap<sarif::Level>`
+// and `SeverityMap<sonar::SonarSeverity>`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SeverityMap<T> {
+    // indexed by `Severity as usize`
+    values: [T; 5],
+}
+
+impl<T: Clone> SeverityMap<T> {
+    pub fn new(none: T, low: T, medium: T, high: T, critical: T) -> Self {
+        Self {
+            values: [none, low, medium, high, critical],
+        }
+    }
+
+    pub fn from_fn(mut f: impl FnMut(Severity) -> T) -> Self {
+        Self::new(
+            f(Severity::None),
+            f(Severity::Low),
+            f(Severity::Medium),
+            f(Severity::High),
+            f(Severity::Critical),
+        )
+    }
+
+    // e.g., `SeverityMap::<sarif::Level>::default().set(Severity::Medium,
+    // Level::Error)` for a stricter policy
+    pub fn set(mut self, severity: Severity, value: T) -> Self {
+        self.values[severity as usize] = value;
+        self
+    }
+
+    pub fn get(&self, severity: Severity) -> &T {
+        &self.values[severity as usize]
+    }
+}
+
+impl<T> Index<Severity> for SeverityMap<T> {
+    type Output = T;
+
+    fn index(&self, severity: Severity) -> &T {
+        &self.values[severity as usize]
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::SeverityMap;
+    use crate::rule::Severity;
+
+    #[test]
+    fn test_severity_map() {
+        // e.g., a 1-5 scale
+        let scale = SeverityMap::from_fn(|severity| severity as u8 + 1);
+        assert_eq!(scale[Severity::None], 1);
+        assert_eq!(*scale.get(Severity::Critical), 5);
+
+        let scale = scale.set(Severity::None, 0);
+        assert_eq!(scale[Severity::None], 0);
+        assert_eq!(scale, SeverityMap::new(0, 2, 3, 4, 5));
+    }
+}
@@ -0,0 +1,308 @@
+use std::io::Write;
+
+use rustc_hash::FxHashMap;
+use serde::Serialize;
+
+use crate::matcher::RuleMatch;
+use crate::reporting::{SeverityMap, MEMORY_PATH};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum IssueType {
+    Bug,
+    #[default]
+    Vulnerability,
+    CodeSmell,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum SonarSeverity {
+    Info,
+    Minor,
+    Major,
+    Critical,
+    Blocker,
+}
+
+impl Default for SeverityMap<SonarSeverity> {
+    fn default() -> Self {
+        Self::new(
+            SonarSeverity::Info,
+            SonarSeverity::Minor,
+            SonarSeverity::Major,
+            SonarSeverity::Critical,
+            SonarSeverity::Blocker,
+        )
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+struct TagOverride {
+    engine_id: Option<String>,
+    issue_type: Option<IssueType>,
+}
+
+#[derive(Debug, Clone)]
+pub struct SonarOptions {
+    engine_id: String,
+    issue_type: IssueType,
+    tags: FxHashMap<String, TagOverride>,
+    severities: SeverityMap<SonarSeverity>,
+}
+
+impl Default for SonarOptions {
+    fn default() -> Self {
+        Self {
+            engine_id: String::from(env!("CARGO_PKG_NAME")),
+            issue_type: IssueType::default(),
+            tags: FxHashMap::default(),
+            severities: SeverityMap::default(),
+        }
+    }
+}
+
+impl SonarOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn engine_id(mut self, engine_id: impl Into<String>) -> Self {
+        self.engine_id = engine_id.into();
+        self
+    }
+
+    pub fn issue_type(mut self, issue_type: IssueType) -> Self {
+        self.issue_type = issue_type;
+        self
+    }
+
+    pub fn with_severity_map(mut self, severities: SeverityMap<SonarSeverity>) -> Self {
+        self.severities = severities;
+        self
+    }
+
+    // overrides the engine id and/or issue type of matches whose rule has
+    // `tag`; when several tags apply, the first (in sorted order) wins
+    pub fn override_tag(
+        mut self,
+        tag: impl Into<String>,
+        engine_id: Option<&str>,
+        issue_type: Option<IssueType>,
+    ) -> Self {
+        self.tags.insert(
+            tag.into(),
+            TagOverride {
+                engine_id: engine_id.map(String::from),
+                issue_type,
+            },
+        );
+        self
+    }
+}
+
+#[derive(Serialize)]
+struct Report<'a> {
+    issues: Vec<Issue<'a>>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Issue<'a> {
+    engine_id: &'a str,
+    rule_id: &'a str,
+    severity: SonarSeverity,
+    #[serde(rename = "type")]
+    issue_type: IssueType,
+    primary_location: Location,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Location {
+    message: String,
+    file_path: String,
+    text_range: TextRange,
+}
+
+// NOTE: lines are 1-based, but columns are 0-based
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct TextRange {
+    start_line: usize,
+    end_line: usize,
+    start_column: usize,
+    end_column: usize,
+}
+
+// Writes matches in SonarQube's generic issue import format; matches without
+// an origin are reported against `MEMORY_PATH`.
+pub fn write(
+    writer: impl Write,
+    matches: &[RuleMatch],
+    options: &SonarOptions,
+) -> Result<(), serde_json::Error> {
+    let issues = matches
+        .iter()
+        .map(|m| {
+            let rule = m.rule();
+
+            let mut tags = rule.tags().iter().collect::<Vec<_>>();
+            tags.sort();
+
+            let engine_id = tags
+                .iter()
+                .find_map(|tag| options.tags.get(*tag)?.engine_id.as_deref())
+                .unwrap_or(&options.engine_id);
+            let issue_type = tags
+                .iter()
+                .find_map(|tag| options.tags.get(*tag)?.issue_type)
+                .unwrap_or(options.issue_type);
+
+            let message = match rule.description() {
+                Some(description) => format!("{} ({})", description.trim(), m.checker().name()),
+                None => format!("{} ({})", rule.id(), m.checker().name()),
+            };
+
+            Issue {
+                engine_id,
+                rule_id: rule.id(),
+                severity: options.severities[m.effective_severity()],
+                issue_type,
+                primary_location: Location {
+                    message,
+                    file_path: m
+                        .origin()
+                        .map(|path| path.to_string_lossy().replace('\\', "/"))
+                        .unwrap_or_else(|| String::from(MEMORY_PATH)),
+                    text_range: TextRange {
+                        start_line: m.start_line(),
+                        end_line: m.end_line(),
+                        start_column: m.start_column() - 1,
+                        end_column: m.end_column() - 1,
+                    },
+                },
+            }
+        })
+        .collect();
+
+    serde_json::to_writer_pretty(writer, &Report { issues })
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::Value;
+
+    use super::{write, IssueType, SonarOptions, SonarSeverity};
+    use crate::matcher::RuleMatcher;
+    use crate::reporting::SeverityMap;
+    use crate::rule::Severity;
+    use crate::testing;
+
+    #[test]
+    fn test_sonar() -> Result<(), Box<dyn std::error::Error>> {
+        let pack = testing::pack_with(&[(
+            "unbounded-copy.yml",
+            r#"
+id: unbounded-copy
+description: call to unbounded copy function
+severity: high
+tags: [CWE-676]
+check-patterns:
+- name: strcpy
+  regex: func=^strcpy$
+  pattern: '{ $func($dst, _); }'
+"#,
+        )])?;
+
+        let mut matcher = RuleMatcher::from_directory_with(pack.path(), false)?;
+        let matches = matcher.matches_file("tests/utf8-comments.c")?;
+
+        let options = SonarOptions::new().override_tag(
+            "CWE-676",
+            Some("hotspots"),
+            Some(IssueType::CodeSmell),
+        );
+
+        let mut output = Vec::new();
+        write(&mut output, &matches, &options)?;
+
+        let report = serde_json::from_slice::<Value>(&output)?;
+        let issues = report["issues"].as_array().unwrap();
+
+        assert_eq!(issues.len(), matches.len());
+
+        let copy = issues
+            .iter()
+            .find(|i| i["ruleId"] == "unbounded-copy")
+            .unwrap();
+
+        assert_eq!(copy["engineId"], "hotspots");
+        assert_eq!(copy["type"], "CODE_SMELL");
+        assert_eq!(copy["severity"], "CRITICAL");
+        assert_eq!(
+            copy["primaryLocation"]["message"],
+            "call to unbounded copy function (strcpy)"
+        );
+        assert_eq!(copy["primaryLocation"]["filePath"], "tests/utf8-comments.c");
+
+        let range = &copy["primaryLocation"]["textRange"];
+
+        assert_eq!(range["startLine"], 6);
+        assert_eq!(range["startColumn"], 4);
+        assert_eq!(range["endColumn"], 14);
+
+        let buffer = issues
+            .iter()
+            .find(|i| i["ruleId"] == "local-buffer")
+            .unwrap();
+
+        assert_eq!(buffer["engineId"], "weggli-ruleset");
+        assert_eq!(buffer["type"], "VULNERABILITY");
+        assert_eq!(buffer["severity"], "MINOR");
+
+        let severities = SeverityMap::default().set(Severity::Low, SonarSeverity::Major);
+        let mut output = Vec::new();
+        write(
+            &mut output,
+            &matches,
+            &SonarOptions::new().with_severity_map(severities),
+        )?;
+
+        let report = serde_json::from_slice::<Value>(&output)?;
+        let severities = report["issues"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|i| {
+                (
+                    i["ruleId"].as_str().unwrap(),
+                    i["severity"].as_str().unwrap(),
+                )
+            })
+            .collect::<Vec<_>>();
+
+        assert!(severities.contains(&("local-buffer", "MAJOR")));
+        assert!(severities.contains(&("unbounded-copy", "CRITICAL")));
+
+        let rule = r#"
+id: multi-line
+check pattern:
+  pattern: '{ char $buf[_]; strcpy($buf, _); }'
+"#;
+
+        let mut matcher = RuleMatcher::from_str(rule)?;
+        let matches = matcher.matches_file("tests/utf8-comments.c")?;
+
+        let mut output = Vec::new();
+        write(&mut output, &matches, &SonarOptions::new())?;
+
+        let report = serde_json::from_slice::<Value>(&output)?;
+        let range = &report["issues"][0]["primaryLocation"]["textRange"];
+
+        assert_eq!(range["startLine"], 10);
+        assert_eq!(range["endLine"], 12);
+
+        Ok(())
+    }
+}
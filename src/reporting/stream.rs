@@ -0,0 +1,294 @@
+use std::io::{self, Write};
+
+#[cfg(feature = "gzip")]
+use flate2::read::GzDecoder;
+#[cfg(feature = "gzip")]
+use flate2::write::GzEncoder;
+use thiserror::Error;
+
+use crate::matcher::RuleMatch;
+#[cfg(feature = "gzip")]
+use crate::reporting::jsonl::{self, JsonlError};
+use crate::reporting::RuleMatchReport;
+
+#[derive(Debug, Error)]
+pub enum StreamError {
+    #[error("cannot write reports: {0}")]
+    Io(#[from] io::Error),
+    #[error("cannot serialize report: {0}")]
+    Serialize(serde_json::Error),
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum StreamFormat {
+    // a single JSON array
+    #[default]
+    Json,
+    // one JSON object per line (see `jsonl`)
+    Jsonl,
+}
+
+// What was written by a `ReportWriter`: `bytes` is the size of the serialized
+// reports, `written` what reached the underlying writer (which differs only
+// if compressed).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct StreamSummary {
+    pub reports: usize,
+    pub bytes: u64,
+    pub written: u64,
+}
+
+struct Counter<W> {
+    inner: W,
+    count: u64,
+}
+
+impl<W: Write> Write for Counter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.inner.write(buf)?;
+        self.count += n as u64;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+enum Sink<W: Write> {
+    Plain(Counter<W>),
+    #[cfg(feature = "gzip")]
+    Gzip(GzEncoder<Counter<W>>),
+}
+
+impl<W: Write> Sink<W> {
+    fn finish(self) -> io::Result<Counter<W>> {
+        match self {
+            Self::Plain(mut writer) => {
+                writer.flush()?;
+                Ok(writer)
+            }
+            #[cfg(feature = "gzip")]
+            Self::Gzip(encoder) => {
+                let mut writer = encoder.finish()?;
+                writer.flush()?;
+                Ok(writer)
+            }
+        }
+    }
+}
+
+impl<W: Write> Write for Sink<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Self::Plain(writer) => writer.write(buf),
+            #[cfg(feature = "gzip")]
+            Self::Gzip(encoder) => encoder.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Self::Plain(writer) => writer.flush(),
+            #[cfg(feature = "gzip")]
+            Self::Gzip(encoder) => encoder.flush(),
+        }
+    }
+}
+
+// Writes reports one at a time, without buffering them, as a JSON array or as
+// JSONL, optionally compressed (with the `gzip` feature).
+//
+// NOTE: the output is only complete (e.g., the array closed) after `finish`
+#[must_use = "the output is incomplete until `finish` is called"]
+pub struct ReportWriter<W: Write> {
+    sink: Counter<Sink<W>>,
+    format: StreamFormat,
+    reports: usize,
+}
+
+impl<W: Write> ReportWriter<W> {
+    pub fn new(writer: W, format: StreamFormat) -> Self {
+        Self::with_sink(Sink::Plain(Counter::new(writer)), format)
+    }
+
+    // compresses the output at `level` (0-9, see `flate2::Compression`)
+    #[cfg(feature = "gzip")]
+    pub fn gzip(writer: W, format: StreamFormat, level: u32) -> Self {
+        let encoder = GzEncoder::new(Counter::new(writer), flate2::Compression::new(level));
+        Self::with_sink(Sink::Gzip(encoder), format)
+    }
+
+    fn with_sink(sink: Sink<W>, format: StreamFormat) -> Self {
+        Self {
+            sink: Counter::new(sink),
+            format,
+            reports: 0,
+        }
+    }
+
+    pub fn write(&mut self, report: &RuleMatchReport) -> Result<(), StreamError> {
+        match self.format {
+            StreamFormat::Json => {
+                self.sink
+                    .write_all(if self.reports == 0 { b"[\n" } else { b",\n" })?;
+                serde_json::to_writer(&mut self.sink, report).map_err(StreamError::Serialize)?;
+            }
+            StreamFormat::Jsonl => {
+                serde_json::to_writer(&mut self.sink, report).map_err(StreamError::Serialize)?;
+                self.sink.write_all(b"\n")?;
+            }
+        }
+
+        self.reports += 1;
+
+        Ok(())
+    }
+
+    pub fn write_match(&mut self, m: &RuleMatch) -> Result<(), StreamError> {
+        self.write(&RuleMatchReport::new(m))
+    }
+
+    pub fn reports(&self) -> usize {
+        self.reports
+    }
+
+    pub fn finish(mut self) -> Result<(W, StreamSummary), StreamError> {
+        if self.format == StreamFormat::Json {
+            self.sink
+                .write_all(if self.reports == 0 { b"[]\n" } else { b"\n]\n" })?;
+        }
+
+        let bytes = self.sink.count;
+        let writer = self.sink.inner.finish()?;
+
+        Ok((
+            writer.inner,
+            StreamSummary {
+                reports: self.reports,
+                bytes,
+                written: writer.count,
+            },
+        ))
+    }
+}
+
+impl<W> Counter<W> {
+    fn new(inner: W) -> Self {
+        Self { inner, count: 0 }
+    }
+}
+
+// Reads gzip-compressed JSONL (e.g., written by `ReportWriter::gzip` with
+// `StreamFormat::Jsonl`) one report at a time.
+#[cfg(feature = "gzip")]
+pub fn read_gzip_jsonl(
+    reader: impl io::Read,
+) -> impl Iterator<Item = Result<RuleMatchReport<'static>, JsonlError>> {
+    jsonl::read_jsonl(io::BufReader::new(GzDecoder::new(reader)))
+}
+
+#[cfg(test)]
+mod test {
+    use super::{ReportWriter, StreamFormat};
+    use crate::matcher::RuleMatcher;
+    use crate::reporting::{load_json, load_jsonl, RuleMatchReport};
+
+    #[test]
+    fn test_report_writer() -> Result<(), Box<dyn std::error::Error>> {
+        let rule = r#"
+id: unbounded-copy
+check pattern:
+  regex: func=^strcpy$
+  pattern: |
+    { $func($dst, _); }
+"#;
+
+        let mut matcher = RuleMatcher::from_str(rule)?;
+        let matches = matcher.matches_file("tests/utf8-comments.c")?;
+
+        let mut writer = ReportWriter::new(Vec::new(), StreamFormat::Json);
+        for m in &matches {
+            writer.write_match(m)?;
+        }
+
+        let (output, summary) = writer.finish()?;
+
+        assert_eq!(summary.reports, matches.len());
+        assert_eq!(summary.bytes, output.len() as u64);
+        assert_eq!(summary.written, summary.bytes);
+
+        let loaded = load_json(output.as_slice())?;
+
+        assert_eq!(loaded.len(), matches.len());
+        assert_eq!(loaded[0].rule(), matches[0].rule().id());
+
+        let (output, _) = ReportWriter::new(Vec::new(), StreamFormat::Json).finish()?;
+
+        assert_eq!(output, b"[]\n");
+        assert!(load_json(output.as_slice())?.is_empty());
+
+        let mut writer = ReportWriter::new(Vec::new(), StreamFormat::Jsonl);
+        for m in &matches {
+            writer.write(&RuleMatchReport::new(m))?;
+        }
+
+        let (output, summary) = writer.finish()?;
+
+        assert_eq!(summary.reports, matches.len());
+        assert_eq!(
+            output.iter().filter(|b| **b == b'\n').count(),
+            matches.len()
+        );
+        assert_eq!(load_jsonl(output.as_slice())?.len(), matches.len());
+
+        Ok(())
+    }
+
+    #[cfg(feature = "gzip")]
+    #[test]
+    fn test_report_writer_gzip() -> Result<(), Box<dyn std::error::Error>> {
+        use std::io::Read;
+
+        use super::{read_gzip_jsonl, GzDecoder};
+
+        let rule = r#"
+id: unbounded-copy
+check pattern:
+  regex: func=^strcpy$
+  pattern: |
+    { $func($dst, _); }
+"#;
+
+        let mut matcher = RuleMatcher::from_str(rule)?;
+        let matches = matcher.matches_file("tests/ls-main.c")?;
+
+        let mut writer = ReportWriter::gzip(Vec::new(), StreamFormat::Jsonl, 6);
+        for m in &matches {
+            writer.write_match(m)?;
+        }
+
+        let (output, summary) = writer.finish()?;
+
+        assert_eq!(summary.written, output.len() as u64);
+        assert!(summary.written * 2 < summary.bytes);
+
+        let reports = read_gzip_jsonl(output.as_slice()).collect::<Result<Vec<_>, _>>()?;
+
+        assert_eq!(reports.len(), matches.len());
+        assert_eq!(reports[0].matched_text(), matches[0].matched_text());
+
+        let mut writer = ReportWriter::gzip(Vec::new(), StreamFormat::Json, 1);
+        writer.write_match(&matches[0])?;
+
+        let (output, summary) = writer.finish()?;
+
+        let mut json = Vec::new();
+        GzDecoder::new(output.as_slice()).read_to_end(&mut json)?;
+
+        assert_eq!(json.len() as u64, summary.bytes);
+        assert_eq!(load_json(json.as_slice())?.len(), 1);
+
+        Ok(())
+    }
+}
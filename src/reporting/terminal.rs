@@ -0,0 +1,708 @@
+use std::fmt::Write;
+use std::io::IsTerminal;
+use std::ops::Range;
+use std::path::Path;
+
+use weggli::result::QueryResult;
+
+use crate::location;
+use crate::matcher::RuleMatch;
+use crate::reporting::{self, RuleMatchReport};
+use crate::rule::Severity;
+
+const RESET: &str = "\x1b[0m";
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum ColorChoice {
+    Always,
+    Never,
+    // colors if stdout is a terminal and `NO_COLOR` is not set
+    #[default]
+    Auto,
+}
+
+impl ColorChoice {
+    fn enabled(self) -> bool {
+        match self {
+            Self::Always => true,
+            Self::Never => false,
+            Self::Auto => {
+                std::env::var_os("NO_COLOR").is_none_or(|v| v.is_empty())
+                    && std::io::stdout().is_terminal()
+            }
+        }
+    }
+}
+
+// How matches are displayed: how many lines of context, with or without line
+// numbers and color, and how to lay out tabs and long lines (e.g., of
+// decompiled one-liners).
+#[derive(Debug, Clone)]
+pub struct DisplayOptions {
+    before: usize,
+    after: usize,
+    line_numbers: bool,
+    color: ColorChoice,
+    highlight_captures: bool,
+    max_line_width: Option<usize>,
+    expand_tabs: Option<usize>,
+}
+
+impl Default for DisplayOptions {
+    fn default() -> Self {
+        Self {
+            before: 2,
+            after: 2,
+            line_numbers: true,
+            color: ColorChoice::default(),
+            highlight_captures: true,
+            max_line_width: None,
+            expand_tabs: None,
+        }
+    }
+}
+
+impl DisplayOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // the options of the positional `display` methods, which underline no
+    // captures
+    pub(crate) fn positional(before: usize, after: usize, line_numbers: bool) -> Self {
+        Self::new()
+            .context(before, after)
+            .line_numbers(line_numbers)
+            .highlight_captures(false)
+    }
+
+    // the number of lines shown before and after each match
+    pub fn context(mut self, before: usize, after: usize) -> Self {
+        self.before = before;
+        self.after = after;
+        self
+    }
+
+    pub fn before(mut self, before: usize) -> Self {
+        self.before = before;
+        self
+    }
+
+    pub fn after(mut self, after: usize) -> Self {
+        self.after = after;
+        self
+    }
+
+    pub fn line_numbers(mut self, line_numbers: bool) -> Self {
+        self.line_numbers = line_numbers;
+        self
+    }
+
+    pub fn color(mut self, color: ColorChoice) -> Self {
+        self.color = color;
+        self
+    }
+
+    // whether to underline the spans captured by the rule's variables
+    pub fn highlight_captures(mut self, highlight_captures: bool) -> Self {
+        self.highlight_captures = highlight_captures;
+        self
+    }
+
+    // truncates lines longer than `width` characters (after expanding tabs)
+    // around the match, marking what is cut with an ellipsis
+    pub fn max_line_width(mut self, width: usize) -> Self {
+        self.max_line_width = Some(width.max(1));
+        self
+    }
+
+    // expands tabs to the next multiple of `width` columns
+    pub fn expand_tabs(mut self, width: usize) -> Self {
+        self.expand_tabs = Some(width.max(1));
+        self
+    }
+}
+
+impl RuleMatch {
+    // Renders a header (location, severity, rule, and checker) followed by the
+    // lines of the match, with the matched spans highlighted in the color of
+    // the rule's severity; without color, only the escape codes are missing.
+    pub fn display_colored(&self, options: &DisplayOptions) -> String {
+        self.finding(self.source_ref(), self.result())
+            .render(options)
+    }
+
+    // Renders the match as `display` does (the first and last line of the
+    // enclosing node, and the highlighted lines with their context, eliding
+    // the lines in between), according to `options`.
+    pub fn display_with(&self, options: &DisplayOptions) -> String {
+        self.finding(self.source_ref(), self.result())
+            .body(options, Layout::Node)
+    }
+
+    pub(crate) fn display_original_with(
+        &self,
+        original: &str,
+        result: &QueryResult,
+        options: &DisplayOptions,
+    ) -> String {
+        self.finding(original, result).body(options, Layout::Node)
+    }
+
+    fn finding<'a>(&'a self, source: &'a str, result: &'a QueryResult) -> Finding<'a> {
+        Finding {
+            source,
+            result,
+            // NOTE: matches of preprocessed sources retain the full source
+            // (see `SourceRetention`), and so does their original
+            first_line: self.first_line(),
+            path: self.origin(),
+            start: (self.start_line(), self.start_column()),
+            end_line: self.end_line(),
+            rule: self.rule().id(),
+            checker: self.checker().name(),
+            severity: self.effective_severity(),
+        }
+    }
+}
+
+impl RuleMatchReport<'_> {
+    // see `RuleMatch::display_colored`; reports that omit their source are
+    // rendered as a header only
+    pub fn display_colored(&self, options: &DisplayOptions) -> String {
+        self.finding().render(options)
+    }
+
+    // see `RuleMatch::display_with`; lines are numbered as in the scanned
+    // source, even if only a snippet of it is embedded
+    pub fn display_with(&self, options: &DisplayOptions) -> String {
+        self.finding().body(options, Layout::Node)
+    }
+
+    fn finding(&self) -> Finding<'_> {
+        Finding {
+            source: self.source(),
+            result: self.result(),
+            first_line: self.source_base().map_or(1, |base| base.line()),
+            path: self.path(),
+            start: (self.start_line(), self.start_column()),
+            end_line: self.end_line(),
+            rule: self.rule(),
+            checker: self.checker(),
+            severity: self.severity(),
+        }
+    }
+}
+
+struct Finding<'a> {
+    source: &'a str,
+    result: &'a QueryResult,
+    // the line number of the first line of `source`
+    first_line: usize,
+    path: Option<&'a Path>,
+    start: (usize, usize),
+    end_line: usize,
+    rule: &'a str,
+    checker: &'a str,
+    severity: Severity,
+}
+
+// which lines of the source are displayed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Layout {
+    // the lines of the match, with context
+    Window,
+    // the first and last line of the outermost node of the match, and the
+    // highlighted lines, with context (as weggli displays its results)
+    Node,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+struct Style {
+    bold: bool,
+    dim: bool,
+    underline: bool,
+    color: Option<u8>,
+}
+
+impl Style {
+    fn write(self, output: &mut String, enabled: bool) {
+        if !enabled {
+            return;
+        }
+
+        output.push_str(RESET);
+
+        let mut codes = Vec::new();
+        if self.bold {
+            codes.push(String::from("1"));
+        }
+        if self.dim {
+            codes.push(String::from("2"));
+        }
+        if self.underline {
+            codes.push(String::from("4"));
+        }
+        if let Some(color) = self.color {
+            codes.push(color.to_string());
+        }
+
+        if !codes.is_empty() {
+            let _ = write!(output, "\x1b[{}m", codes.join(";"));
+        }
+    }
+}
+
+const DIM: Style = Style {
+    bold: false,
+    dim: true,
+    underline: false,
+    color: None,
+};
+
+const ELLIPSIS: char = '…';
+
+fn color(severity: Severity) -> Option<u8> {
+    match severity {
+        Severity::None => None,
+        Severity::Low => Some(36),
+        Severity::Medium => Some(33),
+        Severity::High => Some(31),
+        Severity::Critical => Some(35),
+    }
+}
+
+impl Finding<'_> {
+    fn render(&self, options: &DisplayOptions) -> String {
+        let enabled = options.color.enabled();
+        let severity = Style {
+            bold: true,
+            color: color(self.severity),
+            ..Style::default()
+        };
+        let bold = Style {
+            bold: true,
+            ..Style::default()
+        };
+
+        let mut output = String::new();
+
+        bold.write(&mut output, enabled);
+        match self.path {
+            Some(path) => {
+                let _ = write!(
+                    output,
+                    "{}:{}:{}:",
+                    path.display(),
+                    self.start.0,
+                    self.start.1
+                );
+            }
+            None => {
+                let _ = write!(output, "<source>:{}:{}:", self.start.0, self.start.1);
+            }
+        }
+        output.push(' ');
+        severity.write(&mut output, enabled);
+        output.push_str(&self.severity.to_string().to_uppercase());
+        bold.write(&mut output, enabled);
+        let _ = write!(output, " {}/{}", self.rule, self.checker);
+        Style::default().write(&mut output, enabled);
+        output.push('\n');
+
+        output + &self.body(options, Layout::Window)
+    }
+
+    // the displayed lines of the source, or nothing if there are none (e.g.,
+    // the report omits its source, or its offsets do not fit it)
+    fn body(&self, options: &DisplayOptions, layout: Layout) -> String {
+        let fits = self.result.captures.iter().all(|c| {
+            c.range.start <= c.range.end
+                && self.source.is_char_boundary(c.range.start)
+                && self.source.is_char_boundary(c.range.end)
+        });
+
+        if self.source.is_empty() || self.result.captures.is_empty() || !fits {
+            return String::new();
+        }
+
+        let enabled = options.color.enabled();
+        let highlights = reporting::highlights(self.source, self.result);
+        let vars = if options.highlight_captures {
+            self.vars()
+        } else {
+            Vec::new()
+        };
+
+        let lines = self.lines(options, layout, &highlights);
+        let width = lines.last().map_or(0, |last| last.to_string().len()).max(4);
+
+        let mut output = String::new();
+        let mut offset = 0;
+        let mut previous = None;
+
+        for (i, line) in self.source.split_inclusive('\n').enumerate() {
+            let number = self.first_line + i;
+            let range = offset..offset + line.len();
+            offset = range.end;
+
+            if lines.binary_search(&number).is_err() {
+                continue;
+            }
+
+            // elide the lines in between
+            if previous.is_some_and(|previous| previous + 1 < number) {
+                if options.line_numbers {
+                    DIM.write(&mut output, enabled);
+                    let dots = ".".repeat((number - 1).to_string().len());
+                    let _ = write!(output, "{dots:>width$}");
+                    Style::default().write(&mut output, enabled);
+                    output.push('\n');
+                } else {
+                    output.push_str("...\n");
+                }
+            }
+            previous = Some(number);
+
+            if options.line_numbers {
+                DIM.write(&mut output, enabled);
+                let _ = write!(output, "{number:>width$}: ");
+                Style::default().write(&mut output, enabled);
+            }
+
+            let text = line.trim_end_matches(['\r', '\n']);
+            let range = range.start..range.start + text.len();
+
+            self.line(&mut output, range, &highlights, &vars, options, enabled);
+            output.push('\n');
+        }
+
+        output
+    }
+
+    // the sorted numbers of the displayed lines
+    fn lines(
+        &self,
+        options: &DisplayOptions,
+        layout: Layout,
+        highlights: &[Range<usize>],
+    ) -> Vec<usize> {
+        let line = |offset| self.first_line + location::line_column(self.source, offset).0 - 1;
+
+        let (first, last, marked) = match layout {
+            Layout::Window => (
+                self.first_line,
+                self.end_line + options.after,
+                vec![self.start.0, self.end_line],
+            ),
+            Layout::Node => {
+                let node =
+                    location::clamp_range(self.source, self.result.captures[0].range.clone());
+                let (first, last) = (
+                    line(node.start),
+                    line(node.end.saturating_sub(1).max(node.start)),
+                );
+
+                let mut marked = vec![first, last];
+                for r in highlights {
+                    marked.extend(line(r.start)..=line(r.end.saturating_sub(1).max(r.start)));
+                }
+
+                (first, last, marked)
+            }
+        };
+
+        let mut lines = marked
+            .into_iter()
+            .flat_map(|n| {
+                n.saturating_sub(options.before).max(first)..=(n + options.after).min(last)
+            })
+            .collect::<Vec<_>>();
+        lines.sort_unstable();
+        lines.dedup();
+
+        // the window spans the whole match
+        if layout == Layout::Window {
+            if let (Some(&first), Some(&last)) = (lines.first(), lines.last()) {
+                lines = (first..=last).collect();
+            }
+        }
+
+        lines
+    }
+
+    // renders the line at `range` of the source, styled, with tabs expanded and
+    // truncated to the maximum width (if any)
+    fn line(
+        &self,
+        output: &mut String,
+        range: Range<usize>,
+        highlights: &[Range<usize>],
+        vars: &[Range<usize>],
+        options: &DisplayOptions,
+        enabled: bool,
+    ) {
+        // the displayed columns, with the offsets of their characters
+        let mut cells = Vec::<(usize, char)>::new();
+
+        for (i, c) in self.source[range.clone()].char_indices() {
+            match (c, options.expand_tabs) {
+                ('\t', Some(tab)) => {
+                    let n = tab - cells.len() % tab;
+                    cells.extend(std::iter::repeat_n((range.start + i, ' '), n));
+                }
+                _ => cells.push((range.start + i, c)),
+            }
+        }
+
+        let mut shown = 0..cells.len();
+
+        if let Some(width) = options.max_line_width.filter(|w| cells.len() > *w) {
+            // keep the highlighted columns in view, centered if they fit
+            let highlighted =
+                |(offset, _): &(usize, char)| highlights.iter().any(|r| r.contains(offset));
+            let focus_start = cells.iter().position(highlighted).unwrap_or(0);
+            let focus_end = cells
+                .iter()
+                .rposition(highlighted)
+                .map_or(focus_start, |i| i + 1);
+
+            let available = width.saturating_sub(2).max(1);
+            let slack = available.saturating_sub(focus_end - focus_start);
+            let start = focus_start
+                .saturating_sub(slack / 2)
+                .min(cells.len() - available);
+
+            shown = start..start + available;
+        }
+
+        if shown.start > 0 {
+            output.push(ELLIPSIS);
+        }
+
+        let mut current = Style::default();
+
+        for &(offset, c) in &cells[shown.clone()] {
+            let highlighted = highlights.iter().any(|r| r.contains(&offset));
+            let style = Style {
+                bold: highlighted,
+                underline: vars.iter().any(|r| r.contains(&offset)),
+                color: if highlighted {
+                    color(self.severity)
+                } else {
+                    None
+                },
+                ..Style::default()
+            };
+
+            if style != current {
+                style.write(output, enabled);
+                current = style;
+            }
+
+            output.push(c);
+        }
+
+        if current != Style::default() {
+            Style::default().write(output, enabled);
+        }
+
+        if shown.end < cells.len() {
+            output.push(ELLIPSIS);
+        }
+    }
+
+    // the ranges captured by variables
+    fn vars(&self) -> Vec<Range<usize>> {
+        self.result
+            .vars
+            .values()
+            .filter_map(|i| self.result.captures.get(*i))
+            .map(|c| location::clamp_range(self.source, c.range.clone()))
+            .filter(|r| !r.is_empty())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{ColorChoice, DisplayOptions};
+    use crate::matcher::RuleMatcher;
+    use crate::reporting::{RuleMatchReport, SourceMode};
+
+    fn strip(text: &str) -> String {
+        let mut stripped = String::new();
+        let mut chars = text.chars();
+        while let Some(c) = chars.next() {
+            if c == '\x1b' {
+                chars.by_ref().find(|c| *c == 'm');
+            } else {
+                stripped.push(c);
+            }
+        }
+        stripped
+    }
+
+    #[test]
+    fn test_display_colored() -> Result<(), Box<dyn std::error::Error>> {
+        let rule = r#"
+id: unbounded-copy
+severity: high
+check pattern:
+  regex: func=^strcpy$
+  pattern: '{ $func($dst, _); }'
+"#;
+
+        let source = "\
+void f(char *d, char *s) {
+    int n = 0;
+    strcpy(d, s);
+    return;
+}
+";
+
+        let mut matcher = RuleMatcher::from_str(rule)?;
+        let matches = matcher.matches(source)?;
+        let m = &matches[0];
+
+        let options = DisplayOptions::new().context(1, 0);
+        let plain = m.display_colored(&options.clone().color(ColorChoice::Never));
+
+        assert_eq!(
+            plain,
+            "<source>:3:5: HIGH unbounded-copy/default\n   2:     int n = 0;\n   3:     strcpy(d, s);\n"
+        );
+
+        let colored = m.display_colored(&options.clone().color(ColorChoice::Always));
+
+        assert_eq!(strip(&colored), plain);
+        assert!(colored.contains("\x1b[0m\x1b[1;31mHIGH"));
+        // `$func` and `$dst` are highlighted and underlined, the rest of the
+        // match only highlighted
+        assert!(colored.contains("\x1b[0m\x1b[1;4;31mstrcpy\x1b[0m(\x1b[0m\x1b[1;4;31md\x1b[0m"));
+
+        let colored = m.display_colored(
+            &options
+                .clone()
+                .color(ColorChoice::Always)
+                .highlight_captures(false),
+        );
+
+        assert!(colored.contains("\x1b[0m\x1b[1;31mstrcpy\x1b[0m"));
+        assert!(!colored.contains("\x1b[1;4"));
+
+        // the positional display underlines no captures
+        assert!(!m.display(1, 0, true).contains("\x1b[1;4"));
+
+        // reports render the same, including from snippets
+        let report = RuleMatchReport::new(m);
+
+        assert_eq!(
+            report.display_colored(&options.clone().color(ColorChoice::Never)),
+            plain
+        );
+
+        let snippet = RuleMatchReport::new(m).with_source_mode(SourceMode::Snippet {
+            before: 1,
+            after: 0,
+        });
+
+        assert_eq!(
+            snippet.display_colored(&options.clone().color(ColorChoice::Never)),
+            plain
+        );
+
+        let omitted = RuleMatchReport::new(m).with_source_mode(SourceMode::Omit);
+
+        assert_eq!(
+            omitted.display_colored(
+                &options
+                    .clone()
+                    .color(ColorChoice::Never)
+                    .line_numbers(false)
+            ),
+            "<source>:3:5: HIGH unbounded-copy/default\n"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_display_with() -> Result<(), Box<dyn std::error::Error>> {
+        let rule = r#"
+id: unbounded-copy
+severity: high
+check pattern:
+  regex: func=^strcpy$
+  pattern: '{ $func($dst, _); }'
+"#;
+
+        let source = "void f(char *d, char *s) {\n\tint n = 0;\n\tn++;\n\tn++;\n\tstrcpy(d, s);\n\treturn;\n}\n";
+        let long = format!(
+            "void g(char *d, char *s) {{ {} strcpy(d, s); {} }}\n",
+            "d[0] = 0;".repeat(40),
+            "s[0] = 0;".repeat(40)
+        );
+
+        let mut matcher = RuleMatcher::from_str(rule)?;
+        let matches = matcher.matches(source)?;
+        let m = &matches[0];
+        let options = DisplayOptions::new().color(ColorChoice::Never);
+
+        // by default, the enclosing node is shown in full, as its lines are
+        // all within the context of the first, last, or highlighted line
+        assert_eq!(
+            m.display_with(&options),
+            "   1: void f(char *d, char *s) {\n   2: \tint n = 0;\n   3: \tn++;\n   4: \tn++;\n   5: \tstrcpy(d, s);\n   6: \treturn;\n   7: }\n"
+        );
+
+        // without context, the lines in between are elided
+        assert_eq!(
+            m.display_with(&options.clone().context(0, 0)),
+            "   1: void f(char *d, char *s) {\n   .\n   5: \tstrcpy(d, s);\n   .\n   7: }\n"
+        );
+
+        assert_eq!(
+            m.display_with(
+                &options
+                    .clone()
+                    .context(1, 1)
+                    .line_numbers(false)
+                    .expand_tabs(4)
+            ),
+            "void f(char *d, char *s) {\n    int n = 0;\n...\n    n++;\n    strcpy(d, s);\n    return;\n}\n"
+        );
+
+        // the positional methods are thin wrappers
+        assert_eq!(
+            strip(&m.display(0, 0, true)),
+            m.display_with(&options.clone().context(0, 0))
+        );
+        assert_eq!(
+            strip(&RuleMatchReport::new(m).display(1, 1, false)),
+            m.display_with(&options.clone().context(1, 1).line_numbers(false))
+        );
+
+        // long lines are truncated around the match
+        let matches = matcher.matches(&long)?;
+        let m = &matches[0];
+
+        assert_eq!(
+            m.display_with(&options.clone().max_line_width(40)),
+            "   1: … = 0;d[0] = 0; strcpy(d, s); s[0] = 0;…\n"
+        );
+        assert_eq!(
+            m.display_with(
+                &options
+                    .clone()
+                    .max_line_width(40)
+                    .line_numbers(false)
+                    .color(ColorChoice::Always)
+            ),
+            "… = 0;d[0] = 0; \x1b[0m\x1b[1;4;31mstrcpy\x1b[0m(\x1b[0m\x1b[1;4;31md\x1b[0m, s); s[0] = 0;…\n"
+        );
+        assert_eq!(m.display_with(&options.clone().line_numbers(false)), long);
+
+        Ok(())
+    }
+}
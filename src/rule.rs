@@ -1,23 +1,79 @@
-use std::borrow::Borrow;
-use std::collections::{HashMap, HashSet};
-use std::fmt::Display;
-use std::fs::File;
+use std::borrow::{Borrow, Cow};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt::{Debug, Display};
+#[cfg(feature = "std-fs")]
+use std::fs::{self, File};
+#[cfg(feature = "std-fs")]
 use std::io::BufReader;
+use std::io::Write;
+use std::ops::Range;
 use std::path::{Path, PathBuf};
-use std::sync::Arc;
+use std::sync::{Arc, OnceLock};
 
-use memchr::memmem;
+use memchr::memmem::{self, Finder};
 use nonempty::NonEmpty;
 use regex::Regex;
-use rustc_hash::FxHashSet;
+use rustc_hash::{FxHashMap, FxHashSet};
+use serde::de::DeserializeSeed;
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
-use tree_sitter::Tree;
-use walkdir::WalkDir;
+use tree_sitter::{Node, Tree};
 use weggli::query::QueryTree;
 use weggli::result::QueryResult;
 use weggli::RegexMap;
 
+use crate::matcher::{function_lines_within, match_span};
+use crate::preprocess::comments;
+#[cfg(feature = "std-fs")]
+use crate::trace;
+
+mod condition;
+pub use condition::Condition;
+
+mod constraint;
+pub use constraint::{Comparison, NumericConstraint, Relation};
+
+mod escalation;
+pub use escalation::Escalation;
+use escalation::EscalationT;
+
+pub mod import;
+
+mod transform;
+use transform::TransformT;
+pub use transform::{CaptureTransform, Mangling};
+
+#[cfg(feature = "std-fs")]
+mod cache;
+
+mod limits;
+pub use limits::{LimitKind, RuleLimits};
+
+mod paths;
+pub use paths::PathFilter;
+
+#[cfg(feature = "std-fs")]
+mod files;
+#[cfg(feature = "std-fs")]
+use files::rule_files;
+#[cfg(feature = "std-fs")]
+pub use files::{RuleFileFilter, RuleKeys};
+
+mod size;
+pub use size::FileSize;
+use size::SizeT;
+
+mod ts_query;
+use ts_query::TsQuery;
+pub(crate) use ts_query::VARIABLE_CAPTURE;
+
+mod text_match;
+use text_match::TextMatch;
+
+// the default size (in bytes) above which a check is not run on a source (see
+// `Checker::max_source_bytes`)
+pub const MAX_SOURCE_BYTES: usize = 16 * 1024 * 1024;
+
 #[derive(Debug, Error)]
 pub enum RuleError {
     #[error(transparent)]
@@ -32,8 +88,30 @@ pub enum RuleError {
     NoId,
     #[error("rule has multiple checks with the same name")]
     MultipleChecksWithSameName,
+    #[error("invalid condition: {0}")]
+    InvalidCondition(String),
+    #[error("condition refers to an unknown check `{0}`")]
+    UnknownCheck(String),
+    #[error("invalid path pattern: {0}")]
+    PathPattern(#[from] globset::Error),
     #[error(transparent)]
     Regex(#[from] RegexError),
+    #[error("cannot serialize rule: {0}")]
+    Serialize(serde_yaml::Error),
+    #[error("cannot write rules: {0}")]
+    Io(std::io::Error),
+    #[error("cannot write rule file {}: {1}", _0.display())]
+    Write(PathBuf, std::io::Error),
+    #[error("multiple rules are written to {}", _0.display())]
+    DuplicateFile(PathBuf),
+    #[error("rule `{rule}` has an invalid max-file-size `{size}` (expected bytes, or a size such as `512k` or `2MB`)")]
+    InvalidFileSize { rule: String, size: String },
+    #[error("{kind} of {actual} exceeds the limit of {limit}")]
+    LimitExceeded {
+        kind: LimitKind,
+        limit: usize,
+        actual: usize,
+    },
 }
 
 #[derive(Debug, Error)]
@@ -42,10 +120,35 @@ pub enum CheckError {
     NoCheckName,
     #[error("check has no patterns")]
     NoCheckPatterns,
-    #[error("regex constraint has an invalid query variable `{0}`")]
+    #[error("constraint has an invalid query variable `{0}`")]
     InvalidQueryVariable(String),
+    #[error("`{0}` is not in the format `var <op> integer`")]
+    InvalidConstraint(String),
+    #[error("`{0}` is not in the format `var == var` or `var != var`")]
+    InvalidRelation(String),
+    #[error("`min-function-lines` ({0}) is greater than `max-function-lines` ({1})")]
+    InvalidFunctionLines(usize, usize),
+    #[error("check has more than one of `pattern`, `ts-query`, `match-regex`, and `match-string`")]
+    MultiplePatterns,
+    #[error("`report-capture` is only valid with a `ts-query`")]
+    ReportCaptureWithoutQuery,
+    #[error("`code-only` is only valid with `match-regex` or `match-string`")]
+    CodeOnlyWithoutText,
+    #[error("invalid `ts-query` in check `{check}`: {source}")]
+    Query {
+        check: String,
+        source: tree_sitter::QueryError,
+    },
+    #[error("check `{check}` has no capture `@{capture}` to report")]
+    UnknownReportCapture { check: String, capture: String },
     #[error("invalid pattern: {0}")]
     Pattern(#[from] weggli::WeggliError),
+    #[error("invalid pattern `{pattern}` (compiled as `{wrapped}`): {source}")]
+    WrappedPattern {
+        pattern: String,
+        wrapped: String,
+        source: weggli::WeggliError,
+    },
     #[error(transparent)]
     Regex(#[from] RegexError),
 }
@@ -58,39 +161,71 @@ pub enum RegexError {
     InvalidRegex(#[from] regex::Error),
 }
 
+// Rules are identified by their index within the set, and by a key: the path
+// of the file they were loaded from (for directories, relative to the
+// directory by default; see `RuleKeys`), or `default` for rules loaded from
+// a string. Indices follow load order; for directories, entries are visited
+// in file name order, so that indices are stable for a given directory tree.
 #[derive(Clone)]
 pub struct RuleSet {
-    rules: Arc<[(String, Arc<Rule>)]>,
+    rules: Arc<[(Arc<str>, Arc<Rule>)]>,
 }
 
 impl RuleSet {
+    #[cfg(feature = "std-fs")]
     pub fn from_directory(root: impl AsRef<Path>, ignore_errors: bool) -> Result<Self, RuleError> {
-        let walker = WalkDir::new(root);
+        Self::from_directory_with_limits(root, ignore_errors, &RuleLimits::default())
+    }
+
+    // see `RuleLimits`: the number of rule files is checked before any is
+    // parsed, and is an error even with `ignore_errors`
+    #[cfg(feature = "std-fs")]
+    pub fn from_directory_with_limits(
+        root: impl AsRef<Path>,
+        ignore_errors: bool,
+        limits: &RuleLimits,
+    ) -> Result<Self, RuleError> {
+        Self::from_directory_with_filter(root, ignore_errors, &RuleFileFilter::default(), limits)
+    }
+
+    // loads the files of `root` accepted by `filter` (see `RuleFileFilter`),
+    // rather than the `.yml` and `.yaml` ones
+    #[cfg(feature = "std-fs")]
+    pub fn from_directory_with_filter(
+        root: impl AsRef<Path>,
+        ignore_errors: bool,
+        filter: &RuleFileFilter,
+        limits: &RuleLimits,
+    ) -> Result<Self, RuleError> {
+        Self::from_directory_with_keys(root, ignore_errors, filter, limits, RuleKeys::default())
+    }
+
+    // like `from_directory_with_filter`, but keys the rules as given by
+    // `keys`; the rules' `source_path` is their native path either way
+    #[cfg(feature = "std-fs")]
+    pub fn from_directory_with_keys(
+        root: impl AsRef<Path>,
+        ignore_errors: bool,
+        filter: &RuleFileFilter,
+        limits: &RuleLimits,
+        keys: RuleKeys,
+    ) -> Result<Self, RuleError> {
+        let root = root.as_ref();
         let mut rules = Vec::new();
 
-        for dirent in walker
-            .into_iter()
-            .filter_entry(|e| {
-                e.file_type().is_dir() || {
-                    matches!(e.path().extension(), Some(x) if
-                    ["yml", "yaml"].contains(&x.to_string_lossy().as_ref()))
-                }
-            })
-            .filter_map(Result::ok)
-        {
-            if dirent.file_type().is_dir() {
-                continue;
-            }
+        let paths = rule_files(root, filter);
+        RuleLimits::check(LimitKind::Rules, limits.max_rules_limit(), paths.len())?;
 
-            let path = dirent.path();
-            match Rule::from_file(path) {
+        for path in &paths {
+            match Rule::from_file_with_limits(path, limits) {
                 Ok(rule) => {
-                    rules.push((path.display().to_string(), Arc::new(rule)));
+                    rules.push((Arc::from(keys.key(root, path)), Arc::new(rule)));
                 }
                 Err(e) => {
                     if !ignore_errors {
                         return Err(e);
                     }
+                    trace::event!(DEBUG, "skipped_rule", path = path.display(), error = e);
                 }
             }
         }
@@ -100,20 +235,22 @@ impl RuleSet {
         })
     }
 
+    #[cfg(feature = "std-fs")]
     pub fn from_file(path: impl AsRef<Path>) -> Result<Self, RuleError> {
         let path = path.as_ref();
         Ok(Self {
             rules: Arc::from(vec![(
-                path.display().to_string(),
+                Arc::from(path.display().to_string()),
                 Arc::new(Rule::from_file(path)?),
             )]),
         })
     }
 
+    #[allow(clippy::should_implement_trait)]
     pub fn from_str(rule: impl AsRef<str>) -> Result<Self, RuleError> {
         Ok(Self {
             rules: Arc::from(vec![(
-                String::from("default"),
+                Arc::from("default"),
                 Arc::new(Rule::from_str(rule)?),
             )]),
         })
@@ -127,20 +264,49 @@ impl RuleSet {
         self.rules.get(index).map(|(_, r)| r.as_ref())
     }
 
+    pub fn key(&self, index: usize) -> Option<&str> {
+        self.rules.get(index).map(|(k, _)| k.as_ref())
+    }
+
+    pub(crate) fn key_shared(&self, index: usize) -> Option<Arc<str>> {
+        self.rules.get(index).map(|(k, _)| k.clone())
+    }
+
     pub fn iter(&self) -> impl ExactSizeIterator<Item = (&str, &Rule)> {
-        self.rules.iter().map(|(p, r)| (p.as_str(), r.as_ref()))
+        self.rules.iter().map(|(p, r)| (p.as_ref(), r.as_ref()))
+    }
+
+    // the same rules, in the same order, under the keys `f` returns for each
+    // key and rule, e.g., to strip a machine-specific prefix from native keys
+    pub fn rekey(&self, mut f: impl FnMut(&str, &Rule) -> String) -> Self {
+        Self {
+            rules: self
+                .rules
+                .iter()
+                .map(|(key, rule)| (Arc::from(f(key, rule)), rule.clone()))
+                .collect(),
+        }
     }
 
     pub fn viable_checkers(
         &self,
         source: impl AsRef<str>,
+    ) -> Vec<(usize, Arc<Rule>, usize, &Checker)> {
+        self.viable_checkers_with(source, |_, _| true)
+    }
+
+    pub fn viable_checkers_with(
+        &self,
+        source: impl AsRef<str>,
+        filter: impl Fn(usize, &Rule) -> bool,
     ) -> Vec<(usize, Arc<Rule>, usize, &Checker)> {
         let source = source.as_ref();
 
         self.rules
             .iter()
             .enumerate()
-            .map(|(rule_id, (_, rule))| {
+            .filter(|(rule_id, (_, rule))| filter(*rule_id, rule))
+            .flat_map(|(rule_id, (_, rule))| {
                 rule.checks()
                     .iter()
                     .enumerate()
@@ -152,7 +318,6 @@ impl RuleSet {
                         }
                     })
             })
-            .flatten()
             .collect()
     }
 
@@ -163,6 +328,64 @@ impl RuleSet {
     pub fn len(&self) -> usize {
         self.rules.len()
     }
+
+    // the rules ordered by id (and key, for rules with the same id)
+    fn sorted(&self) -> Vec<&(Arc<str>, Arc<Rule>)> {
+        let mut rules = self.rules.iter().collect::<Vec<_>>();
+        rules.sort_by(|(ka, a), (kb, b)| (a.id(), ka).cmp(&(b.id(), kb)));
+        rules
+    }
+
+    // writes the rules as a stream of YAML documents, one per rule, ordered
+    // by id
+    pub fn to_yaml_writer(&self, mut writer: impl Write) -> Result<(), RuleError> {
+        for (_, rule) in self.sorted() {
+            let document = serde_yaml::to_string(rule.as_ref()).map_err(RuleError::Serialize)?;
+
+            writer
+                .write_all(b"---\n")
+                .and_then(|_| writer.write_all(document.as_bytes()))
+                .map_err(RuleError::Io)?;
+        }
+
+        writer.flush().map_err(RuleError::Io)
+    }
+
+    // writes each rule to `<id>.yml` below `root` (which is created if it
+    // does not exist), so that `from_directory` loads them again; characters
+    // of ids other than ASCII alphanumerics, `-`, `_`, and `.` are replaced
+    // by `_`, and rules whose file names collide are an error
+    #[cfg(feature = "std-fs")]
+    pub fn to_directory(&self, root: impl AsRef<Path>) -> Result<(), RuleError> {
+        let root = root.as_ref();
+        fs::create_dir_all(root).map_err(|e| RuleError::Write(root.to_owned(), e))?;
+
+        let mut written = HashSet::new();
+
+        for (_, rule) in self.sorted() {
+            let name = rule
+                .id()
+                .chars()
+                .map(|c| {
+                    if c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.') {
+                        c
+                    } else {
+                        '_'
+                    }
+                })
+                .collect::<String>();
+            let path = root.join(format!("{name}.yml"));
+
+            if !written.insert(path.clone()) {
+                return Err(RuleError::DuplicateFile(path));
+            }
+
+            let document = serde_yaml::to_string(rule.as_ref()).map_err(RuleError::Serialize)?;
+            fs::write(&path, document).map_err(|e| RuleError::Write(path, e))?;
+        }
+
+        Ok(())
+    }
 }
 
 #[derive(
@@ -178,6 +401,12 @@ pub enum Severity {
     Critical,
 }
 
+impl Severity {
+    pub fn is_none(&self) -> bool {
+        matches!(self, Self::None)
+    }
+}
+
 impl Display for Severity {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.write_str(match self {
@@ -196,19 +425,56 @@ pub struct Rule {
     description: String,
     severity: Severity,
     tags: FxHashSet<String>,
+    paths: PathFilter,
+    max_file_size: Option<FileSize>,
+    metadata: BTreeMap<String, serde_json::Value>,
+    mangling: Mangling,
     checks: Box<[Checker]>,
+    condition: Option<Condition>,
+    source_path: Option<PathBuf>,
 }
 
 impl Rule {
+    #[cfg(feature = "std-fs")]
     pub fn from_file(path: impl AsRef<Path>) -> Result<Self, RuleError> {
+        Self::from_file_with_limits(path, &RuleLimits::default())
+    }
+
+    #[cfg(feature = "std-fs")]
+    fn with_source_path(mut self, path: &Path) -> Self {
+        self.source_path = Some(path.to_owned());
+        self
+    }
+
+    // see `RuleLimits`; `from_file` applies the default limits
+    #[cfg(feature = "std-fs")]
+    pub fn from_file_with_limits(
+        path: impl AsRef<Path>,
+        limits: &RuleLimits,
+    ) -> Result<Self, RuleError> {
         let path = path.as_ref();
+        let _span = trace::span!("load_rule", path = path.display());
+
         let file = File::open(path).map_err(|e| RuleError::ParseFile(path.to_owned(), e.into()))?;
-        serde_yaml::from_reader(BufReader::new(file))
+        WithLimits(limits)
+            .deserialize(serde_yaml::Deserializer::from_reader(BufReader::new(file)))
+            .map(|rule| rule.with_source_path(path))
             .map_err(|e| RuleError::ParseFile(path.to_owned(), e.into()))
     }
 
+    #[allow(clippy::should_implement_trait)]
     pub fn from_str(rule: impl AsRef<str>) -> Result<Self, RuleError> {
-        serde_yaml::from_str(rule.as_ref()).map_err(RuleError::from)
+        Self::from_str_with_limits(rule, &RuleLimits::default())
+    }
+
+    // see `RuleLimits`; `from_str` applies the default limits
+    pub fn from_str_with_limits(
+        rule: impl AsRef<str>,
+        limits: &RuleLimits,
+    ) -> Result<Self, RuleError> {
+        WithLimits(limits)
+            .deserialize(serde_yaml::Deserializer::from_str(rule.as_ref()))
+            .map_err(RuleError::from)
     }
 
     pub fn id(&self) -> &str {
@@ -235,6 +501,14 @@ impl Rule {
         self.severity
     }
 
+    // the highest severity a match of the rule may have, with escalations
+    pub fn max_severity(&self) -> Severity {
+        self.checks
+            .iter()
+            .flat_map(|c| c.escalate().iter().map(Escalation::severity))
+            .fold(self.severity, Severity::max)
+    }
+
     pub fn tags(&self) -> &FxHashSet<String> {
         &self.tags
     }
@@ -243,13 +517,66 @@ impl Rule {
         self.tags.contains(tag.borrow())
     }
 
+    pub fn paths(&self) -> &PathFilter {
+        &self.paths
+    }
+
+    // the size of the largest source the rule is run on, if limited; see
+    // `RuleMatcher::set_max_file_size` for a default
+    pub fn max_file_size(&self) -> Option<&FileSize> {
+        self.max_file_size.as_ref()
+    }
+
+    // whether the rule's path constraints permit matching on `path`
+    pub fn applies_to(&self, path: impl AsRef<Path>) -> bool {
+        self.paths.matches(path)
+    }
+
+    // freeform values (e.g., `owner` or `references`), which the rule does
+    // not interpret; verbose reports carry them
+    pub fn metadata(&self) -> &BTreeMap<String, serde_json::Value> {
+        &self.metadata
+    }
+
+    // how the regex constraints of the rule's checks see through decompiler
+    // names (see `Mangling`)
+    pub fn mangling(&self) -> Mangling {
+        self.mangling
+    }
+
     pub fn checks(&self) -> &[Checker] {
         &self.checks
     }
+
+    pub fn condition(&self) -> Option<&Condition> {
+        self.condition.as_ref()
+    }
+
+    // the file the rule was loaded from, as given (i.e., native, and not
+    // relative to the directory it was loaded from, unlike its key in a
+    // `RuleSet`); `None` for rules loaded from a string
+    pub fn source_path(&self) -> Option<&Path> {
+        self.source_path.as_deref()
+    }
 }
 
 impl<'de> Deserialize<'de> for Rule {
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        WithLimits(&RuleLimits::default()).deserialize(deserializer)
+    }
+}
+
+// Deserializes a rule within limits other than the defaults (see
+// `RuleLimits`), which serde has no other way to pass.
+struct WithLimits<'a>(&'a RuleLimits);
+
+impl<'de> DeserializeSeed<'de> for WithLimits<'_> {
+    type Value = Rule;
+
+    fn deserialize<D>(self, deserializer: D) -> Result<Rule, D::Error>
     where
         D: serde::Deserializer<'de>,
     {
@@ -264,6 +591,14 @@ impl<'de> Deserialize<'de> for Rule {
             severity: Severity,
             #[serde(default)]
             tags: FxHashSet<String>,
+            #[serde(default)]
+            paths: PathFilter,
+            #[serde(rename = "max-file-size", alias = "max_file_size", default)]
+            max_file_size: Option<SizeT>,
+            #[serde(default)]
+            metadata: BTreeMap<String, serde_json::Value>,
+            #[serde(default)]
+            mangling: Mangling,
             #[serde(
                 rename = "check patterns",
                 alias = "check-patterns",
@@ -271,38 +606,85 @@ impl<'de> Deserialize<'de> for Rule {
                 alias = "check-pattern"
             )]
             checks: OneOrMany<CheckerT>,
+            #[serde(default)]
+            condition: Option<String>,
+            #[serde(default)]
+            report: Option<String>,
         }
 
-        let rule = RuleT::deserialize(deserializer)?;
+        let mut rule = RuleT::deserialize(deserializer)?;
 
         if rule.id.is_empty() {
             return Err(<D::Error as serde::de::Error>::custom(RuleError::NoId));
         }
 
+        let max_file_size = rule
+            .max_file_size
+            .map(|size| {
+                FileSize::parse(size.clone()).ok_or_else(|| RuleError::InvalidFileSize {
+                    rule: rule.id.clone(),
+                    size: size.to_string(),
+                })
+            })
+            .transpose()
+            .map_err(<D::Error as serde::de::Error>::custom)?;
+
+        // NOTE: before any pattern or regex is compiled
+        self.0
+            .check_checks(&rule.checks)
+            .map_err(<D::Error as serde::de::Error>::custom)?;
+
+        for check in rule.checks.iter_mut() {
+            check.mangling = rule.mangling;
+        }
+
         let checks = Vec::try_from(rule.checks)
             .map_err(<D::Error as serde::de::Error>::custom)?
             .into_boxed_slice();
 
+        let condition = match (&rule.condition, &rule.report) {
+            (Some(condition), report) => {
+                Some(Condition::new(condition, report.as_deref(), &checks))
+            }
+            (None, Some(_)) => Some(Err(RuleError::InvalidCondition(String::from(
+                "`report` requires a `condition`",
+            )))),
+            (None, None) => None,
+        }
+        .transpose()
+        .map_err(<D::Error as serde::de::Error>::custom)?;
+
         Ok(Rule {
             id: rule.id,
             author: rule.author,
             description: rule.description,
             severity: rule.severity,
             tags: rule.tags,
+            paths: rule.paths,
+            max_file_size,
+            metadata: rule.metadata,
+            mangling: rule.mangling,
             checks,
+            condition,
+            source_path: None,
         })
     }
 }
 
-#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Deserialize)]
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize, Serialize,
+)]
 pub enum CheckerLanguage {
     #[serde(rename = "c")]
     #[default]
     C,
-    #[serde(rename = "c++")]
+    #[serde(rename = "c++", alias = "cxx", alias = "cpp")]
     Cplusplus,
 }
 
+// the grammar used to parse a source
+pub type Language = CheckerLanguage;
+
 impl CheckerLanguage {
     pub fn is_c(&self) -> bool {
         matches!(self, Self::C)
@@ -311,15 +693,249 @@ impl CheckerLanguage {
     pub fn is_cxx(&self) -> bool {
         matches!(self, Self::Cplusplus)
     }
+
+    pub fn from_extension(extension: impl AsRef<str>) -> Option<Self> {
+        match extension.as_ref().to_ascii_lowercase().as_str() {
+            "c" | "h" | "i" => Some(Self::C),
+            "cc" | "cp" | "cpp" | "cxx" | "c++" | "hh" | "hpp" | "hxx" | "h++" | "ii" | "ipp"
+            | "tcc" | "inl" => Some(Self::Cplusplus),
+            _ => None,
+        }
+    }
+
+    pub fn from_path(path: impl AsRef<Path>) -> Option<Self> {
+        path.as_ref()
+            .extension()
+            .and_then(|x| Self::from_extension(x.to_string_lossy()))
+    }
+
+    // NOTE: this is a heuristic; it looks for syntax that cannot appear in
+    // (decompiled) C, such as scope resolution or class declarations, outside
+    // of comments and string literals
+    pub fn detect(source: impl AsRef<str>) -> Self {
+        let source = source.as_ref();
+        let code = comments::blank_comments_and_literals(source).unwrap_or(Cow::Borrowed(source));
+
+        if memmem::find(code.as_bytes(), b"::").is_some() || words(&code).any(is_cplusplus) {
+            Self::Cplusplus
+        } else {
+            Self::C
+        }
+    }
+}
+
+fn is_ident(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_'
+}
+
+// the identifiers and keywords of `code`, each with the code that follows it
+fn words(code: &str) -> impl Iterator<Item = (&str, &str)> {
+    code.char_indices()
+        .filter(|&(_, c)| !is_ident(c))
+        .map(|(i, c)| i + c.len_utf8())
+        .chain([0])
+        .filter_map(move |start| {
+            let rest = code.get(start..)?;
+            let len = rest.find(|c: char| !is_ident(c)).unwrap_or(rest.len());
+            (len > 0 && !rest.starts_with(|c: char| c.is_ascii_digit()))
+                .then(|| (&rest[..len], rest[len..].trim_start()))
+        })
+}
+
+fn is_cplusplus((word, rest): (&str, &str)) -> bool {
+    let ident = |s: &str| s.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_');
+
+    match word {
+        "template" => rest.starts_with('<'),
+        "namespace" => ident(rest) || rest.starts_with('{'),
+        // `class Name {` or `class Name : Base`
+        "class" if ident(rest) => {
+            let name = rest.find(|c: char| !is_ident(c)).unwrap_or(rest.len());
+            rest[name..].trim_start().starts_with(['{', ':'])
+        }
+        "public" | "private" | "protected" => rest.starts_with(':'),
+        "nullptr" => true,
+        // `operator==`, `operator()`, `operator new`, ...
+        "operator" => {
+            rest.starts_with([
+                '+', '-', '*', '/', '%', '^', '&', '|', '~', '!', '=', '<', '>', ',', '(', '[',
+            ]) || ["new", "delete"].iter().any(|op| {
+                rest.strip_prefix(op)
+                    .is_some_and(|s| !s.starts_with(is_ident))
+            })
+        }
+        _ => false,
+    }
+}
+
+impl Display for CheckerLanguage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::C => "c",
+            Self::Cplusplus => "c++",
+        })
+    }
 }
 
 pub struct Checker {
     name: Arc<str>,
     language: CheckerLanguage,
-    pattern: QueryTree,
+    // NOTE: the patterns of checkers read from a rule cache are compiled after
+    // they are built, by `compile`; see `precompiled`
+    pattern: OnceLock<QueryTree>,
+    // the tree-sitter query the check is written as, instead of a pattern
+    ts_query: Option<TsQuery>,
+    // the regex or string the check searches for, instead of a pattern
+    text: Option<TextMatch>,
+    // the regex constraints tested by weggli, which the pattern is compiled
+    // with
+    regex_map: RegexMap,
     identifiers: Box<[String]>,
+    // as written in the rule, if at all
+    explicit_identifiers: Box<[String]>,
+    variables: HashSet<String>,
+    // searchers for the identifiers, longest (i.e., likely rarest) first
+    finders: Box<[Finder<'static>]>,
     limit: bool,
     unique: bool,
+    unique_by: Option<UniqueBy>,
+    constraints: Box<[NumericConstraint]>,
+    // whether matches whose constrained captures are not integers are kept
+    keep_unparsed: bool,
+    // as written in the rule, if at all
+    max_source_bytes: Option<usize>,
+    // whether a bare pattern (e.g., `strcpy($dst, _)`) is compiled as a block
+    auto_wrap: bool,
+    relations: Box<[Relation]>,
+    // bounds on the lines of the function enclosing a match
+    min_function_lines: Option<usize>,
+    max_function_lines: Option<usize>,
+    // guards: a match is dropped if one of these matches in its function
+    unless: OnceLock<Box<[QueryTree]>>,
+    escalate: Box<[Escalation]>,
+    // regex constraints with a transform, tested after matching
+    transformed: Box<[TransformedRegex]>,
+    // all regex constraints, one per variable, in the order of the rule
+    regex_constraints: Box<[RegexConstraint]>,
+    // as written in the rule, to serialize it
+    source: Box<str>,
+    regexes: Box<[RegexSource]>,
+    transform: Option<CaptureTransform>,
+    unless_source: Box<[String]>,
+}
+
+// NOTE: weggli tests regex constraints on captures while matching, and knows
+// nothing of transforms
+struct TransformedRegex {
+    variable: String,
+    negative: bool,
+    regex: Regex,
+    transform: CaptureTransform,
+    mangling: Mangling,
+}
+
+impl TransformedRegex {
+    fn is_match(&self, m: &QueryResult, source: &str) -> bool {
+        m.value(&self.variable, source).is_none_or(|captured| {
+            let matches = |text: &str| self.regex.is_match(&self.transform.apply(text));
+            let matched = matches(captured)
+                || self
+                    .mangling
+                    .demangle(captured)
+                    .is_some_and(|name| matches(&name));
+
+            matched != self.negative
+        })
+    }
+}
+
+// A regex constraint on a variable of a check (e.g., `$func` for
+// `func=^str`), as compiled: a constraint on several variables yields one per
+// variable.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize)]
+pub struct RegexConstraint {
+    variable: String,
+    negative: bool,
+    regex: String,
+}
+
+impl RegexConstraint {
+    pub fn variable(&self) -> &str {
+        &self.variable
+    }
+
+    // whether captures must not match (i.e., `var!=regex`)
+    pub fn is_negative(&self) -> bool {
+        self.negative
+    }
+
+    // the source of the regex, without surrounding whitespace
+    pub fn regex(&self) -> &str {
+        &self.regex
+    }
+}
+
+// a regex constraint as written in the rule: either `var=regex` (or
+// `a,b=regex`), or a regex and the variables it applies to; with its own
+// transform, if any
+struct RegexSource {
+    regex: String,
+    vars: Option<Box<[String]>>,
+    negative: bool,
+    transform: Option<CaptureTransform>,
+}
+
+impl RegexSource {
+    fn parse(&self) -> Result<(Vec<String>, bool, Regex), CheckError> {
+        let Some(vars) = &self.vars else {
+            if self.negative {
+                // the regex names its variables, and whether it is negative
+                return Err(RegexError::InvalidFormat(self.regex.to_owned()).into());
+            }
+            return parse_regex(&self.regex);
+        };
+
+        let vars = vars
+            .iter()
+            .map(|v| normalise_variable(v).ok_or_else(|| RegexError::InvalidFormat(v.to_owned())))
+            .collect::<Result<Vec<_>, _>>()?;
+        let regex = Regex::new(self.regex.trim()).map_err(RegexError::from)?;
+
+        Ok((vars, self.negative, regex))
+    }
+}
+
+impl Serialize for RegexSource {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        #[derive(Serialize)]
+        #[serde(untagged)]
+        enum RegexT<'a> {
+            Plain(&'a str),
+            Structured {
+                #[serde(skip_serializing_if = "Option::is_none")]
+                vars: Option<&'a [String]>,
+                regex: &'a str,
+                #[serde(skip_serializing_if = "std::ops::Not::not")]
+                negative: bool,
+                #[serde(flatten, skip_serializing_if = "Option::is_none")]
+                transform: Option<&'a CaptureTransform>,
+            },
+        }
+
+        match (&self.vars, &self.transform) {
+            (None, None) => RegexT::Plain(&self.regex),
+            (vars, transform) => RegexT::Structured {
+                vars: vars.as_deref(),
+                regex: &self.regex,
+                negative: self.negative,
+                transform: transform.as_ref(),
+            },
+        }
+        .serialize(serializer)
+    }
 }
 
 impl Checker {
@@ -335,8 +951,87 @@ impl Checker {
         self.language
     }
 
-    pub fn pattern(&self) -> &QueryTree {
-        &self.pattern
+    // the compiled pattern of the check; `ts-query` and text checks have none
+    pub fn pattern(&self) -> Option<&QueryTree> {
+        self.pattern.get()
+    }
+
+    // compiles the patterns of a checker read from a rule cache (see
+    // `precompiled`), which are not compiled when it is built
+    fn compile(&self) -> Result<(), CheckError> {
+        if self.pattern.get().is_none() && self.ts_query.is_none() && self.text.is_none() {
+            let (pattern, _) = build_pattern(
+                self.source.to_string(),
+                &self.regex_map,
+                self.language.is_cxx(),
+                self.auto_wrap,
+            )?;
+            let _ = self.pattern.set(pattern);
+        }
+
+        if self.unless.get().is_none() {
+            let unless = build_unless(&self.unless_source, self.language.is_cxx(), self.auto_wrap)?;
+            let _ = self.unless.set(unless);
+        }
+
+        Ok(())
+    }
+
+    // the tree-sitter query the check is written as, if any
+    pub fn ts_query(&self) -> Option<&str> {
+        self.ts_query.as_ref().map(TsQuery::source)
+    }
+
+    // the capture of the `ts-query` matches are reported at, if not all of
+    // them
+    pub fn report_capture(&self) -> Option<&str> {
+        self.ts_query.as_ref().and_then(TsQuery::report_capture)
+    }
+
+    pub fn match_regex(&self) -> Option<&str> {
+        self.text.as_ref().and_then(TextMatch::match_regex)
+    }
+
+    pub fn match_string(&self) -> Option<&str> {
+        self.text.as_ref().and_then(TextMatch::match_string)
+    }
+
+    // whether `match-regex` and `match-string` skip comments and literals
+    pub fn code_only(&self) -> bool {
+        self.text.as_ref().is_some_and(TextMatch::code_only)
+    }
+
+    // whether the check needs the tree of the source, i.e., all but text
+    // checks, unless they are filtered by their enclosing function
+    pub fn needs_parse(&self) -> bool {
+        self.text.is_none()
+            || self.min_function_lines.is_some()
+            || self.max_function_lines.is_some()
+            || !self.unless_source.is_empty()
+    }
+
+    fn unless(&self) -> &[QueryTree] {
+        self.unless
+            .get()
+            .map(|unless| &unless[..])
+            .unwrap_or_default()
+    }
+
+    // NOTE: identifiers and variables are derived from the compiled pattern
+    // when the checker is built (or read from the rule cache), and do not
+    // change afterwards
+
+    // the identifiers a source must contain for the pattern to possibly match
+    // (i.e., those the prefilter searches for), including those listed in
+    // the rule's `identifiers`
+    pub fn identifiers(&self) -> &[String] {
+        &self.identifiers
+    }
+
+    // the variables of the pattern (e.g., `$func`), which regex constraints
+    // may refer to
+    pub fn variables(&self) -> &HashSet<String> {
+        &self.variables
     }
 
     pub fn limit(&self) -> bool {
@@ -347,48 +1042,543 @@ impl Checker {
         self.unique
     }
 
+    pub fn unique_by(&self) -> Option<&UniqueBy> {
+        self.unique_by.as_ref()
+    }
+
+    // the transform applied before the regex constraints without one of their
+    // own
+    pub fn transform(&self) -> Option<&CaptureTransform> {
+        self.transform.as_ref()
+    }
+
+    // the regex constraints of the check, including those tested after a
+    // transform
+    pub fn regex_constraints(&self) -> &[RegexConstraint] {
+        &self.regex_constraints
+    }
+
+    pub fn constraints(&self) -> &[NumericConstraint] {
+        &self.constraints
+    }
+
+    pub fn keep_unparsed(&self) -> bool {
+        self.keep_unparsed
+    }
+
+    // the size of the largest source the check is run on (`MAX_SOURCE_BYTES`,
+    // unless the rule sets `max-source-bytes`), as a guard against patterns
+    // whose matching blows up on large sources
+    pub fn max_source_bytes(&self) -> usize {
+        self.max_source_bytes.unwrap_or(MAX_SOURCE_BYTES)
+    }
+
+    pub fn relations(&self) -> &[Relation] {
+        &self.relations
+    }
+
+    // matches within functions of fewer lines are dropped, e.g., to skip small
+    // wrappers; matches outside any function are kept
+    pub fn min_function_lines(&self) -> Option<usize> {
+        self.min_function_lines
+    }
+
+    // matches within functions of more lines are dropped
+    pub fn max_function_lines(&self) -> Option<usize> {
+        self.max_function_lines
+    }
+
+    // the `unless-in-function` patterns, as written in the rule
+    pub fn unless_in_function(&self) -> &[String] {
+        &self.unless_source
+    }
+
+    pub fn escalate(&self) -> &[Escalation] {
+        &self.escalate
+    }
+
+    // the severity of the first escalation that applies to the match, if any
+    pub fn escalation(&self, m: &QueryResult, source: &str) -> Option<Severity> {
+        self.escalate
+            .iter()
+            .find(|e| e.applies(m, source))
+            .map(Escalation::severity)
+    }
+
     pub fn can_match(&self, source: &str) -> bool {
-        self.identifiers
+        self.finders
             .iter()
-            .all(|ident| memmem::find(source.as_ref(), ident.as_ref()).is_some())
+            .all(|finder| finder.find(source.as_bytes()).is_some())
     }
 
     pub fn check_match(&self, tree: &Tree, source: &str) -> Vec<QueryResult> {
-        let matches = self.pattern.matches(tree.root_node(), source);
+        self.check(Some(tree), source)
+    }
+
+    // NOTE: only checks that need it (see `needs_parse`) are given a tree
+    pub(crate) fn check(&self, tree: Option<&Tree>, source: &str) -> Vec<QueryResult> {
+        self.candidates(tree, source)
+            .into_iter()
+            .filter_map(|(m, dropped)| dropped.is_none().then_some(m))
+            .collect()
+    }
+
+    // the matches of the pattern (or query, or regex), and the first filter
+    // of the check that dropped each, if any
+    pub(crate) fn candidates(
+        &self,
+        tree: Option<&Tree>,
+        source: &str,
+    ) -> Vec<(QueryResult, Option<CandidateFilter>)> {
+        let matches = match (&self.text, &self.ts_query, tree) {
+            (Some(text), _, _) => text.matches(source),
+            (None, Some(query), Some(tree)) => query.matches(tree, source),
+            (None, None, Some(tree)) => match self.pattern() {
+                Some(pattern) => pattern.matches(tree.root_node(), source),
+                None => Vec::with_capacity(0),
+            },
+            (None, _, None) => Vec::with_capacity(0),
+        };
         if matches.is_empty() {
             return Vec::with_capacity(0);
         }
 
+        // NOTE: the matches of text checks have no distinct captures to
+        // compare, and `limit` keeps the first match at each offset
         let check_unique = |m: &QueryResult| {
-            !self.unique || {
+            !self.unique || self.text.is_some() || {
                 let mut seen = FxHashSet::default();
                 m.vars
                     .keys()
-                    .filter_map(|k| m.value(k, &source))
+                    .filter_map(|k| m.value(k, source))
                     .all(|x| seen.insert(x))
             }
         };
 
+        // NOTE: `unique-by` in wider scopes is enforced by the matcher
+        let check_unique_by = |m: &QueryResult| match &self.unique_by {
+            Some(by) if by.scope == UniqueScope::Match => {
+                let mut seen = FxHashSet::default();
+                by.values(m, source).into_iter().all(|x| seen.insert(x))
+            }
+            _ => true,
+        };
+
+        let check_transformed =
+            |m: &QueryResult| self.transformed.iter().all(|r| r.is_match(m, source));
+
+        let check_constraints = |m: &QueryResult| {
+            self.constraints.iter().all(|c| {
+                m.value(c.variable(), source)
+                    .and_then(|captured| c.holds(captured))
+                    .unwrap_or(self.keep_unparsed)
+            })
+        };
+
+        let check_relations = |m: &QueryResult| {
+            self.relations
+                .iter()
+                .all(|r| r.holds(m.value(r.left(), source), m.value(r.right(), source)))
+        };
+
+        // the guards found in each scope, by its range
+        let mut guards = FxHashMap::<Range<usize>, Vec<QueryResult>>::default();
+        let mut check_unless = |m: &QueryResult| {
+            self.unless_source.is_empty() || {
+                let Some(scope) = tree.and_then(|tree| enclosing_scope(tree, match_span(m))) else {
+                    return true;
+                };
+
+                let found = guards.entry(scope.byte_range()).or_insert_with(|| {
+                    self.unless()
+                        .iter()
+                        .flat_map(|guard| guard.matches(scope, source))
+                        .collect()
+                });
+
+                !found.iter().any(|guard| {
+                    guard.vars.keys().all(|k| {
+                        m.value(k, source)
+                            .is_none_or(|v| guard.value(k, source) == Some(v))
+                    })
+                })
+            }
+        };
+
+        let check_function_lines = |m: &QueryResult| {
+            let Some(tree) = tree else {
+                return true;
+            };
+
+            (self.min_function_lines.is_none() && self.max_function_lines.is_none())
+                || function_lines_within(
+                    tree,
+                    match_span(m),
+                    self.min_function_lines,
+                    self.max_function_lines,
+                )
+        };
+
         let mut skip_set = FxHashSet::default();
         let mut check_limit = |m: &QueryResult| !self.limit || skip_set.insert(m.start_offset());
 
+        // NOTE: filters are applied in order, up to the first that drops the
+        // match; in particular, `limit` only considers the matches the others
+        // kept
         matches
             .into_iter()
-            .filter(|v| check_unique(v) && check_limit(v))
+            .map(|v| {
+                let dropped = if !check_transformed(&v) {
+                    Some(CandidateFilter::Regex)
+                } else if !check_unique(&v) {
+                    Some(CandidateFilter::Unique)
+                } else if !check_unique_by(&v) {
+                    Some(CandidateFilter::UniqueBy)
+                } else if !check_constraints(&v) {
+                    Some(CandidateFilter::Constraints)
+                } else if !check_relations(&v) {
+                    Some(CandidateFilter::Relations)
+                } else if !check_function_lines(&v) {
+                    Some(CandidateFilter::FunctionLines)
+                } else if !check_unless(&v) {
+                    Some(CandidateFilter::UnlessInFunction)
+                } else if !check_limit(&v) {
+                    Some(CandidateFilter::Limit)
+                } else {
+                    None
+                };
+                (v, dropped)
+            })
             .collect()
     }
-}
-
-impl<'de> Deserialize<'de> for Checker {
-    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
-    where
-        D: serde::Deserializer<'de>,
-    {
-        let checker = CheckerT::deserialize(deserializer)?
-            .try_into()
-            .map_err(<D::Error as serde::de::Error>::custom)?;
 
-        Ok(checker)
+    // the pattern, query, or regex (or string) of the check, as written in
+    // the rule
+    pub fn raw_pattern(&self) -> &str {
+        self.ts_query()
+            .or_else(|| self.match_regex())
+            .or_else(|| self.match_string())
+            .unwrap_or(&self.source)
+    }
+
+    // whether the regex constraint holds for the match, or `None` if its
+    // variable was not captured; constraints with a transform (or of rules
+    // with a `mangling`) are tested as after matching
+    pub(crate) fn regex_constraint_holds(
+        &self,
+        constraint: &RegexConstraint,
+        m: &QueryResult,
+        source: &str,
+    ) -> Option<bool> {
+        let captured = m.value(&constraint.variable, source)?;
+
+        match self.transformed_regex(constraint) {
+            Some(transformed) => Some(transformed.is_match(m, source)),
+            None => Regex::new(&constraint.regex)
+                .ok()
+                .map(|regex| regex.is_match(captured) != constraint.negative),
+        }
+    }
+
+    // whether the regex constraint is tested after matching (see
+    // `TransformedRegex`)
+    pub(crate) fn is_transformed(&self, constraint: &RegexConstraint) -> bool {
+        self.transformed_regex(constraint).is_some()
+    }
+
+    fn transformed_regex(&self, constraint: &RegexConstraint) -> Option<&TransformedRegex> {
+        self.transformed.iter().find(|t| {
+            t.variable == constraint.variable
+                && t.negative == constraint.negative
+                && t.regex.as_str() == constraint.regex
+        })
+    }
+}
+
+// The filter of a check that dropped a candidate, i.e., a match of its
+// pattern (or query, or regex) that is not reported (see
+// `RuleMatcher::explain`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum CandidateFilter {
+    Regex,
+    Unique,
+    UniqueBy,
+    Constraints,
+    Relations,
+    FunctionLines,
+    UnlessInFunction,
+    Limit,
+}
+
+impl Display for CandidateFilter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Regex => "regex",
+            Self::Unique => "unique",
+            Self::UniqueBy => "unique-by",
+            Self::Constraints => "constraints",
+            Self::Relations => "relations",
+            Self::FunctionLines => "function-lines",
+            Self::UnlessInFunction => "unless-in-function",
+            Self::Limit => "limit",
+        })
+    }
+}
+
+// The function definition containing `range` or, at global scope, the
+// smallest node spanning it; `unless-in-function` guards must match within
+// it, binding the variables they share with the pattern to the same values.
+fn enclosing_scope(tree: &Tree, range: Range<usize>) -> Option<Node<'_>> {
+    let node = tree
+        .root_node()
+        .descendant_for_byte_range(range.start, range.end)?;
+
+    let mut function = Some(node);
+    while let Some(n) = function {
+        if n.kind() == "function_definition" {
+            return Some(n);
+        }
+        function = n.parent();
+    }
+
+    Some(node)
+}
+
+// Where the values of the `unique-by` variables of a check must differ: among
+// themselves within each match (as with `unique`, but only for these
+// variables), or from those of the earlier matches of the check (in location
+// order) within each source or within each scanning call (e.g., a directory
+// scan), so that, e.g., each banned function is reported once per file.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum UniqueScope {
+    Match,
+    #[default]
+    Source,
+    Scan,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize)]
+pub struct UniqueBy {
+    variables: Box<[String]>,
+    scope: UniqueScope,
+}
+
+impl UniqueBy {
+    fn new(by: UniqueByT, pattern_variables: &HashSet<String>) -> Result<Self, CheckError> {
+        let (variables, scope) = match by {
+            UniqueByT::Variables(variables) => (variables, UniqueScope::default()),
+            UniqueByT::Scoped { variables, scope } => (variables, scope),
+        };
+
+        // as for regex constraints, `func` stands for `$func`
+        let variables = Vec::from(variables)
+            .into_iter()
+            .map(|v| {
+                let v = v.trim();
+                let v = if v.starts_with('$') {
+                    v.to_owned()
+                } else {
+                    format!("${v}")
+                };
+
+                if pattern_variables.contains(&v) {
+                    Ok(v)
+                } else {
+                    Err(CheckError::InvalidQueryVariable(v))
+                }
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Self {
+            variables: variables.into_boxed_slice(),
+            scope,
+        })
+    }
+
+    // e.g., `$func`
+    pub fn variables(&self) -> &[String] {
+        &self.variables
+    }
+
+    pub fn scope(&self) -> UniqueScope {
+        self.scope
+    }
+
+    // the values of the variables in `result`, in order (unbound variables
+    // are skipped)
+    pub fn values<'s>(&self, result: &QueryResult, source: &'s str) -> Vec<&'s str> {
+        self.variables
+            .iter()
+            .filter_map(|v| result.value(v, source))
+            .collect()
+    }
+}
+
+// NOTE: rules serialize as they are written (e.g., with the key
+// `check-patterns`), so that they can be loaded again; fields with their
+// default values are omitted, and tags are sorted
+impl Serialize for Rule {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        #[derive(Serialize)]
+        struct RuleT<'a> {
+            id: &'a str,
+            #[serde(skip_serializing_if = "str::is_empty")]
+            author: &'a str,
+            #[serde(skip_serializing_if = "str::is_empty")]
+            description: &'a str,
+            #[serde(skip_serializing_if = "Severity::is_none")]
+            severity: Severity,
+            #[serde(skip_serializing_if = "Vec::is_empty")]
+            tags: Vec<&'a str>,
+            #[serde(skip_serializing_if = "PathFilter::is_empty")]
+            paths: &'a PathFilter,
+            #[serde(rename = "max-file-size", skip_serializing_if = "Option::is_none")]
+            max_file_size: Option<&'a FileSize>,
+            #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+            metadata: &'a BTreeMap<String, serde_json::Value>,
+            #[serde(skip_serializing_if = "Mangling::is_none")]
+            mangling: Mangling,
+            #[serde(rename = "check-patterns")]
+            checks: &'a [Checker],
+            #[serde(skip_serializing_if = "Option::is_none")]
+            condition: Option<&'a str>,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            report: Option<&'a str>,
+        }
+
+        let mut tags = self.tags.iter().map(String::as_str).collect::<Vec<_>>();
+        tags.sort();
+
+        RuleT {
+            id: &self.id,
+            author: &self.author,
+            description: &self.description,
+            severity: self.severity,
+            tags,
+            paths: &self.paths,
+            max_file_size: self.max_file_size.as_ref(),
+            metadata: &self.metadata,
+            mangling: self.mangling,
+            checks: &self.checks,
+            condition: self.condition.as_ref().map(Condition::as_str),
+            report: self.condition.as_ref().and_then(Condition::report),
+        }
+        .serialize(serializer)
+    }
+}
+
+impl Debug for Checker {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Checker")
+            .field("name", &self.name)
+            .field("language", &self.language)
+            .field("pattern", &self.source)
+            .field("ts_query", &self.ts_query())
+            .field("match_regex", &self.match_regex())
+            .field("match_string", &self.match_string())
+            .field("regex_constraints", &self.regex_constraints)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Serialize for Checker {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        #[derive(Serialize)]
+        struct CheckerT<'a> {
+            name: &'a str,
+            #[serde(skip_serializing_if = "CheckerLanguage::is_c")]
+            language: CheckerLanguage,
+            #[serde(rename = "regex", skip_serializing_if = "<[_]>::is_empty")]
+            regexes: &'a [RegexSource],
+            #[serde(skip_serializing_if = "Option::is_none")]
+            transform: Option<&'a CaptureTransform>,
+            #[serde(skip_serializing_if = "str::is_empty")]
+            pattern: &'a str,
+            #[serde(rename = "ts-query", skip_serializing_if = "Option::is_none")]
+            ts_query: Option<&'a str>,
+            #[serde(rename = "report-capture", skip_serializing_if = "Option::is_none")]
+            report_capture: Option<&'a str>,
+            #[serde(rename = "match-regex", skip_serializing_if = "Option::is_none")]
+            match_regex: Option<&'a str>,
+            #[serde(rename = "match-string", skip_serializing_if = "Option::is_none")]
+            match_string: Option<&'a str>,
+            #[serde(rename = "code-only", skip_serializing_if = "std::ops::Not::not")]
+            code_only: bool,
+            #[serde(skip_serializing_if = "<[_]>::is_empty")]
+            identifiers: &'a [String],
+            #[serde(skip_serializing_if = "std::ops::Not::not")]
+            limit: bool,
+            #[serde(skip_serializing_if = "std::ops::Not::not")]
+            unique: bool,
+            #[serde(rename = "unique-by", skip_serializing_if = "Option::is_none")]
+            unique_by: Option<&'a UniqueBy>,
+            #[serde(skip_serializing_if = "Vec::is_empty")]
+            constraints: Vec<String>,
+            #[serde(rename = "keep-unparsed", skip_serializing_if = "std::ops::Not::not")]
+            keep_unparsed: bool,
+            #[serde(rename = "max-source-bytes", skip_serializing_if = "Option::is_none")]
+            max_source_bytes: Option<usize>,
+            #[serde(rename = "auto-wrap", skip_serializing_if = "is_default_auto_wrap")]
+            auto_wrap: bool,
+            #[serde(skip_serializing_if = "Vec::is_empty")]
+            relations: Vec<String>,
+            #[serde(rename = "min-function-lines", skip_serializing_if = "Option::is_none")]
+            min_function_lines: Option<usize>,
+            #[serde(rename = "max-function-lines", skip_serializing_if = "Option::is_none")]
+            max_function_lines: Option<usize>,
+            #[serde(rename = "unless-in-function", skip_serializing_if = "<[_]>::is_empty")]
+            unless_in_function: &'a [String],
+            #[serde(skip_serializing_if = "<[_]>::is_empty")]
+            escalate: &'a [Escalation],
+        }
+
+        CheckerT {
+            name: &self.name,
+            language: self.language,
+            regexes: &self.regexes,
+            transform: self.transform.as_ref(),
+            pattern: &self.source,
+            ts_query: self.ts_query(),
+            report_capture: self.report_capture(),
+            match_regex: self.match_regex(),
+            match_string: self.match_string(),
+            code_only: self.code_only(),
+            identifiers: &self.explicit_identifiers,
+            limit: self.limit,
+            unique: self.unique,
+            unique_by: self.unique_by.as_ref(),
+            constraints: self.constraints.iter().map(ToString::to_string).collect(),
+            keep_unparsed: self.keep_unparsed,
+            max_source_bytes: self.max_source_bytes,
+            auto_wrap: self.auto_wrap,
+            relations: self.relations.iter().map(ToString::to_string).collect(),
+            min_function_lines: self.min_function_lines,
+            max_function_lines: self.max_function_lines,
+            unless_in_function: &self.unless_source,
+            escalate: &self.escalate,
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Checker {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let checker = CheckerT::deserialize(deserializer)?
+            .try_into()
+            .map_err(<D::Error as serde::de::Error>::custom)?;
+
+        Ok(checker)
     }
 }
 
@@ -399,6 +1589,33 @@ enum OneOrMany<T> {
     One(T),
 }
 
+impl<T> OneOrMany<T> {
+    fn len(&self) -> usize {
+        match self {
+            Self::One(_) => 1,
+            Self::Many(vs) => vs.len(),
+        }
+    }
+
+    fn iter(&self) -> impl Iterator<Item = &T> {
+        let (one, many) = match self {
+            Self::One(v) => (Some(v), None),
+            Self::Many(vs) => (None, Some(vs)),
+        };
+        one.into_iter()
+            .chain(many.into_iter().flat_map(|vs| vs.iter()))
+    }
+
+    fn iter_mut(&mut self) -> impl Iterator<Item = &mut T> {
+        let (one, many) = match self {
+            Self::One(v) => (Some(v), None),
+            Self::Many(vs) => (None, Some(vs)),
+        };
+        one.into_iter()
+            .chain(many.into_iter().flat_map(|vs| vs.iter_mut()))
+    }
+}
+
 impl<T> From<OneOrMany<T>> for Vec<T> {
     fn from(value: OneOrMany<T>) -> Self {
         match value {
@@ -414,94 +1631,498 @@ struct CheckerT {
     name: String,
     #[serde(default)]
     language: CheckerLanguage,
+    #[serde(default)]
     pattern: String,
+    #[serde(rename = "ts-query", alias = "ts_query", default)]
+    ts_query: Option<String>,
+    #[serde(rename = "report-capture", alias = "report_capture", default)]
+    report_capture: Option<String>,
+    #[serde(rename = "match-regex", alias = "match_regex", default)]
+    match_regex: Option<String>,
+    #[serde(rename = "match-string", alias = "match_string", default)]
+    match_string: Option<String>,
+    #[serde(rename = "code-only", alias = "code_only", default)]
+    code_only: bool,
+    #[serde(alias = "identifier", default)]
+    identifiers: Option<OneOrMany<String>>,
     #[serde(alias = "regex", default)]
-    regexes: Option<OneOrMany<String>>,
+    regexes: Option<OneOrMany<RegexT>>,
+    #[serde(default)]
+    transform: Option<TransformT>,
     #[serde(default)]
     limit: bool,
     #[serde(default)]
     unique: bool,
+    #[serde(rename = "unique-by", alias = "unique_by", default)]
+    unique_by: Option<UniqueByT>,
+    #[serde(alias = "constraint", default)]
+    constraints: Option<OneOrMany<String>>,
+    #[serde(rename = "keep-unparsed", alias = "keep_unparsed", default)]
+    keep_unparsed: bool,
+    #[serde(rename = "max-source-bytes", alias = "max_source_bytes", default)]
+    max_source_bytes: Option<usize>,
+    #[serde(
+        rename = "auto-wrap",
+        alias = "auto_wrap",
+        default = "default_auto_wrap"
+    )]
+    auto_wrap: bool,
+    #[serde(alias = "relation", default)]
+    relations: Option<OneOrMany<String>>,
+    #[serde(rename = "min-function-lines", alias = "min_function_lines", default)]
+    min_function_lines: Option<usize>,
+    #[serde(rename = "max-function-lines", alias = "max_function_lines", default)]
+    max_function_lines: Option<usize>,
+    #[serde(rename = "unless-in-function", alias = "unless_in_function", default)]
+    unless_in_function: Option<OneOrMany<String>>,
+    #[serde(default)]
+    escalate: Option<OneOrMany<EscalationT>>,
+    // that of the rule, which is not written per check
+    #[serde(skip)]
+    mangling: Mangling,
+}
+
+// a `var=regex` constraint, or one written as a map, e.g., with the
+// variables apart from the regex or with a transform of its own
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum RegexT {
+    Plain(String),
+    Structured {
+        regex: String,
+        #[serde(default)]
+        vars: Option<OneOrMany<String>>,
+        #[serde(default)]
+        negative: bool,
+        #[serde(flatten)]
+        transform: TransformT,
+    },
+}
+
+// either a list of variables (in `source` scope), or the variables and a
+// scope
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum UniqueByT {
+    Variables(OneOrMany<String>),
+    Scoped {
+        variables: OneOrMany<String>,
+        #[serde(default)]
+        scope: UniqueScope,
+    },
 }
 
 fn default_check_name() -> String {
     String::from("default")
 }
 
+fn default_auto_wrap() -> bool {
+    true
+}
+
+fn is_default_auto_wrap(auto_wrap: &bool) -> bool {
+    *auto_wrap == default_auto_wrap()
+}
+
 fn validate_checker(checker: CheckerT) -> Result<CheckerT, CheckError> {
     if checker.name.is_empty() {
         return Err(CheckError::NoCheckName);
     }
 
+    let given = [
+        !checker.pattern.is_empty(),
+        checker.ts_query.is_some(),
+        checker.match_regex.is_some(),
+        checker.match_string.is_some(),
+    ];
+    match given.into_iter().filter(|given| *given).count() {
+        0 => return Err(CheckError::NoCheckPatterns),
+        1 => (),
+        _ => return Err(CheckError::MultiplePatterns),
+    }
+
+    if checker.report_capture.is_some() && checker.ts_query.is_none() {
+        return Err(CheckError::ReportCaptureWithoutQuery);
+    }
+
+    if checker.code_only && checker.match_regex.is_none() && checker.match_string.is_none() {
+        return Err(CheckError::CodeOnlyWithoutText);
+    }
+
     Ok(checker)
 }
 
+// NOTE: with `auto_wrap`, a pattern without braces (i.e., a bare statement or
+// expression, such as `strcpy($dst, _)`) is compiled as a block, `{ ...; }`.
+// weggli attempts the same, but only when the bare pattern parses, and its
+// errors then refer to the pattern as written
+#[cfg(test)]
+thread_local! {
+    static PATTERNS_BUILT: std::cell::Cell<usize> = const { std::cell::Cell::new(0) };
+}
+
 fn build_pattern(
     input: String,
     constraints: &RegexMap,
     cxx: bool,
+    auto_wrap: bool,
 ) -> Result<(QueryTree, HashSet<String>), CheckError> {
-    let pattern = weggli::parse_search_pattern(&input, cxx, false, Some(constraints.clone()))?;
+    #[cfg(test)]
+    PATTERNS_BUILT.with(|n| n.set(n.get() + 1));
+
+    let pattern = match wrap_pattern(&input).filter(|_| auto_wrap) {
+        Some(wrapped) => {
+            weggli::parse_search_pattern(&wrapped, cxx, false, Some(constraints.clone())).map_err(
+                |source| CheckError::WrappedPattern {
+                    pattern: input,
+                    wrapped,
+                    source,
+                },
+            )?
+        }
+        None => weggli::parse_search_pattern(&input, cxx, false, Some(constraints.clone()))?,
+    };
     let variables = pattern.variables();
 
     Ok((pattern, variables))
 }
 
-// NOTE: this is from weggli! maybe replace with nom + regex
-fn build_regex_mapping(regexes: Option<OneOrMany<String>>) -> Result<RegexMap, CheckError> {
-    let mut result = HashMap::new();
+// e.g., `{ strcpy($dst, _); }` for `strcpy($dst, _)`, or `None` for a pattern
+// with braces (a block, a function, or a type)
+fn wrap_pattern(pattern: &str) -> Option<String> {
+    let pattern = pattern.trim();
+    if pattern.is_empty() || pattern.contains(['{', '}']) {
+        return None;
+    }
 
-    let Some(regexes) = regexes.map(Vec::from) else {
-        return Ok(RegexMap::new(result));
-    };
+    let semicolon = if pattern.ends_with(';') { "" } else { ";" };
+    Some(format!("{{ {pattern}{semicolon} }}"))
+}
 
-    for r in regexes {
-        let (var, raw_regex) = r
-            .split_once('=')
-            .ok_or_else(|| RegexError::InvalidFormat(r.to_owned()))?;
+fn build_unless(
+    patterns: &[String],
+    cxx: bool,
+    auto_wrap: bool,
+) -> Result<Box<[QueryTree]>, CheckError> {
+    patterns
+        .iter()
+        .map(|p| build_pattern(p.to_owned(), &RegexMap::new(HashMap::new()), cxx, auto_wrap))
+        .map(|r| r.map(|(pattern, _)| pattern))
+        .collect()
+}
 
-        let var = var.trim();
-        let raw_regex = raw_regex.trim();
+// the identifiers and variables of the checker being built, if it is read
+// from a rule cache, which recorded them when the rule was first loaded
+#[cfg(feature = "std-fs")]
+fn precompiled() -> Option<(Box<[String]>, HashSet<String>)> {
+    cache::take_compiled()
+}
 
-        let mut normalised_var = if var.starts_with('$') {
-            var.to_owned()
-        } else {
-            format!("${var}")
-        };
+#[cfg(not(feature = "std-fs"))]
+fn precompiled() -> Option<(Box<[String]>, HashSet<String>)> {
+    None
+}
 
-        let negative = normalised_var.ends_with('!');
+// NOTE: constraints on several variables share their compiled regex
+fn build_regex_mapping(regexes: &[(Vec<String>, bool, Regex)]) -> RegexMap {
+    let mut result = HashMap::new();
 
-        if negative {
-            normalised_var.pop(); // remove !
+    for (vars, negative, regex) in regexes {
+        for var in vars {
+            result.insert(var.to_owned(), (*negative, regex.clone()));
         }
+    }
+
+    RegexMap::new(result)
+}
+
+// the variables of a `var=regex` (or `a,b=regex`) constraint, whether it is
+// negative (`var!=regex`), and the regex
+//
+// NOTE: this is from weggli! maybe replace with nom + regex
+fn parse_regex(r: &str) -> Result<(Vec<String>, bool, Regex), CheckError> {
+    let (vars, raw_regex) = r
+        .split_once('=')
+        .ok_or_else(|| RegexError::InvalidFormat(r.to_owned()))?;
+
+    let vars = vars.trim_end();
+    let negative = vars.ends_with('!');
+    let vars = vars.strip_suffix('!').unwrap_or(vars);
+
+    let vars = vars
+        .split(',')
+        .map(|v| normalise_variable(v).ok_or_else(|| RegexError::InvalidFormat(r.to_owned())))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let regex = Regex::new(raw_regex.trim()).map_err(RegexError::from)?;
+
+    Ok((vars, negative, regex))
+}
+
+// e.g., `$func` for both `func` and `$func`
+fn normalise_variable(var: &str) -> Option<String> {
+    let var = var.trim();
+    if var.is_empty() || var == "$" {
+        return None;
+    }
+
+    Some(if var.starts_with('$') {
+        var.to_owned()
+    } else {
+        format!("${var}")
+    })
+}
+
+#[cfg(test)]
+thread_local! {
+    static FINDERS_BUILT: std::cell::Cell<usize> = const { std::cell::Cell::new(0) };
+}
+
+// NOTE: the finders own their needles, so that they are built once per
+// checker rather than once per source
+fn build_finders(identifiers: &[String]) -> Box<[Finder<'static>]> {
+    let mut identifiers = identifiers.iter().collect::<Vec<_>>();
+    identifiers.sort_by_key(|ident| std::cmp::Reverse(ident.len()));
 
-        let regex = Regex::new(raw_regex).map_err(RegexError::from)?;
+    identifiers
+        .into_iter()
+        .map(|ident| {
+            #[cfg(test)]
+            FINDERS_BUILT.with(|n| n.set(n.get() + 1));
 
-        result.insert(normalised_var, (negative, regex));
+            Finder::new(ident.as_bytes()).into_owned()
+        })
+        .collect()
+}
+
+// the identifiers of the pattern (or query), and those the rule lists in
+// `identifiers`, e.g., for queries whose literals cannot be derived
+fn with_explicit(mut identifiers: Vec<String>, explicit: &[String]) -> Box<[String]> {
+    for ident in explicit {
+        if !identifiers.contains(ident) {
+            identifiers.push(ident.to_owned());
+        }
     }
 
-    Ok(RegexMap::new(result))
+    identifiers.into_boxed_slice()
 }
 
 impl TryFrom<CheckerT> for Checker {
     type Error = CheckError;
 
     fn try_from(c: CheckerT) -> Result<Self, Self::Error> {
-        let regexes = build_regex_mapping(c.regexes)?;
-        let (pattern, variables) = build_pattern(c.pattern.into(), &regexes, c.language.is_cxx())?;
+        let transform = c.transform.map(CaptureTransform::try_from).transpose()?;
+
+        let sources = c
+            .regexes
+            .map(Vec::from)
+            .unwrap_or_default()
+            .into_iter()
+            .map(|r| {
+                Ok(match r {
+                    RegexT::Plain(regex) => RegexSource {
+                        regex,
+                        vars: None,
+                        negative: false,
+                        transform: None,
+                    },
+                    RegexT::Structured {
+                        regex,
+                        vars,
+                        negative,
+                        transform,
+                    } => RegexSource {
+                        regex,
+                        vars: vars.map(|vars| Vec::from(vars).into_boxed_slice()),
+                        negative,
+                        transform: (!transform.is_empty())
+                            .then(|| transform.try_into())
+                            .transpose()?,
+                    },
+                })
+            })
+            .collect::<Result<Vec<_>, CheckError>>()?;
+
+        // constraints with a transform (of their own, or of the check), and
+        // all constraints of rules that see through mangled names or of checks
+        // without a pattern, are tested after matching, the others by weggli
+        let weggli = c.ts_query.is_none() && c.match_regex.is_none() && c.match_string.is_none();
+        let mut plain = Vec::new();
+        let mut transformed = Vec::new();
+
+        let mut constrained = Vec::new();
+        let mut regex_constraints = Vec::new();
+
+        for r in &sources {
+            let (vars, negative, regex) = r.parse()?;
+            constrained.extend(vars.iter().cloned());
+            regex_constraints.extend(vars.iter().map(|variable| RegexConstraint {
+                variable: variable.to_owned(),
+                negative,
+                regex: regex.as_str().to_owned(),
+            }));
+
+            match r.transform.as_ref().or(transform.as_ref()) {
+                None if c.mangling.is_none() && weggli => plain.push((vars, negative, regex)),
+                t => transformed.extend(vars.into_iter().map(|variable| TransformedRegex {
+                    variable,
+                    negative,
+                    regex: regex.clone(),
+                    transform: t.cloned().unwrap_or_default(),
+                    mangling: c.mangling,
+                })),
+            }
+        }
+
+        let regexes = build_regex_mapping(&plain);
+
+        let ts_query = c
+            .ts_query
+            .as_deref()
+            .map(|query| {
+                TsQuery::new(
+                    &c.name,
+                    query,
+                    c.report_capture.as_deref(),
+                    c.language.is_cxx(),
+                )
+            })
+            .transpose()?;
+        let text = match (&c.match_regex, &c.match_string) {
+            (Some(regex), _) => Some(TextMatch::regex(regex, c.code_only)?),
+            (None, Some(string)) => Some(TextMatch::string(string, c.code_only)?),
+            (None, None) => None,
+        };
+        let explicit_identifiers = c.identifiers.map(Vec::from).unwrap_or_default();
+
+        // NOTE: unlike patterns, queries and regexes are compiled for cached
+        // rules too
+        let precompiled = precompiled();
+        let cached = precompiled.is_some();
+
+        let (pattern, identifiers, variables) = match (precompiled, &ts_query, &text) {
+            (Some((identifiers, variables)), _, _) => (OnceLock::new(), identifiers, variables),
+            (None, Some(query), _) => (
+                OnceLock::new(),
+                with_explicit(query.identifiers(), &explicit_identifiers),
+                query.variables(),
+            ),
+            (None, None, Some(text)) => (
+                OnceLock::new(),
+                with_explicit(text.identifiers(), &explicit_identifiers),
+                text.variables(),
+            ),
+            (None, None, None) => {
+                let (pattern, variables) = build_pattern(
+                    c.pattern.clone(),
+                    &regexes,
+                    c.language.is_cxx(),
+                    c.auto_wrap,
+                )?;
+                let identifiers = with_explicit(pattern.identifiers(), &explicit_identifiers);
+                (OnceLock::from(pattern), identifiers, variables)
+            }
+        };
+
+        // in the order of the rule, to report the first missing variable
+        if let Some(v) = constrained.into_iter().find(|v| !variables.contains(v)) {
+            return Err(CheckError::InvalidQueryVariable(v));
+        }
+
+        let unique_by = c
+            .unique_by
+            .map(|by| UniqueBy::new(by, &variables))
+            .transpose()?;
+
+        let constraints = c
+            .constraints
+            .map(Vec::from)
+            .unwrap_or_default()
+            .iter()
+            .map(|c| NumericConstraint::parse(c))
+            .collect::<Result<Box<[_]>, _>>()?;
+
+        if let Some(c) = constraints
+            .iter()
+            .find(|c| !variables.contains(c.variable()))
+        {
+            return Err(CheckError::InvalidQueryVariable(c.variable().to_owned()));
+        }
+
+        let relations = c
+            .relations
+            .map(Vec::from)
+            .unwrap_or_default()
+            .iter()
+            .map(|r| Relation::parse(r))
+            .collect::<Result<Box<[_]>, _>>()?;
 
-        for v in regexes.variables() {
-            if !variables.contains(v) {
-                return Err(CheckError::InvalidQueryVariable(v.to_owned()));
+        if let Some(v) = relations
+            .iter()
+            .flat_map(|r| [r.left(), r.right()])
+            .find(|v| !variables.contains(*v))
+        {
+            return Err(CheckError::InvalidQueryVariable(v.to_owned()));
+        }
+
+        if let (Some(min), Some(max)) = (c.min_function_lines, c.max_function_lines) {
+            if min > max {
+                return Err(CheckError::InvalidFunctionLines(min, max));
             }
         }
 
+        let unless_source = c.unless_in_function.map(Vec::from).unwrap_or_default();
+        let unless = if cached {
+            OnceLock::new()
+        } else {
+            OnceLock::from(build_unless(
+                &unless_source,
+                c.language.is_cxx(),
+                c.auto_wrap,
+            )?)
+        };
+
+        let escalate = c
+            .escalate
+            .map(Vec::from)
+            .unwrap_or_default()
+            .into_iter()
+            .map(Escalation::try_from)
+            .collect::<Result<Box<[_]>, _>>()?;
+
+        if let Some(e) = escalate.iter().find(|e| !variables.contains(e.variable())) {
+            return Err(CheckError::InvalidQueryVariable(e.variable().to_owned()));
+        }
+
         Ok(Self {
             name: Arc::from(c.name),
             language: c.language,
-            identifiers: pattern.identifiers().into_boxed_slice(),
+            finders: build_finders(&identifiers),
+            identifiers,
+            explicit_identifiers: explicit_identifiers.into_boxed_slice(),
+            variables,
             pattern,
+            ts_query,
+            text,
+            regex_map: regexes,
             limit: c.limit,
             unique: c.unique,
+            unique_by,
+            constraints,
+            keep_unparsed: c.keep_unparsed,
+            max_source_bytes: c.max_source_bytes,
+            auto_wrap: c.auto_wrap,
+            relations,
+            min_function_lines: c.min_function_lines,
+            max_function_lines: c.max_function_lines,
+            unless,
+            escalate,
+            transformed: transformed.into_boxed_slice(),
+            regex_constraints: regex_constraints.into_boxed_slice(),
+            source: c.pattern.into_boxed_str(),
+            regexes: sources.into_boxed_slice(),
+            transform,
+            unless_source: unless_source.into_boxed_slice(),
         })
     }
 }
@@ -538,6 +2159,7 @@ impl TryFrom<OneOrMany<CheckerT>> for Vec<Checker> {
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::testing::{self, TempDir};
 
     #[test]
     fn test_rule_parse() -> Result<(), RuleError> {
@@ -590,4 +2212,451 @@ check-patterns:
 
         Ok(())
     }
+
+    #[test]
+    fn test_can_match() -> Result<(), RuleError> {
+        let before = FINDERS_BUILT.with(|n| n.get());
+
+        let rule = Rule::from_str(
+            r#"
+id: sized-copy
+check-pattern:
+  pattern: '{ memcpy($dst, $src, sizeof(header)); }'
+"#,
+        )?;
+        let checker = &rule.checks()[0];
+
+        assert_eq!(
+            checker.variables(),
+            &HashSet::from([String::from("$dst"), String::from("$src")])
+        );
+        assert!(checker.identifiers().contains(&String::from("memcpy")));
+
+        // a finder per identifier, longest first
+        let built = FINDERS_BUILT.with(|n| n.get()) - before;
+        assert_eq!(built, checker.identifiers().len());
+        assert!(checker
+            .finders
+            .windows(2)
+            .all(|w| w[0].needle().len() >= w[1].needle().len()));
+
+        let sources = [
+            ("memcpy(d, s, sizeof(header));", true),
+            ("memcpy(d, s, sizeof(h));", false),
+            ("sizeof(header) + memmove(d, s, n)", false),
+            ("", false),
+        ];
+
+        for _ in 0..100 {
+            for (source, expected) in sources {
+                let naive = checker
+                    .identifiers()
+                    .iter()
+                    .all(|ident| memmem::find(source.as_bytes(), ident.as_bytes()).is_some());
+
+                assert_eq!(checker.can_match(source), expected);
+                assert_eq!(checker.can_match(source), naive);
+            }
+        }
+
+        // ...and no searcher is built when matching
+        assert_eq!(FINDERS_BUILT.with(|n| n.get()) - before, built);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_regex_multiple_variables() -> Result<(), Box<dyn std::error::Error>> {
+        use crate::matcher::RuleMatcher;
+
+        let source = "
+void f(void) {
+    g_x = g_y;
+    g_x = y;
+    x = g_y;
+    copy(a, b);
+    copy(input, b);
+    copy(a, Input);
+}
+";
+        let rule = |pattern: &str, regex: &str| {
+            format!("id: x\ncheck-pattern:\n  pattern: '{{ {pattern} }}'\n  regex: {regex}\n")
+        };
+        let found = |rule: &str| -> Result<Vec<String>, Box<dyn std::error::Error>> {
+            let mut matcher = RuleMatcher::from_str(rule)?;
+            Ok(matcher
+                .matches(source)?
+                .iter()
+                .map(|m| m.matched_text().trim_end_matches(';').to_owned())
+                .collect())
+        };
+
+        // a list of variables on the left...
+        let both = rule("$a = $b;", "'a, $b=^g_'");
+        assert_eq!(found(&both)?, ["g_x = g_y"]);
+
+        let none = rule("copy($p, $q);", "'p,q!=(?i)input'");
+        assert_eq!(found(&none)?, ["copy(a, b"]);
+
+        // ...or in the map form, with or without a transform
+        let both_map = rule("$a = $b;", "{vars: [a, b], regex: ^g_}");
+        assert_eq!(found(&both_map)?, ["g_x = g_y"]);
+
+        let none_map = rule(
+            "copy($p, $q);",
+            "{vars: [p, q], regex: ^input$, negative: true, lowercase: true}",
+        );
+        assert_eq!(found(&none_map)?, ["copy(a, b"]);
+
+        // rules serialize as written
+        for rule in [&both, &none, &both_map, &none_map] {
+            let yaml = serde_yaml::to_string(&Rule::from_str(rule)?)?;
+            assert_eq!(serde_yaml::to_string(&Rule::from_str(&yaml)?)?, yaml);
+            assert_eq!(found(&yaml)?, found(rule)?);
+        }
+
+        // each variable must be in the pattern
+        for regex in ["'a,c,d=^g_'", "{vars: [a, c, d], regex: ^g_}"] {
+            let Err(error) = Rule::from_str(rule("$a = $b;", regex)) else {
+                panic!("constraint on `$c` accepted");
+            };
+            let expected = CheckError::InvalidQueryVariable(String::from("$c"));
+            assert!(error.to_string().contains(&expected.to_string()));
+        }
+
+        // negation is written in the regex when it names its variables
+        assert!(Rule::from_str(rule("$a = $b;", "{regex: 'a=^g_', negative: true}")).is_err());
+        assert!(Rule::from_str(rule("$a = $b;", "'a,,b=^g_'")).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_regex_constraints() -> Result<(), RuleError> {
+        let rule = Rule::from_str(
+            r#"
+id: constraints
+check-patterns:
+- name: strings
+  regex: ['func=^str', 'a, $b!= ^g_ ']
+  pattern: '{ $a = $func($b); }'
+- name: maps
+  regex:
+  - {vars: [a], regex: '^input$', negative: true, lowercase: true}
+  - {vars: [$func], regex: ^mem}
+  pattern: '{ $a = $func($b); }'
+- name: none
+  pattern: '{ free($p); }'
+"#,
+        )?;
+
+        let constraints = |i: usize| {
+            rule.checks()[i]
+                .regex_constraints()
+                .iter()
+                .map(|c| (c.variable(), c.is_negative(), c.regex()))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            constraints(0),
+            [
+                ("$func", false, "^str"),
+                ("$a", true, "^g_"),
+                ("$b", true, "^g_")
+            ]
+        );
+        assert_eq!(
+            constraints(1),
+            [("$a", true, "^input$"), ("$func", false, "^mem")]
+        );
+        assert!(constraints(2).is_empty());
+
+        let debug = format!("{:?}", rule.checks()[1]);
+        assert!(debug.contains("name: \"maps\"") && debug.contains("regex: \"^mem\""));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_max_file_size() -> Result<(), Box<dyn std::error::Error>> {
+        let rule = |size: &str| {
+            format!("id: sized\nmax-file-size: {size}\ncheck-pattern:\n  pattern: '{{ f(); }}'\n")
+        };
+
+        for (size, bytes) in [("2MB", 2 << 20), ("512k", 512 << 10), ("4096", 4096)] {
+            let parsed = Rule::from_str(rule(size))?;
+            assert_eq!(parsed.max_file_size().map(FileSize::bytes), Some(bytes));
+
+            // as written in the rule
+            let yaml = serde_yaml::to_string(&parsed)?;
+            assert!(yaml.contains(size), "{yaml}");
+            let again = Rule::from_str(&yaml)?;
+            assert_eq!(again.max_file_size(), parsed.max_file_size());
+        }
+        assert!(
+            Rule::from_str("id: x\ncheck-pattern:\n  pattern: '{ f(); }'\n")?
+                .max_file_size()
+                .is_none()
+        );
+
+        // errors name the rule
+        let Err(error) = Rule::from_str(rule("2 parsecs")) else {
+            panic!("invalid size accepted");
+        };
+        assert!(error
+            .to_string()
+            .contains("rule `sized` has an invalid max-file-size `2 parsecs`"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_auto_wrap() -> Result<(), Box<dyn std::error::Error>> {
+        use crate::matcher::RuleMatcher;
+        use crate::rule::Language;
+
+        let rule = |pattern: &str, extra: &str| {
+            format!("id: copy\ncheck-pattern:\n  pattern: '{pattern}'\n{extra}")
+        };
+        let source = "void f(char *s) {\n  char buf[8];\n  strcpy(buf, s);\n}\n";
+
+        // a bare call matches as the block it is wrapped in
+        let lines = |pattern: &str| -> Result<Vec<usize>, Box<dyn std::error::Error>> {
+            let mut matcher = RuleMatcher::from_str(rule(pattern, ""))?;
+            let matches = matcher.matches_lang(source, Language::C)?;
+            Ok(matches.iter().map(|m| m.start_line()).collect())
+        };
+        assert_eq!(lines("strcpy($dst, _)")?, [3]);
+        assert_eq!(lines("strcpy($dst, _);")?, lines("{ strcpy($dst, _); }")?);
+
+        // errors name both forms of a pattern that still does not compile
+        let Err(error) = Rule::from_str(rule("strcpy($dst, _", "")) else {
+            panic!("invalid pattern accepted");
+        };
+        let error = error.to_string();
+        assert!(error.contains("`strcpy($dst, _`"), "{error}");
+        assert!(error.contains("`{ strcpy($dst, _; }`"), "{error}");
+
+        let unwrapped = rule("strcpy($dst, _", "  auto-wrap: false\n");
+        let Err(error) = Rule::from_str(unwrapped) else {
+            panic!("invalid pattern accepted");
+        };
+        assert!(!error.to_string().contains("compiled as"));
+
+        // only disabling it is serialized
+        let yaml = serde_yaml::to_string(&Rule::from_str(rule("{ f(); }", ""))?)?;
+        assert!(!yaml.contains("auto-wrap"));
+        let rule = Rule::from_str(rule("{ f(); }", "  auto-wrap: false\n"))?;
+        let yaml = serde_yaml::to_string(&rule)?;
+        assert!(yaml.contains("auto-wrap: false"), "{yaml}");
+        assert_eq!(serde_yaml::to_string(&Rule::from_str(&yaml)?)?, yaml);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_unless_in_function() -> Result<(), Box<dyn std::error::Error>> {
+        use crate::matcher::RuleMatcher;
+
+        let rule = r#"
+id: command-injection
+check-patterns:
+- name: system
+  pattern: '{ system($cmd); }'
+  unless-in-function: '{ $cmd = "ls -l"; }'
+- name: request
+  pattern: 'struct $s { _; };'
+  unless-in-function: ['{ struct $s $copy; }']
+"#;
+
+        let mut matcher = RuleMatcher::from_str(rule)?;
+        let matches = matcher.matches_file("tests/guarded-system.c")?;
+        let lines = |name| {
+            matches
+                .iter()
+                .filter(|m| m.checker().name() == name)
+                .map(|m| m.start_line())
+                .collect::<Vec<_>>()
+        };
+
+        // the guard binds `cmd` in `run_fixed` only (in `run_other`, the
+        // literal is assigned to another variable)
+        assert_eq!(lines("system"), [16, 23, 29]);
+
+        // matches at global scope are their own scope: the guard in
+        // `run_request` does not apply to the definition of the struct
+        assert_eq!(lines("request"), [3]);
+
+        let rule = Rule::from_str(rule)?;
+        assert_eq!(
+            rule.checks()[0].unless_in_function(),
+            [r#"{ $cmd = "ls -l"; }"#]
+        );
+        let yaml = serde_yaml::to_string(&rule)?;
+        assert!(yaml.contains("unless-in-function:"));
+        assert_eq!(serde_yaml::to_string(&Rule::from_str(&yaml)?)?, yaml);
+
+        assert!(Rule::from_str(
+            "id: x\ncheck-pattern:\n  pattern: '{ f(); }'\n  unless-in-function: '{ g( }'\n"
+        )
+        .is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_function_lines() -> Result<(), Box<dyn std::error::Error>> {
+        use crate::matcher::RuleMatcher;
+
+        let rule = |bounds: &str| {
+            format!(
+                r#"
+id: strcpy
+check-pattern:
+  pattern: '{{ strcpy($dst, _); }}'
+{bounds}"#
+            )
+        };
+        let lines = |matcher: &mut RuleMatcher| -> Result<Vec<usize>, Box<dyn std::error::Error>> {
+            Ok(matcher
+                .matches_file("tests/wrapper-copy.c")?
+                .iter()
+                .map(|m| m.start_line())
+                .collect())
+        };
+
+        // the wrapper spans 4 lines, the parser 28
+        let mut matcher = RuleMatcher::from_str(rule(""))?;
+        assert_eq!(lines(&mut matcher)?, [5, 28]);
+
+        let mut matcher = RuleMatcher::from_str(rule("  min-function-lines: 20\n"))?;
+        assert_eq!(lines(&mut matcher)?, [28]);
+        assert_eq!(
+            matcher.rules().get(0).unwrap().checks()[0].min_function_lines(),
+            Some(20)
+        );
+
+        let mut matcher = RuleMatcher::from_str(rule("  max-function-lines: 4\n"))?;
+        assert_eq!(lines(&mut matcher)?, [5]);
+
+        // at matcher level, for all rules
+        let mut matcher = RuleMatcher::from_str(rule(""))?;
+        matcher.set_min_function_lines(Some(20));
+        assert_eq!(lines(&mut matcher)?, [28]);
+        matcher.set_min_function_lines(None);
+        matcher.set_max_function_lines(Some(10));
+        assert_eq!(lines(&mut matcher)?, [5]);
+
+        // bounds are serialized, and must be ordered
+        let bounded = Rule::from_str(rule(
+            "  min-function-lines: 20\n  max-function-lines: 100\n",
+        ))?;
+        let yaml = serde_yaml::to_string(&bounded)?;
+        assert!(yaml.contains("min-function-lines: 20"), "{yaml}");
+        assert_eq!(serde_yaml::to_string(&Rule::from_str(&yaml)?)?, yaml);
+
+        let Err(error) =
+            Rule::from_str(rule("  min-function-lines: 20\n  max-function-lines: 4\n"))
+        else {
+            panic!("inverted bounds accepted");
+        };
+        assert!(error.to_string().contains("min-function-lines"), "{error}");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_rule_export() -> Result<(), Box<dyn std::error::Error>> {
+        use crate::matcher::{RuleMatcher, RuleMatcherError};
+
+        let pack = testing::pack_with(&testing::DESCRIBED_PACK)?;
+
+        let rules = RuleSet::from_directory(pack.path(), false)?;
+
+        // a stream of documents, ordered by id, that parse as the same rules
+        let mut output = Vec::new();
+        rules.to_yaml_writer(&mut output)?;
+        let output = String::from_utf8(output)?;
+
+        let reloaded = serde_yaml::Deserializer::from_str(&output)
+            .map(Rule::deserialize)
+            .collect::<Result<Vec<_>, _>>()?;
+
+        assert_eq!(
+            reloaded.iter().map(Rule::id).collect::<Vec<_>>(),
+            ["local-buffer", "unbounded-copy"]
+        );
+
+        let mut again = Vec::new();
+        for rule in &reloaded {
+            again.extend_from_slice(b"---\n");
+            again.extend_from_slice(serde_yaml::to_string(rule)?.as_bytes());
+        }
+        assert_eq!(String::from_utf8(again)?, output);
+
+        // all fields are kept, and defaults omitted
+        let rule = Rule::from_str(
+            r#"
+id: sized-copy
+author: appsec@example.com
+severity: medium
+tags: [CWE-787, CWE-120]
+paths:
+  include: ["drivers/**"]
+check-patterns:
+- name: memcpy
+  language: c++
+  regex: [func=^memcpy$, "size!=^sizeof"]
+  pattern: '{ $func($dst, $src, $size); }'
+  limit: true
+  unique: true
+"#,
+        )?;
+        let yaml = serde_yaml::to_string(&rule)?;
+
+        assert!(!yaml.contains("description"));
+        assert!(yaml.contains("- CWE-120\n- CWE-787\n"));
+        assert_eq!(serde_yaml::to_string(&Rule::from_str(&yaml)?)?, yaml);
+
+        // a pack written to a directory matches as the original
+        let dir = TempDir::with_files(&[])?;
+        rules.to_directory(dir.path())?;
+
+        let exported = RuleSet::from_directory(dir.path(), false)?;
+
+        assert_eq!(exported.len(), rules.len());
+
+        let mut original = RuleMatcher::new(rules)?;
+        let mut exported = RuleMatcher::new(exported)?;
+
+        for source in [
+            "tests/utf8-comments.c",
+            "tests/ls-main.c",
+            "tests/edge-copy.c",
+            "tests/hexrays-usercall.c",
+        ] {
+            let summary = |matcher: &mut RuleMatcher| -> Result<Vec<_>, RuleMatcherError> {
+                let mut matches = matcher
+                    .matches_file(source)?
+                    .iter()
+                    .map(|m| {
+                        (
+                            m.rule().id().to_owned(),
+                            m.checker().name().to_owned(),
+                            m.rule().severity(),
+                            m.start_offset(),
+                            m.end_offset(),
+                        )
+                    })
+                    .collect::<Vec<_>>();
+                matches.sort();
+                Ok(matches)
+            };
+
+            assert_eq!(summary(&mut original)?, summary(&mut exported)?);
+        }
+
+        Ok(())
+    }
 }
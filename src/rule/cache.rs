@@ -0,0 +1,388 @@
+use std::cell::RefCell;
+use std::collections::{HashSet, VecDeque};
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::UNIX_EPOCH;
+
+use rustc_hash::FxHashMap;
+use serde::{Deserialize, Serialize};
+
+use crate::matcher::Fnv;
+use crate::rule::{rule_files, Rule, RuleError, RuleFileFilter, RuleKeys, RuleSet};
+
+// the format of the cache files; entries written by other versions of the
+// crate are discarded too, as the rule format may have changed
+const CACHE_VERSION: u32 = 1;
+
+// A cache of the rules loaded from a directory: for each rule file, its
+// fingerprint, the rule as serialized after validation (i.e., with its
+// patterns, regexes, and metadata in canonical form), and the identifiers and
+// variables of each check. Cached rules are read as JSON rather than parsed
+// as YAML and validated, which is what a warm load saves.
+//
+// NOTE: weggli has no serializable form of a compiled pattern, so the
+// patterns of cached rules are still compiled once they are read (which is
+// most of the loading time); an entry whose patterns no longer compile (e.g.,
+// after an update of weggli) is a miss
+#[derive(Serialize, Deserialize)]
+struct RuleCache {
+    version: u32,
+    crate_version: String,
+    entries: Vec<CacheEntry>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct CacheEntry {
+    path: String,
+    // seconds and nanoseconds since the epoch
+    modified: Option<(u64, u32)>,
+    hash: u64,
+    rule: serde_json::Value,
+    checks: Vec<CompiledCheck>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct CompiledCheck {
+    identifiers: Vec<String>,
+    variables: Vec<String>,
+}
+
+thread_local! {
+    // the checks of the cached rule being deserialized, in order
+    static COMPILED: RefCell<VecDeque<CompiledCheck>> = const { RefCell::new(VecDeque::new()) };
+}
+
+// see `precompiled` in `rule.rs`
+pub(super) fn take_compiled() -> Option<(Box<[String]>, HashSet<String>)> {
+    let check = COMPILED.with_borrow_mut(VecDeque::pop_front)?;
+    Some((
+        check.identifiers.into_boxed_slice(),
+        check.variables.into_iter().collect(),
+    ))
+}
+
+impl CacheEntry {
+    // NOTE: the checks of a rule are built in order, each taking the next
+    // entry of the queue, and then their patterns are compiled
+    fn rule(&self) -> Option<Rule> {
+        COMPILED.set(
+            self.checks
+                .iter()
+                .map(|check| CompiledCheck {
+                    identifiers: check.identifiers.clone(),
+                    variables: check.variables.clone(),
+                })
+                .collect(),
+        );
+        let rule = Rule::deserialize(&self.rule).ok();
+        COMPILED.take();
+
+        rule.filter(|rule| rule.checks().len() == self.checks.len())
+            .filter(|rule| rule.checks().iter().all(|c| c.compile().is_ok()))
+    }
+}
+
+fn compiled_checks(rule: &Rule) -> Vec<CompiledCheck> {
+    rule.checks()
+        .iter()
+        .map(|checker| {
+            let mut variables = checker.variables().iter().cloned().collect::<Vec<_>>();
+            variables.sort();
+
+            CompiledCheck {
+                identifiers: checker.identifiers().to_vec(),
+                variables,
+            }
+        })
+        .collect()
+}
+
+impl RuleCache {
+    fn new(entries: Vec<CacheEntry>) -> Self {
+        Self {
+            version: CACHE_VERSION,
+            crate_version: env!("CARGO_PKG_VERSION").to_owned(),
+            entries,
+        }
+    }
+
+    // NOTE: a missing, unreadable, corrupted, or outdated cache is empty
+    fn read(path: &Path) -> FxHashMap<String, CacheEntry> {
+        let Ok(file) = File::open(path) else {
+            return FxHashMap::default();
+        };
+
+        match serde_json::from_reader::<_, Self>(BufReader::new(file)) {
+            Ok(cache)
+                if cache.version == CACHE_VERSION
+                    && cache.crate_version == env!("CARGO_PKG_VERSION") =>
+            {
+                cache
+                    .entries
+                    .into_iter()
+                    .map(|entry| (entry.path.clone(), entry))
+                    .collect()
+            }
+            _ => FxHashMap::default(),
+        }
+    }
+
+    // written to a temporary file first, so that concurrent loads never read
+    // a partial cache
+    fn write(&self, path: &Path) -> std::io::Result<()> {
+        let partial = path.with_extension(format!("{}.tmp", std::process::id()));
+
+        let file = File::create(&partial)?;
+        serde_json::to_writer(BufWriter::new(file), self)?;
+        fs::rename(&partial, path).inspect_err(|_| {
+            let _ = fs::remove_file(&partial);
+        })
+    }
+}
+
+// the cache file of `root`, which is named after its canonical path so that
+// a cache directory can be shared by rule directories
+fn cache_path(root: &Path, cache_dir: &Path) -> PathBuf {
+    let root = fs::canonicalize(root).unwrap_or_else(|_| root.to_owned());
+
+    let mut hasher = Fnv::new();
+    hasher.field(&root.to_string_lossy());
+
+    cache_dir.join(format!("rules-{:016x}.json", hasher.finish()))
+}
+
+fn modified(path: &Path) -> Option<(u64, u32)> {
+    let modified = fs::metadata(path).ok()?.modified().ok()?;
+    let since = modified.duration_since(UNIX_EPOCH).ok()?;
+    Some((since.as_secs(), since.subsec_nanos()))
+}
+
+// the rules of `root`, and how many of them were read from the cache
+pub(super) fn load(root: &Path, cache_dir: &Path) -> Result<(RuleSet, usize), RuleError> {
+    let cache = cache_path(root, cache_dir);
+    let mut cached = RuleCache::read(&cache);
+
+    let mut rules = Vec::new();
+    let mut entries = Vec::new();
+    let mut hits = 0;
+
+    for path in rule_files(root, &RuleFileFilter::default()) {
+        let key = RuleKeys::default().key(root, &path);
+
+        let text = fs::read_to_string(&path)
+            .map_err(|e| RuleError::ParseFile(path.to_owned(), e.into()))?;
+        let modified = modified(&path);
+
+        let mut hasher = Fnv::new();
+        hasher.field(&text);
+        let hash = hasher.finish();
+
+        // NOTE: an entry that no longer deserializes or compiles is stale,
+        // e.g., after a change in validation
+        let hit = cached
+            .remove(&key)
+            .filter(|entry| entry.modified == modified && entry.hash == hash)
+            .and_then(|entry| Some((entry.rule()?, entry)));
+
+        let rule = match hit {
+            Some((rule, entry)) => {
+                hits += 1;
+                entries.push(entry);
+                rule
+            }
+            None => {
+                let rule = Rule::from_str(&text)
+                    .map_err(|e| RuleError::ParseFile(path.to_owned(), e.into()))?;
+
+                if let Ok(value) = serde_json::to_value(&rule) {
+                    entries.push(CacheEntry {
+                        path: key.clone(),
+                        modified,
+                        hash,
+                        rule: value,
+                        checks: compiled_checks(&rule),
+                    });
+                }
+                rule
+            }
+        };
+
+        rules.push((Arc::from(key), Arc::new(rule.with_source_path(&path))));
+    }
+
+    // NOTE: the cache is an optimization, so failing to write it is not an
+    // error; rewriting is skipped when every entry was a hit
+    if hits < rules.len() || !cached.is_empty() {
+        let _ = fs::create_dir_all(cache_dir);
+        let _ = RuleCache::new(entries).write(&cache);
+    }
+
+    Ok((
+        RuleSet {
+            rules: Arc::from(rules),
+        },
+        hits,
+    ))
+}
+
+impl RuleSet {
+    // Loads the rules of `root` as `from_directory` does (without ignoring
+    // errors), reusing the rules cached in `cache_dir` for the files whose
+    // modification time and contents are unchanged, and updates the cache.
+    // A corrupted or outdated cache is rebuilt.
+    pub fn load_cached(
+        root: impl AsRef<Path>,
+        cache_dir: impl AsRef<Path>,
+    ) -> Result<Self, RuleError> {
+        load(root.as_ref(), cache_dir.as_ref()).map(|(rules, _)| rules)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+
+    use super::{cache_path, load};
+    use crate::matcher::RuleMatcher;
+    use crate::rule::{RuleSet, PATTERNS_BUILT};
+
+    const RULES: usize = 20;
+
+    // a pack of rules with several checks, regexes, and metadata each
+    fn write_pack(dir: &std::path::Path) -> std::io::Result<()> {
+        fs::create_dir_all(dir)?;
+        for i in 0..RULES {
+            let rule = format!(
+                r#"id: rule-{i:03}
+description: generated rule {i}
+severity: medium
+tags: [CWE-{i}]
+metadata:
+  owner: appsec
+  references: ['https://example.com/{i}']
+check-patterns:
+- name: copy
+  regex: 'func=^(str|mem)cpy_{i}$'
+  pattern: '{{ $func($dst, _); }}'
+- name: alloc
+  pattern: |
+    {{
+      $p = malloc_{i}($n);
+      _;
+      $p[$n] = _;
+    }}
+- name: format
+  regex: ['func=^s?printf_{i}$', 'fmt!=^"']
+  pattern: '{{ $func(_, $fmt); }}'
+condition: copy or (alloc and not format)
+"#
+            );
+            fs::write(dir.join(format!("rule-{i:03}.yml")), rule)?;
+        }
+        Ok(())
+    }
+
+    fn yaml(rules: &RuleSet) -> Result<String, Box<dyn std::error::Error>> {
+        let mut out = Vec::new();
+        rules.to_yaml_writer(&mut out)?;
+        Ok(String::from_utf8(out)?)
+    }
+
+    #[test]
+    fn test_load_cached() -> Result<(), Box<dyn std::error::Error>> {
+        let base =
+            std::env::temp_dir().join(format!("weggli-ruleset-cache-{}", std::process::id()));
+        let root = base.join("rules");
+        let cache_dir = base.join("cache");
+
+        // e.g., left over by an earlier run that failed
+        let _ = fs::remove_dir_all(&base);
+        write_pack(&root)?;
+
+        let built = || PATTERNS_BUILT.with(|n| n.get());
+
+        let before = built();
+        let (cold, hits) = load(&root, &cache_dir)?;
+        let cold_built = built() - before;
+        assert_eq!((cold.len(), hits), (RULES, 0));
+
+        let before = built();
+        let (warm, hits) = load(&root, &cache_dir)?;
+        let warm_built = built() - before;
+        assert_eq!((warm.len(), hits), (RULES, RULES));
+
+        // a warm load skips parsing the YAML of every rule, but compiles its
+        // patterns as a cold load does (three checks per rule)
+        assert_eq!(cold_built, 3 * RULES);
+        assert_eq!(warm_built, cold_built);
+
+        // the same rules, under the same keys
+        assert_eq!(yaml(&warm)?, yaml(&cold)?);
+        assert_eq!(
+            warm.iter().map(|(key, _)| key).collect::<Vec<_>>(),
+            cold.iter().map(|(key, _)| key).collect::<Vec<_>>()
+        );
+        assert_eq!(yaml(&RuleSet::from_directory(&root, false)?)?, yaml(&cold)?);
+
+        // and the patterns compiled by the warm load match the same
+        let source = "void f(char *d, char *s) {\n    strcpy_3(d, s);\n    int *p = malloc_5(n);\n    p[n] = 0;\n}\n";
+        let cold_matches = RuleMatcher::new(cold.clone())?.matches(source)?;
+        let warm_matches = RuleMatcher::new(warm)?.matches(source)?;
+        assert_eq!(cold_matches.len(), 2);
+        assert_eq!(
+            warm_matches
+                .iter()
+                .map(|m| m.fingerprint())
+                .collect::<Vec<_>>(),
+            cold_matches
+                .iter()
+                .map(|m| m.fingerprint())
+                .collect::<Vec<_>>()
+        );
+
+        // changed files are parsed again
+        let changed = root.join("rule-007.yml");
+        let rule = fs::read_to_string(&changed)?.replace("severity: medium", "severity: high");
+        fs::write(&changed, rule)?;
+        let (rules, hits) = load(&root, &cache_dir)?;
+        assert_eq!(hits, RULES - 1);
+        assert_eq!(
+            rules.get(7).map(|r| r.severity().to_string()),
+            Some("high".to_owned())
+        );
+
+        // a corrupted or outdated cache is rebuilt
+        let cache = cache_path(&root, &cache_dir);
+        for contents in [
+            "{\"version\": 1, \"entr".to_owned(),
+            fs::read_to_string(&cache)?.replacen("\"version\":1", "\"version\":0", 1),
+        ] {
+            fs::write(&cache, contents)?;
+            let (rules, hits) = load(&root, &cache_dir)?;
+            assert_eq!((rules.len(), hits), (RULES, 0));
+            assert_eq!(load(&root, &cache_dir)?.1, RULES);
+        }
+
+        // a cached pattern that no longer compiles is a miss, rather than a
+        // panic when it is first matched
+        let contents = fs::read_to_string(&cache)?;
+        assert!(contents.contains("{ $func($dst, _); }"));
+        fs::write(
+            &cache,
+            contents.replacen("{ $func($dst, _); }", "{ $func($dst, _ }", 1),
+        )?;
+        let (rules, hits) = load(&root, &cache_dir)?;
+        assert_eq!((rules.len(), hits), (RULES, RULES - 1));
+        assert_eq!(RuleMatcher::new(rules)?.matches(source)?.len(), 2);
+
+        // errors are reported as by `from_directory`
+        fs::write(root.join("rule-999.yml"), "id: [")?;
+        assert!(RuleSet::load_cached(&root, &cache_dir).is_err());
+
+        fs::remove_dir_all(&base)?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,354 @@
+use std::fmt::Display;
+
+use crate::rule::{Checker, RuleError};
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum Expr {
+    // the index of a check of the rule
+    Check(usize),
+    Not(Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+}
+
+impl Expr {
+    fn evaluate(&self, fired: &impl Fn(usize) -> bool) -> bool {
+        match self {
+            Self::Check(i) => fired(*i),
+            Self::Not(e) => !e.evaluate(fired),
+            Self::And(a, b) => a.evaluate(fired) && b.evaluate(fired),
+            Self::Or(a, b) => a.evaluate(fired) || b.evaluate(fired),
+        }
+    }
+
+    // the checks that occur under an even number of negations
+    fn positive(&self, negated: bool, checks: &mut Vec<usize>) {
+        match self {
+            Self::Check(i) if !negated && !checks.contains(i) => checks.push(*i),
+            Self::Check(_) => {}
+            Self::Not(e) => e.positive(!negated, checks),
+            Self::And(a, b) | Self::Or(a, b) => {
+                a.positive(negated, checks);
+                b.positive(negated, checks);
+            }
+        }
+    }
+}
+
+// A boolean expression over the checks of a rule, by name, e.g., `alloc and
+// deref and not guarded`: `not` binds tighter than `and`, which binds tighter
+// than `or`, and parentheses group. Names that are not plain words (e.g.,
+// `st(r|p)cpy`) are quoted with backticks.
+//
+// When a rule has a condition, it reports findings for a source only if the
+// condition holds for the checks that matched it, and only those of the
+// `report` check, if any, or else of the checks that matched and occur
+// without negation in the condition.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Condition {
+    expr: Expr,
+    report: Option<usize>,
+    // as written in the rule, to serialize it
+    source: Box<str>,
+    report_source: Option<Box<str>>,
+}
+
+impl Condition {
+    pub(crate) fn new(
+        condition: &str,
+        report: Option<&str>,
+        checks: &[Checker],
+    ) -> Result<Self, RuleError> {
+        let index = |name: &str| {
+            checks
+                .iter()
+                .position(|c| c.name() == name)
+                .ok_or_else(|| RuleError::UnknownCheck(name.to_owned()))
+        };
+
+        let tokens = tokenize(condition)?;
+        let mut parser = Parser {
+            tokens: &tokens,
+            position: 0,
+            index: &index,
+        };
+
+        let expr = parser.or()?;
+        if let Some(token) = parser.peek() {
+            return Err(RuleError::InvalidCondition(format!("unexpected `{token}`")));
+        }
+
+        Ok(Self {
+            expr,
+            report: report.map(index).transpose()?,
+            source: Box::from(condition.trim()),
+            report_source: report.map(Box::from),
+        })
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.source
+    }
+
+    // the name of the check whose findings are reported, if designated
+    pub fn report(&self) -> Option<&str> {
+        self.report_source.as_deref()
+    }
+
+    // whether the condition holds, given whether each check (by index) matched
+    pub fn evaluate(&self, fired: impl Fn(usize) -> bool) -> bool {
+        self.expr.evaluate(&fired)
+    }
+
+    // the checks (by index) whose findings are reported, given whether each
+    // check matched; none if the condition does not hold
+    pub fn reported(&self, fired: impl Fn(usize) -> bool) -> Vec<usize> {
+        if !self.expr.evaluate(&fired) {
+            return Vec::new();
+        }
+
+        if let Some(report) = self.report {
+            return vec![report];
+        }
+
+        let mut positive = Vec::new();
+        self.expr.positive(false, &mut positive);
+        positive.retain(|i| fired(*i));
+        positive
+    }
+}
+
+impl Display for Condition {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.source)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token<'a> {
+    Open,
+    Close,
+    And,
+    Or,
+    Not,
+    Name(&'a str),
+}
+
+impl Display for Token<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Open => f.write_str("("),
+            Self::Close => f.write_str(")"),
+            Self::And => f.write_str("and"),
+            Self::Or => f.write_str("or"),
+            Self::Not => f.write_str("not"),
+            Self::Name(name) => f.write_str(name),
+        }
+    }
+}
+
+fn tokenize(condition: &str) -> Result<Vec<Token<'_>>, RuleError> {
+    let mut tokens = Vec::new();
+    let mut rest = condition.trim_start();
+
+    while let Some(c) = rest.chars().next() {
+        let (token, len) = match c {
+            '(' => (Token::Open, 1),
+            ')' => (Token::Close, 1),
+            '`' => {
+                let end = rest[1..]
+                    .find('`')
+                    .ok_or_else(|| RuleError::InvalidCondition(String::from("unterminated `")))?;
+                (Token::Name(&rest[1..=end]), end + 2)
+            }
+            _ => {
+                let end = rest
+                    .find(|c: char| c.is_whitespace() || c == '(' || c == ')' || c == '`')
+                    .unwrap_or(rest.len());
+                let token = match &rest[..end] {
+                    "and" => Token::And,
+                    "or" => Token::Or,
+                    "not" => Token::Not,
+                    name => Token::Name(name),
+                };
+                (token, end)
+            }
+        };
+
+        tokens.push(token);
+        rest = rest[len..].trim_start();
+    }
+
+    Ok(tokens)
+}
+
+struct Parser<'t, 'a, F> {
+    tokens: &'t [Token<'a>],
+    position: usize,
+    index: &'t F,
+}
+
+impl<F: Fn(&str) -> Result<usize, RuleError>> Parser<'_, '_, F> {
+    fn peek(&self) -> Option<&Token<'_>> {
+        self.tokens.get(self.position)
+    }
+
+    fn next(&mut self) -> Option<Token<'_>> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+
+    fn or(&mut self) -> Result<Expr, RuleError> {
+        let mut expr = self.and()?;
+        while self.peek() == Some(&Token::Or) {
+            self.position += 1;
+            expr = Expr::Or(Box::new(expr), Box::new(self.and()?));
+        }
+        Ok(expr)
+    }
+
+    fn and(&mut self) -> Result<Expr, RuleError> {
+        let mut expr = self.not()?;
+        while self.peek() == Some(&Token::And) {
+            self.position += 1;
+            expr = Expr::And(Box::new(expr), Box::new(self.not()?));
+        }
+        Ok(expr)
+    }
+
+    fn not(&mut self) -> Result<Expr, RuleError> {
+        if self.peek() == Some(&Token::Not) {
+            self.position += 1;
+            return Ok(Expr::Not(Box::new(self.not()?)));
+        }
+        self.primary()
+    }
+
+    fn primary(&mut self) -> Result<Expr, RuleError> {
+        let index = self.index;
+
+        match self.next() {
+            Some(Token::Name(name)) => Ok(Expr::Check(index(name)?)),
+            Some(Token::Open) => {
+                let expr = self.or()?;
+                match self.next() {
+                    Some(Token::Close) => Ok(expr),
+                    _ => Err(RuleError::InvalidCondition(String::from("missing `)`"))),
+                }
+            }
+            Some(token) => Err(RuleError::InvalidCondition(format!("unexpected `{token}`"))),
+            None => Err(RuleError::InvalidCondition(String::from(
+                "unexpected end of condition",
+            ))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::matcher::RuleMatcher;
+    use crate::rule::Rule;
+
+    const RULE: &str = r#"
+id: unchecked-alloc
+check-patterns:
+- name: alloc
+  pattern: '{ $p = malloc(_); }'
+- name: deref
+  pattern: '{ *$p = _; }'
+- name: guarded
+  pattern: '{ if (!$p) return; }'
+condition: alloc and deref and not guarded
+"#;
+
+    #[test]
+    fn test_condition() -> Result<(), Box<dyn std::error::Error>> {
+        let source = "
+void unchecked(void) {
+    int *p;
+    p = malloc(4);
+    *p = 1;
+}
+";
+        let guarded = "
+void checked(void) {
+    int *p;
+    p = malloc(4);
+    if (!p) return;
+    *p = 1;
+}
+";
+        let rule = Rule::from_str(RULE)?;
+        let condition = rule.condition().unwrap();
+        assert_eq!(condition.as_str(), "alloc and deref and not guarded");
+        assert!(condition.evaluate(|i| i < 2));
+        assert!(!condition.evaluate(|_| true));
+
+        // the findings of the positive terms, when the condition holds
+        let mut matcher = RuleMatcher::from_str(RULE)?;
+        let matches = matcher.matches(source)?;
+        let mut checks = matches
+            .iter()
+            .map(|m| m.checker().name())
+            .collect::<Vec<_>>();
+        checks.sort();
+        assert_eq!(checks, ["alloc", "deref"]);
+        assert!(matcher.matches(guarded)?.is_empty());
+
+        // ...or those of the designated check
+        let report = format!("{RULE}report: deref\n");
+        let mut matcher = RuleMatcher::from_str(&report)?;
+        let matches = matcher.matches(source)?;
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].checker().name(), "deref");
+
+        // precedence, grouping, and quoted names
+        let rule = Rule::from_str(RULE.replace(
+            "alloc and deref and not guarded",
+            "not (`guarded` or not alloc) and deref or alloc and guarded",
+        ))?;
+        let condition = rule.condition().unwrap();
+        assert!(condition.evaluate(|i| i < 2));
+        assert!(condition.evaluate(|i| i != 1));
+        assert!(!condition.evaluate(|i| i == 1));
+        assert_eq!(condition.reported(|i| i < 2), [0, 1]);
+
+        // rules serialize with their condition
+        for rule in [RULE, &report] {
+            let yaml = serde_yaml::to_string(&Rule::from_str(rule)?)?;
+            assert!(yaml.contains("condition: alloc and deref and not guarded"));
+            assert_eq!(serde_yaml::to_string(&Rule::from_str(&yaml)?)?, yaml);
+        }
+
+        // errors are reported when loading the rule
+        for (condition, error) in [
+            ("alloc and free", "unknown check `free`"),
+            ("alloc and", "unexpected end of condition"),
+            ("(alloc or deref", "missing `)`"),
+            ("alloc deref", "unexpected `deref`"),
+            ("alloc or `deref", "unterminated `"),
+        ] {
+            let Err(e) = Rule::from_str(RULE.replace("alloc and deref and not guarded", condition))
+            else {
+                panic!("`{condition}` accepted");
+            };
+            assert!(e.to_string().contains(error), "{condition}: {e}");
+        }
+
+        let Err(e) = Rule::from_str(format!("{RULE}report: free\n")) else {
+            panic!("unknown report check accepted");
+        };
+        assert!(e.to_string().contains("unknown check `free`"));
+
+        let Err(e) = Rule::from_str(RULE.replace(
+            "condition: alloc and deref and not guarded",
+            "report: deref",
+        )) else {
+            panic!("report without a condition accepted");
+        };
+        assert!(e.to_string().contains("condition"));
+
+        Ok(())
+    }
+}
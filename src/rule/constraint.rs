@@ -0,0 +1,440 @@
+use std::fmt::Display;
+
+use crate::rule::CheckError;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Comparison {
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+    Equal,
+    NotEqual,
+}
+
+impl Comparison {
+    pub fn compare(self, lhs: i128, rhs: i128) -> bool {
+        match self {
+            Self::Less => lhs < rhs,
+            Self::LessOrEqual => lhs <= rhs,
+            Self::Greater => lhs > rhs,
+            Self::GreaterOrEqual => lhs >= rhs,
+            Self::Equal => lhs == rhs,
+            Self::NotEqual => lhs != rhs,
+        }
+    }
+}
+
+impl Display for Comparison {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Less => "<",
+            Self::LessOrEqual => "<=",
+            Self::Greater => ">",
+            Self::GreaterOrEqual => ">=",
+            Self::Equal => "==",
+            Self::NotEqual => "!=",
+        })
+    }
+}
+
+// A comparison of the integer literal captured by a variable with a constant,
+// e.g., `$1 < 16` or `size >= 0x1000`.
+//
+// NOTE: weggli captures number literals with numbered variables (e.g., `$1`)
+// only; other variables capture identifiers, whose text never parses (e.g.,
+// `BUFSIZ`)
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct NumericConstraint {
+    variable: String,
+    comparison: Comparison,
+    value: i128,
+    // as written in the rule, to serialize it
+    source: Box<str>,
+}
+
+impl NumericConstraint {
+    pub fn parse(constraint: &str) -> Result<Self, CheckError> {
+        let invalid = || CheckError::InvalidConstraint(constraint.to_owned());
+
+        let i = constraint.find(['<', '>', '!', '=']).ok_or_else(invalid)?;
+        let (variable, rest) = constraint.split_at(i);
+
+        let (comparison, value) = [
+            ("<=", Comparison::LessOrEqual),
+            (">=", Comparison::GreaterOrEqual),
+            ("==", Comparison::Equal),
+            ("!=", Comparison::NotEqual),
+            ("<", Comparison::Less),
+            (">", Comparison::Greater),
+        ]
+        .into_iter()
+        .find_map(|(op, comparison)| Some((comparison, rest.strip_prefix(op)?)))
+        .ok_or_else(invalid)?;
+
+        // as for regex constraints, `size` stands for `$size`
+        let variable = variable.trim();
+        if variable.is_empty() || variable == "$" {
+            return Err(invalid());
+        }
+
+        let variable = if variable.starts_with('$') {
+            variable.to_owned()
+        } else {
+            format!("${variable}")
+        };
+
+        Ok(Self {
+            variable,
+            comparison,
+            value: parse_integer(value.trim()).ok_or_else(invalid)?,
+            source: Box::from(constraint.trim()),
+        })
+    }
+
+    // e.g., `$size`
+    pub fn variable(&self) -> &str {
+        &self.variable
+    }
+
+    pub fn comparison(&self) -> Comparison {
+        self.comparison
+    }
+
+    pub fn value(&self) -> i128 {
+        self.value
+    }
+
+    // whether the constraint holds for the text captured by the variable, or
+    // `None` if it is not an integer literal
+    pub fn holds(&self, captured: &str) -> Option<bool> {
+        let captured = parse_integer(captured.trim())?;
+        Some(self.comparison.compare(captured, self.value))
+    }
+}
+
+impl Display for NumericConstraint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.source)
+    }
+}
+
+// A comparison of the texts captured by two variables of a match, e.g.,
+// `dst != src` or `first == second`; the texts are compared without the
+// whitespace that does not separate two words (so `p->x` equals `p -> x`).
+//
+// NOTE: a variable that captured nothing makes the relation fail
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Relation {
+    left: String,
+    right: String,
+    equal: bool,
+    // as written in the rule, to serialize it
+    source: Box<str>,
+}
+
+impl Relation {
+    pub fn parse(relation: &str) -> Result<Self, CheckError> {
+        let invalid = || CheckError::InvalidRelation(relation.to_owned());
+
+        let (left, right, equal) = if let Some((left, right)) = relation.split_once("==") {
+            (left, right, true)
+        } else if let Some((left, right)) = relation.split_once("!=") {
+            (left, right, false)
+        } else {
+            return Err(invalid());
+        };
+
+        let variable = |v: &str| {
+            let v = v.trim();
+            let name = v.strip_prefix('$').unwrap_or(v);
+            if name.is_empty() || !name.chars().all(|c| c.is_alphanumeric() || c == '_') {
+                return Err(invalid());
+            }
+            Ok(format!("${name}"))
+        };
+
+        Ok(Self {
+            left: variable(left)?,
+            right: variable(right)?,
+            equal,
+            source: Box::from(relation.trim()),
+        })
+    }
+
+    // e.g., `$dst`
+    pub fn left(&self) -> &str {
+        &self.left
+    }
+
+    pub fn right(&self) -> &str {
+        &self.right
+    }
+
+    // whether the texts must be equal, rather than different
+    pub fn is_equality(&self) -> bool {
+        self.equal
+    }
+
+    pub fn holds(&self, left: Option<&str>, right: Option<&str>) -> bool {
+        match (left, right) {
+            (Some(left), Some(right)) => (normalise(left) == normalise(right)) == self.equal,
+            _ => false,
+        }
+    }
+}
+
+impl Display for Relation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.source)
+    }
+}
+
+// the text without whitespace, except for a space between two words
+fn normalise(text: &str) -> String {
+    let is_word = |c: char| c.is_alphanumeric() || c == '_';
+
+    let mut normalised = String::with_capacity(text.len());
+    let mut pending_space = false;
+
+    for c in text.trim().chars() {
+        if c.is_whitespace() {
+            pending_space = true;
+            continue;
+        }
+
+        if pending_space && is_word(c) && normalised.ends_with(is_word) {
+            normalised.push(' ');
+        }
+        pending_space = false;
+        normalised.push(c);
+    }
+
+    normalised
+}
+
+// Parses a C integer literal: decimal, hex (`0x`), binary (`0b`), or octal
+// (leading `0`), with an optional sign, digit separators (`'`), and suffixes
+// (e.g., `UL`).
+//
+// NOTE: this is from weggli (which does not expose it); character literals
+// are not integers here
+pub(crate) fn parse_integer(literal: &str) -> Option<i128> {
+    let (negative, literal) = match literal.strip_prefix('-') {
+        Some(literal) => (true, literal.trim_start()),
+        None => (false, literal),
+    };
+
+    let literal = literal
+        .trim_end_matches(['u', 'U', 'l', 'L', 'z', 'Z'])
+        .replace('\'', "");
+
+    let (digits, radix) = if let Some(hex) = literal
+        .strip_prefix("0x")
+        .or_else(|| literal.strip_prefix("0X"))
+    {
+        (hex, 16)
+    } else if let Some(binary) = literal
+        .strip_prefix("0b")
+        .or_else(|| literal.strip_prefix("0B"))
+    {
+        (binary, 2)
+    } else if literal.len() > 1 && literal.starts_with('0') {
+        (&literal[1..], 8)
+    } else {
+        (literal.as_str(), 10)
+    };
+
+    // `from_str_radix` accepts a sign of its own
+    if !digits.chars().all(|c| c.is_digit(radix)) {
+        return None;
+    }
+
+    let value = i128::from_str_radix(digits, radix).ok()?;
+    Some(if negative { -value } else { value })
+}
+
+#[cfg(test)]
+mod test {
+    use super::{normalise, parse_integer, Comparison, NumericConstraint, Relation};
+    use crate::matcher::RuleMatcher;
+    use crate::rule::{CheckError, Rule};
+
+    #[test]
+    fn test_parse_constraint() -> Result<(), CheckError> {
+        assert_eq!(parse_integer("16"), Some(16));
+        assert_eq!(parse_integer("0x1000"), Some(4096));
+        assert_eq!(parse_integer("0X1fUL"), Some(31));
+        assert_eq!(parse_integer("010"), Some(8));
+        assert_eq!(parse_integer("0b101"), Some(5));
+        assert_eq!(parse_integer("1'000'000"), Some(1_000_000));
+        assert_eq!(parse_integer("-1"), Some(-1));
+        assert_eq!(parse_integer("0"), Some(0));
+        assert_eq!(parse_integer("'a'"), None);
+        assert_eq!(parse_integer("BUFSIZ"), None);
+        assert_eq!(parse_integer("1.5"), None);
+        assert_eq!(parse_integer("+1"), None);
+
+        let constraint = NumericConstraint::parse("size >= 0x400")?;
+        assert_eq!(constraint.variable(), "$size");
+        assert_eq!(constraint.comparison(), Comparison::GreaterOrEqual);
+        assert_eq!(constraint.value(), 1024);
+        assert_eq!(constraint.to_string(), "size >= 0x400");
+
+        assert_eq!(constraint.holds("1024"), Some(true));
+        assert_eq!(constraint.holds("0x3ff"), Some(false));
+        assert_eq!(constraint.holds("N"), None);
+
+        let constraint = NumericConstraint::parse("$len!=0")?;
+        assert_eq!(constraint.variable(), "$len");
+        assert_eq!(constraint.comparison(), Comparison::NotEqual);
+
+        for invalid in ["size", "size = 1", "< 16", "size < N", "size <> 1"] {
+            assert!(
+                matches!(
+                    NumericConstraint::parse(invalid),
+                    Err(CheckError::InvalidConstraint(_))
+                ),
+                "{invalid}"
+            );
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_numeric_constraints() -> Result<(), Box<dyn std::error::Error>> {
+        let source = "
+void f(char *src) {
+    char a[8];
+    char b[0x10];
+    char c[64];
+    char d[0x2000];
+    char e[BUFSIZ];
+}
+";
+        let rule = |constraints: &str| {
+            format!(
+                r#"
+id: local-buffer
+check-pattern:
+  pattern: '{{ char $buf[$1]; }}'
+  constraints: {constraints}
+"#
+            )
+        };
+        let buffers = |rule: String| -> Result<Vec<String>, Box<dyn std::error::Error>> {
+            let mut matcher = RuleMatcher::from_str(rule)?;
+            let mut buffers = matcher
+                .matches(source)?
+                .iter()
+                .filter_map(|m| m.result().value("$buf", m.source_ref()).map(str::to_owned))
+                .collect::<Vec<_>>();
+            buffers.sort();
+            Ok(buffers)
+        };
+
+        // decimal and hex literals, in the source and in the constraint
+        assert_eq!(buffers(rule("'$1 < 16'"))?, ["a"]);
+        assert_eq!(buffers(rule("'$1 <= 0x10'"))?, ["a", "b"]);
+        assert_eq!(buffers(rule("['$1 >= 16', '$1 != 64']"))?, ["b", "d"]);
+        assert_eq!(buffers(rule("'$1 == 8192'"))?, ["d"]);
+
+        // named variables capture identifiers, which never satisfy a constraint...
+        let rule = r#"
+id: local-buffer
+check-pattern:
+  pattern: '{ char $buf[$size]; }'
+  constraints: 'size > 0'
+"#;
+        assert!(buffers(rule.to_owned())?.is_empty());
+
+        // ...unless requested
+        assert_eq!(buffers(format!("{rule}  keep-unparsed: true\n"))?, ["e"]);
+
+        // constraints on variables of other patterns are rejected
+        let Err(error) = Rule::from_str(
+            "id: x\ncheck-pattern:\n  pattern: '{ char $buf[$1]; }'\n  constraints: 'len > 1'\n",
+        ) else {
+            panic!("constraint on `$len` accepted");
+        };
+        let expected = CheckError::InvalidQueryVariable(String::from("$len"));
+        assert!(error.to_string().contains(&expected.to_string()));
+
+        // rules serialize with their constraints, as written
+        let rule = Rule::from_str(format!("{rule}  keep-unparsed: true\n"))?;
+        let yaml = serde_yaml::to_string(&rule)?;
+        assert!(yaml.contains("constraints:\n  - size > 0\n"));
+        assert!(yaml.contains("keep-unparsed: true"));
+        assert_eq!(serde_yaml::to_string(&Rule::from_str(&yaml)?)?, yaml);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_relations() -> Result<(), Box<dyn std::error::Error>> {
+        assert_eq!(normalise(" p -> x "), "p->x");
+        assert_eq!(normalise("unsigned\n\tint"), "unsigned int");
+        assert_eq!(normalise("a [ i + 1 ]"), "a[i+1]");
+
+        let relation = Relation::parse("dst != $src")?;
+        assert_eq!((relation.left(), relation.right()), ("$dst", "$src"));
+        assert!(!relation.is_equality());
+        assert!(relation.holds(Some("a"), Some("b")));
+        assert!(!relation.holds(Some("p->x"), Some("p -> x")));
+        assert!(!relation.holds(Some("a"), None));
+
+        for invalid in ["dst", "dst = src", "dst <= src", "== src", "d st != src"] {
+            assert!(
+                matches!(
+                    Relation::parse(invalid),
+                    Err(CheckError::InvalidRelation(_))
+                ),
+                "{invalid}"
+            );
+        }
+
+        let source = "
+void f(struct s *p, char *a, char *b) {
+    p->x = p -> x;
+    p->x = p->y;
+    a = a;
+    a = b;
+}
+";
+        let found = |relations: &str| -> Result<Vec<String>, Box<dyn std::error::Error>> {
+            let rule = format!(
+                "id: x\ncheck-pattern:\n  pattern: '{{ $dst = $src; }}'\n  relations: {relations}\n"
+            );
+            let mut matcher = RuleMatcher::from_str(&rule)?;
+            let mut found = matcher
+                .matches(source)?
+                .iter()
+                .map(|m| m.matched_text().trim_end_matches(';').to_owned())
+                .collect::<Vec<_>>();
+            found.sort();
+
+            // rules serialize with their relations
+            let yaml = serde_yaml::to_string(&Rule::from_str(&rule)?)?;
+            assert!(yaml.contains("relations:"));
+            assert_eq!(serde_yaml::to_string(&Rule::from_str(&yaml)?)?, yaml);
+
+            Ok(found)
+        };
+
+        // self-assignments...
+        assert_eq!(found("'dst == src'")?, ["a = a", "p->x = p -> x"]);
+        // ...and the others
+        assert_eq!(found("['dst != src']")?, ["a = b", "p->x = p->y"]);
+
+        let Err(error) = Rule::from_str(
+            "id: x\ncheck-pattern:\n  pattern: '{ $dst = $src; }'\n  relations: 'dst != len'\n",
+        ) else {
+            panic!("relation on `$len` accepted");
+        };
+        let expected = CheckError::InvalidQueryVariable(String::from("$len"));
+        assert!(error.to_string().contains(&expected.to_string()));
+
+        Ok(())
+    }
+}
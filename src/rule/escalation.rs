@@ -0,0 +1,180 @@
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use weggli::result::QueryResult;
+
+use crate::rule::{RegexError, Severity};
+
+// An override of the severity of a rule for the matches of a check whose
+// variable captured a text matching the regex, e.g., `memcpy` calls whose
+// length comes from `strlen`; the first applicable entry of a check wins.
+#[derive(Debug, Clone)]
+pub struct Escalation {
+    variable: String,
+    regex: Regex,
+    severity: Severity,
+}
+
+impl Escalation {
+    // e.g., `$len`
+    pub fn variable(&self) -> &str {
+        &self.variable
+    }
+
+    pub fn regex(&self) -> &Regex {
+        &self.regex
+    }
+
+    pub fn severity(&self) -> Severity {
+        self.severity
+    }
+
+    pub fn applies(&self, result: &QueryResult, source: &str) -> bool {
+        result
+            .value(&self.variable, source)
+            .is_some_and(|captured| self.regex.is_match(captured))
+    }
+}
+
+impl PartialEq for Escalation {
+    fn eq(&self, other: &Self) -> bool {
+        self.variable == other.variable
+            && self.regex.as_str() == other.regex.as_str()
+            && self.severity == other.severity
+    }
+}
+
+impl Eq for Escalation {}
+
+#[derive(Debug, Deserialize, Serialize)]
+struct WhenT<S> {
+    var: S,
+    regex: S,
+}
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct EscalationT {
+    when: WhenT<String>,
+    severity: Severity,
+}
+
+impl EscalationT {
+    pub(crate) fn regex(&self) -> &str {
+        &self.when.regex
+    }
+}
+
+impl TryFrom<EscalationT> for Escalation {
+    type Error = RegexError;
+
+    fn try_from(e: EscalationT) -> Result<Self, Self::Error> {
+        let var = e.when.var.trim();
+
+        Ok(Self {
+            // as for regex constraints, `len` stands for `$len`
+            variable: if var.starts_with('$') {
+                var.to_owned()
+            } else {
+                format!("${var}")
+            },
+            regex: Regex::new(&e.when.regex)?,
+            severity: e.severity,
+        })
+    }
+}
+
+impl Serialize for Escalation {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        #[derive(Serialize)]
+        struct EscalationT<'a> {
+            when: WhenT<&'a str>,
+            severity: Severity,
+        }
+
+        EscalationT {
+            when: WhenT {
+                var: self.variable.strip_prefix('$').unwrap_or(&self.variable),
+                regex: self.regex.as_str(),
+            },
+            severity: self.severity,
+        }
+        .serialize(serializer)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::matcher::RuleMatcher;
+    use crate::reporting::RuleMatchReport;
+    use crate::rule::{CheckError, Rule, Severity};
+
+    const RULE: &str = r#"
+id: unbounded-memcpy
+severity: medium
+check-pattern:
+  pattern: '{ memcpy($dst, _, $len); }'
+  escalate:
+  - when: {var: len, regex: 'strlen|recv|read'}
+    severity: high
+  - when: {var: $dst, regex: '^stack_'}
+    severity: critical
+"#;
+
+    #[test]
+    fn test_escalation() -> Result<(), Box<dyn std::error::Error>> {
+        let source = "
+void f(char *dst, char *src, size_t n) {
+    memcpy(dst, src, n);
+    memcpy(dst, src, strlen);
+    memcpy(stack_buf, src, strlen);
+}
+";
+        let mut matcher = RuleMatcher::from_str(RULE)?;
+        let mut matches = matcher.matches(source)?;
+        matches.sort_by_key(|m| m.start_offset());
+
+        // two matches of the same check, with different severities (and the
+        // first applicable entry wins)
+        let severities = matches
+            .iter()
+            .map(|m| m.effective_severity())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            severities,
+            [Severity::Medium, Severity::High, Severity::High]
+        );
+        assert!(matches
+            .iter()
+            .all(|m| m.rule().severity() == Severity::Medium));
+
+        let report = serde_json::to_value(RuleMatchReport::new(&matches[1]))?;
+        assert_eq!(report["severity"], "high");
+
+        // rules whose escalations reach the minimum severity are considered,
+        // but only matches that reach it are reported
+        matcher.set_min_severity(Severity::High);
+        let matches = matcher.matches(source)?;
+        assert_eq!(matches.len(), 2);
+
+        matcher.set_min_severity(Severity::Critical);
+        assert!(matcher.matches(source)?.is_empty());
+
+        // rules serialize with their escalations
+        let rule = Rule::from_str(RULE)?;
+        assert_eq!(rule.max_severity(), Severity::Critical);
+        let yaml = serde_yaml::to_string(&rule)?;
+        assert!(yaml.contains("escalate:"));
+        assert_eq!(serde_yaml::to_string(&Rule::from_str(&yaml)?)?, yaml);
+
+        // escalations refer to variables of the pattern
+        let Err(error) = Rule::from_str(RULE.replace("var: len", "var: size")) else {
+            panic!("escalation on `$size` accepted");
+        };
+        let expected = CheckError::InvalidQueryVariable(String::from("$size"));
+        assert!(error.to_string().contains(&expected.to_string()));
+
+        Ok(())
+    }
+}
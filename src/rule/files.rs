@@ -0,0 +1,263 @@
+use std::fmt::Display;
+use std::path::{Path, PathBuf};
+
+use walkdir::WalkDir;
+
+// Which files of a directory are loaded as rules (see
+// `RuleSet::from_directory_with_filter`): those whose name ends with one of
+// the accepted suffixes, which are the extensions `yml` and `yaml` by
+// default. `extensions` and `suffixes` each replace the accepted suffixes,
+// e.g., `RuleFileFilter::new().suffixes([".weggli.yaml", ".rule"])`.
+// Suffixes are case-sensitive, and never match a whole file name (e.g.,
+// `.yml` alone).
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct RuleFileFilter {
+    suffixes: Vec<String>,
+}
+
+impl Default for RuleFileFilter {
+    fn default() -> Self {
+        Self {
+            suffixes: vec![String::from(".yml"), String::from(".yaml")],
+        }
+    }
+}
+
+impl RuleFileFilter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // e.g., `rule` for `*.rule`
+    pub fn extensions(self, extensions: impl IntoIterator<Item = impl AsRef<str>>) -> Self {
+        self.suffixes(
+            extensions
+                .into_iter()
+                .map(|ext| format!(".{}", ext.as_ref().trim_start_matches('.'))),
+        )
+    }
+
+    // e.g., `.rule.yml` for `*.rule.yml`
+    pub fn suffixes(mut self, suffixes: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        self.suffixes = suffixes.into_iter().map(Into::into).collect();
+        self
+    }
+
+    pub fn suffix_list(&self) -> &[String] {
+        &self.suffixes
+    }
+
+    pub fn matches(&self, path: impl AsRef<Path>) -> bool {
+        let Some(name) = path.as_ref().file_name() else {
+            return false;
+        };
+        let name = name.to_string_lossy();
+
+        self.suffixes
+            .iter()
+            .any(|suffix| name.len() > suffix.len() && name.ends_with(suffix.as_str()))
+    }
+}
+
+// e.g., `*.yml, *.yaml`
+impl Display for RuleFileFilter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let patterns = self
+            .suffixes
+            .iter()
+            .map(|suffix| format!("*{suffix}"))
+            .collect::<Vec<_>>();
+        f.write_str(&patterns.join(", "))
+    }
+}
+
+// How the rules loaded from a directory are keyed in a `RuleSet` (see
+// `RuleSet::from_directory_with_keys`): by their path relative to the
+// directory, with `/` separators, so that keys (and the reports and
+// baselines that carry them) are the same on every machine and platform; or
+// by their native path, as walked (e.g., `C:\rules\copy.yml`).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum RuleKeys {
+    #[default]
+    Relative,
+    Native,
+}
+
+impl RuleKeys {
+    pub fn key(&self, root: &Path, path: &Path) -> String {
+        match self {
+            Self::Relative => relative_key(&root.to_string_lossy(), &path.to_string_lossy()),
+            Self::Native => path.display().to_string(),
+        }
+    }
+}
+
+// `path` relative to `root`, with `/` separators; both `/` and `\` separate
+// components whatever the platform, and `.` components are ignored. A path
+// that is not below `root` keeps all of its components, and `root` itself
+// (i.e., a single file) keeps its file name.
+pub(crate) fn relative_key(root: &str, path: &str) -> String {
+    fn components(path: &str) -> Vec<&str> {
+        path.split(['/', '\\'])
+            .filter(|c| !c.is_empty() && *c != ".")
+            .collect()
+    }
+
+    let root = components(root);
+    let path_components = components(path);
+
+    match path_components.strip_prefix(root.as_slice()) {
+        Some([]) => path_components
+            .last()
+            .copied()
+            .unwrap_or_default()
+            .to_owned(),
+        Some(relative) => relative.join("/"),
+        None => path.replace('\\', "/"),
+    }
+}
+
+// the rule files below `root`, in file name order
+//
+// NOTE: files are filtered while walking, so that others are never opened
+pub(crate) fn rule_files(root: &Path, filter: &RuleFileFilter) -> Vec<PathBuf> {
+    WalkDir::new(root)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_entry(|e| e.file_type().is_dir() || filter.matches(e.path()))
+        .filter_map(Result::ok)
+        .filter(|dirent| !dirent.file_type().is_dir())
+        .map(|dirent| dirent.into_path())
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::{relative_key, RuleFileFilter, RuleKeys};
+
+    #[test]
+    fn test_rule_file_filter() {
+        let default = RuleFileFilter::default();
+        assert_eq!(default, RuleFileFilter::new());
+        assert_eq!(default.to_string(), "*.yml, *.yaml");
+        assert!(default.matches("rules/a.yml") && default.matches("b.yaml"));
+        assert!(!default.matches("a.YML") && !default.matches(".yml"));
+        assert!(!default.matches("a.yml.bak") && !default.matches("rules"));
+
+        let custom = RuleFileFilter::new().extensions([".rule"]);
+        assert_eq!(custom.suffix_list(), [".rule"]);
+        assert!(custom.matches("a.rule") && !custom.matches("a.yml"));
+
+        let suffixes = RuleFileFilter::new().suffixes([".weggli.yaml", ".rule"]);
+        assert_eq!(suffixes.to_string(), "*.weggli.yaml, *.rule");
+        assert!(suffixes.matches("copy.weggli.yaml") && suffixes.matches("copy.rule"));
+        assert!(!suffixes.matches("ci.yaml"));
+    }
+
+    #[test]
+    fn test_rule_files() -> Result<(), Box<dyn std::error::Error>> {
+        use crate::rule::{RuleLimits, RuleSet};
+
+        // by default, the CI config is loaded (and fails to parse), and the
+        // `.rule` files are not
+        assert!(RuleSet::from_directory("tests/rule-files", false).is_err());
+        let rules = RuleSet::from_directory("tests/rule-files", true)?;
+        assert_eq!(
+            rules.iter().map(|(_, rule)| rule.id()).collect::<Vec<_>>(),
+            ["alloc"]
+        );
+
+        let filter = RuleFileFilter::new().suffixes([".rule", ".weggli.yaml"]);
+        let rules = RuleSet::from_directory_with_filter(
+            "tests/rule-files",
+            false,
+            &filter,
+            &RuleLimits::default(),
+        )?;
+        assert_eq!(
+            rules.iter().map(|(_, rule)| rule.id()).collect::<Vec<_>>(),
+            ["alloc", "copy", "format"]
+        );
+
+        let rules = RuleSet::from_directory_with_filter(
+            "tests/rule-files",
+            false,
+            &RuleFileFilter::new().extensions(["rule"]),
+            &RuleLimits::default(),
+        )?;
+        assert_eq!(rules.len(), 2);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_relative_key() {
+        // the same rule below a Windows and a Linux checkout
+        assert_eq!(
+            relative_key(r"C:\Users\ci\rules", r"C:\Users\ci\rules\c\copy.yml"),
+            "c/copy.yml"
+        );
+        assert_eq!(
+            relative_key("/home/ci/rules/", "/home/ci/rules/c/copy.yml"),
+            "c/copy.yml"
+        );
+        assert_eq!(relative_key(r".\rules", r"rules\copy.yml"), "copy.yml");
+        assert_eq!(relative_key("./rules", "./rules/copy.yml"), "copy.yml");
+
+        assert_eq!(relative_key("rules/copy.yml", "rules/copy.yml"), "copy.yml");
+        assert_eq!(relative_key("rules", r"other\copy.yml"), "other/copy.yml");
+    }
+
+    #[test]
+    fn test_rule_keys() -> Result<(), Box<dyn std::error::Error>> {
+        use std::fs;
+        use std::path::Path;
+
+        use crate::rule::{RuleLimits, RuleSet};
+
+        let dir = std::env::temp_dir().join(format!("weggli-ruleset-keys-{}", std::process::id()));
+        fs::create_dir_all(dir.join("c"))?;
+
+        fs::write(
+            dir.join("c/copy.yml"),
+            "id: copy\ncheck-pattern:\n  pattern: '{ strcpy($dst, _); }'\n",
+        )?;
+        fs::write(
+            dir.join("gets.yml"),
+            "id: gets\ncheck-pattern:\n  pattern: '{ gets(_); }'\n",
+        )?;
+
+        let relative = RuleSet::from_directory(&dir, false)?;
+        let dotted = RuleSet::from_directory(dir.join("."), false)?;
+
+        assert!(relative.iter().any(|(key, _)| key == "c/copy.yml"));
+        assert!(relative
+            .iter()
+            .zip(dotted.iter())
+            .all(|((a, _), (b, _))| a == b));
+
+        let native = RuleSet::from_directory_with_keys(
+            &dir,
+            false,
+            &RuleFileFilter::default(),
+            &RuleLimits::default(),
+            RuleKeys::Native,
+        )?;
+
+        for ((relative, rule), (native, _)) in relative.iter().zip(native.iter()) {
+            assert_eq!(native, dir.join(relative).display().to_string());
+            assert_eq!(rule.source_path(), Some(Path::new(native)));
+        }
+
+        let rekeyed = native.rekey(|key, rule| format!("{}:{key}", rule.id()));
+        assert_eq!(rekeyed.len(), native.len());
+        assert!(rekeyed
+            .iter()
+            .zip(native.iter())
+            .all(|((a, rule), (b, _))| a == format!("{}:{b}", rule.id())));
+
+        fs::remove_dir_all(&dir)?;
+
+        Ok(())
+    }
+}
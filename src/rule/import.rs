@@ -0,0 +1,320 @@
+use std::collections::BTreeMap;
+
+use nonempty::NonEmpty;
+use rustc_hash::FxHashSet;
+use thiserror::Error;
+
+use crate::matcher::Fnv;
+use crate::rule::{
+    CheckError, Checker, CheckerLanguage, CheckerT, Mangling, OneOrMany, PathFilter, RegexError,
+    RegexT, Rule, Severity,
+};
+
+#[derive(Debug, Error)]
+pub enum ImportError {
+    #[error("no pattern is given")]
+    MissingPattern,
+    #[error("missing value for `{0}`")]
+    MissingValue(String),
+    #[error("unsupported flag `{0}`")]
+    UnsupportedFlag(String),
+    #[error("regex constraint `{0}` refers to a variable of no pattern")]
+    UnusedRegex(String),
+    #[error("invalid pattern `{0}`: {1}")]
+    Check(String, CheckError),
+}
+
+// Converts the arguments of a weggli invocation, e.g.,
+//
+//   weggli -R 'func=^gets$' '{$func();}' src
+//
+// into a rule with a check for each pattern (the positional one, then those of
+// `-p/--pattern`, in order), each constrained by the `-R/--regex` constraints
+// on its variables; `-X/--cpp`, `-u/--unique`, and `-l/--limit` apply to all
+// checks. The id is derived from the arguments, e.g., `weggli-1a2b3c4d`.
+//
+// NOTE: weggli reports a file only if all patterns match it, while each check
+// reports its own findings; the paths to search (the positional arguments
+// after the first, and a leading `weggli`) are ignored, and flags that do not
+// translate into the rule (e.g., `-A`, `--include`) are errors
+pub fn from_weggli_args(args: &[&str]) -> Result<Rule, ImportError> {
+    let args = match args.split_first() {
+        Some((program, args)) if program.rsplit(['/', '\\']).next() == Some("weggli") => args,
+        _ => args,
+    };
+
+    let mut patterns = Vec::new();
+    let mut additional = Vec::new();
+    let mut regexes = Vec::new();
+    let mut language = CheckerLanguage::C;
+    let mut unique = false;
+    let mut limit = false;
+
+    let mut args = args.iter().copied();
+    let mut positional_only = false;
+
+    while let Some(arg) = args.next() {
+        if positional_only || arg == "-" || !arg.starts_with('-') {
+            // the pattern, then the paths to search
+            if patterns.is_empty() {
+                patterns.push(arg.to_owned());
+            }
+            continue;
+        }
+
+        // accepts both `--option value` and `--option=value`
+        let (name, inline) = match arg.split_once('=') {
+            Some((name, value)) if name.starts_with("--") => (name, Some(value)),
+            _ => (arg, None),
+        };
+
+        let mut value = || {
+            inline
+                .or_else(|| args.next())
+                .map(str::to_owned)
+                .ok_or_else(|| ImportError::MissingValue(name.to_owned()))
+        };
+
+        match name {
+            "--" => positional_only = true,
+            "-p" | "--pattern" => additional.push(value()?),
+            "-R" | "--regex" => regexes.push(value()?),
+            "-X" | "--cpp" => language = CheckerLanguage::Cplusplus,
+            "-u" | "--unique" => unique = true,
+            "-l" | "--limit" => limit = true,
+            _ => return Err(ImportError::UnsupportedFlag(arg.to_owned())),
+        }
+    }
+
+    patterns.extend(additional);
+
+    if patterns.is_empty() {
+        return Err(ImportError::MissingPattern);
+    }
+
+    // constraints are shared by all patterns in weggli, but a check may only
+    // constrain its own variables
+    let mut used = FxHashSet::default();
+    let mut checks = Vec::with_capacity(patterns.len());
+
+    for (i, pattern) in patterns.iter().enumerate() {
+        let unconstrained = Checker::try_from(CheckerT {
+            name: String::from("default"),
+            language,
+            pattern: pattern.to_owned(),
+            ts_query: None,
+            report_capture: None,
+            match_regex: None,
+            match_string: None,
+            code_only: false,
+            identifiers: None,
+            regexes: None,
+            limit,
+            unique,
+            unique_by: None,
+            transform: None,
+            constraints: None,
+            keep_unparsed: false,
+            max_source_bytes: None,
+            auto_wrap: true,
+            relations: None,
+            min_function_lines: None,
+            max_function_lines: None,
+            unless_in_function: None,
+            escalate: None,
+            mangling: Mangling::None,
+        })
+        .map_err(|e| ImportError::Check(pattern.to_owned(), e))?;
+
+        let mut constraints = Vec::new();
+        for regex in &regexes {
+            let variable = regex_variable(regex)
+                .map_err(|e| ImportError::Check(pattern.to_owned(), e.into()))?;
+
+            if unconstrained.variables().contains(&variable) {
+                used.insert(regex.as_str());
+                constraints.push(RegexT::Plain(regex.to_owned()));
+            }
+        }
+
+        let name = if patterns.len() == 1 {
+            String::from("default")
+        } else {
+            format!("pattern-{}", i + 1)
+        };
+
+        let checker = Checker::try_from(CheckerT {
+            name,
+            language,
+            pattern: pattern.to_owned(),
+            ts_query: None,
+            report_capture: None,
+            match_regex: None,
+            match_string: None,
+            code_only: false,
+            identifiers: None,
+            regexes: NonEmpty::from_vec(constraints).map(OneOrMany::Many),
+            limit,
+            unique,
+            unique_by: None,
+            transform: None,
+            constraints: None,
+            keep_unparsed: false,
+            max_source_bytes: None,
+            auto_wrap: true,
+            relations: None,
+            min_function_lines: None,
+            max_function_lines: None,
+            unless_in_function: None,
+            escalate: None,
+            mangling: Mangling::None,
+        });
+
+        checks.push(checker.map_err(|e| ImportError::Check(pattern.to_owned(), e))?);
+    }
+
+    if let Some(regex) = regexes.iter().find(|r| !used.contains(r.as_str())) {
+        return Err(ImportError::UnusedRegex(regex.to_owned()));
+    }
+
+    let mut hasher = Fnv::new();
+    for pattern in &patterns {
+        hasher.field(pattern);
+    }
+    for regex in &regexes {
+        hasher.field(regex);
+    }
+    hasher.field(&format!("{language}/{unique}/{limit}"));
+
+    Ok(Rule {
+        id: format!("weggli-{:08x}", hasher.finish() >> 32),
+        author: String::new(),
+        description: String::new(),
+        severity: Severity::default(),
+        tags: FxHashSet::default(),
+        paths: PathFilter::default(),
+        max_file_size: None,
+        metadata: BTreeMap::new(),
+        mangling: Mangling::None,
+        checks: checks.into_boxed_slice(),
+        condition: None,
+        source_path: None,
+    })
+}
+
+// the variable of a `var=regex` constraint, as weggli names it (e.g., `$func`
+// for both `func=...` and `func!=...`)
+fn regex_variable(regex: &str) -> Result<String, RegexError> {
+    let (variable, _) = regex
+        .split_once('=')
+        .ok_or_else(|| RegexError::InvalidFormat(regex.to_owned()))?;
+
+    let variable = variable.trim();
+    let variable = variable.strip_suffix('!').unwrap_or(variable);
+
+    Ok(if variable.starts_with('$') {
+        variable.to_owned()
+    } else {
+        format!("${variable}")
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::{from_weggli_args, ImportError};
+    use crate::matcher::RuleMatcher;
+
+    #[test]
+    fn test_from_weggli_args() -> Result<(), Box<dyn std::error::Error>> {
+        // as in weggli's documentation: calls to strcpy or stpcpy
+        let rule = from_weggli_args(&[
+            "weggli",
+            "--unique",
+            "-R",
+            "func=^st(r|p)cpy$",
+            "{ $func($dst, _); }",
+            "tests",
+        ])?;
+
+        assert!(rule.id().starts_with("weggli-"));
+        assert_eq!(rule.checks().len(), 1);
+        assert!(rule.checks()[0].unique() && !rule.checks()[0].limit());
+
+        // the rule serializes into YAML that finds what the original rule does
+        let yaml = serde_yaml::to_string(&rule)?;
+        let mut imported = RuleMatcher::from_str(&yaml)?;
+        let mut original = RuleMatcher::from_str(
+            r#"
+id: unbounded-copy
+check-patterns:
+- name: strcpy
+  regex: func=^st(r|p)cpy$
+  pattern: |
+    { $func($dst, _); }
+- name: gets
+  regex: func=^gets$
+  pattern: |
+    { $func(_); }
+"#,
+        )?;
+
+        let source = std::fs::read_to_string("tests/utf8-comments.c")?;
+        let offsets = |matcher: &mut RuleMatcher| -> Result<Vec<_>, Box<dyn std::error::Error>> {
+            Ok(matcher
+                .matches(&source)?
+                .iter()
+                .filter(|m| m.checker().name() != "gets")
+                .map(|m| (m.start_offset(), m.end_offset()))
+                .collect())
+        };
+        let found = offsets(&mut imported)?;
+        assert_eq!(found.len(), 2);
+        assert_eq!(found, offsets(&mut original)?);
+
+        // constraints apply to the patterns with their variable
+        let rule = from_weggli_args(&[
+            "-X",
+            "--regex=func=^gets$",
+            "{ $func(_); }",
+            "--pattern",
+            "{ char $buf[_]; }",
+            "-l",
+        ])?;
+
+        assert_eq!(rule.checks().len(), 2);
+        assert_eq!(rule.checks()[0].name(), "pattern-1");
+        assert!(rule.checks()[1].language().is_cxx() && rule.checks()[1].limit());
+
+        let yaml = serde_yaml::to_string(&rule)?;
+        assert_eq!(yaml.matches("regex:").count(), 1);
+
+        // the same arguments yield the same id
+        assert_eq!(
+            from_weggli_args(&["{ $f(); }"])?.id(),
+            from_weggli_args(&["{ $f(); }", "src"])?.id()
+        );
+
+        assert!(matches!(
+            from_weggli_args(&["-A", "3", "{ $f(); }"]),
+            Err(ImportError::UnsupportedFlag(flag)) if flag == "-A"
+        ));
+        assert!(matches!(
+            from_weggli_args(&["-R", "x=^a$", "{ $f(); }"]),
+            Err(ImportError::UnusedRegex(_))
+        ));
+        assert!(matches!(
+            from_weggli_args(&["-R"]),
+            Err(ImportError::MissingValue(_))
+        ));
+        assert!(matches!(
+            from_weggli_args(&["-u"]),
+            Err(ImportError::MissingPattern)
+        ));
+        assert!(matches!(
+            from_weggli_args(&["{ $f( }"]),
+            Err(ImportError::Check(..))
+        ));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,313 @@
+use std::fmt::Display;
+
+use crate::rule::{CheckerT, EscalationT, OneOrMany, RegexT, RuleError};
+
+// What a `RuleLimits` bounds (see `RuleError::LimitExceeded`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LimitKind {
+    Checks,
+    PatternLength,
+    RegexLength,
+    Rules,
+}
+
+impl Display for LimitKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Checks => "number of checks",
+            Self::PatternLength => "pattern length",
+            Self::RegexLength => "regex length",
+            Self::Rules => "number of rules",
+        })
+    }
+}
+
+// Limits on the rules accepted by the loading functions (e.g.,
+// `Rule::from_str_with_limits`), so that services loading untrusted rules
+// are not exhausted by pathological ones: the checks of a rule, the length
+// (in bytes) of each pattern (including those of `unless-in-function`,
+// numeric constraints, tree-sitter queries, and `match-string`) and of each
+// regex (including those of `escalate`, and `match-regex`), and the rule files
+// of a directory. They are checked before the patterns and regexes are
+// compiled, and the defaults are far above those of any real rule set.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct RuleLimits {
+    max_checks: usize,
+    max_pattern_len: usize,
+    max_regex_len: usize,
+    max_rules: usize,
+}
+
+impl Default for RuleLimits {
+    fn default() -> Self {
+        Self {
+            max_checks: 1024,
+            max_pattern_len: 64 * 1024,
+            max_regex_len: 16 * 1024,
+            max_rules: 100_000,
+        }
+    }
+}
+
+impl RuleLimits {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn max_checks(mut self, max_checks: usize) -> Self {
+        self.max_checks = max_checks;
+        self
+    }
+
+    pub fn max_pattern_len(mut self, max_pattern_len: usize) -> Self {
+        self.max_pattern_len = max_pattern_len;
+        self
+    }
+
+    pub fn max_regex_len(mut self, max_regex_len: usize) -> Self {
+        self.max_regex_len = max_regex_len;
+        self
+    }
+
+    pub fn max_rules(mut self, max_rules: usize) -> Self {
+        self.max_rules = max_rules;
+        self
+    }
+
+    pub fn max_checks_limit(&self) -> usize {
+        self.max_checks
+    }
+
+    pub fn max_pattern_len_limit(&self) -> usize {
+        self.max_pattern_len
+    }
+
+    pub fn max_regex_len_limit(&self) -> usize {
+        self.max_regex_len
+    }
+
+    pub fn max_rules_limit(&self) -> usize {
+        self.max_rules
+    }
+
+    pub(crate) fn check(kind: LimitKind, limit: usize, actual: usize) -> Result<(), RuleError> {
+        if actual > limit {
+            return Err(RuleError::LimitExceeded {
+                kind,
+                limit,
+                actual,
+            });
+        }
+        Ok(())
+    }
+
+    pub(super) fn check_checks(&self, checks: &OneOrMany<CheckerT>) -> Result<(), RuleError> {
+        Self::check(LimitKind::Checks, self.max_checks, checks.len())?;
+        checks.iter().try_for_each(|c| self.check_checker(c))
+    }
+
+    fn check_checker(&self, checker: &CheckerT) -> Result<(), RuleError> {
+        let patterns = std::iter::once(&checker.pattern)
+            .chain(
+                checker
+                    .unless_in_function
+                    .iter()
+                    .flat_map(|patterns| patterns.iter()),
+            )
+            .chain(
+                checker
+                    .constraints
+                    .iter()
+                    .flat_map(|constraints| constraints.iter()),
+            )
+            .chain(&checker.ts_query)
+            .chain(&checker.match_string);
+        for pattern in patterns {
+            Self::check(
+                LimitKind::PatternLength,
+                self.max_pattern_len,
+                pattern.len(),
+            )?;
+        }
+
+        let regexes = checker
+            .regexes
+            .iter()
+            .flat_map(|regexes| regexes.iter())
+            .map(|regex| match regex {
+                RegexT::Plain(regex) | RegexT::Structured { regex, .. } => regex.as_str(),
+            })
+            .chain(
+                checker
+                    .escalate
+                    .iter()
+                    .flat_map(|escalations| escalations.iter())
+                    .map(EscalationT::regex),
+            )
+            .chain(checker.match_regex.as_deref());
+        for regex in regexes {
+            Self::check(LimitKind::RegexLength, self.max_regex_len, regex.len())?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{LimitKind, RuleLimits};
+    use crate::rule::{Rule, RuleError};
+
+    fn rule(checks: usize, pattern: &str, regex: &str) -> String {
+        let mut rule = String::from("id: limits\ncheck-patterns:\n");
+        for i in 0..checks {
+            rule.push_str(&format!(
+                "- name: c{i}\n  pattern: '{pattern}'\n  regex: '{regex}'\n"
+            ));
+        }
+        rule
+    }
+
+    fn exceeded(result: Result<Rule, RuleError>) -> Option<String> {
+        match result {
+            Ok(_) => None,
+            Err(e) => Some(e.to_string()),
+        }
+    }
+
+    #[test]
+    fn test_rule_limits() {
+        let pattern = "{ strcpy($dst, _); }";
+        let regex = "dst=^buf$";
+        let limits = RuleLimits::new()
+            .max_checks(3)
+            .max_pattern_len(pattern.len())
+            .max_regex_len(regex.len());
+
+        // exactly at the limits
+        assert!(Rule::from_str_with_limits(rule(3, pattern, regex), &limits).is_ok());
+
+        // one over each limit
+        let over = [
+            (
+                rule(4, pattern, regex),
+                "number of checks of 4 exceeds the limit of 3",
+            ),
+            (
+                rule(1, "{ strcpy($dst,  _); }", regex),
+                "pattern length of 21 exceeds the limit of 20",
+            ),
+            (
+                rule(1, pattern, "dst=^bufx$"),
+                "regex length of 10 exceeds the limit of 9",
+            ),
+        ];
+        for (rule, expected) in over {
+            let error = exceeded(Rule::from_str_with_limits(&rule, &limits));
+            assert!(
+                error.as_ref().is_some_and(|e| e.contains(expected)),
+                "{error:?}"
+            );
+
+            // the default limits are generous
+            assert!(Rule::from_str(&rule).is_ok());
+        }
+
+        // patterns of `unless-in-function` are bounded too
+        let unless = format!(
+            "{}  unless-in-function: '{{ strcpy($dst,  _); }}'\n",
+            rule(1, pattern, regex)
+        );
+        assert!(exceeded(Rule::from_str_with_limits(&unless, &limits))
+            .is_some_and(|e| e.contains("pattern length of 21")));
+
+        // and so are numeric constraints
+        let constraint = format!(
+            "{}  constraints: ['$dst < 0x1000000000000000']\n",
+            rule(1, pattern, regex)
+        );
+        assert!(exceeded(Rule::from_str_with_limits(&constraint, &limits))
+            .is_some_and(|e| e.contains("pattern length of 25")));
+
+        // and the regexes of `escalate`
+        let escalate = format!(
+            "{}  escalate:\n  - when: {{var: dst, regex: '^buffers$'}}\n    severity: high\n",
+            rule(1, pattern, regex)
+        );
+        assert!(Rule::from_str_with_limits(&escalate, &limits).is_ok());
+        let escalate = escalate.replace("^buffers$", "^bufferss$");
+        assert!(exceeded(Rule::from_str_with_limits(&escalate, &limits))
+            .is_some_and(|e| e.contains("regex length of 10")));
+
+        // and tree-sitter queries
+        let query = "id: limits\ncheck-pattern:\n  ts-query: '(call_expression) @call'\n";
+        assert!(exceeded(Rule::from_str_with_limits(query, &limits))
+            .is_some_and(|e| e.contains("pattern length of 23")));
+
+        // and the text of `match-regex` and `match-string` checks
+        let text = |key: &str, value: &str| {
+            Rule::from_str_with_limits(
+                format!("id: limits\ncheck-pattern:\n  {key}: '{value}'\n"),
+                &limits,
+            )
+        };
+        assert!(text("match-regex", "^strcpy\\(").is_ok());
+        assert!(exceeded(text("match-regex", "\\bstrcpy\\("))
+            .is_some_and(|e| e.contains("regex length of 10")));
+        assert!(text("match-string", "strcpy(dst, src)").is_ok());
+        assert!(exceeded(text("match-string", "strcpy(dst, sources);"))
+            .is_some_and(|e| e.contains("pattern length of 21")));
+
+        assert_eq!(LimitKind::Rules.to_string(), "number of rules");
+    }
+
+    #[cfg(feature = "std-fs")]
+    #[test]
+    fn test_rule_limits_directory() -> Result<(), Box<dyn std::error::Error>> {
+        use std::fs;
+
+        use crate::rule::RuleSet;
+
+        // two rule files, of one check and of two
+        let dir =
+            std::env::temp_dir().join(format!("weggli-ruleset-limits-{}", std::process::id()));
+        fs::create_dir_all(&dir)?;
+        fs::write(
+            dir.join("one.yml"),
+            rule(1, "{ strcpy($dst, _); }", "dst=^buf$"),
+        )?;
+        fs::write(
+            dir.join("two.yml"),
+            rule(2, "{ gets($buf); }", "buf=^line$"),
+        )?;
+
+        let at = RuleLimits::new().max_rules(2);
+        assert_eq!(
+            RuleSet::from_directory_with_limits(&dir, false, &at)?.len(),
+            2
+        );
+
+        let over = RuleLimits::new().max_rules(1);
+        let Err(RuleError::LimitExceeded {
+            kind,
+            limit,
+            actual,
+        }) = RuleSet::from_directory_with_limits(&dir, true, &over)
+        else {
+            panic!("rule limit not enforced");
+        };
+        assert_eq!((kind, limit, actual), (LimitKind::Rules, 1, 2));
+
+        // rules over a limit are skipped with `ignore_errors`
+        let checks = RuleLimits::new().max_checks(1);
+        assert_eq!(
+            RuleSet::from_directory_with_limits(&dir, true, &checks)?.len(),
+            1
+        );
+        assert!(RuleSet::from_directory_with_limits(&dir, false, &checks).is_err());
+
+        fs::remove_dir_all(&dir)?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,158 @@
+use std::path::Path;
+
+use globset::{Glob, GlobBuilder, GlobSet, GlobSetBuilder};
+use serde::{Deserialize, Serialize};
+
+use crate::rule::RuleError;
+
+// Path constraints for a rule.
+//
+// A rule applies to a path if no `exclude` pattern matches it and, when
+// `include` patterns are given, at least one of them matches it; exclusion
+// always wins. Patterns starting with `/` must match the whole path as given
+// to the matcher; other patterns may match any trailing portion of it, i.e.,
+// `third_party/**` behaves as `**/third_party/**`. Paths are compared using
+// `/` as separator, and `*` does not match across separators.
+#[derive(Debug, Clone, Default)]
+pub struct PathFilter {
+    include: Vec<String>,
+    exclude: Vec<String>,
+    include_set: Option<GlobSet>,
+    exclude_set: Option<GlobSet>,
+}
+
+impl PathFilter {
+    pub fn new(
+        include: impl IntoIterator<Item = impl Into<String>>,
+        exclude: impl IntoIterator<Item = impl Into<String>>,
+    ) -> Result<Self, RuleError> {
+        let include = include.into_iter().map(Into::into).collect::<Vec<_>>();
+        let exclude = exclude.into_iter().map(Into::into).collect::<Vec<_>>();
+
+        Ok(Self {
+            include_set: build_glob_set(&include)?,
+            exclude_set: build_glob_set(&exclude)?,
+            include,
+            exclude,
+        })
+    }
+
+    pub fn include(&self) -> &[String] {
+        &self.include
+    }
+
+    pub fn exclude(&self) -> &[String] {
+        &self.exclude
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.include.is_empty() && self.exclude.is_empty()
+    }
+
+    pub fn matches(&self, path: impl AsRef<Path>) -> bool {
+        if self.is_empty() {
+            return true;
+        }
+
+        let path = path.as_ref().to_string_lossy().replace('\\', "/");
+
+        if matches!(self.exclude_set, Some(ref set) if set.is_match(&path)) {
+            return false;
+        }
+
+        self.include_set
+            .as_ref()
+            .is_none_or(|set| set.is_match(&path))
+    }
+}
+
+fn build_glob(pattern: &str) -> Result<Glob, RuleError> {
+    GlobBuilder::new(pattern)
+        .literal_separator(true)
+        .build()
+        .map_err(RuleError::from)
+}
+
+fn build_glob_set(patterns: &[String]) -> Result<Option<GlobSet>, RuleError> {
+    if patterns.is_empty() {
+        return Ok(None);
+    }
+
+    let mut builder = GlobSetBuilder::new();
+
+    for pattern in patterns {
+        let pattern = pattern.replace('\\', "/");
+
+        builder.add(build_glob(&pattern)?);
+
+        if !pattern.starts_with('/') && !pattern.starts_with("**/") {
+            builder.add(build_glob(&format!("**/{pattern}"))?);
+        }
+    }
+
+    Ok(Some(builder.build()?))
+}
+
+#[derive(Deserialize, Serialize)]
+struct PathFilterT {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    include: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    exclude: Vec<String>,
+}
+
+impl<'de> Deserialize<'de> for PathFilter {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let filter = PathFilterT::deserialize(deserializer)?;
+        PathFilter::new(filter.include, filter.exclude)
+            .map_err(<D::Error as serde::de::Error>::custom)
+    }
+}
+
+impl Serialize for PathFilter {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        PathFilterT {
+            include: self.include.clone(),
+            exclude: self.exclude.clone(),
+        }
+        .serialize(serializer)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::PathFilter;
+
+    #[test]
+    fn test_path_filter() -> Result<(), Box<dyn std::error::Error>> {
+        let filter = PathFilter::new(["drivers/**", "/opt/sdk/*.c"], ["**/third_party/**"])?;
+
+        assert!(filter.matches("drivers/net/e1000.c"));
+        assert!(filter.matches("/src/linux/drivers/net/e1000.c"));
+        assert!(filter.matches("C:\\src\\linux\\drivers\\net\\e1000.c"));
+        assert!(filter.matches("/opt/sdk/main.c"));
+
+        assert!(!filter.matches("/home/opt/sdk/main.c"));
+        assert!(!filter.matches("/opt/sdk/lib/main.c"));
+        assert!(!filter.matches("kernel/fork.c"));
+
+        // exclusion wins
+        assert!(!filter.matches("drivers/third_party/zlib/inflate.c"));
+
+        let exclude_only = PathFilter::new(Vec::<String>::new(), ["*.h"])?;
+
+        assert!(exclude_only.matches("kernel/fork.c"));
+        assert!(!exclude_only.matches("include/linux/sched.h"));
+
+        assert!(PathFilter::default().matches("anything.c"));
+        assert!(PathFilter::new(["[a-"], Vec::<String>::new()).is_err());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,114 @@
+use std::fmt::Display;
+
+use serde::{Deserialize, Serialize};
+
+// The size of the largest source a rule is run on (see `max-file-size`),
+// written either as a number of bytes or with a unit: `512k`, `2MB`, or
+// `1GiB`. Units are case-insensitive and binary, i.e., `k`, `kb`, and `kib`
+// all stand for 1024 bytes.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct FileSize {
+    bytes: usize,
+    // as written in the rule, to serialize it
+    source: SizeT,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(untagged)]
+pub(crate) enum SizeT {
+    Bytes(usize),
+    Text(String),
+}
+
+impl Display for SizeT {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Bytes(bytes) => write!(f, "{bytes}"),
+            Self::Text(text) => f.write_str(text),
+        }
+    }
+}
+
+const UNITS: [(&[&str], usize); 4] = [
+    (&["", "b"], 1),
+    (&["k", "kb", "kib"], 1 << 10),
+    (&["m", "mb", "mib"], 1 << 20),
+    (&["g", "gb", "gib"], 1 << 30),
+];
+
+impl FileSize {
+    pub fn bytes(&self) -> usize {
+        self.bytes
+    }
+
+    // NOTE: returns `None` for an invalid size, which the caller reports
+    // along with the rule it belongs to
+    pub(crate) fn parse(source: SizeT) -> Option<Self> {
+        let bytes = match &source {
+            SizeT::Bytes(bytes) => *bytes,
+            SizeT::Text(text) => parse_size(text)?,
+        };
+
+        Some(Self { bytes, source })
+    }
+}
+
+// e.g., `2MB` or `512 k`
+fn parse_size(text: &str) -> Option<usize> {
+    let text = text.trim();
+    let digits = text
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(text.len());
+    let (number, unit) = text.split_at(digits);
+
+    let number = number.parse::<usize>().ok()?;
+    let unit = unit.trim_start().to_ascii_lowercase();
+    let (_, multiplier) = UNITS
+        .iter()
+        .find(|(names, _)| names.contains(&unit.as_str()))?;
+
+    number.checked_mul(*multiplier)
+}
+
+impl Serialize for FileSize {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        self.source.serialize(serializer)
+    }
+}
+
+impl Display for FileSize {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.source.fmt(f)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::parse_size;
+
+    #[test]
+    fn test_parse_size() {
+        for (text, expected) in [
+            ("0", Some(0)),
+            ("4096", Some(4096)),
+            ("100b", Some(100)),
+            ("512k", Some(512 * 1024)),
+            ("512 KiB", Some(512 * 1024)),
+            ("2MB", Some(2 * 1024 * 1024)),
+            (" 2 mb ", Some(2 * 1024 * 1024)),
+            ("1GiB", Some(1 << 30)),
+            ("", None),
+            ("MB", None),
+            ("1.5MB", None),
+            ("-1", None),
+            ("2 TB", None),
+            ("2MB2", None),
+            ("99999999999999999999G", None),
+        ] {
+            assert_eq!(parse_size(text), expected, "{text:?}");
+        }
+    }
+}
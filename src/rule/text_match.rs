@@ -0,0 +1,244 @@
+use std::borrow::Cow;
+use std::collections::HashSet;
+
+use regex::Regex;
+use rustc_hash::FxHashMap;
+use weggli::result::{CaptureResult, QueryResult};
+
+use crate::preprocess::comments;
+use crate::rule::{CheckError, RegexError};
+
+// A check written as a regex (`match-regex`) or a literal (`match-string`)
+// rather than a pattern, for findings that really are just text (e.g.,
+// `rand() %`): it searches the source as is, without parsing it, and, with
+// `code-only`, not within comments or string and character literals. Named
+// groups of the regex are the check's variables (`(?P<n>...)` is `$n`).
+//
+// NOTE: if the source cannot be tokenized (e.g., it has an unterminated
+// comment), `code-only` checks search it as is
+pub(crate) struct TextMatch {
+    regex: Regex,
+    // as written in the rule
+    source: Box<str>,
+    literal: bool,
+    code_only: bool,
+}
+
+impl TextMatch {
+    pub(crate) fn regex(source: &str, code_only: bool) -> Result<Self, CheckError> {
+        Ok(Self {
+            regex: Regex::new(source).map_err(RegexError::from)?,
+            source: source.into(),
+            literal: false,
+            code_only,
+        })
+    }
+
+    pub(crate) fn string(source: &str, code_only: bool) -> Result<Self, CheckError> {
+        Ok(Self {
+            regex: Regex::new(&regex::escape(source)).map_err(RegexError::from)?,
+            source: source.into(),
+            literal: true,
+            code_only,
+        })
+    }
+
+    pub(crate) fn match_regex(&self) -> Option<&str> {
+        (!self.literal).then_some(&*self.source)
+    }
+
+    pub(crate) fn match_string(&self) -> Option<&str> {
+        self.literal.then_some(&*self.source)
+    }
+
+    pub(crate) fn code_only(&self) -> bool {
+        self.code_only
+    }
+
+    pub(crate) fn variables(&self) -> HashSet<String> {
+        self.regex
+            .capture_names()
+            .flatten()
+            .map(|name| format!("${name}"))
+            .collect()
+    }
+
+    // a literal searched for in code only must occur in the source, even with
+    // `PrefilterMode::CodeOnly`; the literals of a regex are not derived
+    pub(crate) fn identifiers(&self) -> Vec<String> {
+        if self.literal && self.code_only && !self.source.is_empty() {
+            vec![self.source.to_string()]
+        } else {
+            Vec::new()
+        }
+    }
+
+    // as weggli's, the first capture of a result spans the whole match (here,
+    // as does the second), and the variables refer to the others
+    pub(crate) fn matches(&self, source: &str) -> Vec<QueryResult> {
+        let text = if self.code_only {
+            comments::blank_comments_and_literals(source).unwrap_or(Cow::Borrowed(source))
+        } else {
+            Cow::Borrowed(source)
+        };
+
+        self.regex
+            .captures_iter(&text)
+            .filter_map(|c| {
+                let whole = c.get(0).filter(|m| !m.is_empty())?.range();
+                let capture = |range, index| CaptureResult {
+                    range,
+                    query_id: 0,
+                    capture_idx: index,
+                };
+
+                let mut captures = vec![capture(whole.clone(), 0), capture(whole.clone(), 0)];
+                let mut vars = FxHashMap::default();
+
+                for (index, name) in self.regex.capture_names().enumerate() {
+                    if let (Some(name), Some(group)) = (name, c.get(index)) {
+                        captures.push(capture(group.range(), index));
+                        vars.insert(format!("${name}"), captures.len() - 1);
+                    }
+                }
+
+                Some(QueryResult::new(captures, vars, whole))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::matcher::RuleMatcher;
+    use crate::rule::Rule;
+
+    const SOURCE: &str = r#"int roll(void)
+{
+  // rand() % 6 is biased
+  const char *msg = "rand() % 6";
+  int a = rand() % 6;
+  int b = rand()%  100;
+  return a + b;
+}
+"#;
+
+    fn lines(rule: &str) -> Result<Vec<usize>, Box<dyn std::error::Error>> {
+        let mut matcher = RuleMatcher::from_str(rule)?;
+        Ok(matcher
+            .matches(SOURCE)?
+            .iter()
+            .map(|m| m.start_line())
+            .collect())
+    }
+
+    #[test]
+    fn test_text_match() -> Result<(), Box<dyn std::error::Error>> {
+        let rule = r#"
+id: modulo-bias
+check-pattern:
+  match-regex: 'rand\(\)\s*%'
+"#;
+        assert_eq!(lines(rule)?, [3, 4, 5, 6]);
+
+        let code_only = format!("{rule}  code-only: true\n");
+        assert_eq!(lines(&code_only)?, [5, 6]);
+
+        // offsets are those of the source, and the source is not parsed
+        let mut matcher = RuleMatcher::from_str(&code_only)?;
+        let found = matcher.matches(SOURCE)?;
+        assert_eq!(found[0].matched_text(), "rand() %");
+        assert_eq!(found[1].matched_text(), "rand()%");
+        assert_eq!((found[1].start_column(), found[1].end_column()), (11, 18));
+        assert!(found[0]
+            .display(0, 0, false)
+            .contains("int a = rand() % 6;"));
+        assert_eq!(matcher.last_metrics().parsed(), 0);
+
+        // literals, and `unique` is a no-op
+        let string = r#"
+id: modulo-bias
+check-pattern:
+  match-string: 'rand() %'
+  code-only: true
+  unique: true
+"#;
+        assert_eq!(lines(string)?, [5]);
+        assert_eq!(
+            Rule::from_str(string)?.checks()[0].identifiers(),
+            ["rand() %"]
+        );
+
+        // `limit` keeps a match per offset, and so (as the matches of a regex
+        // never overlap) every match, even several on a line
+        let source = "int r = rand() % 6 + rand() % 3;\nint s = rand() % 2;\n";
+        let offsets = |rule: &str| -> Result<Vec<usize>, Box<dyn std::error::Error>> {
+            let mut matcher = RuleMatcher::from_str(rule)?;
+            Ok(matcher
+                .matches(source)?
+                .iter()
+                .map(|m| m.start_offset())
+                .collect())
+        };
+        let limited = format!("{rule}  limit: true\n");
+        assert_eq!(offsets(&limited)?, [8, 21, 41]);
+        assert_eq!(offsets(&limited)?, offsets(rule)?);
+
+        // named groups are variables
+        let named = r#"
+id: modulo-bias
+check-pattern:
+  match-regex: '(?P<func>s?rand)\(\)\s*(?P<op>%)'
+  regex: op=^%$
+"#;
+        let mut matcher = RuleMatcher::from_str(named)?;
+        let found = matcher.matches(SOURCE)?;
+        assert_eq!(found[0].capture("$func"), Some("rand"));
+        assert!(Rule::from_str(named.replace("op=", "other=")).is_err());
+
+        // rules serialize with their regexes
+        for rule in [code_only.as_str(), string] {
+            let yaml = serde_yaml::to_string(&Rule::from_str(rule)?)?;
+            assert_eq!(serde_yaml::to_string(&Rule::from_str(&yaml)?)?, yaml);
+            assert_eq!(lines(&yaml)?, lines(rule)?);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_text_match_mixed() -> Result<(), Box<dyn std::error::Error>> {
+        let rule = r#"
+id: modulo-bias
+check-patterns:
+- name: text
+  match-regex: 'rand\(\)\s*%'
+  code-only: true
+- name: pattern
+  pattern: '{ rand(); }'
+"#;
+
+        let mut matcher = RuleMatcher::from_str(rule)?;
+        let found = matcher.matches(SOURCE)?;
+        let lines = |name| {
+            found
+                .iter()
+                .filter(|m| m.checker().name() == name)
+                .map(|m| m.start_line())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(lines("text"), [5, 6]);
+        assert_eq!(lines("pattern"), [5, 6]);
+        assert_eq!(matcher.last_metrics().parsed(), 1);
+
+        // exactly one kind of check
+        let both = "id: both\ncheck-pattern:\n  pattern: '{ rand(); }'\n  match-string: rand\n";
+        assert!(Rule::from_str(both).is_err());
+        let misplaced =
+            "id: misplaced\ncheck-pattern:\n  pattern: '{ rand(); }'\n  code-only: true\n";
+        assert!(Rule::from_str(misplaced).is_err());
+        assert!(Rule::from_str("id: invalid\ncheck-pattern:\n  match-regex: '('\n").is_err());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,400 @@
+use std::borrow::Cow;
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use crate::rule::{OneOrMany, RegexError};
+
+// Rewrites of the text captured by a variable before a regex constraint tests
+// it, e.g., to see through the wrappers decompilers emit (`j__strcpy`,
+// `_strcpy`, `strcpy_0`): the prefixes are stripped in order (each at most
+// once), then the first match of the suffix regex if it extends to the end of
+// the text, and the rest is lowercased if requested.
+#[derive(Debug, Clone, Default)]
+pub struct CaptureTransform {
+    strip_prefix: Box<[String]>,
+    strip_suffix_regex: Option<Regex>,
+    lowercase: bool,
+}
+
+impl CaptureTransform {
+    pub fn strip_prefix(&self) -> &[String] {
+        &self.strip_prefix
+    }
+
+    pub fn strip_suffix_regex(&self) -> Option<&Regex> {
+        self.strip_suffix_regex.as_ref()
+    }
+
+    pub fn lowercase(&self) -> bool {
+        self.lowercase
+    }
+
+    pub fn apply<'s>(&self, captured: &'s str) -> Cow<'s, str> {
+        let mut text = captured;
+
+        for prefix in &self.strip_prefix {
+            text = text.strip_prefix(prefix.as_str()).unwrap_or(text);
+        }
+
+        if let Some(m) = self
+            .strip_suffix_regex
+            .as_ref()
+            .and_then(|suffix| suffix.find(text))
+        {
+            if m.end() == text.len() {
+                text = &text[..m.start()];
+            }
+        }
+
+        if self.lowercase {
+            Cow::Owned(text.to_lowercase())
+        } else {
+            Cow::Borrowed(text)
+        }
+    }
+}
+
+// How a rule's regex constraints see through the names decompilers give to
+// imports and thunks: with `hexrays`, a constraint also tests the captured
+// name without one of the prefixes `j__`, `__imp_`, `imp_`, or `_`, and
+// without a numeric suffix (e.g., `_0` or `_12`), so that `^strcpy$` matches
+// `j__strcpy` and `strcpy_0`, and `func!=^strcpy$` rejects them.
+//
+// With `itanium` (and the `demangle` feature), a constraint also tests the
+// demangled form of C++ names (without parameters), so that `func=^Foo::bar$`
+// matches a call to `_ZN3Foo3barEPc`; names that fail to demangle are only
+// tested as they are.
+//
+// NOTE: unlike a `transform`, the captured name is tested as is too, so that
+// anchored constraints on names with such prefixes still hold
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Mangling {
+    #[default]
+    None,
+    #[serde(alias = "hex-rays")]
+    HexRays,
+    #[cfg(feature = "demangle")]
+    Itanium,
+}
+
+const HEXRAYS_PREFIXES: &[&str] = &["j__", "__imp_", "imp_", "_"];
+
+impl Mangling {
+    pub fn is_none(&self) -> bool {
+        matches!(self, Self::None)
+    }
+
+    // the name without its decorations, if it has any
+    pub fn demangle<'s>(&self, name: &'s str) -> Option<Cow<'s, str>> {
+        match self {
+            Self::None => None,
+            Self::HexRays => demangle_hexrays(name).map(Cow::Borrowed),
+            #[cfg(feature = "demangle")]
+            Self::Itanium => demangle_itanium(name).map(Cow::Owned),
+        }
+    }
+}
+
+fn demangle_hexrays(name: &str) -> Option<&str> {
+    let stripped = HEXRAYS_PREFIXES
+        .iter()
+        .find_map(|prefix| name.strip_prefix(prefix))
+        .filter(|rest| !rest.is_empty())
+        .unwrap_or(name);
+
+    let stripped = match stripped.rsplit_once('_') {
+        Some((stem, digits))
+            if !stem.is_empty()
+                && !digits.is_empty()
+                && digits.bytes().all(|b| b.is_ascii_digit()) =>
+        {
+            stem
+        }
+        _ => stripped,
+    };
+
+    (stripped != name).then_some(stripped)
+}
+
+// NOTE: Mach-O symbols have an extra leading underscore
+#[cfg(feature = "demangle")]
+fn demangle_itanium(name: &str) -> Option<String> {
+    use cpp_demangle::{DemangleOptions, Symbol};
+
+    let name = name
+        .strip_prefix('_')
+        .filter(|n| n.starts_with("_Z"))
+        .unwrap_or(name);
+    if !name.starts_with("_Z") {
+        return None;
+    }
+
+    Symbol::new(name)
+        .ok()?
+        .demangle(&DemangleOptions::new().no_params().no_return_type())
+        .ok()
+}
+
+impl PartialEq for CaptureTransform {
+    fn eq(&self, other: &Self) -> bool {
+        self.strip_prefix == other.strip_prefix
+            && self.strip_suffix_regex.as_ref().map(Regex::as_str)
+                == other.strip_suffix_regex.as_ref().map(Regex::as_str)
+            && self.lowercase == other.lowercase
+    }
+}
+
+impl Eq for CaptureTransform {}
+
+impl Serialize for CaptureTransform {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        #[derive(Serialize)]
+        struct TransformT<'a> {
+            #[serde(rename = "strip-prefix", skip_serializing_if = "<[_]>::is_empty")]
+            strip_prefix: &'a [String],
+            #[serde(rename = "strip-suffix-regex", skip_serializing_if = "Option::is_none")]
+            strip_suffix_regex: Option<&'a str>,
+            #[serde(skip_serializing_if = "std::ops::Not::not")]
+            lowercase: bool,
+        }
+
+        TransformT {
+            strip_prefix: &self.strip_prefix,
+            strip_suffix_regex: self.strip_suffix_regex.as_ref().map(Regex::as_str),
+            lowercase: self.lowercase,
+        }
+        .serialize(serializer)
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub(crate) struct TransformT {
+    #[serde(rename = "strip-prefix", alias = "strip_prefix", default)]
+    strip_prefix: Option<OneOrMany<String>>,
+    #[serde(rename = "strip-suffix-regex", alias = "strip_suffix_regex", default)]
+    strip_suffix_regex: Option<String>,
+    #[serde(default)]
+    lowercase: bool,
+}
+
+impl TransformT {
+    pub(crate) fn is_empty(&self) -> bool {
+        self.strip_prefix.is_none() && self.strip_suffix_regex.is_none() && !self.lowercase
+    }
+}
+
+impl TryFrom<TransformT> for CaptureTransform {
+    type Error = RegexError;
+
+    fn try_from(t: TransformT) -> Result<Self, Self::Error> {
+        Ok(Self {
+            strip_prefix: t
+                .strip_prefix
+                .map(Vec::from)
+                .unwrap_or_default()
+                .into_boxed_slice(),
+            strip_suffix_regex: t
+                .strip_suffix_regex
+                .as_deref()
+                .map(Regex::new)
+                .transpose()?,
+            lowercase: t.lowercase,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Mangling;
+    use crate::matcher::RuleMatcher;
+    use crate::rule::Rule;
+
+    const SOURCE: &str = r#"
+char *sub_XYZ(char *s)
+{
+  char *v3;
+  v3 = j__secure_getenv("ZZZ");
+  v3 = _secure_getenv("HOME");
+  v3 = secure_getenv_0("TMP");
+  v3 = _Secure_Getenv_12("PATH");
+  v3 = secure_getenv_fallback("SHELL");
+  return v3;
+}
+"#;
+
+    fn calls(rule: &str) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+        let mut matcher = RuleMatcher::from_str(rule)?;
+        Ok(matcher
+            .matches(SOURCE)?
+            .iter()
+            .filter_map(|m| m.result().value("$func", m.source_ref()).map(str::to_owned))
+            .collect())
+    }
+
+    #[test]
+    fn test_capture_transform() -> Result<(), Box<dyn std::error::Error>> {
+        // without transforms, only the exact name matches (none here)
+        let plain = r#"
+id: getenv
+check-pattern:
+  regex: func=^secure_getenv$
+  pattern: '{ $func(_); }'
+"#;
+        assert!(calls(plain)?.is_empty());
+
+        // per check, for all constraints
+        let per_check = r#"
+id: getenv
+check-pattern:
+  regex: func=^secure_getenv$
+  transform:
+    strip-prefix: [j__, _]
+    strip-suffix-regex: _[0-9]+$
+    lowercase: true
+  pattern: '{ $func(_); }'
+"#;
+        let mut found = calls(per_check)?;
+        found.sort();
+        assert_eq!(
+            found,
+            [
+                "_Secure_Getenv_12",
+                "_secure_getenv",
+                "j__secure_getenv",
+                "secure_getenv_0"
+            ]
+        );
+
+        // per constraint, with negative constraints
+        let per_constraint = r#"
+id: getenv
+check-pattern:
+  regex:
+  - regex: func!=^secure_getenv$
+    strip-prefix: j__
+  pattern: '{ $func(_); }'
+"#;
+        let mut found = calls(per_constraint)?;
+        found.sort();
+        assert_eq!(
+            found,
+            [
+                "_Secure_Getenv_12",
+                "_secure_getenv",
+                "secure_getenv_0",
+                "secure_getenv_fallback"
+            ]
+        );
+
+        // rules serialize with their transforms
+        for rule in [per_check, per_constraint] {
+            let yaml = serde_yaml::to_string(&Rule::from_str(rule)?)?;
+            assert_eq!(serde_yaml::to_string(&Rule::from_str(&yaml)?)?, yaml);
+            assert_eq!(calls(&yaml)?.len(), calls(rule)?.len());
+        }
+
+        // transformed constraints are validated as the others
+        assert!(Rule::from_str(per_check.replace("func=", "fn=")).is_err());
+        assert!(Rule::from_str(per_check.replace("_[0-9]+$", "_[0-9+$")).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_mangling() -> Result<(), Box<dyn std::error::Error>> {
+        assert_eq!(
+            Mangling::HexRays.demangle("j__strcpy").as_deref(),
+            Some("strcpy")
+        );
+        assert_eq!(
+            Mangling::HexRays.demangle("imp_CreateFileW_0").as_deref(),
+            Some("CreateFileW")
+        );
+        assert_eq!(Mangling::HexRays.demangle("strcpy"), None);
+        assert_eq!(Mangling::HexRays.demangle("_"), None);
+        assert_eq!(Mangling::None.demangle("j__strcpy"), None);
+
+        // anchored constraints reject decorated names...
+        let plain = r#"
+id: getenv
+check-pattern:
+  regex: func=^secure_getenv$
+  pattern: '{ $func(_); }'
+"#;
+        assert!(calls(plain)?.is_empty());
+
+        // ...unless the rule sees through them (but not through case, or
+        // other suffixes)
+        let mangled = format!("mangling: hexrays\n{plain}");
+        let mut found = calls(&mangled)?;
+        found.sort();
+        assert_eq!(
+            found,
+            ["_secure_getenv", "j__secure_getenv", "secure_getenv_0"]
+        );
+
+        // negative constraints reject them
+        let mut found = calls(&mangled.replace("func=", "func!="))?;
+        found.sort();
+        assert_eq!(found, ["_Secure_Getenv_12", "secure_getenv_fallback"]);
+
+        // along with transforms, and through serialization
+        let transformed = mangled.replace(
+            "  pattern:",
+            "  transform:\n    lowercase: true\n  pattern:",
+        );
+        assert_eq!(calls(&transformed)?.len(), 4);
+
+        let yaml = serde_yaml::to_string(&Rule::from_str(&mangled)?)?;
+        assert!(yaml.contains("mangling: hexrays"));
+        assert_eq!(calls(&yaml)?.len(), 3);
+
+        Ok(())
+    }
+
+    #[cfg(feature = "demangle")]
+    #[test]
+    fn test_itanium_mangling() -> Result<(), Box<dyn std::error::Error>> {
+        assert_eq!(
+            Mangling::Itanium.demangle("_ZN3Foo3barEPc").as_deref(),
+            Some("Foo::bar")
+        );
+        assert_eq!(
+            Mangling::Itanium.demangle("__ZN3Foo3barEPc").as_deref(),
+            Some("Foo::bar")
+        );
+        // malformed names, and others, pass through
+        assert_eq!(Mangling::Itanium.demangle("_Z12short"), None);
+        assert_eq!(Mangling::Itanium.demangle("strcpy"), None);
+
+        let source = std::fs::read_to_string("tests/mangled-calls.c")?;
+        let rule = r#"
+id: foo-bar
+check-pattern:
+  regex: func=^Foo::bar$
+  pattern: '{ $func($buf); }'
+"#;
+
+        // the call is only found when demangling
+        let mut matcher = RuleMatcher::from_str(rule)?;
+        assert!(matcher.matches(&source)?.is_empty());
+
+        let mut matcher = RuleMatcher::from_str(format!("mangling: itanium\n{rule}"))?;
+        let found = matcher.matches(&source)?;
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].capture("$func"), Some("_ZN3Foo3barEPc"));
+        assert_eq!(
+            found[0].demangled_capture("$func").as_deref(),
+            Some("Foo::bar")
+        );
+        assert_eq!(found[0].capture("$buf"), Some("buf"));
+        assert_eq!(found[0].demangled_capture("$buf"), None);
+
+        Ok(())
+    }
+}
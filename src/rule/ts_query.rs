@@ -0,0 +1,277 @@
+use std::collections::HashSet;
+use std::sync::LazyLock;
+
+use regex::Regex;
+use tree_sitter::{Language, Query, QueryCursor, StreamingIterator, Tree};
+use weggli::result::{CaptureResult, QueryResult};
+
+use crate::matcher::function_definition;
+use crate::rule::CheckError;
+
+// The `query_id` of the captures of a `ts-query` match that are variables
+// only, i.e., that do not count towards its span (see `match_span`), since
+// the check reports another capture.
+pub(crate) const VARIABLE_CAPTURE: usize = usize::MAX;
+
+// A check written as a tree-sitter query (`ts-query`) rather than a weggli
+// pattern, e.g., to match node kinds or fields weggli patterns cannot
+// express. Its captures are the check's variables (`@fn` is `$fn`), and its
+// matches span all captures, or only the `report-capture`.
+//
+// NOTE: the text predicates of the query (`#eq?`, `#match?`, `#any-of?`) are
+// evaluated by tree-sitter; the literals of its `#eq?` predicates, if it has
+// a single pattern, are identifiers the prefilter searches for
+pub(crate) struct TsQuery {
+    query: Query,
+    report: Option<u32>,
+    source: Box<str>,
+}
+
+// e.g., `(#eq? @fn "memcpy")`, but not `(#not-eq? ...)`, nor literals with
+// escapes
+static EQ_LITERAL: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r#"\(#eq\?\s+@[^\s)]+\s+"([^"\\]+)"\s*\)"#).unwrap());
+
+impl TsQuery {
+    // NOTE: errors name the check, as rules with several checks would
+    // otherwise be hard to debug
+    pub(crate) fn new(
+        check: &str,
+        source: &str,
+        report_capture: Option<&str>,
+        cxx: bool,
+    ) -> Result<Self, CheckError> {
+        let parser = weggli::get_parser(cxx)?;
+        let language = parser
+            .language()
+            .map(|language| Language::clone(&language))
+            .expect("weggli's parsers have a language");
+
+        let query = Query::new(&language, source).map_err(|source| CheckError::Query {
+            check: check.to_owned(),
+            source,
+        })?;
+
+        let report = report_capture
+            .map(|capture| {
+                let capture = capture.trim_start_matches(['@', '$']);
+                query.capture_index_for_name(capture).ok_or_else(|| {
+                    CheckError::UnknownReportCapture {
+                        check: check.to_owned(),
+                        capture: capture.to_owned(),
+                    }
+                })
+            })
+            .transpose()?;
+
+        Ok(Self {
+            query,
+            report,
+            source: source.into(),
+        })
+    }
+
+    // the query, as written in the rule
+    pub(crate) fn source(&self) -> &str {
+        &self.source
+    }
+
+    pub(crate) fn report_capture(&self) -> Option<&str> {
+        self.report
+            .map(|index| self.query.capture_names()[index as usize])
+    }
+
+    pub(crate) fn variables(&self) -> HashSet<String> {
+        self.query
+            .capture_names()
+            .iter()
+            .map(|name| format!("${name}"))
+            .collect()
+    }
+
+    pub(crate) fn identifiers(&self) -> Vec<String> {
+        if self.query.pattern_count() != 1 {
+            return Vec::new();
+        }
+
+        let mut identifiers = EQ_LITERAL
+            .captures_iter(&self.source)
+            .map(|c| c[1].to_owned())
+            .collect::<Vec<_>>();
+
+        identifiers.sort();
+        identifiers.dedup();
+        identifiers
+    }
+
+    // as weggli's, the first capture of a result spans the whole match, and
+    // the variables refer to the others
+    pub(crate) fn matches(&self, tree: &Tree, source: &str) -> Vec<QueryResult> {
+        let mut cursor = QueryCursor::new();
+        let mut matches = cursor.matches(&self.query, tree.root_node(), source.as_bytes());
+
+        let mut results = Vec::new();
+        while let Some(m) = matches.next() {
+            let Some(whole) = m
+                .captures
+                .iter()
+                .map(|c| c.node.byte_range())
+                .reduce(|a, b| a.start.min(b.start)..a.end.max(b.end))
+            else {
+                continue;
+            };
+
+            let reported = self
+                .report
+                .is_none_or(|index| m.captures.iter().any(|c| c.index == index));
+
+            let mut captures = vec![CaptureResult {
+                range: whole.clone(),
+                query_id: 0,
+                capture_idx: 0,
+            }];
+            let mut vars = HashSet::new();
+            let mut variables = Vec::new();
+
+            for c in m.captures {
+                let spanned = !reported || self.report.is_none_or(|index| c.index == index);
+                captures.push(CaptureResult {
+                    range: c.node.byte_range(),
+                    query_id: if spanned { 0 } else { VARIABLE_CAPTURE },
+                    capture_idx: c.index as usize,
+                });
+
+                let name = self.query.capture_names()[c.index as usize];
+                if vars.insert(name) {
+                    variables.push((format!("${name}"), captures.len() - 1));
+                }
+            }
+
+            let function =
+                function_definition(tree, whole.clone()).map_or(whole, |node| node.byte_range());
+
+            results.push(QueryResult::new(
+                captures,
+                variables.into_iter().collect(),
+                function,
+            ));
+        }
+
+        results
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::matcher::RuleMatcher;
+    use crate::rule::{Rule, Severity};
+
+    const SOURCE: &str = r#"
+void f(char *dst, const char *src, size_t n)
+{
+  memcpy(dst, src, n);
+  memset(dst, 0, n);
+  strcpy(dst, src);
+}
+"#;
+
+    const RULE: &str = r#"
+id: mem-calls
+severity: high
+check-pattern:
+  name: mem
+  ts-query: '(call_expression function: (identifier) @fn (#match? @fn "^mem")) @call'
+"#;
+
+    #[test]
+    fn test_ts_query() -> Result<(), Box<dyn std::error::Error>> {
+        // matches span all captures, with regex predicates
+        let mut matcher = RuleMatcher::from_str(RULE)?;
+        let found = matcher.matches(SOURCE)?;
+        assert_eq!(
+            found.iter().map(|m| m.matched_text()).collect::<Vec<_>>(),
+            ["memcpy(dst, src, n)", "memset(dst, 0, n)"]
+        );
+        assert_eq!(found[0].capture("$fn"), Some("memcpy"));
+        assert_eq!(found[0].effective_severity(), Severity::High);
+        assert_eq!(found[0].start_line(), 4);
+
+        // ...or the reported one only
+        let reported = RULE.replace("  name: mem\n", "  name: mem\n  report-capture: fn\n");
+        let mut matcher = RuleMatcher::from_str(&reported)?;
+        let found = matcher.matches(SOURCE)?;
+        assert_eq!(
+            found.iter().map(|m| m.matched_text()).collect::<Vec<_>>(),
+            ["memcpy", "memset"]
+        );
+        assert_eq!(found[1].capture("$call"), Some("memset(dst, 0, n)"));
+
+        // captures are variables for regex constraints
+        let constrained = format!("{reported}  regex: fn=set$\n");
+        let mut matcher = RuleMatcher::from_str(&constrained)?;
+        let found = matcher.matches(SOURCE)?;
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].matched_text(), "memset");
+
+        // rules serialize with their queries
+        let rule = Rule::from_str(&reported)?;
+        assert_eq!(rule.checks()[0].report_capture(), Some("fn"));
+        // and have no pattern
+        assert!(rule.checks()[0].pattern().is_none());
+        let yaml = serde_yaml::to_string(&rule)?;
+        assert!(yaml.contains("report-capture: fn"), "{yaml}");
+        assert!(!yaml.contains("pattern:"), "{yaml}");
+        assert_eq!(serde_yaml::to_string(&Rule::from_str(&yaml)?)?, yaml);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_ts_query_identifiers() -> Result<(), Box<dyn std::error::Error>> {
+        // derived from `#eq?` literals
+        let rule = Rule::from_str(RULE.replace("#match? @fn \"^mem\"", "#eq? @fn \"memcpy\""))?;
+        let checker = &rule.checks()[0];
+        assert_eq!(checker.identifiers(), ["memcpy"]);
+        assert!(checker.can_match(SOURCE));
+        assert!(!checker.can_match("void g(void) { memset(p, 0, 1); }"));
+
+        // or listed in the rule
+        assert!(rule_with(RULE, "  identifiers: [mem]\n")?.checks()[0]
+            .identifiers()
+            .contains(&String::from("mem")));
+        assert!(Rule::from_str(RULE)?.checks()[0].identifiers().is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_ts_query_errors() {
+        // compile errors name the check
+        let Err(error) = Rule::from_str(RULE.replace("@call'", "@call (oops'")) else {
+            panic!("invalid query accepted");
+        };
+        let error = error.to_string();
+        assert!(error.contains("`mem`"), "{error}");
+
+        let Err(error) = rule_with(RULE, "  report-capture: missing\n") else {
+            panic!("unknown capture accepted");
+        };
+        assert!(error.to_string().contains("@missing"), "{error}");
+
+        assert!(rule_with(RULE, "  pattern: '{ memcpy(_, _, _); }'\n").is_err());
+        assert!(rule_with(RULE, "  regex: dst=^d\n").is_err());
+        assert!(Rule::from_str(
+            r#"
+id: no-query
+check-pattern:
+  pattern: '{ memcpy(_, _, _); }'
+  report-capture: fn
+"#
+        )
+        .is_err());
+    }
+
+    fn rule_with(rule: &str, field: &str) -> Result<Rule, crate::rule::RuleError> {
+        Rule::from_str(format!("{rule}{field}"))
+    }
+}
@@ -0,0 +1,149 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+// a temporary directory of files (e.g., rules) for tests, unique within the
+// process, and removed when dropped, even if the test fails.
+pub(crate) struct TempDir(PathBuf);
+
+impl TempDir {
+    // e.g., `TempDir::with_files(&[("local-buffer.yml", "id: local-buffer ...")])`;
+    // the names of the files may include directories
+    pub(crate) fn with_files(files: &[(&str, &str)]) -> io::Result<Self> {
+        static NEXT: AtomicUsize = AtomicUsize::new(0);
+
+        let dir = Self(std::env::temp_dir().join(format!(
+            "weggli-ruleset-{}-{}",
+            std::process::id(),
+            NEXT.fetch_add(1, Ordering::Relaxed)
+        )));
+
+        // e.g., left over by an earlier process with the same id
+        let _ = fs::remove_dir_all(dir.path());
+        fs::create_dir_all(dir.path())?;
+
+        for (name, contents) in files {
+            let path = dir.path().join(name);
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::write(path, contents)?;
+        }
+
+        Ok(dir)
+    }
+
+    pub(crate) fn path(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+// the rule pack that the tests match `tests/*.c` with: `local-buffer` matches
+// the `char` buffer of `tests/utf8-comments.c`, and `unbounded-copy` both of
+// its calls to `strcpy`.
+pub(crate) const LOCAL_BUFFER: &str = r#"
+id: local-buffer
+severity: low
+check-pattern:
+  pattern: '{ char $buf[_]; }'
+"#;
+
+pub(crate) const UNBOUNDED_COPY: &str = r#"
+id: unbounded-copy
+severity: high
+check-pattern:
+  pattern: '{ strcpy($dst, _); }'
+"#;
+
+// `unbounded-copy` with a second check, which never matches the sources of
+// `tests`
+pub(crate) const UNBOUNDED_COPY_GETS: &str = r#"
+id: unbounded-copy
+severity: high
+check-patterns:
+- name: strcpy
+  pattern: '{ strcpy($dst, _); }'
+- name: gets
+  pattern: '{ gets(_); }'
+"#;
+
+// `unbounded-copy`, described, and with a named check
+pub(crate) const UNBOUNDED_COPY_DESCRIBED: &str = r#"
+id: unbounded-copy
+description: call to unbounded copy function
+severity: high
+tags: [CWE-120]
+check-patterns:
+- name: strcpy
+  regex: func=^strcpy$
+  pattern: |
+    { $func($dst, _); }
+"#;
+
+// the pack with the fields that reports and exports carry: descriptions,
+// tags, an author, and named checks with regexes
+pub(crate) const DESCRIBED_PACK: [(&str, &str); 2] = [
+    (
+        "local-buffer.yml",
+        r#"
+id: local-buffer
+description: fixed-size local character buffer
+severity: low
+tags:
+- CWE-121
+check-pattern:
+  pattern: |
+    { char $buf[_]; }
+"#,
+    ),
+    (
+        "unbounded-copy.yml",
+        r#"
+id: unbounded-copy
+author: appsec@example.com
+description: call to unbounded copy function
+severity: high
+tags:
+- CWE-120
+- CWE-676
+check-patterns:
+- name: strcpy
+  regex: func=^st(r|p)cpy$
+  pattern: |
+    { $func($dst, _); }
+- name: gets
+  regex: func=^gets$
+  pattern: |
+    { $func(_); }
+"#,
+    ),
+];
+
+pub(crate) fn pack() -> io::Result<TempDir> {
+    pack_with(&[])
+}
+
+// the pack, with `files` in place of the rules of the same name, or in
+// addition to them.
+pub(crate) fn pack_with(files: &[(&str, &str)]) -> io::Result<TempDir> {
+    let mut pack = vec![
+        ("local-buffer.yml", LOCAL_BUFFER),
+        ("unbounded-copy.yml", UNBOUNDED_COPY),
+    ];
+
+    for &(name, contents) in files {
+        match pack.iter_mut().find(|(file, _)| *file == name) {
+            Some(file) => file.1 = contents,
+            None => pack.push((name, contents)),
+        }
+    }
+
+    TempDir::with_files(&pack)
+}
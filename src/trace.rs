@@ -0,0 +1,218 @@
+// Instrumentation of rule loading and matching. With the `trace` feature,
+// spans and events are emitted with `tracing`, at the trace level for spans
+// and at their own level for events, with the paths of their modules as
+// targets (e.g., `weggli_ruleset::matcher`); fields are recorded as displayed.
+// Without it, the macros expand to nothing, and their fields are never
+// evaluated.
+
+#[cfg(not(feature = "trace"))]
+pub(crate) struct Span;
+
+// a guard that exits the span when dropped, e.g.,
+// `let _span = trace::span!("load_rule", path = path.display());`
+macro_rules! span {
+    ($name:literal $(, $key:ident = $value:expr)* $(,)?) => {{
+        #[cfg(feature = "trace")]
+        let span = tracing::trace_span!(
+            $name,
+            $($key = tracing::field::display(&$value)),*
+        )
+        .entered();
+        #[cfg(not(feature = "trace"))]
+        let span = $crate::trace::Span;
+        span
+    }};
+}
+pub(crate) use span;
+
+// e.g., `trace::event!(DEBUG, "skipped_rule", path = path.display());`
+macro_rules! event {
+    ($level:ident, $name:literal $(, $key:ident = $value:expr)* $(,)?) => {
+        #[cfg(feature = "trace")]
+        tracing::event!(
+            tracing::Level::$level,
+            $($key = tracing::field::display(&$value),)*
+            $name
+        );
+    };
+}
+pub(crate) use event;
+
+#[cfg(all(test, feature = "trace", feature = "std-fs"))]
+mod test {
+    use std::fmt::{self, Write};
+    use std::sync::{Arc, Mutex};
+
+    use tracing::field::{Field, Visit};
+    use tracing::span::{Attributes, Id, Record};
+    use tracing::{Dispatch, Event, Level, Metadata, Subscriber};
+
+    use crate::matcher::RuleMatcher;
+    use crate::testing;
+
+    // the name, fields, and parent (an index into `spans`) of a span
+    type CapturedSpan = (&'static str, String, Option<usize>);
+
+    // the level, fields (the first being its message), and span (if any) of
+    // an event
+    type CapturedEvent = (Level, String, Option<usize>);
+
+    // records the spans and events of the thread it is the default of, and
+    // which span each is within
+    #[derive(Default)]
+    struct Capture {
+        spans: Mutex<Vec<CapturedSpan>>,
+        events: Mutex<Vec<CapturedEvent>>,
+        entered: Mutex<Vec<usize>>,
+    }
+
+    // e.g., ` rule=copy check=strcpy`
+    #[derive(Default)]
+    struct Fields(String);
+
+    impl Visit for Fields {
+        fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+            let _ = write!(self.0, " {}={:?}", field.name(), value);
+        }
+    }
+
+    impl Capture {
+        fn current(&self) -> Option<usize> {
+            self.entered.lock().unwrap().last().copied()
+        }
+    }
+
+    impl Subscriber for Capture {
+        fn enabled(&self, metadata: &Metadata<'_>) -> bool {
+            metadata.target().starts_with("weggli_ruleset")
+        }
+
+        fn new_span(&self, span: &Attributes<'_>) -> Id {
+            let mut fields = Fields::default();
+            span.record(&mut fields);
+
+            let parent = match span.parent() {
+                Some(parent) => Some(parent.into_u64() as usize - 1),
+                None if span.is_contextual() => self.current(),
+                None => None,
+            };
+
+            let mut spans = self.spans.lock().unwrap();
+            spans.push((span.metadata().name(), fields.0, parent));
+            Id::from_u64(spans.len() as u64)
+        }
+
+        fn record(&self, _span: &Id, _values: &Record<'_>) {}
+
+        fn record_follows_from(&self, _span: &Id, _follows: &Id) {}
+
+        fn event(&self, event: &Event<'_>) {
+            let mut fields = Fields::default();
+            event.record(&mut fields);
+
+            self.events
+                .lock()
+                .unwrap()
+                .push((*event.metadata().level(), fields.0, self.current()));
+        }
+
+        fn enter(&self, span: &Id) {
+            self.entered
+                .lock()
+                .unwrap()
+                .push(span.into_u64() as usize - 1);
+        }
+
+        fn exit(&self, _span: &Id) {
+            self.entered.lock().unwrap().pop();
+        }
+    }
+
+    fn capture<T>(f: impl FnOnce() -> T) -> (T, Vec<CapturedSpan>, Vec<CapturedEvent>) {
+        let capture = Arc::new(Capture::default());
+        let result = tracing::dispatcher::with_default(&Dispatch::new(Arc::clone(&capture)), f);
+
+        let spans = capture.spans.lock().unwrap().clone();
+        let events = capture.events.lock().unwrap().clone();
+        (result, spans, events)
+    }
+
+    #[test]
+    fn test_trace() -> Result<(), Box<dyn std::error::Error>> {
+        let pack = testing::pack_with(&[("invalid.yml", "id: [")])?;
+
+        let (matcher, spans, events) =
+            capture(|| RuleMatcher::from_directory_with(pack.path(), true));
+        let mut matcher = matcher?;
+
+        // a span per rule file, and an event per invalid one
+        let loaded = spans
+            .iter()
+            .filter(|(name, fields, _)| *name == "load_rule" && fields.starts_with(" path="))
+            .count();
+        let skipped = events
+            .iter()
+            .filter(|(level, fields, _)| {
+                *level == Level::DEBUG
+                    && fields.starts_with(" message=skipped_rule ")
+                    && fields.contains(" path=")
+            })
+            .count();
+        assert_eq!(loaded, matcher.rules().len() + skipped);
+        assert_eq!(skipped, 1);
+
+        // `char` does not occur, so only the checks of `unbounded-copy` run
+        let source = "void f(int *d, int *s) {\n    strcpy(d, s);\n}\n";
+        let (matches, spans, events) = capture(|| matcher.matches(source));
+        let matches = matches?;
+        assert!(!matches.is_empty());
+
+        // the spans of the checks that passed the prefilter are children of
+        // that of the source, which has no parent
+        let source_span = spans
+            .iter()
+            .position(|(name, _, _)| *name == "match_source")
+            .unwrap();
+        assert_eq!(spans[source_span].2, None);
+        assert!(spans[source_span]
+            .1
+            .contains(&format!(" bytes={}", source.len())));
+
+        let checks = spans
+            .iter()
+            .enumerate()
+            .filter(|(_, (name, _, _))| *name == "check_match")
+            .collect::<Vec<_>>();
+        assert_eq!(checks.len() + 1, spans.len());
+        assert!(checks
+            .iter()
+            .all(|(_, (_, fields, parent))| *parent == Some(source_span)
+                && fields.starts_with(" rule=unbounded-copy check=")));
+
+        let viable = events
+            .iter()
+            .filter(|(_, fields, _)| {
+                fields.starts_with(" message=prefilter ") && fields.ends_with(" passed=true")
+            })
+            .count();
+        assert!(viable > 0 && viable < matcher.rules().iter().map(|(_, r)| r.checks().len()).sum());
+        assert_eq!(checks.len(), viable);
+
+        // the matches of each check are recorded within its span, and their
+        // total within that of the source
+        let checked = events
+            .iter()
+            .filter(|(_, fields, _)| fields.starts_with(" message=checked "))
+            .map(|(_, _, parent)| parent.unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(checked, checks.iter().map(|(i, _)| *i).collect::<Vec<_>>());
+
+        assert!(events
+            .iter()
+            .any(|(level, fields, parent)| *level == Level::DEBUG
+                && *fields == format!(" message=matched matches={}", matches.len())
+                && *parent == Some(source_span)));
+
+        Ok(())
+    }
+}
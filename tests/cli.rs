@@ -0,0 +1,141 @@
+#![cfg(feature = "cli")]
+
+// NOTE: drives the binary with `std::process::Command` over the fixtures, as
+// `assert_cmd` would
+use std::fs;
+use std::io::{ErrorKind, Write};
+use std::path::PathBuf;
+use std::process::{Command, Output, Stdio};
+
+fn run(args: &[&str], stdin: Option<&str>) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_weggli-ruleset"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+
+    // NOTE: the binary may exit (e.g., on a usage error) before reading stdin
+    let mut input = child.stdin.take().unwrap();
+    match input.write_all(stdin.unwrap_or_default().as_bytes()) {
+        Err(e) if e.kind() == ErrorKind::BrokenPipe => (),
+        result => result.unwrap(),
+    }
+    drop(input);
+
+    child.wait_with_output().unwrap()
+}
+
+// a directory of the given rule files, named after the test
+fn rules(test: &str, files: &[(&str, &str)]) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("weggli-ruleset-{test}-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    for (name, rule) in files {
+        fs::write(dir.join(name), rule).unwrap();
+    }
+    dir
+}
+
+const LOCAL_BUFFER: &str = r#"
+id: local-buffer
+severity: low
+check-pattern:
+  pattern: '{ char $buf[_]; }'
+"#;
+
+const UNBOUNDED_COPY: &str = r#"
+id: unbounded-copy
+severity: high
+check-patterns:
+- name: strcpy
+  pattern: '{ strcpy($dst, _); }'
+"#;
+
+#[test]
+fn test_cli_formats() {
+    let dir = rules(
+        "cli-formats",
+        &[
+            ("local-buffer.yml", LOCAL_BUFFER),
+            ("unbounded-copy.yml", UNBOUNDED_COPY),
+        ],
+    );
+    let rules = dir.to_str().unwrap();
+
+    let output = run(&["-f", "oneline", rules, "tests/utf8-comments.c"], None);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(stdout.lines().count(), 3);
+    assert!(stdout.starts_with("tests/utf8-comments.c:6:5: high unbounded-copy/strcpy: "));
+
+    let output = run(&["-C", "0", rules, "tests/utf8-comments.c"], None);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+
+    assert!(stdout.contains("tests/utf8-comments.c:6:5: HIGH unbounded-copy/strcpy\n"));
+    assert!(stdout.contains("   6:     strcpy(dst, src);"));
+    assert!(!stdout.contains("   5:"));
+
+    for format in ["json", "jsonl", "sarif"] {
+        let output = run(&["--format", format, rules, "tests/utf8-comments.c"], None);
+        assert_eq!(output.status.code(), Some(0));
+
+        let stdout = String::from_utf8(output.stdout).unwrap();
+        for line in stdout.lines().filter(|_| format == "jsonl") {
+            serde_json::from_str::<serde_json::Value>(line).unwrap();
+        }
+        if format != "jsonl" {
+            serde_json::from_str::<serde_json::Value>(&stdout).unwrap();
+        }
+    }
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_cli_stdin_and_exit_codes() {
+    let dir = rules(
+        "cli-stdin",
+        &[
+            ("local-buffer.yml", LOCAL_BUFFER),
+            ("unbounded-copy.yml", UNBOUNDED_COPY),
+        ],
+    );
+    let rules = dir.to_str().unwrap();
+    let source = fs::read_to_string("tests/utf8-comments.c").unwrap();
+
+    // findings from stdin have no path
+    let output = run(
+        &["-f", "oneline", "--min-severity", "high", rules, "-"],
+        Some(&source),
+    );
+    let stdout = String::from_utf8(output.stdout).unwrap();
+
+    assert_eq!(stdout.lines().count(), 2);
+    assert!(stdout.lines().all(|l| l.starts_with("<source>:")));
+
+    let fail_on = |severity| {
+        run(
+            &["-f", "oneline", "--fail-on", severity, rules, "-"],
+            Some(&source),
+        )
+        .status
+        .code()
+    };
+
+    assert_eq!(fail_on("high"), Some(2));
+    assert_eq!(fail_on("critical"), Some(0));
+
+    let output = run(&["--lang", "rust", rules, "-"], Some(&source));
+
+    assert_eq!(output.status.code(), Some(3));
+    assert!(String::from_utf8(output.stderr)
+        .unwrap()
+        .contains("unknown language `rust`"));
+
+    assert_eq!(run(&[rules], None).status.code(), Some(3));
+    assert_eq!(run(&["--help"], None).status.code(), Some(0));
+
+    fs::remove_dir_all(&dir).unwrap();
+}
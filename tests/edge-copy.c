@@ -0,0 +1,5 @@
+void first(char *s) { strcpy(s, "first"); }
+
+int middle(void) { return 0; }
+
+void last(char *s) { strcpy(s, "last"); }
\ No newline at end of file
@@ -0,0 +1,25 @@
+/* Ghidra decompiler output */
+
+undefined8 FUN_00101189(char *param_1,uint param_2)
+
+{
+  undefined8 uVar1;
+  long in_FS_OFFSET;
+  byte local_28 [16];
+  long local_10;
+  
+  local_10 = *(long *)(in_FS_OFFSET + 0x28);
+  if (param_2 < 0x11) {
+    memcpy(local_28,param_1,(ulong)param_2);
+    uVar1 = 0;
+  }
+  else {
+    uVar1 = 0xffffffff;
+    goto LAB_001011d4;
+  }
+  if (local_10 != *(long *)(in_FS_OFFSET + 0x28)) {
+    __stack_chk_fail();
+  }
+  return uVar1;
+LAB_001011d4:
+}
@@ -0,0 +1,30 @@
+#include <stdlib.h>
+
+struct request {
+    char *cmd;
+};
+
+void run_fixed(void) {
+    char *cmd;
+    cmd = "ls -l";
+    system(cmd);
+}
+
+void run_input(char *input) {
+    char *cmd;
+    cmd = input;
+    system(cmd);
+}
+
+void run_other(void) {
+    char *cmd, *other;
+    other = "ls -l";
+    cmd = getenv("CMD");
+    system(cmd);
+}
+
+void run_request(struct request *req) {
+    struct request copy;
+    copy = *req;
+    system(copy.cmd);
+}
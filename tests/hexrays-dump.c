@@ -0,0 +1,53 @@
+/* This file was generated by the Hex-Rays decompiler.
+   Copyright (c) 2007-2021 Hex-Rays <info@hex-rays.com>
+
+   Detected compiler: GNU C++
+*/
+
+#include <defs.h>
+
+
+//-------------------------------------------------------------------------
+// Function declarations
+
+int __fastcall init_table(int *table, unsigned int n);
+char *__fastcall copy_name(char *dst, const char *src);
+void __fastcall __noreturn fatal(const char *msg);
+
+//-------------------------------------------------------------------------
+// Data declarations
+
+char byte_404040[16]; // weak
+
+
+//----- (0000000000401000) ----------------------------------------------------
+int __fastcall init_table(int *table, unsigned int n)
+{
+  unsigned int i; // [rsp+1Ch] [rbp-4h]
+
+  for ( i = 0; i < n; ++i )
+    table[i] = -1;
+  return 0;
+}
+
+//----- (0000000000401040) ----------------------------------------------------
+char *__fastcall copy_name(char *dst, const char *src)
+{
+  char *result; // rax
+
+  if ( !src )
+    fatal("no name {");
+  result = strcpy(dst, src);
+  byte_404040[0] = '}';
+  return result;
+}
+
+//----- (0000000000401090) ----------------------------------------------------
+void __fastcall __noreturn fatal(const char *msg)
+{
+  puts(msg);
+  exit(1);
+}
+
+// nfuncs=3 queued=3 decompiled=3 lumina nreq=0 worse=0 better=0
+// ALL OK, 3 function(s) have been successfully decompiled
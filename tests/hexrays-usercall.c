@@ -0,0 +1,30 @@
+// Hex-Rays decompiler output (x86, custom calling conventions)
+int __usercall copy_name@<eax>(char *dst@<edi>, const char *src@<esi>)
+{
+  _DWORD *v2; // ebx
+  unsigned __int8 v3; // al
+
+  v2 = (_DWORD *)dst;
+  v3 = __OFSUB__(*v2, 16);
+  if ( !v3 )
+    strcpy(dst, src);
+  return *v2;
+}
+
+void __fastcall __noreturn fatal(const char *msg)
+{
+  puts(msg);
+  exit(1);
+}
+
+__int64 __fastcall sub_401200(__int64 a1, unsigned int a2)
+{
+  __int64 result; // rax
+  char v4[16]; // [rsp+0h] [rbp-18h] BYREF
+
+  result = *(_QWORD *)fs:0x28;
+  if ( a2 > 0x10 )
+    goto LABEL_4;
+  memcpy(v4, (const void *)a1, a2);
+LABEL_4:
+}
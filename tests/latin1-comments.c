@@ -0,0 +1,6 @@
+/* Copyright  2019 ACME Corp.  */
+/* Kontrollgert firmware  */
+void set_name(const char *name) {
+    char buf[32]; /*  */
+    strcpy(buf, name);
+}
@@ -0,0 +1,16 @@
+#include <stdio.h>
+#include <string.h>
+
+#define NAME_LEN 16
+#define BUF_SIZE NAME_LEN
+#define COPY(dst, src) strcpy(dst, src)
+
+/* the copy is only visible once COPY is expanded */
+void greet(const char *name)
+{
+	char buf[BUF_SIZE];
+
+	COPY(buf,
+	     name);
+	printf("hello, %s\n", buf);
+}
@@ -0,0 +1,8 @@
+void handler(char *input)
+{
+  char buf[16];
+  _ZN3Foo3barEPc(buf);
+  _ZN3Foo3bazEPc(buf);
+  _Z12short(buf);
+  bar(buf);
+}
@@ -0,0 +1,3 @@
+void copy(char *dst, const char *src) {
+    strcpy(dst, src);
+}
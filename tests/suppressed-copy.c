@@ -0,0 +1,12 @@
+void copy(char *dst, const char *src) {
+    strcpy(dst, src); // weggli-ignore: unbounded-copy
+}
+
+void copy_again(char *dst, const char *src) {
+    // weggli-ignore
+    strcpy(dst, src);
+}
+
+void copy_unchecked(char *dst, const char *src) {
+    strcpy(dst, src);
+}
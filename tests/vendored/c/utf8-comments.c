@@ -0,0 +1,13 @@
+// ファームウェア SDK — 🚀 vendor header
+/* 著作権 © 2021 🦀🦀 */
+
+void copy_name(char *dst, const char *src) {
+    // 复制名称 🙂
+    strcpy(dst, src); /* 注意: 境界なし 🚨 */
+}
+
+void copy_kanji(const char *src) {
+    char 名前[16]; // 日本語の変数名
+    /* 🔥 */
+    /* 🔥 */ strcpy(名前, src);
+}
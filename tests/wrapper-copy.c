@@ -0,0 +1,35 @@
+#include <string.h>
+
+void copy_name(char *dst, const char *src)
+{
+  strcpy(dst, src);
+}
+
+int parse_record(const char *input, size_t len)
+{
+  char name[32];
+  char value[64];
+  size_t i = 0;
+  size_t j = 0;
+
+  if (len == 0) {
+    return -1;
+  }
+
+  while (i < len && input[i] != '=') {
+    name[j++] = input[i++];
+  }
+  name[j] = '\0';
+
+  if (i == len) {
+    return -1;
+  }
+
+  strcpy(value, input + i + 1);
+
+  if (strcmp(name, "user") == 0) {
+    return 1;
+  }
+
+  return 0;
+}